@@ -1,13 +1,18 @@
 use crate::config::{ChannelConfig, StateConfig, StrategyConfig};
-use crate::constants::MAX_INITIAL_SEQUENCE;
+use crate::constants::{IPV4_ICMP_HEADER_SIZE, IPV6_ICMP_HEADER_SIZE, MAX_INITIAL_SEQUENCE};
 use crate::error::Result;
+use crate::net::channel::MAX_PACKET_SIZE;
 use crate::{
-    Error, IcmpExtensionParseMode, MAX_TTL, MaxInflight, MaxRounds, MultipathStrategy, PacketSize,
-    PayloadPattern, PortDirection, PrivilegeMode, Protocol, Sequence, TimeToLive, TraceId, Tracer,
-    TypeOfService,
+    DontFragmentMode, Error, HopAddrFilter, IcmpExtensionParseMode, IcmpIdentifierMatchMode,
+    IcmpQuotedPayloadMode, IcmpSourceMatchMode, MAX_TTL, MaxInflight, MaxPackets, MaxRounds,
+    MultiResponseMode, MultipathStrategy, NegativeRttMode, PacketSize, PayloadPattern,
+    PortDirection, PrivilegeMode, Protocol, Sequence, SourceAddr, TcpFlags, TimeToLive, TraceId,
+    TraceObserver, Tracer, TypeOfService, UnreachableMode,
 };
 use std::net::IpAddr;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Build a tracer.
@@ -38,6 +43,7 @@ use std::time::Duration;
 #[derive(Debug)]
 pub struct Builder {
     interface: Option<String>,
+    write_pcap: Option<PathBuf>,
     source_addr: Option<IpAddr>,
     target_addr: IpAddr,
     privilege_mode: PrivilegeMode,
@@ -46,28 +52,54 @@ pub struct Builder {
     payload_pattern: PayloadPattern,
     tos: TypeOfService,
     icmp_extension_parse_mode: IcmpExtensionParseMode,
+    icmp_identifier_match_mode: IcmpIdentifierMatchMode,
+    icmp_source_match_mode: IcmpSourceMatchMode,
+    icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+    fwmark: Option<u32>,
+    icmp_nonce_key: Option<u64>,
+    icmp_identifier_range: Option<u16>,
+    icmp_payload_len: Option<u16>,
+    icmp_echo_code: u8,
+    dont_fragment_mode: DontFragmentMode,
+    tcp_flags: TcpFlags,
     read_timeout: Duration,
     tcp_connect_timeout: Duration,
+    icmp_bind_attempts: u8,
     trace_identifier: TraceId,
     max_rounds: Option<MaxRounds>,
+    max_packets: Option<MaxPackets>,
+    max_silent_rounds: Option<MaxRounds>,
+    send_interval: Option<Duration>,
     first_ttl: TimeToLive,
     max_ttl: TimeToLive,
     grace_duration: Duration,
+    target_grace_duration: Duration,
     max_inflight: MaxInflight,
     initial_sequence: Sequence,
     multipath_strategy: MultipathStrategy,
     port_direction: PortDirection,
+    src_port_seed: Option<u64>,
     min_round_duration: Duration,
     max_round_duration: Duration,
+    continue_past_target: bool,
+    unreachable_mode: UnreachableMode,
+    icmp_unreachable_reached: bool,
+    multi_response_mode: MultiResponseMode,
+    hop_addr_filter: HopAddrFilter,
     max_samples: usize,
     max_flows: usize,
+    negative_rtt_mode: NegativeRttMode,
+    window: Option<NonZeroUsize>,
+    max_addrs: Option<NonZeroUsize>,
     drop_privileges: bool,
+    observer: Option<Arc<dyn TraceObserver>>,
 }
 
 impl Default for Builder {
     fn default() -> Self {
         Self {
             interface: None,
+            write_pcap: None,
             source_addr: None,
             target_addr: ChannelConfig::default().target_addr,
             privilege_mode: ChannelConfig::default().privilege_mode,
@@ -76,22 +108,47 @@ impl Default for Builder {
             payload_pattern: ChannelConfig::default().payload_pattern,
             tos: ChannelConfig::default().tos,
             icmp_extension_parse_mode: ChannelConfig::default().icmp_extension_parse_mode,
+            icmp_identifier_match_mode: ChannelConfig::default().icmp_identifier_match_mode,
+            icmp_source_match_mode: ChannelConfig::default().icmp_source_match_mode,
+            icmp_quoted_payload_mode: ChannelConfig::default().icmp_quoted_payload_mode,
+            fwmark: ChannelConfig::default().fwmark,
+            icmp_nonce_key: ChannelConfig::default().icmp_nonce_key,
+            icmp_identifier_range: ChannelConfig::default().icmp_identifier_range,
+            icmp_payload_len: ChannelConfig::default().icmp_payload_len,
+            icmp_echo_code: ChannelConfig::default().icmp_echo_code,
+            dont_fragment_mode: ChannelConfig::default().dont_fragment_mode,
+            tcp_flags: ChannelConfig::default().tcp_flags,
             read_timeout: ChannelConfig::default().read_timeout,
             tcp_connect_timeout: ChannelConfig::default().tcp_connect_timeout,
+            icmp_bind_attempts: ChannelConfig::default().icmp_bind_attempts,
             trace_identifier: StrategyConfig::default().trace_identifier,
             max_rounds: StrategyConfig::default().max_rounds,
+            max_packets: StrategyConfig::default().max_packets,
+            max_silent_rounds: StrategyConfig::default().max_silent_rounds,
+            send_interval: StrategyConfig::default().send_interval,
             first_ttl: StrategyConfig::default().first_ttl,
             max_ttl: StrategyConfig::default().max_ttl,
             grace_duration: StrategyConfig::default().grace_duration,
+            target_grace_duration: StrategyConfig::default().target_grace_duration,
             max_inflight: StrategyConfig::default().max_inflight,
             initial_sequence: StrategyConfig::default().initial_sequence,
             multipath_strategy: StrategyConfig::default().multipath_strategy,
             port_direction: StrategyConfig::default().port_direction,
+            src_port_seed: StrategyConfig::default().src_port_seed,
             min_round_duration: StrategyConfig::default().min_round_duration,
             max_round_duration: StrategyConfig::default().max_round_duration,
+            continue_past_target: StrategyConfig::default().continue_past_target,
+            unreachable_mode: StrategyConfig::default().unreachable_mode,
+            icmp_unreachable_reached: StrategyConfig::default().icmp_unreachable_reached,
+            multi_response_mode: StrategyConfig::default().multi_response_mode,
+            hop_addr_filter: HopAddrFilter::default(),
             max_samples: StateConfig::default().max_samples,
             max_flows: StateConfig::default().max_flows,
+            negative_rtt_mode: StateConfig::default().negative_rtt_mode,
+            window: StateConfig::default().window,
+            max_addrs: StateConfig::default().max_addrs,
             drop_privileges: false,
+            observer: None,
         }
     }
 }
@@ -174,6 +231,34 @@ impl Builder {
         }
     }
 
+    /// Set the pcap file to write the probes sent to.
+    ///
+    /// Each IPv4 probe sent using a raw socket is written, with its IP header, to the file.
+    ///
+    /// Only probes sent using raw sockets can be written and so it is an error to set a pcap file
+    /// in unprivileged mode, for an IPv6 target or for the tcp protocol with `SYN` `tcp_flags`, for
+    /// which probes are sent using stream sockets.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).write_pcap(Some("trace.pcap")).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn write_pcap<P: Into<PathBuf>>(self, write_pcap: Option<P>) -> Self {
+        Self {
+            write_pcap: write_pcap.map(Into::into),
+            ..self
+        }
+    }
+
     /// Set the protocol.
     ///
     /// # Examples
@@ -287,6 +372,9 @@ impl Builder {
 
     /// Set the payload pattern.
     ///
+    /// Every byte of the payload is set to `payload_pattern`, see
+    /// [`Builder::payload_pattern_mode`] to use an incrementing or random payload.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -302,7 +390,31 @@ impl Builder {
     #[must_use]
     pub fn payload_pattern(self, payload_pattern: u8) -> Self {
         Self {
-            payload_pattern: PayloadPattern(payload_pattern),
+            payload_pattern: PayloadPattern::Fixed(payload_pattern),
+            ..self
+        }
+    }
+
+    /// Set the payload pattern mode.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, PayloadPattern};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .payload_pattern_mode(PayloadPattern::Incrementing)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn payload_pattern_mode(self, payload_pattern: PayloadPattern) -> Self {
+        Self {
+            payload_pattern,
             ..self
         }
     }
@@ -356,83 +468,89 @@ impl Builder {
         }
     }
 
-    /// Set the read timeout.
+    /// Set the ICMP identifier match mode.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use std::time::Duration;
-    /// use trippy_core::Builder;
+    /// use trippy_core::{Builder, IcmpIdentifierMatchMode};
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .read_timeout(Duration::from_millis(50))
+    ///     .icmp_identifier_match_mode(IcmpIdentifierMatchMode::Tolerant)
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn read_timeout(self, read_timeout: Duration) -> Self {
+    pub fn icmp_identifier_match_mode(
+        self,
+        icmp_identifier_match_mode: IcmpIdentifierMatchMode,
+    ) -> Self {
         Self {
-            read_timeout,
+            icmp_identifier_match_mode,
             ..self
         }
     }
 
-    /// Set the TCP connect timeout.
+    /// Set the ICMP source match mode.
+    ///
+    /// In strict mode an `EchoReply` is only accepted if it originates from the target address.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use std::time::Duration;
-    /// use trippy_core::Builder;
+    /// use trippy_core::{Builder, IcmpSourceMatchMode};
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .tcp_connect_timeout(Duration::from_millis(100))
+    ///     .icmp_source_match_mode(IcmpSourceMatchMode::Strict)
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn tcp_connect_timeout(self, tcp_connect_timeout: Duration) -> Self {
+    pub fn icmp_source_match_mode(self, icmp_source_match_mode: IcmpSourceMatchMode) -> Self {
         Self {
-            tcp_connect_timeout,
+            icmp_source_match_mode,
             ..self
         }
     }
 
-    /// Set the maximum number of rounds.
+    /// Set the ICMP quoted payload mode.
     ///
-    /// If set to `None` then the tracer will run indefinitely, otherwise it
-    /// will stop after the given number of rounds.
+    /// If enabled then the original datagram quoted in `TimeExceeded` and `DestinationUnreachable`
+    /// responses is retained, up to a bounded size, in each completed probe.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use trippy_core::Builder;
+    /// use trippy_core::{Builder, IcmpQuotedPayloadMode};
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_rounds(Some(10)).build()?;
+    /// let tracer = Builder::new(addr)
+    ///     .icmp_quoted_payload_mode(IcmpQuotedPayloadMode::Enabled)
+    ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_rounds(self, max_rounds: Option<usize>) -> Self {
+    pub fn icmp_quoted_payload_mode(self, icmp_quoted_payload_mode: IcmpQuotedPayloadMode) -> Self {
         Self {
-            max_rounds: max_rounds
-                .and_then(|max_rounds| NonZeroUsize::new(max_rounds).map(MaxRounds)),
+            icmp_quoted_payload_mode,
             ..self
         }
     }
 
-    /// Set the first ttl.
+    /// Set the firewall mark (`SO_MARK`) to apply to probe sockets.
+    ///
+    /// This is only supported on Linux.
     ///
     /// # Examples
     ///
@@ -442,19 +560,21 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).first_ttl(2).build()?;
+    /// let tracer = Builder::new(addr).fwmark(Some(42)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn first_ttl(self, first_ttl: u8) -> Self {
-        Self {
-            first_ttl: TimeToLive(first_ttl),
-            ..self
-        }
+    pub fn fwmark(self, fwmark: Option<u32>) -> Self {
+        Self { fwmark, ..self }
     }
 
-    /// Set the maximum ttl.
+    /// Set the key used to derive the nonce included in ICMP echo request payloads.
+    ///
+    /// If set then the first 8 bytes of the payload of each ICMP echo request are replaced with a
+    /// nonce derived from the probe sequence number and this key, and echo replies whose payload
+    /// does not carry the expected nonce are discarded.  This allows replies to be verified even
+    /// if the ICMP identifier is rewritten, for example by a NAT.
     ///
     /// # Examples
     ///
@@ -464,44 +584,54 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_ttl(16).build()?;
+    /// let tracer = Builder::new(addr).icmp_nonce_key(Some(0x1234)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_ttl(self, max_ttl: u8) -> Self {
+    pub fn icmp_nonce_key(self, icmp_nonce_key: Option<u64>) -> Self {
         Self {
-            max_ttl: TimeToLive(max_ttl),
+            icmp_nonce_key,
             ..self
         }
     }
 
-    /// Set the grace duration.
+    /// Set the range of ICMP identifiers used to probe for identifier rewriting.
+    ///
+    /// If set then each ICMP echo request is sent with an identifier offset from the trace
+    /// identifier by its sequence number modulo the range, and the identifier of each echo reply is
+    /// compared with that sent.  This reveals how, if at all, a NAT rewrote the identifiers.  The
+    /// comparisons are available from [`Tracer::identifier_comparisons`].
+    ///
+    /// Only applicable for the ICMP protocol.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use std::time::Duration;
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr)
-    ///     .grace_duration(Duration::from_millis(100))
-    ///     .build()?;
+    /// let tracer = Builder::new(addr).icmp_identifier_range(Some(16)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn grace_duration(self, grace_duration: Duration) -> Self {
+    pub fn icmp_identifier_range(self, icmp_identifier_range: Option<u16>) -> Self {
         Self {
-            grace_duration,
+            icmp_identifier_range,
             ..self
         }
     }
 
-    /// Set the max number of probes in flight at any given time.
+    /// Set the exact length of the payload of ICMP probes.
+    ///
+    /// If set then the payload of each ICMP echo request is exactly this length and the packet
+    /// size is computed from it, overriding [`Builder::packet_size`].  This is useful for testing
+    /// fragmentation behaviour where the payload length must be controlled directly.
+    ///
+    /// Only applicable for the ICMP protocol.
     ///
     /// # Examples
     ///
@@ -511,19 +641,24 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_inflight(22).build()?;
+    /// let tracer = Builder::new(addr).icmp_payload_len(Some(512)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_inflight(self, max_inflight: u8) -> Self {
+    pub fn icmp_payload_len(self, icmp_payload_len: Option<u16>) -> Self {
         Self {
-            max_inflight: MaxInflight(max_inflight),
+            icmp_payload_len,
             ..self
         }
     }
 
-    /// Set the initial sequence number.
+    /// Set the ICMP code of ICMP echo request probes.
+    ///
+    /// The code of a standard ICMP echo request is zero.  A non-zero code may be set to test how
+    /// responders handle echo requests with unexpected codes.
+    ///
+    /// Only applicable for the ICMP protocol and not supported in unprivileged mode.
     ///
     /// # Examples
     ///
@@ -533,43 +668,72 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).initial_sequence(35000).build()?;
+    /// let tracer = Builder::new(addr).icmp_echo_code(1).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn initial_sequence(self, initial_sequence: u16) -> Self {
+    pub fn icmp_echo_code(self, icmp_echo_code: u8) -> Self {
         Self {
-            initial_sequence: Sequence(initial_sequence),
+            icmp_echo_code,
             ..self
         }
     }
 
-    /// Set the port direction.
+    /// Set the `Don't fragment` bit mode of IPv4 probes.
+    ///
+    /// The `Don't fragment` bit is set by default.  It may be cleared to send probes which routers
+    /// may fragment, such as to observe how fragments are reassembled along the path.
+    ///
+    /// Only applicable for IPv4, IPv6 has no `Don't fragment` bit.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # fn main() -> anyhow::Result<()> {
     /// use std::net::IpAddr;
-    /// use trippy_core::{Builder, Port, PortDirection};
+    /// use trippy_core::{Builder, DontFragmentMode};
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .port_direction(PortDirection::FixedDest(Port(8080)))
+    ///     .dont_fragment_mode(DontFragmentMode::Disabled)
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn port_direction(self, port_direction: PortDirection) -> Self {
+    pub fn dont_fragment_mode(self, dont_fragment_mode: DontFragmentMode) -> Self {
         Self {
-            port_direction,
+            dont_fragment_mode,
             ..self
         }
     }
 
-    /// Set the minimum round duration.
+    /// Set the TCP flags of TCP probes.
+    ///
+    /// Flags other than `TcpFlags::Syn` require privileged mode and IPv4.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, Protocol, TcpFlags};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .protocol(Protocol::Tcp)
+    ///     .tcp_flags(TcpFlags::Ack)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn tcp_flags(self, tcp_flags: TcpFlags) -> Self {
+        Self { tcp_flags, ..self }
+    }
+
+    /// Set the read timeout.
     ///
     /// # Examples
     ///
@@ -581,20 +745,20 @@ impl Builder {
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .min_round_duration(Duration::from_millis(500))
+    ///     .read_timeout(Duration::from_millis(50))
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn min_round_duration(self, min_round_duration: Duration) -> Self {
+    pub fn read_timeout(self, read_timeout: Duration) -> Self {
         Self {
-            min_round_duration,
+            read_timeout,
             ..self
         }
     }
 
-    /// Set the maximum round duration.
+    /// Set the TCP connect timeout.
     ///
     /// # Examples
     ///
@@ -606,20 +770,24 @@ impl Builder {
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
     /// let tracer = Builder::new(addr)
-    ///     .max_round_duration(Duration::from_millis(1500))
+    ///     .tcp_connect_timeout(Duration::from_millis(100))
     ///     .build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_round_duration(self, max_round_duration: Duration) -> Self {
+    pub fn tcp_connect_timeout(self, tcp_connect_timeout: Duration) -> Self {
         Self {
-            max_round_duration,
+            tcp_connect_timeout,
             ..self
         }
     }
 
-    /// Set the maximum number of samples to record.
+    /// Set the maximum number of attempts to bind an unprivileged ICMP socket.
+    ///
+    /// For unprivileged ICMP sockets on Linux the kernel uses the bound local port as the ICMP
+    /// identifier.  If the port for the trace identifier is in use then the next port is tried, up
+    /// to the given number of attempts.  This has no effect on other platforms.
     ///
     /// # Examples
     ///
@@ -629,19 +797,22 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_samples(256).build()?;
+    /// let tracer = Builder::new(addr).icmp_bind_attempts(5).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_samples(self, max_samples: usize) -> Self {
+    pub fn icmp_bind_attempts(self, icmp_bind_attempts: u8) -> Self {
         Self {
-            max_samples,
+            icmp_bind_attempts,
             ..self
         }
     }
 
-    /// Set the maximum number of flows to record.
+    /// Set the maximum number of rounds.
+    ///
+    /// If set to `None` then the tracer will run indefinitely, otherwise it
+    /// will stop after the given number of rounds.
     ///
     /// # Examples
     ///
@@ -651,16 +822,24 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).max_flows(64).build()?;
+    /// let tracer = Builder::new(addr).max_rounds(Some(10)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn max_flows(self, max_flows: usize) -> Self {
-        Self { max_flows, ..self }
+    pub fn max_rounds(self, max_rounds: Option<usize>) -> Self {
+        Self {
+            max_rounds: max_rounds
+                .and_then(|max_rounds| NonZeroUsize::new(max_rounds).map(MaxRounds)),
+            ..self
+        }
     }
 
-    /// Drop privileges after connection is established.
+    /// Set the maximum number of probes to send.
+    ///
+    /// If set to `None` then the tracer will send probes indefinitely,
+    /// otherwise it will stop sending once the given number of probes have
+    /// been sent across all rounds and stop after the current round completes.
     ///
     /// # Examples
     ///
@@ -670,19 +849,26 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).drop_privileges(true).build()?;
+    /// let tracer = Builder::new(addr).max_packets(Some(100)).build()?;
     /// # Ok(())
     /// # }
     /// ```
     #[must_use]
-    pub fn drop_privileges(self, drop_privileges: bool) -> Self {
+    pub fn max_packets(self, max_packets: Option<usize>) -> Self {
         Self {
-            drop_privileges,
+            max_packets: max_packets
+                .and_then(|max_packets| NonZeroUsize::new(max_packets).map(MaxPackets)),
             ..self
         }
     }
 
-    /// Build the `Tracer`.
+    /// Set the maximum number of consecutive rounds without a response from the target.
+    ///
+    /// If set to `None` then the tracer will trace indefinitely when the
+    /// target never responds, otherwise it will stop once the given number of
+    /// consecutive rounds have probed up to the maximum ttl without a
+    /// response from the target and the final round will be completed with
+    /// `CompletionReason::DestinationSilent`.
     ///
     /// # Examples
     ///
@@ -692,82 +878,824 @@ impl Builder {
     /// use trippy_core::Builder;
     ///
     /// let addr = IpAddr::from([1, 1, 1, 1]);
-    /// let tracer = Builder::new(addr).build()?;
+    /// let tracer = Builder::new(addr).max_silent_rounds(Some(5)).build()?;
     /// # Ok(())
     /// # }
     /// ```
+    #[must_use]
+    pub fn max_silent_rounds(self, max_silent_rounds: Option<usize>) -> Self {
+        Self {
+            max_silent_rounds: max_silent_rounds
+                .and_then(|max_silent_rounds| NonZeroUsize::new(max_silent_rounds).map(MaxRounds)),
+            ..self
+        }
+    }
+
+    /// Set the minimum interval between sending consecutive probes.
     ///
-    /// # Errors
+    /// If set to `None` then probes are sent as fast as the maximum number of
+    /// in-flight probes allows, otherwise each probe is sent no sooner than
+    /// the given interval after the previous probe, across all rounds.  The
+    /// maximum round duration is extended as needed such that a probe can be
+    /// sent for every ttl in a round.
     ///
-    /// This function will return `Error::BadConfig` if the configuration is invalid.
-    pub fn build(self) -> Result<Tracer> {
-        match (self.protocol, self.port_direction) {
-            (Protocol::Udp, PortDirection::None) => {
-                return Err(Error::BadConfig(
-                    "port_direction may not be None for udp protocol".to_string(),
-                ));
-            }
-            (Protocol::Tcp, PortDirection::None) => {
-                return Err(Error::BadConfig(
-                    "port_direction may not be None for tcp protocol".to_string(),
-                ));
-            }
-            _ => (),
-        }
-        if self.first_ttl.0 > MAX_TTL {
-            return Err(Error::BadConfig(format!(
-                "first_ttl {} > {MAX_TTL}",
-                self.first_ttl.0
-            )));
-        }
-        if self.max_ttl.0 > MAX_TTL {
-            return Err(Error::BadConfig(format!(
-                "max_ttl {} > {MAX_TTL}",
-                self.max_ttl.0
-            )));
-        }
-        if self.initial_sequence.0 > MAX_INITIAL_SEQUENCE {
-            return Err(Error::BadConfig(format!(
-                "initial_sequence {} > {MAX_INITIAL_SEQUENCE}",
-                self.initial_sequence.0
-            )));
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .send_interval(Some(Duration::from_millis(20)))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn send_interval(self, send_interval: Option<Duration>) -> Self {
+        Self {
+            send_interval,
+            ..self
         }
-        Ok(Tracer::new(
-            self.interface,
-            self.source_addr,
-            self.target_addr,
-            self.privilege_mode,
-            self.protocol,
-            self.packet_size,
-            self.payload_pattern,
-            self.tos,
-            self.icmp_extension_parse_mode,
-            self.read_timeout,
-            self.tcp_connect_timeout,
-            self.trace_identifier,
-            self.max_rounds,
-            self.first_ttl,
-            self.max_ttl,
-            self.grace_duration,
-            self.max_inflight,
-            self.initial_sequence,
-            self.multipath_strategy,
-            self.port_direction,
-            self.min_round_duration,
-            self.max_round_duration,
-            self.max_samples,
-            self.max_flows,
-            self.drop_privileges,
-        ))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{Port, config};
-    use config::defaults;
-    use std::net::Ipv4Addr;
+    /// Set the first ttl.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).first_ttl(2).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn first_ttl(self, first_ttl: u8) -> Self {
+        Self {
+            first_ttl: TimeToLive(first_ttl),
+            ..self
+        }
+    }
+
+    /// Set the maximum ttl.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_ttl(16).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_ttl(self, max_ttl: u8) -> Self {
+        Self {
+            max_ttl: TimeToLive(max_ttl),
+            ..self
+        }
+    }
+
+    /// Set the grace duration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .grace_duration(Duration::from_millis(100))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn grace_duration(self, grace_duration: Duration) -> Self {
+        Self {
+            grace_duration,
+            ..self
+        }
+    }
+
+    /// Set the grace duration used when the last response was received from the target.
+    ///
+    /// The `grace_duration` is used when the last response was received from an intermediate
+    /// hop.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .grace_duration(Duration::from_millis(50))
+    ///     .target_grace_duration(Duration::from_millis(500))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn target_grace_duration(self, target_grace_duration: Duration) -> Self {
+        Self {
+            target_grace_duration,
+            ..self
+        }
+    }
+
+    /// Set the max number of probes in flight at any given time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_inflight(22).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_inflight(self, max_inflight: u8) -> Self {
+        Self {
+            max_inflight: MaxInflight(max_inflight),
+            ..self
+        }
+    }
+
+    /// Set the initial sequence number.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).initial_sequence(35000).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn initial_sequence(self, initial_sequence: u16) -> Self {
+        Self {
+            initial_sequence: Sequence(initial_sequence),
+            ..self
+        }
+    }
+
+    /// Set the port direction.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, Port, PortDirection};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .port_direction(PortDirection::FixedDest(Port(8080)))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn port_direction(self, port_direction: PortDirection) -> Self {
+        Self {
+            port_direction,
+            ..self
+        }
+    }
+
+    /// Set the seed used to randomize the source port of each round.
+    ///
+    /// If set then the source port of every probe in a round is derived from
+    /// the seed and the round, such that successive rounds sample different
+    /// flows, and the same seed always yields the same sequence of ports.
+    ///
+    /// This is only supported for the `udp` protocol with the `paris` or
+    /// `dublin` multipath strategy and a fixed destination port.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, MultipathStrategy, Port, PortDirection, Protocol};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .protocol(Protocol::Udp)
+    ///     .multipath_strategy(MultipathStrategy::Paris)
+    ///     .port_direction(PortDirection::FixedDest(Port(33434)))
+    ///     .src_port_seed(Some(42))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn src_port_seed(self, src_port_seed: Option<u64>) -> Self {
+        Self {
+            src_port_seed,
+            ..self
+        }
+    }
+
+    /// Set the minimum round duration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .min_round_duration(Duration::from_millis(500))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn min_round_duration(self, min_round_duration: Duration) -> Self {
+        Self {
+            min_round_duration,
+            ..self
+        }
+    }
+
+    /// Set the maximum round duration.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::time::Duration;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .max_round_duration(Duration::from_millis(1500))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_round_duration(self, max_round_duration: Duration) -> Self {
+        Self {
+            max_round_duration,
+            ..self
+        }
+    }
+
+    /// Continue probing past the target.
+    ///
+    /// By default, no probes are sent for time-to-live values beyond that of
+    /// the target once it has been found.  If enabled, the full range of
+    /// time-to-live values up to `max_ttl` is probed in every round.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).continue_past_target(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn continue_past_target(self, continue_past_target: bool) -> Self {
+        Self {
+            continue_past_target,
+            ..self
+        }
+    }
+
+    /// Set how `DestinationUnreachable` responses are classified.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, UnreachableMode};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .unreachable_mode(UnreachableMode::Code)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn unreachable_mode(self, unreachable_mode: UnreachableMode) -> Self {
+        Self {
+            unreachable_mode,
+            ..self
+        }
+    }
+
+    /// Treat a `DestinationUnreachable` response as reaching the target in ICMP mode.
+    ///
+    /// By default, only an `EchoReply` marks the target as reached when tracing with the ICMP
+    /// protocol.  If enabled, a `DestinationUnreachable` response is also considered, as
+    /// classified by the `unreachable_mode`.  This has no effect for other protocols.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).icmp_unreachable_reached(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn icmp_unreachable_reached(self, icmp_unreachable_reached: bool) -> Self {
+        Self {
+            icmp_unreachable_reached,
+            ..self
+        }
+    }
+
+    /// Set which responses are attributed to a hop when multiple addresses respond to a probe.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, MultiResponseMode};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .multi_response_mode(MultiResponseMode::All)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn multi_response_mode(self, multi_response_mode: MultiResponseMode) -> Self {
+        Self {
+            multi_response_mode,
+            ..self
+        }
+    }
+
+    /// Set which hops are probed, by address, after the first round.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, HopAddrFilter};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .hop_addr_filter(HopAddrFilter::Allow(vec![IpAddr::from([10, 0, 0, 1])]))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn hop_addr_filter(self, hop_addr_filter: HopAddrFilter) -> Self {
+        Self {
+            hop_addr_filter,
+            ..self
+        }
+    }
+
+    /// Set the maximum number of samples to record.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_samples(256).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_samples(self, max_samples: usize) -> Self {
+        Self {
+            max_samples,
+            ..self
+        }
+    }
+
+    /// Set the number of most recent rounds which contribute to the hop statistics.
+    ///
+    /// Only the last `window` rounds contribute to the loss and round trip time statistics of each
+    /// hop, older rounds are evicted as newer rounds are recorded.  If `None` (or zero) then all
+    /// rounds contribute.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).window(Some(10)).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn window(self, window: Option<usize>) -> Self {
+        Self {
+            window: window.and_then(NonZeroUsize::new),
+            ..self
+        }
+    }
+
+    /// Set the maximum number of distinct addresses to record per hop.
+    ///
+    /// Once a hop has recorded `max_addrs` distinct addresses, recording a further address evicts
+    /// the least frequently seen of the others.  If `None` (or zero) then all addresses are
+    /// recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_addrs(Some(8)).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_addrs(self, max_addrs: Option<usize>) -> Self {
+        Self {
+            max_addrs: max_addrs.and_then(NonZeroUsize::new),
+            ..self
+        }
+    }
+
+    /// Set the maximum number of flows to record.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).max_flows(64).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn max_flows(self, max_flows: usize) -> Self {
+        Self { max_flows, ..self }
+    }
+
+    /// Set how to handle responses with a negative round trip time.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::{Builder, NegativeRttMode};
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr)
+    ///     .negative_rtt_mode(NegativeRttMode::Discard)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn negative_rtt_mode(self, negative_rtt_mode: NegativeRttMode) -> Self {
+        Self {
+            negative_rtt_mode,
+            ..self
+        }
+    }
+
+    /// Drop privileges after connection is established.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).drop_privileges(true).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn drop_privileges(self, drop_privileges: bool) -> Self {
+        Self {
+            drop_privileges,
+            ..self
+        }
+    }
+
+    /// Register a [`TraceObserver`] to be notified of the progress of the
+    /// trace.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use std::sync::Arc;
+    /// use trippy_core::{Builder, Round, TraceObserver};
+    ///
+    /// #[derive(Debug)]
+    /// struct Progress;
+    ///
+    /// impl TraceObserver for Progress {
+    ///     fn on_round_complete(&self, round: &Round<'_>) {
+    ///         println!("{:?}", round.reason);
+    ///     }
+    /// }
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).observer(Arc::new(Progress)).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn observer(self, observer: Arc<dyn TraceObserver>) -> Self {
+        Self {
+            observer: Some(observer),
+            ..self
+        }
+    }
+
+    /// Build the `Tracer`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::net::IpAddr;
+    /// use trippy_core::Builder;
+    ///
+    /// let addr = IpAddr::from([1, 1, 1, 1]);
+    /// let tracer = Builder::new(addr).build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return `Error::BadConfig` if the configuration is invalid.
+    ///
+    /// This function will return `Error::AddressFamilyMismatch` if the source address is not of
+    /// the same address family as the target address.
+    #[expect(clippy::too_many_lines)]
+    pub fn build(self) -> Result<Tracer> {
+        if let Some(source_addr) = self.source_addr {
+            SourceAddr::check_family(source_addr, self.target_addr)?;
+        }
+        match (self.protocol, self.port_direction) {
+            (Protocol::Udp, PortDirection::None) => {
+                return Err(Error::BadConfig(
+                    "port_direction may not be None for udp protocol".to_string(),
+                ));
+            }
+            (Protocol::Tcp, PortDirection::None) => {
+                return Err(Error::BadConfig(
+                    "port_direction may not be None for tcp protocol".to_string(),
+                ));
+            }
+            _ => (),
+        }
+        if self.src_port_seed.is_some()
+            && !matches!(
+                (self.protocol, self.multipath_strategy, self.port_direction),
+                (
+                    Protocol::Udp,
+                    MultipathStrategy::Paris | MultipathStrategy::Dublin,
+                    PortDirection::FixedDest(_)
+                )
+            )
+        {
+            return Err(Error::BadConfig(
+                "src_port_seed requires the udp protocol with the paris or dublin multipath strategy and a fixed destination port".to_string(),
+            ));
+        }
+        if self.protocol == Protocol::Tcp && !self.tcp_flags.is_syn() {
+            if self.privilege_mode.is_unprivileged() {
+                return Err(Error::BadConfig(format!(
+                    "tcp_flags {} is not supported in unprivileged mode",
+                    self.tcp_flags
+                )));
+            }
+            if self.target_addr.is_ipv6() {
+                return Err(Error::BadConfig(format!(
+                    "tcp_flags {} is not supported for IPv6",
+                    self.tcp_flags
+                )));
+            }
+            if cfg!(windows) {
+                return Err(Error::BadConfig(format!(
+                    "tcp_flags {} is not supported on Windows",
+                    self.tcp_flags
+                )));
+            }
+        }
+        if self.write_pcap.is_some() {
+            if self.privilege_mode.is_unprivileged() {
+                return Err(Error::BadConfig(
+                    "write_pcap is not supported in unprivileged mode".to_string(),
+                ));
+            }
+            if self.target_addr.is_ipv6() {
+                return Err(Error::BadConfig(
+                    "write_pcap is not supported for IPv6".to_string(),
+                ));
+            }
+            if self.protocol == Protocol::Tcp && self.tcp_flags.is_syn() {
+                return Err(Error::BadConfig(
+                    "write_pcap is not supported for tcp with syn tcp_flags".to_string(),
+                ));
+            }
+        }
+        if self.first_ttl.0 > MAX_TTL {
+            return Err(Error::BadConfig(format!(
+                "first_ttl {} > {MAX_TTL}",
+                self.first_ttl.0
+            )));
+        }
+        if self.max_ttl.0 > MAX_TTL {
+            return Err(Error::BadConfig(format!(
+                "max_ttl {} > {MAX_TTL}",
+                self.max_ttl.0
+            )));
+        }
+        if self.initial_sequence.0 > MAX_INITIAL_SEQUENCE {
+            return Err(Error::BadConfig(format!(
+                "initial_sequence {} > {MAX_INITIAL_SEQUENCE}",
+                self.initial_sequence.0
+            )));
+        }
+        if let Some(range) = self.icmp_identifier_range {
+            if range == 0 {
+                return Err(Error::BadConfig(
+                    "icmp_identifier_range must be greater than zero".to_string(),
+                ));
+            }
+            if self.protocol != Protocol::Icmp {
+                return Err(Error::BadConfig(format!(
+                    "icmp_identifier_range is not supported for {} protocol",
+                    self.protocol
+                )));
+            }
+            if self.privilege_mode.is_unprivileged() {
+                return Err(Error::BadConfig(
+                    "icmp_identifier_range is not supported in unprivileged mode".to_string(),
+                ));
+            }
+        }
+        if self.icmp_echo_code != 0 {
+            if self.protocol != Protocol::Icmp {
+                return Err(Error::BadConfig(format!(
+                    "icmp_echo_code is not supported for {} protocol",
+                    self.protocol
+                )));
+            }
+            if self.privilege_mode.is_unprivileged() {
+                return Err(Error::BadConfig(
+                    "icmp_echo_code is not supported in unprivileged mode".to_string(),
+                ));
+            }
+        }
+        let packet_size = if let Some(payload_len) = self.icmp_payload_len {
+            if self.protocol != Protocol::Icmp {
+                return Err(Error::BadConfig(format!(
+                    "icmp_payload_len is not supported for {} protocol",
+                    self.protocol
+                )));
+            }
+            let header_size = if self.target_addr.is_ipv4() {
+                IPV4_ICMP_HEADER_SIZE
+            } else {
+                IPV6_ICMP_HEADER_SIZE
+            };
+            let max_payload_len = MAX_PACKET_SIZE - header_size;
+            if usize::from(payload_len) > max_payload_len {
+                return Err(Error::BadConfig(format!(
+                    "icmp_payload_len {payload_len} > {max_payload_len}"
+                )));
+            }
+            PacketSize(u16::try_from(usize::from(payload_len) + header_size).unwrap_or(u16::MAX))
+        } else {
+            self.packet_size
+        };
+        if self.icmp_bind_attempts == 0 {
+            return Err(Error::BadConfig(
+                "icmp_bind_attempts must be greater than zero".to_string(),
+            ));
+        }
+        Ok(Tracer::new(
+            self.interface,
+            self.write_pcap,
+            self.source_addr,
+            self.target_addr,
+            self.privilege_mode,
+            self.protocol,
+            packet_size,
+            self.payload_pattern,
+            self.tos,
+            self.icmp_extension_parse_mode,
+            self.icmp_identifier_match_mode,
+            self.icmp_source_match_mode,
+            self.icmp_quoted_payload_mode,
+            self.fwmark,
+            self.icmp_nonce_key,
+            self.icmp_identifier_range,
+            self.icmp_payload_len,
+            self.icmp_echo_code,
+            self.dont_fragment_mode,
+            self.tcp_flags,
+            self.read_timeout,
+            self.tcp_connect_timeout,
+            self.icmp_bind_attempts,
+            self.trace_identifier,
+            self.max_rounds,
+            self.max_packets,
+            self.max_silent_rounds,
+            self.send_interval,
+            self.first_ttl,
+            self.max_ttl,
+            self.grace_duration,
+            self.target_grace_duration,
+            self.max_inflight,
+            self.initial_sequence,
+            self.multipath_strategy,
+            self.port_direction,
+            self.src_port_seed,
+            self.min_round_duration,
+            self.max_round_duration,
+            self.continue_past_target,
+            self.unreachable_mode,
+            self.icmp_unreachable_reached,
+            self.multi_response_mode,
+            self.hop_addr_filter,
+            self.max_samples,
+            self.max_flows,
+            self.negative_rtt_mode,
+            self.window,
+            self.max_addrs,
+            self.drop_privileges,
+            self.observer,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Port, config};
+    use config::defaults;
+    use std::net::Ipv4Addr;
     use std::num::NonZeroUsize;
 
     const SOURCE_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
@@ -778,9 +1706,22 @@ mod tests {
         let tracer = Builder::new(TARGET_ADDR).build().unwrap();
         assert_eq!(TARGET_ADDR, tracer.target_addr());
         assert_eq!(None, tracer.source_addr());
+        assert_eq!(0, tracer.protocol_mismatches());
+        assert_eq!(0, tracer.truncated_responses());
         assert_eq!(None, tracer.interface());
         assert_eq!(defaults::DEFAULT_MAX_SAMPLES, tracer.max_samples());
         assert_eq!(defaults::DEFAULT_MAX_FLOWS, tracer.max_flows());
+        assert_eq!(
+            defaults::DEFAULT_NEGATIVE_RTT_MODE,
+            tracer.negative_rtt_mode()
+        );
+        assert_eq!(None, tracer.window());
+        assert_eq!(None, tracer.max_addrs());
+    }
+
+    #[test]
+    fn test_builder_minimal_channel() {
+        let tracer = Builder::new(TARGET_ADDR).build().unwrap();
         assert_eq!(defaults::DEFAULT_STRATEGY_PROTOCOL, tracer.protocol());
         assert_eq!(TraceId::default(), tracer.trace_identifier());
         assert_eq!(defaults::DEFAULT_PRIVILEGE_MODE, tracer.privilege_mode());
@@ -793,23 +1734,61 @@ mod tests {
             tracer.packet_size().0
         );
         assert_eq!(
-            defaults::DEFAULT_STRATEGY_PAYLOAD_PATTERN,
-            tracer.payload_pattern().0
+            PayloadPattern::Fixed(defaults::DEFAULT_STRATEGY_PAYLOAD_PATTERN),
+            tracer.payload_pattern()
+        );
+        assert_eq!(defaults::DEFAULT_STRATEGY_TOS, tracer.tos().0);
+        assert_eq!(DontFragmentMode::Enabled, tracer.dont_fragment_mode());
+        assert_eq!(defaults::DEFAULT_TCP_FLAGS, tracer.tcp_flags());
+        assert_eq!(PortDirection::None, tracer.port_direction());
+        assert_eq!(None, tracer.src_port_seed());
+        assert_eq!(None, tracer.fwmark());
+        assert_eq!(
+            defaults::DEFAULT_STRATEGY_READ_TIMEOUT,
+            tracer.read_timeout()
+        );
+        assert_eq!(
+            defaults::DEFAULT_STRATEGY_TCP_CONNECT_TIMEOUT,
+            tracer.tcp_connect_timeout()
+        );
+    }
+
+    #[test]
+    fn test_builder_minimal_icmp() {
+        let tracer = Builder::new(TARGET_ADDR).build().unwrap();
+        assert_eq!(
+            defaults::DEFAULT_ICMP_EXTENSION_PARSE_MODE,
+            tracer.icmp_extension_parse_mode()
+        );
+        assert_eq!(
+            defaults::DEFAULT_ICMP_IDENTIFIER_MATCH_MODE,
+            tracer.icmp_identifier_match_mode()
         );
-        assert_eq!(defaults::DEFAULT_STRATEGY_TOS, tracer.tos().0);
         assert_eq!(
-            defaults::DEFAULT_ICMP_EXTENSION_PARSE_MODE,
-            tracer.icmp_extension_parse_mode()
+            defaults::DEFAULT_ICMP_SOURCE_MATCH_MODE,
+            tracer.icmp_source_match_mode()
         );
         assert_eq!(
-            defaults::DEFAULT_STRATEGY_READ_TIMEOUT,
-            tracer.read_timeout()
+            defaults::DEFAULT_ICMP_QUOTED_PAYLOAD_MODE,
+            tracer.icmp_quoted_payload_mode()
         );
+        assert_eq!(None, tracer.icmp_nonce_key());
+        assert_eq!(None, tracer.icmp_identifier_range());
+        assert_eq!(None, tracer.icmp_payload_len());
+        assert_eq!(0, tracer.icmp_echo_code());
         assert_eq!(
-            defaults::DEFAULT_STRATEGY_TCP_CONNECT_TIMEOUT,
-            tracer.tcp_connect_timeout()
+            defaults::DEFAULT_ICMP_BIND_ATTEMPTS,
+            tracer.icmp_bind_attempts()
         );
+    }
+
+    #[test]
+    fn test_builder_minimal_strategy() {
+        let tracer = Builder::new(TARGET_ADDR).build().unwrap();
         assert_eq!(None, tracer.max_rounds());
+        assert_eq!(None, tracer.max_packets());
+        assert_eq!(None, tracer.max_silent_rounds());
+        assert_eq!(None, tracer.send_interval());
         assert_eq!(defaults::DEFAULT_STRATEGY_FIRST_TTL, tracer.first_ttl().0);
         assert_eq!(defaults::DEFAULT_STRATEGY_MAX_TTL, tracer.max_ttl().0);
         assert_eq!(
@@ -824,7 +1803,10 @@ mod tests {
             defaults::DEFAULT_STRATEGY_INITIAL_SEQUENCE,
             tracer.initial_sequence().0
         );
-        assert_eq!(PortDirection::None, tracer.port_direction());
+        assert_eq!(
+            defaults::DEFAULT_STRATEGY_TARGET_GRACE_DURATION,
+            tracer.target_grace_duration()
+        );
         assert_eq!(
             defaults::DEFAULT_STRATEGY_MIN_ROUND_DURATION,
             tracer.min_round_duration()
@@ -833,6 +1815,27 @@ mod tests {
             defaults::DEFAULT_STRATEGY_MAX_ROUND_DURATION,
             tracer.max_round_duration()
         );
+        assert!(!tracer.continue_past_target());
+        assert_eq!(
+            defaults::DEFAULT_UNREACHABLE_MODE,
+            tracer.unreachable_mode()
+        );
+        assert!(!tracer.icmp_unreachable_reached());
+        assert_eq!(
+            defaults::DEFAULT_MULTI_RESPONSE_MODE,
+            tracer.multi_response_mode()
+        );
+        assert_eq!(&HopAddrFilter::All, tracer.hop_addr_filter());
+    }
+
+    #[test]
+    fn test_builder_payload_pattern_mode() {
+        let tracer = Builder::new(TARGET_ADDR)
+            .payload_pattern(0xff)
+            .payload_pattern_mode(PayloadPattern::Random)
+            .build()
+            .unwrap();
+        assert_eq!(PayloadPattern::Random, tracer.payload_pattern());
     }
 
     #[test]
@@ -842,6 +1845,25 @@ mod tests {
             .interface(Some("eth0"))
             .max_samples(10)
             .max_flows(20)
+            .negative_rtt_mode(NegativeRttMode::Discard)
+            .window(Some(10))
+            .max_addrs(Some(8))
+            .build()
+            .unwrap();
+        assert_eq!(TARGET_ADDR, tracer.target_addr());
+        // note that `source_addr` is not set until the tracer is run
+        assert_eq!(None, tracer.source_addr());
+        assert_eq!(Some("eth0"), tracer.interface());
+        assert_eq!(10, tracer.max_samples());
+        assert_eq!(20, tracer.max_flows());
+        assert_eq!(NegativeRttMode::Discard, tracer.negative_rtt_mode());
+        assert_eq!(NonZeroUsize::new(10), tracer.window());
+        assert_eq!(NonZeroUsize::new(8), tracer.max_addrs());
+    }
+
+    #[test]
+    fn test_builder_full_channel() {
+        let tracer = Builder::new(TARGET_ADDR)
             .protocol(Protocol::Udp)
             .trace_identifier(101)
             .privilege_mode(PrivilegeMode::Unprivileged)
@@ -849,52 +1871,108 @@ mod tests {
             .packet_size(128)
             .payload_pattern(0xff)
             .tos(0x1a)
-            .icmp_extension_parse_mode(IcmpExtensionParseMode::Enabled)
+            .dont_fragment_mode(DontFragmentMode::Disabled)
+            .tcp_flags(TcpFlags::Fin)
+            .port_direction(PortDirection::FixedSrc(Port(8080)))
+            .fwmark(Some(42))
             .read_timeout(Duration::from_millis(50))
             .tcp_connect_timeout(Duration::from_millis(100))
-            .max_rounds(Some(10))
-            .first_ttl(2)
-            .max_ttl(16)
-            .grace_duration(Duration::from_millis(100))
-            .max_inflight(22)
-            .initial_sequence(35000)
-            .port_direction(PortDirection::FixedSrc(Port(8080)))
-            .min_round_duration(Duration::from_millis(500))
-            .max_round_duration(Duration::from_millis(1500))
             .build()
             .unwrap();
-
-        assert_eq!(TARGET_ADDR, tracer.target_addr());
-        // note that `source_addr` is not set until the tracer is run
-        assert_eq!(None, tracer.source_addr());
-        assert_eq!(Some("eth0"), tracer.interface());
-        assert_eq!(10, tracer.max_samples());
-        assert_eq!(20, tracer.max_flows());
         assert_eq!(Protocol::Udp, tracer.protocol());
         assert_eq!(TraceId(101), tracer.trace_identifier());
         assert_eq!(PrivilegeMode::Unprivileged, tracer.privilege_mode());
         assert_eq!(MultipathStrategy::Paris, tracer.multipath_strategy());
         assert_eq!(PacketSize(128), tracer.packet_size());
-        assert_eq!(PayloadPattern(0xff), tracer.payload_pattern());
+        assert_eq!(PayloadPattern::Fixed(0xff), tracer.payload_pattern());
         assert_eq!(TypeOfService(0x1a), tracer.tos());
+        assert_eq!(DontFragmentMode::Disabled, tracer.dont_fragment_mode());
+        assert_eq!(TcpFlags::Fin, tracer.tcp_flags());
+        assert_eq!(PortDirection::FixedSrc(Port(8080)), tracer.port_direction());
+        assert_eq!(Some(42), tracer.fwmark());
+        assert_eq!(Duration::from_millis(50), tracer.read_timeout());
+        assert_eq!(Duration::from_millis(100), tracer.tcp_connect_timeout());
+    }
+
+    #[test]
+    fn test_builder_full_icmp() {
+        let tracer = Builder::new(TARGET_ADDR)
+            .icmp_extension_parse_mode(IcmpExtensionParseMode::Enabled)
+            .icmp_identifier_match_mode(IcmpIdentifierMatchMode::Tolerant)
+            .icmp_source_match_mode(IcmpSourceMatchMode::Strict)
+            .icmp_quoted_payload_mode(IcmpQuotedPayloadMode::Enabled)
+            .icmp_nonce_key(Some(0x1234))
+            .icmp_bind_attempts(5)
+            .build()
+            .unwrap();
         assert_eq!(
             IcmpExtensionParseMode::Enabled,
             tracer.icmp_extension_parse_mode()
         );
-        assert_eq!(Duration::from_millis(50), tracer.read_timeout());
-        assert_eq!(Duration::from_millis(100), tracer.tcp_connect_timeout());
+        assert_eq!(
+            IcmpIdentifierMatchMode::Tolerant,
+            tracer.icmp_identifier_match_mode()
+        );
+        assert_eq!(IcmpSourceMatchMode::Strict, tracer.icmp_source_match_mode());
+        assert_eq!(
+            IcmpQuotedPayloadMode::Enabled,
+            tracer.icmp_quoted_payload_mode()
+        );
+        assert_eq!(Some(0x1234), tracer.icmp_nonce_key());
+        assert_eq!(5, tracer.icmp_bind_attempts());
+    }
+
+    #[test]
+    fn test_builder_full_strategy() {
+        let tracer = Builder::new(TARGET_ADDR)
+            .max_rounds(Some(10))
+            .max_packets(Some(100))
+            .max_silent_rounds(Some(5))
+            .send_interval(Some(Duration::from_millis(20)))
+            .first_ttl(2)
+            .max_ttl(16)
+            .grace_duration(Duration::from_millis(100))
+            .target_grace_duration(Duration::from_millis(300))
+            .max_inflight(22)
+            .initial_sequence(35000)
+            .min_round_duration(Duration::from_millis(500))
+            .max_round_duration(Duration::from_millis(1500))
+            .continue_past_target(true)
+            .unreachable_mode(UnreachableMode::Code)
+            .icmp_unreachable_reached(true)
+            .multi_response_mode(MultiResponseMode::Last)
+            .hop_addr_filter(HopAddrFilter::Deny(vec![SOURCE_ADDR]))
+            .build()
+            .unwrap();
         assert_eq!(
             Some(MaxRounds(NonZeroUsize::new(10).unwrap())),
             tracer.max_rounds()
         );
+        assert_eq!(
+            Some(MaxPackets(NonZeroUsize::new(100).unwrap())),
+            tracer.max_packets()
+        );
+        assert_eq!(
+            Some(MaxRounds(NonZeroUsize::new(5).unwrap())),
+            tracer.max_silent_rounds()
+        );
+        assert_eq!(Some(Duration::from_millis(20)), tracer.send_interval());
         assert_eq!(TimeToLive(2), tracer.first_ttl());
         assert_eq!(TimeToLive(16), tracer.max_ttl());
         assert_eq!(Duration::from_millis(100), tracer.grace_duration());
+        assert_eq!(Duration::from_millis(300), tracer.target_grace_duration());
         assert_eq!(MaxInflight(22), tracer.max_inflight());
         assert_eq!(Sequence(35000), tracer.initial_sequence());
-        assert_eq!(PortDirection::FixedSrc(Port(8080)), tracer.port_direction());
         assert_eq!(Duration::from_millis(500), tracer.min_round_duration());
         assert_eq!(Duration::from_millis(1500), tracer.max_round_duration());
+        assert!(tracer.continue_past_target());
+        assert_eq!(UnreachableMode::Code, tracer.unreachable_mode());
+        assert!(tracer.icmp_unreachable_reached());
+        assert_eq!(MultiResponseMode::Last, tracer.multi_response_mode());
+        assert_eq!(
+            &HopAddrFilter::Deny(vec![SOURCE_ADDR]),
+            tracer.hop_addr_filter()
+        );
     }
 
     #[test]
@@ -906,6 +1984,42 @@ mod tests {
         assert_eq!(None, tracer.max_rounds());
     }
 
+    #[test]
+    fn test_zero_max_packets() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .max_packets(Some(0))
+            .build()
+            .unwrap();
+        assert_eq!(None, tracer.max_packets());
+    }
+
+    #[test]
+    fn test_zero_max_silent_rounds() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .max_silent_rounds(Some(0))
+            .build()
+            .unwrap();
+        assert_eq!(None, tracer.max_silent_rounds());
+    }
+
+    #[test]
+    fn test_zero_window() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .window(Some(0))
+            .build()
+            .unwrap();
+        assert_eq!(None, tracer.window());
+    }
+
+    #[test]
+    fn test_zero_max_addrs() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .max_addrs(Some(0))
+            .build()
+            .unwrap();
+        assert_eq!(None, tracer.max_addrs());
+    }
+
     #[test]
     fn test_invalid_initial_sequence() {
         let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
@@ -914,4 +2028,237 @@ mod tests {
             .unwrap_err();
         assert!(matches!(err, Error::BadConfig(s) if s == "initial_sequence 65535 > 64511"));
     }
+
+    #[test]
+    fn test_zero_icmp_bind_attempts() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .icmp_bind_attempts(0)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_bind_attempts must be greater than zero")
+        );
+    }
+
+    #[test]
+    fn test_icmp_payload_len() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .packet_size(84)
+            .icmp_payload_len(Some(512))
+            .build()
+            .unwrap();
+        assert_eq!(Some(512), tracer.icmp_payload_len());
+        assert_eq!(PacketSize(540), tracer.packet_size());
+    }
+
+    #[test]
+    fn test_icmp_payload_len_ipv6() {
+        let tracer = Builder::new(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+            .icmp_payload_len(Some(512))
+            .build()
+            .unwrap();
+        assert_eq!(PacketSize(560), tracer.packet_size());
+    }
+
+    #[test]
+    fn test_icmp_payload_len_too_large() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .icmp_payload_len(Some(1000))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "icmp_payload_len 1000 > 996"));
+    }
+
+    #[test]
+    fn test_icmp_payload_len_udp() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedDest(Port(33434)))
+            .icmp_payload_len(Some(512))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_payload_len is not supported for udp protocol")
+        );
+    }
+
+    #[test]
+    fn test_icmp_echo_code() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .icmp_echo_code(1)
+            .build()
+            .unwrap();
+        assert_eq!(1, tracer.icmp_echo_code());
+    }
+
+    #[test]
+    fn test_icmp_echo_code_udp() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedDest(Port(33434)))
+            .icmp_echo_code(1)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_echo_code is not supported for udp protocol")
+        );
+    }
+
+    #[test]
+    fn test_icmp_echo_code_unprivileged() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .privilege_mode(PrivilegeMode::Unprivileged)
+            .icmp_echo_code(1)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_echo_code is not supported in unprivileged mode")
+        );
+    }
+
+    #[test]
+    fn test_source_addr_family_mismatch() {
+        let source_addr = IpAddr::from([192, 168, 1, 1]);
+        let target_addr = IpAddr::from([0x2606, 0x4700, 0, 0, 0, 0, 0, 0x1111]);
+        let err = Builder::new(target_addr)
+            .source_addr(Some(source_addr))
+            .build()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::AddressFamilyMismatch { source_addr: s, target_addr: t } if s == source_addr && t == target_addr
+        ));
+    }
+
+    #[test]
+    fn test_icmp_identifier_range() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .icmp_identifier_range(Some(16))
+            .build()
+            .unwrap();
+        assert_eq!(Some(16), tracer.icmp_identifier_range());
+        assert!(tracer.identifier_comparisons().is_empty());
+    }
+
+    #[test]
+    fn test_zero_icmp_identifier_range() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .icmp_identifier_range(Some(0))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_identifier_range must be greater than zero")
+        );
+    }
+
+    #[test]
+    fn test_icmp_identifier_range_udp() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedDest(Port(33434)))
+            .icmp_identifier_range(Some(16))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_identifier_range is not supported for udp protocol")
+        );
+    }
+
+    #[test]
+    fn test_icmp_identifier_range_unprivileged() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .privilege_mode(PrivilegeMode::Unprivileged)
+            .icmp_identifier_range(Some(16))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "icmp_identifier_range is not supported in unprivileged mode")
+        );
+    }
+
+    #[test]
+    fn test_src_port_seed() {
+        let tracer = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Udp)
+            .multipath_strategy(MultipathStrategy::Dublin)
+            .port_direction(PortDirection::FixedDest(Port(33434)))
+            .src_port_seed(Some(42))
+            .build()
+            .unwrap();
+        assert_eq!(Some(42), tracer.src_port_seed());
+    }
+
+    #[test]
+    fn test_src_port_seed_classic() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::FixedDest(Port(33434)))
+            .src_port_seed(Some(42))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "src_port_seed requires the udp protocol with the paris or dublin multipath strategy and a fixed destination port")
+        );
+    }
+
+    #[test]
+    fn test_tcp_flags_unprivileged() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Tcp)
+            .port_direction(PortDirection::FixedDest(Port(80)))
+            .privilege_mode(PrivilegeMode::Unprivileged)
+            .tcp_flags(TcpFlags::Ack)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "tcp_flags ack is not supported in unprivileged mode")
+        );
+    }
+
+    #[test]
+    fn test_tcp_flags_ipv6() {
+        let err = Builder::new(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+            .protocol(Protocol::Tcp)
+            .port_direction(PortDirection::FixedDest(Port(80)))
+            .tcp_flags(TcpFlags::Null)
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "tcp_flags null is not supported for IPv6")
+        );
+    }
+
+    #[test]
+    fn test_write_pcap_unprivileged() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .privilege_mode(PrivilegeMode::Unprivileged)
+            .write_pcap(Some("trace.pcap"))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "write_pcap is not supported in unprivileged mode")
+        );
+    }
+
+    #[test]
+    fn test_write_pcap_ipv6() {
+        let err = Builder::new(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]))
+            .write_pcap(Some("trace.pcap"))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::BadConfig(s) if s == "write_pcap is not supported for IPv6"));
+    }
+
+    #[test]
+    fn test_write_pcap_tcp_syn() {
+        let err = Builder::new(IpAddr::from([1, 2, 3, 4]))
+            .protocol(Protocol::Tcp)
+            .port_direction(PortDirection::FixedDest(Port(80)))
+            .write_pcap(Some("trace.pcap"))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::BadConfig(s) if s == "write_pcap is not supported for tcp with syn tcp_flags")
+        );
+    }
 }
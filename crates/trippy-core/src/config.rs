@@ -1,15 +1,19 @@
 use crate::types::Port;
 use crate::{
-    MaxInflight, MaxRounds, PacketSize, PayloadPattern, Sequence, TimeToLive, TraceId,
+    MaxInflight, MaxPackets, MaxRounds, PacketSize, PayloadPattern, Sequence, TimeToLive, TraceId,
     TypeOfService,
 };
 use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr};
+use std::num::NonZeroUsize;
 use std::time::Duration;
 
 /// Default values for configuration.
 pub mod defaults {
-    use crate::config::IcmpExtensionParseMode;
+    use crate::config::{
+        DontFragmentMode, IcmpExtensionParseMode, IcmpIdentifierMatchMode, IcmpQuotedPayloadMode,
+        IcmpSourceMatchMode, MultiResponseMode, NegativeRttMode, TcpFlags, UnreachableMode,
+    };
     use crate::{MultipathStrategy, PrivilegeMode, Protocol};
     use std::time::Duration;
 
@@ -26,6 +30,26 @@ pub mod defaults {
     pub const DEFAULT_ICMP_EXTENSION_PARSE_MODE: IcmpExtensionParseMode =
         IcmpExtensionParseMode::Disabled;
 
+    /// The default value for `icmp-tolerant-identifier`.
+    pub const DEFAULT_ICMP_IDENTIFIER_MATCH_MODE: IcmpIdentifierMatchMode =
+        IcmpIdentifierMatchMode::Strict;
+
+    /// The default value for `icmp-strict-source`.
+    pub const DEFAULT_ICMP_SOURCE_MATCH_MODE: IcmpSourceMatchMode = IcmpSourceMatchMode::Any;
+
+    /// The default value for `icmp-quoted-payload`.
+    pub const DEFAULT_ICMP_QUOTED_PAYLOAD_MODE: IcmpQuotedPayloadMode =
+        IcmpQuotedPayloadMode::Disabled;
+
+    /// The default value for `icmp-echo-code`.
+    pub const DEFAULT_ICMP_ECHO_CODE: u8 = 0;
+
+    /// The default value for `dont-fragment-mode`.
+    pub const DEFAULT_DONT_FRAGMENT_MODE: DontFragmentMode = DontFragmentMode::Enabled;
+
+    /// The default value for `icmp-bind-attempts`.
+    pub const DEFAULT_ICMP_BIND_ATTEMPTS: u8 = 3;
+
     /// The default value for `max-inflight`.
     pub const DEFAULT_STRATEGY_MAX_INFLIGHT: u8 = 24;
 
@@ -59,6 +83,12 @@ pub mod defaults {
     /// The default value for `grace-duration`.
     pub const DEFAULT_STRATEGY_GRACE_DURATION: Duration = Duration::from_millis(100);
 
+    /// The default value for `target-grace-duration`.
+    pub const DEFAULT_STRATEGY_TARGET_GRACE_DURATION: Duration = Duration::from_millis(100);
+
+    /// The default value for `continue-past-target`.
+    pub const DEFAULT_STRATEGY_CONTINUE_PAST_TARGET: bool = false;
+
     /// The default TCP connect timeout.
     pub const DEFAULT_STRATEGY_TCP_CONNECT_TIMEOUT: Duration = Duration::from_millis(1000);
 
@@ -67,6 +97,21 @@ pub mod defaults {
 
     /// The default value for `max-flows`.
     pub const DEFAULT_MAX_FLOWS: usize = 64;
+
+    /// The default value for `negative-rtt-mode`.
+    pub const DEFAULT_NEGATIVE_RTT_MODE: NegativeRttMode = NegativeRttMode::Clamp;
+
+    /// The default value for `tcp-flags`.
+    pub const DEFAULT_TCP_FLAGS: TcpFlags = TcpFlags::Syn;
+
+    /// The default value for `unreachable-mode`.
+    pub const DEFAULT_UNREACHABLE_MODE: UnreachableMode = UnreachableMode::Address;
+
+    /// The default value for `icmp-unreachable-reached`.
+    pub const DEFAULT_ICMP_UNREACHABLE_REACHED: bool = false;
+
+    /// The default value for `multi-response-mode`.
+    pub const DEFAULT_MULTI_RESPONSE_MODE: MultiResponseMode = MultiResponseMode::First;
 }
 
 /// The privilege mode.
@@ -125,6 +170,341 @@ impl Display for IcmpExtensionParseMode {
     }
 }
 
+/// How the identifier of an ICMP `EchoReply` is matched against the trace.
+///
+/// Some systems, such as those using unprivileged ICMP sockets or behind a NAT, rewrite the
+/// identifier of outgoing `EchoRequest` packets and so the identifier of the corresponding
+/// `EchoReply` cannot be relied upon.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IcmpIdentifierMatchMode {
+    /// The `EchoReply` identifier must match the trace identifier.
+    Strict,
+    /// The `EchoReply` identifier is ignored and replies are matched on the sequence number and
+    /// source address only.
+    Tolerant,
+}
+
+impl IcmpIdentifierMatchMode {
+    #[must_use]
+    pub const fn is_tolerant(self) -> bool {
+        match self {
+            Self::Strict => false,
+            Self::Tolerant => true,
+        }
+    }
+}
+
+impl Display for IcmpIdentifierMatchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Strict => write!(f, "strict"),
+            Self::Tolerant => write!(f, "tolerant"),
+        }
+    }
+}
+
+/// How the source address of an ICMP `EchoReply` is matched against the trace.
+///
+/// A stray or spoofed `EchoReply` with a matching identifier and sequence number would otherwise
+/// be accepted as having reached the target.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IcmpSourceMatchMode {
+    /// The `EchoReply` may originate from any address.
+    Any,
+    /// The `EchoReply` must originate from the target address.
+    Strict,
+}
+
+impl IcmpSourceMatchMode {
+    #[must_use]
+    pub const fn is_strict(self) -> bool {
+        match self {
+            Self::Any => false,
+            Self::Strict => true,
+        }
+    }
+}
+
+impl Display for IcmpSourceMatchMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "any"),
+            Self::Strict => write!(f, "strict"),
+        }
+    }
+}
+
+/// Whether the quoted original datagram of ICMP error responses is retained.
+///
+/// This is a debugging aid which allows the datagram quoted back by a router in a
+/// `TimeExceeded` or `DestinationUnreachable` response to be compared with the probe that was
+/// sent.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IcmpQuotedPayloadMode {
+    /// Do not retain the quoted original datagram.
+    Disabled,
+    /// Retain the quoted original datagram.
+    Enabled,
+}
+
+impl IcmpQuotedPayloadMode {
+    #[must_use]
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            Self::Disabled => false,
+            Self::Enabled => true,
+        }
+    }
+}
+
+impl Display for IcmpQuotedPayloadMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "disabled"),
+            Self::Enabled => write!(f, "enabled"),
+        }
+    }
+}
+
+/// Whether the `Don't fragment` bit is set on IPv4 probes.
+///
+/// IPv6 has no `Don't fragment` bit and so this does not apply to IPv6 probes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DontFragmentMode {
+    /// Do not set the `Don't fragment` bit and allow probes to be fragmented.
+    Disabled,
+    /// Set the `Don't fragment` bit.
+    Enabled,
+}
+
+impl DontFragmentMode {
+    #[must_use]
+    pub const fn is_enabled(self) -> bool {
+        match self {
+            Self::Disabled => false,
+            Self::Enabled => true,
+        }
+    }
+}
+
+impl Display for DontFragmentMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "disabled"),
+            Self::Enabled => write!(f, "enabled"),
+        }
+    }
+}
+
+/// How to handle a response received before the probe was sent.
+///
+/// Clock adjustments or kernel timestamp quirks can yield a receive time which is earlier than
+/// the send time of the probe and therefore a negative round trip time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NegativeRttMode {
+    /// Clamp negative round trip times to zero.
+    Clamp,
+    /// Discard responses with a negative round trip time.
+    Discard,
+}
+
+impl NegativeRttMode {
+    #[must_use]
+    pub const fn is_discard(self) -> bool {
+        match self {
+            Self::Clamp => false,
+            Self::Discard => true,
+        }
+    }
+}
+
+impl Display for NegativeRttMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Clamp => write!(f, "clamp"),
+            Self::Discard => write!(f, "discard"),
+        }
+    }
+}
+
+/// The TCP flags set on TCP probes.
+///
+/// `Syn` probes are sent by initiating a TCP connection whereas all other flags are set on a
+/// crafted TCP segment sent from a raw socket, which requires privileged mode and IPv4.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TcpFlags {
+    /// Set the `SYN` flag.
+    Syn,
+    /// Set the `ACK` flag.
+    Ack,
+    /// Set the `FIN` flag.
+    Fin,
+    /// Set no flags.
+    Null,
+}
+
+impl TcpFlags {
+    #[must_use]
+    pub const fn is_syn(self) -> bool {
+        match self {
+            Self::Syn => true,
+            Self::Ack | Self::Fin | Self::Null => false,
+        }
+    }
+}
+
+impl Display for TcpFlags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syn => write!(f, "syn"),
+            Self::Ack => write!(f, "ack"),
+            Self::Fin => write!(f, "fin"),
+            Self::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// How `DestinationUnreachable` responses are classified.
+///
+/// In `Address` mode a `DestinationUnreachable` response is terminal only if it originates from
+/// the target address.  In `Code` mode it is terminal only if the code indicates that the target
+/// host was reached (i.e. port or protocol unreachable) whereas codes such as net or host
+/// unreachable are treated as a non-terminal failure of the path at the responding hop.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UnreachableMode {
+    /// Classify by the address of the responding host.
+    Address,
+    /// Classify by the `DestinationUnreachable` code.
+    Code,
+}
+
+impl UnreachableMode {
+    #[must_use]
+    pub const fn is_code(self) -> bool {
+        match self {
+            Self::Address => false,
+            Self::Code => true,
+        }
+    }
+}
+
+impl Display for UnreachableMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Address => write!(f, "address"),
+            Self::Code => write!(f, "code"),
+        }
+    }
+}
+
+/// Which responses are attributed to a hop when multiple addresses respond to the same probe.
+///
+/// A probe can elicit responses from more than one address, for example a late duplicate from a
+/// different router following a reroute.  Only the responding address(es) recorded for the hop
+/// are affected, all other probe data is taken from the first response.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MultiResponseMode {
+    /// Attribute the address of the first response only.
+    First,
+    /// Attribute the addresses of all responses.
+    All,
+    /// Attribute the address of the last response only.
+    Last,
+}
+
+impl Display for MultiResponseMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::First => write!(f, "first"),
+            Self::All => write!(f, "all"),
+            Self::Last => write!(f, "last"),
+        }
+    }
+}
+
+/// Which hops are probed, by address, after the first round.
+///
+/// The address of each hop is discovered in the first round, in which all hops are probed.  In
+/// later rounds only the hops whose most recently observed address is permitted are probed.  Hops
+/// whose address is not yet known, such as those which did not respond in the first round, are
+/// always probed so that their address may be discovered in a later round.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum HopAddrFilter {
+    /// Probe all hops.
+    #[default]
+    All,
+    /// Probe only the hops whose address is in the list, or is not yet known.
+    Allow(Vec<IpAddr>),
+    /// Probe all hops except those whose address is in the list.
+    Deny(Vec<IpAddr>),
+}
+
+impl HopAddrFilter {
+    /// Is a hop with the given address, if known, permitted to be probed?
+    ///
+    /// A hop whose address is not known is always permitted.
+    #[must_use]
+    pub fn permits(&self, addr: Option<IpAddr>) -> bool {
+        match (self, addr) {
+            (Self::All, _) | (_, None) => true,
+            (Self::Allow(addrs), Some(addr)) => addrs.contains(&addr),
+            (Self::Deny(addrs), Some(addr)) => !addrs.contains(&addr),
+        }
+    }
+}
+
+/// How to break a tie when selecting the primary address of a hop.
+///
+/// The primary address of a hop is the address which responded most often, see
+/// [`Hop::best_addr`](crate::Hop::best_addr).  If several addresses responded equally often then
+/// this determines which of them is selected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AddrTieBreak {
+    /// Select the numerically lowest address.
+    LowestIp,
+    /// Select the address which responded first.
+    FirstSeen,
+    /// Select the address which responded most recently.
+    MostRecent,
+}
+
+impl Display for AddrTieBreak {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LowestIp => write!(f, "lowest-ip"),
+            Self::FirstSeen => write!(f, "first-seen"),
+            Self::MostRecent => write!(f, "most-recent"),
+        }
+    }
+}
+
+/// The address family of the tracer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TracerAddrFamily {
+    /// Internet Protocol V4
+    Ipv4,
+    /// Internet Protocol V6
+    Ipv6,
+}
+
+impl From<IpAddr> for TracerAddrFamily {
+    fn from(addr: IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => Self::Ipv4,
+            IpAddr::V6(_) => Self::Ipv6,
+        }
+    }
+}
+
+impl Display for TracerAddrFamily {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ipv4 => write!(f, "ipv4"),
+            Self::Ipv6 => write!(f, "ipv6"),
+        }
+    }
+}
+
 /// The tracing protocol.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Protocol {
@@ -153,6 +533,9 @@ pub enum MultipathStrategy {
     ///
     /// This does _not_ allow fixing both the src and dest port and so `PortDirection::Both` and
     /// `SequenceField::Port` are mutually exclusive.
+    ///
+    /// As the port varies for every probe, each probe may take a different path and so this
+    /// strategy is not flow aware; all responses are collapsed into a single flow.
     Classic,
     /// The UDP `checksum` field is used to store the sequence number.
     ///
@@ -180,6 +563,17 @@ pub enum MultipathStrategy {
     Dublin,
 }
 
+impl MultipathStrategy {
+    /// Does the strategy keep a fixed `flowid` for all probes in a round?
+    ///
+    /// The `Classic` strategy varies the src or dest port for every probe and so each probe may
+    /// take a different path; the responses are therefore not separated into flows.
+    #[must_use]
+    pub const fn is_flow_aware(self) -> bool {
+        !matches!(self, Self::Classic)
+    }
+}
+
 impl Display for MultipathStrategy {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -258,6 +652,23 @@ pub struct StateConfig {
     /// Once the maximum number of flows has been reached no new flows will be
     /// created, existing flows are updated and are never removed.
     pub max_flows: usize,
+    /// How to handle responses with a negative round trip time.
+    pub negative_rtt_mode: NegativeRttMode,
+    /// The number of most recent rounds which contribute to the hop statistics.
+    ///
+    /// If `None` then all rounds contribute, otherwise the contributions of
+    /// older rounds are evicted (FIFO).
+    pub window: Option<NonZeroUsize>,
+    /// The maximum number of distinct addresses to record per hop.
+    ///
+    /// If `None` then all addresses are recorded, otherwise once the maximum
+    /// has been exceeded the least frequently seen address is evicted.
+    pub max_addrs: Option<NonZeroUsize>,
+    /// Whether to record each flow separately.
+    ///
+    /// If `false` then the flows are registered but all rounds are collapsed into
+    /// the default flow, which is reported for every flow.
+    pub flow_aware: bool,
 }
 
 impl Default for StateConfig {
@@ -265,6 +676,10 @@ impl Default for StateConfig {
         Self {
             max_samples: defaults::DEFAULT_MAX_SAMPLES,
             max_flows: defaults::DEFAULT_MAX_FLOWS,
+            negative_rtt_mode: defaults::DEFAULT_NEGATIVE_RTT_MODE,
+            window: None,
+            max_addrs: None,
+            flow_aware: true,
         }
     }
 }
@@ -281,8 +696,20 @@ pub struct ChannelConfig {
     pub initial_sequence: Sequence,
     pub tos: TypeOfService,
     pub icmp_extension_parse_mode: IcmpExtensionParseMode,
+    pub icmp_identifier_match_mode: IcmpIdentifierMatchMode,
+    pub icmp_source_match_mode: IcmpSourceMatchMode,
+    pub icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+    pub fwmark: Option<u32>,
+    pub icmp_nonce_key: Option<u64>,
+    pub icmp_identifier_range: Option<u16>,
+    pub icmp_payload_len: Option<u16>,
+    pub icmp_echo_code: u8,
+    pub dont_fragment_mode: DontFragmentMode,
+    pub tcp_flags: TcpFlags,
     pub read_timeout: Duration,
     pub tcp_connect_timeout: Duration,
+    pub trace_identifier: TraceId,
+    pub icmp_bind_attempts: u8,
 }
 
 impl Default for ChannelConfig {
@@ -293,12 +720,24 @@ impl Default for ChannelConfig {
             source_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             target_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             packet_size: PacketSize(defaults::DEFAULT_STRATEGY_PACKET_SIZE),
-            payload_pattern: PayloadPattern(defaults::DEFAULT_STRATEGY_PAYLOAD_PATTERN),
+            payload_pattern: PayloadPattern::Fixed(defaults::DEFAULT_STRATEGY_PAYLOAD_PATTERN),
             initial_sequence: Sequence(defaults::DEFAULT_STRATEGY_INITIAL_SEQUENCE),
             tos: TypeOfService(defaults::DEFAULT_STRATEGY_TOS),
             icmp_extension_parse_mode: defaults::DEFAULT_ICMP_EXTENSION_PARSE_MODE,
+            icmp_identifier_match_mode: defaults::DEFAULT_ICMP_IDENTIFIER_MATCH_MODE,
+            icmp_source_match_mode: defaults::DEFAULT_ICMP_SOURCE_MATCH_MODE,
+            icmp_quoted_payload_mode: defaults::DEFAULT_ICMP_QUOTED_PAYLOAD_MODE,
+            fwmark: None,
+            icmp_nonce_key: None,
+            icmp_identifier_range: None,
+            icmp_payload_len: None,
+            icmp_echo_code: defaults::DEFAULT_ICMP_ECHO_CODE,
+            dont_fragment_mode: defaults::DEFAULT_DONT_FRAGMENT_MODE,
+            tcp_flags: defaults::DEFAULT_TCP_FLAGS,
             read_timeout: defaults::DEFAULT_STRATEGY_READ_TIMEOUT,
             tcp_connect_timeout: defaults::DEFAULT_STRATEGY_TCP_CONNECT_TIMEOUT,
+            trace_identifier: TraceId::default(),
+            icmp_bind_attempts: defaults::DEFAULT_ICMP_BIND_ATTEMPTS,
         }
     }
 }
@@ -310,15 +749,24 @@ pub struct StrategyConfig {
     pub protocol: Protocol,
     pub trace_identifier: TraceId,
     pub max_rounds: Option<MaxRounds>,
+    pub max_packets: Option<MaxPackets>,
+    pub max_silent_rounds: Option<MaxRounds>,
+    pub send_interval: Option<Duration>,
     pub first_ttl: TimeToLive,
     pub max_ttl: TimeToLive,
     pub grace_duration: Duration,
+    pub target_grace_duration: Duration,
     pub max_inflight: MaxInflight,
     pub initial_sequence: Sequence,
     pub multipath_strategy: MultipathStrategy,
     pub port_direction: PortDirection,
+    pub src_port_seed: Option<u64>,
     pub min_round_duration: Duration,
     pub max_round_duration: Duration,
+    pub continue_past_target: bool,
+    pub unreachable_mode: UnreachableMode,
+    pub icmp_unreachable_reached: bool,
+    pub multi_response_mode: MultiResponseMode,
 }
 
 impl Default for StrategyConfig {
@@ -328,15 +776,24 @@ impl Default for StrategyConfig {
             protocol: defaults::DEFAULT_STRATEGY_PROTOCOL,
             trace_identifier: TraceId::default(),
             max_rounds: None,
+            max_packets: None,
+            max_silent_rounds: None,
+            send_interval: None,
             first_ttl: TimeToLive(defaults::DEFAULT_STRATEGY_FIRST_TTL),
             max_ttl: TimeToLive(defaults::DEFAULT_STRATEGY_MAX_TTL),
             grace_duration: defaults::DEFAULT_STRATEGY_GRACE_DURATION,
+            target_grace_duration: defaults::DEFAULT_STRATEGY_TARGET_GRACE_DURATION,
             max_inflight: MaxInflight(defaults::DEFAULT_STRATEGY_MAX_INFLIGHT),
             initial_sequence: Sequence(defaults::DEFAULT_STRATEGY_INITIAL_SEQUENCE),
             multipath_strategy: defaults::DEFAULT_STRATEGY_MULTIPATH,
             port_direction: PortDirection::None,
+            src_port_seed: None,
             min_round_duration: defaults::DEFAULT_STRATEGY_MIN_ROUND_DURATION,
             max_round_duration: defaults::DEFAULT_STRATEGY_MAX_ROUND_DURATION,
+            continue_past_target: defaults::DEFAULT_STRATEGY_CONTINUE_PAST_TARGET,
+            unreachable_mode: defaults::DEFAULT_UNREACHABLE_MODE,
+            icmp_unreachable_reached: defaults::DEFAULT_ICMP_UNREACHABLE_REACHED,
+            multi_response_mode: defaults::DEFAULT_MULTI_RESPONSE_MODE,
         }
     }
 }
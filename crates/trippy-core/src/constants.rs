@@ -17,3 +17,15 @@ pub const MAX_SEQUENCE_PER_ROUND: u16 = 512;
 /// require two rounds to ensure that delayed probe responses from the immediate prior round can be
 /// detected and excluded.
 pub const MAX_INITIAL_SEQUENCE: u16 = u16::MAX - (MAX_SEQUENCE_PER_ROUND * 2);
+
+/// The lowest port of the dynamic (private) port range.
+///
+/// Randomized source ports are chosen from this range (49152..=65535) to avoid well known and
+/// registered ports.
+pub const MIN_DYNAMIC_PORT: u16 = 49152;
+
+/// The combined size of the IPv4 and ICMP headers of an ICMP probe.
+pub const IPV4_ICMP_HEADER_SIZE: usize = 28;
+
+/// The combined size of the IPv6 and `ICMPv6` headers of an ICMP probe.
+pub const IPV6_ICMP_HEADER_SIZE: usize = 48;
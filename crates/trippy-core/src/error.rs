@@ -27,6 +27,13 @@ pub enum Error {
     AddressInUse(SocketAddr),
     #[error("source IP address {0} could not be bound")]
     InvalidSourceAddr(IpAddr),
+    #[error(
+        "source IP address {source_addr} and target IP address {target_addr} are of different address families"
+    )]
+    AddressFamilyMismatch {
+        source_addr: IpAddr,
+        target_addr: IpAddr,
+    },
     #[error("missing address from socket call")]
     MissingAddr,
     #[error("connect callback error: {0}")]
@@ -35,6 +42,21 @@ pub enum Error {
     Other(String),
 }
 
+impl Error {
+    /// Is this error transient, such that retrying the trace may succeed?
+    ///
+    /// Network errors, such as an unreachable network or an address which is in use or not yet
+    /// available, are transient whereas configuration and privilege errors are not.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::IoError(err) | Self::ProbeFailed(err) => err.kind().is_transient(),
+            Self::AddressInUse(_) => true,
+            _ => false,
+        }
+    }
+}
+
 /// Custom IO error result.
 pub type IoResult<T> = std::result::Result<T, IoError>;
 
@@ -73,6 +95,29 @@ pub enum ErrorKind {
     Std(io::ErrorKind),
 }
 
+impl ErrorKind {
+    /// Is this error kind transient?
+    const fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::HostUnreachable
+                | Self::NetUnreachable
+                | Self::Std(
+                    io::ErrorKind::AddrInUse
+                        | io::ErrorKind::AddrNotAvailable
+                        | io::ErrorKind::ConnectionRefused
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                        | io::ErrorKind::NetworkDown
+                        | io::ErrorKind::NetworkUnreachable
+                        | io::ErrorKind::HostUnreachable
+                )
+        )
+    }
+}
+
 /// Io operation.
 #[derive(Debug)]
 pub enum IoOperation {
@@ -91,6 +136,15 @@ pub enum IoOperation {
     SetReusePort,
     SetHeaderIncluded,
     SetUnicastHopsV6,
+    SetMark,
+    SetMtuDiscover,
+    SetRecvTtl,
+    RecvTtl,
+    SetRecvPktInfo,
+    RecvIfIndex,
+    SetRecvTclass,
+    CreatePcap,
+    WritePcap,
     WSACreateEvent,
     WSARecvFrom,
     WSAEventSelect,
@@ -122,6 +176,15 @@ impl Display for IoOperation {
             Self::SetReusePort => write!(f, "set reuse port"),
             Self::SetHeaderIncluded => write!(f, "set header included"),
             Self::SetUnicastHopsV6 => write!(f, "set unicast hops v6"),
+            Self::SetMark => write!(f, "set mark"),
+            Self::SetMtuDiscover => write!(f, "set MTU discover"),
+            Self::SetRecvTtl => write!(f, "set recv TTL"),
+            Self::RecvTtl => write!(f, "recv TTL"),
+            Self::SetRecvPktInfo => write!(f, "set recv pktinfo"),
+            Self::RecvIfIndex => write!(f, "recv ifindex"),
+            Self::SetRecvTclass => write!(f, "set recv tclass"),
+            Self::CreatePcap => write!(f, "create pcap file"),
+            Self::WritePcap => write!(f, "write pcap record"),
             Self::WSACreateEvent => write!(f, "WSA create event"),
             Self::WSARecvFrom => write!(f, "WSA recv from"),
             Self::WSAEventSelect => write!(f, "WSA event select"),
@@ -136,3 +199,26 @@ impl Display for IoOperation {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(io::ErrorKind::NetworkUnreachable, true; "network unreachable")]
+    #[test_case(io::ErrorKind::AddrNotAvailable, true; "address not available")]
+    #[test_case(io::ErrorKind::PermissionDenied, false; "permission denied")]
+    #[test_case(io::ErrorKind::InvalidInput, false; "invalid input")]
+    fn test_is_transient(kind: io::ErrorKind, expected: bool) {
+        let err = Error::IoError(IoError::Other(
+            io::Error::from(kind),
+            IoOperation::NewSocket,
+        ));
+        assert_eq!(expected, err.is_transient());
+    }
+
+    #[test]
+    fn test_is_transient_config() {
+        assert!(!Error::BadConfig(String::from("bad")).is_transient());
+    }
+}
@@ -61,6 +61,7 @@ mod constants;
 mod error;
 mod flows;
 mod net;
+mod observer;
 mod probe;
 mod state;
 mod strategy;
@@ -72,19 +73,26 @@ use net::source::SourceAddr;
 
 pub use builder::Builder;
 pub use config::{
-    IcmpExtensionParseMode, MultipathStrategy, PortDirection, PrivilegeMode, Protocol, defaults,
+    AddrTieBreak, DontFragmentMode, HopAddrFilter, IcmpExtensionParseMode, IcmpIdentifierMatchMode,
+    IcmpQuotedPayloadMode, IcmpSourceMatchMode, MultiResponseMode, MultipathStrategy,
+    NegativeRttMode, PortDirection, PrivilegeMode, Protocol, TcpFlags, TracerAddrFamily,
+    UnreachableMode, defaults,
 };
 pub use constants::MAX_TTL;
 pub use error::Error;
 pub use flows::{FlowEntry, FlowId};
+pub use net::effective_payload_size;
+pub use net::identifier::IdentifierComparison;
+pub use net::source::{AddressClass, address_class};
+pub use observer::TraceObserver;
 pub use probe::{
-    Extension, Extensions, IcmpPacketType, MplsLabelStack, MplsLabelStackMember, Probe,
-    ProbeComplete, ProbeStatus, UnknownExtension,
+    Extension, Extensions, IcmpPacketCode, IcmpPacketType, MplsLabelStack, MplsLabelStackMember,
+    Probe, ProbeComplete, ProbeStatus, UnknownExtension,
 };
-pub use state::{Hop, NatStatus, State};
-pub use strategy::{Action, CompletionReason, Round, Strategy};
+pub use state::{AsnChange, AsnTracker, DestinationRtt, Hop, NatStatus, State};
+pub use strategy::{Action, CompletionReason, Round, StopReason, Strategy};
 pub use tracer::Tracer;
 pub use types::{
-    Dscp, Ecn, Flags, MaxInflight, MaxRounds, PacketSize, PayloadPattern, Port, RoundId, Sequence,
-    TimeToLive, TraceId, TypeOfService,
+    Dscp, Ecn, Flags, MaxInflight, MaxPackets, MaxRounds, PacketSize, PayloadPattern, Port,
+    RoundId, Sequence, TimeToLive, TraceId, TypeOfService,
 };
@@ -1,5 +1,8 @@
+use crate::Protocol;
+use crate::config::TracerAddrFamily;
 use crate::error::Result;
 use crate::probe::{Probe, Response};
+use crate::types::PacketSize;
 
 /// Common types and helper functions.
 mod common;
@@ -19,9 +22,15 @@ mod platform;
 /// A network socket.
 mod socket;
 
+/// Write probe packets to a pcap file.
+pub mod pcap;
+
 /// A channel for sending and receiving probes.
 pub mod channel;
 
+/// Probe for ICMP identifier rewriting.
+pub mod identifier;
+
 /// Determine the source address.
 pub mod source;
 
@@ -39,3 +48,81 @@ pub trait Network {
     /// Returns `None` if the read times out or the packet read is not one of the types expected.
     fn recv_probe(&mut self) -> Result<Option<Response>>;
 }
+
+/// The effective payload size of probes of the given packet size, protocol and address family.
+///
+/// The packet size includes the IP header and the ICMP or UDP header. The packet size is not used
+/// for tcp probes and so the payload is always empty.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use trippy_core::{PacketSize, Protocol, TracerAddrFamily, effective_payload_size};
+///
+/// let payload_size =
+///     effective_payload_size(PacketSize(84), Protocol::Icmp, TracerAddrFamily::Ipv4)?;
+/// assert_eq!(56, payload_size);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns `Error::InvalidPacketSize` if the packet size is too small to hold the headers or
+/// exceeds the maximum packet size.
+pub fn effective_payload_size(
+    packet_size: PacketSize,
+    protocol: Protocol,
+    family: TracerAddrFamily,
+) -> Result<usize> {
+    let packet_size = usize::from(packet_size.0);
+    match family {
+        TracerAddrFamily::Ipv4 => ipv4::payload_size(packet_size, protocol),
+        TracerAddrFamily::Ipv6 => ipv6::payload_size(packet_size, protocol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use test_case::test_case;
+
+    #[test_case(84, Protocol::Icmp, TracerAddrFamily::Ipv4, 56; "ipv4 icmp")]
+    #[test_case(28, Protocol::Icmp, TracerAddrFamily::Ipv4, 0; "ipv4 icmp minimum")]
+    #[test_case(1024, Protocol::Icmp, TracerAddrFamily::Ipv4, 996; "ipv4 icmp maximum")]
+    #[test_case(84, Protocol::Udp, TracerAddrFamily::Ipv4, 56; "ipv4 udp")]
+    #[test_case(28, Protocol::Udp, TracerAddrFamily::Ipv4, 0; "ipv4 udp minimum")]
+    #[test_case(84, Protocol::Tcp, TracerAddrFamily::Ipv4, 0; "ipv4 tcp")]
+    #[test_case(84, Protocol::Icmp, TracerAddrFamily::Ipv6, 36; "ipv6 icmp")]
+    #[test_case(48, Protocol::Icmp, TracerAddrFamily::Ipv6, 0; "ipv6 icmp minimum")]
+    #[test_case(1024, Protocol::Icmp, TracerAddrFamily::Ipv6, 976; "ipv6 icmp maximum")]
+    #[test_case(84, Protocol::Udp, TracerAddrFamily::Ipv6, 36; "ipv6 udp")]
+    #[test_case(48, Protocol::Udp, TracerAddrFamily::Ipv6, 0; "ipv6 udp minimum")]
+    #[test_case(84, Protocol::Tcp, TracerAddrFamily::Ipv6, 0; "ipv6 tcp")]
+    fn test_effective_payload_size(
+        packet_size: u16,
+        protocol: Protocol,
+        family: TracerAddrFamily,
+        expected: usize,
+    ) {
+        let payload_size = effective_payload_size(PacketSize(packet_size), protocol, family);
+        assert_eq!(expected, payload_size.unwrap());
+    }
+
+    #[test_case(27, Protocol::Icmp, TracerAddrFamily::Ipv4; "ipv4 icmp too small")]
+    #[test_case(1025, Protocol::Icmp, TracerAddrFamily::Ipv4; "ipv4 icmp too large")]
+    #[test_case(27, Protocol::Udp, TracerAddrFamily::Ipv4; "ipv4 udp too small")]
+    #[test_case(47, Protocol::Icmp, TracerAddrFamily::Ipv6; "ipv6 icmp too small")]
+    #[test_case(47, Protocol::Udp, TracerAddrFamily::Ipv6; "ipv6 udp too small")]
+    #[test_case(1025, Protocol::Udp, TracerAddrFamily::Ipv6; "ipv6 udp too large")]
+    fn test_effective_payload_size_invalid(
+        packet_size: u16,
+        protocol: Protocol,
+        family: TracerAddrFamily,
+    ) {
+        let err = effective_payload_size(PacketSize(packet_size), protocol, family).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(size) if size == usize::from(packet_size)));
+    }
+}
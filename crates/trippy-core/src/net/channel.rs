@@ -1,29 +1,48 @@
 use crate::config::ChannelConfig;
 use crate::error::{Error, Result};
+use crate::net::common::{ErrorMapper, PayloadBuf};
+use crate::net::identifier::IdentifierProbe;
+use crate::net::pcap::PcapWriter;
 use crate::net::socket::Socket;
 use crate::net::{Network, ipv4::Ipv4, ipv6::Ipv6, platform};
 use crate::probe::{Probe, Response};
-use crate::{Port, PrivilegeMode, Protocol};
+use crate::{Port, PrivilegeMode, Protocol, TcpFlags, TraceId};
 use arrayvec::ArrayVec;
-use std::net::IpAddr;
+use std::cell::RefCell;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, SystemTime};
 use tracing::instrument;
 
 /// The maximum size of the IP packet we allow.
 pub const MAX_PACKET_SIZE: usize = 1024;
 
+/// The space allowed beyond `MAX_PACKET_SIZE` for a received ICMP error message.
+///
+/// An ICMP error message may quote the whole of a probe of up to `MAX_PACKET_SIZE` bytes and also
+/// carry an rfc4884 extension structure, such as a deep MPLS label stack and interface
+/// information, which must not be truncated.
+pub const ICMP_EXTENSION_HEADROOM: usize = 512;
+
+/// The size of the buffer used to receive a probe response.
+pub const MAX_RECV_PACKET_SIZE: usize = MAX_PACKET_SIZE + ICMP_EXTENSION_HEADROOM;
+
 /// The maximum number of TCP probes we allow.
 const MAX_TCP_PROBES: usize = 256;
 
 /// A channel for sending and receiving `Probe` packets.
 pub struct Channel<S: Socket> {
     protocol: Protocol,
+    tcp_flags: TcpFlags,
     read_timeout: Duration,
     tcp_connect_timeout: Duration,
     send_socket: Option<S>,
     recv_socket: S,
+    tcp_recv_socket: Option<S>,
     tcp_probes: ArrayVec<TcpProbe<S>, MAX_TCP_PROBES>,
     family_config: FamilyConfig,
+    trace_identifier: Option<TraceId>,
 }
 
 /// The IP family configuration for the channel.
@@ -45,12 +64,36 @@ impl<S: Socket> Channel<S> {
         let raw = config.privilege_mode == PrivilegeMode::Privileged;
         platform::startup()?;
         let ipv4_length_order = platform::Ipv4ByteOrder::for_address(config.source_addr)?;
-        let send_socket = match config.protocol {
+        let raw_tcp = config.protocol == Protocol::Tcp && !config.tcp_flags.is_syn();
+        let mut send_socket = match config.protocol {
             Protocol::Icmp => Some(make_icmp_send_socket(config.source_addr, raw)?),
             Protocol::Udp => Some(make_udp_send_socket(config.source_addr, raw)?),
+            Protocol::Tcp if raw_tcp => Some(make_tcp_send_socket(config.source_addr)?),
             Protocol::Tcp => None,
         };
+        // Only Linux uses the local port of an unprivileged `ICMP` socket as the identifier.
+        let trace_identifier = match (config.protocol, send_socket.as_mut()) {
+            (Protocol::Icmp, Some(socket))
+                if !raw && cfg!(any(target_os = "linux", target_os = "android")) =>
+            {
+                Some(bind_icmp_send_socket(
+                    socket,
+                    config.source_addr,
+                    config.trace_identifier,
+                    config.icmp_bind_attempts,
+                )?)
+            }
+            _ => None,
+        };
         let recv_socket = make_recv_socket(config.source_addr, raw)?;
+        let tcp_recv_socket = if raw_tcp {
+            Some(make_tcp_recv_socket(config.source_addr)?)
+        } else {
+            None
+        };
+        let identifier_probe = config
+            .icmp_identifier_range
+            .map(|range| Arc::new(IdentifierProbe::new(range)));
         let family_config = match (config.source_addr, config.target_addr) {
             (IpAddr::V4(src_addr), IpAddr::V4(dest_addr)) => FamilyConfig::V4(Ipv4 {
                 src_addr,
@@ -62,6 +105,21 @@ impl<S: Socket> Channel<S> {
                 tos: config.tos,
                 protocol: config.protocol,
                 icmp_extension_mode: config.icmp_extension_parse_mode,
+                icmp_identifier_mode: config.icmp_identifier_match_mode,
+                icmp_source_mode: config.icmp_source_match_mode,
+                icmp_quoted_payload_mode: config.icmp_quoted_payload_mode,
+                fwmark: config.fwmark,
+                icmp_nonce_key: config.icmp_nonce_key,
+                icmp_payload_len: config.icmp_payload_len,
+                icmp_echo_code: config.icmp_echo_code,
+                dont_fragment_mode: config.dont_fragment_mode,
+                tcp_flags: config.tcp_flags,
+                protocol_mismatches: Arc::default(),
+                truncated_responses: Arc::default(),
+                effective_privilege_mode: Arc::default(),
+                identifier_probe,
+                pcap: None,
+                payload_buf: PayloadBuf::default(),
             }),
             (IpAddr::V6(src_addr), IpAddr::V6(dest_addr)) => FamilyConfig::V6(Ipv6 {
                 src_addr,
@@ -72,20 +130,94 @@ impl<S: Socket> Channel<S> {
                 tos: config.tos,
                 protocol: config.protocol,
                 icmp_extension_mode: config.icmp_extension_parse_mode,
+                icmp_identifier_mode: config.icmp_identifier_match_mode,
+                icmp_source_mode: config.icmp_source_match_mode,
+                icmp_quoted_payload_mode: config.icmp_quoted_payload_mode,
+                fwmark: config.fwmark,
+                icmp_nonce_key: config.icmp_nonce_key,
+                icmp_payload_len: config.icmp_payload_len,
+                icmp_echo_code: config.icmp_echo_code,
+                protocol_mismatches: Arc::default(),
+                truncated_responses: Arc::default(),
+                effective_privilege_mode: Arc::default(),
+                identifier_probe,
                 initial_sequence: config.initial_sequence,
+                payload_buf: PayloadBuf::default(),
             }),
             _ => unreachable!(),
         };
         Ok(Self {
             protocol: config.protocol,
+            tcp_flags: config.tcp_flags,
             read_timeout: config.read_timeout,
             tcp_connect_timeout: config.tcp_connect_timeout,
             send_socket,
             recv_socket,
+            tcp_recv_socket,
             tcp_probes: ArrayVec::new(),
             family_config,
+            trace_identifier,
         })
     }
+
+    /// The ICMP identifier assigned by the kernel to the unprivileged ICMP send socket, if any.
+    ///
+    /// This may differ from the configured trace identifier if the local port for it was in use.
+    pub const fn trace_identifier(&self) -> Option<TraceId> {
+        self.trace_identifier
+    }
+
+    /// The counter of ICMP error responses dropped as the packet they quote was not of the
+    /// protocol being traced.
+    ///
+    /// Such a mismatch may indicate a NAT device or a responder which quotes the wrong packet.
+    pub fn protocol_mismatches(&self) -> Arc<AtomicUsize> {
+        match &self.family_config {
+            FamilyConfig::V4(ipv4) => Arc::clone(&ipv4.protocol_mismatches),
+            FamilyConfig::V6(ipv6) => Arc::clone(&ipv6.protocol_mismatches),
+        }
+    }
+
+    /// The counter of ICMP responses discarded as they may have been truncated.
+    ///
+    /// A response which fills the whole receive buffer may have been truncated.
+    pub fn truncated_responses(&self) -> Arc<AtomicUsize> {
+        match &self.family_config {
+            FamilyConfig::V4(ipv4) => Arc::clone(&ipv4.truncated_responses),
+            FamilyConfig::V6(ipv6) => Arc::clone(&ipv6.truncated_responses),
+        }
+    }
+
+    /// The privilege mode of the path used to dispatch probes.
+    ///
+    /// This is recorded when the first probe is sent and may differ from the configured privilege
+    /// mode, such as for TCP probes which are sent using a connected stream socket.
+    pub fn effective_privilege_mode(&self) -> Arc<OnceLock<PrivilegeMode>> {
+        match &self.family_config {
+            FamilyConfig::V4(ipv4) => Arc::clone(&ipv4.effective_privilege_mode),
+            FamilyConfig::V6(ipv6) => Arc::clone(&ipv6.effective_privilege_mode),
+        }
+    }
+
+    /// The probe for ICMP identifier rewriting, if enabled.
+    pub fn identifier_probe(&self) -> Option<Arc<IdentifierProbe>> {
+        match &self.family_config {
+            FamilyConfig::V4(ipv4) => ipv4.identifier_probe.clone(),
+            FamilyConfig::V6(ipv6) => ipv6.identifier_probe.clone(),
+        }
+    }
+
+    /// Write each probe sent to `pcap`.
+    ///
+    /// Only IPv4 probes sent using a raw socket, where the full IP packet is built by the
+    /// tracer, are written.
+    #[must_use]
+    pub fn with_pcap(mut self, pcap: PcapWriter) -> Self {
+        if let FamilyConfig::V4(ipv4) = &mut self.family_config {
+            ipv4.pcap = Some(RefCell::new(pcap));
+        }
+        self
+    }
 }
 
 impl<S: Socket> Network for Channel<S> {
@@ -95,14 +227,19 @@ impl<S: Socket> Network for Channel<S> {
         match self.protocol {
             Protocol::Icmp => self.dispatch_icmp_probe(&probe),
             Protocol::Udp => self.dispatch_udp_probe(&probe),
-            Protocol::Tcp => self.dispatch_tcp_probe(&probe),
+            Protocol::Tcp if self.tcp_flags.is_syn() => self.dispatch_tcp_probe(&probe),
+            Protocol::Tcp => self.dispatch_tcp_probe_raw(&probe),
         }
     }
     #[instrument(skip_all, level = "trace")]
     fn recv_probe(&mut self) -> Result<Option<Response>> {
         let prob_response = match self.protocol {
             Protocol::Icmp | Protocol::Udp => self.recv_icmp_probe(),
-            Protocol::Tcp => match self.recv_tcp_sockets()? {
+            Protocol::Tcp if self.tcp_flags.is_syn() => match self.recv_tcp_sockets()? {
+                None => self.recv_icmp_probe(),
+                resp => Ok(resp),
+            },
+            Protocol::Tcp => match self.recv_tcp_segment()? {
                 None => self.recv_icmp_probe(),
                 resp => Ok(resp),
             },
@@ -151,6 +288,15 @@ impl<S: Socket> Channel<S> {
         Ok(())
     }
 
+    /// Dispatch a TCP probe as a crafted TCP segment.
+    #[instrument(skip_all, level = "trace")]
+    fn dispatch_tcp_probe_raw(&mut self, probe: &Probe) -> Result<()> {
+        match (&self.family_config, self.send_socket.as_mut()) {
+            (FamilyConfig::V4(ipv4), Some(socket)) => ipv4.dispatch_tcp_probe_raw(socket, probe),
+            _ => unreachable!(),
+        }
+    }
+
     /// Generate a `ProbeResponse` for the next available ICMP packet, if any
     #[instrument(skip(self), level = "trace")]
     fn recv_icmp_probe(&mut self) -> Result<Option<Response>> {
@@ -197,6 +343,15 @@ impl<S: Socket> Channel<S> {
             Ok(None)
         }
     }
+
+    /// Generate a `ProbeResponse` for the next available TCP segment from the target, if any.
+    #[instrument(skip(self), level = "trace")]
+    fn recv_tcp_segment(&mut self) -> Result<Option<Response>> {
+        match (&self.family_config, self.tcp_recv_socket.as_mut()) {
+            (FamilyConfig::V4(ipv4), Some(socket)) => ipv4.recv_tcp_segment(socket),
+            _ => unreachable!(),
+        }
+    }
 }
 
 /// An entry in the TCP probes array.
@@ -227,6 +382,39 @@ fn make_icmp_send_socket<S: Socket>(addr: IpAddr, raw: bool) -> Result<S> {
     }?)
 }
 
+/// Bind an unprivileged socket for sending `ICMP` packets.
+///
+/// The kernel uses the local port of an unprivileged `ICMP` socket as the ICMP identifier.  If the
+/// port for `identifier` is in use then the next port is tried, up to `attempts` times, and the
+/// identifier of the bound socket is returned.
+#[instrument(skip(socket), level = "trace")]
+fn bind_icmp_send_socket<S: Socket>(
+    socket: &mut S,
+    addr: IpAddr,
+    identifier: TraceId,
+    attempts: u8,
+) -> Result<TraceId> {
+    let mut port = identifier.0;
+    let mut attempt = 1;
+    loop {
+        let local_addr = SocketAddr::new(addr, port);
+        match socket
+            .bind(local_addr)
+            .map_err(|err| ErrorMapper::addr_in_use(Error::IoError(err), local_addr))
+        {
+            Ok(()) => break,
+            Err(Error::AddressInUse(_)) if attempt < attempts => {
+                tracing::debug!(?local_addr, attempt, "icmp identifier in use");
+                port = port.wrapping_add(1);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    let bound_port = socket.local_addr()?.map_or(port, |addr| addr.port());
+    Ok(TraceId(bound_port))
+}
+
 /// Make a socket for sending `UDP` packets.
 #[instrument(level = "trace")]
 fn make_udp_send_socket<S: Socket>(addr: IpAddr, raw: bool) -> Result<S> {
@@ -244,3 +432,84 @@ fn make_recv_socket<S: Socket>(addr: IpAddr, raw: bool) -> Result<S> {
         IpAddr::V6(ipv6addr) => S::new_recv_socket_ipv6(ipv6addr, raw),
     }?)
 }
+
+/// Make a raw socket for sending crafted `TCP` segments.
+#[instrument(level = "trace")]
+fn make_tcp_send_socket<S: Socket>(addr: IpAddr) -> Result<S> {
+    match addr {
+        IpAddr::V4(_) => Ok(S::new_tcp_send_socket_ipv4()?),
+        IpAddr::V6(_) => Err(Error::BadConfig(String::from(
+            "crafted TCP segments are not supported for IPv6",
+        ))),
+    }
+}
+
+/// Make a raw socket for receiving `TCP` segments.
+#[instrument(level = "trace")]
+fn make_tcp_recv_socket<S: Socket>(addr: IpAddr) -> Result<S> {
+    match addr {
+        IpAddr::V4(ipv4addr) => Ok(S::new_tcp_recv_socket_ipv4(ipv4addr)?),
+        IpAddr::V6(_) => Err(Error::BadConfig(String::from(
+            "crafted TCP segments are not supported for IPv6",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::IoError;
+    use crate::net::socket::MockSocket;
+    use mockall::predicate;
+    use std::io;
+    use std::net::Ipv4Addr;
+
+    const SRC_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+
+    #[test]
+    fn test_bind_icmp_send_socket_retry() -> anyhow::Result<()> {
+        let mut mocket = MockSocket::new();
+        let mut seq = mockall::Sequence::new();
+        mocket
+            .expect_bind()
+            .with(predicate::eq(SocketAddr::new(SRC_ADDR, 1234)))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|addr| {
+                Err(IoError::Bind(
+                    io::Error::from(io::ErrorKind::AddrInUse),
+                    addr,
+                ))
+            });
+        mocket
+            .expect_bind()
+            .with(predicate::eq(SocketAddr::new(SRC_ADDR, 1235)))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(()));
+        mocket
+            .expect_local_addr()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(Some(SocketAddr::new(SRC_ADDR, 1235))));
+        let identifier = bind_icmp_send_socket(&mut mocket, SRC_ADDR, TraceId(1234), 3)?;
+        assert_eq!(TraceId(1235), identifier);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_icmp_send_socket_attempts_exhausted() {
+        let mut mocket = MockSocket::new();
+        mocket.expect_bind().times(2).returning(|addr| {
+            Err(IoError::Bind(
+                io::Error::from(io::ErrorKind::AddrInUse),
+                addr,
+            ))
+        });
+        mocket.expect_local_addr().never();
+        let err = bind_icmp_send_socket(&mut mocket, SRC_ADDR, TraceId(1234), 2).unwrap_err();
+        assert!(
+            matches!(err, Error::AddressInUse(addr) if addr == SocketAddr::new(SRC_ADDR, 1235))
+        );
+    }
+}
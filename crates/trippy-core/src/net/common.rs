@@ -1,10 +1,106 @@
 use crate::error::ErrorKind;
 use crate::error::{Error, Result};
+use crate::types::{PayloadPattern, Sequence};
+use siphasher::sip::SipHasher13;
+use std::cell::{RefCell, RefMut};
+use std::hash::{BuildHasher, Hasher, RandomState};
 use std::net::SocketAddr;
 
+/// The maximum length of the nonce included in ICMP echo request payloads.
+pub const ICMP_NONCE_LEN: usize = 8;
+
+/// Write the nonce for `key` and `sequence` to the start of an ICMP echo request `payload`.
+///
+/// If the payload is shorter than [`ICMP_NONCE_LEN`] then the nonce is truncated.
+pub fn write_icmp_nonce(payload: &mut [u8], key: u64, sequence: Sequence) {
+    let nonce = icmp_nonce(key, sequence);
+    let len = payload.len().min(ICMP_NONCE_LEN);
+    payload[..len].copy_from_slice(&nonce[..len]);
+}
+
+/// Does the ICMP echo reply `payload` carry the nonce for `key` and `sequence`?
+///
+/// The `payload_size` is the size of the payload of the ICMP echo requests sent and so the
+/// reply must echo at least as much of the nonce as was sent.
+#[must_use]
+pub fn verify_icmp_nonce(
+    payload: &[u8],
+    payload_size: usize,
+    key: u64,
+    sequence: Sequence,
+) -> bool {
+    let nonce = icmp_nonce(key, sequence);
+    let len = payload_size.min(ICMP_NONCE_LEN);
+    payload.get(..len) == Some(&nonce[..len])
+}
+
+/// The maximum number of bytes of a quoted original datagram to retain.
+pub const MAX_QUOTED_PAYLOAD_LEN: usize = 128;
+
+/// Retain the quoted original datagram `payload` of an ICMP error response.
+///
+/// The `payload` is bounded by the `declared_len` of the quoted datagram, as the buffer may extend
+/// beyond it, and at most [`MAX_QUOTED_PAYLOAD_LEN`] bytes are retained.
+#[must_use]
+pub fn quoted_payload(payload: &[u8], declared_len: usize) -> Vec<u8> {
+    let len = payload.len().min(declared_len).min(MAX_QUOTED_PAYLOAD_LEN);
+    payload[..len].to_vec()
+}
+
+/// Derive the nonce for `sequence` from a keyed hash.
+///
+/// The nonce is the SipHash-1-3, keyed with `key`, of the big-endian `sequence`, such that it is
+/// stable across platforms and Rust versions.
+fn icmp_nonce(key: u64, sequence: Sequence) -> [u8; ICMP_NONCE_LEN] {
+    let mut hasher = SipHasher13::new_with_keys(key, 0);
+    hasher.write(&sequence.0.to_be_bytes());
+    hasher.finish().to_be_bytes()
+}
+
 /// Utility methods to map errors.
 pub struct ErrorMapper;
 
+/// A reusable buffer in which the payload of each outgoing probe is built.
+///
+/// Only the bytes of the payload being built are written, rather than a buffer of the maximum
+/// payload size, and the allocation is retained across probes such that it grows only to the size
+/// of the largest payload built.
+///
+/// The seed of the `Random` payload pattern is chosen when the buffer is created such that every
+/// payload built with it, including those built to recalculate the checksum of quoted probes, is
+/// the same.
+#[derive(Debug)]
+pub struct PayloadBuf {
+    buf: RefCell<Vec<u8>>,
+    seed: u64,
+}
+
+impl PayloadBuf {
+    /// Build a payload of `size` bytes filled with `pattern`.
+    pub fn build(&self, pattern: PayloadPattern, size: usize) -> RefMut<'_, Vec<u8>> {
+        let mut buf = self.buf.borrow_mut();
+        buf.clear();
+        buf.resize(size, 0);
+        pattern.fill(&mut buf, self.seed);
+        buf
+    }
+
+    /// The number of bytes allocated for the buffer.
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        self.buf.borrow().capacity()
+    }
+}
+
+impl Default for PayloadBuf {
+    fn default() -> Self {
+        Self {
+            buf: RefCell::default(),
+            seed: RandomState::new().hash_one(0),
+        }
+    }
+}
+
 impl ErrorMapper {
     /// Convert [`ErrorKind::InProgress`] to [`Ok`].
     pub fn in_progress(err: Error) -> Result<()> {
@@ -48,6 +144,76 @@ mod tests {
 
     const ADDR: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0));
 
+    #[test]
+    fn test_payload_buf() {
+        let buf = PayloadBuf::default();
+        assert_eq!(
+            &[0xaa; 4],
+            buf.build(PayloadPattern::Fixed(0xaa), 4).as_slice()
+        );
+        assert_eq!(
+            &[0xbb; 2],
+            buf.build(PayloadPattern::Fixed(0xbb), 2).as_slice()
+        );
+        assert!(buf.build(PayloadPattern::Fixed(0x00), 0).is_empty());
+        assert!(buf.capacity() >= 4);
+    }
+
+    #[test]
+    fn test_payload_buf_random_is_stable() {
+        let buf = PayloadBuf::default();
+        let first = buf.build(PayloadPattern::Random, 16).clone();
+        assert_eq!(first, *buf.build(PayloadPattern::Random, 16));
+        assert_eq!(first[..8], buf.build(PayloadPattern::Random, 8)[..]);
+    }
+
+    #[test]
+    fn test_payload_buf_scales_with_payload_size() {
+        let buf = PayloadBuf::default();
+        for _ in 0..1000 {
+            drop(buf.build(PayloadPattern::Fixed(0x00), 16));
+        }
+        assert!(buf.capacity() >= 16);
+        assert!(buf.capacity() < 1024);
+    }
+
+    #[test]
+    fn test_icmp_nonce() {
+        let mut payload = [0_u8; 12];
+        write_icmp_nonce(&mut payload, 42, Sequence(33000));
+        assert!(verify_icmp_nonce(&payload, 12, 42, Sequence(33000)));
+        assert!(!verify_icmp_nonce(&payload, 12, 43, Sequence(33000)));
+        assert!(!verify_icmp_nonce(&payload, 12, 42, Sequence(33001)));
+        assert!(!verify_icmp_nonce(&payload[..4], 12, 42, Sequence(33000)));
+        assert_eq!([0_u8; 4], payload[8..]);
+    }
+
+    #[test]
+    fn test_icmp_nonce_stable() {
+        assert_eq!(
+            hex_literal::hex!("4f 7e c7 fb 94 16 6d 5a"),
+            icmp_nonce(42, Sequence(33000))
+        );
+    }
+
+    #[test]
+    fn test_icmp_nonce_truncated() {
+        let mut payload = [0_u8; 3];
+        write_icmp_nonce(&mut payload, 42, Sequence(33000));
+        assert!(verify_icmp_nonce(&payload, 3, 42, Sequence(33000)));
+        assert!(verify_icmp_nonce(&[], 0, 42, Sequence(33000)));
+    }
+
+    #[test]
+    fn test_quoted_payload() {
+        assert_eq!(vec![1, 2, 3], quoted_payload(&[1, 2, 3], 8));
+        assert_eq!(vec![1, 2], quoted_payload(&[1, 2, 3], 2));
+        assert_eq!(
+            MAX_QUOTED_PAYLOAD_LEN,
+            quoted_payload(&[0_u8; MAX_QUOTED_PAYLOAD_LEN + 1], usize::MAX).len()
+        );
+    }
+
     #[test]
     fn test_in_progress() {
         let io_err = io::Error::from(ErrorKind::InProgress);
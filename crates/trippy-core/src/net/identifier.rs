@@ -0,0 +1,156 @@
+use crate::types::{Sequence, TraceId};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The identifier of an ICMP `EchoRequest` compared with that of the corresponding `EchoReply`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct IdentifierComparison {
+    /// The sequence number of the probe.
+    pub sequence: Sequence,
+    /// The identifier sent in the `EchoRequest`.
+    pub sent: u16,
+    /// The identifier received in the `EchoReply`.
+    pub received: u16,
+}
+
+impl IdentifierComparison {
+    /// Whether the identifier was rewritten, such as by a NAT, between the request and the reply.
+    #[must_use]
+    pub const fn is_rewritten(&self) -> bool {
+        self.sent != self.received
+    }
+}
+
+/// Probe for ICMP identifier rewriting.
+///
+/// Each `EchoRequest` is sent with an identifier offset from the trace identifier by its sequence
+/// number modulo `range`, such that a range of known identifiers is sent.  The identifier of each
+/// `EchoReply` is compared with the identifier sent for its sequence number, which reveals how, if
+/// at all, a NAT rewrote the identifier.
+#[derive(Debug)]
+pub struct IdentifierProbe {
+    range: u16,
+    sent: Mutex<HashMap<u16, u16>>,
+    comparisons: Mutex<HashMap<u16, IdentifierComparison>>,
+}
+
+impl IdentifierProbe {
+    pub fn new(range: u16) -> Self {
+        Self {
+            range: range.max(1),
+            sent: Mutex::new(HashMap::new()),
+            comparisons: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The identifier to send in the `EchoRequest` for `trace_id` and `sequence`.
+    ///
+    /// The identifier is recorded so that it may later be compared with that of the reply.
+    pub fn identifier(&self, trace_id: TraceId, sequence: Sequence) -> TraceId {
+        let identifier = trace_id.0.wrapping_add(sequence.0 % self.range);
+        self.sent
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(sequence.0, identifier);
+        TraceId(identifier)
+    }
+
+    /// Compare the `received` identifier of an `EchoReply` for `sequence` with the identifier
+    /// sent, if any.
+    ///
+    /// The comparison is recorded, replacing any earlier comparison for the same sequence number.
+    pub fn compare(&self, sequence: u16, received: u16) -> Option<IdentifierComparison> {
+        let sent = *self
+            .sent
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(&sequence)?;
+        let comparison = IdentifierComparison {
+            sequence: Sequence(sequence),
+            sent,
+            received,
+        };
+        if comparison.is_rewritten() {
+            tracing::debug!(?comparison, "icmp identifier rewritten");
+        }
+        self.comparisons
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .insert(sequence, comparison);
+        Some(comparison)
+    }
+
+    /// The trace identifier for an `identifier` received for `sequence`.
+    ///
+    /// If `identifier` is that which was sent for `sequence` then the offset applied when sending
+    /// is removed, otherwise `identifier` is returned as is and is matched against the trace as
+    /// any other identifier would be.
+    pub fn trace_identifier(&self, sequence: u16, identifier: u16) -> u16 {
+        let sent = self
+            .sent
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(&sequence)
+            .copied();
+        if sent == Some(identifier) {
+            identifier.wrapping_sub(sequence % self.range)
+        } else {
+            identifier
+        }
+    }
+
+    /// The comparisons recorded, ordered by sequence number.
+    pub fn comparisons(&self) -> Vec<IdentifierComparison> {
+        let mut comparisons: Vec<_> = self
+            .comparisons
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .values()
+            .copied()
+            .collect();
+        comparisons.sort_by_key(|comparison| comparison.sequence);
+        comparisons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_range() {
+        let probe = IdentifierProbe::new(4);
+        let identifiers: Vec<_> = (33000..33006)
+            .map(|seq| probe.identifier(TraceId(1000), Sequence(seq)).0)
+            .collect();
+        assert_eq!(vec![1000, 1001, 1002, 1003, 1000, 1001], identifiers);
+    }
+
+    #[test]
+    fn test_compare_not_rewritten() {
+        let probe = IdentifierProbe::new(4);
+        let sent = probe.identifier(TraceId(1000), Sequence(33001));
+        let comparison = probe.compare(33001, sent.0).unwrap();
+        assert!(!comparison.is_rewritten());
+        assert_eq!(1000, probe.trace_identifier(33001, sent.0));
+    }
+
+    #[test]
+    fn test_compare_rewritten() {
+        let probe = IdentifierProbe::new(4);
+        probe.identifier(TraceId(1000), Sequence(33001));
+        let comparison = probe.compare(33001, 4321).unwrap();
+        assert!(comparison.is_rewritten());
+        assert_eq!(1001, comparison.sent);
+        assert_eq!(4321, comparison.received);
+        assert_eq!(4321, probe.trace_identifier(33001, 4321));
+        assert_eq!(vec![comparison], probe.comparisons());
+    }
+
+    #[test]
+    fn test_compare_not_sent() {
+        let probe = IdentifierProbe::new(4);
+        assert_eq!(None, probe.compare(33001, 1000));
+        assert!(probe.comparisons().is_empty());
+    }
+}
@@ -1,28 +1,37 @@
-use crate::config::IcmpExtensionParseMode;
+use crate::config::{
+    DontFragmentMode, IcmpExtensionParseMode, IcmpIdentifierMatchMode, IcmpQuotedPayloadMode,
+    IcmpSourceMatchMode, TcpFlags,
+};
+use crate::constants::IPV4_ICMP_HEADER_SIZE;
 use crate::error::{Error, ErrorKind, Result};
-use crate::net::channel::MAX_PACKET_SIZE;
-use crate::net::common::ErrorMapper;
+use crate::net::channel::{MAX_PACKET_SIZE, MAX_RECV_PACKET_SIZE};
+use crate::net::common::{self, ErrorMapper, PayloadBuf};
+use crate::net::identifier::IdentifierProbe;
+use crate::net::pcap::PcapWriter;
 use crate::net::platform;
-use crate::net::socket::{Socket, SocketError};
+use crate::net::socket::{MtuDiscover, Socket, SocketError};
 use crate::probe::{
     Extensions, IcmpPacketCode, IcmpProtocolResponse, Probe, ProtocolResponse, Response,
-    ResponseData, TcpProtocolResponse, UdpProtocolResponse,
+    ResponseData, TcpProtocolResponse, UdpProtocolResponse, icmp_ip_identification,
 };
 use crate::types::{PacketSize, PayloadPattern, Sequence, TraceId, TypeOfService};
 use crate::{Flags, Port, PrivilegeMode, Protocol};
+use std::cell::RefCell;
 use std::io;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::SystemTime;
 use tracing::instrument;
 use trippy_packet::IpProtocol;
-use trippy_packet::checksum::{icmp_ipv4_checksum, udp_ipv4_checksum};
+use trippy_packet::checksum::{icmp_ipv4_checksum, tcp_ipv4_checksum, udp_ipv4_checksum};
 use trippy_packet::icmpv4::destination_unreachable::DestinationUnreachablePacket;
 use trippy_packet::icmpv4::echo_reply::EchoReplyPacket;
 use trippy_packet::icmpv4::echo_request::EchoRequestPacket;
 use trippy_packet::icmpv4::time_exceeded::TimeExceededPacket;
 use trippy_packet::icmpv4::{IcmpCode, IcmpPacket, IcmpTimeExceededCode, IcmpType};
 use trippy_packet::ipv4::Ipv4Packet;
-use trippy_packet::tcp::TcpPacket;
+use trippy_packet::tcp::{TcpPacket, flags};
 use trippy_packet::udp::UdpPacket;
 
 /// The maximum size of UDP packet we allow.
@@ -34,9 +43,6 @@ const MAX_UDP_PAYLOAD_BUF: usize = MAX_UDP_PACKET_BUF - UdpPacket::minimum_packe
 /// The maximum size of ICMP packet we allow.
 const MAX_ICMP_PACKET_BUF: usize = MAX_PACKET_SIZE - Ipv4Packet::minimum_packet_size();
 
-/// The maximum size of ICMP payload we allow.
-const MAX_ICMP_PAYLOAD_BUF: usize = MAX_ICMP_PACKET_BUF - IcmpPacket::minimum_packet_size();
-
 /// The minimum size of ICMP packets we allow.
 const MIN_PACKET_SIZE_ICMP: usize =
     Ipv4Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size();
@@ -45,11 +51,20 @@ const MIN_PACKET_SIZE_ICMP: usize =
 const MIN_PACKET_SIZE_UDP: usize =
     Ipv4Packet::minimum_packet_size() + UdpPacket::minimum_packet_size();
 
+/// The window size of crafted TCP segments.
+const TCP_WINDOW_SIZE: u16 = 1024;
+
 /// The value for the IPv4 `flags_and_fragment_offset` field to set the `Don't fragment` bit.
 ///
 /// 0100 0000 0000 0000
 const DONT_FRAGMENT: u16 = 0x4000;
 
+/// The path MTU discovery mode for non-raw sockets if the `Don't fragment` bit is set.
+///
+/// This sets the `Don't fragment` bit, as we do for crafted IPv4 headers, without the kernel
+/// rejecting probes which exceed a previously discovered path MTU.
+const MTU_DISCOVER: MtuDiscover = MtuDiscover::Probe;
+
 /// IPv4 configuration.
 #[derive(Debug)]
 pub struct Ipv4 {
@@ -62,6 +77,32 @@ pub struct Ipv4 {
     pub tos: TypeOfService,
     pub protocol: Protocol,
     pub icmp_extension_mode: IcmpExtensionParseMode,
+    pub icmp_identifier_mode: IcmpIdentifierMatchMode,
+    pub icmp_source_mode: IcmpSourceMatchMode,
+    pub icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+    pub fwmark: Option<u32>,
+    pub icmp_nonce_key: Option<u64>,
+    /// The exact length of the payload of ICMP probes, overriding `packet_size` if set.
+    pub icmp_payload_len: Option<u16>,
+    /// The ICMP code of ICMP `EchoRequest` probes.
+    pub icmp_echo_code: u8,
+    /// Whether the `Don't fragment` bit is set on probes.
+    pub dont_fragment_mode: DontFragmentMode,
+    /// The number of ICMP error responses dropped as the quoted packet was not of `protocol`.
+    pub protocol_mismatches: Arc<AtomicUsize>,
+    /// The number of ICMP responses discarded as they filled the receive buffer and so may have
+    /// been truncated.
+    pub truncated_responses: Arc<AtomicUsize>,
+    /// The privilege mode of the path used to dispatch probes, recorded when the first probe is
+    /// sent.
+    pub effective_privilege_mode: Arc<OnceLock<PrivilegeMode>>,
+    /// The probe for ICMP identifier rewriting, if enabled.
+    pub identifier_probe: Option<Arc<IdentifierProbe>>,
+    pub tcp_flags: TcpFlags,
+    /// Write each probe sent using a raw socket to a pcap file.
+    pub pcap: Option<RefCell<PcapWriter>>,
+    /// The buffer in which the payload of each probe is built.
+    pub payload_buf: PayloadBuf,
 }
 
 impl Default for Ipv4 {
@@ -71,11 +112,26 @@ impl Default for Ipv4 {
             dest_addr: Ipv4Addr::UNSPECIFIED,
             byte_order: platform::Ipv4ByteOrder::Network,
             packet_size: PacketSize(0),
-            payload_pattern: PayloadPattern(0),
+            payload_pattern: PayloadPattern::Fixed(0),
             privilege_mode: PrivilegeMode::Privileged,
             tos: TypeOfService(0),
             protocol: Protocol::Icmp,
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            icmp_identifier_mode: IcmpIdentifierMatchMode::Strict,
+            icmp_source_mode: IcmpSourceMatchMode::Any,
+            icmp_quoted_payload_mode: IcmpQuotedPayloadMode::Disabled,
+            fwmark: None,
+            icmp_nonce_key: None,
+            icmp_payload_len: None,
+            icmp_echo_code: 0,
+            dont_fragment_mode: DontFragmentMode::Enabled,
+            protocol_mismatches: Arc::default(),
+            truncated_responses: Arc::default(),
+            effective_privilege_mode: Arc::default(),
+            identifier_probe: None,
+            tcp_flags: TcpFlags::Syn,
+            pcap: None,
+            payload_buf: PayloadBuf::default(),
         }
     }
 }
@@ -90,31 +146,30 @@ impl Ipv4 {
     ) -> Result<()> {
         let mut ipv4_buf = [0_u8; MAX_PACKET_SIZE];
         let mut icmp_buf = [0_u8; MAX_ICMP_PACKET_BUF];
-        let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_ICMP..=MAX_PACKET_SIZE).contains(&packet_size) {
-            return Err(Error::InvalidPacketSize(packet_size));
-        }
+        let payload_size = self.echo_payload_size()?;
         let echo_request = self.make_echo_request_icmp_packet(
             &mut icmp_buf,
-            probe.identifier,
+            self.echo_identifier(probe),
             probe.sequence,
-            icmp_payload_size(packet_size),
+            payload_size,
         )?;
         let ipv4 = self.make_ipv4_packet(
             &mut ipv4_buf,
             IpProtocol::Icmp,
             probe.ttl.0,
-            0,
+            icmp_ip_identification(probe.identifier, probe.sequence),
             echo_request.packet(),
         )?;
         let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), 0);
+        self.set_mark(icmp_send_socket)?;
+        self.record_privilege_mode(self.privilege_mode);
         icmp_send_socket
             .send_to(ipv4.packet(), remote_addr)
             .map_err(Error::IoError)
             .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::HostUnreachable))
             .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::NetUnreachable))
             .map_err(|err| ErrorMapper::probe_failed(err, INVALID_INPUT_KIND))?;
-        Ok(())
+        self.write_pcap(probe, ipv4.packet())
     }
 
     /// Dispatch a UDP probe.
@@ -124,17 +179,14 @@ impl Ipv4 {
         raw_send_socket: &mut S,
         probe: &Probe,
     ) -> Result<()> {
-        let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_UDP..=MAX_PACKET_SIZE).contains(&packet_size) {
-            return Err(Error::InvalidPacketSize(packet_size));
-        }
-        let payload_size = udp_payload_size(packet_size);
-        let payload = &[self.payload_pattern.0; MAX_UDP_PAYLOAD_BUF][0..payload_size];
+        let payload_size = payload_size(usize::from(self.packet_size.0), Protocol::Udp)?;
+        let payload = self.payload_buf.build(self.payload_pattern, payload_size);
+        self.record_privilege_mode(self.privilege_mode);
         match self.privilege_mode {
             PrivilegeMode::Privileged => {
-                self.dispatch_udp_probe_raw(raw_send_socket, probe, payload)
+                self.dispatch_udp_probe_raw(raw_send_socket, probe, &payload)
             }
-            PrivilegeMode::Unprivileged => self.dispatch_udp_probe_non_raw::<S>(probe, payload),
+            PrivilegeMode::Unprivileged => self.dispatch_udp_probe_non_raw::<S>(probe, &payload),
         }
     }
 
@@ -173,12 +225,13 @@ impl Ipv4 {
             udp.packet(),
         )?;
         let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), probe.dest_port.0);
+        self.set_mark(raw_send_socket)?;
         raw_send_socket
             .send_to(ipv4.packet(), remote_addr)
             .map_err(Error::IoError)
             .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::HostUnreachable))
             .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::NetUnreachable))?;
-        Ok(())
+        self.write_pcap(probe, ipv4.packet())
     }
 
     /// Dispatch a UDP probe using a new UDP datagram socket.
@@ -195,6 +248,8 @@ impl Ipv4 {
             .map_err(|err| ErrorMapper::probe_failed(err, ADDR_NOT_AVAILABLE_KIND))?;
         socket.set_ttl(u32::from(probe.ttl.0))?;
         socket.set_tos(u32::from(self.tos.0))?;
+        socket.set_mtu_discover(self.mtu_discover())?;
+        self.set_mark(&mut socket)?;
         socket.send_to(payload, remote_addr)?;
         Ok(())
     }
@@ -202,6 +257,7 @@ impl Ipv4 {
     /// Dispatch a TCP probe.
     #[instrument(skip(self), level = "trace")]
     pub fn dispatch_tcp_probe<S: Socket>(&self, probe: &Probe) -> Result<S> {
+        self.record_privilege_mode(PrivilegeMode::Unprivileged);
         let mut socket = S::new_stream_socket_ipv4()?;
         let local_addr = SocketAddr::new(IpAddr::V4(self.src_addr), probe.src_port.0);
         socket
@@ -212,6 +268,8 @@ impl Ipv4 {
             .map_err(|err| ErrorMapper::probe_failed(err, ADDR_NOT_AVAILABLE_KIND))?;
         socket.set_ttl(u32::from(probe.ttl.0))?;
         socket.set_tos(u32::from(self.tos.0))?;
+        socket.set_mtu_discover(self.mtu_discover())?;
+        self.set_mark(&mut socket)?;
         let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), probe.dest_port.0);
         socket
             .connect(remote_addr)
@@ -222,11 +280,74 @@ impl Ipv4 {
         Ok(socket)
     }
 
+    /// Dispatch a TCP probe using a raw socket with `IP_HDRINCL` set.
+    ///
+    /// As `IP_HDRINCL` is set we must supply the IP and TCP headers which allows us to set the TCP
+    /// flags of the segment rather than the `SYN` sent when initiating a connection.
+    #[instrument(skip(self, raw_send_socket), level = "trace")]
+    pub fn dispatch_tcp_probe_raw<S: Socket>(
+        &self,
+        raw_send_socket: &mut S,
+        probe: &Probe,
+    ) -> Result<()> {
+        self.record_privilege_mode(PrivilegeMode::Privileged);
+        let mut ipv4_buf = [0_u8; MAX_PACKET_SIZE];
+        let mut tcp_buf = [0_u8; TcpPacket::minimum_packet_size()];
+        let tcp = self.make_tcp_packet(
+            &mut tcp_buf,
+            probe.src_port.0,
+            probe.dest_port.0,
+            probe.sequence,
+        )?;
+        let ipv4 = self.make_ipv4_packet(
+            &mut ipv4_buf,
+            IpProtocol::Tcp,
+            probe.ttl.0,
+            probe.identifier.0,
+            tcp.packet(),
+        )?;
+        let remote_addr = SocketAddr::new(IpAddr::V4(self.dest_addr), probe.dest_port.0);
+        self.set_mark(raw_send_socket)?;
+        raw_send_socket
+            .send_to(ipv4.packet(), remote_addr)
+            .map_err(Error::IoError)
+            .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::HostUnreachable))
+            .map_err(|err| ErrorMapper::probe_failed(err, ErrorKind::NetUnreachable))?;
+        self.write_pcap(probe, ipv4.packet())
+    }
+
+    /// Write the `packet` sent for `probe` to the pcap file, if configured.
+    ///
+    /// The `total_length` and `flags_and_fragment_offset` fields of the IPv4 header of `packet`
+    /// may be in host byte order on some platforms and so are always restored to network byte
+    /// order before the packet is written.
+    fn write_pcap(&self, probe: &Probe, packet: &[u8]) -> Result<()> {
+        if let Some(pcap) = &self.pcap {
+            let mut buf = [0_u8; MAX_PACKET_SIZE];
+            buf[..packet.len()].copy_from_slice(packet);
+            let mut ipv4 = Ipv4Packet::new(&mut buf[..packet.len()])?;
+            ipv4.set_total_length(self.byte_order.adjust_length(ipv4.get_total_length()));
+            ipv4.set_flags_and_fragment_offset(
+                self.byte_order
+                    .adjust_length(ipv4.get_flags_and_fragment_offset()),
+            );
+            pcap.borrow_mut().write_packet(probe.sent, ipv4.packet())?;
+        }
+        Ok(())
+    }
+
     /// Receive an ICMP probe response.
+    ///
+    /// A packet which fills the whole receive buffer may have been truncated and so is discarded
+    /// rather than parsed into misleading data.
     #[instrument(skip(self, recv_socket), level = "trace")]
     pub fn recv_icmp_probe<S: Socket>(&self, recv_socket: &mut S) -> Result<Option<Response>> {
-        let mut buf = [0_u8; MAX_PACKET_SIZE];
+        let mut buf = [0_u8; MAX_RECV_PACKET_SIZE];
         match recv_socket.read(&mut buf) {
+            Ok(bytes_read) if bytes_read >= buf.len() => {
+                self.truncated_response(bytes_read);
+                Ok(None)
+            }
             Ok(bytes_read) => {
                 let ipv4 = Ipv4Packet::new_view(&buf[..bytes_read])?;
                 Ok(self.extract_probe_resp(&ipv4)?)
@@ -255,12 +376,14 @@ impl Ipv4 {
         match tcp_socket.take_error()? {
             None => {
                 let addr = tcp_socket.peer_addr()?.ok_or(Error::MissingAddr)?.ip();
+                let ttl = tcp_socket.recv_ttl()?;
+                let ifindex = tcp_socket.recv_ifindex()?;
                 tcp_socket.shutdown()?;
-                return Ok(Some(Response::TcpReply(ResponseData::new(
-                    SystemTime::now(),
-                    addr,
-                    proto_resp,
-                ))));
+                return Ok(Some(Response::TcpReply(
+                    ResponseData::new(SystemTime::now(), addr, proto_resp)
+                        .with_ttl(ttl)
+                        .with_ifindex(ifindex),
+                )));
             }
             Some(err) => match err {
                 SocketError::ConnectionRefused => {
@@ -284,6 +407,86 @@ impl Ipv4 {
         Ok(None)
     }
 
+    /// Receive a TCP segment sent by the target in response to a crafted TCP probe.
+    #[instrument(skip(self, recv_socket), level = "trace")]
+    pub fn recv_tcp_segment<S: Socket>(&self, recv_socket: &mut S) -> Result<Option<Response>> {
+        let mut buf = [0_u8; MAX_RECV_PACKET_SIZE];
+        match recv_socket.read(&mut buf) {
+            Ok(bytes_read) => {
+                let ipv4 = Ipv4Packet::new_view(&buf[..bytes_read])?;
+                Ok(self.extract_tcp_resp(&ipv4)?)
+            }
+            Err(err) => match err.kind() {
+                ErrorKind::Std(io::ErrorKind::WouldBlock) => Ok(None),
+                _ => Err(Error::IoError(err)),
+            },
+        }
+    }
+
+    /// Record the privilege mode of the path used to dispatch a probe.
+    fn record_privilege_mode(&self, privilege_mode: PrivilegeMode) {
+        _ = self.effective_privilege_mode.set(privilege_mode);
+    }
+
+    /// The size of the payload of an `EchoRequest`.
+    ///
+    /// If `icmp_payload_len` is set then the payload is exactly that length and the packet size is
+    /// computed from it, otherwise the payload size is derived from `packet_size`.
+    fn echo_payload_size(&self) -> Result<usize> {
+        match self.icmp_payload_len.map(usize::from) {
+            Some(payload_len) if payload_len + IPV4_ICMP_HEADER_SIZE <= MAX_PACKET_SIZE => {
+                Ok(payload_len)
+            }
+            Some(payload_len) => Err(Error::InvalidPacketSize(
+                payload_len + IPV4_ICMP_HEADER_SIZE,
+            )),
+            None => payload_size(usize::from(self.packet_size.0), Protocol::Icmp),
+        }
+    }
+
+    /// The identifier of the `EchoRequest` for `probe`.
+    ///
+    /// This is the trace identifier unless ICMP identifier rewriting is being probed.
+    fn echo_identifier(&self, probe: &Probe) -> TraceId {
+        self.identifier_probe
+            .as_ref()
+            .map_or(probe.identifier, |identifier_probe| {
+                identifier_probe.identifier(probe.identifier, probe.sequence)
+            })
+    }
+
+    /// The identifier of an `EchoReply` for `sequence` as matched against the trace.
+    ///
+    /// If ICMP identifier rewriting is being probed then the raw `identifier` of the reply is
+    /// compared with the identifier sent, even if they do not match.
+    fn reply_identifier(&self, sequence: u16, identifier: u16) -> u16 {
+        match &self.identifier_probe {
+            Some(identifier_probe) => {
+                identifier_probe.compare(sequence, identifier);
+                identifier_probe.trace_identifier(sequence, identifier)
+            }
+            None => identifier,
+        }
+    }
+
+    /// The identifier of an `EchoRequest` for `sequence` quoted by an ICMP error as matched
+    /// against the trace.
+    fn quoted_identifier(&self, sequence: u16, identifier: u16) -> u16 {
+        self.identifier_probe
+            .as_ref()
+            .map_or(identifier, |identifier_probe| {
+                identifier_probe.trace_identifier(sequence, identifier)
+            })
+    }
+
+    /// Set the firewall mark on a send socket, if configured.
+    fn set_mark<S: Socket>(&self, socket: &mut S) -> Result<()> {
+        if let Some(mark) = self.fwmark {
+            socket.set_mark(mark)?;
+        }
+        Ok(())
+    }
+
     #[instrument(skip(self), level = "trace")]
     fn extract_probe_resp(&self, ipv4: &Ipv4Packet<'_>) -> Result<Option<Response>> {
         let recv = SystemTime::now();
@@ -291,7 +494,7 @@ impl Ipv4 {
         let icmp_v4 = IcmpPacket::new_view(ipv4.payload())?;
         let icmp_type = icmp_v4.get_icmp_type();
         let icmp_code = icmp_v4.get_icmp_code();
-        Ok(match icmp_type {
+        let resp = match icmp_type {
             IcmpType::TimeExceeded => {
                 if IcmpTimeExceededCode::from(icmp_code) == IcmpTimeExceededCode::TtlExpired {
                     let packet = TimeExceededPacket::new_view(icmp_v4.packet())?;
@@ -306,10 +509,12 @@ impl Ipv4 {
                             (ipv4, None)
                         }
                     };
+                    let quoted_payload = self.quoted_payload(&nested_ipv4);
                     self.extract_probe_proto_resp(&nested_ipv4)?
                         .map(|proto_resp| {
                             Response::TimeExceeded(
-                                ResponseData::new(recv, src, proto_resp),
+                                ResponseData::new(recv, src, proto_resp)
+                                    .with_quoted_payload(quoted_payload),
                                 IcmpPacketCode(icmp_code.0),
                                 extension,
                             )
@@ -327,30 +532,103 @@ impl Ipv4 {
                     }
                     IcmpExtensionParseMode::Disabled => None,
                 };
+                let quoted_payload = self.quoted_payload(&nested_ipv4);
                 self.extract_probe_proto_resp(&nested_ipv4)?
                     .map(|proto_resp| {
                         Response::DestinationUnreachable(
-                            ResponseData::new(recv, src, proto_resp),
+                            ResponseData::new(recv, src, proto_resp)
+                                .with_quoted_payload(quoted_payload),
                             IcmpPacketCode(icmp_code.0),
                             extension,
                         )
                     })
             }
             IcmpType::EchoReply => match self.protocol {
+                // In strict source mode a reply with a matching identifier and sequence number is
+                // rejected unless it originates from the target.
+                Protocol::Icmp
+                    if self.icmp_source_mode.is_strict() && src != IpAddr::V4(self.dest_addr) =>
+                {
+                    None
+                }
                 Protocol::Icmp => {
                     let packet = EchoReplyPacket::new_view(icmp_v4.packet())?;
-                    let id = packet.get_identifier();
+                    // In tolerant mode the identifier may have been rewritten (i.e. by the kernel
+                    // for unprivileged sockets or by a NAT) and so we report the wildcard
+                    // identifier and instead require that the reply originates from the target.
                     let seq = packet.get_sequence();
-                    let proto_resp =
-                        ProtocolResponse::Icmp(IcmpProtocolResponse::new(id, seq, None));
-                    Some(Response::EchoReply(
-                        ResponseData::new(recv, src, proto_resp),
-                        IcmpPacketCode(icmp_code.0),
-                    ))
+                    let identifier = self.reply_identifier(seq, packet.get_identifier());
+                    let id = match self.icmp_identifier_mode {
+                        IcmpIdentifierMatchMode::Strict => Some(identifier),
+                        IcmpIdentifierMatchMode::Tolerant if src == IpAddr::V4(self.dest_addr) => {
+                            Some(0)
+                        }
+                        IcmpIdentifierMatchMode::Tolerant => None,
+                    };
+                    // A reply which does not echo the nonce for its sequence number was not
+                    // elicited by a probe sent by this tracer.
+                    let nonce_verified = match self.icmp_nonce_key {
+                        Some(key) => {
+                            let payload_size = self.echo_payload_size()?;
+                            common::verify_icmp_nonce(
+                                packet.payload(),
+                                payload_size,
+                                key,
+                                Sequence(seq),
+                            )
+                        }
+                        None => true,
+                    };
+                    id.filter(|_| nonce_verified).map(|id| {
+                        let proto_resp =
+                            ProtocolResponse::Icmp(IcmpProtocolResponse::new(id, seq, None));
+                        Response::EchoReply(
+                            ResponseData::new(recv, src, proto_resp),
+                            IcmpPacketCode(icmp_code.0),
+                        )
+                    })
                 }
                 Protocol::Udp | Protocol::Tcp => None,
             },
             _ => None,
+        };
+        // The type of service of the response itself, as distinct from that of the quoted datagram.
+        let response_tos = Some(TypeOfService(ipv4.get_tos()));
+        let ttl = Some(ipv4.get_ttl());
+        Ok(resp.map(|resp| resp.with_response_tos(response_tos).with_ttl(ttl)))
+    }
+
+    /// Extract a TCP response from a segment sent by the target.
+    ///
+    /// A `RST` is reported as `TcpRefused` and any other segment as `TcpReply`.
+    #[instrument(skip(self), level = "trace")]
+    fn extract_tcp_resp(&self, ipv4: &Ipv4Packet<'_>) -> Result<Option<Response>> {
+        let recv = SystemTime::now();
+        if ipv4.get_protocol() != IpProtocol::Tcp || ipv4.get_source() != self.dest_addr {
+            return Ok(None);
+        }
+        let tcp = TcpPacket::new_view(ipv4.payload())?;
+        // The segment was sent from the target to us and so the ports are reversed.
+        let proto_resp = ProtocolResponse::Tcp(TcpProtocolResponse::new(
+            IpAddr::V4(self.dest_addr),
+            tcp.get_destination(),
+            tcp.get_source(),
+            None,
+        ));
+        let data = ResponseData::new(recv, IpAddr::V4(self.dest_addr), proto_resp);
+        Ok(Some(if tcp.get_flags() & flags::RST == 0 {
+            Response::TcpReply(data)
+        } else {
+            Response::TcpRefused(data)
+        }))
+    }
+
+    /// The bounded payload of the original datagram quoted by an ICMP error, if enabled.
+    fn quoted_payload(&self, ipv4: &Ipv4Packet<'_>) -> Option<Vec<u8>> {
+        self.icmp_quoted_payload_mode.is_enabled().then(|| {
+            let header_len = usize::from(ipv4.get_header_length()) * 4;
+            let declared_len = usize::from(ipv4.get_total_length()).saturating_sub(header_len);
+            common::quoted_payload(ipv4.payload(), declared_len)
         })
     }
 
@@ -361,11 +639,14 @@ impl Ipv4 {
                 let echo_request = extract_echo_request(ipv4)?;
                 let identifier = echo_request.get_identifier();
                 let sequence = echo_request.get_sequence();
-                Some(ProtocolResponse::Icmp(IcmpProtocolResponse::new(
-                    identifier,
-                    sequence,
-                    Some(TypeOfService(ipv4.get_tos())),
-                )))
+                Some(ProtocolResponse::Icmp(
+                    IcmpProtocolResponse::new(
+                        self.quoted_identifier(sequence, identifier),
+                        sequence,
+                        Some(TypeOfService(ipv4.get_tos())),
+                    )
+                    .with_ip_identification(Some(ipv4.get_identification())),
+                ))
             }
             (Protocol::Udp, IpProtocol::Udp) => {
                 let (src_port, dest_port, actual_checksum, identifier, payload_length) =
@@ -393,10 +674,31 @@ impl Ipv4 {
                     Some(TypeOfService(ipv4.get_tos())),
                 )))
             }
-            _ => None,
+            (expected, actual) => {
+                self.protocol_mismatch(expected, actual);
+                None
+            }
         })
     }
 
+    /// Record a response of `bytes_read` bytes discarded as it may have been truncated.
+    ///
+    /// Only the first such response is logged as a warning as all further responses are likely to
+    /// be truncated for the same reason.
+    fn truncated_response(&self, bytes_read: usize) {
+        if self.truncated_responses.fetch_add(1, Ordering::Relaxed) == 0 {
+            tracing::warn!(bytes_read, "discarding potentially truncated icmp packet");
+        } else {
+            tracing::debug!(bytes_read, "discarding potentially truncated icmp packet");
+        }
+    }
+
+    /// Record a response quoting a packet of the `actual` protocol rather than `expected`.
+    fn protocol_mismatch(&self, expected: Protocol, actual: IpProtocol) {
+        self.protocol_mismatches.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(?expected, ?actual, "quoted packet protocol mismatch");
+    }
+
     /// Create an ICMP `EchoRequest` packet.
     fn make_echo_request_icmp_packet<'a>(
         &self,
@@ -405,13 +707,16 @@ impl Ipv4 {
         sequence: Sequence,
         payload_size: usize,
     ) -> Result<EchoRequestPacket<'a>> {
-        let payload_buf = [self.payload_pattern.0; MAX_ICMP_PAYLOAD_BUF];
+        let mut payload_buf = self.payload_buf.build(self.payload_pattern, payload_size);
+        if let Some(key) = self.icmp_nonce_key {
+            common::write_icmp_nonce(&mut payload_buf, key, sequence);
+        }
         let packet_size = IcmpPacket::minimum_packet_size() + payload_size;
         let mut icmp = EchoRequestPacket::new(&mut icmp_buf[..packet_size])?;
         icmp.set_icmp_type(IcmpType::EchoRequest);
-        icmp.set_icmp_code(IcmpCode(0));
+        icmp.set_icmp_code(IcmpCode(self.icmp_echo_code));
         icmp.set_identifier(identifier.0);
-        icmp.set_payload(&payload_buf[..payload_size]);
+        icmp.set_payload(&payload_buf);
         icmp.set_sequence(sequence.0);
         icmp.set_checksum(icmp_ipv4_checksum(icmp.packet()));
         Ok(icmp)
@@ -439,6 +744,33 @@ impl Ipv4 {
         Ok(udp)
     }
 
+    /// Create a `TcpPacket` with the configured TCP flags.
+    fn make_tcp_packet<'a>(
+        &self,
+        tcp_buf: &'a mut [u8],
+        src_port: u16,
+        dest_port: u16,
+        sequence: Sequence,
+    ) -> Result<TcpPacket<'a>> {
+        let tcp_flags = tcp_flags_value(self.tcp_flags);
+        let mut tcp = TcpPacket::new(tcp_buf)?;
+        tcp.set_source(src_port);
+        tcp.set_destination(dest_port);
+        tcp.set_sequence(u32::from(sequence.0));
+        if tcp_flags & flags::ACK != 0 {
+            tcp.set_acknowledgement(u32::from(sequence.0));
+        }
+        tcp.set_data_offset(5);
+        tcp.set_flags(tcp_flags);
+        tcp.set_window_size(TCP_WINDOW_SIZE);
+        tcp.set_checksum(tcp_ipv4_checksum(
+            tcp.packet(),
+            self.src_addr,
+            self.dest_addr,
+        ));
+        Ok(tcp)
+    }
+
     /// Create an `Ipv4Packet`.
     fn make_ipv4_packet<'a>(
         &self,
@@ -450,7 +782,13 @@ impl Ipv4 {
     ) -> Result<Ipv4Packet<'a>> {
         let ipv4_total_length = (Ipv4Packet::minimum_packet_size() + payload.len()) as u16;
         let ipv4_total_length_header = self.byte_order.adjust_length(ipv4_total_length);
-        let ipv4_flags_and_fragment_offset_header = self.byte_order.adjust_length(DONT_FRAGMENT);
+        let flags_and_fragment_offset = if self.dont_fragment_mode.is_enabled() {
+            DONT_FRAGMENT
+        } else {
+            0
+        };
+        let ipv4_flags_and_fragment_offset_header =
+            self.byte_order.adjust_length(flags_and_fragment_offset);
         let mut ipv4 = Ipv4Packet::new(&mut ipv4_buf[..ipv4_total_length as usize])?;
         ipv4.set_version(4);
         ipv4.set_header_length(5);
@@ -466,6 +804,15 @@ impl Ipv4 {
         Ok(ipv4)
     }
 
+    /// The path MTU discovery mode for non-raw sockets.
+    const fn mtu_discover(&self) -> MtuDiscover {
+        if self.dont_fragment_mode.is_enabled() {
+            MTU_DISCOVER
+        } else {
+            MtuDiscover::Dont
+        }
+    }
+
     /// Calculate the expected checksum for a UDP packet.
     ///
     /// Note that this calculation takes place for incoming UDP packet before
@@ -479,8 +826,8 @@ impl Ipv4 {
     ) -> Result<u16> {
         let mut udp_buf = [0_u8; MAX_UDP_PACKET_BUF];
         let size = usize::from(payload_size).min(MAX_UDP_PAYLOAD_BUF);
-        let payload = &[self.payload_pattern.0; MAX_UDP_PAYLOAD_BUF][0..size];
-        let udp = self.make_udp_packet(&mut udp_buf, src_port.0, dest_port.0, payload)?;
+        let payload = self.payload_buf.build(self.payload_pattern, size);
+        let udp = self.make_udp_packet(&mut udp_buf, src_port.0, dest_port.0, &payload)?;
         Ok(udp.get_checksum())
     }
 }
@@ -494,12 +841,38 @@ const fn icmp_payload_size(packet_size: usize) -> usize {
     packet_size - icmp_header_size - ip_header_size
 }
 
+/// The value of the TCP flags field for the given `TcpFlags`.
+const fn tcp_flags_value(tcp_flags: TcpFlags) -> u16 {
+    match tcp_flags {
+        TcpFlags::Syn => flags::SYN,
+        TcpFlags::Ack => flags::ACK,
+        TcpFlags::Fin => flags::FIN,
+        TcpFlags::Null => 0,
+    }
+}
+
 const fn udp_payload_size(packet_size: usize) -> usize {
     let ip_header_size = Ipv4Packet::minimum_packet_size();
     let udp_header_size = UdpPacket::minimum_packet_size();
     packet_size - udp_header_size - ip_header_size
 }
 
+/// The payload size of a probe of the given packet size and protocol.
+///
+/// The packet size is not used for tcp probes and so the payload is always empty.
+pub(super) fn payload_size(packet_size: usize, protocol: Protocol) -> Result<usize> {
+    match protocol {
+        Protocol::Icmp if (MIN_PACKET_SIZE_ICMP..=MAX_PACKET_SIZE).contains(&packet_size) => {
+            Ok(icmp_payload_size(packet_size))
+        }
+        Protocol::Udp if (MIN_PACKET_SIZE_UDP..=MAX_PACKET_SIZE).contains(&packet_size) => {
+            Ok(udp_payload_size(packet_size))
+        }
+        Protocol::Tcp => Ok(0),
+        Protocol::Icmp | Protocol::Udp => Err(Error::InvalidPacketSize(packet_size)),
+    }
+}
+
 #[instrument(level = "trace")]
 fn extract_echo_request<'a>(ipv4: &'a Ipv4Packet<'a>) -> Result<EchoRequestPacket<'a>> {
     Ok(EchoRequestPacket::new_view(ipv4.payload())?)
@@ -548,11 +921,16 @@ mod tests {
     use super::*;
     use crate::error::IoResult;
     use crate::mocket_read;
+    use crate::net::identifier::IdentifierComparison;
+    use crate::net::pcap::testing::SharedSink;
     use crate::net::socket::MockSocket;
-    use crate::{Flags, Port, RoundId, TimeToLive};
+    use crate::{
+        Dscp, Extension, Flags, MplsLabelStack, MplsLabelStackMember, Port, RoundId, TimeToLive,
+    };
     use mockall::predicate;
     use std::str::FromStr;
     use std::sync::Mutex;
+    use test_case::test_case;
 
     static MTX: Mutex<()> = Mutex::new(());
 
@@ -563,11 +941,11 @@ mod tests {
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
         let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 1c 00 00 40 00 0a 01 00 00 01 02 03 04
+            45 00 00 1c 87 6c 40 00 0a 01 00 00 01 02 03 04
             05 06 07 08 08 00 70 93 04 d2 82 9a
             "
         );
@@ -601,11 +979,11 @@ mod tests {
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
         let packet_size = PacketSize(48);
-        let payload_pattern = PayloadPattern(0xff);
+        let payload_pattern = PayloadPattern::Fixed(0xff);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 30 00 00 40 00 0a 01 00 00 01 02 03 04
+            45 00 00 30 87 6c 40 00 0a 01 00 00 01 02 03 04
             05 06 07 08 08 00 70 93 04 d2 82 9a ff ff ff ff
             ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff
             "
@@ -635,61 +1013,13 @@ mod tests {
     }
 
     #[test]
-    fn test_dispatch_icmp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
-        let probe = make_icmp_probe();
-        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
-        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let packet_size = PacketSize(27);
-        let payload_pattern = PayloadPattern(0x00);
-        let byte_order = platform::Ipv4ByteOrder::Network;
-        let mut mocket = MockSocket::new();
-        let ipv4 = Ipv4 {
-            src_addr,
-            dest_addr,
-            byte_order,
-            packet_size,
-            payload_pattern,
-            ..Default::default()
-        };
-        let err = ipv4.dispatch_icmp_probe(&mut mocket, &probe).unwrap_err();
-        assert!(matches!(err, Error::InvalidPacketSize(_)));
-        Ok(())
-    }
-
-    #[test]
-    fn test_dispatch_icmp_probe_invalid_packet_size_high() -> anyhow::Result<()> {
-        let probe = make_icmp_probe();
-        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
-        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let packet_size = PacketSize(1025);
-        let payload_pattern = PayloadPattern(0x00);
-        let byte_order = platform::Ipv4ByteOrder::Network;
-        let mut mocket = MockSocket::new();
-        let ipv4 = Ipv4 {
-            src_addr,
-            dest_addr,
-            byte_order,
-            packet_size,
-            payload_pattern,
-            ..Default::default()
-        };
-        let err = ipv4.dispatch_icmp_probe(&mut mocket, &probe).unwrap_err();
-        assert!(matches!(err, Error::InvalidPacketSize(_)));
-        Ok(())
-    }
-
-    #[test]
-    fn test_dispatch_icmp_probe_with_tos() -> anyhow::Result<()> {
+    fn test_dispatch_icmp_probe_no_dont_fragment() -> anyhow::Result<()> {
         let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0x00);
-        let tos = TypeOfService(0xE0);
-        let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 e0 00 1c 00 00 40 00 0a 01 00 00 01 02 03 04
+            45 00 00 1c 87 6c 00 00 0a 01 00 00 01 02 03 04
             05 06 07 08 08 00 70 93 04 d2 82 9a
             "
         );
@@ -708,10 +1038,9 @@ mod tests {
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
-            byte_order,
-            packet_size,
-            payload_pattern,
-            tos,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(28),
+            dont_fragment_mode: DontFragmentMode::Disabled,
             ..Default::default()
         };
         ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
@@ -719,21 +1048,20 @@ mod tests {
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_privileged_no_payload() -> anyhow::Result<()> {
-        let probe = make_udp_probe(123, 456);
+    fn test_dispatch_icmp_probe_with_payload_len() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
-        let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0xff);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 1c 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            45 00 00 30 87 6c 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a ff ff ff ff
+            ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff
             "
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
         let mut mocket = MockSocket::new();
         mocket
@@ -745,36 +1073,35 @@ mod tests {
             .times(1)
             .returning(|_, _| Ok(()));
 
+        // The payload length takes precedence over the packet size.
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
             byte_order,
-            packet_size,
+            packet_size: PacketSize(84),
+            icmp_payload_len: Some(20),
             payload_pattern,
-            privilege_mode,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_privileged_with_payload() -> anyhow::Result<()> {
-        let probe = make_udp_probe(123, 456);
+    fn test_dispatch_icmp_probe_with_echo_code() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
-        let packet_size = PacketSize(38);
-        let payload_pattern = PayloadPattern(0xaa);
+        let payload_pattern = PayloadPattern::Fixed(0xff);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 26 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 12 98 1e aa aa aa aa
-            aa aa aa aa aa aa
+            45 00 00 30 87 6c 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 07 70 8c 04 d2 82 9a ff ff ff ff
+            ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff
             "
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
         let mut mocket = MockSocket::new();
         mocket
@@ -790,248 +1117,244 @@ mod tests {
             src_addr,
             dest_addr,
             byte_order,
-            packet_size,
+            icmp_payload_len: Some(20),
+            icmp_echo_code: 7,
             payload_pattern,
-            privilege_mode,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
+        let echo = EchoRequestPacket::new_view(&expected_send_to_buf[20..])?;
+        assert_eq!(IcmpCode(7), echo.get_icmp_code());
+        assert_eq!(icmp_ipv4_checksum(echo.packet()), echo.get_checksum());
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_paris_privileged() -> anyhow::Result<()> {
-        let probe = Probe {
-            flags: Flags::PARIS_CHECKSUM,
-            ..make_udp_probe(123, 456)
-        };
-        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
-        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
-        // packet size and payload pattern are ignored for paris mode as a
-        // fixed two byte payload is used to hold the sequence
-        let packet_size = PacketSize(300);
-        let payload_pattern = PayloadPattern(0xaa);
-        let byte_order = platform::Ipv4ByteOrder::Network;
-        let expected_send_to_buf = hex_literal::hex!(
-            "
-            45 00 00 1e 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 0a 82 9a 6a e9
-            "
+    fn test_icmp_packet_size_from_payload_len() {
+        assert_eq!(
+            Ipv4Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size(),
+            IPV4_ICMP_HEADER_SIZE
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        assert_eq!(1000, icmp_payload_size(1000 + IPV4_ICMP_HEADER_SIZE));
+    }
 
+    #[test]
+    fn test_dispatch_icmp_probe_payload_len_too_large() {
+        let probe = make_icmp_probe();
         let mut mocket = MockSocket::new();
-        mocket
-            .expect_send_to()
-            .with(
-                predicate::eq(expected_send_to_buf),
-                predicate::eq(expected_send_to_addr),
-            )
-            .times(1)
-            .returning(|_, _| Ok(()));
-
+        mocket.expect_send_to().times(0);
         let ipv4 = Ipv4 {
-            src_addr,
-            dest_addr,
-            byte_order,
-            packet_size,
-            payload_pattern,
-            privilege_mode,
+            icmp_payload_len: Some(1000),
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, &probe).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(1028)));
+    }
+
+    #[test]
+    fn test_dispatch_icmp_probe_ip_identification_per_probe() -> anyhow::Result<()> {
+        let probe1 = make_icmp_probe();
+        let probe2 = Probe {
+            sequence: Sequence(33435),
+            ..make_icmp_probe()
+        };
+        let sent_ids = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut mocket = MockSocket::new();
+        mocket.expect_send_to().times(2).returning({
+            let sent_ids = Arc::clone(&sent_ids);
+            move |buf, _| {
+                sent_ids
+                    .lock()
+                    .unwrap()
+                    .push(u16::from_be_bytes([buf[4], buf[5]]));
+                Ok(())
+            }
+        });
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(28),
+            ..Default::default()
+        };
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe1)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe2)?;
+        assert_eq!(vec![34668, 34669], *sent_ids.lock().unwrap());
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_dublin_privileged() -> anyhow::Result<()> {
-        let probe = Probe {
-            // note: this is always set for UDP/Dublin but is a no-op for IPv4
-            flags: Flags::DUBLIN_IPV6_PAYLOAD_LENGTH,
-            identifier: TraceId(33434),
-            ..make_udp_probe(123, 456)
+    fn test_dispatch_icmp_probe_write_pcap() -> anyhow::Result<()> {
+        let probe1 = make_icmp_probe();
+        let probe2 = Probe {
+            ttl: TimeToLive(11),
+            ..make_icmp_probe()
         };
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
-        let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0xaa);
-        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_packet1 = hex_literal::hex!(
+            "
+            45 00 00 1c 87 6c 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a
+            "
+        );
+        let expected_packet2 = hex_literal::hex!(
+            "
+            45 00 00 1c 87 6c 40 00 0b 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a
+            "
+        );
+
+        let mut mocket = MockSocket::new();
+        mocket.expect_send_to().times(2).returning(|_, _| Ok(()));
+
+        let sink = SharedSink::default();
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(28),
+            pcap: Some(RefCell::new(PcapWriter::new(Box::new(sink.clone()))?)),
+            ..Default::default()
+        };
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe1)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe2)?;
+        let records = sink.records();
+        assert_eq!(2, records.len());
+        assert_eq!((28, 28, expected_packet1.to_vec()), records[0]);
+        assert_eq!((28, 28, expected_packet2.to_vec()), records[1]);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(all(unix, not(target_os = "linux"), not(target_os = "windows")))]
+    fn test_dispatch_icmp_probe_write_pcap_host_byte_order() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 00 00 1c 82 9a 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            45 00 1c 00 04 d2 00 40 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a
+            "
+        );
+        let expected_packet = hex_literal::hex!(
+            "
+            45 00 00 1c 87 6c 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a
             "
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
 
         let mut mocket = MockSocket::new();
         mocket
             .expect_send_to()
-            .with(
-                predicate::eq(expected_send_to_buf),
-                predicate::eq(expected_send_to_addr),
-            )
+            .with(predicate::eq(expected_send_to_buf), predicate::always())
             .times(1)
             .returning(|_, _| Ok(()));
 
+        let sink = SharedSink::default();
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
-            byte_order,
-            packet_size,
-            payload_pattern,
-            privilege_mode,
+            byte_order: platform::Ipv4ByteOrder::Host,
+            packet_size: PacketSize(28),
+            pcap: Some(RefCell::new(PcapWriter::new(Box::new(sink.clone()))?)),
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
+        assert_eq!(vec![(28, 28, expected_packet.to_vec())], sink.records());
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_unprivileged_no_payload() -> anyhow::Result<()> {
-        let _m = MTX.lock();
-        let probe = make_udp_probe(123, 456);
-        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+    fn test_dispatch_icmp_probe_with_fwmark() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Unprivileged;
-        let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0x00);
-        let byte_order = platform::Ipv4ByteOrder::Network;
-        let expected_send_to_buf = hex_literal::hex!("");
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
-        let expected_bind_addr = SocketAddr::new(IpAddr::V4(src_addr), 123);
-        let expected_set_ttl = 10;
-        let expected_set_tos = 0;
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
         let mut mocket = MockSocket::new();
+        mocket
+            .expect_set_mark()
+            .with(predicate::eq(42))
+            .times(2)
+            .returning(|_| Ok(()));
+        mocket
+            .expect_send_to()
+            .with(predicate::always(), predicate::eq(expected_send_to_addr))
+            .times(2)
+            .returning(|_, _| Ok(()));
 
-        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
-        ctx.expect().with(predicate::eq(false)).returning(move |_| {
-            let mut mocket = MockSocket::new();
-            mocket
-                .expect_bind()
-                .with(predicate::eq(expected_bind_addr))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_set_ttl()
-                .with(predicate::eq(expected_set_ttl))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_set_tos()
-                .with(predicate::eq(expected_set_tos))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_send_to()
-                .with(
-                    predicate::eq(expected_send_to_buf),
-                    predicate::eq(expected_send_to_addr),
-                )
-                .times(1)
-                .returning(|_, _| Ok(()));
+        let ipv4 = Ipv4 {
+            dest_addr,
+            packet_size: PacketSize(28),
+            fwmark: Some(42),
+            ..Default::default()
+        };
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
 
-            Ok(mocket)
-        });
+    #[test]
+    fn test_dispatch_icmp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let packet_size = PacketSize(27);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let mut mocket = MockSocket::new();
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
             byte_order,
             packet_size,
             payload_pattern,
-            privilege_mode,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, &probe).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(_)));
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_unprivileged_with_payload() -> anyhow::Result<()> {
-        let _m = MTX.lock();
-        let probe = make_udp_probe(123, 456);
+    fn test_dispatch_icmp_probe_invalid_packet_size_high() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Unprivileged;
-        let packet_size = PacketSize(36);
-        let payload_pattern = PayloadPattern(0x1f);
+        let packet_size = PacketSize(1025);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
-        let expected_send_to_buf = hex_literal::hex!("1f 1f 1f 1f 1f 1f 1f 1f");
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
-        let expected_bind_addr = SocketAddr::new(IpAddr::V4(src_addr), 123);
-        let expected_set_ttl = 10;
-        let expected_set_tos = 0;
-
         let mut mocket = MockSocket::new();
-
-        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
-        ctx.expect().with(predicate::eq(false)).returning(move |_| {
-            let mut mocket = MockSocket::new();
-            mocket
-                .expect_bind()
-                .with(predicate::eq(expected_bind_addr))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_set_ttl()
-                .with(predicate::eq(expected_set_ttl))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_set_tos()
-                .with(predicate::eq(expected_set_tos))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_send_to()
-                .with(
-                    predicate::eq(expected_send_to_buf),
-                    predicate::eq(expected_send_to_addr),
-                )
-                .times(1)
-                .returning(|_, _| Ok(()));
-
-            Ok(mocket)
-        });
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
             byte_order,
             packet_size,
             payload_pattern,
-            privilege_mode,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        let err = ipv4.dispatch_icmp_probe(&mut mocket, &probe).unwrap_err();
+        assert!(matches!(err, Error::InvalidPacketSize(_)));
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_privileged_with_tos() -> anyhow::Result<()> {
-        let probe = make_udp_probe(123, 456);
+    fn test_dispatch_icmp_probe_with_tos() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0x00);
-        let byte_order = platform::Ipv4ByteOrder::Network;
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let tos = TypeOfService(0xE0);
+        let byte_order = platform::Ipv4ByteOrder::Network;
         let expected_send_to_buf = hex_literal::hex!(
             "
-            45 e0 00 1c 04 d2 40 00 0a 11 00 00 01 02 03 04
-            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            45 e0 00 1c 87 6c 40 00 0a 01 00 00 01 02 03 04
+            05 06 07 08 08 00 70 93 04 d2 82 9a
             "
         );
-        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 0);
 
         let mut mocket = MockSocket::new();
         mocket
@@ -1049,65 +1372,40 @@ mod tests {
             byte_order,
             packet_size,
             payload_pattern,
-            privilege_mode,
             tos,
             ..Default::default()
         };
-        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        ipv4.dispatch_icmp_probe(&mut mocket, &probe)?;
         Ok(())
     }
 
     #[test]
-    fn test_dispatch_udp_probe_classic_unprivileged_with_tos() -> anyhow::Result<()> {
-        let _m = MTX.lock();
+    fn test_dispatch_udp_probe_classic_privileged_no_payload() -> anyhow::Result<()> {
         let probe = make_udp_probe(123, 456);
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
-        let privilege_mode = PrivilegeMode::Unprivileged;
+        let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(28);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
-        let tos = TypeOfService(224);
-        let expected_send_to_buf = hex_literal::hex!("");
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 1c 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            "
+        );
         let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
-        let expected_bind_addr = SocketAddr::new(IpAddr::V4(src_addr), 123);
-        let expected_set_ttl = 10;
-        let expected_set_tos = u32::from(tos.0);
 
         let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
 
-        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
-        ctx.expect().with(predicate::eq(false)).returning(move |_| {
-            let mut mocket = MockSocket::new();
-            mocket
-                .expect_bind()
-                .with(predicate::eq(expected_bind_addr))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_set_ttl()
-                .with(predicate::eq(expected_set_ttl))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_set_tos()
-                .with(predicate::eq(expected_set_tos))
-                .times(1)
-                .returning(|_| Ok(()));
-
-            mocket
-                .expect_send_to()
-                .with(
-                    predicate::eq(expected_send_to_buf),
-                    predicate::eq(expected_send_to_addr),
-                )
-                .times(1)
-                .returning(|_, _| Ok(()));
-
-            Ok(mocket)
-        });
         let ipv4 = Ipv4 {
             src_addr,
             dest_addr,
@@ -1115,7 +1413,6 @@ mod tests {
             packet_size,
             payload_pattern,
             privilege_mode,
-            tos,
             ..Default::default()
         };
         ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
@@ -1123,13 +1420,583 @@ mod tests {
     }
 
     #[test]
-    fn test_dispatch_udp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
+    fn test_dispatch_udp_probe_classic_privileged_no_dont_fragment() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 1c 04 d2 00 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(28),
+            privilege_mode: PrivilegeMode::Privileged,
+            dont_fragment_mode: DontFragmentMode::Disabled,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_unprivileged_no_dont_fragment() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let mut mocket = MockSocket::new();
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(|_| {
+            let mut mocket = MockSocket::new();
+            mocket.expect_bind().returning(|_| Ok(()));
+            mocket.expect_set_ttl().returning(|_| Ok(()));
+            mocket.expect_set_tos().returning(|_| Ok(()));
+            mocket
+                .expect_set_mtu_discover()
+                .with(predicate::eq(MtuDiscover::Dont))
+                .times(1)
+                .returning(|_| Ok(()));
+            mocket.expect_send_to().times(1).returning(|_, _| Ok(()));
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(28),
+            privilege_mode: PrivilegeMode::Unprivileged,
+            dont_fragment_mode: DontFragmentMode::Disabled,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_privileged_with_payload() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        let packet_size = PacketSize(38);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 26 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 12 98 1e aa aa aa aa
+            aa aa aa aa aa aa
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_payload_buf_sized_to_payload() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let mut mocket = MockSocket::new();
+        mocket.expect_send_to().times(1).returning(|_, _| Ok(()));
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(38),
+            payload_pattern: PayloadPattern::Fixed(0xaa),
+            privilege_mode: PrivilegeMode::Privileged,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        assert!(ipv4.payload_buf.capacity() >= 10);
+        assert!(ipv4.payload_buf.capacity() < MAX_UDP_PAYLOAD_BUF);
+        Ok(())
+    }
+
+    // The expected checksum of a quoted UDP probe with a random payload must match the checksum
+    // of the probe as sent, else every such probe would be reported as NAT.
+    #[test]
+    fn test_dispatch_udp_probe_random_payload_checksum() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let sent_checksum = Arc::new(std::sync::Mutex::new(0_u16));
+        let mut mocket = MockSocket::new();
+        mocket.expect_send_to().times(1).returning({
+            let sent_checksum = Arc::clone(&sent_checksum);
+            move |buf, _| {
+                *sent_checksum.lock().unwrap() = u16::from_be_bytes([buf[26], buf[27]]);
+                Ok(())
+            }
+        });
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            byte_order: platform::Ipv4ByteOrder::Network,
+            packet_size: PacketSize(38),
+            payload_pattern: PayloadPattern::Random,
+            privilege_mode: PrivilegeMode::Privileged,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        let expected_checksum = ipv4.calc_udp_checksum(Port(123), Port(456), 10)?;
+        assert_eq!(expected_checksum, *sent_checksum.lock().unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_paris_privileged() -> anyhow::Result<()> {
+        let probe = Probe {
+            flags: Flags::PARIS_CHECKSUM,
+            ..make_udp_probe(123, 456)
+        };
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        // packet size and payload pattern are ignored for paris mode as a
+        // fixed two byte payload is used to hold the sequence
+        let packet_size = PacketSize(300);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 1e 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 0a 82 9a 6a e9
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_dublin_privileged() -> anyhow::Result<()> {
+        let probe = Probe {
+            // note: this is always set for UDP/Dublin but is a no-op for IPv4
+            flags: Flags::DUBLIN_IPV6_PAYLOAD_LENGTH,
+            identifier: TraceId(33434),
+            ..make_udp_probe(123, 456)
+        };
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        let packet_size = PacketSize(28);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 1c 82 9a 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_unprivileged_no_payload() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Unprivileged;
+        let packet_size = PacketSize(28);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!("");
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_bind_addr = SocketAddr::new(IpAddr::V4(src_addr), 123);
+        let expected_set_ttl = 10;
+        let expected_set_tos = 0;
+
+        let mut mocket = MockSocket::new();
+
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(move |_| {
+            let mut mocket = MockSocket::new();
+            mocket
+                .expect_bind()
+                .with(predicate::eq(expected_bind_addr))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_ttl()
+                .with(predicate::eq(expected_set_ttl))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_tos()
+                .with(predicate::eq(expected_set_tos))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_mtu_discover()
+                .with(predicate::eq(MtuDiscover::Probe))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_send_to()
+                .with(
+                    predicate::eq(expected_send_to_buf),
+                    predicate::eq(expected_send_to_addr),
+                )
+                .times(1)
+                .returning(|_, _| Ok(()));
+
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_unprivileged_effective_privilege_mode() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let mut mocket = MockSocket::new();
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(|_| {
+            let mut mocket = MockSocket::new();
+            mocket.expect_bind().returning(|_| Ok(()));
+            mocket.expect_set_ttl().returning(|_| Ok(()));
+            mocket.expect_set_tos().returning(|_| Ok(()));
+            mocket.expect_set_mtu_discover().returning(|_| Ok(()));
+            mocket.expect_send_to().times(1).returning(|_, _| Ok(()));
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(28),
+            privilege_mode: PrivilegeMode::Unprivileged,
+            ..Default::default()
+        };
+        assert_eq!(None, ipv4.effective_privilege_mode.get());
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        assert_eq!(
+            Some(&PrivilegeMode::Unprivileged),
+            ipv4.effective_privilege_mode.get()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_unprivileged_with_payload() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Unprivileged;
+        let packet_size = PacketSize(36);
+        let payload_pattern = PayloadPattern::Fixed(0x1f);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_buf = hex_literal::hex!("1f 1f 1f 1f 1f 1f 1f 1f");
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_bind_addr = SocketAddr::new(IpAddr::V4(src_addr), 123);
+        let expected_set_ttl = 10;
+        let expected_set_tos = 0;
+
+        let mut mocket = MockSocket::new();
+
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(move |_| {
+            let mut mocket = MockSocket::new();
+            mocket
+                .expect_bind()
+                .with(predicate::eq(expected_bind_addr))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_ttl()
+                .with(predicate::eq(expected_set_ttl))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_tos()
+                .with(predicate::eq(expected_set_tos))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_mtu_discover()
+                .with(predicate::eq(MtuDiscover::Probe))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_send_to()
+                .with(
+                    predicate::eq(expected_send_to_buf),
+                    predicate::eq(expected_send_to_addr),
+                )
+                .times(1)
+                .returning(|_, _| Ok(()));
+
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_privileged_with_tos() -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        let packet_size = PacketSize(28);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let tos = TypeOfService(0xE0);
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 e0 00 1c 04 d2 40 00 0a 11 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 08 ed 87
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            tos,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_unprivileged_with_tos() -> anyhow::Result<()> {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let privilege_mode = PrivilegeMode::Unprivileged;
+        let packet_size = PacketSize(28);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let tos = TypeOfService(224);
+        let expected_send_to_buf = hex_literal::hex!("");
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+        let expected_bind_addr = SocketAddr::new(IpAddr::V4(src_addr), 123);
+        let expected_set_ttl = 10;
+        let expected_set_tos = u32::from(tos.0);
+
+        let mut mocket = MockSocket::new();
+
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(move |_| {
+            let mut mocket = MockSocket::new();
+            mocket
+                .expect_bind()
+                .with(predicate::eq(expected_bind_addr))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_ttl()
+                .with(predicate::eq(expected_set_ttl))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_tos()
+                .with(predicate::eq(expected_set_tos))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_set_mtu_discover()
+                .with(predicate::eq(MtuDiscover::Probe))
+                .times(1)
+                .returning(|_| Ok(()));
+
+            mocket
+                .expect_send_to()
+                .with(
+                    predicate::eq(expected_send_to_buf),
+                    predicate::eq(expected_send_to_addr),
+                )
+                .times(1)
+                .returning(|_, _| Ok(()));
+
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            tos,
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_classic_unprivileged_mtu_discover_before_send() -> anyhow::Result<()>
+    {
+        let _m = MTX.lock();
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+
+        let mut mocket = MockSocket::new();
+
+        let ctx = MockSocket::new_udp_send_socket_ipv4_context();
+        ctx.expect().with(predicate::eq(false)).returning(move |_| {
+            let mut seq = mockall::Sequence::new();
+            let mut mocket = MockSocket::new();
+            mocket.expect_bind().returning(|_| Ok(()));
+            mocket.expect_set_ttl().returning(|_| Ok(()));
+            mocket.expect_set_tos().returning(|_| Ok(()));
+            mocket
+                .expect_set_mtu_discover()
+                .with(predicate::eq(MtuDiscover::Probe))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_| Ok(()));
+            mocket
+                .expect_send_to()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_, _| Ok(()));
+            Ok(mocket)
+        });
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            privilege_mode: PrivilegeMode::Unprivileged,
+            packet_size: PacketSize(28),
+            ..Default::default()
+        };
+        ipv4.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_udp_probe_invalid_packet_size_low() -> anyhow::Result<()> {
         let probe = make_udp_probe(123, 456);
         let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
         let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(27);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let mut mocket = MockSocket::new();
         let ipv4 = Ipv4 {
@@ -1153,7 +2020,7 @@ mod tests {
         let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
         let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(1025);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let byte_order = platform::Ipv4ByteOrder::Network;
         let mut mocket = MockSocket::new();
         let ipv4 = Ipv4 {
@@ -1203,27 +2070,438 @@ mod tests {
                 .times(1)
                 .returning(|_| Ok(()));
 
+            mocket
+                .expect_set_mtu_discover()
+                .with(predicate::eq(MtuDiscover::Probe))
+                .times(1)
+                .returning(|_| Ok(()));
+
             mocket
                 .expect_connect()
                 .with(predicate::eq(expected_connect_addr))
                 .times(1)
                 .returning(|_| Ok(()));
 
-            Ok(mocket)
-        });
+            Ok(mocket)
+        });
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            tos,
+            ..Default::default()
+        };
+        ipv4.dispatch_tcp_probe::<MockSocket>(&probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_tcp_probe_raw_syn() -> anyhow::Result<()> {
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 28 04 d2 40 00 0a 06 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 00 82 9a 00 00 00 00
+            50 02 04 00 16 f2 00 00
+            "
+        );
+        dispatch_tcp_probe_raw(TcpFlags::Syn, flags::SYN, expected_send_to_buf)
+    }
+
+    #[test]
+    fn test_dispatch_tcp_probe_raw_ack() -> anyhow::Result<()> {
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 28 04 d2 40 00 0a 06 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 00 82 9a 00 00 82 9a
+            50 10 04 00 94 49 00 00
+            "
+        );
+        dispatch_tcp_probe_raw(TcpFlags::Ack, flags::ACK, expected_send_to_buf)
+    }
+
+    #[test]
+    fn test_dispatch_tcp_probe_raw_fin() -> anyhow::Result<()> {
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 28 04 d2 40 00 0a 06 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 00 82 9a 00 00 00 00
+            50 01 04 00 16 f3 00 00
+            "
+        );
+        dispatch_tcp_probe_raw(TcpFlags::Fin, flags::FIN, expected_send_to_buf)
+    }
+
+    #[test]
+    fn test_dispatch_tcp_probe_raw_null() -> anyhow::Result<()> {
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            45 00 00 28 04 d2 40 00 0a 06 00 00 01 02 03 04
+            05 06 07 08 00 7b 01 c8 00 00 82 9a 00 00 00 00
+            50 00 04 00 16 f4 00 00
+            "
+        );
+        dispatch_tcp_probe_raw(TcpFlags::Null, 0, expected_send_to_buf)
+    }
+
+    fn dispatch_tcp_probe_raw(
+        tcp_flags: TcpFlags,
+        expected_flags: u16,
+        expected_send_to_buf: [u8; 40],
+    ) -> anyhow::Result<()> {
+        let probe = make_udp_probe(123, 456);
+        let src_addr = Ipv4Addr::from_str("1.2.3.4")?;
+        let dest_addr = Ipv4Addr::from_str("5.6.7.8")?;
+        let byte_order = platform::Ipv4ByteOrder::Network;
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V4(dest_addr), 456);
+
+        // the crafted segment must carry exactly the requested flags and a valid checksum.
+        let tcp = TcpPacket::new_view(&expected_send_to_buf[20..])?;
+        assert_eq!(expected_flags, tcp.get_flags());
+        assert_eq!(
+            tcp_ipv4_checksum(tcp.packet(), src_addr, dest_addr),
+            tcp.get_checksum()
+        );
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let ipv4 = Ipv4 {
+            src_addr,
+            dest_addr,
+            byte_order,
+            protocol: Protocol::Tcp,
+            tcp_flags,
+            ..Default::default()
+        };
+        ipv4.dispatch_tcp_probe_raw(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_tcp_segment_rst() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 00 00 28 00 00 40 00 40 06 00 00 05 06 07 08
+            01 02 03 04 01 c8 00 7b 00 00 00 00 00 00 82 9a
+            50 14 00 00 00 00 00 00
+            "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            protocol: Protocol::Tcp,
+            tcp_flags: TcpFlags::Ack,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_tcp_segment(&mut mocket)?.unwrap();
+
+        let Response::TcpRefused(ResponseData {
+            addr,
+            proto_resp:
+                ProtocolResponse::Tcp(TcpProtocolResponse {
+                    dest_addr,
+                    src_port,
+                    dest_port,
+                    tos,
+                }),
+            ..
+        }) = resp
+        else {
+            panic!("expected TcpRefused")
+        };
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("5.6.7.8")?), addr);
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("5.6.7.8")?), dest_addr);
+        assert_eq!(123, src_port);
+        assert_eq!(456, dest_port);
+        assert_eq!(None, tos);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_tcp_segment_wrong_source_ignored() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 00 00 28 00 00 40 00 40 06 00 00 05 06 07 08
+            01 02 03 04 01 c8 00 7b 00 00 00 00 00 00 82 9a
+            50 14 00 00 00 00 00 00
+            "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("9.9.9.9")?,
+            protocol: Protocol::Tcp,
+            tcp_flags: TcpFlags::Ack,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_tcp_segment(&mut mocket)?;
+        assert!(resp.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_echo_reply() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+
+        let Response::EchoReply(
+            ResponseData {
+                addr,
+                ttl,
+                proto_resp:
+                    ProtocolResponse::Icmp(IcmpProtocolResponse {
+                        identifier,
+                        sequence,
+                        tos,
+                        ..
+                    }),
+                ..
+            },
+            icmp_code,
+        ) = resp
+        else {
+            panic!("expected EchoReply")
+        };
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("142.251.222.206")?), addr);
+        assert_eq!(30167, identifier);
+        assert_eq!(33049, sequence);
+        assert_eq!(None, tos);
+        assert_eq!(IcmpPacketCode(0), icmp_code);
+        assert_eq!(Some(59), ttl);
+        Ok(())
+    }
+
+    // The outer IPv4 header of the reply has a TOS of 0xb9, which is DSCP 46 (EF) and ECN 1.
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_response_dscp() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 b9 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let response_tos = resp.data().response_tos.unwrap();
+        assert_eq!(TypeOfService(0xb9), response_tos);
+        assert_eq!(Dscp::EF, response_tos.dscp());
+        Ok(())
+    }
+
+    // The reply identifier (30167) differs from that of the probe but the reply is still matched,
+    // with the wildcard identifier, as it originates from the target.
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_tolerant_identifier() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            dest_addr: Ipv4Addr::from_str("142.251.222.206")?,
+            protocol: Protocol::Icmp,
+            icmp_identifier_mode: IcmpIdentifierMatchMode::Tolerant,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+
+        let Response::EchoReply(
+            ResponseData {
+                addr,
+                proto_resp:
+                    ProtocolResponse::Icmp(IcmpProtocolResponse {
+                        identifier,
+                        sequence,
+                        ..
+                    }),
+                ..
+            },
+            _,
+        ) = resp
+        else {
+            panic!("expected EchoReply")
+        };
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("142.251.222.206")?), addr);
+        assert_eq!(0, identifier);
+        assert_eq!(33049, sequence);
+        Ok(())
+    }
+
+    // The probe (identifier 1234, sequence 33434) is sent with identifier 1236 as the sequence is 2
+    // modulo the range.  A reply which echoes 1236 is matched with the trace identifier whereas a
+    // rewritten reply is reported with its raw identifier.
+    #[test_case(1236, 1234, false; "not rewritten")]
+    #[test_case(4321, 4321, true; "rewritten")]
+    fn test_recv_icmp_probe_echo_reply_identifier_probe(
+        reply_identifier: u16,
+        expected_identifier: u16,
+        expected_rewritten: bool,
+    ) -> anyhow::Result<()> {
+        let identifier_probe = Arc::new(IdentifierProbe::new(4));
+        let ipv4 = Ipv4 {
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(28),
+            identifier_probe: Some(Arc::clone(&identifier_probe)),
+            ..Default::default()
+        };
+        let mut mocket = MockSocket::new();
+        mocket.expect_send_to().times(1).returning(|_, _| Ok(()));
+        ipv4.dispatch_icmp_probe(&mut mocket, &make_icmp_probe())?;
+        let mut icmp_buf = [0_u8; MAX_ICMP_PACKET_BUF];
+        let echo = ipv4.make_echo_request_icmp_packet(
+            &mut icmp_buf,
+            TraceId(reply_identifier),
+            Sequence(33434),
+            0,
+        )?;
+        let mut reply = echo.packet().to_vec();
+        reply[0] = IcmpType::EchoReply.id();
+        let mut ipv4_buf = [0_u8; MAX_PACKET_SIZE];
+        let packet = ipv4.make_ipv4_packet(&mut ipv4_buf, IpProtocol::Icmp, 64, 0, &reply)?;
+        let Some(Response::EchoReply(
+            ResponseData {
+                proto_resp: ProtocolResponse::Icmp(IcmpProtocolResponse { identifier, .. }),
+                ..
+            },
+            _,
+        )) = ipv4.extract_probe_resp(&packet)?
+        else {
+            panic!("expected EchoReply")
+        };
+        assert_eq!(expected_identifier, identifier);
+        let comparisons = identifier_probe.comparisons();
+        assert_eq!(
+            vec![IdentifierComparison {
+                sequence: Sequence(33434),
+                sent: 1236,
+                received: reply_identifier,
+            }],
+            comparisons
+        );
+        assert_eq!(expected_rewritten, comparisons[0].is_rewritten());
+        Ok(())
+    }
 
+    #[test_case(false, true; "valid nonce")]
+    #[test_case(true, false; "tampered nonce")]
+    fn test_recv_icmp_probe_echo_reply_nonce(tamper: bool, expected: bool) -> anyhow::Result<()> {
         let ipv4 = Ipv4 {
-            src_addr,
-            dest_addr,
-            tos,
+            src_addr: Ipv4Addr::from_str("1.2.3.4")?,
+            dest_addr: Ipv4Addr::from_str("5.6.7.8")?,
+            packet_size: PacketSize(48),
+            icmp_nonce_key: Some(0x0123_4567_89ab_cdef),
             ..Default::default()
         };
-        ipv4.dispatch_tcp_probe::<MockSocket>(&probe)?;
+        let mut icmp_buf = [0_u8; MAX_ICMP_PACKET_BUF];
+        let echo =
+            ipv4.make_echo_request_icmp_packet(&mut icmp_buf, TraceId(1234), Sequence(33434), 20)?;
+        let mut reply = echo.packet().to_vec();
+        reply[0] = IcmpType::EchoReply.id();
+        if tamper {
+            reply[8] ^= 0xff;
+        }
+        let mut ipv4_buf = [0_u8; MAX_PACKET_SIZE];
+        let packet = ipv4.make_ipv4_packet(&mut ipv4_buf, IpProtocol::Icmp, 64, 0, &reply)?;
+        let resp = ipv4.extract_probe_resp(&packet)?;
+        assert_eq!(expected, matches!(resp, Some(Response::EchoReply(..))));
         Ok(())
     }
 
     #[test]
-    fn test_recv_icmp_probe_echo_reply() -> anyhow::Result<()> {
+    fn test_recv_icmp_probe_echo_reply_tolerant_identifier_wrong_source() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            dest_addr: Ipv4Addr::from_str("1.1.1.1")?,
+            protocol: Protocol::Icmp,
+            icmp_identifier_mode: IcmpIdentifierMatchMode::Tolerant,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        assert!(resp.is_none());
+        Ok(())
+    }
+
+    // The reply has a matching identifier and sequence but originates from 142.251.222.206 and
+    // so is rejected in strict source mode when tracing to 1.1.1.1.
+    #[test_case("142.251.222.206", true; "from target")]
+    #[test_case("1.1.1.1", false; "not from target")]
+    fn test_recv_icmp_probe_echo_reply_strict_source(
+        dest_addr: &str,
+        expected_accepted: bool,
+    ) -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
             "
             45 20 00 54 00 00 00 00 3b 01 50 02 8e fb de ce
@@ -1235,6 +2513,35 @@ mod tests {
            "
         );
         let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            dest_addr: Ipv4Addr::from_str(dest_addr)?,
+            protocol: Protocol::Icmp,
+            icmp_source_mode: IcmpSourceMatchMode::Strict,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?;
+        assert_eq!(expected_accepted, resp.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_icmp_no_extensions() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+             45 20 00 70 07 d7 00 00 3b 01 e9 5d 8e fa 3d 81
+             c0 a8 01 15 0b 00 f4 ff 00 00 00 00 45 60 00 54
+             65 b0 40 00 01 01 e4 11 c0 a8 01 15 8e fb de ce
+             08 00 01 11 75 d7 81 17 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
         mocket
             .expect_read()
             .times(1)
@@ -1246,7 +2553,7 @@ mod tests {
         };
         let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
 
-        let Response::EchoReply(
+        let Response::TimeExceeded(
             ResponseData {
                 addr,
                 proto_resp:
@@ -1254,24 +2561,76 @@ mod tests {
                         identifier,
                         sequence,
                         tos,
+                        ..
                     }),
                 ..
             },
             icmp_code,
+            extensions,
         ) = resp
         else {
-            panic!("expected EchoReply")
+            panic!("expected TimeExceeded")
         };
-        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("142.251.222.206")?), addr);
+        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("142.250.61.129")?), addr);
         assert_eq!(30167, identifier);
-        assert_eq!(33049, sequence);
-        assert_eq!(None, tos);
+        assert_eq!(33047, sequence);
+        assert_eq!(Some(TypeOfService(96)), tos);
         assert_eq!(IcmpPacketCode(0), icmp_code);
+        assert_eq!(None, extensions);
         Ok(())
     }
 
+    // The router rewrote the quoted ICMP identifier (to 5678) but left the IP identification of the
+    // quoted original datagram (1234) intact.
     #[test]
-    fn test_recv_icmp_probe_time_exceeded_icmp_no_extensions() -> anyhow::Result<()> {
+    fn test_recv_icmp_probe_time_exceeded_icmp_identifier_rewritten() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+             45 20 00 70 07 d7 00 00 3b 01 e9 5d 8e fa 3d 81
+             c0 a8 01 15 0b 00 f4 ff 00 00 00 00 45 00 00 54
+             04 d2 40 00 01 01 e4 11 c0 a8 01 15 8e fb de ce
+             08 00 01 11 16 2e 81 17 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+
+        let Response::TimeExceeded(
+            ResponseData {
+                proto_resp:
+                    ProtocolResponse::Icmp(IcmpProtocolResponse {
+                        identifier,
+                        sequence,
+                        ip_identification,
+                        ..
+                    }),
+                ..
+            },
+            _,
+            _,
+        ) = resp
+        else {
+            panic!("expected TimeExceeded")
+        };
+        assert_eq!(5678, identifier);
+        assert_eq!(33047, sequence);
+        assert_eq!(Some(1234), ip_identification);
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_icmp_quoted_payload() -> anyhow::Result<()> {
         let expected_read_buf = hex_literal::hex!(
             "
              45 20 00 70 07 d7 00 00 3b 01 e9 5d 8e fa 3d 81
@@ -1284,40 +2643,207 @@ mod tests {
            "
         );
         let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(expected_read_buf));
+        let mut ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_quoted_payload_mode: IcmpQuotedPayloadMode::Enabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let Response::TimeExceeded(ResponseData { quoted_payload, .. }, _, _) = resp else {
+            panic!("expected TimeExceeded")
+        };
+        assert_eq!(Some(expected_read_buf[48..].to_vec()), quoted_payload);
+        ipv4.icmp_quoted_payload_mode = IcmpQuotedPayloadMode::Disabled;
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+        let Response::TimeExceeded(ResponseData { quoted_payload, .. }, _, _) = resp else {
+            panic!("expected TimeExceeded")
+        };
+        assert_eq!(None, quoted_payload);
+        Ok(())
+    }
+
+    // Test receiving a `TimeExceeded` with an RFC 4884 length which carries an MPLS label stack of
+    // two members.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_icmp_mpls_extensions() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+             45 20 00 ac 07 d7 00 00 3b 01 00 00 8e fa 3d 81
+             c0 a8 01 15 0b 00 00 00 00 20 00 00 45 60 00 54
+             65 b0 40 00 01 01 e4 11 c0 a8 01 15 8e fb de ce
+             08 00 01 11 75 d7 81 17 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+             00 00 00 00 00 00 00 00 00 00 00 00 20 00 96 53
+             00 0c 01 01 06 9f 18 01 00 00 29 ff
+           "
+        );
+        let mut mocket = MockSocket::new();
         mocket
             .expect_read()
             .times(1)
             .returning(mocket_read!(expected_read_buf));
         let ipv4 = Ipv4 {
             protocol: Protocol::Icmp,
-            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            icmp_extension_mode: IcmpExtensionParseMode::Enabled,
             ..Default::default()
         };
         let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
 
         let Response::TimeExceeded(
             ResponseData {
-                addr,
                 proto_resp:
                     ProtocolResponse::Icmp(IcmpProtocolResponse {
                         identifier,
                         sequence,
-                        tos,
+                        ..
                     }),
                 ..
             },
-            icmp_code,
-            extensions,
+            _,
+            Some(extensions),
         ) = resp
         else {
-            panic!("expected TimeExceeded")
+            panic!("expected TimeExceeded with extensions")
         };
-        assert_eq!(IpAddr::V4(Ipv4Addr::from_str("142.250.61.129")?), addr);
         assert_eq!(30167, identifier);
         assert_eq!(33047, sequence);
-        assert_eq!(Some(TypeOfService(96)), tos);
-        assert_eq!(IcmpPacketCode(0), icmp_code);
-        assert_eq!(None, extensions);
+        let [Extension::Mpls(MplsLabelStack { members })] = extensions.extensions.as_slice() else {
+            panic!("expected a single MPLS extension")
+        };
+        assert_eq!(
+            &[
+                MplsLabelStackMember {
+                    label: 27121,
+                    exp: 4,
+                    bos: 0,
+                    ttl: 1,
+                },
+                MplsLabelStackMember {
+                    label: 2,
+                    exp: 4,
+                    bos: 1,
+                    ttl: 255,
+                },
+            ],
+            members.as_slice()
+        );
+        assert!(!members[0].is_bottom_of_stack());
+        assert!(members[1].is_bottom_of_stack());
+        Ok(())
+    }
+
+    // Test receiving a `TimeExceeded` which quotes a whole probe of the maximum packet size and
+    // carries a deep MPLS label stack, and so is larger than the maximum packet size.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_icmp_oversized_extensions() -> anyhow::Result<()> {
+        const LABELS: usize = 32;
+        let quoted_len = MAX_PACKET_SIZE - 4;
+        let object_len = 4 + LABELS * 4;
+        let total_len = 20 + 8 + quoted_len + 4 + object_len;
+        let mut expected_read_buf = Vec::with_capacity(total_len);
+        expected_read_buf.extend_from_slice(&hex_literal::hex!("45 00"));
+        expected_read_buf.extend_from_slice(&(total_len as u16).to_be_bytes());
+        expected_read_buf.extend_from_slice(&hex_literal::hex!(
+            "00 00 00 00 3b 01 00 00 8e fa 3d 81 c0 a8 01 15"
+        ));
+        expected_read_buf.extend_from_slice(&hex_literal::hex!("0b 00 00 00 00"));
+        expected_read_buf.push((quoted_len / 4) as u8);
+        expected_read_buf.extend_from_slice(&hex_literal::hex!("00 00"));
+        let mut quoted = vec![0_u8; quoted_len];
+        quoted[..28].copy_from_slice(&hex_literal::hex!(
+            "
+             45 00 04 00 65 b0 40 00 01 01 00 00 c0 a8 01 15
+             8e fb de ce 08 00 00 00 75 d7 81 17
+            "
+        ));
+        expected_read_buf.extend_from_slice(&quoted);
+        expected_read_buf.extend_from_slice(&hex_literal::hex!("20 00 00 00"));
+        expected_read_buf.extend_from_slice(&(object_len as u16).to_be_bytes());
+        expected_read_buf.extend_from_slice(&hex_literal::hex!("01 01"));
+        for label in 0..LABELS {
+            let bos = u32::from(label == LABELS - 1);
+            let member = ((label as u32) << 12) | (bos << 8) | 1;
+            expected_read_buf.extend_from_slice(&member.to_be_bytes());
+        }
+        assert!(expected_read_buf.len() > MAX_PACKET_SIZE);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(1)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            icmp_extension_mode: IcmpExtensionParseMode::Enabled,
+            ..Default::default()
+        };
+        let resp = ipv4.recv_icmp_probe(&mut mocket)?.unwrap();
+
+        let Response::TimeExceeded(
+            ResponseData {
+                proto_resp:
+                    ProtocolResponse::Icmp(IcmpProtocolResponse {
+                        identifier,
+                        sequence,
+                        ..
+                    }),
+                ..
+            },
+            _,
+            Some(extensions),
+        ) = resp
+        else {
+            panic!("expected TimeExceeded with extensions")
+        };
+        assert_eq!(30167, identifier);
+        assert_eq!(33047, sequence);
+        let expected_members = (0..LABELS)
+            .map(|label| MplsLabelStackMember {
+                label: label as u32,
+                exp: 0,
+                bos: u8::from(label == LABELS - 1),
+                ttl: 1,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![Extension::Mpls(MplsLabelStack {
+                members: expected_members
+            })],
+            extensions.extensions
+        );
+        Ok(())
+    }
+
+    // Test receiving a packet which fills the whole receive buffer, and so may have been
+    // truncated, is discarded rather than parsed.
+    #[test]
+    fn test_recv_icmp_probe_truncated() -> anyhow::Result<()> {
+        let mut expected_read_buf = vec![0_u8; MAX_RECV_PACKET_SIZE];
+        expected_read_buf[..28].copy_from_slice(&hex_literal::hex!(
+            "
+            45 20 06 00 00 00 00 00 3b 01 50 02 8e fb de ce
+            c0 a8 01 15 00 00 09 0f 75 d7 81 19
+            "
+        ));
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Icmp,
+            ..Default::default()
+        };
+        assert!(ipv4.recv_icmp_probe(&mut mocket)?.is_none());
+        assert!(ipv4.recv_icmp_probe(&mut mocket)?.is_none());
+        assert_eq!(2, ipv4.truncated_responses.load(Ordering::Relaxed));
         Ok(())
     }
 
@@ -1351,6 +2877,7 @@ mod tests {
                         identifier,
                         sequence,
                         tos,
+                        ..
                     }),
                 ..
             },
@@ -1743,6 +3270,35 @@ mod tests {
         Ok(())
     }
 
+    // Test receiving a `TimeExceeded` which quotes a TCP packet when tracing with UDP.
+    #[test]
+    fn test_recv_icmp_probe_udp_quoting_tcp_protocol_mismatch() -> anyhow::Result<()> {
+        let expected_read_buf = hex_literal::hex!(
+            "
+            45 20 00 5c a6 9d 00 00 3b 01 54 e5 d1 55 f0 eb
+            c0 a8 01 15 0b 00 12 79 00 00 00 00 45 80 00 40
+            00 00 40 00 01 06 5b f2 c0 a8 01 15 8e fa cc 8e
+            80 fd 00 50 61 f2 4d 4a 00 00 00 00 b0 02 ff ff
+            14 05 00 00 02 04 05 b4 01 03 03 06 01 01 08 0a
+            55 59 7f cd 00 00 00 00 04 02 00 00
+           "
+        );
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_read()
+            .times(2)
+            .returning(mocket_read!(expected_read_buf));
+        let ipv4 = Ipv4 {
+            protocol: Protocol::Udp,
+            ..Default::default()
+        };
+        assert!(ipv4.recv_icmp_probe(&mut mocket)?.is_none());
+        assert_eq!(1, ipv4.protocol_mismatches.load(Ordering::Relaxed));
+        assert!(ipv4.recv_icmp_probe(&mut mocket)?.is_none());
+        assert_eq!(2, ipv4.protocol_mismatches.load(Ordering::Relaxed));
+        Ok(())
+    }
+
     #[test]
     fn test_recv_tcp_socket_tcp_reply() -> anyhow::Result<()> {
         let dest_addr = Ipv4Addr::from_str("1.2.3.4")?;
@@ -1754,6 +3310,11 @@ mod tests {
             .expect_peer_addr()
             .times(1)
             .returning(move || Ok(Some(expected_peer_addr)));
+        mocket.expect_recv_ttl().times(1).returning(|| Ok(Some(57)));
+        mocket
+            .expect_recv_ifindex()
+            .times(1)
+            .returning(|| Ok(Some(3)));
         mocket.expect_shutdown().times(1).returning(|| Ok(()));
 
         let ipv4 = Ipv4 {
@@ -1773,6 +3334,8 @@ mod tests {
                     dest_port,
                     tos,
                 }),
+            ttl,
+            ifindex,
             ..
         }) = resp
         else {
@@ -1782,6 +3345,8 @@ mod tests {
         assert_eq!(33434, src_port);
         assert_eq!(456, dest_port);
         assert_eq!(None, tos);
+        assert_eq!(Some(57), ttl);
+        assert_eq!(Some(3), ifindex);
         Ok(())
     }
 
@@ -1,7 +1,11 @@
-use crate::config::IcmpExtensionParseMode;
+use crate::config::{
+    IcmpExtensionParseMode, IcmpIdentifierMatchMode, IcmpQuotedPayloadMode, IcmpSourceMatchMode,
+};
+use crate::constants::IPV6_ICMP_HEADER_SIZE;
 use crate::error::{Error, ErrorKind, Result};
-use crate::net::channel::MAX_PACKET_SIZE;
-use crate::net::common::ErrorMapper;
+use crate::net::channel::{MAX_PACKET_SIZE, MAX_RECV_PACKET_SIZE};
+use crate::net::common::{self, ErrorMapper, PayloadBuf};
+use crate::net::identifier::IdentifierProbe;
 use crate::net::socket::{Socket, SocketError};
 use crate::probe::{
     Extensions, IcmpPacketCode, IcmpProtocolResponse, Probe, ProtocolResponse, Response,
@@ -11,6 +15,8 @@ use crate::types::{PacketSize, PayloadPattern, Sequence, TraceId};
 use crate::{Flags, Port, PrivilegeMode, Protocol, TypeOfService};
 use std::io;
 use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::SystemTime;
 use tracing::instrument;
 use trippy_packet::IpProtocol;
@@ -27,15 +33,9 @@ use trippy_packet::udp::UdpPacket;
 /// The maximum size of UDP packet we allow.
 const MAX_UDP_PACKET_BUF: usize = MAX_PACKET_SIZE - Ipv6Packet::minimum_packet_size();
 
-/// The maximum size of UDP payload we allow.
-const MAX_UDP_PAYLOAD_BUF: usize = MAX_UDP_PACKET_BUF - UdpPacket::minimum_packet_size();
-
 /// The maximum size of UDP packet we allow.
 const MAX_ICMP_PACKET_BUF: usize = MAX_PACKET_SIZE - Ipv6Packet::minimum_packet_size();
 
-/// The maximum size of ICMP payload we allow.
-const MAX_ICMP_PAYLOAD_BUF: usize = MAX_ICMP_PACKET_BUF - IcmpPacket::minimum_packet_size();
-
 /// The minimum size of ICMP packets we allow.
 const MIN_PACKET_SIZE_ICMP: usize =
     Ipv6Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size();
@@ -58,7 +58,28 @@ pub struct Ipv6 {
     pub tos: TypeOfService,
     pub protocol: Protocol,
     pub icmp_extension_mode: IcmpExtensionParseMode,
+    pub icmp_identifier_mode: IcmpIdentifierMatchMode,
+    pub icmp_source_mode: IcmpSourceMatchMode,
+    pub icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+    pub fwmark: Option<u32>,
+    pub icmp_nonce_key: Option<u64>,
+    /// The exact length of the payload of ICMP probes, overriding `packet_size` if set.
+    pub icmp_payload_len: Option<u16>,
+    /// The ICMP code of ICMP `EchoRequest` probes.
+    pub icmp_echo_code: u8,
+    /// The number of ICMP error responses dropped as the quoted packet was not of `protocol`.
+    pub protocol_mismatches: Arc<AtomicUsize>,
+    /// The number of ICMP responses discarded as they filled the receive buffer and so may have
+    /// been truncated.
+    pub truncated_responses: Arc<AtomicUsize>,
+    /// The privilege mode of the path used to dispatch probes, recorded when the first probe is
+    /// sent.
+    pub effective_privilege_mode: Arc<OnceLock<PrivilegeMode>>,
+    /// The probe for ICMP identifier rewriting, if enabled.
+    pub identifier_probe: Option<Arc<IdentifierProbe>>,
     pub initial_sequence: Sequence,
+    /// The buffer in which the payload of each probe is built.
+    pub payload_buf: PayloadBuf,
 }
 
 impl Default for Ipv6 {
@@ -67,12 +88,24 @@ impl Default for Ipv6 {
             src_addr: Ipv6Addr::UNSPECIFIED,
             dest_addr: Ipv6Addr::UNSPECIFIED,
             packet_size: PacketSize(0),
-            payload_pattern: PayloadPattern(0),
+            payload_pattern: PayloadPattern::Fixed(0),
             privilege_mode: PrivilegeMode::Privileged,
             tos: TypeOfService(0),
             protocol: Protocol::Icmp,
             icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            icmp_identifier_mode: IcmpIdentifierMatchMode::Strict,
+            icmp_source_mode: IcmpSourceMatchMode::Any,
+            icmp_quoted_payload_mode: IcmpQuotedPayloadMode::Disabled,
+            fwmark: None,
+            icmp_nonce_key: None,
+            icmp_payload_len: None,
+            icmp_echo_code: 0,
+            protocol_mismatches: Arc::default(),
+            truncated_responses: Arc::default(),
+            effective_privilege_mode: Arc::default(),
+            identifier_probe: None,
             initial_sequence: Sequence(0),
+            payload_buf: PayloadBuf::default(),
         }
     }
 }
@@ -86,19 +119,18 @@ impl Ipv6 {
         probe: &Probe,
     ) -> Result<()> {
         let mut icmp_buf = [0_u8; MAX_ICMP_PACKET_BUF];
-        let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_ICMP..=MAX_PACKET_SIZE).contains(&packet_size) {
-            return Err(Error::InvalidPacketSize(packet_size));
-        }
+        let payload_size = self.echo_payload_size()?;
         let echo_request = self.make_echo_request_icmp_packet(
             &mut icmp_buf,
-            probe.identifier,
+            self.echo_identifier(probe),
             probe.sequence,
-            icmp_payload_size(packet_size),
+            payload_size,
         )?;
         icmp_send_socket.set_unicast_hops_v6(probe.ttl.0)?;
         icmp_send_socket.set_tclass_v6(u32::from(self.tos.0))?;
+        self.set_mark(icmp_send_socket)?;
         let remote_addr = SocketAddr::new(IpAddr::V6(self.dest_addr), 0);
+        self.record_privilege_mode(self.privilege_mode);
         icmp_send_socket.send_to(echo_request.packet(), remote_addr)?;
         Ok(())
     }
@@ -110,38 +142,52 @@ impl Ipv6 {
         raw_send_socket: &mut S,
         probe: &Probe,
     ) -> Result<()> {
-        let packet_size = usize::from(self.packet_size.0);
-        if !(MIN_PACKET_SIZE_UDP..=MAX_PACKET_SIZE).contains(&packet_size) {
-            return Err(Error::InvalidPacketSize(packet_size));
-        }
-        let payload_size = udp_payload_size(packet_size);
-        let payload = &[self.payload_pattern.0; MAX_UDP_PAYLOAD_BUF][0..payload_size];
+        let payload_size = payload_size(usize::from(self.packet_size.0), Protocol::Udp)?;
+        self.record_privilege_mode(self.privilege_mode);
         match self.privilege_mode {
             PrivilegeMode::Privileged => {
-                self.dispatch_udp_probe_raw(raw_send_socket, probe, payload)
+                self.dispatch_udp_probe_raw(raw_send_socket, probe, payload_size)
+            }
+            PrivilegeMode::Unprivileged => {
+                let payload = self.payload_buf.build(self.payload_pattern, payload_size);
+                self.dispatch_udp_probe_non_raw::<S>(probe, &payload)
             }
-            PrivilegeMode::Unprivileged => self.dispatch_udp_probe_non_raw::<S>(probe, payload),
         }
     }
 
+    /// Dispatch a UDP probe using a raw socket.
+    ///
+    /// For the Paris tracing strategy the UDP checksum is computed, with the IPv6 pseudo-header,
+    /// over a two byte payload holding the sequence number, and then the checksum and payload are
+    /// swapped such that the checksum field holds the sequence and the payload holds the value
+    /// required for the checksum to remain valid.  The flow identifying fields are therefore fixed
+    /// for all probes.
+    ///
+    /// For the Dublin tracing strategy the sequence is instead encoded in the length of the
+    /// payload, following a magic prefix, as IPv6 has no header identifier field to hold it.
     #[instrument(skip(self, udp_send_socket), level = "trace")]
     fn dispatch_udp_probe_raw<S: Socket>(
         &self,
         udp_send_socket: &mut S,
         probe: &Probe,
-        payload: &[u8],
+        payload_size: usize,
     ) -> Result<()> {
         let mut udp_buf = [0_u8; MAX_UDP_PACKET_BUF];
-        let mut dublin_payload = [self.payload_pattern.0; MAX_UDP_PAYLOAD_BUF];
         let payload_paris = probe.sequence.0.to_be_bytes();
+        let payload_buf;
         let payload = if probe.flags.contains(Flags::PARIS_CHECKSUM) {
             payload_paris.as_slice()
         } else if probe.flags.contains(Flags::DUBLIN_IPV6_PAYLOAD_LENGTH) {
-            let payload_len = probe.sequence.0 - self.initial_sequence.0;
+            let payload_len = usize::from(probe.sequence.0 - self.initial_sequence.0);
+            let mut dublin_payload = self
+                .payload_buf
+                .build(self.payload_pattern, payload_len + MAGIC.len());
             dublin_payload[..MAGIC.len()].copy_from_slice(MAGIC);
-            &dublin_payload[..usize::from(payload_len) + MAGIC.len()]
+            payload_buf = dublin_payload;
+            payload_buf.as_slice()
         } else {
-            payload
+            payload_buf = self.payload_buf.build(self.payload_pattern, payload_size);
+            payload_buf.as_slice()
         };
         let mut udp =
             self.make_udp_packet(&mut udp_buf, probe.src_port.0, probe.dest_port.0, payload)?;
@@ -153,6 +199,7 @@ impl Ipv6 {
         }
         udp_send_socket.set_unicast_hops_v6(probe.ttl.0)?;
         udp_send_socket.set_tclass_v6(u32::from(self.tos.0))?;
+        self.set_mark(udp_send_socket)?;
         // Note that we set the port to be 0 in the remote `SocketAddr` as the target port is
         // encoded in the `UDP` packet.  If we (redundantly) set the target port here then
         // the `send_to` will fail with `EINVAL`.
@@ -173,6 +220,7 @@ impl Ipv6 {
             .map_err(|err| ErrorMapper::addr_in_use(err, local_addr))?;
         socket.set_unicast_hops_v6(probe.ttl.0)?;
         socket.set_tclass_v6(u32::from(self.tos.0))?;
+        self.set_mark(&mut socket)?;
         socket.send_to(payload, remote_addr)?;
         Ok(())
     }
@@ -180,6 +228,7 @@ impl Ipv6 {
     /// Dispatch a TCP probe.
     #[instrument(skip(self), level = "trace")]
     pub fn dispatch_tcp_probe<S: Socket>(&self, probe: &Probe) -> Result<S> {
+        self.record_privilege_mode(PrivilegeMode::Unprivileged);
         let mut socket = S::new_stream_socket_ipv6()?;
         let local_addr = SocketAddr::new(IpAddr::V6(self.src_addr), probe.src_port.0);
         socket
@@ -189,6 +238,7 @@ impl Ipv6 {
             .map_err(|err| ErrorMapper::addr_in_use(err, local_addr))?;
         socket.set_unicast_hops_v6(probe.ttl.0)?;
         socket.set_tclass_v6(u32::from(self.tos.0))?;
+        self.set_mark(&mut socket)?;
         let remote_addr = SocketAddr::new(IpAddr::V6(self.dest_addr), probe.dest_port.0);
         socket
             .connect(remote_addr)
@@ -199,17 +249,29 @@ impl Ipv6 {
     }
 
     /// Receive an ICMP probe.
+    ///
+    /// A packet which fills the whole receive buffer may have been truncated and so is discarded
+    /// rather than parsed into misleading data.
     #[instrument(skip(self, recv_socket), level = "trace")]
     pub fn recv_icmp_probe<S: Socket>(&self, recv_socket: &mut S) -> Result<Option<Response>> {
-        let mut buf = [0_u8; MAX_PACKET_SIZE];
+        let mut buf = [0_u8; MAX_RECV_PACKET_SIZE];
+        // The hop limit and traffic class are not part of the ICMPv6 packet and so are read from
+        // the ancillary data received with it.
         match recv_socket.recv_from(&mut buf) {
-            Ok((bytes_read, addr)) => {
+            Ok((bytes_read, _, _)) if bytes_read >= buf.len() => {
+                self.truncated_response(bytes_read);
+                Ok(None)
+            }
+            Ok((bytes_read, addr, info)) => {
                 let icmp_v6 = IcmpPacket::new_view(&buf[..bytes_read])?;
                 let src_addr = match addr.as_ref().ok_or(Error::MissingAddr)? {
                     SocketAddr::V6(addr) => addr.ip(),
                     SocketAddr::V4(_) => panic!(),
                 };
-                Ok(self.extract_probe_resp(&icmp_v6, *src_addr)?)
+                Ok(self.extract_probe_resp(&icmp_v6, *src_addr)?.map(|resp| {
+                    resp.with_response_tos(info.tclass.map(TypeOfService))
+                        .with_ttl(info.ttl)
+                }))
             }
             Err(err) => match err.kind() {
                 ErrorKind::Std(io::ErrorKind::WouldBlock) => Ok(None),
@@ -235,12 +297,14 @@ impl Ipv6 {
         match tcp_socket.take_error()? {
             None => {
                 let addr = tcp_socket.peer_addr()?.ok_or(Error::MissingAddr)?.ip();
+                let ttl = tcp_socket.recv_ttl()?;
+                let ifindex = tcp_socket.recv_ifindex()?;
                 tcp_socket.shutdown()?;
-                return Ok(Some(Response::TcpReply(ResponseData::new(
-                    SystemTime::now(),
-                    addr,
-                    proto_resp,
-                ))));
+                return Ok(Some(Response::TcpReply(
+                    ResponseData::new(SystemTime::now(), addr, proto_resp)
+                        .with_ttl(ttl)
+                        .with_ifindex(ifindex),
+                )));
             }
             Some(err) => match err {
                 SocketError::ConnectionRefused => {
@@ -264,6 +328,70 @@ impl Ipv6 {
         Ok(None)
     }
 
+    /// Record the privilege mode of the path used to dispatch a probe.
+    fn record_privilege_mode(&self, privilege_mode: PrivilegeMode) {
+        _ = self.effective_privilege_mode.set(privilege_mode);
+    }
+
+    /// The size of the payload of an `EchoRequest`.
+    ///
+    /// If `icmp_payload_len` is set then the payload is exactly that length and the packet size is
+    /// computed from it, otherwise the payload size is derived from `packet_size`.
+    fn echo_payload_size(&self) -> Result<usize> {
+        match self.icmp_payload_len.map(usize::from) {
+            Some(payload_len) if payload_len + IPV6_ICMP_HEADER_SIZE <= MAX_PACKET_SIZE => {
+                Ok(payload_len)
+            }
+            Some(payload_len) => Err(Error::InvalidPacketSize(
+                payload_len + IPV6_ICMP_HEADER_SIZE,
+            )),
+            None => payload_size(usize::from(self.packet_size.0), Protocol::Icmp),
+        }
+    }
+
+    /// The identifier of the `EchoRequest` for `probe`.
+    ///
+    /// This is the trace identifier unless ICMP identifier rewriting is being probed.
+    fn echo_identifier(&self, probe: &Probe) -> TraceId {
+        self.identifier_probe
+            .as_ref()
+            .map_or(probe.identifier, |identifier_probe| {
+                identifier_probe.identifier(probe.identifier, probe.sequence)
+            })
+    }
+
+    /// The identifier of an `EchoReply` for `sequence` as matched against the trace.
+    ///
+    /// If ICMP identifier rewriting is being probed then the raw `identifier` of the reply is
+    /// compared with the identifier sent, even if they do not match.
+    fn reply_identifier(&self, sequence: u16, identifier: u16) -> u16 {
+        match &self.identifier_probe {
+            Some(identifier_probe) => {
+                identifier_probe.compare(sequence, identifier);
+                identifier_probe.trace_identifier(sequence, identifier)
+            }
+            None => identifier,
+        }
+    }
+
+    /// The identifier of an `EchoRequest` for `sequence` quoted by an ICMP error as matched
+    /// against the trace.
+    fn quoted_identifier(&self, sequence: u16, identifier: u16) -> u16 {
+        self.identifier_probe
+            .as_ref()
+            .map_or(identifier, |identifier_probe| {
+                identifier_probe.trace_identifier(sequence, identifier)
+            })
+    }
+
+    /// Set the firewall mark on a send socket, if configured.
+    fn set_mark<S: Socket>(&self, socket: &mut S) -> Result<()> {
+        if let Some(mark) = self.fwmark {
+            socket.set_mark(mark)?;
+        }
+        Ok(())
+    }
+
     fn extract_probe_resp(
         &self,
         icmp_v6: &IcmpPacket<'_>,
@@ -288,10 +416,12 @@ impl Ipv6 {
                             (ipv6, None)
                         }
                     };
+                    let quoted_payload = self.quoted_payload(&nested_ipv6);
                     self.extract_probe_proto_resp(&nested_ipv6)?
                         .map(|proto_resp| {
                             Response::TimeExceeded(
-                                ResponseData::new(recv, ip, proto_resp),
+                                ResponseData::new(recv, ip, proto_resp)
+                                    .with_quoted_payload(quoted_payload),
                                 IcmpPacketCode(icmp_code.0),
                                 extension,
                             )
@@ -309,26 +439,61 @@ impl Ipv6 {
                     }
                     IcmpExtensionParseMode::Disabled => None,
                 };
+                let quoted_payload = self.quoted_payload(&nested_ipv6);
                 self.extract_probe_proto_resp(&nested_ipv6)?
                     .map(|proto_resp| {
                         Response::DestinationUnreachable(
-                            ResponseData::new(recv, ip, proto_resp),
+                            ResponseData::new(recv, ip, proto_resp)
+                                .with_quoted_payload(quoted_payload),
                             IcmpPacketCode(icmp_code.0),
                             extension,
                         )
                     })
             }
             IcmpType::EchoReply => match self.protocol {
+                // In strict source mode a reply with a matching identifier and sequence number is
+                // rejected unless it originates from the target.
+                Protocol::Icmp
+                    if self.icmp_source_mode.is_strict() && ip != IpAddr::V6(self.dest_addr) =>
+                {
+                    None
+                }
                 Protocol::Icmp => {
                     let packet = EchoReplyPacket::new_view(icmp_v6.packet())?;
-                    let id = packet.get_identifier();
+                    // In tolerant mode the identifier may have been rewritten (i.e. by the kernel
+                    // for unprivileged sockets or by a NAT) and so we report the wildcard
+                    // identifier and instead require that the reply originates from the target.
                     let seq = packet.get_sequence();
-                    let proto_resp =
-                        ProtocolResponse::Icmp(IcmpProtocolResponse::new(id, seq, None));
-                    Some(Response::EchoReply(
-                        ResponseData::new(recv, ip, proto_resp),
-                        IcmpPacketCode(icmp_code.0),
-                    ))
+                    let identifier = self.reply_identifier(seq, packet.get_identifier());
+                    let id = match self.icmp_identifier_mode {
+                        IcmpIdentifierMatchMode::Strict => Some(identifier),
+                        IcmpIdentifierMatchMode::Tolerant if ip == IpAddr::V6(self.dest_addr) => {
+                            Some(0)
+                        }
+                        IcmpIdentifierMatchMode::Tolerant => None,
+                    };
+                    // A reply which does not echo the nonce for its sequence number was not
+                    // elicited by a probe sent by this tracer.
+                    let nonce_verified = match self.icmp_nonce_key {
+                        Some(key) => {
+                            let payload_size = self.echo_payload_size()?;
+                            common::verify_icmp_nonce(
+                                packet.payload(),
+                                payload_size,
+                                key,
+                                Sequence(seq),
+                            )
+                        }
+                        None => true,
+                    };
+                    id.filter(|_| nonce_verified).map(|id| {
+                        let proto_resp =
+                            ProtocolResponse::Icmp(IcmpProtocolResponse::new(id, seq, None));
+                        Response::EchoReply(
+                            ResponseData::new(recv, ip, proto_resp),
+                            IcmpPacketCode(icmp_code.0),
+                        )
+                    })
                 }
                 Protocol::Udp | Protocol::Tcp => None,
             },
@@ -336,12 +501,20 @@ impl Ipv6 {
         })
     }
 
+    /// The bounded payload of the original datagram quoted by an ICMP error, if enabled.
+    fn quoted_payload(&self, ipv6: &Ipv6Packet<'_>) -> Option<Vec<u8>> {
+        self.icmp_quoted_payload_mode.is_enabled().then(|| {
+            let declared_len = usize::from(ipv6.get_payload_length());
+            common::quoted_payload(ipv6.payload(), declared_len)
+        })
+    }
+
     fn extract_probe_proto_resp(&self, ipv6: &Ipv6Packet<'_>) -> Result<Option<ProtocolResponse>> {
-        Ok(match (self.protocol, ipv6.get_next_header()) {
+        Ok(match (self.protocol, ipv6.get_upper_layer_protocol()) {
             (Protocol::Icmp, IpProtocol::IcmpV6) => {
                 let (identifier, sequence) = extract_echo_request(ipv6)?;
                 Some(ProtocolResponse::Icmp(IcmpProtocolResponse::new(
-                    identifier,
+                    self.quoted_identifier(sequence, identifier),
                     sequence,
                     Some(TypeOfService(ipv6.get_traffic_class())),
                 )))
@@ -376,10 +549,31 @@ impl Ipv6 {
                     Some(TypeOfService(ipv6.get_traffic_class())),
                 )))
             }
-            _ => None,
+            (expected, actual) => {
+                self.protocol_mismatch(expected, actual);
+                None
+            }
         })
     }
 
+    /// Record a response of `bytes_read` bytes discarded as it may have been truncated.
+    ///
+    /// Only the first such response is logged as a warning as all further responses are likely to
+    /// be truncated for the same reason.
+    fn truncated_response(&self, bytes_read: usize) {
+        if self.truncated_responses.fetch_add(1, Ordering::Relaxed) == 0 {
+            tracing::warn!(bytes_read, "discarding potentially truncated icmp packet");
+        } else {
+            tracing::debug!(bytes_read, "discarding potentially truncated icmp packet");
+        }
+    }
+
+    /// Record a response quoting a packet of the `actual` protocol rather than `expected`.
+    fn protocol_mismatch(&self, expected: Protocol, actual: IpProtocol) {
+        self.protocol_mismatches.fetch_add(1, Ordering::Relaxed);
+        tracing::debug!(?expected, ?actual, "quoted packet protocol mismatch");
+    }
+
     /// Create a `UdpPacket`
     fn make_udp_packet<'a>(
         &self,
@@ -410,13 +604,16 @@ impl Ipv6 {
         sequence: Sequence,
         payload_size: usize,
     ) -> Result<EchoRequestPacket<'a>> {
-        let payload_buf = [self.payload_pattern.0; MAX_ICMP_PAYLOAD_BUF];
+        let mut payload_buf = self.payload_buf.build(self.payload_pattern, payload_size);
+        if let Some(key) = self.icmp_nonce_key {
+            common::write_icmp_nonce(&mut payload_buf, key, sequence);
+        }
         let packet_size = IcmpPacket::minimum_packet_size() + payload_size;
         let mut icmp = EchoRequestPacket::new(&mut icmp_buf[..packet_size])?;
         icmp.set_icmp_type(IcmpType::EchoRequest);
-        icmp.set_icmp_code(IcmpCode(0));
+        icmp.set_icmp_code(IcmpCode(self.icmp_echo_code));
         icmp.set_identifier(identifier.0);
-        icmp.set_payload(&payload_buf[..payload_size]);
+        icmp.set_payload(&payload_buf);
         icmp.set_sequence(sequence.0);
         icmp.set_checksum(icmp_ipv6_checksum(
             icmp.packet(),
@@ -439,8 +636,24 @@ const fn udp_payload_size(packet_size: usize) -> usize {
     packet_size - udp_header_size - ip_header_size
 }
 
+/// The payload size of a probe of the given packet size and protocol.
+///
+/// The packet size is not used for tcp probes and so the payload is always empty.
+pub(super) fn payload_size(packet_size: usize, protocol: Protocol) -> Result<usize> {
+    match protocol {
+        Protocol::Icmp if (MIN_PACKET_SIZE_ICMP..=MAX_PACKET_SIZE).contains(&packet_size) => {
+            Ok(icmp_payload_size(packet_size))
+        }
+        Protocol::Udp if (MIN_PACKET_SIZE_UDP..=MAX_PACKET_SIZE).contains(&packet_size) => {
+            Ok(udp_payload_size(packet_size))
+        }
+        Protocol::Tcp => Ok(0),
+        Protocol::Icmp | Protocol::Udp => Err(Error::InvalidPacketSize(packet_size)),
+    }
+}
+
 fn extract_echo_request(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16)> {
-    let echo_request_packet = EchoRequestPacket::new_view(ipv6.payload())?;
+    let echo_request_packet = EchoRequestPacket::new_view(ipv6.upper_layer_payload())?;
     Ok((
         echo_request_packet.get_identifier(),
         echo_request_packet.get_sequence(),
@@ -448,7 +661,7 @@ fn extract_echo_request(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16)> {
 }
 
 fn extract_udp_packet(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16, u16, u16)> {
-    let udp_packet = UdpPacket::new_view(ipv6.payload())?;
+    let udp_packet = UdpPacket::new_view(ipv6.upper_layer_payload())?;
     Ok((
         udp_packet.get_source(),
         udp_packet.get_destination(),
@@ -477,12 +690,12 @@ fn extract_udp_packet(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16, u16, u16)> {
 /// [rfc4443]: https://datatracker.ietf.org/doc/html/rfc4443#section-2.4
 /// [rfc2460]: https://datatracker.ietf.org/doc/html/rfc2460#section-5
 fn extract_tcp_packet(ipv6: &Ipv6Packet<'_>) -> Result<(u16, u16)> {
-    let tcp_packet = TcpPacket::new_view(ipv6.payload())?;
+    let tcp_packet = TcpPacket::new_view(ipv6.upper_layer_payload())?;
     Ok((tcp_packet.get_source(), tcp_packet.get_destination()))
 }
 
 fn udp_payload_has_magic_prefix(ipv6: &Ipv6Packet<'_>) -> Result<bool> {
-    let udp_packet = UdpPacket::new_view(ipv6.payload())?;
+    let udp_packet = UdpPacket::new_view(ipv6.upper_layer_payload())?;
     Ok(udp_packet.payload().starts_with(MAGIC))
 }
 
@@ -492,13 +705,23 @@ mod tests {
     use crate::error::IoResult;
     use crate::mocket_recv_from;
     use crate::net::socket::MockSocket;
-    use crate::{Flags, Port, RoundId, TimeToLive};
+    use crate::net::socket::RecvInfo;
+    use crate::{Dscp, Flags, Port, RoundId, TimeToLive, TypeOfService};
     use mockall::predicate;
     use std::str::FromStr;
     use std::sync::Mutex;
 
     static MTX: Mutex<()> = Mutex::new(());
 
+    #[test]
+    fn test_icmp_packet_size_from_payload_len() {
+        assert_eq!(
+            Ipv6Packet::minimum_packet_size() + IcmpPacket::minimum_packet_size(),
+            IPV6_ICMP_HEADER_SIZE
+        );
+        assert_eq!(1000, icmp_payload_size(1000 + IPV6_ICMP_HEADER_SIZE));
+    }
+
     // Test dispatching an IPv6/ICMP probe.
     #[test]
     fn test_dispatch_icmp_probe_no_payload() -> anyhow::Result<()> {
@@ -506,7 +729,7 @@ mod tests {
         let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let packet_size = PacketSize(48);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let expected_send_to_buf = hex_literal::hex!("80 00 75 a2 04 d2 82 9a");
         let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 0);
 
@@ -540,13 +763,79 @@ mod tests {
         Ok(())
     }
 
+    // The IPv6 header of ICMPv6 probes is written by the kernel and so the traffic class is set
+    // with the `IPV6_TCLASS` socket option rather than in a crafted header.
+    #[test]
+    fn test_dispatch_icmp_probe_with_traffic_class() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 0);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(predicate::always(), predicate::eq(expected_send_to_addr))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mocket
+            .expect_set_unicast_hops_v6()
+            .times(1)
+            .returning(|_| Ok(()));
+        mocket
+            .expect_set_tclass_v6()
+            .times(1)
+            .with(predicate::eq(0xb8))
+            .returning(|_| Ok(()));
+        let ipv6 = Ipv6 {
+            dest_addr,
+            packet_size: PacketSize(48),
+            tos: TypeOfService(0xb8),
+            ..Default::default()
+        };
+        ipv6.dispatch_icmp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_icmp_probe_with_fwmark() -> anyhow::Result<()> {
+        let probe = make_icmp_probe();
+        let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 0);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_set_unicast_hops_v6()
+            .times(2)
+            .returning(|_| Ok(()));
+        mocket.expect_set_tclass_v6().times(2).returning(|_| Ok(()));
+        mocket
+            .expect_set_mark()
+            .with(predicate::eq(42))
+            .times(2)
+            .returning(|_| Ok(()));
+        mocket
+            .expect_send_to()
+            .with(predicate::always(), predicate::eq(expected_send_to_addr))
+            .times(2)
+            .returning(|_, _| Ok(()));
+        let ipv6 = Ipv6 {
+            dest_addr,
+            packet_size: PacketSize(48),
+            fwmark: Some(42),
+            ..Default::default()
+        };
+        ipv6.dispatch_icmp_probe(&mut mocket, &probe)?;
+        ipv6.dispatch_icmp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
     #[test]
     fn test_dispatch_icmp_probe_with_payload() -> anyhow::Result<()> {
         let probe = make_icmp_probe();
         let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let packet_size = PacketSize(68);
-        let payload_pattern = PayloadPattern(0xff);
+        let payload_pattern = PayloadPattern::Fixed(0xff);
         let expected_send_to_buf = hex_literal::hex!(
             "
             80 00 75 8e 04 d2 82 9a ff ff ff ff ff ff ff ff
@@ -591,7 +880,7 @@ mod tests {
         let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let packet_size = PacketSize(47);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let mut mocket = MockSocket::new();
         let ipv6 = Ipv6 {
             src_addr,
@@ -611,7 +900,7 @@ mod tests {
         let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let packet_size = PacketSize(1025);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let mut mocket = MockSocket::new();
         let ipv6 = Ipv6 {
             src_addr,
@@ -632,7 +921,7 @@ mod tests {
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(48);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let initial_sequence = Sequence(33434);
         let expected_send_to_buf = hex_literal::hex!("00 7b 01 c8 00 08 7a ed");
         let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 0);
@@ -677,7 +966,7 @@ mod tests {
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(56);
-        let payload_pattern = PayloadPattern(0xaa);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
         let initial_sequence = Sequence(33434);
         let expected_send_to_buf = hex_literal::hex!(
             "
@@ -731,7 +1020,7 @@ mod tests {
         // packet size and payload pattern are ignored for paris mode as a
         // fixed two byte payload is used to hold the sequence
         let packet_size = PacketSize(300);
-        let payload_pattern = PayloadPattern(0xaa);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
         let initial_sequence = Sequence(33434);
         let expected_send_to_buf = hex_literal::hex!(
             "
@@ -773,6 +1062,61 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dispatch_udp_probe_paris_privileged_sequence() -> anyhow::Result<()> {
+        let probe = Probe {
+            flags: Flags::PARIS_CHECKSUM,
+            sequence: Sequence(33441),
+            ..make_udp_probe(123, 456)
+        };
+        let src_addr = Ipv6Addr::from_str("fd7a:115c:a1e0:ab12:4843:cd96:6263:82a")?;
+        let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
+        let privilege_mode = PrivilegeMode::Privileged;
+        // the sequence is held in the checksum field and the payload holds
+        // the value required for the checksum to be valid
+        let packet_size = PacketSize(300);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
+        let initial_sequence = Sequence(33434);
+        let expected_send_to_buf = hex_literal::hex!(
+            "
+            00 7b 01 c8 00 0a 82 a1 f8 47
+            "
+        );
+        let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 0);
+
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_send_to()
+            .with(
+                predicate::eq(expected_send_to_buf),
+                predicate::eq(expected_send_to_addr),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+        mocket
+            .expect_set_unicast_hops_v6()
+            .times(1)
+            .with(predicate::eq(10))
+            .returning(|_| Ok(()));
+        mocket
+            .expect_set_tclass_v6()
+            .times(1)
+            .with(predicate::eq(0))
+            .returning(|_| Ok(()));
+
+        let ipv6 = Ipv6 {
+            src_addr,
+            dest_addr,
+            packet_size,
+            payload_pattern,
+            privilege_mode,
+            initial_sequence,
+            ..Default::default()
+        };
+        ipv6.dispatch_udp_probe(&mut mocket, &probe)?;
+        Ok(())
+    }
+
     // Here we send probe 33007 (the 8th probe when starting from 33434) and
     // so the payload will be 13 octets in length (7 + 6 for the magic prefix
     // "trippy").
@@ -789,7 +1133,7 @@ mod tests {
         let privilege_mode = PrivilegeMode::Privileged;
         // packet size and payload pattern are ignored for ipv6/udp/dublin mode.
         let packet_size = PacketSize(300);
-        let payload_pattern = PayloadPattern(0xaa);
+        let payload_pattern = PayloadPattern::Fixed(0xaa);
         let initial_sequence = Sequence(33434);
         let expected_send_to_buf = hex_literal::hex!(
             "
@@ -841,7 +1185,7 @@ mod tests {
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Unprivileged;
         let packet_size = PacketSize(48);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let initial_sequence = Sequence(33434);
         let expected_send_to_buf = hex_literal::hex!("");
         let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 456);
@@ -904,7 +1248,7 @@ mod tests {
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Unprivileged;
         let packet_size = PacketSize(56);
-        let payload_pattern = PayloadPattern(0x1f);
+        let payload_pattern = PayloadPattern::Fixed(0x1f);
         let initial_sequence = Sequence(33434);
         let expected_send_to_buf = hex_literal::hex!("1f 1f 1f 1f 1f 1f 1f 1f");
         let expected_send_to_addr = SocketAddr::new(IpAddr::V6(dest_addr), 456);
@@ -966,7 +1310,7 @@ mod tests {
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(47);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let initial_sequence = Sequence(33434);
         let mut mocket = MockSocket::new();
         let ipv6 = Ipv6 {
@@ -990,7 +1334,7 @@ mod tests {
         let dest_addr = Ipv6Addr::from_str("2a00:1450:4009:815::200e")?;
         let privilege_mode = PrivilegeMode::Privileged;
         let packet_size = PacketSize(1025);
-        let payload_pattern = PayloadPattern(0x00);
+        let payload_pattern = PayloadPattern::Fixed(0x00);
         let initial_sequence = Sequence(33434);
         let mut mocket = MockSocket::new();
         let ipv6 = Ipv6 {
@@ -1068,12 +1412,17 @@ mod tests {
         );
         let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
         let mut mocket = MockSocket::new();
+        mocket.expect_recv_ttl().times(0);
         mocket
             .expect_recv_from()
             .times(1)
             .returning(mocket_recv_from!(
                 expected_recv_from_buf,
-                expected_recv_from_addr
+                expected_recv_from_addr,
+                RecvInfo {
+                    ttl: Some(57),
+                    tclass: Some(0xb9),
+                }
             ));
         let ipv6 = Ipv6 {
             protocol: Protocol::Icmp,
@@ -1085,11 +1434,14 @@ mod tests {
         let Response::EchoReply(
             ResponseData {
                 addr,
+                ttl,
+                response_tos,
                 proto_resp:
                     ProtocolResponse::Icmp(IcmpProtocolResponse {
                         identifier,
                         sequence,
                         tos,
+                        ..
                     }),
                 ..
             },
@@ -1103,6 +1455,84 @@ mod tests {
         assert_eq!(33062, sequence);
         assert_eq!(None, tos);
         assert_eq!(IcmpPacketCode(0), icmp_code);
+        assert_eq!(Some(57), ttl);
+        assert_eq!(Some(TypeOfService(0xb9)), response_tos);
+        assert_eq!(Some(Dscp::EF), response_tos.map(|tos| tos.dscp()));
+        Ok(())
+    }
+
+    // Test receiving a packet which fills the whole receive buffer, and so may have been
+    // truncated, is discarded rather than parsed.
+    #[test]
+    fn test_recv_icmp_probe_truncated() -> anyhow::Result<()> {
+        let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?);
+        let mut expected_recv_from_buf = vec![0_u8; MAX_RECV_PACKET_SIZE];
+        expected_recv_from_buf[..8].copy_from_slice(&hex_literal::hex!("81 00 52 c0 55 b9 81 26"));
+        let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_recv_from()
+            .times(2)
+            .returning(mocket_recv_from!(
+                expected_recv_from_buf,
+                expected_recv_from_addr
+            ));
+        let ipv6 = Ipv6 {
+            protocol: Protocol::Icmp,
+            ..Default::default()
+        };
+        assert!(ipv6.recv_icmp_probe(&mut mocket)?.is_none());
+        assert!(ipv6.recv_icmp_probe(&mut mocket)?.is_none());
+        assert_eq!(2, ipv6.truncated_responses.load(Ordering::Relaxed));
+        Ok(())
+    }
+
+    #[test]
+    fn test_recv_icmp_probe_echo_reply_tolerant_identifier() -> anyhow::Result<()> {
+        let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?);
+        let expected_recv_from_buf = hex_literal::hex!(
+            "
+            81 00 52 c0 55 b9 81 26 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+            00 00 00 00 00 00 00 00 00 00 00 00
+           "
+        );
+        let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_recv_from()
+            .times(1)
+            .returning(mocket_recv_from!(
+                expected_recv_from_buf,
+                expected_recv_from_addr
+            ));
+        let ipv6 = Ipv6 {
+            dest_addr: Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?,
+            protocol: Protocol::Icmp,
+            icmp_identifier_mode: IcmpIdentifierMatchMode::Tolerant,
+            ..Default::default()
+        };
+        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+
+        let Response::EchoReply(
+            ResponseData {
+                addr,
+                proto_resp:
+                    ProtocolResponse::Icmp(IcmpProtocolResponse {
+                        identifier,
+                        sequence,
+                        ..
+                    }),
+                ..
+            },
+            _,
+        ) = resp
+        else {
+            panic!("expected EchoReply")
+        };
+        assert_eq!(recv_from_addr, addr);
+        assert_eq!(0, identifier);
+        assert_eq!(33062, sequence);
         Ok(())
     }
 
@@ -1143,6 +1573,7 @@ mod tests {
                         identifier,
                         sequence,
                         tos,
+                        ..
                     }),
                 ..
             },
@@ -1198,6 +1629,7 @@ mod tests {
                         identifier,
                         sequence,
                         tos,
+                        ..
                     }),
                 ..
             },
@@ -1283,6 +1715,64 @@ mod tests {
         Ok(())
     }
 
+    // The original datagram embedded in the `TimeExceeded` includes a Hop-by-Hop extension header
+    // (containing a single `PadN` option) before the UDP header.
+    #[test]
+    fn test_recv_icmp_probe_time_exceeded_udp_hop_by_hop_extension_header() -> anyhow::Result<()> {
+        let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?);
+        let expected_recv_from_buf = hex_literal::hex!(
+            "
+            03 00 00 00 00 00 00 00 60 00 00 00 00 18 00 01
+            fd 7a 11 5c a1 e0 ab 12 48 43 cd 96 62 63 08 2a
+            2a 04 4e 42 00 00 00 00 00 00 00 00 00 00 00 81
+            11 00 01 04 00 00 00 00 58 a6 81 05 00 10 d0 f1
+            00 00 00 00 00 00 00 00
+           "
+        );
+        let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_recv_from()
+            .times(1)
+            .returning(mocket_recv_from!(
+                expected_recv_from_buf,
+                expected_recv_from_addr
+            ));
+        let ipv6 = Ipv6 {
+            protocol: Protocol::Udp,
+            icmp_extension_mode: IcmpExtensionParseMode::Disabled,
+            ..Default::default()
+        };
+        let resp = ipv6.recv_icmp_probe(&mut mocket)?.unwrap();
+
+        let Response::TimeExceeded(
+            ResponseData {
+                addr,
+                proto_resp:
+                    ProtocolResponse::Udp(UdpProtocolResponse {
+                        dest_addr,
+                        src_port,
+                        dest_port,
+                        actual_udp_checksum,
+                        payload_len,
+                        ..
+                    }),
+                ..
+            },
+            ..,
+        ) = resp
+        else {
+            panic!("expected TimeExceeded")
+        };
+        assert_eq!(recv_from_addr, addr);
+        assert_eq!(IpAddr::V6(Ipv6Addr::from_str("2a04:4e42::81")?), dest_addr);
+        assert_eq!(22694, src_port);
+        assert_eq!(33029, dest_port);
+        assert_eq!(53489, actual_udp_checksum);
+        assert_eq!(8, payload_len);
+        Ok(())
+    }
+
     #[test]
     fn test_recv_icmp_probe_destination_unreachable_udp_no_extensions() -> anyhow::Result<()> {
         let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?);
@@ -1696,6 +2186,38 @@ mod tests {
         Ok(())
     }
 
+    // Test receiving a `TimeExceeded` which quotes a TCP packet when tracing with UDP.
+    #[test]
+    fn test_recv_icmp_probe_udp_quoting_tcp_protocol_mismatch() -> anyhow::Result<()> {
+        let recv_from_addr = IpAddr::V6(Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?);
+        let expected_recv_from_buf = hex_literal::hex!(
+            "
+            03 00 f0 2d 00 00 00 00 68 0b 09 00 00 2c 06 01
+            fd 7a 11 5c a1 e0 ab 12 48 43 cd 96 62 63 08 2a
+            2a 00 14 50 40 09 08 15 00 00 00 00 00 00 20 0e
+            81 0e 00 50 aa c4 08 e6 00 00 00 00 b0 c2 ff ff
+            6d b4 00 00 02 04 04 c4 01 03 03 06 01 01 08 0a
+            cc f7 44 c9 00 00 00 00 04 02 00 00
+           "
+        );
+        let expected_recv_from_addr = SocketAddr::new(recv_from_addr, 0);
+        let mut mocket = MockSocket::new();
+        mocket
+            .expect_recv_from()
+            .times(1)
+            .returning(mocket_recv_from!(
+                expected_recv_from_buf,
+                expected_recv_from_addr
+            ));
+        let ipv6 = Ipv6 {
+            protocol: Protocol::Udp,
+            ..Default::default()
+        };
+        assert!(ipv6.recv_icmp_probe(&mut mocket)?.is_none());
+        assert_eq!(1, ipv6.protocol_mismatches.load(Ordering::Relaxed));
+        Ok(())
+    }
+
     #[test]
     fn test_recv_tcp_socket_tcp_reply() -> anyhow::Result<()> {
         let dest_addr = Ipv6Addr::from_str("2604:a880:ffff:6:1::41c")?;
@@ -1707,6 +2229,11 @@ mod tests {
             .expect_peer_addr()
             .times(1)
             .returning(move || Ok(Some(expected_peer_addr)));
+        mocket.expect_recv_ttl().times(1).returning(|| Ok(Some(57)));
+        mocket
+            .expect_recv_ifindex()
+            .times(1)
+            .returning(|| Ok(Some(3)));
         mocket.expect_shutdown().times(1).returning(|| Ok(()));
 
         let ipv6 = Ipv6 {
@@ -1726,6 +2253,8 @@ mod tests {
                     dest_port,
                     tos,
                 }),
+            ttl,
+            ifindex,
             ..
         }) = resp
         else {
@@ -1735,6 +2264,8 @@ mod tests {
         assert_eq!(33434, src_port);
         assert_eq!(456, dest_port);
         assert_eq!(None, tos);
+        assert_eq!(Some(57), ttl);
+        assert_eq!(Some(3), ifindex);
         Ok(())
     }
 
@@ -0,0 +1,142 @@
+use crate::error::{IoError, IoOperation, IoResult};
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The pcap magic number for microsecond resolution timestamps.
+const MAGIC: u32 = 0xa1b2_c3d4;
+
+/// The pcap major version.
+const VERSION_MAJOR: u16 = 2;
+
+/// The pcap minor version.
+const VERSION_MINOR: u16 = 4;
+
+/// The maximum length of a captured packet.
+const SNAPLEN: u32 = 65535;
+
+/// The `LINKTYPE_RAW` link-layer header type, where each packet begins with an IP header.
+const LINKTYPE_RAW: u32 = 101;
+
+/// Writes the packets of dispatched probes to a pcap file.
+///
+/// See <https://wiki.wireshark.org/Development/LibpcapFileFormat>.
+pub struct PcapWriter {
+    sink: Box<dyn Write + Send>,
+}
+
+impl PcapWriter {
+    /// Create a new pcap file at `path`, replacing any existing file.
+    pub fn create(path: &Path) -> IoResult<Self> {
+        let file =
+            File::create(path).map_err(|err| IoError::Other(err, IoOperation::CreatePcap))?;
+        Self::new(Box::new(file))
+    }
+
+    /// Create a new `PcapWriter` and write the pcap global header to `sink`.
+    pub fn new(mut sink: Box<dyn Write + Send>) -> IoResult<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&MAGIC.to_le_bytes());
+        header.extend_from_slice(&VERSION_MAJOR.to_le_bytes());
+        header.extend_from_slice(&VERSION_MINOR.to_le_bytes());
+        header.extend_from_slice(&0_i32.to_le_bytes());
+        header.extend_from_slice(&0_u32.to_le_bytes());
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        sink.write_all(&header)
+            .and_then(|()| sink.flush())
+            .map_err(|err| IoError::Other(err, IoOperation::WritePcap))?;
+        Ok(Self { sink })
+    }
+
+    /// Write a record for `packet`, which begins with an IP header, sent at `timestamp`.
+    pub fn write_packet(&mut self, timestamp: SystemTime, packet: &[u8]) -> IoResult<()> {
+        let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let len = packet.len() as u32;
+        let mut record = Vec::with_capacity(16 + packet.len());
+        record.extend_from_slice(&(since_epoch.as_secs() as u32).to_le_bytes());
+        record.extend_from_slice(&since_epoch.subsec_micros().to_le_bytes());
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(&len.to_le_bytes());
+        record.extend_from_slice(packet);
+        self.sink
+            .write_all(&record)
+            .and_then(|()| self.sink.flush())
+            .map_err(|err| IoError::Other(err, IoOperation::WritePcap))
+    }
+}
+
+impl Debug for PcapWriter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PcapWriter").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// A pcap sink which may be inspected after it has been written to.
+    #[derive(Debug, Clone, Default)]
+    pub struct SharedSink(pub Arc<Mutex<Vec<u8>>>);
+
+    impl SharedSink {
+        /// The records written after the global header, as `(incl_len, orig_len, data)`.
+        pub fn records(&self) -> Vec<(u32, u32, Vec<u8>)> {
+            let buf = self.0.lock().unwrap().clone();
+            let mut records = vec![];
+            let mut rest = &buf[24..];
+            while !rest.is_empty() {
+                let incl_len = u32::from_le_bytes(rest[8..12].try_into().unwrap());
+                let orig_len = u32::from_le_bytes(rest[12..16].try_into().unwrap());
+                let data = rest[16..16 + incl_len as usize].to_vec();
+                records.push((incl_len, orig_len, data));
+                rest = &rest[16 + incl_len as usize..];
+            }
+            records
+        }
+    }
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::SharedSink;
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_global_header() {
+        let sink = SharedSink::default();
+        PcapWriter::new(Box::new(sink.clone())).unwrap();
+        let expected = hex_literal::hex!(
+            "d4 c3 b2 a1 02 00 04 00 00 00 00 00 00 00 00 00 ff ff 00 00 65 00 00 00"
+        );
+        assert_eq!(&expected[..], sink.0.lock().unwrap().as_slice());
+    }
+
+    #[test]
+    fn test_write_packet() {
+        let sink = SharedSink::default();
+        let mut writer = PcapWriter::new(Box::new(sink.clone())).unwrap();
+        let timestamp = UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456);
+        writer
+            .write_packet(timestamp, &[0x45, 0x00, 0x00, 0x04])
+            .unwrap();
+        let expected =
+            hex_literal::hex!("00 f1 53 65 40 e2 01 00 04 00 00 00 04 00 00 00 45 00 00 04");
+        assert_eq!(&expected[..], &sink.0.lock().unwrap()[24..]);
+    }
+}
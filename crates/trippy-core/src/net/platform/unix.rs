@@ -17,16 +17,18 @@ impl Platform for PlatformImpl {
 }
 
 mod address {
+    use crate::SourceAddr;
     use crate::error::{Error, Result};
     use crate::net::SocketImpl;
     use crate::net::platform::Ipv4ByteOrder;
-    use crate::net::socket::Socket;
     use nix::sys::socket::{AddressFamily, SockaddrLike};
-    use std::net::{IpAddr, SocketAddr};
+    use std::net::IpAddr;
     use tracing::instrument;
 
     #[cfg(not(target_os = "linux"))]
-    use std::net::Ipv4Addr;
+    use crate::net::socket::Socket;
+    #[cfg(not(target_os = "linux"))]
+    use std::net::{Ipv4Addr, SocketAddr};
 
     /// The size of the test packet to use for discovering the `total_length` byte order.
     #[cfg(not(target_os = "linux"))]
@@ -137,22 +139,16 @@ mod address {
             .ok_or_else(|| Error::UnknownInterface(name.to_string()))
     }
 
-    // Note that no packets are transmitted by this method.
     #[instrument(ret, level = "trace")]
     pub fn discover_local_addr(target_addr: IpAddr, port: u16) -> Result<IpAddr> {
-        let mut socket = match target_addr {
-            IpAddr::V4(_) => SocketImpl::new_udp_dgram_socket_ipv4(),
-            IpAddr::V6(_) => SocketImpl::new_udp_dgram_socket_ipv6(),
-        }?;
-        socket.connect(SocketAddr::new(target_addr, port))?;
-        Ok(socket.local_addr()?.ok_or(Error::MissingAddr)?.ip())
+        SourceAddr::select_source_addr::<SocketImpl>(target_addr, port)
     }
 }
 
 mod socket {
     use crate::error::{ErrorKind, IoError, IoOperation};
     use crate::error::{IoResult, Result};
-    use crate::net::socket::{Socket, SocketError};
+    use crate::net::socket::{MtuDiscover, RecvInfo, Socket, SocketError};
     use itertools::Itertools;
     use nix::{
         Error,
@@ -173,6 +169,19 @@ mod socket {
         Ok(())
     }
 
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    use nix::{libc, setsockopt_impl, sockopt_impl};
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    sockopt_impl!(
+        /// The `IP_MTU_DISCOVER` socket option, which is not provided by `nix`.
+        IpMtuDiscover,
+        SetOnly,
+        libc::IPPROTO_IP,
+        libc::IP_MTU_DISCOVER,
+        libc::c_int
+    );
+
     /// A network socket.
     pub struct SocketImpl {
         inner: socket2::Socket,
@@ -220,12 +229,69 @@ mod socket {
                 .map_err(|err| IoError::Other(err, IoOperation::SetNonBlocking))
         }
 
-        pub(super) fn local_addr(&self) -> IoResult<Option<SocketAddr>> {
-            Ok(self
-                .inner
-                .local_addr()
-                .map_err(|err| IoError::Other(err, IoOperation::LocalAddr))?
-                .as_socket())
+        /// Request the `IP_RECVTTL` (IPv4) or `IPV6_RECVHOPLIMIT` (IPv6) ancillary data.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        fn set_recv_ttl(&self, domain: Domain) -> IoResult<()> {
+            use nix::sys::socket::{setsockopt, sockopt};
+            if domain == Domain::IPV6 {
+                setsockopt(&self.inner, sockopt::Ipv6RecvHopLimit, &true)
+            } else {
+                setsockopt(&self.inner, sockopt::Ipv4RecvTtl, &true)
+            }
+            .map_err(|err| IoError::Other(io::Error::from(err), IoOperation::SetRecvTtl))
+        }
+
+        /// Request the `IPV6_RECVTCLASS` ancillary data.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        fn set_recv_tclass_v6(&self) -> IoResult<()> {
+            use nix::sys::socket::{setsockopt, sockopt};
+            setsockopt(&self.inner, sockopt::Ipv6RecvTClass, &true)
+                .map_err(|err| IoError::Other(io::Error::from(err), IoOperation::SetRecvTclass))
+        }
+
+        /// Request the `IP_PKTINFO` (IPv4) or `IPV6_RECVPKTINFO` (IPv6) ancillary data.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        fn set_recv_pktinfo(&self, domain: Domain) -> IoResult<()> {
+            use nix::sys::socket::{setsockopt, sockopt};
+            if domain == Domain::IPV6 {
+                setsockopt(&self.inner, sockopt::Ipv6RecvPacketInfo, &true)
+            } else {
+                setsockopt(&self.inner, sockopt::Ipv4PacketInfo, &true)
+            }
+            .map_err(|err| IoError::Other(io::Error::from(err), IoOperation::SetRecvPktInfo))
+        }
+
+        /// Peek the ancillary data of the next message without consuming it.
+        ///
+        /// Returns `None` if no message is available or the peek fails, as the ancillary data is
+        /// informational only.  Note that stream sockets, such as connected TCP sockets, may not
+        /// deliver ancillary data at all.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        fn peek_cmsgs(
+            &self,
+            operation: &IoOperation,
+        ) -> Option<Vec<nix::sys::socket::ControlMessageOwned>> {
+            use nix::libc::{c_int, in6_pktinfo};
+            use nix::sys::socket::{MsgFlags, SockaddrStorage, recvmsg};
+            use std::io::IoSliceMut;
+            use std::os::fd::AsRawFd;
+            let mut buf = [0_u8; 1];
+            let mut iov = [IoSliceMut::new(&mut buf)];
+            let mut cmsg_buf = nix::cmsg_space!(c_int, in6_pktinfo);
+            let msg = match recvmsg::<SockaddrStorage>(
+                self.inner.as_raw_fd(),
+                &mut iov,
+                Some(&mut cmsg_buf),
+                MsgFlags::MSG_PEEK | MsgFlags::MSG_DONTWAIT,
+            ) {
+                Ok(msg) => msg,
+                Err(Error::EAGAIN) => return None,
+                Err(err) => {
+                    tracing::debug!("failed to {operation}: {err}");
+                    return None;
+                }
+            };
+            Some(msg.cmsgs().into_iter().flatten().collect())
         }
     }
 
@@ -299,10 +365,18 @@ mod socket {
             if raw {
                 let socket = Self::new_raw_ipv6(Protocol::ICMPV6)?;
                 socket.set_nonblocking(true)?;
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                socket.set_recv_ttl(Domain::IPV6)?;
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                socket.set_recv_tclass_v6()?;
                 Ok(socket)
             } else {
                 let socket = Self::new_dgram_ipv6(Protocol::ICMPV6)?;
                 socket.set_nonblocking(true)?;
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                socket.set_recv_ttl(Domain::IPV6)?;
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                socket.set_recv_tclass_v6()?;
                 Ok(socket)
             }
         }
@@ -311,6 +385,10 @@ mod socket {
             let mut socket = Self::new(Domain::IPV4, Type::STREAM, Protocol::TCP)?;
             socket.set_nonblocking(true)?;
             socket.set_reuse_port(true)?;
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            socket.set_recv_ttl(Domain::IPV4)?;
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            socket.set_recv_pktinfo(Domain::IPV4)?;
             Ok(socket)
         }
         #[instrument(level = "trace")]
@@ -318,6 +396,24 @@ mod socket {
             let mut socket = Self::new(Domain::IPV6, Type::STREAM, Protocol::TCP)?;
             socket.set_nonblocking(true)?;
             socket.set_reuse_port(true)?;
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            socket.set_recv_ttl(Domain::IPV6)?;
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            socket.set_recv_pktinfo(Domain::IPV6)?;
+            Ok(socket)
+        }
+        #[instrument(level = "trace")]
+        fn new_tcp_send_socket_ipv4() -> IoResult<Self> {
+            let mut socket = Self::new_raw_ipv4(Protocol::from(nix::libc::IPPROTO_RAW))?;
+            socket.set_nonblocking(true)?;
+            socket.set_header_included(true)?;
+            Ok(socket)
+        }
+        /// Note that only Linux delivers incoming TCP segments to raw sockets.
+        #[instrument(level = "trace")]
+        fn new_tcp_recv_socket_ipv4(_: Ipv4Addr) -> IoResult<Self> {
+            let socket = Self::new_raw_ipv4(Protocol::TCP)?;
+            socket.set_nonblocking(true)?;
             Ok(socket)
         }
         #[instrument(level = "trace")]
@@ -370,6 +466,39 @@ mod socket {
                 .set_unicast_hops_v6(u32::from(hops))
                 .map_err(|err| IoError::Other(err, IoOperation::SetUnicastHopsV6))
         }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        #[instrument(skip(self), level = "trace")]
+        fn set_mark(&mut self, mark: u32) -> IoResult<()> {
+            self.inner
+                .set_mark(mark)
+                .map_err(|err| IoError::Other(err, IoOperation::SetMark))
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        #[instrument(skip(self), level = "trace")]
+        fn set_mark(&mut self, _mark: u32) -> IoResult<()> {
+            Err(IoError::Other(
+                io::Error::from(io::ErrorKind::Unsupported),
+                IoOperation::SetMark,
+            ))
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        #[instrument(skip(self), level = "trace")]
+        fn set_mtu_discover(&mut self, mode: MtuDiscover) -> IoResult<()> {
+            use nix::sys::socket::setsockopt;
+            let mode = match mode {
+                MtuDiscover::Do => libc::IP_PMTUDISC_DO,
+                MtuDiscover::Dont => libc::IP_PMTUDISC_DONT,
+                MtuDiscover::Probe => libc::IP_PMTUDISC_PROBE,
+            };
+            setsockopt(&self.inner, IpMtuDiscover, &mode)
+                .map_err(|err| IoError::Other(io::Error::from(err), IoOperation::SetMtuDiscover))
+        }
+        /// The `IP_MTU_DISCOVER` socket option is Linux specific and so this is a no-op.
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        #[instrument(skip(self), level = "trace")]
+        fn set_mtu_discover(&mut self, _mode: MtuDiscover) -> IoResult<()> {
+            Ok(())
+        }
         #[instrument(skip(self), level = "trace")]
         fn connect(&mut self, address: SocketAddr) -> IoResult<()> {
             tracing::trace!(?address);
@@ -425,8 +554,60 @@ mod socket {
                 )),
             }
         }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        #[instrument(skip(self, buf), level = "trace")]
+        fn recv_from(&mut self, buf: &mut [u8]) -> IoResult<(usize, Option<SocketAddr>, RecvInfo)> {
+            use nix::libc::c_int;
+            use nix::sys::socket::{
+                AddressFamily, ControlMessageOwned, MsgFlags, SockaddrLike, SockaddrStorage,
+                recvmsg,
+            };
+            use std::io::IoSliceMut;
+            use std::net::{SocketAddrV4, SocketAddrV6};
+            use std::os::fd::AsRawFd;
+            let mut iov = [IoSliceMut::new(buf)];
+            let mut cmsg_buf = nix::cmsg_space!(c_int, c_int);
+            let msg = recvmsg::<SockaddrStorage>(
+                self.inner.as_raw_fd(),
+                &mut iov,
+                Some(&mut cmsg_buf),
+                MsgFlags::empty(),
+            )
+            .map_err(|err| IoError::Other(io::Error::from(err), IoOperation::RecvFrom))?;
+            let addr = msg.address.and_then(|addr| match addr.family() {
+                Some(AddressFamily::Inet) => addr
+                    .as_sockaddr_in()
+                    .map(|addr| SocketAddr::V4(SocketAddrV4::from(*addr))),
+                Some(AddressFamily::Inet6) => addr
+                    .as_sockaddr_in6()
+                    .map(|addr| SocketAddr::V6(SocketAddrV6::from(*addr))),
+                _ => None,
+            });
+            let mut info = RecvInfo::default();
+            for cmsg in msg.cmsgs().into_iter().flatten() {
+                match cmsg {
+                    ControlMessageOwned::Ipv4Ttl(ttl) | ControlMessageOwned::Ipv6HopLimit(ttl) => {
+                        info.ttl = u8::try_from(ttl).ok();
+                    }
+                    ControlMessageOwned::Ipv6TClass(tclass) => {
+                        info.tclass = u8::try_from(tclass).ok();
+                    }
+                    _ => {}
+                }
+            }
+            let bytes_read = msg.bytes;
+            tracing::trace!(
+                buf = format!("{:02x?}", buf[..bytes_read].iter().format(" ")),
+                bytes_read,
+                ?addr,
+                ?info
+            );
+            Ok((bytes_read, addr, info))
+        }
+        /// The ancillary data is only available on Linux and Android.
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
         #[instrument(skip(self, buf), level = "trace")]
-        fn recv_from(&mut self, buf: &mut [u8]) -> IoResult<(usize, Option<SocketAddr>)> {
+        fn recv_from(&mut self, buf: &mut [u8]) -> IoResult<(usize, Option<SocketAddr>, RecvInfo)> {
             let (bytes_read, addr) = self
                 .inner
                 .recv_from_into_buf(buf)
@@ -436,7 +617,7 @@ mod socket {
                 bytes_read,
                 ?addr
             );
-            Ok((bytes_read, addr))
+            Ok((bytes_read, addr, RecvInfo::default()))
         }
         #[instrument(skip(self, buf), level = "trace")]
         fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
@@ -467,6 +648,14 @@ mod socket {
             Ok(addr)
         }
         #[instrument(skip(self), ret, level = "trace")]
+        fn local_addr(&mut self) -> IoResult<Option<SocketAddr>> {
+            Ok(self
+                .inner
+                .local_addr()
+                .map_err(|err| IoError::Other(err, IoOperation::LocalAddr))?
+                .as_socket())
+        }
+        #[instrument(skip(self), ret, level = "trace")]
         fn take_error(&mut self) -> IoResult<Option<SocketError>> {
             self.inner
                 .take_error()
@@ -484,6 +673,40 @@ mod socket {
         fn icmp_error_info(&mut self) -> IoResult<IpAddr> {
             Ok(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
         }
+        /// Peek at the next pending message to read the received TTL ancillary data.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        #[instrument(skip(self), ret, level = "trace")]
+        fn recv_ttl(&mut self) -> IoResult<Option<u8>> {
+            use nix::sys::socket::ControlMessageOwned;
+            let cmsgs = self.peek_cmsgs(&IoOperation::RecvTtl);
+            Ok(cmsgs.into_iter().flatten().find_map(|cmsg| match cmsg {
+                ControlMessageOwned::Ipv4Ttl(ttl) | ControlMessageOwned::Ipv6HopLimit(ttl) => {
+                    u8::try_from(ttl).ok()
+                }
+                _ => None,
+            }))
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        #[instrument(skip(self), ret, level = "trace")]
+        fn recv_ttl(&mut self) -> IoResult<Option<u8>> {
+            Ok(None)
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        #[instrument(skip(self), ret, level = "trace")]
+        fn recv_ifindex(&mut self) -> IoResult<Option<u32>> {
+            use nix::sys::socket::ControlMessageOwned;
+            let cmsgs = self.peek_cmsgs(&IoOperation::RecvIfIndex);
+            Ok(cmsgs.into_iter().flatten().find_map(|cmsg| match cmsg {
+                ControlMessageOwned::Ipv4PacketInfo(info) => u32::try_from(info.ipi_ifindex).ok(),
+                ControlMessageOwned::Ipv6PacketInfo(info) => Some(info.ipi6_ifindex),
+                _ => None,
+            }))
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        #[instrument(skip(self), ret, level = "trace")]
+        fn recv_ifindex(&mut self) -> IoResult<Option<u32>> {
+            Ok(None)
+        }
     }
 
     impl From<&io::Error> for ErrorKind {
@@ -523,10 +746,12 @@ mod socket {
     /// This is required for `socket2::Socket` which [does not currently provide] this method.
     ///
     /// [does not currently provide]: https://github.com/rust-lang/socket2/issues/223
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
     trait RecvFrom {
         fn recv_from_into_buf(&self, buf: &mut [u8]) -> io::Result<(usize, Option<SocketAddr>)>;
     }
 
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
     impl RecvFrom for socket2::Socket {
         // Safety: the `recv` implementation promises not to write uninitialised
         // bytes to the `buf`fer, so this casting is safe.
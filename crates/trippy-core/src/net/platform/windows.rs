@@ -1,9 +1,9 @@
 use super::byte_order::Ipv4ByteOrder;
 use crate::error::{Error, ErrorKind, IoError, IoOperation, IoResult, Result};
-use crate::net::channel::MAX_PACKET_SIZE;
+use crate::net::channel::MAX_RECV_PACKET_SIZE;
 use crate::net::platform::Platform;
 use crate::net::platform::windows::adapter::Adapters;
-use crate::net::socket::{Socket, SocketError};
+use crate::net::socket::{MtuDiscover, RecvInfo, Socket, SocketError};
 use itertools::Itertools;
 use socket2::{Domain, Protocol, SockAddr, Type};
 use std::ffi::c_void;
@@ -17,12 +17,12 @@ use tracing::instrument;
 use windows_sys::Win32::Foundation::{HANDLE, WAIT_FAILED, WAIT_TIMEOUT};
 use windows_sys::Win32::Networking::WinSock::{
     AF_INET, AF_INET6, FD_CONNECT, FD_WRITE, ICMP_ERROR_INFO, IN_ADDR, IN_ADDR_0, IN6_ADDR,
-    IN6_ADDR_0, IPPROTO_RAW, IPPROTO_TCP, SIO_ROUTING_INTERFACE_QUERY, SO_ERROR,
-    SO_PORT_SCALABILITY, SO_REUSE_UNICASTPORT, SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_IN6_0,
-    SOCKADDR_STORAGE, SOCKET_ERROR, SOL_SOCKET, TCP_FAIL_CONNECT_ON_ICMP_ERROR,
-    TCP_ICMP_ERROR_INFO, WSA_INVALID_EVENT, WSA_IO_INCOMPLETE, WSA_IO_PENDING, WSABUF, WSADATA,
-    WSAEADDRNOTAVAIL, WSAECONNREFUSED, WSAEHOSTUNREACH, WSAEINPROGRESS, WSAENETUNREACH, WSAENOBUFS,
-    WSAEVENT,
+    IN6_ADDR_0, IP_MTU_DISCOVER, IP_PMTUDISC_DO, IP_PMTUDISC_DONT, IP_PMTUDISC_PROBE, IPPROTO_IP,
+    IPPROTO_RAW, IPPROTO_TCP, SIO_ROUTING_INTERFACE_QUERY, SO_ERROR, SO_PORT_SCALABILITY,
+    SO_REUSE_UNICASTPORT, SOCKADDR_IN, SOCKADDR_IN6, SOCKADDR_IN6_0, SOCKADDR_STORAGE,
+    SOCKET_ERROR, SOL_SOCKET, TCP_FAIL_CONNECT_ON_ICMP_ERROR, TCP_ICMP_ERROR_INFO,
+    WSA_INVALID_EVENT, WSA_IO_INCOMPLETE, WSA_IO_PENDING, WSABUF, WSADATA, WSAEADDRNOTAVAIL,
+    WSAECONNREFUSED, WSAEHOSTUNREACH, WSAEINPROGRESS, WSAENETUNREACH, WSAENOBUFS, WSAEVENT,
 };
 use windows_sys::Win32::System::IO::OVERLAPPED;
 
@@ -118,7 +118,7 @@ impl SocketImpl {
         let from = Box::new(Self::new_sockaddr_storage());
         let from_len = std::mem::size_of::<SOCKADDR_STORAGE>() as i32;
         let ol = Box::new(Self::new_overlapped());
-        let buf = Box::new([0; MAX_PACKET_SIZE]);
+        let buf = Box::new([0; MAX_RECV_PACKET_SIZE]);
         Ok(Self {
             inner,
             ol,
@@ -221,7 +221,7 @@ impl SocketImpl {
                 && StdIoError::last_os_error().raw_os_error() != Some(WSA_IO_PENDING)
         }
         let wbuf = WSABUF {
-            len: MAX_PACKET_SIZE as u32,
+            len: MAX_RECV_PACKET_SIZE as u32,
             buf: self.buf.as_mut_ptr(),
         };
         self.recv_flags = 0;
@@ -390,6 +390,16 @@ impl Socket for SocketImpl {
         Ok(sock)
     }
 
+    #[instrument(level = "trace")]
+    fn new_tcp_send_socket_ipv4() -> IoResult<Self> {
+        Err(raw_tcp_unsupported())
+    }
+
+    #[instrument(level = "trace")]
+    fn new_tcp_recv_socket_ipv4(_: Ipv4Addr) -> IoResult<Self> {
+        Err(raw_tcp_unsupported())
+    }
+
     #[instrument(level = "trace")]
     fn new_udp_dgram_socket_ipv4() -> IoResult<Self> {
         Self::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
@@ -460,6 +470,25 @@ impl Socket for SocketImpl {
             .map_err(|err| IoError::Other(err, IoOperation::SetUnicastHopsV6))
     }
 
+    #[instrument(skip(self), level = "trace")]
+    fn set_mark(&mut self, _mark: u32) -> IoResult<()> {
+        Err(IoError::Other(
+            StdIoError::from(StdErrorKind::Unsupported),
+            IoOperation::SetMark,
+        ))
+    }
+
+    #[instrument(skip(self), level = "trace")]
+    fn set_mtu_discover(&mut self, mode: MtuDiscover) -> IoResult<()> {
+        let mode = match mode {
+            MtuDiscover::Do => IP_PMTUDISC_DO,
+            MtuDiscover::Dont => IP_PMTUDISC_DONT,
+            MtuDiscover::Probe => IP_PMTUDISC_PROBE,
+        };
+        self.setsockopt_u32(IPPROTO_IP, IP_MTU_DISCOVER as _, mode as _)
+            .map_err(|err| IoError::Other(err, IoOperation::SetMtuDiscover))
+    }
+
     #[instrument(skip(self), level = "trace")]
     fn connect(&mut self, addr: SocketAddr) -> IoResult<()> {
         self.set_fail_connect_on_icmp_error(true)?;
@@ -519,8 +548,9 @@ impl Socket for SocketImpl {
         Ok(true)
     }
 
+    /// The ancillary data is not available on Windows.
     #[instrument(skip(self, buf), level = "trace")]
-    fn recv_from(&mut self, buf: &mut [u8]) -> IoResult<(usize, Option<SocketAddr>)> {
+    fn recv_from(&mut self, buf: &mut [u8]) -> IoResult<(usize, Option<SocketAddr>, RecvInfo)> {
         let addr = sockaddrptr_to_ipaddr(addr_of_mut!(*self.from))
             .map_err(|err| IoError::Other(err, IoOperation::RecvFrom))?;
         let len = self.read(buf)?;
@@ -529,7 +559,7 @@ impl Socket for SocketImpl {
             len,
             ?addr
         );
-        Ok((len, Some(SocketAddr::new(addr, 0))))
+        Ok((len, Some(SocketAddr::new(addr, 0)), RecvInfo::default()))
     }
 
     #[instrument(skip(self, buf), ret, level = "trace")]
@@ -557,6 +587,15 @@ impl Socket for SocketImpl {
             .as_socket())
     }
 
+    #[instrument(skip(self), ret, level = "trace")]
+    fn local_addr(&mut self) -> IoResult<Option<SocketAddr>> {
+        Ok(self
+            .inner
+            .local_addr()
+            .map_err(|err| IoError::Other(err, IoOperation::LocalAddr))?
+            .as_socket())
+    }
+
     #[instrument(skip(self), ret, level = "trace")]
     fn take_error(&mut self) -> IoResult<Option<SocketError>> {
         match self.getsockopt(SOL_SOCKET as _, SO_ERROR as _, 0) {
@@ -595,6 +634,18 @@ impl Socket for SocketImpl {
             )),
         }
     }
+
+    /// The received TTL is not available for connected sockets on Windows.
+    #[instrument(skip(self), ret, level = "trace")]
+    fn recv_ttl(&mut self) -> IoResult<Option<u8>> {
+        Ok(None)
+    }
+
+    /// The arrival interface is not available for connected sockets on Windows.
+    #[instrument(skip(self), ret, level = "trace")]
+    fn recv_ifindex(&mut self) -> IoResult<Option<u32>> {
+        Ok(None)
+    }
 }
 
 // Note that we handle `WSAENOBUFS`, which can occurs when calling `send_to()`
@@ -630,6 +681,17 @@ impl From<ErrorKind> for StdIoError {
     }
 }
 
+/// The error for a raw TCP socket, which Windows does not support.
+fn raw_tcp_unsupported() -> IoError {
+    IoError::Other(
+        StdIoError::new(
+            StdErrorKind::Unsupported,
+            "raw TCP sockets are not supported on Windows",
+        ),
+        IoOperation::NewSocket,
+    )
+}
+
 /// NOTE under Windows, we cannot use a bind connect/getsockname as "If the socket
 /// is using a connectionless protocol, the address may not be available until I/O
 /// occurs on the socket."  We use `SIO_ROUTING_INTERFACE_QUERY` instead.
@@ -23,6 +23,11 @@ where
     fn new_stream_socket_ipv4() -> Result<Self>;
     /// Create a IPv6/TCP socket for sending TCP probes.
     fn new_stream_socket_ipv6() -> Result<Self>;
+    /// Create a raw IPv4 socket for sending crafted TCP segments.
+    fn new_tcp_send_socket_ipv4() -> Result<Self>;
+    /// Create a raw IPv4/TCP socket for receiving TCP segments sent in response to crafted TCP
+    /// segments.
+    fn new_tcp_recv_socket_ipv4(addr: Ipv4Addr) -> Result<Self>;
     /// Create (non-raw) IPv4/UDP socket for local address validation.
     fn new_udp_dgram_socket_ipv4() -> Result<Self>;
     /// Create (non-raw) IPv6/UDP socket for local address validation.
@@ -34,18 +39,62 @@ where
     fn set_reuse_port(&mut self, reuse: bool) -> Result<()>;
     fn set_header_included(&mut self, included: bool) -> Result<()>;
     fn set_unicast_hops_v6(&mut self, hops: u8) -> Result<()>;
+    /// Set the `SO_MARK` (firewall mark) of the socket.
+    fn set_mark(&mut self, mark: u32) -> Result<()>;
+    /// Set the `IP_MTU_DISCOVER` (path MTU discovery) mode of an IPv4 socket.
+    fn set_mtu_discover(&mut self, mode: MtuDiscover) -> Result<()>;
     fn connect(&mut self, address: SocketAddr) -> Result<()>;
     fn send_to(&mut self, buf: &[u8], addr: SocketAddr) -> Result<()>;
     /// Returns true if the socket becomes readable before the timeout, false otherwise.
     fn is_readable(&mut self, timeout: Duration) -> Result<bool>;
     /// Returns true if the socket is currently writable, false otherwise.
     fn is_writable(&mut self) -> Result<bool>;
-    fn recv_from(&mut self, buf: &mut [u8]) -> Result<(usize, Option<SocketAddr>)>;
+    /// Receive a message along with its ancillary data.
+    ///
+    /// The ancillary data is read by the same `recvmsg` call as the message itself, rather than
+    /// by peeking at the next pending message, see `RecvInfo`.
+    fn recv_from(&mut self, buf: &mut [u8]) -> Result<(usize, Option<SocketAddr>, RecvInfo)>;
     fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
     fn shutdown(&mut self) -> Result<()>;
     fn peer_addr(&mut self) -> Result<Option<SocketAddr>>;
+    fn local_addr(&mut self) -> Result<Option<SocketAddr>>;
     fn take_error(&mut self) -> Result<Option<SocketError>>;
     fn icmp_error_info(&mut self) -> Result<IpAddr>;
+    /// Returns the time-to-live (or hop limit) of the last received response, if known.
+    ///
+    /// This is read from the `IP_RECVTTL` (IPv4) or `IPV6_RECVHOPLIMIT` (IPv6) ancillary data
+    /// for sockets where the outer IP header is not available.  This is `None` if the ancillary
+    /// data cannot be read, as is typically the case for connected TCP sockets.
+    fn recv_ttl(&mut self) -> Result<Option<u8>>;
+    /// Returns the index of the local interface the last response arrived on, if known.
+    ///
+    /// This is read from the `IP_PKTINFO` (IPv4) or `IPV6_PKTINFO` (IPv6) ancillary data.
+    fn recv_ifindex(&mut self) -> Result<Option<u32>>;
+}
+
+/// The ancillary data received with a message, see `Socket::recv_from`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct RecvInfo {
+    /// The hop limit (or time-to-live) of the message, if known.
+    ///
+    /// This is read from the `IPV6_RECVHOPLIMIT` (IPv6) or `IP_RECVTTL` (IPv4) ancillary data.
+    pub ttl: Option<u8>,
+    /// The traffic class (IPv6) of the message, if known.
+    ///
+    /// This is read from the `IPV6_RECVTCLASS` ancillary data.
+    pub tclass: Option<u8>,
+}
+
+/// The path MTU discovery mode of an IPv4 socket, see `Socket::set_mtu_discover`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MtuDiscover {
+    /// Set the `Don't fragment` bit and fail to send packets larger than the path MTU.
+    #[allow(dead_code)]
+    Do,
+    /// Do not set the `Don't fragment` bit and allow packets to be fragmented.
+    Dont,
+    /// Set the `Don't fragment` bit and ignore the path MTU.
+    Probe,
 }
 
 /// A socket error returned by `Socket::take_error`.
@@ -64,7 +113,7 @@ pub mod tests {
         ($packet: expr) => {
             move |buf: &mut [u8]| -> IoResult<usize> {
                 buf[..$packet.len()].copy_from_slice(&$packet);
-                Ok(buf.len())
+                Ok($packet.len())
             }
         };
     }
@@ -72,9 +121,12 @@ pub mod tests {
     #[macro_export]
     macro_rules! mocket_recv_from {
         ($packet: expr, $addr: expr) => {
-            move |buf: &mut [u8]| -> IoResult<(usize, Option<SocketAddr>)> {
+            $crate::mocket_recv_from!($packet, $addr, $crate::net::socket::RecvInfo::default())
+        };
+        ($packet: expr, $addr: expr, $info: expr) => {
+            move |buf: &mut [u8]| -> IoResult<(usize, Option<SocketAddr>, RecvInfo)> {
                 buf[..$packet.len()].copy_from_slice(&$packet);
-                Ok((buf.len(), Some($addr)))
+                Ok(($packet.len(), Some($addr), $info))
             }
         };
     }
@@ -1,14 +1,59 @@
 use crate::PortDirection;
-use crate::error::Error::InvalidSourceAddr;
+use crate::error::Error::{AddressFamilyMismatch, InvalidSourceAddr, MissingAddr};
 use crate::error::Result;
 use crate::net::platform::Platform;
 use crate::net::socket::Socket;
 use crate::types::Port;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 /// The port used for local address discovery if not dest port is available.
 const DISCOVERY_PORT: Port = Port(80);
 
+/// The class of an `IpAddr`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AddressClass {
+    /// The unspecified address.
+    Unspecified,
+    /// A loopback address.
+    Loopback,
+    /// A link-local address.
+    LinkLocal,
+    /// A private address (RFC 1918 for IPv4 or a unique local address for IPv6).
+    Private,
+    /// A shared address space address (RFC 6598) used for carrier-grade NAT.
+    Shared,
+    /// A multicast or broadcast address.
+    Multicast,
+    /// A publicly routable address.
+    Public,
+}
+
+/// The shared address space (`100.64.0.0/10`) defined in RFC 6598.
+const SHARED_ADDR_SPACE: (Ipv4Addr, u32) = (Ipv4Addr::new(100, 64, 0, 0), 0xffc0_0000);
+
+/// Classify `addr`.
+#[must_use]
+pub fn address_class(addr: IpAddr) -> AddressClass {
+    match addr {
+        IpAddr::V4(addr) if addr.is_unspecified() => AddressClass::Unspecified,
+        IpAddr::V4(addr) if addr.is_loopback() => AddressClass::Loopback,
+        IpAddr::V4(addr) if addr.is_link_local() => AddressClass::LinkLocal,
+        IpAddr::V4(addr) if addr.is_private() => AddressClass::Private,
+        IpAddr::V4(addr) if addr.is_multicast() || addr.is_broadcast() => AddressClass::Multicast,
+        IpAddr::V4(addr)
+            if u32::from(addr) & SHARED_ADDR_SPACE.1 == u32::from(SHARED_ADDR_SPACE.0) =>
+        {
+            AddressClass::Shared
+        }
+        IpAddr::V6(addr) if addr.is_unspecified() => AddressClass::Unspecified,
+        IpAddr::V6(addr) if addr.is_loopback() => AddressClass::Loopback,
+        IpAddr::V6(addr) if addr.is_unicast_link_local() => AddressClass::LinkLocal,
+        IpAddr::V6(addr) if addr.is_unique_local() => AddressClass::Private,
+        IpAddr::V6(addr) if addr.is_multicast() => AddressClass::Multicast,
+        IpAddr::V4(_) | IpAddr::V6(_) => AddressClass::Public,
+    }
+}
+
 /// Discover or validate a source address.
 pub struct SourceAddr;
 
@@ -26,6 +71,34 @@ impl SourceAddr {
         }
     }
 
+    /// Select the source `IpAddr` the OS would use to reach the target.
+    ///
+    /// A UDP socket of the same address family as the target is connected to
+    /// the target and the local address chosen by the kernel routing table is
+    /// read back.  This selects the correct source address on multi-homed
+    /// hosts.
+    ///
+    /// Note that no packets are transmitted by this method.
+    pub fn select_source_addr<S: Socket>(target_addr: IpAddr, port: u16) -> Result<IpAddr> {
+        let mut socket = match target_addr {
+            IpAddr::V4(_) => S::new_udp_dgram_socket_ipv4(),
+            IpAddr::V6(_) => S::new_udp_dgram_socket_ipv6(),
+        }?;
+        socket.connect(SocketAddr::new(target_addr, port))?;
+        Ok(socket.local_addr()?.ok_or(MissingAddr)?.ip())
+    }
+
+    /// Check that the source `IpAddr` is of the same address family as the target `IpAddr`.
+    pub const fn check_family(source_addr: IpAddr, target_addr: IpAddr) -> Result<()> {
+        match (source_addr, target_addr) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => Ok(()),
+            _ => Err(AddressFamilyMismatch {
+                source_addr,
+                target_addr,
+            }),
+        }
+    }
+
     /// Validate that we can bind to the source `IpAddr`.
     pub fn validate<S: Socket>(source_addr: IpAddr) -> Result<IpAddr> {
         let mut socket = match source_addr {
@@ -49,9 +122,44 @@ mod tests {
     use mockall::predicate;
     use std::str::FromStr;
     use std::sync::Mutex;
+    use test_case::test_case;
 
     static MTX: Mutex<()> = Mutex::new(());
 
+    #[test_case("0.0.0.0", AddressClass::Unspecified; "ipv4 unspecified")]
+    #[test_case("127.0.0.1", AddressClass::Loopback; "ipv4 loopback")]
+    #[test_case("169.254.1.1", AddressClass::LinkLocal; "ipv4 link local")]
+    #[test_case("10.1.2.3", AddressClass::Private; "ipv4 private 10")]
+    #[test_case("172.16.0.1", AddressClass::Private; "ipv4 private 172")]
+    #[test_case("192.168.0.1", AddressClass::Private; "ipv4 private 192")]
+    #[test_case("100.64.0.1", AddressClass::Shared; "ipv4 shared")]
+    #[test_case("100.128.0.1", AddressClass::Public; "ipv4 after shared")]
+    #[test_case("224.0.0.1", AddressClass::Multicast; "ipv4 multicast")]
+    #[test_case("255.255.255.255", AddressClass::Multicast; "ipv4 broadcast")]
+    #[test_case("1.1.1.1", AddressClass::Public; "ipv4 public")]
+    #[test_case("::", AddressClass::Unspecified; "ipv6 unspecified")]
+    #[test_case("::1", AddressClass::Loopback; "ipv6 loopback")]
+    #[test_case("fe80::1", AddressClass::LinkLocal; "ipv6 link local")]
+    #[test_case("fd00::1", AddressClass::Private; "ipv6 unique local")]
+    #[test_case("ff02::1", AddressClass::Multicast; "ipv6 multicast")]
+    #[test_case("2606:4700::1111", AddressClass::Public; "ipv6 public")]
+    fn test_address_class(addr: &str, expected: AddressClass) {
+        assert_eq!(expected, address_class(IpAddr::from_str(addr).unwrap()));
+    }
+
+    #[test_case("1.2.3.4", "5.6.7.8", true; "ipv4 source ipv4 target")]
+    #[test_case("::1", "2606:4700::1111", true; "ipv6 source ipv6 target")]
+    #[test_case("1.2.3.4", "2606:4700::1111", false; "ipv4 source ipv6 target")]
+    #[test_case("::1", "5.6.7.8", false; "ipv6 source ipv4 target")]
+    fn test_check_family(source_addr: &str, target_addr: &str, expected: bool) {
+        let source_addr = IpAddr::from_str(source_addr).unwrap();
+        let target_addr = IpAddr::from_str(target_addr).unwrap();
+        assert_eq!(
+            expected,
+            SourceAddr::check_family(source_addr, target_addr).is_ok()
+        );
+    }
+
     #[test]
     fn test_discover_local_addr_default_port() {
         let _m = MTX.lock();
@@ -143,6 +251,84 @@ mod tests {
         assert_eq!(expected_src, src_addr);
     }
 
+    #[test]
+    fn test_select_source_addr_ipv4() {
+        let _m = MTX.lock();
+
+        let target = IpAddr::from_str("1.2.3.4").unwrap();
+        let expected_connect_addr = SocketAddr::new(target, 80);
+        let expected_src = IpAddr::from_str("192.168.0.1").unwrap();
+
+        let ctx = MockSocket::new_udp_dgram_socket_ipv4_context();
+        ctx.expect().times(1).returning(move || {
+            let mut mocket = MockSocket::new();
+            let mut seq = mockall::Sequence::new();
+            mocket
+                .expect_connect()
+                .with(predicate::eq(expected_connect_addr))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_| Ok(()));
+            mocket
+                .expect_local_addr()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(move || Ok(Some(SocketAddr::new(expected_src, 50123))));
+            Ok(mocket)
+        });
+
+        let src_addr = SourceAddr::select_source_addr::<MockSocket>(target, 80).unwrap();
+        assert_eq!(expected_src, src_addr);
+    }
+
+    #[test]
+    fn test_select_source_addr_ipv6() {
+        let _m = MTX.lock();
+
+        let target = IpAddr::from_str("2a00:1450:4009:815::200e").unwrap();
+        let expected_connect_addr = SocketAddr::new(target, 33434);
+        let expected_src = IpAddr::from_str("fe80::1").unwrap();
+
+        let ctx = MockSocket::new_udp_dgram_socket_ipv6_context();
+        ctx.expect().times(1).returning(move || {
+            let mut mocket = MockSocket::new();
+            let mut seq = mockall::Sequence::new();
+            mocket
+                .expect_connect()
+                .with(predicate::eq(expected_connect_addr))
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_| Ok(()));
+            mocket
+                .expect_local_addr()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(move || Ok(Some(SocketAddr::new(expected_src, 50123))));
+            Ok(mocket)
+        });
+
+        let src_addr = SourceAddr::select_source_addr::<MockSocket>(target, 33434).unwrap();
+        assert_eq!(expected_src, src_addr);
+    }
+
+    #[test]
+    fn test_select_source_addr_missing() {
+        let _m = MTX.lock();
+
+        let target = IpAddr::from_str("1.2.3.4").unwrap();
+
+        let ctx = MockSocket::new_udp_dgram_socket_ipv4_context();
+        ctx.expect().times(1).returning(move || {
+            let mut mocket = MockSocket::new();
+            mocket.expect_connect().times(1).returning(|_| Ok(()));
+            mocket.expect_local_addr().times(1).returning(|| Ok(None));
+            Ok(mocket)
+        });
+
+        let err = SourceAddr::select_source_addr::<MockSocket>(target, 80).unwrap_err();
+        assert!(matches!(err, MissingAddr));
+    }
+
     #[test]
     fn test_validate_ipv4() {
         let _m = MTX.lock();
@@ -0,0 +1,52 @@
+use crate::{Probe, ProbeComplete, Round};
+use std::fmt::Debug;
+
+/// Observe the progress of a trace.
+///
+/// A `TraceObserver` may be registered with a [`crate::Tracer`] via
+/// [`crate::Builder::observer`] to receive a callback as each probe is sent,
+/// as each response is received and as each round completes.
+///
+/// All methods have a default no-op implementation and so implementors need
+/// only override the callbacks they are interested in.
+///
+/// The callbacks are invoked on the tracer thread and so should return
+/// promptly to avoid delaying the trace.
+///
+/// # Example
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// # use std::net::IpAddr;
+/// # use std::str::FromStr;
+/// # use std::sync::Arc;
+/// use trippy_core::{Builder, Round, TraceObserver};
+///
+/// #[derive(Debug)]
+/// struct Progress;
+///
+/// impl TraceObserver for Progress {
+///     fn on_round_complete(&self, round: &Round<'_>) {
+///         println!("round complete: {:?}", round.reason);
+///     }
+/// }
+///
+/// let addr = IpAddr::from_str("1.1.1.1")?;
+/// Builder::new(addr)
+///     .observer(Arc::new(Progress))
+///     .max_rounds(Some(3))
+///     .build()?
+///     .run()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait TraceObserver: Debug + Send + Sync {
+    /// Called after a probe has been successfully sent.
+    fn on_probe_sent(&self, _probe: &Probe) {}
+
+    /// Called after a response has been received and matched to a probe.
+    fn on_response(&self, _probe: &ProbeComplete) {}
+
+    /// Called after a round of tracing has completed.
+    fn on_round_complete(&self, _round: &Round<'_>) {}
+}
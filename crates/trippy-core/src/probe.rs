@@ -101,6 +101,7 @@ impl Probe {
         expected_udp_checksum: Option<Checksum>,
         actual_udp_checksum: Option<Checksum>,
         extensions: Option<Extensions>,
+        quoted_payload: Option<Vec<u8>>,
     ) -> ProbeComplete {
         ProbeComplete {
             sequence: self.sequence,
@@ -117,6 +118,9 @@ impl Probe {
             expected_udp_checksum,
             actual_udp_checksum,
             extensions,
+            quoted_payload,
+            extra_hosts: Vec::new(),
+            response_tos: None,
         }
     }
 
@@ -174,6 +178,29 @@ pub struct ProbeComplete {
     pub actual_udp_checksum: Option<Checksum>,
     /// The ICMP response extensions.
     pub extensions: Option<Extensions>,
+    /// The original datagram quoted in the ICMP response.
+    ///
+    /// This is only populated when using `IcmpQuotedPayloadMode::Enabled`.
+    pub quoted_payload: Option<Vec<u8>>,
+    /// Any additional hosts which responded to the probe.
+    ///
+    /// This is only populated when using `MultiResponseMode::All`.
+    pub extra_hosts: Vec<IpAddr>,
+    /// The type of service (DSCP/ECN) of the response itself, if known.
+    ///
+    /// This may differ from `tos` where a router remarks the DSCP of the response.
+    pub response_tos: Option<TypeOfService>,
+}
+
+impl ProbeComplete {
+    /// Set the type of service (DSCP/ECN) of the response itself.
+    #[must_use]
+    pub fn with_response_tos(self, response_tos: Option<TypeOfService>) -> Self {
+        Self {
+            response_tos,
+            ..self
+        }
+    }
 }
 
 /// A failed network tracing probe.
@@ -236,6 +263,40 @@ impl Response {
             | Self::TcpRefused(data) => data,
         }
     }
+
+    /// Set the type of service (DSCP/ECN) of the response itself.
+    #[must_use]
+    pub fn with_response_tos(self, response_tos: Option<TypeOfService>) -> Self {
+        match self {
+            Self::TimeExceeded(data, code, exts) => {
+                Self::TimeExceeded(data.with_response_tos(response_tos), code, exts)
+            }
+            Self::DestinationUnreachable(data, code, exts) => {
+                Self::DestinationUnreachable(data.with_response_tos(response_tos), code, exts)
+            }
+            Self::EchoReply(data, code) => {
+                Self::EchoReply(data.with_response_tos(response_tos), code)
+            }
+            Self::TcpReply(data) => Self::TcpReply(data.with_response_tos(response_tos)),
+            Self::TcpRefused(data) => Self::TcpRefused(data.with_response_tos(response_tos)),
+        }
+    }
+
+    /// Set the time-to-live (or hop limit) the response was received with.
+    #[must_use]
+    pub fn with_ttl(self, ttl: Option<u8>) -> Self {
+        match self {
+            Self::TimeExceeded(data, code, exts) => {
+                Self::TimeExceeded(data.with_ttl(ttl), code, exts)
+            }
+            Self::DestinationUnreachable(data, code, exts) => {
+                Self::DestinationUnreachable(data.with_ttl(ttl), code, exts)
+            }
+            Self::EchoReply(data, code) => Self::EchoReply(data.with_ttl(ttl), code),
+            Self::TcpReply(data) => Self::TcpReply(data.with_ttl(ttl)),
+            Self::TcpRefused(data) => Self::TcpRefused(data.with_ttl(ttl)),
+        }
+    }
 }
 
 /// The ICMP extensions for a probe response.
@@ -272,6 +333,14 @@ pub struct MplsLabelStackMember {
     pub ttl: u8,
 }
 
+impl MplsLabelStackMember {
+    /// Whether this is the last member of the label stack.
+    #[must_use]
+    pub const fn is_bottom_of_stack(&self) -> bool {
+        self.bos != 0
+    }
+}
+
 /// An unknown ICMP extension.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct UnknownExtension {
@@ -289,6 +358,14 @@ pub struct ResponseData {
     pub addr: IpAddr,
     /// Protocol specific response information.
     pub proto_resp: ProtocolResponse,
+    /// The time-to-live (or hop limit) the response was received with, if known.
+    pub ttl: Option<u8>,
+    /// The index of the local interface the response arrived on, if known.
+    pub ifindex: Option<u32>,
+    /// The original datagram quoted in an ICMP error response, if retained.
+    pub quoted_payload: Option<Vec<u8>>,
+    /// The type of service (DSCP/ECN) of the response itself, if known.
+    pub response_tos: Option<TypeOfService>,
 }
 
 impl ResponseData {
@@ -297,6 +374,40 @@ impl ResponseData {
             recv,
             addr,
             proto_resp,
+            ttl: None,
+            ifindex: None,
+            quoted_payload: None,
+            response_tos: None,
+        }
+    }
+
+    /// Set the time-to-live (or hop limit) the response was received with.
+    #[must_use]
+    pub fn with_ttl(self, ttl: Option<u8>) -> Self {
+        Self { ttl, ..self }
+    }
+
+    /// Set the index of the local interface the response arrived on.
+    #[must_use]
+    pub fn with_ifindex(self, ifindex: Option<u32>) -> Self {
+        Self { ifindex, ..self }
+    }
+
+    /// Set the original datagram quoted in an ICMP error response.
+    #[must_use]
+    pub fn with_quoted_payload(self, quoted_payload: Option<Vec<u8>>) -> Self {
+        Self {
+            quoted_payload,
+            ..self
+        }
+    }
+
+    /// Set the type of service (DSCP/ECN) of the response itself.
+    #[must_use]
+    pub fn with_response_tos(self, response_tos: Option<TypeOfService>) -> Self {
+        Self {
+            response_tos,
+            ..self
         }
     }
 }
@@ -327,6 +438,21 @@ pub struct IcmpProtocolResponse {
     pub sequence: u16,
     /// The type of service (DSCP/ECN) of the original datagram.
     pub tos: Option<TypeOfService>,
+    /// The IP identification of the original datagram, if known.
+    ///
+    /// IPv4 ICMP probes sent via a raw socket carry an IP identification derived from the trace
+    /// identifier and sequence, see [`icmp_ip_identification`], which is used to match the
+    /// response if a router rewrote the ICMP identifier.
+    pub ip_identification: Option<u16>,
+}
+
+/// The IP identification of an IPv4 ICMP probe with the trace `identifier` and `sequence`.
+///
+/// The identification differs for each probe of a trace, as required of datagrams which may be
+/// fragmented, whilst still allowing the trace identifier to be recovered from it given the
+/// sequence.
+pub const fn icmp_ip_identification(identifier: TraceId, sequence: Sequence) -> u16 {
+    identifier.0.wrapping_add(sequence.0)
 }
 
 impl IcmpProtocolResponse {
@@ -335,6 +461,15 @@ impl IcmpProtocolResponse {
             identifier,
             sequence,
             tos,
+            ip_identification: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_ip_identification(self, ip_identification: Option<u16>) -> Self {
+        Self {
+            ip_identification,
+            ..self
         }
     }
 }
@@ -1,16 +1,44 @@
-use crate::config::StateConfig;
+use crate::config::{AddrTieBreak, StateConfig};
 use crate::constants::MAX_TTL;
 use crate::flows::{Flow, FlowId, FlowRegistry};
+use crate::strategy::{StopReason, is_target_unreachable_code};
 use crate::{
-    Dscp, Ecn, Extensions, IcmpPacketType, ProbeStatus, Round, RoundId, TimeToLive, TypeOfService,
+    Dscp, Ecn, Extensions, IcmpPacketType, NegativeRttMode, ProbeStatus, Round, RoundId,
+    TimeToLive, TypeOfService,
 };
 use indexmap::IndexMap;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::iter::once;
 use std::net::IpAddr;
-use std::time::Duration;
+use std::num::NonZeroUsize;
+use std::time::{Duration, SystemTime};
 use tracing::instrument;
 
+/// The minimum number of response gaps required before rate limiting is considered.
+const MIN_RATE_LIMIT_RECV_GAPS: usize = 5;
+
+/// The weight of the source address signal of the destination confidence.
+const CONFIDENCE_SOURCE_WEIGHT: f64 = 0.6;
+
+/// The weight of the response type signal of the destination confidence.
+const CONFIDENCE_RESPONSE_WEIGHT: f64 = 0.2;
+
+/// The weight of the TTL consistency signal of the destination confidence.
+const CONFIDENCE_TTL_WEIGHT: f64 = 0.2;
+
+/// The weight of the address consistency component of the path stability score.
+const STABILITY_ADDR_WEIGHT: f64 = 0.4;
+
+/// The weight of the delivery component of the path stability score.
+const STABILITY_DELIVERY_WEIGHT: f64 = 0.4;
+
+/// The weight of the round trip time consistency component of the path stability score.
+const STABILITY_RTT_WEIGHT: f64 = 0.2;
+
+/// The minimum number of times the address of a hop must change back to a prior address within
+/// the recorded rounds for the hop to be considered to be flapping.
+const FLAP_MIN_OSCILLATIONS: usize = 2;
+
 /// The state of a trace.
 #[derive(Debug, Clone, Default)]
 pub struct State {
@@ -24,6 +52,14 @@ pub struct State {
     registry: FlowRegistry,
     /// Tracing error message.
     error: Option<String>,
+    /// Is the tracing error transient?
+    error_transient: bool,
+    /// The reason the tracer stopped, if it has.
+    stop_reason: Option<StopReason>,
+    /// The time the first probe of the trace was sent.
+    start_time: Option<SystemTime>,
+    /// The time the last probe of the trace was sent or a response received.
+    end_time: Option<SystemTime>,
 }
 
 impl State {
@@ -33,13 +69,22 @@ impl State {
         Self {
             state: once((
                 Self::default_flow_id(),
-                FlowState::new(state_config.max_samples),
+                FlowState::new(
+                    state_config.max_samples,
+                    state_config.negative_rtt_mode,
+                    state_config.window,
+                    state_config.max_addrs,
+                ),
             ))
             .collect::<HashMap<FlowId, FlowState>>(),
             round_flow_id: Self::default_flow_id(),
             state_config,
             registry: FlowRegistry::new(),
             error: None,
+            error_transient: false,
+            stop_reason: None,
+            start_time: None,
+            end_time: None,
         }
     }
 
@@ -58,7 +103,7 @@ impl State {
     /// Information about each hop for a given flow.
     #[must_use]
     pub fn hops_for_flow(&self, flow_id: FlowId) -> &[Hop] {
-        self.state[&flow_id].hops()
+        self.flow_state(flow_id).hops()
     }
 
     /// Is a given `Hop` the target hop for a given flow?
@@ -69,31 +114,92 @@ impl State {
     /// will be one greater than the `ttl` of the last host which did respond.
     #[must_use]
     pub fn is_target(&self, hop: &Hop, flow_id: FlowId) -> bool {
-        self.state[&flow_id].is_target(hop)
+        self.flow_state(flow_id).is_target(hop)
     }
 
     /// Is a given `Hop` in the current round for a given flow?
     #[must_use]
     pub fn is_in_round(&self, hop: &Hop, flow_id: FlowId) -> bool {
-        self.state[&flow_id].is_in_round(hop)
+        self.flow_state(flow_id).is_in_round(hop)
     }
 
     /// Return the target `Hop` for a given flow.
     #[must_use]
     pub fn target_hop(&self, flow_id: FlowId) -> &Hop {
-        self.state[&flow_id].target_hop()
+        self.flow_state(flow_id).target_hop()
     }
 
     /// The current round of tracing for a given flow.
     #[must_use]
     pub fn round(&self, flow_id: FlowId) -> Option<usize> {
-        self.state[&flow_id].round()
+        self.flow_state(flow_id).round()
     }
 
     /// The total rounds of tracing for a given flow.
     #[must_use]
     pub fn round_count(&self, flow_id: FlowId) -> usize {
-        self.state[&flow_id].round_count()
+        self.flow_state(flow_id).round_count()
+    }
+
+    /// The end-to-end round trip time to the destination for a given flow.
+    #[must_use]
+    pub fn destination_rtt(&self, flow_id: FlowId) -> &DestinationRtt {
+        self.flow_state(flow_id).destination_rtt()
+    }
+
+    /// Has the destination been reached for a given flow?
+    ///
+    /// The destination is reached once any round has completed because the target responded,
+    /// such as with an ICMP `EchoReply`, an ICMP `DestinationUnreachable` (port unreachable) or a
+    /// TCP `RST`.
+    #[must_use]
+    pub fn destination_reached(&self, flow_id: FlowId) -> bool {
+        self.flow_state(flow_id).destination_reached()
+    }
+
+    /// Has the destination been silent for a given flow?
+    ///
+    /// The destination is silent once a round has completed without a response from the target
+    /// for the configured maximum number of consecutive rounds, after which the trace is finished.
+    #[must_use]
+    pub fn destination_silent(&self, flow_id: FlowId) -> bool {
+        self.flow_state(flow_id).destination_silent()
+    }
+
+    /// The confidence, between 0.0 and 1.0, that the target hop of a given flow is the true
+    /// destination `target_addr`, rather than a middlebox responding on its behalf.
+    ///
+    /// The score is the weighted sum of three signals from the target hop:
+    ///
+    /// - source address (weight 0.6): the fraction of responses received from `target_addr`
+    /// - response type (weight 0.2): the last response is one the destination itself sends, an
+    ///   ICMP `EchoReply`, an ICMP `DestinationUnreachable` port unreachable (or protocol
+    ///   unreachable for IPv4) or a TCP reply
+    /// - TTL consistency (weight 0.2): `target_addr` did not respond at any lower TTL
+    ///
+    /// As the source address is the strongest signal, a destination determined only from
+    /// responses from another address has a confidence of at most 0.4.  The confidence is 0.0 if
+    /// the destination has not been reached.
+    #[must_use]
+    pub fn destination_confidence(&self, flow_id: FlowId, target_addr: IpAddr) -> f64 {
+        self.flow_state(flow_id).destination_confidence(target_addr)
+    }
+
+    /// The stability, between 0.0 and 100.0, of the path of a given flow over the session.
+    ///
+    /// The score is 100 times the weighted sum of three components:
+    ///
+    /// - address consistency (weight 0.4): the mean, over all hops which responded, of the
+    ///   fraction of responses received from the most common address of the hop
+    /// - delivery (weight 0.4): the fraction of probes to the target hop which were not lost
+    /// - RTT consistency (weight 0.2): one minus the coefficient of variation of the round trip
+    ///   time of the target hop, but not less than 0.0, or 1.0 if there are fewer than two samples
+    ///
+    /// A path which never changes, loses nothing and has a constant round trip time therefore
+    /// scores 100.0.  The score is 0.0 if no hop has responded.
+    #[must_use]
+    pub fn path_stability(&self, flow_id: FlowId) -> f64 {
+        self.flow_state(flow_id).path_stability()
     }
 
     /// The `FlowId` for the current round.
@@ -118,6 +224,47 @@ impl State {
         self.error = error;
     }
 
+    /// Is the error for the trace transient, such that retrying the trace may succeed?
+    #[must_use]
+    pub const fn is_error_transient(&self) -> bool {
+        self.error_transient
+    }
+
+    pub fn set_error_transient(&mut self, transient: bool) {
+        self.error_transient = transient;
+    }
+
+    /// The reason the tracer stopped, or `None` if it is still running.
+    #[must_use]
+    pub const fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// Has the tracer stopped?
+    ///
+    /// The tracer has stopped once it has finished tracing, for any `StopReason`, or failed with
+    /// an error.
+    #[must_use]
+    pub const fn finished(&self) -> bool {
+        self.stop_reason.is_some() || self.error.is_some()
+    }
+
+    pub fn set_stop_reason(&mut self, stop_reason: Option<StopReason>) {
+        self.stop_reason = stop_reason;
+    }
+
+    /// The time the first probe of the trace was sent, if any.
+    #[must_use]
+    pub const fn start_time(&self) -> Option<SystemTime> {
+        self.start_time
+    }
+
+    /// The time the last probe of the trace was sent or a response was received, if any.
+    #[must_use]
+    pub const fn end_time(&self) -> Option<SystemTime> {
+        self.end_time
+    }
+
     /// The maximum number of samples to record per hop.
     #[must_use]
     pub const fn max_samples(&self) -> usize {
@@ -133,6 +280,8 @@ impl State {
     /// Update the tracing state from a `TracerRound`.
     #[instrument(skip(self, round), level = "trace")]
     pub fn update_from_round(&mut self, round: &Round<'_>) {
+        self.update_times(round);
+        self.update_trace_flow(Self::default_flow_id(), round);
         let flow = Flow::from_hops(
             round
                 .probes
@@ -144,21 +293,50 @@ impl State {
                 })
                 .take(usize::from(round.largest_ttl.0)),
         );
-        self.update_trace_flow(Self::default_flow_id(), round);
         if self.registry.flows().len() < self.state_config.max_flows {
             let flow_id = self.registry.register(flow);
             self.round_flow_id = flow_id;
-            self.update_trace_flow(flow_id, round);
+            if self.state_config.flow_aware {
+                self.update_trace_flow(flow_id, round);
+            }
+        }
+    }
+
+    /// The tracing state for a given flow.
+    ///
+    /// If the state is not flow aware then only the default flow is recorded and so the state of
+    /// the default flow is returned for every flow.
+    fn flow_state(&self, flow_id: FlowId) -> &FlowState {
+        self.state
+            .get(&flow_id)
+            .unwrap_or_else(|| &self.state[&Self::default_flow_id()])
+    }
+
+    /// Update the start and end times of the trace from the probes of `round`.
+    fn update_times(&mut self, round: &Round<'_>) {
+        for probe in round.probes {
+            let (sent, last) = match probe {
+                ProbeStatus::Complete(complete) => (complete.sent, complete.received),
+                ProbeStatus::Awaited(awaited) => (awaited.sent, awaited.sent),
+                ProbeStatus::Failed(failed) => (failed.sent, failed.sent),
+                ProbeStatus::NotSent | ProbeStatus::Skipped => continue,
+            };
+            self.start_time = Some(self.start_time.map_or(sent, |start| start.min(sent)));
+            self.end_time = Some(self.end_time.map_or(last, |end| end.max(last)));
         }
     }
 
     #[instrument(skip(self, round), level = "trace")]
     fn update_trace_flow(&mut self, flow_id: FlowId, round: &Round<'_>) {
-        let flow_trace = self
-            .state
-            .entry(flow_id)
-            .or_insert_with(|| FlowState::new(self.state_config.max_samples));
-        flow_trace.update_from_round(round);
+        let flow_trace = self.state.entry(flow_id).or_insert_with(|| {
+            FlowState::new(
+                self.state_config.max_samples,
+                self.state_config.negative_rtt_mode,
+                self.state_config.window,
+                self.state_config.max_addrs,
+            )
+        });
+        flow_trace.update_from_round(round, self.start_time);
     }
 }
 
@@ -169,12 +347,16 @@ pub struct Hop {
     ttl: u8,
     /// The addrs of this hop and associated counts.
     addrs: IndexMap<IpAddr, usize>,
+    /// The addrs of this hop, least recently seen first.
+    recent_addrs: Vec<IpAddr>,
     /// The total probes sent for this hop.
     total_sent: usize,
     /// The total probes received for this hop.
     total_recv: usize,
     /// The total probes that failed for this hop.
     total_failed: usize,
+    /// The total probes responses discarded for this hop.
+    total_discarded: usize,
     /// The total forward loss for this hop.
     total_forward_lost: usize,
     /// The total backward loss for this hop.
@@ -207,10 +389,46 @@ pub struct Hop {
     last_nat_status: NatStatus,
     /// The history of round trip times across the last N rounds.
     samples: Vec<Duration>,
+    /// The round and flow of the probe of each of the `samples`, most recent first.
+    sample_probes: Vec<SampleProbe>,
+    /// The address which responded for each flow in each of the last N rounds with a response,
+    /// most recent first.
+    round_addrs: Vec<RoundAddr>,
     /// The type of service (DSCP/ECN) for this hop.
     tos: Option<TypeOfService>,
+    /// The type of service (DSCP/ECN) of the latest response from this hop.
+    response_tos: Option<TypeOfService>,
     /// The ICMP extensions for this hop.
     extensions: Option<Extensions>,
+    /// The original datagram quoted in the latest ICMP error response from this hop.
+    last_quoted_payload: Option<Vec<u8>>,
+    /// The time the last probe was sent for this hop.
+    last_sent: Option<SystemTime>,
+    /// The time the last response was received for this hop.
+    last_received: Option<SystemTime>,
+    /// The time from the start of the trace to the first response received for this hop.
+    time_to_first: Option<Duration>,
+    /// The number of responses received before the response to an earlier probe in the same round
+    /// for this hop.
+    total_reordered: usize,
+    /// The number of consecutive probes without a response up to the latest probe for this hop.
+    current_loss_run: usize,
+    /// The largest number of consecutive probes without a response for this hop.
+    max_loss_run: usize,
+    /// The sum of the gaps between consecutive probes sent for this hop.
+    total_send_gap: Duration,
+    /// The number of gaps between consecutive probes sent for this hop.
+    send_gaps: usize,
+    /// The sum of the gaps between consecutive responses received for this hop.
+    total_recv_gap: Duration,
+    /// The number of gaps between consecutive responses received for this hop.
+    recv_gaps: usize,
+    /// The smallest gap between consecutive responses received for this hop.
+    min_recv_gap: Option<Duration>,
+    /// The contributions of each probe within the window of recent rounds, oldest first.
+    ///
+    /// Only recorded if a window is configured.
+    window: VecDeque<WindowEntry>,
     mean: f64,
     m2: f64,
 }
@@ -231,6 +449,31 @@ impl Hop {
         self.addrs.iter()
     }
 
+    /// The primary address of this hop, if any.
+    ///
+    /// This is the address which responded most often, with ties broken by `tie_break`.
+    #[must_use]
+    pub fn best_addr(&self, tie_break: AddrTieBreak) -> Option<IpAddr> {
+        let max = self.addrs.values().max()?;
+        let mut candidates = self
+            .addrs
+            .iter()
+            .filter(|&(_, count)| count == max)
+            .map(|(addr, _)| *addr);
+        match tie_break {
+            AddrTieBreak::LowestIp => candidates.min(),
+            AddrTieBreak::FirstSeen => candidates.next(),
+            AddrTieBreak::MostRecent => candidates
+                .max_by_key(|addr| self.recent_addrs.iter().position(|recent| recent == addr)),
+        }
+    }
+
+    /// The most recently seen address of this hop, if any.
+    #[must_use]
+    pub fn last_addr(&self) -> Option<IpAddr> {
+        self.recent_addrs.last().copied()
+    }
+
     /// The number of unique address observed for this time-to-live.
     #[must_use]
     pub fn addr_count(&self) -> usize {
@@ -267,17 +510,57 @@ impl Hop {
         self.total_failed
     }
 
+    /// The total number of probe responses discarded due to a negative round trip time.
+    ///
+    /// Discarded responses are not included in the received count or the round trip time
+    /// statistics, nor are they considered lost.
+    #[must_use]
+    pub const fn total_discarded(&self) -> usize {
+        self.total_discarded
+    }
+
     /// The % of packets that are lost.
     #[must_use]
     pub fn loss_pct(&self) -> f64 {
         if self.total_sent > 0 {
-            let lost = self.total_sent - self.total_recv;
+            let lost = self.total_sent - self.total_recv - self.total_discarded;
             lost as f64 / self.total_sent as f64 * 100_f64
         } else {
             0_f64
         }
     }
 
+    /// The fraction of responses which arrived out of order.
+    ///
+    /// A response is out of order if it was received before the response to a probe with a lower
+    /// sequence number in the same round, such as may occur due to packet reordering or differing
+    /// timing across multiple paths.
+    #[must_use]
+    pub fn reorder_ratio(&self) -> f64 {
+        if self.total_recv > 0 {
+            self.total_reordered as f64 / self.total_recv as f64
+        } else {
+            0_f64
+        }
+    }
+
+    /// The largest number of consecutive rounds without a response for this hop.
+    ///
+    /// Isolated losses are often benign, such as due to ICMP rate limiting, whereas a long run of
+    /// consecutive losses may indicate an outage.
+    #[must_use]
+    pub const fn max_loss_run(&self) -> usize {
+        self.max_loss_run
+    }
+
+    /// The number of consecutive rounds without a response for this hop, up to the latest round.
+    ///
+    /// This is zero if a response was received in the latest round.
+    #[must_use]
+    pub const fn current_loss_run(&self) -> usize {
+        self.current_loss_run
+    }
+
     /// The % of packets that are lost forward.
     #[must_use]
     pub fn forward_loss_pct(&self) -> f64 {
@@ -312,6 +595,16 @@ impl Hop {
         self.best.map(|last| last.as_secs_f64() * 1000_f64)
     }
 
+    /// The time from the start of the trace to the first response received for this hop.
+    ///
+    /// Unlike the round trip time statistics, this reflects how long the trace ran before this
+    /// hop was first seen to respond.
+    #[must_use]
+    pub fn time_to_first_ms(&self) -> Option<f64> {
+        self.time_to_first
+            .map(|time_to_first| time_to_first.as_secs_f64() * 1000_f64)
+    }
+
     /// The duration of the worst probe observed.
     #[must_use]
     pub fn worst_ms(&self) -> Option<f64> {
@@ -338,6 +631,195 @@ impl Hop {
         }
     }
 
+    /// The number of samples used to calculate the average and standard deviation.
+    #[must_use]
+    pub const fn sample_count(&self) -> usize {
+        self.total_recv
+    }
+
+    /// Is the loss at this hop consistent with ICMP rate limiting?
+    ///
+    /// This is a heuristic: a hop is considered likely to be rate limited if it has lost probes
+    /// and its responses arrive at a steady rate which is well below the rate at which probes are
+    /// sent, i.e. the receive rate has plateaued regardless of the send rate.
+    #[must_use]
+    pub fn is_icmp_rate_limited(&self) -> bool {
+        if self.recv_gaps < MIN_RATE_LIMIT_RECV_GAPS
+            || self.send_gaps == 0
+            || self.total_recv >= self.total_sent
+        {
+            return false;
+        }
+        let mean_send_gap = self.total_send_gap.as_secs_f64() / self.send_gaps as f64;
+        let mean_recv_gap = self.total_recv_gap.as_secs_f64() / self.recv_gaps as f64;
+        let min_recv_gap = self.min_recv_gap.unwrap_or_default().as_secs_f64();
+        let is_throttled = mean_send_gap * 2_f64 <= mean_recv_gap;
+        let is_steady = min_recv_gap >= mean_recv_gap * 0.9;
+        is_throttled && is_steady
+    }
+
+    /// The standard error of the mean of all probes.
+    ///
+    /// Returns `None` if fewer than two samples have been recorded.
+    #[must_use]
+    pub fn stderr_ms(&self) -> Option<f64> {
+        if self.total_recv > 1 {
+            Some(self.stddev_ms() / (self.total_recv as f64).sqrt())
+        } else {
+            None
+        }
+    }
+
+    /// The coefficient of variation of the round trip time of all probes.
+    ///
+    /// This is the ratio of the standard deviation to the mean.  Returns `None` if fewer than two
+    /// samples have been recorded.
+    #[must_use]
+    pub fn cv(&self) -> Option<f64> {
+        let avg = self.avg_ms();
+        if self.total_recv > 1 && avg > 0_f64 {
+            Some(self.stddev_ms() / avg)
+        } else {
+            None
+        }
+    }
+
+    /// Is the round trip time variance at this hop consistent with load balancing?
+    ///
+    /// This is a heuristic: a single hop address which fronts several load balanced devices
+    /// typically has a round trip time coefficient of variation which exceeds `cv_threshold`.
+    #[must_use]
+    pub fn is_likely_load_balanced(&self, cv_threshold: f64) -> bool {
+        self.cv().is_some_and(|cv| cv > cv_threshold)
+    }
+
+    /// The number of times the address of this hop changed back to the address it had two
+    /// rounds earlier for the same flow, i.e. `A` -> `B` -> `A`, within the last N rounds.
+    ///
+    /// The addresses of each flow, identified by the source and destination ports of its probes,
+    /// are compared separately.
+    #[must_use]
+    pub fn oscillations(&self) -> usize {
+        self.flow_round_addrs()
+            .values()
+            .map(|addrs| Self::flow_oscillations(addrs))
+            .sum()
+    }
+
+    /// Is the route at this hop flapping?
+    ///
+    /// A hop is flapping if, for any single flow, its address oscillates back and forth between
+    /// two addresses across the last N rounds.  This is distinct from a load balanced (ECMP) hop
+    /// which responds from a different address for each flow, or which may respond from several
+    /// addresses without a recurring alternation between exactly two of them.
+    #[must_use]
+    pub fn is_flapping(&self) -> bool {
+        self.flow_round_addrs().values().any(|addrs| {
+            let distinct = addrs.iter().collect::<HashSet<_>>().len();
+            distinct == 2 && Self::flow_oscillations(addrs) >= FLAP_MIN_OSCILLATIONS
+        })
+    }
+
+    /// The address which responded in each round for each flow, most recent first.
+    fn flow_round_addrs(&self) -> HashMap<(u16, u16), Vec<IpAddr>> {
+        let mut flows: HashMap<_, Vec<_>> = HashMap::new();
+        for round_addr in &self.round_addrs {
+            flows
+                .entry(round_addr.flow)
+                .or_default()
+                .push(round_addr.addr);
+        }
+        flows
+    }
+
+    /// The number of `A` -> `B` -> `A` changes in the addresses of a single flow.
+    fn flow_oscillations(addrs: &[IpAddr]) -> usize {
+        addrs
+            .windows(3)
+            .filter(|addrs| addrs[0] == addrs[2] && addrs[0] != addrs[1])
+            .count()
+    }
+
+    /// The inter-quartile range of the round trip time of the recorded samples.
+    ///
+    /// This is the difference between the 75th and 25th percentiles of the recorded samples which
+    /// received a response.  Returns `None` if fewer than two such samples have been recorded.
+    #[must_use]
+    pub fn iqr_ms(&self) -> Option<f64> {
+        self.quartiles_ms().map(|(q1, q3)| q3 - q1)
+    }
+
+    /// The number of recorded samples whose round trip time is an outlier.
+    ///
+    /// A sample is an outlier if it lies more than 1.5 times the inter-quartile range below the
+    /// first quartile or above the third quartile.
+    #[must_use]
+    pub fn outlier_count(&self) -> usize {
+        let Some((q1, q3)) = self.quartiles_ms() else {
+            return 0;
+        };
+        let fence = 1.5 * (q3 - q1);
+        self.received_samples_ms()
+            .filter(|&rtt_ms| rtt_ms < q1 - fence || rtt_ms > q3 + fence)
+            .count()
+    }
+
+    /// The first and third quartiles of the recorded samples which received a response.
+    fn quartiles_ms(&self) -> Option<(f64, f64)> {
+        let mut rtts_ms = self.received_samples_ms().collect::<Vec<_>>();
+        if rtts_ms.len() < 2 {
+            return None;
+        }
+        rtts_ms.sort_by(f64::total_cmp);
+        Some((percentile(&rtts_ms, 25_f64), percentile(&rtts_ms, 75_f64)))
+    }
+
+    /// The round trip time of the recorded samples which received a response.
+    ///
+    /// Probes which did not receive a response are recorded as a zero duration sample.
+    fn received_samples_ms(&self) -> impl Iterator<Item = f64> + '_ {
+        self.samples
+            .iter()
+            .filter(|sample| !sample.is_zero())
+            .map(|sample| sample.as_secs_f64() * 1000_f64)
+    }
+
+    /// Was the last probe for this hop filtered?
+    ///
+    /// A probe is considered to be filtered if the hop responded with an ICMP
+    /// `DestinationUnreachable` indicating that communication is administratively prohibited,
+    /// which is codes 9, 10 & 13 for IPv4 and code 1 for IPv6.
+    #[must_use]
+    pub fn is_filtered(&self) -> bool {
+        match (self.last_icmp_packet_type, self.addrs.first()) {
+            (Some(IcmpPacketType::Unreachable(code)), Some((IpAddr::V4(_), _))) => {
+                matches!(code.0, 9 | 10 | 13)
+            }
+            (Some(IcmpPacketType::Unreachable(code)), Some((IpAddr::V6(_), _))) => code.0 == 1,
+            _ => false,
+        }
+    }
+
+    /// The estimated Mean Opinion Score (MOS) for voice quality at this hop.
+    ///
+    /// This uses a simplified form of the ITU-T G.107 E-model, where the effective latency is
+    /// derived from the average round trip time and average jitter, and the resulting R-factor
+    /// is reduced by the packet loss.  The score ranges from 1.0 (bad) to 4.5 (best).
+    ///
+    /// Returns `None` if no responses have been received.
+    #[must_use]
+    pub fn mos(&self) -> Option<f64> {
+        if self.total_recv > 0 {
+            Some(r_factor_to_mos(r_factor(
+                self.avg_ms(),
+                self.javg_ms(),
+                self.loss_pct(),
+            )))
+        } else {
+            None
+        }
+    }
+
     /// The duration of the jitter probe observed.
     #[must_use]
     pub fn jitter_ms(&self) -> Option<f64> {
@@ -410,12 +892,59 @@ impl Hop {
         self.tos.map(|tos| tos.ecn())
     }
 
+    /// The type of service (DSCP/ECN) of the latest response from this hop.
+    ///
+    /// Unlike [`Hop::tos`], which is quoted from the original datagram, this is read from the
+    /// outer IP header of the response and so reveals any remarking along the return path.
+    #[must_use]
+    pub fn response_tos(&self) -> Option<TypeOfService> {
+        self.response_tos
+    }
+
+    /// The original datagram quoted in the latest ICMP error response from this hop.
+    ///
+    /// This is only retained when using `IcmpQuotedPayloadMode::Enabled`.
+    #[must_use]
+    pub fn last_quoted_payload(&self) -> Option<&[u8]> {
+        self.last_quoted_payload.as_deref()
+    }
+
+    /// The `DSCP` of the latest response from this hop.
+    #[must_use]
+    pub fn response_dscp(&self) -> Option<Dscp> {
+        self.response_tos.map(|tos| tos.dscp())
+    }
+
     /// The last N samples.
     #[must_use]
     pub fn samples(&self) -> &[Duration] {
         &self.samples
     }
 
+    /// The round in which the probe of each of the last N samples was sent, most recent first.
+    #[must_use]
+    pub fn sample_rounds(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.sample_probes.iter().map(|probe| probe.round)
+    }
+
+    /// The address which responded to the probe of each of the last N samples, if any, most
+    /// recent first.
+    #[must_use]
+    pub fn sample_addrs(&self) -> impl DoubleEndedIterator<Item = Option<IpAddr>> + '_ {
+        self.sample_probes.iter().map(|probe| {
+            self.round_addrs
+                .iter()
+                .find(|round_addr| round_addr.flow == probe.flow && round_addr.round == probe.round)
+                .map(|round_addr| round_addr.addr)
+        })
+    }
+
+    /// The time the last probe was sent for this hop, if any.
+    #[must_use]
+    pub const fn last_sent(&self) -> Option<SystemTime> {
+        self.last_sent
+    }
+
     #[must_use]
     pub const fn extensions(&self) -> Option<&Extensions> {
         self.extensions.as_ref()
@@ -427,11 +956,13 @@ impl Default for Hop {
         Self {
             ttl: 0,
             addrs: IndexMap::default(),
+            recent_addrs: Vec::new(),
             total_sent: 0,
             total_recv: 0,
             total_forward_lost: 0,
             total_backward_lost: 0,
             total_failed: 0,
+            total_discarded: 0,
             total_time: Duration::default(),
             last: None,
             best: None,
@@ -447,13 +978,313 @@ impl Default for Hop {
             mean: 0f64,
             m2: 0f64,
             samples: Vec::default(),
+            sample_probes: Vec::default(),
+            round_addrs: Vec::default(),
             tos: None,
+            response_tos: None,
             extensions: None,
+            last_quoted_payload: None,
+            last_sent: None,
+            last_received: None,
+            time_to_first: None,
+            total_reordered: 0,
+            current_loss_run: 0,
+            max_loss_run: 0,
+            total_send_gap: Duration::default(),
+            send_gaps: 0,
+            total_recv_gap: Duration::default(),
+            recv_gaps: 0,
+            min_recv_gap: None,
+            window: VecDeque::default(),
             last_nat_status: NatStatus::NotApplicable,
         }
     }
 }
 
+/// The end-to-end round trip time measured directly to the destination.
+///
+/// Only responses from the destination, such as an ICMP `EchoReply` or the response to the final
+/// ttl of a round which found the target, contribute to these statistics.  Unlike the target
+/// `Hop`, these statistics are never affected by the responses of intermediate hops and include
+/// all rounds.
+#[derive(Debug, Clone, Default)]
+pub struct DestinationRtt {
+    /// The total responses received from the destination.
+    total_recv: usize,
+    /// The total round trip time for all responses from the destination.
+    total_time: Duration,
+    /// The round trip time of the last response from the destination.
+    last: Option<Duration>,
+    /// The best round trip time of all responses from the destination.
+    best: Option<Duration>,
+    /// The worst round trip time of all responses from the destination.
+    worst: Option<Duration>,
+    /// The running mean of the round trip time (in ms).
+    mean: f64,
+    /// The running sum of squared differences from the mean (in ms).
+    m2: f64,
+}
+
+impl DestinationRtt {
+    /// The total responses received from the destination.
+    #[must_use]
+    pub const fn total_recv(&self) -> usize {
+        self.total_recv
+    }
+
+    /// The round trip time of the last response from the destination.
+    #[must_use]
+    pub fn last_ms(&self) -> Option<f64> {
+        self.last.map(|last| last.as_secs_f64() * 1000_f64)
+    }
+
+    /// The best round trip time of all responses from the destination.
+    #[must_use]
+    pub fn best_ms(&self) -> Option<f64> {
+        self.best.map(|best| best.as_secs_f64() * 1000_f64)
+    }
+
+    /// The worst round trip time of all responses from the destination.
+    #[must_use]
+    pub fn worst_ms(&self) -> Option<f64> {
+        self.worst.map(|worst| worst.as_secs_f64() * 1000_f64)
+    }
+
+    /// The average round trip time of all responses from the destination.
+    #[must_use]
+    pub fn avg_ms(&self) -> f64 {
+        if self.total_recv > 0 {
+            (self.total_time.as_secs_f64() * 1000_f64) / self.total_recv as f64
+        } else {
+            0_f64
+        }
+    }
+
+    /// The standard deviation of the round trip time of all responses from the destination.
+    #[must_use]
+    pub fn stddev_ms(&self) -> f64 {
+        if self.total_recv > 1 {
+            (self.m2 / (self.total_recv - 1) as f64).sqrt()
+        } else {
+            0_f64
+        }
+    }
+
+    /// Record a response from the destination with a round trip time of `dur`.
+    fn update(&mut self, dur: Duration) {
+        let dur_ms = dur.as_secs_f64() * 1000_f64;
+        self.total_recv += 1;
+        self.total_time += dur;
+        self.last = Some(dur);
+        self.best = self.best.map_or(Some(dur), |d| Some(d.min(dur)));
+        self.worst = self.worst.map_or(Some(dur), |d| Some(d.max(dur)));
+        let delta = dur_ms - self.mean;
+        self.mean += delta / self.total_recv as f64;
+        self.m2 = delta.mul_add(dur_ms - self.mean, self.m2);
+    }
+}
+
+/// A change of the autonomous system (AS) of a hop between updates.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AsnChange {
+    /// The ttl of the hop.
+    pub ttl: u8,
+    /// The AS number of the hop at the prior update.
+    pub old: String,
+    /// The AS number of the hop at the latest update.
+    pub new: String,
+}
+
+/// Track the autonomous system (AS) of each hop across updates to detect reroutes.
+///
+/// The AS of a hop is that of the address which most recently responded for the hop.  The AS
+/// number of an address is supplied by the caller, typically from a DNS resolver, as the AS
+/// information is not known to the tracer.
+#[derive(Debug, Default)]
+pub struct AsnTracker {
+    asns: HashMap<u8, String>,
+}
+
+impl AsnTracker {
+    /// Record the AS of each hop of `trace` and return the hops whose AS changed since the last
+    /// update.
+    ///
+    /// The AS number of an address is looked up with `asn_lookup` and hops whose AS number is not
+    /// (yet) known are ignored.
+    pub fn update(
+        &mut self,
+        trace: &State,
+        asn_lookup: impl Fn(IpAddr) -> Option<String>,
+    ) -> Vec<AsnChange> {
+        trace
+            .hops()
+            .iter()
+            .filter_map(|hop| {
+                let asn = asn_lookup(hop.last_addr()?)?;
+                match self.asns.insert(hop.ttl(), asn.clone()) {
+                    Some(old) if old != asn => Some(AsnChange {
+                        ttl: hop.ttl(),
+                        old,
+                        new: asn,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Hop {
+    /// Evict the contributions of all probes sent in rounds before `oldest_round`.
+    ///
+    /// The loss counts and total round trip time are reduced by the evicted contributions and the
+    /// best, worst, mean and variance of the round trip time are recalculated for the remaining
+    /// contributions.
+    fn evict_window(&mut self, oldest_round: usize) {
+        let mut evicted = false;
+        while let Some(entry) = self
+            .window
+            .front()
+            .filter(|entry| entry.round < oldest_round)
+        {
+            self.total_sent -= 1;
+            match entry.outcome {
+                WindowOutcome::Received(rtt) => {
+                    self.total_recv -= 1;
+                    self.total_time -= rtt;
+                }
+                WindowOutcome::Discarded => self.total_discarded -= 1,
+                WindowOutcome::ForwardLost => self.total_forward_lost -= 1,
+                WindowOutcome::BackwardLost => self.total_backward_lost -= 1,
+                WindowOutcome::Failed => self.total_failed -= 1,
+                WindowOutcome::Lost => {}
+            }
+            self.window.pop_front();
+            evicted = true;
+        }
+        if evicted {
+            let rtts = self
+                .window
+                .iter()
+                .filter_map(|entry| match entry.outcome {
+                    WindowOutcome::Received(rtt) => Some(rtt),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            self.best = rtts.iter().min().copied();
+            self.worst = rtts.iter().max().copied();
+            let rtts_ms = rtts.iter().map(|rtt| rtt.as_secs_f64() * 1000_f64);
+            self.mean = if rtts.is_empty() {
+                0_f64
+            } else {
+                rtts_ms.clone().sum::<f64>() / rtts.len() as f64
+            };
+            self.m2 = rtts_ms.map(|rtt_ms| (rtt_ms - self.mean).powi(2)).sum();
+        }
+    }
+
+    /// Evict the least frequently seen addresses until at most `max_addrs` remain.
+    ///
+    /// The most recently seen address is never evicted and ties are broken by evicting the least
+    /// recently seen address.  The counts of the remaining addresses are unchanged.
+    fn evict_addrs(&mut self, max_addrs: NonZeroUsize) {
+        while self.addrs.len() > max_addrs.get() {
+            let latest = self.recent_addrs.last().copied();
+            let Some(evicted) = self
+                .recent_addrs
+                .iter()
+                .filter(|&&addr| Some(addr) != latest)
+                .min_by_key(|addr| self.addrs.get(*addr))
+                .copied()
+            else {
+                return;
+            };
+            self.addrs.shift_remove(&evicted);
+            self.recent_addrs.retain(|&recent| recent != evicted);
+        }
+    }
+}
+
+/// The probe of a sample of a `Hop`.
+#[derive(Debug, Copy, Clone)]
+struct SampleProbe {
+    /// The flow of the probe, identified by its source and destination ports.
+    flow: (u16, u16),
+    /// The round in which the probe was sent.
+    round: usize,
+}
+
+/// The address which responded for a hop for a given flow in a given round.
+#[derive(Debug, Copy, Clone)]
+struct RoundAddr {
+    /// The flow of the probe, identified by its source and destination ports.
+    flow: (u16, u16),
+    /// The round in which the probe was sent.
+    round: usize,
+    /// The address which responded.
+    addr: IpAddr,
+}
+
+/// The contribution of a single probe to the statistics of a `Hop` within the window.
+#[derive(Debug, Copy, Clone)]
+struct WindowEntry {
+    /// The ordinal of the round, within the flow, in which the probe was sent.
+    round: usize,
+    /// The outcome of the probe.
+    outcome: WindowOutcome,
+}
+
+/// The outcome of a single probe within the window.
+#[derive(Debug, Copy, Clone)]
+enum WindowOutcome {
+    /// A response was received with the given round trip time.
+    Received(Duration),
+    /// The response was discarded due to a negative round trip time.
+    Discarded,
+    /// No response was received due to forward loss.
+    ForwardLost,
+    /// No response was received due to backward loss.
+    BackwardLost,
+    /// No response was received.
+    Lost,
+    /// The probe failed.
+    Failed,
+}
+
+/// The `pct` percentile of the non-empty ascending `sorted` values.
+///
+/// The percentile is linearly interpolated between the closest ranks.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "the rank is within the bounds of the values"
+)]
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = pct / 100_f64 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    (sorted[upper] - sorted[lower]).mul_add(rank - lower as f64, sorted[lower])
+}
+
+/// Calculate the simplified E-model R-factor from latency, jitter and loss.
+///
+/// The R-factor is clamped to the range 0 to 100.
+fn r_factor(latency_ms: f64, jitter_ms: f64, loss_pct: f64) -> f64 {
+    let effective_latency_ms = jitter_ms.mul_add(2_f64, latency_ms) + 10_f64;
+    let r_factor = if effective_latency_ms < 160_f64 {
+        93.2 - effective_latency_ms / 40_f64
+    } else {
+        93.2 - (effective_latency_ms - 120_f64) / 10_f64
+    };
+    loss_pct.mul_add(-2.5, r_factor).clamp(0_f64, 100_f64)
+}
+
+/// Convert an E-model R-factor to a MOS, as per ITU-T G.107 Annex B.
+fn r_factor_to_mos(r_factor: f64) -> f64 {
+    (0.000_007 * r_factor * (r_factor - 60_f64)).mul_add(100_f64 - r_factor, 0.035 * r_factor)
+        + 1_f64
+}
+
 /// The state of a NAT detection for a `Hop`.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum NatStatus {
@@ -470,6 +1301,12 @@ pub enum NatStatus {
 struct FlowState {
     /// The maximum number of samples to record.
     max_samples: usize,
+    /// How to handle responses with a negative round trip time.
+    negative_rtt_mode: NegativeRttMode,
+    /// The number of most recent rounds which contribute to the hop statistics, if any.
+    window: Option<NonZeroUsize>,
+    /// The maximum number of distinct addresses to record per hop, if any.
+    max_addrs: Option<NonZeroUsize>,
     /// The lowest ttl observed across all rounds.
     lowest_ttl: u8,
     /// The highest ttl observed across all rounds.
@@ -480,19 +1317,36 @@ struct FlowState {
     round: Option<usize>,
     /// The total number of rounds received.
     round_count: usize,
+    /// Whether any round has completed because the target responded.
+    destination_reached: bool,
+    /// Whether a round has completed because the target was silent.
+    destination_silent: bool,
+    /// The end-to-end round trip time to the destination.
+    destination_rtt: DestinationRtt,
     /// The hops in this trace.
     hops: Vec<Hop>,
 }
 
 impl FlowState {
-    fn new(max_samples: usize) -> Self {
+    fn new(
+        max_samples: usize,
+        negative_rtt_mode: NegativeRttMode,
+        window: Option<NonZeroUsize>,
+        max_addrs: Option<NonZeroUsize>,
+    ) -> Self {
         Self {
             max_samples,
+            negative_rtt_mode,
+            window,
+            max_addrs,
             lowest_ttl: 0,
             highest_ttl: 0,
             highest_ttl_for_round: 0,
             round: None,
             round_count: 0,
+            destination_reached: false,
+            destination_silent: false,
+            destination_rtt: DestinationRtt::default(),
             hops: (0..MAX_TTL).map(|_| Hop::default()).collect(),
         }
     }
@@ -531,8 +1385,74 @@ impl FlowState {
         self.round_count
     }
 
-    fn update_from_round(&mut self, round: &Round<'_>) {
-        state_updater::StateUpdater::new(self, round).apply();
+    const fn destination_reached(&self) -> bool {
+        self.destination_reached
+    }
+
+    const fn destination_silent(&self) -> bool {
+        self.destination_silent
+    }
+
+    const fn destination_rtt(&self) -> &DestinationRtt {
+        &self.destination_rtt
+    }
+
+    fn destination_confidence(&self, target_addr: IpAddr) -> f64 {
+        if !self.destination_reached {
+            return 0_f64;
+        }
+        let target = self.target_hop();
+        let total = target.addrs.values().sum::<usize>();
+        let source = if total > 0 {
+            target.addrs.get(&target_addr).copied().unwrap_or_default() as f64 / total as f64
+        } else {
+            0_f64
+        };
+        let response = match target.last_icmp_packet_type {
+            Some(IcmpPacketType::EchoReply(_) | IcmpPacketType::NotApplicable) => true,
+            Some(IcmpPacketType::Unreachable(code)) => {
+                is_target_unreachable_code(target_addr, code)
+            }
+            Some(IcmpPacketType::TimeExceeded(_)) | None => false,
+        };
+        let ttl_consistent = !self
+            .hops()
+            .iter()
+            .filter(|hop| hop.ttl < target.ttl)
+            .any(|hop| hop.addrs.contains_key(&target_addr));
+        let signal = |present: bool| if present { 1_f64 } else { 0_f64 };
+        CONFIDENCE_TTL_WEIGHT.mul_add(
+            signal(ttl_consistent),
+            CONFIDENCE_RESPONSE_WEIGHT.mul_add(signal(response), CONFIDENCE_SOURCE_WEIGHT * source),
+        )
+    }
+
+    fn path_stability(&self) -> f64 {
+        let consistency = self
+            .hops()
+            .iter()
+            .filter_map(|hop| {
+                let total = hop.addrs.values().sum::<usize>();
+                let most = hop.addrs.values().max()?;
+                Some(*most as f64 / total as f64)
+            })
+            .collect::<Vec<_>>();
+        if consistency.is_empty() {
+            return 0_f64;
+        }
+        let addr = consistency.iter().sum::<f64>() / consistency.len() as f64;
+        let target = self.target_hop();
+        let delivery = 1_f64 - target.loss_pct() / 100_f64;
+        let rtt = target.cv().map_or(1_f64, |cv| (1_f64 - cv).max(0_f64));
+        100_f64
+            * STABILITY_RTT_WEIGHT.mul_add(
+                rtt,
+                STABILITY_DELIVERY_WEIGHT.mul_add(delivery, STABILITY_ADDR_WEIGHT * addr),
+            )
+    }
+
+    fn update_from_round(&mut self, round: &Round<'_>, start_time: Option<SystemTime>) {
+        state_updater::StateUpdater::new(self, round, start_time).apply();
     }
 
     fn update_round(&mut self, round: RoundId) {
@@ -553,9 +1473,11 @@ impl FlowState {
 
 mod state_updater {
     use crate::state::FlowState;
+    use crate::state::{Hop, RoundAddr, SampleProbe, WindowEntry, WindowOutcome};
     use crate::types::Checksum;
-    use crate::{NatStatus, ProbeStatus, Round, TimeToLive};
-    use std::time::Duration;
+    use crate::{CompletionReason, NatStatus, ProbeComplete, ProbeStatus, Round, TimeToLive};
+    use std::num::NonZeroUsize;
+    use std::time::{Duration, SystemTime};
     use tracing::instrument;
 
     /// Update the state of a `FlowState` from a `Round`.
@@ -564,16 +1486,23 @@ mod state_updater {
         state: &'a mut FlowState,
         /// The `Round` being processed.
         round: &'a Round<'a>,
+        /// The time the trace started, if known.
+        start_time: Option<SystemTime>,
         /// The checksum of the previous hop, if any.
         prev_hop_checksum: Option<u16>,
         /// Whether any previous hop in the round had forward loss.
         forward_loss: bool,
     }
     impl<'a> StateUpdater<'a> {
-        pub(super) fn new(state: &'a mut FlowState, round: &'a Round<'_>) -> Self {
+        pub(super) fn new(
+            state: &'a mut FlowState,
+            round: &'a Round<'_>,
+            start_time: Option<SystemTime>,
+        ) -> Self {
             Self {
                 state,
                 round,
+                start_time,
                 prev_hop_checksum: None,
                 forward_loss: false,
             }
@@ -585,14 +1514,65 @@ mod state_updater {
             self.state.highest_ttl =
                 std::cmp::max(self.state.highest_ttl, self.round.largest_ttl.0);
             self.state.highest_ttl_for_round = self.round.largest_ttl.0;
+            match self.round.reason {
+                CompletionReason::TargetFound => self.state.destination_reached = true,
+                CompletionReason::DestinationSilent => self.state.destination_silent = true,
+                CompletionReason::RoundTimeLimitExceeded => {}
+            }
             for probe in self.round.probes {
                 self.update_for_probe(probe);
             }
+            self.update_reordered();
+            if let Some(window) = self.state.window {
+                let oldest_round = (self.state.round_count + 1).saturating_sub(window.get());
+                for hop in &mut self.state.hops {
+                    hop.evict_window(oldest_round);
+                }
+            }
         }
 
+        /// Record the responses of the round which were received before the response to a probe
+        /// with a lower sequence number.
+        ///
+        /// Sequence numbers are compared relative to the first probe of the round so that a round
+        /// in which the sequence number wraps is ordered correctly.  Responses which were discarded
+        /// are ignored.
+        fn update_reordered(&mut self) {
+            let discard = self.state.negative_rtt_mode.is_discard();
+            let mut completed = self
+                .round
+                .probes
+                .iter()
+                .filter_map(|probe| match probe {
+                    ProbeStatus::Complete(complete)
+                        if !discard || complete.received >= complete.sent =>
+                    {
+                        Some(complete)
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let Some(first) = completed.first().map(|complete| complete.sequence.0) else {
+                return;
+            };
+            completed.sort_by_key(|complete| complete.sequence.0.wrapping_sub(first));
+            let mut latest = None;
+            for complete in completed {
+                match latest {
+                    Some(latest) if complete.received < latest => {
+                        self.state.hops[usize::from(complete.ttl.0) - 1].total_reordered += 1;
+                    }
+                    _ => latest = Some(complete.received),
+                }
+            }
+        }
+
+        #[expect(clippy::too_many_lines)]
         #[instrument(skip(self), level = "trace")]
         fn update_for_probe(&mut self, probe: &ProbeStatus) {
             let state = &mut *self.state;
+            let window = state.window;
+            let window_round = state.round_count;
             match probe {
                 ProbeStatus::Complete(complete) => {
                     state.update_lowest_ttl(complete.ttl);
@@ -601,13 +1581,34 @@ mod state_updater {
                     let hop = &mut state.hops[index];
                     hop.ttl = complete.ttl.0;
                     hop.total_sent += 1;
+                    update_send_gap(hop, complete.sent);
+                    // A response received before the probe was sent yields a negative round trip
+                    // time, which is either clamped to zero or discarded.
+                    let dur = match complete.received.duration_since(complete.sent) {
+                        Ok(dur) => dur,
+                        Err(_) if state.negative_rtt_mode.is_discard() => {
+                            hop.total_discarded += 1;
+                            update_window(hop, window, window_round, WindowOutcome::Discarded);
+                            return;
+                        }
+                        Err(_) => Duration::ZERO,
+                    };
                     hop.total_recv += 1;
-                    let dur = complete
-                        .received
-                        .duration_since(complete.sent)
-                        .unwrap_or_default();
+                    update_recv_gap(hop, complete.received);
+                    hop.current_loss_run = 0;
+                    if hop.time_to_first.is_none() {
+                        hop.time_to_first = self
+                            .start_time
+                            .and_then(|start| complete.received.duration_since(start).ok());
+                    }
+                    update_window(hop, window, window_round, WindowOutcome::Received(dur));
                     let dur_ms = dur.as_secs_f64() * 1000_f64;
                     hop.total_time += dur;
+                    if self.round.reason == CompletionReason::TargetFound
+                        && complete.ttl == self.round.largest_ttl
+                    {
+                        state.destination_rtt.update(dur);
+                    }
                     // Before last is set use it to calc jitter
                     let last_ms = hop.last_ms().unwrap_or_default();
                     let jitter_ms = (dur_ms - last_ms).abs();
@@ -620,22 +1621,35 @@ mod state_updater {
                         .jmax
                         .map_or(Some(jitter_dur), |d| Some(d.max(jitter_dur)));
                     hop.last = Some(dur);
-                    hop.samples.insert(0, dur);
+                    insert_sample(
+                        hop,
+                        dur,
+                        complete.round.0,
+                        (complete.src_port.0, complete.dest_port.0),
+                        state.max_samples,
+                    );
                     hop.best = hop.best.map_or(Some(dur), |d| Some(d.min(dur)));
                     hop.worst = hop.worst.map_or(Some(dur), |d| Some(d.max(dur)));
                     hop.mean += (dur_ms - hop.mean) / hop.total_recv as f64;
                     hop.m2 = (dur_ms - hop.mean).mul_add(dur_ms - hop.mean, hop.m2);
-                    if hop.samples.len() > state.max_samples {
-                        hop.samples.pop();
-                    }
                     let host = complete.host;
-                    *hop.addrs.entry(host).or_default() += 1;
+                    update_round_addrs(hop, complete, state.max_samples);
+                    for &addr in std::iter::once(&host).chain(&complete.extra_hosts) {
+                        *hop.addrs.entry(addr).or_default() += 1;
+                        hop.recent_addrs.retain(|&recent| recent != addr);
+                        hop.recent_addrs.push(addr);
+                        if let Some(max_addrs) = state.max_addrs {
+                            hop.evict_addrs(max_addrs);
+                        }
+                    }
                     hop.extensions.clone_from(&complete.extensions);
+                    hop.last_quoted_payload.clone_from(&complete.quoted_payload);
                     hop.last_src_port = complete.src_port.0;
                     hop.last_dest_port = complete.dest_port.0;
                     hop.last_sequence = complete.sequence.0;
                     hop.last_icmp_packet_type = Some(complete.icmp_packet_type);
                     hop.tos = complete.tos;
+                    hop.response_tos = complete.response_tos;
                     if let (Some(expected), Some(actual)) =
                         (complete.expected_udp_checksum, complete.actual_udp_checksum)
                     {
@@ -652,19 +1666,29 @@ mod state_updater {
                     let hop = &mut state.hops[index];
                     hop.total_sent += 1;
                     hop.ttl = awaited.ttl.0;
-                    hop.samples.insert(0, Duration::default());
-                    if hop.samples.len() > state.max_samples {
-                        hop.samples.pop();
-                    }
+                    update_send_gap(hop, awaited.sent);
+                    insert_sample(
+                        hop,
+                        Duration::default(),
+                        awaited.round.0,
+                        (awaited.src_port.0, awaited.dest_port.0),
+                        state.max_samples,
+                    );
                     hop.last_src_port = awaited.src_port.0;
                     hop.last_dest_port = awaited.dest_port.0;
                     hop.last_sequence = awaited.sequence.0;
-                    if self.forward_loss {
+                    update_loss_run(hop);
+                    let outcome = if self.forward_loss {
                         hop.total_backward_lost += 1;
+                        WindowOutcome::BackwardLost
                     } else if is_forward_loss(self.round.probes, awaited.ttl) {
                         hop.total_forward_lost += 1;
                         self.forward_loss = true;
-                    }
+                        WindowOutcome::ForwardLost
+                    } else {
+                        WindowOutcome::Lost
+                    };
+                    update_window(hop, window, window_round, outcome);
                 }
                 ProbeStatus::Failed(failed) => {
                     state.update_lowest_ttl(failed.ttl);
@@ -674,19 +1698,107 @@ mod state_updater {
                     hop.total_sent += 1;
                     hop.total_failed += 1;
                     hop.ttl = failed.ttl.0;
-                    hop.samples.insert(0, Duration::default());
-                    if hop.samples.len() > state.max_samples {
-                        hop.samples.pop();
-                    }
+                    update_send_gap(hop, failed.sent);
+                    update_window(hop, window, window_round, WindowOutcome::Failed);
+                    insert_sample(
+                        hop,
+                        Duration::default(),
+                        failed.round.0,
+                        (failed.src_port.0, failed.dest_port.0),
+                        state.max_samples,
+                    );
                     hop.last_src_port = failed.src_port.0;
                     hop.last_dest_port = failed.dest_port.0;
                     hop.last_sequence = failed.sequence.0;
+                    update_loss_run(hop);
                 }
                 ProbeStatus::NotSent | ProbeStatus::Skipped => {}
             }
         }
     }
 
+    /// Record the contribution of a probe to the window of a hop, if a window is configured.
+    fn update_window(
+        hop: &mut Hop,
+        window: Option<NonZeroUsize>,
+        round: usize,
+        outcome: WindowOutcome,
+    ) {
+        if window.is_some() {
+            hop.window.push_back(WindowEntry { round, outcome });
+        }
+    }
+
+    /// Record the gap between this and the previous probe sent for a hop.
+    fn update_send_gap(hop: &mut Hop, sent: SystemTime) {
+        if let Some(gap) = hop
+            .last_sent
+            .and_then(|last| sent.duration_since(last).ok())
+        {
+            hop.total_send_gap += gap;
+            hop.send_gaps += 1;
+        }
+        hop.last_sent = Some(sent);
+    }
+
+    /// Record the gap between this and the previous response received for a hop.
+    fn update_recv_gap(hop: &mut Hop, received: SystemTime) {
+        if let Some(gap) = hop
+            .last_received
+            .and_then(|last| received.duration_since(last).ok())
+        {
+            hop.total_recv_gap += gap;
+            hop.recv_gaps += 1;
+            hop.min_recv_gap = Some(hop.min_recv_gap.map_or(gap, |min| min.min(gap)));
+        }
+        hop.last_received = Some(received);
+    }
+
+    /// Record a sample, and the round and flow of its probe, retaining the latest `max_samples`.
+    fn insert_sample(
+        hop: &mut Hop,
+        sample: Duration,
+        round: usize,
+        flow: (u16, u16),
+        max_samples: usize,
+    ) {
+        hop.samples.insert(0, sample);
+        hop.sample_probes.insert(0, SampleProbe { flow, round });
+        if hop.samples.len() > max_samples {
+            hop.samples.pop();
+            hop.sample_probes.pop();
+        }
+    }
+
+    /// Record the address which responded to `complete` for the flow and round of the probe.
+    ///
+    /// Only the latest response for a given flow in a given round is recorded.
+    fn update_round_addrs(hop: &mut Hop, complete: &ProbeComplete, max_samples: usize) {
+        let round_addr = RoundAddr {
+            flow: (complete.src_port.0, complete.dest_port.0),
+            round: complete.round.0,
+            addr: complete.host,
+        };
+        if let Some(existing) = hop
+            .round_addrs
+            .iter_mut()
+            .find(|existing| existing.flow == round_addr.flow && existing.round == round_addr.round)
+        {
+            *existing = round_addr;
+        } else {
+            hop.round_addrs.insert(0, round_addr);
+            if hop.round_addrs.len() > max_samples {
+                hop.round_addrs.pop();
+            }
+        }
+    }
+
+    /// Record a probe without a response for a hop, extending the current run of losses.
+    fn update_loss_run(hop: &mut Hop) {
+        hop.current_loss_run += 1;
+        hop.max_loss_run = hop.max_loss_run.max(hop.current_loss_run);
+    }
+
     /// Determine if forward loss has occurred at a given time-to-live.
     ///
     /// This is determined by checking if all probes after the awaited probe are all also awaited.
@@ -811,6 +1923,9 @@ mod state_updater {
                             expected_udp_checksum: None,
                             actual_udp_checksum: None,
                             extensions: None,
+                            extra_hosts: vec![],
+                            quoted_payload: None,
+                            response_tos: None,
                         }),
                         _ => unreachable!(),
                     }
@@ -833,6 +1948,7 @@ mod state_updater {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::probe::IcmpPacketCode;
     use crate::types::Checksum;
     use crate::{
         CompletionReason, Flags, IcmpPacketType, Port, Probe, ProbeComplete, ProbeStatus, Sequence,
@@ -842,6 +1958,7 @@ mod tests {
     use serde::Deserialize;
     use std::collections::HashSet;
     use std::fmt::Debug;
+    use std::net::Ipv4Addr;
     use std::ops::Add;
     use std::str::FromStr;
     use std::time::SystemTime;
@@ -928,6 +2045,7 @@ mod tests {
                                 expected_udp_checksum,
                                 actual_udp_checksum,
                                 None,
+                                None,
                             ),
                         ))
                     }
@@ -985,6 +2103,8 @@ mod tests {
         jinta: Option<f64>,
         addrs: Option<HashMap<IpAddr, usize>>,
         samples: Option<Vec<f64>>,
+        sample_count: Option<usize>,
+        stderr_ms: Option<f64>,
         last_src: Option<u16>,
         last_dest: Option<u16>,
         last_sequence: Option<u16>,
@@ -1076,6 +2196,8 @@ mod tests {
             assert_eq_opt_f64(Some(&actual.javg_ms()), expected.javg.as_ref());
             assert_eq_opt_f64(actual.jmax_ms().as_ref(), expected.jmax.as_ref());
             assert_eq_opt_f64(Some(&actual.jinta()), expected.jinta.as_ref());
+            assert_eq_opt(Some(&actual.sample_count()), expected.sample_count.as_ref());
+            assert_eq_opt_f64(actual.stderr_ms().as_ref(), expected.stderr_ms.as_ref());
             assert_eq_opt(Some(&actual.last_src_port()), expected.last_src.as_ref());
             assert_eq_opt(Some(&actual.last_dest_port()), expected.last_dest.as_ref());
             assert_eq_opt(
@@ -1100,6 +2222,1181 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_stderr_single_sample() {
+        let scenario: Scenario = file!("minimal.toml");
+        let mut trace = State::new(StateConfig::default());
+        let probes = scenario
+            .rounds
+            .into_iter()
+            .flat_map(|round| round.probes)
+            .map(|p| ProbeRound(p, RoundId(0)))
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let largest_ttl = TimeToLive(scenario.largest_ttl);
+        trace.update_from_round(&Round::new(
+            &probes,
+            largest_ttl,
+            CompletionReason::TargetFound,
+        ));
+        for hop in trace.hops() {
+            assert_eq!(1, hop.sample_count());
+            assert_eq!(None, hop.stderr_ms());
+        }
+    }
+
+    #[test_case(40_f64, 0.5, true; "high variance")]
+    #[test_case(2_f64, 0.5, false; "low variance")]
+    #[test_case(40_f64, 0.9, false; "high variance below threshold")]
+    fn test_likely_load_balanced(stddev_ms: f64, cv_threshold: f64, expected: bool) {
+        let hop = Hop {
+            total_recv: 2,
+            total_time: Duration::from_millis(100),
+            m2: stddev_ms * stddev_ms,
+            ..Default::default()
+        };
+        assert_eq_opt_f64(Some(&hop.avg_ms()), Some(&50_f64));
+        assert_eq_opt_f64(Some(&hop.stddev_ms()), Some(&stddev_ms));
+        assert_eq_opt_f64(hop.cv().as_ref(), Some(&(stddev_ms / 50_f64)));
+        assert_eq!(expected, hop.is_likely_load_balanced(cv_threshold));
+    }
+
+    #[test_case("10.0.0.1", IcmpPacketType::Unreachable(IcmpPacketCode(13)), true; "ipv4 admin prohibited")]
+    #[test_case("10.0.0.1", IcmpPacketType::Unreachable(IcmpPacketCode(9)), true; "ipv4 net prohibited")]
+    #[test_case("10.0.0.1", IcmpPacketType::Unreachable(IcmpPacketCode(10)), true; "ipv4 host prohibited")]
+    #[test_case("10.0.0.1", IcmpPacketType::Unreachable(IcmpPacketCode(3)), false; "ipv4 port unreachable")]
+    #[test_case("10.0.0.1", IcmpPacketType::Unreachable(IcmpPacketCode(1)), false; "ipv4 host unreachable")]
+    #[test_case("10.0.0.1", IcmpPacketType::TimeExceeded(IcmpPacketCode(0)), false; "ipv4 time exceeded")]
+    #[test_case("2001:db8::1", IcmpPacketType::Unreachable(IcmpPacketCode(1)), true; "ipv6 admin prohibited")]
+    #[test_case("2001:db8::1", IcmpPacketType::Unreachable(IcmpPacketCode(13)), false; "ipv6 code 13")]
+    fn test_is_filtered(addr: &str, icmp_packet_type: IcmpPacketType, expected: bool) {
+        let hop = Hop {
+            addrs: IndexMap::from([(IpAddr::from_str(addr).unwrap(), 1)]),
+            last_icmp_packet_type: Some(icmp_packet_type),
+            ..Default::default()
+        };
+        assert_eq!(expected, hop.is_filtered());
+    }
+
+    #[test]
+    fn test_cv_single_sample() {
+        let hop = Hop {
+            total_recv: 1,
+            total_time: Duration::from_millis(50),
+            ..Default::default()
+        };
+        assert_eq!(None, hop.cv());
+        assert!(!hop.is_likely_load_balanced(0_f64));
+    }
+
+    #[test]
+    fn test_iqr_and_outliers() {
+        let samples = [12, 0, 10, 14, 100, 11, 0, 15, 13]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        let hop = Hop {
+            samples,
+            ..Default::default()
+        };
+        assert_eq!(Some(3_f64), hop.iqr_ms());
+        assert_eq!(1, hop.outlier_count());
+    }
+
+    #[test]
+    fn test_iqr_no_outliers() {
+        let samples = [10, 20, 30, 40]
+            .into_iter()
+            .map(Duration::from_millis)
+            .collect();
+        let hop = Hop {
+            samples,
+            ..Default::default()
+        };
+        assert_eq!(Some(15_f64), hop.iqr_ms());
+        assert_eq!(0, hop.outlier_count());
+    }
+
+    #[test_case(&[]; "no samples")]
+    #[test_case(&[10]; "single sample")]
+    #[test_case(&[0, 10, 0]; "single response")]
+    fn test_iqr_too_few_samples(samples: &[u64]) {
+        let hop = Hop {
+            samples: samples.iter().copied().map(Duration::from_millis).collect(),
+            ..Default::default()
+        };
+        assert_eq!(None, hop.iqr_ms());
+        assert_eq!(0, hop.outlier_count());
+    }
+
+    #[test_case(NegativeRttMode::Clamp, 1, 0, Some(0.0); "clamp")]
+    #[test_case(NegativeRttMode::Discard, 0, 1, None; "discard")]
+    fn test_negative_rtt(
+        negative_rtt_mode: NegativeRttMode,
+        expected_recv: usize,
+        expected_discarded: usize,
+        expected_last_ms: Option<f64>,
+    ) {
+        let mut trace = State::new(StateConfig {
+            negative_rtt_mode,
+            ..StateConfig::default()
+        });
+        let sent = SystemTime::now();
+        let received = sent - Duration::from_millis(5);
+        let probe = Probe::new(
+            Sequence(33000),
+            TraceId(0),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        )
+        .complete(
+            IpAddr::from([10, 0, 0, 1]),
+            received,
+            IcmpPacketType::NotApplicable,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        let probes = [ProbeStatus::Complete(probe)];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(1),
+            CompletionReason::TargetFound,
+        ));
+        let hop = &trace.hops()[0];
+        assert_eq!(1, hop.total_sent());
+        assert_eq!(expected_recv, hop.total_recv());
+        assert_eq!(expected_discarded, hop.total_discarded());
+        assert_eq_opt_f64(hop.last_ms().as_ref(), expected_last_ms.as_ref());
+        assert_eq_opt_f64(Some(&hop.loss_pct()), Some(&0.0));
+    }
+
+    #[test_case(Duration::from_millis(10), 10, true; "responses plateau at 100ms")]
+    #[test_case(Duration::from_millis(10), 1, false; "no loss")]
+    fn test_icmp_rate_limited(send_gap: Duration, recv_every: usize, expected: bool) {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::now();
+        for i in 0_u16..100 {
+            let sent = start + send_gap * u32::from(i);
+            let probe = Probe::new(
+                Sequence(33000 + i),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(usize::from(i)),
+                sent,
+                Flags::empty(),
+            );
+            // Only every `recv_every` probe gets a response.
+            let status = if usize::from(i).is_multiple_of(recv_every) {
+                ProbeStatus::Complete(probe.complete(
+                    IpAddr::from([10, 0, 0, 1]),
+                    sent + Duration::from_millis(1),
+                    IcmpPacketType::NotApplicable,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ))
+            } else {
+                ProbeStatus::Awaited(probe)
+            };
+            let probes = [status];
+            trace.update_from_round(&Round::new(
+                &probes,
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+        }
+        assert_eq!(expected, trace.hops()[0].is_icmp_rate_limited());
+    }
+
+    #[test_case(93.2, 4.41; "best narrowband")]
+    #[test_case(80.0, 4.02; "high")]
+    #[test_case(70.0, 3.60; "medium")]
+    #[test_case(60.0, 3.10; "low")]
+    #[test_case(50.0, 2.58; "poor")]
+    #[test_case(100.0, 4.50; "maximum")]
+    #[test_case(0.0, 1.00; "minimum")]
+    fn test_r_factor_to_mos(r_factor: f64, expected: f64) {
+        assert!((r_factor_to_mos(r_factor) - expected).abs() < 0.01);
+    }
+
+    #[test_case(0, 0.0, 0, 4.40; "no latency")]
+    #[test_case(20, 2.0, 0, 4.39; "low latency")]
+    #[test_case(100, 10.0, 0, 4.34; "moderate latency")]
+    #[test_case(150, 10.0, 0, 4.26; "high latency")]
+    #[test_case(50, 5.0, 2, 4.24; "low loss")]
+    #[test_case(300, 20.0, 10, 2.33; "high latency and loss")]
+    #[test_case(100, 10.0, 50, 1.00; "severe loss")]
+    fn test_mos(latency_ms: u64, jitter_ms: f64, lost: usize, expected: f64) {
+        let total_sent = 100;
+        let total_recv = total_sent - lost;
+        let hop = Hop {
+            total_sent,
+            total_recv,
+            total_time: Duration::from_millis(latency_ms) * u32::try_from(total_recv).unwrap(),
+            javg: jitter_ms,
+            ..Hop::default()
+        };
+        let mos = hop.mos().unwrap();
+        assert!((mos - expected).abs() < 0.01, "{mos} != {expected}");
+    }
+
+    #[test]
+    fn test_mos_no_responses() {
+        let hop = Hop {
+            total_sent: 10,
+            ..Hop::default()
+        };
+        assert_eq!(None, hop.mos());
+    }
+
+    #[test]
+    fn test_window_evicts_old_rounds() {
+        let mut trace = State::new(StateConfig {
+            window: NonZeroUsize::new(5),
+            ..StateConfig::default()
+        });
+        let start = SystemTime::now();
+        // 5 rounds with no response, i.e. 100% loss.
+        for i in 0..5 {
+            update_single_hop_round(&mut trace, start, i, None);
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(5, hop.total_sent());
+        assert_eq!(0, hop.total_recv());
+        assert_eq_opt_f64(Some(&hop.loss_pct()), Some(&100.0));
+        // 3 good rounds, the oldest 3 lossy rounds age out.
+        for i in 5..8 {
+            update_single_hop_round(&mut trace, start, i, Some(Duration::from_millis(10)));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(5, hop.total_sent());
+        assert_eq!(3, hop.total_recv());
+        assert_eq_opt_f64(Some(&hop.loss_pct()), Some(&40.0));
+        // 2 more good rounds, all lossy rounds have aged out.
+        for (i, rtt) in [(8, 50), (9, 30)] {
+            update_single_hop_round(&mut trace, start, i, Some(Duration::from_millis(rtt)));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(5, hop.total_sent());
+        assert_eq!(5, hop.total_recv());
+        assert_eq!(5, hop.sample_count());
+        assert_eq_opt_f64(Some(&hop.loss_pct()), Some(&0.0));
+        assert_eq_opt_f64(Some(&hop.avg_ms()), Some(&22.0));
+        assert_eq_opt_f64(hop.best_ms().as_ref(), Some(&10.0));
+        assert_eq_opt_f64(hop.worst_ms().as_ref(), Some(&50.0));
+        assert_eq_opt_f64(Some(&hop.stddev_ms()), Some(&320_f64.sqrt()));
+        // A further good round evicts the first 10ms round.
+        update_single_hop_round(&mut trace, start, 10, Some(Duration::from_millis(40)));
+        let hop = &trace.hops()[0];
+        assert_eq!(5, hop.total_sent());
+        assert_eq_opt_f64(Some(&hop.avg_ms()), Some(&28.0));
+        assert_eq_opt_f64(hop.best_ms().as_ref(), Some(&10.0));
+    }
+
+    #[test]
+    fn test_no_window() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::now();
+        for i in 0..5 {
+            update_single_hop_round(&mut trace, start, i, None);
+        }
+        for i in 5..10 {
+            update_single_hop_round(&mut trace, start, i, Some(Duration::from_millis(10)));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(10, hop.total_sent());
+        assert_eq!(5, hop.total_recv());
+        assert_eq_opt_f64(Some(&hop.loss_pct()), Some(&50.0));
+    }
+
+    #[test]
+    fn test_extra_hosts() {
+        let mut trace = State::new(StateConfig::default());
+        let sent = SystemTime::now();
+        let mut complete = Probe::new(
+            Sequence(33000),
+            TraceId(0),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        )
+        .complete(
+            IpAddr::from([10, 0, 0, 1]),
+            sent + Duration::from_millis(10),
+            IcmpPacketType::NotApplicable,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        complete.extra_hosts.push(IpAddr::from([10, 0, 0, 2]));
+        let probes = [ProbeStatus::Complete(complete)];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(1),
+            CompletionReason::TargetFound,
+        ));
+        let hop = &trace.hops()[0];
+        assert_eq!(1, hop.total_recv());
+        assert_eq!(
+            vec![IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2])],
+            hop.addrs().copied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_last_quoted_payload() {
+        let mut trace = State::new(StateConfig::default());
+        let sent = SystemTime::now();
+        let complete = Probe::new(
+            Sequence(33000),
+            TraceId(0),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            sent,
+            Flags::empty(),
+        )
+        .complete(
+            IpAddr::from([10, 0, 0, 1]),
+            sent + Duration::from_millis(10),
+            IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+            None,
+            None,
+            None,
+            None,
+            Some(vec![0x08, 0x00, 0x12, 0x34]),
+        );
+        let probes = [ProbeStatus::Complete(complete)];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(1),
+            CompletionReason::TargetFound,
+        ));
+        assert_eq!(
+            Some([0x08, 0x00, 0x12, 0x34].as_slice()),
+            trace.hops()[0].last_quoted_payload()
+        );
+    }
+
+    #[test_case(AddrTieBreak::LowestIp, &[5, 9, 2, 2, 5, 9], 2; "lowest ip")]
+    #[test_case(AddrTieBreak::FirstSeen, &[5, 9, 2, 2, 5, 9], 5; "first seen")]
+    #[test_case(AddrTieBreak::MostRecent, &[5, 9, 2, 2, 5, 9], 9; "most recent")]
+    #[test_case(AddrTieBreak::LowestIp, &[5, 9, 9, 2], 9; "no tie")]
+    fn test_best_addr(tie_break: AddrTieBreak, hosts: &[u8], expected: u8) {
+        let mut trace = State::new(StateConfig::default());
+        let sent = SystemTime::now();
+        for (round, host) in hosts.iter().enumerate() {
+            let complete = Probe::new(
+                Sequence(33000),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(round),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::from([10, 0, 0, *host]),
+                sent + Duration::from_millis(10),
+                IcmpPacketType::NotApplicable,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            trace.update_from_round(&Round::new(
+                &[ProbeStatus::Complete(complete)],
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(
+            Some(IpAddr::from([10, 0, 0, expected])),
+            hop.best_addr(tie_break)
+        );
+        assert_eq!(
+            hosts.last().map(|host| IpAddr::from([10, 0, 0, *host])),
+            hop.last_addr()
+        );
+    }
+
+    #[test]
+    fn test_max_addrs() {
+        let mut trace = State::new(StateConfig {
+            max_addrs: NonZeroUsize::new(3),
+            ..StateConfig::default()
+        });
+        let sent = SystemTime::now();
+        for (round, host) in [1, 1, 1, 2, 2, 3, 4, 5].into_iter().enumerate() {
+            let complete = Probe::new(
+                Sequence(33000),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(round),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::from([10, 0, 0, host]),
+                sent + Duration::from_millis(10),
+                IcmpPacketType::NotApplicable,
+                None,
+                None,
+                None,
+                None,
+                None,
+            );
+            trace.update_from_round(&Round::new(
+                &[ProbeStatus::Complete(complete)],
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(8, hop.total_recv());
+        assert_eq!(3, hop.addr_count());
+        assert_eq!(
+            vec![
+                (IpAddr::from([10, 0, 0, 1]), 3),
+                (IpAddr::from([10, 0, 0, 2]), 2),
+                (IpAddr::from([10, 0, 0, 5]), 1),
+            ],
+            hop.addrs_with_counts()
+                .map(|(addr, count)| (*addr, *count))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_best_addr_no_response() {
+        assert_eq!(None, Hop::default().best_addr(AddrTieBreak::FirstSeen));
+        assert_eq!(None, Hop::default().last_addr());
+    }
+
+    #[test]
+    fn test_destination_reached() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::now();
+        assert!(!trace.destination_reached(State::default_flow_id()));
+        update_single_hop_round(&mut trace, start, 0, Some(Duration::from_millis(10)));
+        assert!(trace.destination_reached(State::default_flow_id()));
+    }
+
+    #[test]
+    fn test_destination_not_reached() {
+        let mut trace = State::new(StateConfig::default());
+        let sent = SystemTime::now();
+        let probe = |ttl: u8| {
+            Probe::new(
+                Sequence(33000 + u16::from(ttl)),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(ttl),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+        };
+        let probes = [
+            ProbeStatus::Complete(probe(1).complete(
+                IpAddr::from([10, 0, 0, 1]),
+                sent + Duration::from_millis(10),
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )),
+            ProbeStatus::Awaited(probe(2)),
+        ];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(2),
+            CompletionReason::RoundTimeLimitExceeded,
+        ));
+        assert_eq!(2, trace.hops().len());
+        assert!(!trace.destination_reached(State::default_flow_id()));
+        assert!(!trace.destination_silent(State::default_flow_id()));
+    }
+
+    #[test]
+    fn test_destination_silent() {
+        let mut trace = State::new(StateConfig::default());
+        let probe = Probe::new(
+            Sequence(33001),
+            TraceId(0),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(0),
+            SystemTime::now(),
+            Flags::empty(),
+        );
+        trace.update_from_round(&Round::new(
+            &[ProbeStatus::Awaited(probe)],
+            TimeToLive(1),
+            CompletionReason::DestinationSilent,
+        ));
+        assert!(!trace.destination_reached(State::default_flow_id()));
+        assert!(trace.destination_silent(State::default_flow_id()));
+    }
+
+    #[test]
+    fn test_start_end_time() {
+        let mut trace = State::new(StateConfig::default());
+        assert_eq!(None, trace.start_time());
+        assert_eq!(None, trace.end_time());
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for round in 0..2 {
+            let sent = start + Duration::from_secs(round);
+            let probe = Probe::new(
+                Sequence(33000),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(usize::try_from(round).unwrap()),
+                sent,
+                Flags::empty(),
+            );
+            let probes = [ProbeStatus::Complete(probe.complete(
+                IpAddr::from([10, 0, 0, 1]),
+                sent + Duration::from_millis(10),
+                IcmpPacketType::EchoReply(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ))];
+            trace.update_from_round(&Round::new(
+                &probes,
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+        }
+        assert_eq!(Some(start), trace.start_time());
+        assert_eq!(Some(start + Duration::from_millis(1010)), trace.end_time());
+    }
+
+    #[test]
+    fn test_reorder_ratio() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        // The probes for ttl 2, 3 and 5 have a sequence number which wraps and their responses
+        // arrive before that of a probe with a lower sequence number in the round.
+        let arrivals = [2, 0, 1, 4, 3];
+        let probes = (1..)
+            .zip(arrivals)
+            .map(|(ttl, arrival)| {
+                let probe = Probe::new(
+                    Sequence(65534_u16.wrapping_add(u16::from(ttl))),
+                    TraceId(0),
+                    Port(0),
+                    Port(0),
+                    TimeToLive(ttl),
+                    RoundId(0),
+                    start,
+                    Flags::empty(),
+                );
+                ProbeStatus::Complete(probe.complete(
+                    IpAddr::from([10, 0, 0, ttl]),
+                    start + Duration::from_millis(10 + arrival),
+                    IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ))
+            })
+            .collect::<Vec<_>>();
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(5),
+            CompletionReason::RoundTimeLimitExceeded,
+        ));
+        let ratios = trace
+            .hops()
+            .iter()
+            .map(Hop::reorder_ratio)
+            .collect::<Vec<_>>();
+        assert_eq_vec_f64(
+            Some(&ratios),
+            Some(&vec![0_f64, 1_f64, 1_f64, 0_f64, 1_f64]),
+        );
+    }
+
+    // Responses to probes of different rounds are never reordered, even if a response of a later
+    // round arrives before that of an earlier round.
+    #[test]
+    fn test_reorder_ratio_across_rounds() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for (round, arrival) in [2, 0, 1].into_iter().enumerate() {
+            let probe = Probe::new(
+                Sequence(33000 + u16::try_from(round).unwrap()),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(round),
+                start,
+                Flags::empty(),
+            );
+            let probes = [ProbeStatus::Complete(probe.complete(
+                IpAddr::from([10, 0, 0, 1]),
+                start + Duration::from_millis(10 + arrival),
+                IcmpPacketType::EchoReply(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ))];
+            trace.update_from_round(&Round::new(
+                &probes,
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(3, hop.total_recv());
+        assert!(hop.reorder_ratio().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_loss_run() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::now();
+        // respond, miss, miss, miss, respond, miss
+        let rtts = [Some(10), None, None, None, Some(10), None];
+        for (i, rtt) in (0..).zip(rtts) {
+            update_single_hop_round(&mut trace, start, i, rtt.map(Duration::from_millis));
+        }
+        let hop = &trace.hops()[0];
+        assert_eq!(3, hop.max_loss_run());
+        assert_eq!(1, hop.current_loss_run());
+    }
+
+    #[test]
+    fn test_time_to_first() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for round in 0..4 {
+            let sent = start + Duration::from_secs(round);
+            let probe = Probe::new(
+                Sequence(33000),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(usize::try_from(round).unwrap()),
+                sent,
+                Flags::empty(),
+            );
+            let status = if round < 2 {
+                ProbeStatus::Awaited(probe)
+            } else {
+                ProbeStatus::Complete(probe.complete(
+                    IpAddr::from([10, 0, 0, 1]),
+                    sent + Duration::from_millis(500),
+                    IcmpPacketType::EchoReply(IcmpPacketCode(0)),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ))
+            };
+            trace.update_from_round(&Round::new(
+                &[status],
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+            let hop = &trace.hops()[0];
+            match round {
+                0 | 1 => assert_eq!(None, hop.time_to_first_ms()),
+                _ => assert_eq!(Some(2500_f64), hop.time_to_first_ms()),
+            }
+        }
+    }
+
+    #[test_case(&[1, 2, 1, 2], 2, true; "alternating")]
+    #[test_case(&[1, 2, 1, 2, 1, 2], 4, true; "alternating repeatedly")]
+    #[test_case(&[1, 1, 1, 1], 0, false; "stable")]
+    #[test_case(&[1, 1, 2, 2], 0, false; "route change")]
+    #[test_case(&[1, 2, 1, 1], 1, false; "single oscillation")]
+    #[test_case(&[1, 2, 3, 1, 2, 3], 0, false; "ecmp")]
+    #[test_case(&[1, 2, 1, 3, 1, 2], 2, false; "ecmp alternating")]
+    fn test_is_flapping(hosts: &[u8], expected_oscillations: usize, expected: bool) {
+        let flows = hosts.iter().map(|&host| (0, host)).collect::<Vec<_>>();
+        let hop = flap_trace(&flows);
+        assert_eq!(expected_oscillations, hop.oscillations());
+        assert_eq!(expected, hop.is_flapping());
+    }
+
+    #[test_case(&[(1, 1), (2, 2), (1, 1), (2, 2), (1, 1), (2, 2)], 0, false; "ecmp per flow")]
+    #[test_case(&[(1, 1), (2, 3), (1, 2), (2, 3), (1, 1), (2, 3), (1, 2)], 2, true; "flapping flow")]
+    #[test_case(&[(1, 1), (2, 2), (1, 2), (2, 1), (1, 1), (2, 2)], 2, false; "ecmp alternating flows")]
+    fn test_is_flapping_per_flow(
+        flows: &[(u16, u8)],
+        expected_oscillations: usize,
+        expected: bool,
+    ) {
+        let hop = flap_trace(flows);
+        assert_eq!(expected_oscillations, hop.oscillations());
+        assert_eq!(expected, hop.is_flapping());
+    }
+
+    #[test]
+    fn test_sample_rounds_and_addrs() {
+        let hop = flap_trace(&[(1, 1), (2, 2), (1, 3)]);
+        assert_eq!(vec![2, 1, 0], hop.sample_rounds().collect::<Vec<_>>());
+        assert_eq!(
+            vec![
+                Some(IpAddr::from([10, 0, 0, 3])),
+                Some(IpAddr::from([10, 0, 0, 2])),
+                Some(IpAddr::from([10, 0, 0, 1]))
+            ],
+            hop.sample_addrs().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_sample_rounds_and_addrs_retained() {
+        let mut trace = State::new(StateConfig {
+            max_samples: 2,
+            ..StateConfig::default()
+        });
+        let start = SystemTime::now();
+        update_single_hop_round(&mut trace, start, 0, Some(Duration::from_millis(10)));
+        update_single_hop_round(&mut trace, start, 1, Some(Duration::from_millis(10)));
+        update_single_hop_round(&mut trace, start, 2, None);
+        let hop = &trace.hops()[0];
+        assert_eq!(2, hop.samples().len());
+        assert_eq!(vec![2, 1], hop.sample_rounds().collect::<Vec<_>>());
+        assert_eq!(
+            vec![None, Some(IpAddr::from([10, 0, 0, 1]))],
+            hop.sample_addrs().collect::<Vec<_>>()
+        );
+    }
+
+    /// Trace a single hop over one round per `(src_port, host)` pair and return the hop.
+    fn flap_trace(flows: &[(u16, u8)]) -> Hop {
+        let mut trace = State::new(StateConfig::default());
+        for (round, &(src_port, host)) in flows.iter().enumerate() {
+            let sent = SystemTime::now();
+            let probes = [ProbeStatus::Complete(
+                Probe::new(
+                    Sequence(33000 + u16::try_from(round).unwrap()),
+                    TraceId(0),
+                    Port(src_port),
+                    Port(0),
+                    TimeToLive(1),
+                    RoundId(round),
+                    sent,
+                    Flags::empty(),
+                )
+                .complete(
+                    IpAddr::from([10, 0, 0, host]),
+                    sent + Duration::from_millis(10),
+                    IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            )];
+            trace.update_from_round(&Round::new(
+                &probes,
+                TimeToLive(1),
+                CompletionReason::TargetFound,
+            ));
+        }
+        trace.hops()[0].clone()
+    }
+
+    #[test_case("10.0.0.1", "10.0.0.2", IcmpPacketType::Unreachable(IcmpPacketCode(3)), 1_f64; "port unreachable from target")]
+    #[test_case("10.0.0.1", "10.0.0.2", IcmpPacketType::EchoReply(IcmpPacketCode(0)), 1_f64; "echo reply from target")]
+    #[test_case("10.0.0.1", "10.0.0.99", IcmpPacketType::Unreachable(IcmpPacketCode(3)), 0.4; "port unreachable from other source")]
+    #[test_case("10.0.0.1", "10.0.0.2", IcmpPacketType::Unreachable(IcmpPacketCode(1)), 0.8; "host unreachable from target")]
+    #[test_case("10.0.0.2", "10.0.0.99", IcmpPacketType::Unreachable(IcmpPacketCode(3)), 0.2; "target seen at lower ttl")]
+    fn test_destination_confidence(
+        first_hop: &str,
+        host: &str,
+        icmp_packet_type: IcmpPacketType,
+        expected: f64,
+    ) {
+        let mut trace = State::new(StateConfig::default());
+        let target_addr = IpAddr::from([10, 0, 0, 2]);
+        let probe = |ttl: u8, host: IpAddr, icmp_packet_type: IcmpPacketType| {
+            let sent = SystemTime::now();
+            ProbeStatus::Complete(
+                Probe::new(
+                    Sequence(33000 + u16::from(ttl)),
+                    TraceId(0),
+                    Port(0),
+                    Port(0),
+                    TimeToLive(ttl),
+                    RoundId(0),
+                    sent,
+                    Flags::empty(),
+                )
+                .complete(
+                    host,
+                    sent + Duration::from_millis(10),
+                    icmp_packet_type,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            )
+        };
+        let probes = [
+            probe(
+                1,
+                IpAddr::from_str(first_hop).unwrap(),
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+            ),
+            probe(2, IpAddr::from_str(host).unwrap(), icmp_packet_type),
+        ];
+        assert!(
+            trace
+                .destination_confidence(State::default_flow_id(), target_addr)
+                .abs()
+                < f64::EPSILON
+        );
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(2),
+            CompletionReason::TargetFound,
+        ));
+        let confidence = trace.destination_confidence(State::default_flow_id(), target_addr);
+        assert!(
+            (confidence - expected).abs() < 1e-9,
+            "expected {expected}, got {confidence}"
+        );
+    }
+
+    // Test a path which never changes, loses nothing and has a constant round trip time scores
+    // 100, whereas a path which flaps at each hop, loses most probes to the target and has a
+    // varying round trip time scores low.
+    #[test_case(false, 100_f64, 100_f64; "stable")]
+    #[test_case(true, 30_f64, 40_f64; "flapping and lossy")]
+    fn test_path_stability(unstable: bool, min: f64, max: f64) {
+        let mut trace = State::new(StateConfig::default());
+        assert!(trace.path_stability(State::default_flow_id()).abs() < f64::EPSILON);
+        let start = SystemTime::now();
+        let probe = |round: usize, ttl: u8, response: Option<(IpAddr, u64)>| {
+            let sent = start + Duration::from_secs(round as u64);
+            let probe = Probe::new(
+                Sequence(33000 + u16::from(ttl)),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(ttl),
+                RoundId(round),
+                sent,
+                Flags::empty(),
+            );
+            match response {
+                Some((host, rtt_ms)) => ProbeStatus::Complete(probe.complete(
+                    host,
+                    sent + Duration::from_millis(rtt_ms),
+                    IcmpPacketType::NotApplicable,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                )),
+                None => ProbeStatus::Awaited(probe),
+            }
+        };
+        for round in 0..10 {
+            let probes = if unstable {
+                // the first hop cycles between three addresses and the target, which alternates
+                // between two addresses, only responds every third round.
+                let first_hop = IpAddr::from([10, 0, 0, 1 + (round % 3) as u8]);
+                let target = IpAddr::from([10, 0, 1, 1 + (round / 3 % 2) as u8]);
+                let rtt_ms = if round / 3 % 2 == 0 { 10 } else { 100 };
+                [
+                    probe(round, 1, Some((first_hop, 5))),
+                    probe(round, 2, (round % 3 == 0).then_some((target, rtt_ms))),
+                ]
+            } else {
+                [
+                    probe(round, 1, Some((IpAddr::from([10, 0, 0, 1]), 5))),
+                    probe(round, 2, Some((IpAddr::from([10, 0, 1, 1]), 10))),
+                ]
+            };
+            trace.update_from_round(&Round::new(
+                &probes,
+                TimeToLive(2),
+                CompletionReason::TargetFound,
+            ));
+        }
+        let score = trace.path_stability(State::default_flow_id());
+        assert!(
+            (min..=max).contains(&score),
+            "expected between {min} and {max}, got {score}"
+        );
+    }
+
+    #[test]
+    fn test_destination_rtt() {
+        let mut trace = State::new(StateConfig::default());
+        let start = SystemTime::now();
+        let probe = |round: usize, ttl: u8, rtt_ms: u64| {
+            let sent = start + Duration::from_secs(round as u64);
+            ProbeStatus::Complete(
+                Probe::new(
+                    Sequence(33000 + u16::from(ttl)),
+                    TraceId(0),
+                    Port(0),
+                    Port(0),
+                    TimeToLive(ttl),
+                    RoundId(round),
+                    sent,
+                    Flags::empty(),
+                )
+                .complete(
+                    IpAddr::from([10, 0, 0, ttl]),
+                    sent + Duration::from_millis(rtt_ms),
+                    IcmpPacketType::NotApplicable,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            )
+        };
+        // The intermediate hop de-prioritizes ICMP and so is slower than the destination.
+        for (round, (hop_rtt, dest_rtt)) in [(50, 20), (60, 30)].into_iter().enumerate() {
+            let probes = [probe(round, 1, hop_rtt), probe(round, 2, dest_rtt)];
+            trace.update_from_round(&Round::new(
+                &probes,
+                TimeToLive(2),
+                CompletionReason::TargetFound,
+            ));
+        }
+        // A round which did not reach the destination does not contribute.
+        let probes = [probe(2, 1, 70), probe(2, 2, 90)];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(2),
+            CompletionReason::RoundTimeLimitExceeded,
+        ));
+        let destination_rtt = trace.destination_rtt(State::default_flow_id());
+        assert_eq!(2, destination_rtt.total_recv());
+        assert_eq_opt_f64(Some(&destination_rtt.avg_ms()), Some(&25_f64));
+        assert_eq_opt_f64(destination_rtt.last_ms().as_ref(), Some(&30_f64));
+        assert_eq_opt_f64(destination_rtt.best_ms().as_ref(), Some(&20_f64));
+        assert_eq_opt_f64(destination_rtt.worst_ms().as_ref(), Some(&30_f64));
+        assert_eq_opt_f64(Some(&destination_rtt.stddev_ms()), Some(&50_f64.sqrt()));
+        let max_hop_rtt = trace
+            .hops()
+            .iter()
+            .filter_map(Hop::worst_ms)
+            .fold(0_f64, f64::max);
+        assert_eq_opt_f64(Some(&max_hop_rtt), Some(&90_f64));
+        assert!(destination_rtt.avg_ms() < trace.hops()[0].avg_ms());
+    }
+
+    #[test]
+    fn test_destination_rtt_not_reached() {
+        let mut trace = State::new(StateConfig::default());
+        assert_eq!(
+            0,
+            trace.destination_rtt(State::default_flow_id()).total_recv()
+        );
+        let sent = SystemTime::now();
+        let probes = [ProbeStatus::Complete(
+            Probe::new(
+                Sequence(33000),
+                TraceId(0),
+                Port(0),
+                Port(0),
+                TimeToLive(1),
+                RoundId(0),
+                sent,
+                Flags::empty(),
+            )
+            .complete(
+                IpAddr::from([10, 0, 0, 1]),
+                sent + Duration::from_millis(10),
+                IcmpPacketType::TimeExceeded(IcmpPacketCode(0)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ),
+        )];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(1),
+            CompletionReason::RoundTimeLimitExceeded,
+        ));
+        let destination_rtt = trace.destination_rtt(State::default_flow_id());
+        assert_eq!(0, destination_rtt.total_recv());
+        assert_eq!(None, destination_rtt.last_ms());
+    }
+
+    const ASN_HOP1: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const ASN_HOP2: Ipv4Addr = Ipv4Addr::new(10, 0, 1, 2);
+    const ASN_HOP2_REROUTED: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+
+    fn asn_lookup(addr: IpAddr) -> Option<String> {
+        match addr {
+            IpAddr::V4(ASN_HOP1) => Some(String::from("64500")),
+            IpAddr::V4(ASN_HOP2) => Some(String::from("64501")),
+            IpAddr::V4(ASN_HOP2_REROUTED) => Some(String::from("64502")),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_asn_change() {
+        let mut tracker = AsnTracker::default();
+        let mut trace = State::new(StateConfig::default());
+        update_route_round(&mut trace, 0, &[ASN_HOP1, ASN_HOP2]);
+        assert!(tracker.update(&trace, asn_lookup).is_empty());
+        update_route_round(&mut trace, 1, &[ASN_HOP1, ASN_HOP2_REROUTED]);
+        assert_eq!(
+            vec![AsnChange {
+                ttl: 2,
+                old: String::from("64501"),
+                new: String::from("64502"),
+            }],
+            tracker.update(&trace, asn_lookup)
+        );
+        assert!(tracker.update(&trace, asn_lookup).is_empty());
+    }
+
+    #[test]
+    fn test_asn_unchanged() {
+        let mut tracker = AsnTracker::default();
+        let mut trace = State::new(StateConfig::default());
+        update_route_round(&mut trace, 0, &[ASN_HOP1, ASN_HOP2]);
+        update_route_round(&mut trace, 1, &[ASN_HOP1, ASN_HOP2]);
+        assert!(tracker.update(&trace, asn_lookup).is_empty());
+        assert!(tracker.update(&trace, asn_lookup).is_empty());
+    }
+
+    #[test]
+    fn test_asn_unknown() {
+        let mut tracker = AsnTracker::default();
+        let mut trace = State::new(StateConfig::default());
+        update_route_round(&mut trace, 0, &[ASN_HOP1, ASN_HOP2]);
+        assert!(tracker.update(&trace, |_| None).is_empty());
+        update_route_round(&mut trace, 1, &[ASN_HOP1, ASN_HOP2_REROUTED]);
+        assert!(tracker.update(&trace, asn_lookup).is_empty());
+    }
+
+    /// Update the state from a round in which the hop at each ttl responds from the given `hosts`.
+    fn update_route_round(trace: &mut State, round: u16, hosts: &[Ipv4Addr]) {
+        let sent = SystemTime::now();
+        let probes = (1..)
+            .zip(hosts)
+            .map(|(ttl, host)| {
+                let probe = Probe::new(
+                    Sequence(33000 + round * 100 + u16::from(ttl)),
+                    TraceId(0),
+                    Port(0),
+                    Port(0),
+                    TimeToLive(ttl),
+                    RoundId(usize::from(round)),
+                    sent,
+                    Flags::empty(),
+                );
+                ProbeStatus::Complete(probe.complete(
+                    IpAddr::V4(*host),
+                    sent + Duration::from_millis(10),
+                    IcmpPacketType::NotApplicable,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                ))
+            })
+            .collect::<Vec<_>>();
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(u8::try_from(hosts.len()).unwrap()),
+            CompletionReason::TargetFound,
+        ));
+    }
+
+    /// Update the state from a round with a single hop which responds after `rtt`, if any.
+    fn update_single_hop_round(
+        trace: &mut State,
+        start: SystemTime,
+        round: u16,
+        rtt: Option<Duration>,
+    ) {
+        let sent = start + Duration::from_secs(u64::from(round));
+        let probe = Probe::new(
+            Sequence(33000 + round),
+            TraceId(0),
+            Port(0),
+            Port(0),
+            TimeToLive(1),
+            RoundId(usize::from(round)),
+            sent,
+            Flags::empty(),
+        );
+        let status = match rtt {
+            Some(rtt) => ProbeStatus::Complete(probe.complete(
+                IpAddr::from([10, 0, 0, 1]),
+                sent + rtt,
+                IcmpPacketType::NotApplicable,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )),
+            None => ProbeStatus::Awaited(probe),
+        };
+        let probes = [status];
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(1),
+            CompletionReason::TargetFound,
+        ));
+    }
+
     #[expect(clippy::needless_pass_by_value)]
     fn assert_eq_opt<T: Eq + Debug>(actual: Option<T>, expected: Option<T>) {
         assert_eq_inner(actual.as_ref(), expected.as_ref(), |a, e| a == e);
@@ -3,14 +3,16 @@ use crate::config::StrategyConfig;
 use crate::error::{Error, Result};
 use crate::net::Network;
 use crate::probe::{
-    IcmpProtocolResponse, ProbeStatus, ProtocolResponse, Response, ResponseData,
-    TcpProtocolResponse, UdpProtocolResponse,
+    IcmpPacketCode, IcmpProtocolResponse, ProbeStatus, ProtocolResponse, Response, ResponseData,
+    TcpProtocolResponse, UdpProtocolResponse, icmp_ip_identification,
 };
 use crate::types::{Checksum, Sequence, TimeToLive, TraceId};
 use crate::{
-    Extensions, IcmpPacketType, MultipathStrategy, PortDirection, Probe, Protocol, TypeOfService,
+    Extensions, HopAddrFilter, IcmpPacketType, MultipathStrategy, PortDirection, Probe, Protocol,
+    TraceObserver, TypeOfService, UnreachableMode,
 };
 use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use tracing::instrument;
 
@@ -47,6 +49,23 @@ pub enum CompletionReason {
     TargetFound,
     /// The round ended because the time exceeded the configured maximum round time.
     RoundTimeLimitExceeded,
+    /// The round ended without a response from the target for the configured maximum number of
+    /// consecutive rounds, and so the trace is finished.
+    DestinationSilent,
+}
+
+/// Indicates why the tracer stopped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StopReason {
+    /// The tracer stopped because a round handler returned `Action::Stop`.
+    Stopped,
+    /// The tracer stopped because the configured maximum number of rounds were completed.
+    MaxRounds,
+    /// The tracer stopped because the configured `max_packets` budget was exhausted.
+    PacketsExhausted,
+    /// The tracer stopped because the target was silent for the configured maximum number of
+    /// consecutive rounds.
+    DestinationSilent,
 }
 
 /// The action to take after the completion of a round.
@@ -69,6 +88,8 @@ impl From<()> for Action {
 pub struct Strategy<F> {
     config: StrategyConfig,
     publish: F,
+    observer: Option<Arc<dyn TraceObserver>>,
+    hop_addr_filter: HopAddrFilter,
 }
 
 impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
@@ -78,19 +99,40 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
         Self {
             config: *config,
             publish,
+            observer: None,
+            hop_addr_filter: HopAddrFilter::default(),
+        }
+    }
+
+    /// Register a `TraceObserver` to be notified of the progress of the trace.
+    #[must_use]
+    pub fn with_observer(self, observer: Option<Arc<dyn TraceObserver>>) -> Self {
+        Self { observer, ..self }
+    }
+
+    /// Restrict which hops are probed, by address, after the first round.
+    #[must_use]
+    pub fn with_hop_addr_filter(self, hop_addr_filter: HopAddrFilter) -> Self {
+        Self {
+            hop_addr_filter,
+            ..self
         }
     }
 
     /// Run a continuous trace and publish results.
+    ///
+    /// Returns the reason the trace stopped.
     #[instrument(skip(self, network), level = "trace")]
-    pub fn run<N: Network>(self, mut network: N) -> Result<()> {
+    pub fn run<N: Network>(self, mut network: N) -> Result<StopReason> {
         let mut state = TracerState::new(self.config);
-        while !state.finished(self.config.max_rounds) {
+        loop {
+            if let Some(reason) = state.stop_reason(self.config.max_rounds) {
+                return Ok(reason);
+            }
             self.send_request(&mut network, &mut state)?;
             self.recv_response(&mut network, &mut state)?;
             self.update_round(&mut state);
         }
-        Ok(())
     }
 
     /// Send the next probe if required.
@@ -104,19 +146,37 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
     ///         round
     ///     otherwise:
     ///       - the number of unknown-in-flight probes is lower than the maximum allowed
+    ///
+    /// If `continue_past_target` is enabled then conditions 1 and 3A do not apply and the full
+    /// range of ttl values up to the maximum allowed ttl is probed every round.
+    ///
+    /// No probe is sent, regardless of the above, once the `max_packets` budget is exhausted.
+    ///
+    /// If a `send_interval` is configured then no probe is sent until at least that interval has
+    /// elapsed since the previous probe was sent, including the final probe of the prior round.
+    ///
+    /// Any ttl whose hop address is known and is not permitted by the `HopAddrFilter` is skipped.
     fn send_request<N: Network>(&self, network: &mut N, st: &mut TracerState) -> Result<()> {
-        let can_send_ttl = if let Some(target_ttl) = st.target_ttl() {
-            st.ttl() <= target_ttl
-        } else {
-            st.ttl() - st.max_received_ttl().unwrap_or_default()
-                < TimeToLive(self.config.max_inflight.0)
+        st.skip_filtered_ttls(&self.hop_addr_filter, self.config.max_ttl);
+        let continue_past_target = self.config.continue_past_target;
+        let can_send_ttl = match st.target_ttl() {
+            Some(target_ttl) if !continue_past_target => st.ttl() <= target_ttl,
+            _ => {
+                st.ttl() - st.max_received_ttl().unwrap_or_default()
+                    < TimeToLive(self.config.max_inflight.0)
+            }
         };
-        if !st.target_found() && st.ttl() <= self.config.max_ttl && can_send_ttl {
+        if (continue_past_target || !st.target_found())
+            && st.ttl() <= self.config.max_ttl
+            && can_send_ttl
+            && !st.packets_exhausted()
+            && st.send_due(SystemTime::now())
+        {
             let sent = SystemTime::now();
             match self.config.protocol {
                 Protocol::Icmp | Protocol::Udp => {
                     let probe = st.next_probe(sent);
-                    Self::do_send(network, st, probe)?;
+                    self.do_send(network, st, probe)?;
                 }
                 Protocol::Tcp => {
                     let mut probe = if st.round_has_capacity() {
@@ -124,7 +184,7 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
                     } else {
                         return Err(Error::InsufficientCapacity);
                     };
-                    while let Err(err) = Self::do_send(network, st, probe) {
+                    while let Err(err) = self.do_send(network, st, probe) {
                         match err {
                             Error::AddressInUse(_) => {
                                 if st.round_has_capacity() {
@@ -146,10 +206,21 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
     ///
     /// Some errors are transient and should not be considered fatal.  In these cases we mark the
     /// probe as failed and continue.
-    #[instrument(skip(network, st), level = "trace")]
-    fn do_send<N: Network>(network: &mut N, st: &mut TracerState, probe: Probe) -> Result<()> {
+    #[instrument(skip(self, network, st), level = "trace")]
+    fn do_send<N: Network>(
+        &self,
+        network: &mut N,
+        st: &mut TracerState,
+        probe: Probe,
+    ) -> Result<()> {
+        let sent = self.observer.as_ref().map(|_| probe.clone());
         match network.send_probe(probe) {
-            Ok(()) => Ok(()),
+            Ok(()) => {
+                if let (Some(observer), Some(probe)) = (&self.observer, sent) {
+                    observer.on_probe_sent(&probe);
+                }
+                Ok(())
+            }
             Err(Error::ProbeFailed(_)) => {
                 st.fail_probe();
                 Ok(())
@@ -183,8 +254,17 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
             && self.validate(resp.data())
         {
             let resp = StrategyResponse::from((resp, &self.config));
-            if self.check_trace_id(resp.trace_id) && st.in_round(resp.sequence) {
-                st.complete_probe(resp);
+            if self.check_trace_id(resp.trace_id)
+                && st.in_round(resp.sequence)
+                && st.sent_in_round(resp.sequence)
+            {
+                let sequence = resp.sequence;
+                if st.complete_probe(resp)
+                    && let Some(observer) = &self.observer
+                    && let ProbeStatus::Complete(complete) = st.probe_at(sequence)
+                {
+                    observer.on_response(&complete);
+                }
             }
         }
         Ok(())
@@ -195,27 +275,75 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
     /// A round is considered to be complete when:
     ///
     /// 1 - the round has exceeded the minimum round duration AND
-    /// 2 - the duration since the last packet was received exceeds the grace period AND
+    /// 2 - the duration since the last packet was received exceeds the grace period (or the target
+    ///     grace period if the last packet was received from the target) AND
     /// 3 - either:
     ///     A - the target has been found OR
-    ///     B - the target has not been found and the round has exceeded the maximum round duration
+    ///     B - the `max_packets` budget has been exhausted OR
+    ///     C - the target has not been found and the round has exceeded the maximum round duration
+    ///
+    /// If `continue_past_target` is enabled then, in place of 3A, all ttl values up to the maximum
+    /// allowed ttl must have been probed.
+    ///
+    /// If a `send_interval` is configured then the maximum round duration is extended as needed
+    /// such that the full range of ttl values can be probed within a round.
     fn update_round(&self, st: &mut TracerState) {
         let now = SystemTime::now();
         let round_duration = now.duration_since(st.round_start()).unwrap_or_default();
         let round_min = round_duration > self.config.min_round_duration;
-        let grace_exceeded = exceeds(st.received_time(), now, self.config.grace_duration);
-        let round_max = round_duration > self.config.max_round_duration;
-        let target_found = st.target_found();
-        if round_min && grace_exceeded && target_found || round_max {
+        let grace_exceeded = exceeds(st.received_time(), now, self.grace_duration(st));
+        let round_max = round_duration > self.max_round_duration();
+        let target_found = if self.config.continue_past_target {
+            st.ttl() > self.config.max_ttl
+        } else {
+            st.target_found()
+        };
+        if round_min && grace_exceeded && (target_found || st.packets_exhausted()) || round_max {
+            st.update_silent_rounds();
             let action = self.publish_trace(st);
             st.advance_round(self.config.first_ttl, action);
         }
     }
 
+    /// The maximum duration of a round.
+    ///
+    /// This is the configured `max_round_duration`, or the time needed to send a probe for every
+    /// ttl from `first_ttl` to `max_ttl` at the configured `send_interval` if that is longer.
+    fn max_round_duration(&self) -> Duration {
+        let ttl_count = u32::from(
+            self.config
+                .max_ttl
+                .0
+                .saturating_sub(self.config.first_ttl.0),
+        ) + 1;
+        self.config
+            .send_interval
+            .map_or(self.config.max_round_duration, |send_interval| {
+                self.config
+                    .max_round_duration
+                    .max(send_interval.saturating_mul(ttl_count))
+            })
+    }
+
+    /// The grace period to apply after the last packet received in the round.
+    ///
+    /// A separate (typically longer) grace period applies if the last packet was received from the
+    /// target host, as intermediate hops may rate limit responses.
+    const fn grace_duration(&self, st: &TracerState) -> Duration {
+        if st.received_from_target() {
+            self.config.target_grace_duration
+        } else {
+            self.config.grace_duration
+        }
+    }
+
     /// Publish details of all `ProbeStatus` in the completed round.
     ///
     /// If the round completed without receiving an `EchoReply` from the target host then we also
     /// publish the next `ProbeStatus` which is assumed to represent the TTL of the target host.
+    ///
+    /// If the target host has been silent for `max_silent_rounds` consecutive rounds then the
+    /// round is published as `DestinationSilent` and the trace is finished.
     #[instrument(skip(self, state), level = "trace")]
     fn publish_trace(&self, state: &TracerState) -> Action {
         let max_received_ttl = if let Some(target_ttl) = state.target_ttl() {
@@ -232,10 +360,16 @@ impl<F: Fn(&Round<'_>) -> Action> Strategy<F> {
         let largest_ttl = max_received_ttl;
         let reason = if state.target_found() {
             CompletionReason::TargetFound
+        } else if state.destination_silent() {
+            CompletionReason::DestinationSilent
         } else {
             CompletionReason::RoundTimeLimitExceeded
         };
-        (self.publish)(&Round::new(probes, largest_ttl, reason))
+        let round = Round::new(probes, largest_ttl, reason);
+        if let Some(observer) = &self.observer {
+            observer.on_round_complete(&round);
+        }
+        (self.publish)(&round)
     }
 
     /// Check if the `TraceId` matches the expected value for this tracer.
@@ -318,6 +452,8 @@ struct StrategyResponse {
     addr: IpAddr,
     is_target: bool,
     exts: Option<Extensions>,
+    quoted_payload: Option<Vec<u8>>,
+    response_tos: Option<TypeOfService>,
 }
 
 impl From<(Response, &StrategyConfig)> for StrategyResponse {
@@ -337,11 +473,20 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target,
                     exts,
+                    quoted_payload: data.quoted_payload,
+                    response_tos: data.response_tos,
                 }
             }
             Response::DestinationUnreachable(data, code, exts) => {
                 let proto_resp = ProtocolStrategyResponse::from((data.proto_resp, config));
-                let is_target = data.addr == config.target_addr;
+                let is_target = (config.protocol != Protocol::Icmp
+                    || config.icmp_unreachable_reached)
+                    && match config.unreachable_mode {
+                        UnreachableMode::Address => data.addr == config.target_addr,
+                        UnreachableMode::Code => {
+                            is_target_unreachable_code(config.target_addr, code)
+                        }
+                    };
                 Self {
                     icmp_packet_type: IcmpPacketType::Unreachable(code),
                     trace_id: proto_resp.trace_id,
@@ -353,6 +498,8 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target,
                     exts,
+                    quoted_payload: data.quoted_payload,
+                    response_tos: data.response_tos,
                 }
             }
             Response::EchoReply(data, code) => {
@@ -368,6 +515,8 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target: true,
                     exts: None,
+                    quoted_payload: None,
+                    response_tos: data.response_tos,
                 }
             }
             Response::TcpReply(data) | Response::TcpRefused(data) => {
@@ -383,12 +532,49 @@ impl From<(Response, &StrategyConfig)> for StrategyResponse {
                     addr: data.addr,
                     is_target: true,
                     exts: None,
+                    quoted_payload: None,
+                    response_tos: data.response_tos,
                 }
             }
         }
     }
 }
 
+/// Does a `DestinationUnreachable` code indicate that the probe reached the target host?
+///
+/// Port unreachable (and protocol unreachable for IPv4) is sent by the target host itself,
+/// whereas all other codes, such as net or host unreachable, are sent by a router on the path.
+pub const fn is_target_unreachable_code(target_addr: IpAddr, code: IcmpPacketCode) -> bool {
+    match target_addr {
+        IpAddr::V4(_) => matches!(code.0, 2 | 3),
+        IpAddr::V6(_) => code.0 == 4,
+    }
+}
+
+/// The trace identifier of an ICMP probe response.
+///
+/// If the quoted ICMP identifier does not match the trace, such as when a router rewrote it, then
+/// the IP identification of the quoted original datagram, which is derived from the trace
+/// identifier and sequence, is tried as a fallback.
+fn icmp_trace_id(
+    identifier: u16,
+    sequence: u16,
+    ip_identification: Option<u16>,
+    config: &StrategyConfig,
+) -> TraceId {
+    let trace_id = TraceId(identifier);
+    match ip_identification {
+        Some(ip_identification)
+            if trace_id != config.trace_identifier
+                && ip_identification
+                    == icmp_ip_identification(config.trace_identifier, Sequence(sequence)) =>
+        {
+            config.trace_identifier
+        }
+        _ => trace_id,
+    }
+}
+
 /// Derived response sequence based on strategy config.
 #[derive(Debug)]
 struct ProtocolStrategyResponse {
@@ -406,8 +592,9 @@ impl From<(ProtocolResponse, &StrategyConfig)> for ProtocolStrategyResponse {
                 identifier,
                 sequence,
                 tos,
+                ip_identification,
             }) => Self {
-                trace_id: TraceId(identifier),
+                trace_id: icmp_trace_id(identifier, sequence, ip_identification, config),
                 sequence: Sequence(sequence),
                 tos,
                 expected_udp_checksum: None,
@@ -479,11 +666,15 @@ impl From<(ProtocolResponse, &StrategyConfig)> for ProtocolStrategyResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::StateConfig;
+    use crate::constants::MIN_DYNAMIC_PORT;
     use crate::net::MockNetwork;
-    use crate::probe::IcmpPacketCode;
-    use crate::{MaxRounds, Port};
-    use std::net::Ipv4Addr;
+    use crate::{MaxPackets, MaxRounds, MultiResponseMode, Port, ProbeComplete, RoundId, State};
+    use std::net::{Ipv4Addr, Ipv6Addr};
     use std::num::NonZeroUsize;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+    use test_case::test_case;
 
     #[test]
     fn test_time_exceeded_target_response() {
@@ -531,6 +722,7 @@ mod tests {
     fn test_destination_unreachable_target_response() {
         let config = StrategyConfig {
             target_addr: IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+            protocol: Protocol::Udp,
             ..Default::default()
         };
         let now = SystemTime::now();
@@ -549,6 +741,53 @@ mod tests {
         assert!(resp.exts.is_none());
     }
 
+    #[test_case(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 3, true; "port unreachable from target")]
+    #[test_case(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 2, true; "protocol unreachable from target")]
+    #[test_case(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 1, false; "host unreachable from target")]
+    #[test_case(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 1, false; "host unreachable from midpoint")]
+    #[test_case(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 0, false; "net unreachable from midpoint")]
+    #[test_case(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 3, true; "port unreachable from other")]
+    #[test_case(IpAddr::V6(Ipv6Addr::LOCALHOST), 4, true; "ipv6 port unreachable")]
+    #[test_case(IpAddr::V6(Ipv6Addr::LOCALHOST), 3, false; "ipv6 address unreachable")]
+    fn test_destination_unreachable_code_mode(target_addr: IpAddr, code: u8, expected: bool) {
+        let config = StrategyConfig {
+            target_addr,
+            protocol: Protocol::Udp,
+            unreachable_mode: UnreachableMode::Code,
+            ..Default::default()
+        };
+        let now = SystemTime::now();
+        let resp_data =
+            Response::DestinationUnreachable(response_data(now), IcmpPacketCode(code), None);
+        let resp = StrategyResponse::from((resp_data, &config));
+        assert_eq!(
+            resp.icmp_packet_type,
+            IcmpPacketType::Unreachable(IcmpPacketCode(code))
+        );
+        assert_eq!(resp.is_target, expected);
+    }
+
+    #[test_case(false, false; "echo reply only")]
+    #[test_case(true, true; "unreachable reached")]
+    fn test_destination_unreachable_icmp_mode(icmp_unreachable_reached: bool, expected: bool) {
+        let config = StrategyConfig {
+            target_addr: IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+            protocol: Protocol::Icmp,
+            icmp_unreachable_reached,
+            ..Default::default()
+        };
+        let now = SystemTime::now();
+        let resp_data =
+            Response::DestinationUnreachable(response_data(now), IcmpPacketCode(3), None);
+        let resp = StrategyResponse::from((resp_data, &config));
+        assert_eq!(
+            resp.icmp_packet_type,
+            IcmpPacketType::Unreachable(IcmpPacketCode(3))
+        );
+        assert_eq!(resp.addr, IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+        assert_eq!(resp.is_target, expected);
+    }
+
     #[test]
     fn test_destination_unreachable_not_target_response() {
         let config = StrategyConfig::default();
@@ -623,12 +862,39 @@ mod tests {
             identifier: 1234,
             sequence: 33434,
             tos: Some(TypeOfService(0)),
+            ip_identification: None,
         });
         let strategy_resp = ProtocolStrategyResponse::from((proto_resp, &config));
         assert_eq!(strategy_resp.trace_id, TraceId(1234));
         assert_eq!(strategy_resp.sequence, Sequence(33434));
     }
 
+    // A router which rewrites the quoted ICMP identifier leaves the IP identification of the
+    // quoted original datagram, which is derived from the trace identifier and sequence, intact.
+    #[test_case(1234, None, 1234; "identifier matches")]
+    #[test_case(1234, Some(4321), 1234; "identifier matches with other ip identification")]
+    #[test_case(5678, Some(34668), 1234; "identifier rewritten with ip identification")]
+    #[test_case(5678, Some(1234), 5678; "identifier rewritten with trace identifier as ip identification")]
+    #[test_case(5678, Some(4321), 5678; "identifier rewritten with other ip identification")]
+    #[test_case(5678, None, 5678; "identifier rewritten without ip identification")]
+    fn test_icmp_response_ip_identification_fallback(
+        identifier: u16,
+        ip_identification: Option<u16>,
+        expected_trace_id: u16,
+    ) {
+        let config = StrategyConfig {
+            trace_identifier: TraceId(1234),
+            ..Default::default()
+        };
+        let proto_resp = ProtocolResponse::Icmp(
+            IcmpProtocolResponse::new(identifier, 33434, None)
+                .with_ip_identification(ip_identification),
+        );
+        let strategy_resp = ProtocolStrategyResponse::from((proto_resp, &config));
+        assert_eq!(TraceId(expected_trace_id), strategy_resp.trace_id);
+        assert_eq!(Sequence(33434), strategy_resp.sequence);
+    }
+
     #[test]
     fn test_udp_classic_fixed_src_response() {
         let config = StrategyConfig {
@@ -863,6 +1129,793 @@ mod tests {
         Ok(())
     }
 
+    // Test that the `MultiResponseMode` determines which addresses are
+    // attributed to a probe which elicits responses from multiple addresses.
+    //
+    // This test simulates sending 1 ICMP probe (seq=33434) and receiving two
+    // `TimeExceeded` responses for that probe from different addresses.
+    #[test_case(MultiResponseMode::First, [10, 0, 0, 1], &[]; "first")]
+    #[test_case(MultiResponseMode::All, [10, 0, 0, 1], &[[10, 0, 0, 2]]; "all")]
+    #[test_case(MultiResponseMode::Last, [10, 0, 0, 2], &[]; "last")]
+    fn test_multi_response_mode(
+        multi_response_mode: MultiResponseMode,
+        expected_host: [u8; 4],
+        expected_extra_hosts: &[[u8; 4]],
+    ) -> anyhow::Result<()> {
+        let sequence = 33434;
+        let mut network = MockNetwork::new();
+        let mut seq = mockall::Sequence::new();
+        network.expect_send_probe().times(1).returning(|_| Ok(()));
+        for addr in [Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)] {
+            network
+                .expect_recv_probe()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(move || {
+                    Ok(Some(Response::TimeExceeded(
+                        ResponseData::new(
+                            SystemTime::now(),
+                            IpAddr::V4(addr),
+                            ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, sequence, None)),
+                        ),
+                        IcmpPacketCode(0),
+                        None,
+                    )))
+                });
+        }
+        let config = StrategyConfig {
+            target_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9)),
+            initial_sequence: Sequence(sequence),
+            multi_response_mode,
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| Action::Continue);
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        let ProbeStatus::Complete(complete) = state.probe_at(Sequence(sequence)) else {
+            panic!("expected ProbeStatus::Complete");
+        };
+        let expected_extra_hosts = expected_extra_hosts
+            .iter()
+            .map(|&addr| IpAddr::from(addr))
+            .collect::<Vec<_>>();
+        assert_eq!(IpAddr::from(expected_host), complete.host);
+        assert_eq!(expected_extra_hosts, complete.extra_hosts);
+        Ok(())
+    }
+
+    // Test that the grace duration for the round is the target grace duration
+    // if the last response was received from the target and the (shorter)
+    // grace duration otherwise.
+    //
+    // This test simulates sending 2 ICMP probes (seq=33434 & seq=33435) and
+    // receiving a `TimeExceeded` from an intermediate hop followed by an
+    // `EchoReply` from the target.
+    #[test]
+    fn test_target_grace_duration() -> anyhow::Result<()> {
+        let sequence = 33434;
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let mut network = MockNetwork::new();
+        let mut seq = mockall::Sequence::new();
+        network.expect_send_probe().times(2).returning(|_| Ok(()));
+        network
+            .expect_recv_probe()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move || {
+                Ok(Some(Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                        ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, sequence, None)),
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                )))
+            });
+        network
+            .expect_recv_probe()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move || {
+                Ok(Some(Response::EchoReply(
+                    ResponseData::new(
+                        SystemTime::now(),
+                        target_addr,
+                        ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, sequence + 1, None)),
+                    ),
+                    IcmpPacketCode(0),
+                )))
+            });
+        let config = StrategyConfig {
+            target_addr,
+            initial_sequence: Sequence(sequence),
+            grace_duration: Duration::from_millis(50),
+            target_grace_duration: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| Action::Continue);
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.recv_response(&mut network, &mut state)?;
+        assert_eq!(Duration::from_millis(50), tracer.grace_duration(&state));
+        tracer.recv_response(&mut network, &mut state)?;
+        assert_eq!(Duration::from_millis(500), tracer.grace_duration(&state));
+        Ok(())
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingObserver {
+        probes_sent: AtomicUsize,
+        responses: AtomicUsize,
+        rounds: AtomicUsize,
+    }
+
+    impl TraceObserver for CountingObserver {
+        fn on_probe_sent(&self, _probe: &Probe) {
+            self.probes_sent.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_response(&self, _probe: &ProbeComplete) {
+            self.responses.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_round_complete(&self, _round: &Round<'_>) {
+            self.rounds.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    // Trace a target at ttl 1 for two rounds such that each round sends a
+    // single probe which is answered by an `EchoReply` from the target.
+    #[test]
+    fn test_observer_callbacks() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let last_sequence = Arc::new(AtomicU16::new(0));
+        let mut network = MockNetwork::new();
+        let sent_sequence = Arc::clone(&last_sequence);
+        network
+            .expect_send_probe()
+            .times(2)
+            .returning(move |probe| {
+                sent_sequence.store(probe.sequence.0, Ordering::SeqCst);
+                Ok(())
+            });
+        network.expect_recv_probe().times(2).returning(move || {
+            Ok(Some(Response::EchoReply(
+                ResponseData::new(
+                    SystemTime::UNIX_EPOCH,
+                    target_addr,
+                    ProtocolResponse::Icmp(IcmpProtocolResponse::new(
+                        0,
+                        last_sequence.load(Ordering::SeqCst),
+                        None,
+                    )),
+                ),
+                IcmpPacketCode(0),
+            )))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        let observer = Arc::new(CountingObserver::default());
+        Strategy::new(&config, |_| Action::Continue)
+            .with_observer(Some(observer.clone()))
+            .run(network)?;
+        assert_eq!(2, observer.probes_sent.load(Ordering::SeqCst));
+        assert_eq!(2, observer.responses.load(Ordering::SeqCst));
+        assert_eq!(2, observer.rounds.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    // Trace a target at ttl 4 with a max ttl of 8 for two rounds and record the
+    // ttl of every probe sent.  Hops 1-3 respond with `TimeExceeded` and all
+    // probes with a ttl of 4 or more are answered by the target.
+    #[test_case(false, &[1, 2, 3, 4, 1, 2, 3, 4]; "stop at target")]
+    #[test_case(true, &[1, 2, 3, 4, 5, 6, 7, 8, 1, 2, 3, 4, 5, 6, 7, 8]; "continue past target")]
+    fn test_continue_past_target(
+        continue_past_target: bool,
+        expected_ttls: &[u8],
+    ) -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let ttl = probe.ttl.0;
+            let proto_resp =
+                ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, probe.sequence.0, None));
+            Ok(Some(if ttl < 4 {
+                Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::UNIX_EPOCH,
+                        IpAddr::V4(Ipv4Addr::new(10, 0, 0, ttl)),
+                        proto_resp,
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                )
+            } else {
+                Response::EchoReply(
+                    ResponseData::new(SystemTime::UNIX_EPOCH, target_addr, proto_resp),
+                    IcmpPacketCode(0),
+                )
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            max_ttl: TimeToLive(8),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            continue_past_target,
+            ..Default::default()
+        };
+        Strategy::new(&config, |_| Action::Continue).run(network)?;
+        let ttls = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.ttl.0)
+            .collect::<Vec<_>>();
+        assert_eq!(expected_ttls, ttls.as_slice());
+        Ok(())
+    }
+
+    // Trace a target at ttl 4 with a budget of 10 probes and no maximum number
+    // of rounds and record the ttl of every probe sent.  Hops 1-3 respond with
+    // `TimeExceeded` and the target responds to a ttl of 4, so two complete
+    // rounds of 4 probes are sent followed by a partial round of 2 probes after
+    // which the trace finishes.
+    #[test]
+    fn test_max_packets() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let ttl = probe.ttl.0;
+            let proto_resp =
+                ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, probe.sequence.0, None));
+            Ok(Some(if ttl < 4 {
+                Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::UNIX_EPOCH,
+                        IpAddr::V4(Ipv4Addr::new(10, 0, 0, ttl)),
+                        proto_resp,
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                )
+            } else {
+                Response::EchoReply(
+                    ResponseData::new(SystemTime::UNIX_EPOCH, target_addr, proto_resp),
+                    IcmpPacketCode(0),
+                )
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            max_packets: Some(MaxPackets(NonZeroUsize::new(10).unwrap())),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        let observer = Arc::new(CountingObserver::default());
+        let stop_reason = Strategy::new(&config, |_| Action::Continue)
+            .with_observer(Some(observer.clone()))
+            .run(network)?;
+        assert_eq!(StopReason::PacketsExhausted, stop_reason);
+        let ttls = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.ttl.0)
+            .collect::<Vec<_>>();
+        assert_eq!(&[1, 2, 3, 4, 1, 2, 3, 4, 1, 2], ttls.as_slice());
+        assert_eq!(10, observer.probes_sent.load(Ordering::SeqCst));
+        assert_eq!(3, observer.rounds.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    // A send interval of 20ms (50 packets per second) for the ttl range 1-64
+    // requires 1280ms to probe every ttl and so extends the default maximum
+    // round duration of 1s, whereas a ttl range of 1-30 requires only 600ms.
+    #[test]
+    fn test_max_round_duration_send_interval() {
+        let config = StrategyConfig {
+            send_interval: Some(Duration::from_millis(20)),
+            max_ttl: TimeToLive(64),
+            max_round_duration: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let strategy = Strategy::new(&config, |_| Action::Continue);
+        assert_eq!(Duration::from_millis(1280), strategy.max_round_duration());
+        let config = StrategyConfig {
+            max_ttl: TimeToLive(30),
+            ..config
+        };
+        let strategy = Strategy::new(&config, |_| Action::Continue);
+        assert_eq!(Duration::from_secs(1), strategy.max_round_duration());
+    }
+
+    // Trace a target which never responds with a maximum ttl of 3 and a
+    // threshold of 2 silent rounds and no maximum number of rounds.  Each round
+    // probes ttl 1-3 without any response and times out, the first round
+    // completes as `RoundTimeLimitExceeded` and the second completes as
+    // `DestinationSilent` after which the trace finishes.
+    #[test]
+    fn test_max_silent_rounds() -> anyhow::Result<()> {
+        let mut network = MockNetwork::new();
+        network.expect_send_probe().times(6).returning(|_| Ok(()));
+        network.expect_recv_probe().returning(|| Ok(None));
+        let config = StrategyConfig {
+            target_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4)),
+            max_ttl: TimeToLive(3),
+            max_silent_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            max_round_duration: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let reasons = Mutex::new(Vec::new());
+        let stop_reason = Strategy::new(&config, |round| {
+            reasons.lock().unwrap().push(round.reason);
+            Action::Continue
+        })
+        .run(network)?;
+        assert_eq!(StopReason::DestinationSilent, stop_reason);
+        assert_eq!(
+            vec![
+                CompletionReason::RoundTimeLimitExceeded,
+                CompletionReason::DestinationSilent
+            ],
+            reasons.into_inner().unwrap()
+        );
+        Ok(())
+    }
+
+    // Trace a target at ttl 3 for two rounds, starting from a custom initial
+    // sequence, and record the sequence of every probe sent.  The first probe
+    // carries the initial sequence and every subsequent probe increments from
+    // it, including across rounds.
+    #[test]
+    fn test_initial_sequence() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let ttl = probe.ttl.0;
+            let proto_resp =
+                ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, probe.sequence.0, None));
+            Ok(Some(if ttl < 3 {
+                Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::UNIX_EPOCH,
+                        IpAddr::V4(Ipv4Addr::new(10, 0, 0, ttl)),
+                        proto_resp,
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                )
+            } else {
+                Response::EchoReply(
+                    ResponseData::new(SystemTime::UNIX_EPOCH, target_addr, proto_resp),
+                    IcmpPacketCode(0),
+                )
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            initial_sequence: Sequence(40000),
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            max_ttl: TimeToLive(3),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        Strategy::new(&config, |_| Action::Continue).run(network)?;
+        let sequences = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.sequence.0)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [40000, 40001, 40002, 40003, 40004, 40005],
+            sequences.as_slice()
+        );
+        Ok(())
+    }
+
+    // Send two probes in the first round such that the sequence number wraps
+    // around and the second round reuses the same sequence numbers.  A delayed
+    // response to the second probe of the first round, which has not yet been
+    // sent in the second round, must be discarded rather than attributed to the
+    // second round.
+    #[test]
+    fn test_delayed_response_after_sequence_wrap() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9));
+        let mut network = MockNetwork::new();
+        network.expect_send_probe().times(3).returning(|_| Ok(()));
+        let responses = Mutex::new(vec![65021, 65022]);
+        network.expect_recv_probe().times(2).returning(move || {
+            let sequence = responses.lock().unwrap().pop().unwrap();
+            Ok(Some(Response::TimeExceeded(
+                ResponseData::new(
+                    SystemTime::now(),
+                    IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                    ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, sequence, None)),
+                ),
+                IcmpPacketCode(0),
+                None,
+            )))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            initial_sequence: Sequence(65021),
+            ..Default::default()
+        };
+        let tracer = Strategy::new(&config, |_| Action::Continue);
+        let mut state = TracerState::new(config);
+        tracer.send_request(&mut network, &mut state)?;
+        tracer.send_request(&mut network, &mut state)?;
+        state.advance_round(TimeToLive(1), Action::Continue);
+        tracer.send_request(&mut network, &mut state)?;
+        assert!(state.in_round(Sequence(65022)));
+        assert!(!state.sent_in_round(Sequence(65022)));
+        tracer.recv_response(&mut network, &mut state)?;
+        let ProbeStatus::Awaited(awaited) = state.probe_at(Sequence(65022)) else {
+            panic!("expected ProbeStatus::Awaited");
+        };
+        assert_eq!(RoundId(0), awaited.round);
+        assert_eq!(None, state.received_time());
+        tracer.recv_response(&mut network, &mut state)?;
+        let ProbeStatus::Complete(complete) = state.probe_at(Sequence(65021)) else {
+            panic!("expected ProbeStatus::Complete");
+        };
+        assert_eq!(RoundId(1), complete.round);
+        Ok(())
+    }
+
+    // Trace a target at ttl 3 for two rounds with the classic UDP strategy, where
+    // hop 2 responds from a different address in each round as if the probes
+    // were load balanced over two paths.  The dest port of every probe is
+    // incremented and, as the classic strategy is not flow aware, all responses
+    // are collapsed into the single default flow.  The path of each round is
+    // still registered as a flow, and each reports the collapsed default flow.
+    #[test]
+    fn test_udp_classic_single_flow() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let proto_resp = ProtocolResponse::Udp(UdpProtocolResponse::new(
+                0,
+                target_addr,
+                probe.src_port.0,
+                probe.dest_port.0,
+                None,
+                0,
+                0,
+                0,
+                false,
+            ));
+            let ttl = probe.ttl.0;
+            let round = probe.round.0 as u8;
+            Ok(Some(match ttl {
+                1 | 2 => Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::UNIX_EPOCH,
+                        IpAddr::V4(Ipv4Addr::new(10, round, 0, ttl)),
+                        proto_resp,
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                ),
+                _ => Response::DestinationUnreachable(
+                    ResponseData::new(SystemTime::UNIX_EPOCH, target_addr, proto_resp),
+                    IcmpPacketCode(3),
+                    None,
+                ),
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            protocol: Protocol::Udp,
+            multipath_strategy: MultipathStrategy::Classic,
+            port_direction: PortDirection::FixedSrc(Port(5000)),
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            max_ttl: TimeToLive(3),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        let state = Arc::new(Mutex::new(State::new(StateConfig {
+            flow_aware: config.multipath_strategy.is_flow_aware(),
+            ..StateConfig::default()
+        })));
+        let round_state = Arc::clone(&state);
+        Strategy::new(&config, move |round| {
+            round_state.lock().unwrap().update_from_round(round);
+            Action::Continue
+        })
+        .run(network)?;
+        let dest_ports = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.dest_port.0)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [33434, 33435, 33436, 33437, 33438, 33439],
+            dest_ports.as_slice()
+        );
+        let state = state.lock().unwrap().clone();
+        assert_eq!(2, state.flows().len());
+        assert_eq!(2, state.round_count(State::default_flow_id()));
+        assert_eq!(2, state.hops()[1].addrs().count());
+        for (_, flow_id) in state.flows() {
+            assert_eq!(2, state.round_count(*flow_id));
+            assert_eq!(2, state.hops_for_flow(*flow_id)[1].addrs().count());
+        }
+        Ok(())
+    }
+
+    // Trace a target at ttl 3 for two rounds with the paris UDP strategy, a
+    // fixed dest port and a fixed source port seed.  Every probe in a round
+    // shares a source port drawn from the dynamic range, the two rounds use
+    // different source ports and every response is matched to its probe.
+    #[test]
+    fn test_udp_paris_src_port_seed() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let proto_resp = ProtocolResponse::Udp(UdpProtocolResponse::new(
+                0,
+                target_addr,
+                probe.src_port.0,
+                probe.dest_port.0,
+                None,
+                0,
+                probe.sequence.0,
+                0,
+                false,
+            ));
+            let ttl = probe.ttl.0;
+            Ok(Some(match ttl {
+                1 | 2 => Response::TimeExceeded(
+                    ResponseData::new(
+                        SystemTime::UNIX_EPOCH,
+                        IpAddr::V4(Ipv4Addr::new(10, 0, 0, ttl)),
+                        proto_resp,
+                    ),
+                    IcmpPacketCode(0),
+                    None,
+                ),
+                _ => Response::DestinationUnreachable(
+                    ResponseData::new(SystemTime::UNIX_EPOCH, target_addr, proto_resp),
+                    IcmpPacketCode(3),
+                    None,
+                ),
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            protocol: Protocol::Udp,
+            multipath_strategy: MultipathStrategy::Paris,
+            port_direction: PortDirection::FixedDest(Port(33434)),
+            src_port_seed: Some(42),
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            max_ttl: TimeToLive(3),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        let observer = Arc::new(CountingObserver::default());
+        Strategy::new(&config, |_| Action::Continue)
+            .with_observer(Some(observer.clone()))
+            .run(network)?;
+        let src_ports = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.src_port.0)
+            .collect::<Vec<_>>();
+        assert_eq!(6, src_ports.len());
+        let [first, second] = [&src_ports[..3], &src_ports[3..]].map(|round| {
+            assert!(round.iter().all(|&port| port == round[0]));
+            round[0]
+        });
+        assert!(first >= MIN_DYNAMIC_PORT && second >= MIN_DYNAMIC_PORT);
+        assert_ne!(first, second);
+        assert_eq!(6, observer.responses.load(Ordering::SeqCst));
+        assert_eq!(2, observer.rounds.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    // Trace a target at ttl 4 for two rounds with a hop address filter.  All hops are probed in
+    // the first round, in which their addresses are discovered, and only the hops permitted by the
+    // filter are probed in the second round.
+    #[test_case(HopAddrFilter::All, &[1, 2, 3, 4, 1, 2, 3, 4]; "all")]
+    #[test_case(HopAddrFilter::Allow(vec![IpAddr::from([10, 0, 0, 2]), IpAddr::from([10, 0, 0, 4])]), &[1, 2, 3, 4, 2, 4]; "allow")]
+    #[test_case(HopAddrFilter::Deny(vec![IpAddr::from([10, 0, 0, 2])]), &[1, 2, 3, 4, 1, 3, 4]; "deny")]
+    fn test_hop_addr_filter(hop_addr_filter: HopAddrFilter, expected: &[u8]) -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let proto_resp =
+                ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, probe.sequence.0, None));
+            let ttl = probe.ttl.0;
+            let data = ResponseData::new(
+                SystemTime::UNIX_EPOCH,
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, ttl)),
+                proto_resp,
+            );
+            Ok(Some(if ttl < 4 {
+                Response::TimeExceeded(data, IcmpPacketCode(0), None)
+            } else {
+                Response::EchoReply(data, IcmpPacketCode(0))
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            protocol: Protocol::Icmp,
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(2).unwrap())),
+            max_ttl: TimeToLive(4),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        Strategy::new(&config, |_| Action::Continue)
+            .with_hop_addr_filter(hop_addr_filter)
+            .run(network)?;
+        let ttls = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.ttl.0)
+            .collect::<Vec<_>>();
+        assert_eq!(expected, ttls.as_slice());
+        Ok(())
+    }
+
+    // Trace a target at ttl 4 for three rounds with a hop address allow filter, where the hop at
+    // ttl 2 does not respond in the first round.  The address of the hop is not known after the
+    // first round and so it is probed in the second round, in which it responds with a permitted
+    // address, and so it continues to be probed in the third round.
+    #[test]
+    fn test_hop_addr_filter_unknown_addr() -> anyhow::Result<()> {
+        let target_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut network = MockNetwork::new();
+        let sent_probes = Arc::clone(&sent);
+        network.expect_send_probe().returning(move |probe| {
+            sent_probes.lock().unwrap().push(probe);
+            Ok(())
+        });
+        let recv_probes = Arc::clone(&sent);
+        let next = AtomicUsize::new(0);
+        network.expect_recv_probe().returning(move || {
+            let index = next.fetch_add(1, Ordering::SeqCst);
+            let Some(probe) = recv_probes.lock().unwrap().get(index).cloned() else {
+                return Ok(None);
+            };
+            let ttl = probe.ttl.0;
+            if probe.round == RoundId(0) && ttl == 2 {
+                return Ok(None);
+            }
+            let proto_resp =
+                ProtocolResponse::Icmp(IcmpProtocolResponse::new(0, probe.sequence.0, None));
+            let data = ResponseData::new(
+                SystemTime::UNIX_EPOCH,
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, ttl)),
+                proto_resp,
+            );
+            Ok(Some(if ttl < 4 {
+                Response::TimeExceeded(data, IcmpPacketCode(0), None)
+            } else {
+                Response::EchoReply(data, IcmpPacketCode(0))
+            }))
+        });
+        let config = StrategyConfig {
+            target_addr,
+            protocol: Protocol::Icmp,
+            max_rounds: Some(MaxRounds(NonZeroUsize::new(3).unwrap())),
+            max_ttl: TimeToLive(4),
+            grace_duration: Duration::ZERO,
+            min_round_duration: Duration::ZERO,
+            ..Default::default()
+        };
+        let hop_addr_filter = HopAddrFilter::Allow(vec![
+            IpAddr::from([10, 0, 0, 2]),
+            IpAddr::from([10, 0, 0, 4]),
+        ]);
+        Strategy::new(&config, |_| Action::Continue)
+            .with_hop_addr_filter(hop_addr_filter)
+            .run(network)?;
+        let ttls = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|probe| probe.ttl.0)
+            .collect::<Vec<_>>();
+        assert_eq!(&[1, 2, 3, 4, 2, 4, 2, 4], ttls.as_slice());
+        Ok(())
+    }
+
     const fn response_data(now: SystemTime) -> ResponseData {
         ResponseData::new(
             now,
@@ -871,6 +1924,7 @@ mod tests {
                 identifier: 0,
                 sequence: 33434,
                 tos: Some(TypeOfService(0)),
+                ip_identification: None,
             }),
         )
     }
@@ -881,12 +1935,17 @@ mod tests {
 /// This is contained within a submodule to ensure that mutations are only performed via methods on
 /// the `TracerState` struct.
 mod state {
-    use crate::constants::MAX_SEQUENCE_PER_ROUND;
-    use crate::probe::{Probe, ProbeStatus};
-    use crate::strategy::{StrategyConfig, StrategyResponse};
+    use crate::constants::{MAX_SEQUENCE_PER_ROUND, MIN_DYNAMIC_PORT};
+    use crate::probe::{Probe, ProbeComplete, ProbeStatus};
+    use crate::strategy::{StopReason, StrategyConfig, StrategyResponse};
     use crate::types::{MaxRounds, Port, RoundId, Sequence, TimeToLive, TraceId};
-    use crate::{Action, Flags, MultipathStrategy, PortDirection, Protocol};
+    use crate::{
+        Action, Flags, HopAddrFilter, MultiResponseMode, MultipathStrategy, PortDirection, Protocol,
+    };
+    use siphasher::sip::SipHasher13;
     use std::array::from_fn;
+    use std::collections::HashMap;
+    use std::hash::Hasher;
     use std::net::IpAddr;
     use std::time::SystemTime;
     use tracing::instrument;
@@ -944,8 +2003,21 @@ mod state {
         target_ttl: Option<TimeToLive>,
         /// The timestamp of the echo response packet.
         received_time: Option<SystemTime>,
+        /// Was the latest response packet received from the target host?
+        received_from_target: bool,
         /// The action to take before starting the next round.
         next_round_action: Action,
+        /// The number of probes sent across all rounds.
+        packets_sent: usize,
+        /// The number of consecutive rounds which probed up to `max_ttl` without a response from
+        /// the target.
+        silent_rounds: usize,
+        /// The time the most recent probe was sent, across all rounds.
+        last_sent: Option<SystemTime>,
+        /// The round in which each sequence number was most recently sent.
+        sequence_rounds: HashMap<u16, RoundId>,
+        /// The address which most recently responded at each time-to-live, across all rounds.
+        hop_addrs: HashMap<u8, IpAddr>,
     }
 
     impl TracerState {
@@ -962,7 +2034,13 @@ mod state {
                 max_received_ttl: None,
                 target_ttl: None,
                 received_time: None,
+                received_from_target: false,
                 next_round_action: Action::Continue,
+                packets_sent: 0,
+                silent_rounds: 0,
+                last_sent: None,
+                sequence_rounds: HashMap::new(),
+                hop_addrs: HashMap::new(),
             }
         }
 
@@ -1001,24 +2079,90 @@ mod state {
             self.received_time
         }
 
+        pub const fn received_from_target(&self) -> bool {
+            self.received_from_target
+        }
+
         /// Is `sequence` in the current round?
         pub fn in_round(&self, sequence: Sequence) -> bool {
             sequence >= self.round_sequence && sequence.0 - self.round_sequence.0 < BUFFER_SIZE
         }
 
+        /// Was `sequence` most recently sent in the current round?
+        ///
+        /// Sequence numbers are reused once they wrap around, and so a delayed response to a probe
+        /// from a prior round may have a sequence number which is within the current round.  Such
+        /// a response must not be attributed to the probe of the current round.
+        pub fn sent_in_round(&self, sequence: Sequence) -> bool {
+            self.sequence_rounds.get(&sequence.0) == Some(&self.round)
+        }
+
         /// Do we have capacity in the current round for another sequence?
         pub fn round_has_capacity(&self) -> bool {
             let round_size = self.sequence - self.round_sequence;
             round_size.0 < BUFFER_SIZE
         }
 
-        /// Are all rounds complete?
-        pub fn finished(&self, max_rounds: Option<MaxRounds>) -> bool {
+        /// Have all `max_packets` probes been sent?
+        pub fn packets_exhausted(&self) -> bool {
+            self.config
+                .max_packets
+                .is_some_and(|max_packets| self.packets_sent >= max_packets.0.get())
+        }
+
+        /// Has at least `send_interval` elapsed since the most recent probe was sent at `now`?
+        pub fn send_due(&self, now: SystemTime) -> bool {
+            match (self.config.send_interval, self.last_sent) {
+                (Some(send_interval), Some(last_sent)) => {
+                    now.duration_since(last_sent).unwrap_or_default() >= send_interval
+                }
+                _ => true,
+            }
+        }
+
+        /// Record whether the current round probed up to `max_ttl` without a response from the
+        /// target.
+        pub fn update_silent_rounds(&mut self) {
+            if !self.target_found && self.ttl > self.config.max_ttl {
+                self.silent_rounds += 1;
+            } else {
+                self.silent_rounds = 0;
+            }
+        }
+
+        /// Has the target been silent for `max_silent_rounds` consecutive rounds?
+        pub fn destination_silent(&self) -> bool {
+            self.config
+                .max_silent_rounds
+                .is_some_and(|max_silent_rounds| self.silent_rounds >= max_silent_rounds.0.get())
+        }
+
+        /// Why the trace is finished, or `None` if it is not.
+        ///
+        /// The trace is finished once all rounds are complete.
+        ///
+        /// If the `max_packets` budget is exhausted then the trace is finished once the round in
+        /// which the final probe was sent is complete.
+        ///
+        /// If the target has been silent for `max_silent_rounds` consecutive rounds then the trace
+        /// is finished.
+        pub fn stop_reason(&self, max_rounds: Option<MaxRounds>) -> Option<StopReason> {
             let complete = match max_rounds {
                 None => false,
                 Some(max_rounds) => self.round.0 > max_rounds.0.get() - 1,
             };
-            self.next_round_action == Action::Stop || complete
+            let exhausted = self.packets_exhausted() && self.sequence == self.round_sequence;
+            if self.next_round_action == Action::Stop {
+                Some(StopReason::Stopped)
+            } else if complete {
+                Some(StopReason::MaxRounds)
+            } else if exhausted {
+                Some(StopReason::PacketsExhausted)
+            } else if self.destination_silent() {
+                Some(StopReason::DestinationSilent)
+            } else {
+                None
+            }
         }
 
         /// Create and return the next `Probe` at the current `sequence` and `ttl`.
@@ -1040,13 +2184,26 @@ mod state {
             );
             let probe_index = usize::from(self.sequence - self.round_sequence);
             self.buffer[probe_index] = ProbeStatus::Awaited(probe.clone());
+            self.sequence_rounds.insert(self.sequence.0, self.round);
             debug_assert!(self.ttl < TimeToLive(u8::MAX));
             self.ttl += TimeToLive(1);
             debug_assert!(self.sequence < Sequence(u16::MAX));
             self.sequence += Sequence(1);
+            self.packets_sent += 1;
+            self.last_sent = Some(sent);
             probe
         }
 
+        /// Advance the `ttl` past any time-to-live whose hop address is not permitted by `filter`.
+        ///
+        /// The hop address of each time-to-live is that of its most recent response, from any
+        /// round, and so hops which have not yet responded continue to be probed until they do.
+        pub fn skip_filtered_ttls(&mut self, filter: &HopAddrFilter, max_ttl: TimeToLive) {
+            while self.ttl <= max_ttl && !filter.permits(self.hop_addrs.get(&self.ttl.0).copied()) {
+                self.ttl += TimeToLive(1);
+            }
+        }
+
         /// Re-issue the `Probe` with the next sequence number.
         ///
         /// This will mark the `ProbeStatus` at the previous `sequence` as skipped and re-create it
@@ -1072,6 +2229,7 @@ mod state {
                 flags,
             );
             self.buffer[probe_index] = ProbeStatus::Awaited(probe.clone());
+            self.sequence_rounds.insert(self.sequence.0, self.round);
             debug_assert!(self.sequence < Sequence(u16::MAX));
             self.sequence += Sequence(1);
             probe
@@ -1143,7 +2301,7 @@ mod state {
                             Flags::PARIS_CHECKSUM,
                         ),
                         PortDirection::FixedDest(dest_port) => (
-                            Port(round_port),
+                            Port(self.round_src_port(round_port)),
                             Port(dest_port.0),
                             TraceId(0),
                             Flags::PARIS_CHECKSUM,
@@ -1168,7 +2326,7 @@ mod state {
                             Flags::DUBLIN_IPV6_PAYLOAD_LENGTH,
                         ),
                         PortDirection::FixedDest(dest_port) => (
-                            Port(round_port),
+                            Port(self.round_src_port(round_port)),
                             Port(dest_port.0),
                             TraceId(self.sequence.0),
                             Flags::DUBLIN_IPV6_PAYLOAD_LENGTH,
@@ -1185,6 +2343,20 @@ mod state {
             }
         }
 
+        /// Determine the source port for the current round when the destination port is fixed.
+        ///
+        /// This is the `round_port` unless a `src_port_seed` is configured, in which case it is a
+        /// port from the dynamic range derived from the seed and the current round, such that each
+        /// round uses a different flow and the sequence of ports is repeatable for a given seed.
+        fn round_src_port(&self, round_port: u16) -> u16 {
+            self.config.src_port_seed.map_or(round_port, |seed| {
+                let mut hasher = SipHasher13::new_with_keys(seed, 0);
+                hasher.write_usize(self.round.0);
+                let range = u64::from(u16::MAX - MIN_DYNAMIC_PORT) + 1;
+                MIN_DYNAMIC_PORT + (hasher.finish() % range) as u16
+            })
+        }
+
         /// Determine the `src_port`, `dest_port` and `identifier` for the current TCP probe.
         fn probe_tcp_data(&self) -> (Port, Port, TraceId, Flags) {
             let (src_port, dest_port) = match self.config.port_direction {
@@ -1201,44 +2373,52 @@ mod state {
         ///
         /// - the `target_ttl` to be the time-to-live of the `ProbeStatus` request from the target
         /// - the `max_received_ttl` we have observed this round
-        /// - the latest packet `received_time` in this round
+        /// - the latest packet `received_time` in this round and whether it was from the target
         /// - whether the target has been found in this round
         ///
         /// The ICMP replies may arrive out-of-order, and so we must be careful here to avoid
         /// overwriting the state with stale values.  We may also receive multiple replies
         /// from the target host with differing time-to-live values and so must ensure we
         /// use the time-to-live with the lowest sequence number.
+        ///
+        /// Returns `true` if the probe was completed by this response.
         #[instrument(skip(self), level = "trace")]
-        pub fn complete_probe(&mut self, resp: StrategyResponse) {
+        pub fn complete_probe(&mut self, resp: StrategyResponse) -> bool {
             // Retrieve and update the `ProbeStatus` at `sequence`.
             let probe = self.probe_at(resp.sequence);
             let awaited = match probe {
                 ProbeStatus::Awaited(awaited) => awaited,
                 // there is a valid scenario for TCP where a probe is already
-                // `Complete`, see `test_tcp_dest_unreachable_and_refused`.
-                ProbeStatus::Complete(_) => {
-                    return;
+                // `Complete`, see `test_tcp_dest_unreachable_and_refused`, and
+                // a probe may also elicit responses from multiple addresses.
+                ProbeStatus::Complete(completed) => {
+                    self.attribute_response(completed, resp.addr);
+                    return false;
                 }
                 _ => {
                     debug_assert!(
                         false,
                         "completed probe was not in Awaited state (probe={probe:#?})"
                     );
-                    return;
+                    return false;
                 }
             };
-            let completed = awaited.complete(
-                resp.addr,
-                resp.received,
-                resp.icmp_packet_type,
-                resp.tos,
-                resp.expected_udp_checksum,
-                resp.actual_udp_checksum,
-                resp.exts,
-            );
+            let completed = awaited
+                .complete(
+                    resp.addr,
+                    resp.received,
+                    resp.icmp_packet_type,
+                    resp.tos,
+                    resp.expected_udp_checksum,
+                    resp.actual_udp_checksum,
+                    resp.exts,
+                    resp.quoted_payload,
+                )
+                .with_response_tos(resp.response_tos);
             let ttl = completed.ttl;
             self.buffer[usize::from(resp.sequence - self.round_sequence)] =
                 ProbeStatus::Complete(completed);
+            self.hop_addrs.insert(ttl.0, resp.addr);
 
             // If this `ProbeStatus` found the target then we set the `target_ttl` if not already
             // set, being careful to account for `Probes` being received out-of-order.
@@ -1267,7 +2447,28 @@ mod state {
             };
 
             self.received_time = Some(resp.received);
+            self.received_from_target = resp.is_target;
             self.target_found |= resp.is_target;
+            true
+        }
+
+        /// Attribute an additional response for an already completed probe.
+        ///
+        /// Only the responding address(es) recorded for the probe are updated, as determined by
+        /// the `MultiResponseMode`.
+        fn attribute_response(&mut self, mut completed: ProbeComplete, addr: IpAddr) {
+            match self.config.multi_response_mode {
+                MultiResponseMode::First => return,
+                MultiResponseMode::All => {
+                    if completed.host == addr || completed.extra_hosts.contains(&addr) {
+                        return;
+                    }
+                    completed.extra_hosts.push(addr);
+                }
+                MultiResponseMode::Last => completed.host = addr,
+            }
+            let probe_index = usize::from(completed.sequence - self.round_sequence);
+            self.buffer[probe_index] = ProbeStatus::Complete(completed);
         }
 
         /// Advance to the next round.
@@ -1284,6 +2485,7 @@ mod state {
             self.target_found = false;
             self.round_sequence = self.sequence;
             self.received_time = None;
+            self.received_from_target = false;
             self.round_start = SystemTime::now();
             self.max_received_ttl = None;
             self.round += RoundId(1);
@@ -1313,12 +2515,14 @@ mod state {
     #[cfg(test)]
     mod tests {
         use super::*;
-        use crate::TypeOfService;
-        use crate::probe::{IcmpPacketCode, IcmpPacketType};
+        use crate::probe::{IcmpPacketCode, IcmpPacketType, ProtocolResponse, UdpProtocolResponse};
+        use crate::strategy::ProtocolStrategyResponse;
         use crate::types::MaxInflight;
+        use crate::{MultiResponseMode, TypeOfService, UnreachableMode};
         use rand::RngExt;
         use std::net::{IpAddr, Ipv4Addr};
         use std::time::Duration;
+        use test_case::test_case;
 
         #[expect(clippy::too_many_lines, clippy::bool_assert_comparison)]
         #[test]
@@ -1361,6 +2565,8 @@ mod state {
                 addr: host,
                 is_target: false,
                 exts: None,
+                quoted_payload: None,
+                response_tos: None,
             });
 
             // Validate the state of the probe 1 after the update
@@ -1437,6 +2643,8 @@ mod state {
                 addr: host,
                 is_target: false,
                 exts: None,
+                quoted_payload: None,
+                response_tos: None,
             });
             let probe_2_recv = state.probe_at(Sequence(33435));
 
@@ -1473,6 +2681,8 @@ mod state {
                 addr: host,
                 is_target: true,
                 exts: None,
+                quoted_payload: None,
+                response_tos: None,
             });
             let probe_3_recv = state.probe_at(Sequence(33436));
 
@@ -1624,21 +2834,131 @@ mod state {
             assert!(!state.in_round(Sequence(64491)));
         }
 
+        // Pace probes at 50 packets per second (a send interval of 20ms) for a
+        // ttl range of 1-5 and step a simulated clock forward 1ms at a time,
+        // sending a probe whenever one is due.  Each round of 5 probes spans
+        // 80ms and the first probe of the next round is paced from the final
+        // probe of the prior round.
+        #[test]
+        fn test_send_due() {
+            let config = StrategyConfig {
+                max_ttl: TimeToLive(5),
+                send_interval: Some(Duration::from_secs(1) / 50),
+                ..cfg(Sequence(33434))
+            };
+            let mut state = TracerState::new(config);
+            let start = SystemTime::UNIX_EPOCH;
+            let mut sent = vec![];
+            for ms in 0..200 {
+                let now = start + Duration::from_millis(ms);
+                if state.ttl() > config.max_ttl {
+                    state.advance_round(config.first_ttl, Action::Continue);
+                }
+                if state.send_due(now) {
+                    let probe = state.next_probe(now);
+                    sent.push((probe.ttl.0, ms));
+                }
+            }
+            assert_eq!(
+                &[
+                    (1, 0),
+                    (2, 20),
+                    (3, 40),
+                    (4, 60),
+                    (5, 80),
+                    (1, 100),
+                    (2, 120),
+                    (3, 140),
+                    (4, 160),
+                    (5, 180)
+                ],
+                sent.as_slice()
+            );
+        }
+
+        #[test]
+        fn test_send_due_no_interval() {
+            let mut state = TracerState::new(cfg(Sequence(33434)));
+            let now = SystemTime::now();
+            assert!(state.send_due(now));
+            _ = state.next_probe(now);
+            assert!(state.send_due(now));
+        }
+
+        // Test the sequence is carried in the dest port of classic UDP probes with a fixed src port
+        // and in the src port with a fixed dest port, and is recovered from the same port of the
+        // probe quoted in a response.
+        #[test_case(PortDirection::FixedSrc(Port(5000)), [(5000, 33434), (5000, 33435)]; "fixed src")]
+        #[test_case(PortDirection::FixedDest(Port(53)), [(33434, 53), (33435, 53)]; "fixed dest")]
+        fn test_udp_classic_probe_ports(port_direction: PortDirection, expected: [(u16, u16); 2]) {
+            let config = StrategyConfig {
+                protocol: Protocol::Udp,
+                port_direction,
+                ..cfg(Sequence(33434))
+            };
+            let mut state = TracerState::new(config);
+            let now = SystemTime::now();
+            for (src_port, dest_port) in expected {
+                let probe = state.next_probe(now);
+                assert_eq!(Port(src_port), probe.src_port);
+                assert_eq!(Port(dest_port), probe.dest_port);
+                let proto_resp = ProtocolResponse::Udp(UdpProtocolResponse {
+                    identifier: 0,
+                    dest_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    src_port,
+                    dest_port,
+                    tos: None,
+                    expected_udp_checksum: 0,
+                    actual_udp_checksum: 0,
+                    payload_len: 0,
+                    has_magic: false,
+                });
+                let strategy_resp = ProtocolStrategyResponse::from((proto_resp, &config));
+                assert_eq!(probe.sequence, strategy_resp.sequence);
+            }
+        }
+
+        // Test the source port of each round derived from a src port seed is fixed for the seed.
+        #[test]
+        fn test_round_src_port_seed() {
+            let config = StrategyConfig {
+                src_port_seed: Some(42),
+                ..cfg(Sequence(33434))
+            };
+            let mut state = TracerState::new(config);
+            let ports = (0..4)
+                .map(|round| {
+                    state.round = RoundId(round);
+                    state.round_src_port(33434)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(vec![57856, 59431, 61302, 60174], ports);
+        }
+
         fn cfg(initial_sequence: Sequence) -> StrategyConfig {
             StrategyConfig {
                 target_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
                 protocol: Protocol::Icmp,
                 trace_identifier: TraceId::default(),
                 max_rounds: None,
+                max_packets: None,
+                max_silent_rounds: None,
+                send_interval: None,
                 first_ttl: TimeToLive(1),
                 max_ttl: TimeToLive(24),
                 grace_duration: Duration::default(),
+                target_grace_duration: Duration::default(),
                 max_inflight: MaxInflight::default(),
                 initial_sequence,
                 multipath_strategy: MultipathStrategy::Classic,
                 port_direction: PortDirection::None,
+                src_port_seed: None,
                 min_round_duration: Duration::default(),
                 max_round_duration: Duration::default(),
+                continue_past_target: false,
+                unreachable_mode: UnreachableMode::Address,
+                icmp_unreachable_reached: false,
+                multi_response_mode: MultiResponseMode::First,
             }
         }
     }
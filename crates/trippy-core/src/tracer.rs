@@ -1,11 +1,15 @@
 use crate::error::Result;
 use crate::{
-    Action, Error, IcmpExtensionParseMode, MaxInflight, MaxRounds, MultipathStrategy, PacketSize,
-    PayloadPattern, PortDirection, PrivilegeMode, Protocol, Round, Sequence, State, TimeToLive,
-    TraceId, TypeOfService,
+    Action, DontFragmentMode, Error, HopAddrFilter, IcmpExtensionParseMode,
+    IcmpIdentifierMatchMode, IcmpQuotedPayloadMode, IcmpSourceMatchMode, IdentifierComparison,
+    MaxInflight, MaxPackets, MaxRounds, MultiResponseMode, MultipathStrategy, NegativeRttMode,
+    PacketSize, PayloadPattern, PortDirection, PrivilegeMode, Protocol, Round, Sequence, State,
+    TcpFlags, TimeToLive, TraceId, TraceObserver, TypeOfService, UnreachableMode,
 };
 use std::fmt::Debug;
 use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
@@ -29,6 +33,7 @@ impl Tracer {
     #[must_use]
     pub(crate) fn new(
         interface: Option<String>,
+        write_pcap: Option<PathBuf>,
         source_addr: Option<IpAddr>,
         target_addr: IpAddr,
         privilege_mode: PrivilegeMode,
@@ -37,26 +42,52 @@ impl Tracer {
         payload_pattern: PayloadPattern,
         tos: TypeOfService,
         icmp_extension_parse_mode: IcmpExtensionParseMode,
+        icmp_identifier_match_mode: IcmpIdentifierMatchMode,
+        icmp_source_match_mode: IcmpSourceMatchMode,
+        icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+        fwmark: Option<u32>,
+        icmp_nonce_key: Option<u64>,
+        icmp_identifier_range: Option<u16>,
+        icmp_payload_len: Option<u16>,
+        icmp_echo_code: u8,
+        dont_fragment_mode: DontFragmentMode,
+        tcp_flags: TcpFlags,
         read_timeout: Duration,
         tcp_connect_timeout: Duration,
+        icmp_bind_attempts: u8,
         trace_identifier: TraceId,
         max_rounds: Option<MaxRounds>,
+        max_packets: Option<MaxPackets>,
+        max_silent_rounds: Option<MaxRounds>,
+        send_interval: Option<Duration>,
         first_ttl: TimeToLive,
         max_ttl: TimeToLive,
         grace_duration: Duration,
+        target_grace_duration: Duration,
         max_inflight: MaxInflight,
         initial_sequence: Sequence,
         multipath_strategy: MultipathStrategy,
         port_direction: PortDirection,
+        src_port_seed: Option<u64>,
         min_round_duration: Duration,
         max_round_duration: Duration,
+        continue_past_target: bool,
+        unreachable_mode: UnreachableMode,
+        icmp_unreachable_reached: bool,
+        multi_response_mode: MultiResponseMode,
+        hop_addr_filter: HopAddrFilter,
         max_samples: usize,
         max_flows: usize,
+        negative_rtt_mode: NegativeRttMode,
+        window: Option<NonZeroUsize>,
+        max_addrs: Option<NonZeroUsize>,
         drop_privileges: bool,
+        observer: Option<Arc<dyn TraceObserver>>,
     ) -> Self {
         Self {
             inner: Arc::new(inner::TracerInner::new(
                 interface,
+                write_pcap,
                 source_addr,
                 target_addr,
                 privilege_mode,
@@ -65,22 +96,47 @@ impl Tracer {
                 payload_pattern,
                 tos,
                 icmp_extension_parse_mode,
+                icmp_identifier_match_mode,
+                icmp_source_match_mode,
+                icmp_quoted_payload_mode,
+                fwmark,
+                icmp_nonce_key,
+                icmp_identifier_range,
+                icmp_payload_len,
+                icmp_echo_code,
+                dont_fragment_mode,
+                tcp_flags,
                 read_timeout,
                 tcp_connect_timeout,
+                icmp_bind_attempts,
                 trace_identifier,
                 max_rounds,
+                max_packets,
+                max_silent_rounds,
+                send_interval,
                 first_ttl,
                 max_ttl,
                 grace_duration,
+                target_grace_duration,
                 max_inflight,
                 initial_sequence,
                 multipath_strategy,
                 port_direction,
+                src_port_seed,
                 min_round_duration,
                 max_round_duration,
+                continue_past_target,
+                unreachable_mode,
+                icmp_unreachable_reached,
+                multi_response_mode,
+                hop_addr_filter,
                 max_samples,
                 max_flows,
+                negative_rtt_mode,
+                window,
+                max_addrs,
                 drop_privileges,
+                observer,
             )),
         }
     }
@@ -353,6 +409,28 @@ impl Tracer {
         self.inner.max_samples()
     }
 
+    /// How the tracer handles responses with a negative round trip time.
+    #[must_use]
+    pub fn negative_rtt_mode(&self) -> NegativeRttMode {
+        self.inner.negative_rtt_mode()
+    }
+
+    /// The number of most recent rounds which contribute to the hop statistics.
+    ///
+    /// Returns `None` if all rounds contribute.
+    #[must_use]
+    pub fn window(&self) -> Option<NonZeroUsize> {
+        self.inner.window()
+    }
+
+    /// The maximum number of distinct addresses to record per hop.
+    ///
+    /// Returns `None` if all addresses are recorded.
+    #[must_use]
+    pub fn max_addrs(&self) -> Option<NonZeroUsize> {
+        self.inner.max_addrs()
+    }
+
     /// The privilege mode of the tracer.
     #[must_use]
     pub fn privilege_mode(&self) -> PrivilegeMode {
@@ -371,12 +449,50 @@ impl Tracer {
         self.inner.interface()
     }
 
+    /// The pcap file to write the probes sent to.
+    #[must_use]
+    pub fn write_pcap(&self) -> Option<&Path> {
+        self.inner.write_pcap()
+    }
+
     /// The source address of the tracer.
     #[must_use]
     pub fn source_addr(&self) -> Option<IpAddr> {
         self.inner.source_addr()
     }
 
+    /// The number of ICMP error responses dropped by the tracer as the packet they quote was not
+    /// of the tracing protocol.
+    #[must_use]
+    pub fn protocol_mismatches(&self) -> usize {
+        self.inner.protocol_mismatches()
+    }
+
+    /// The number of ICMP responses discarded by the tracer as they filled the receive buffer and
+    /// so may have been truncated.
+    #[must_use]
+    pub fn truncated_responses(&self) -> usize {
+        self.inner.truncated_responses()
+    }
+
+    /// The privilege mode of the path used to dispatch probes, if any probe has been sent.
+    ///
+    /// This may differ from the configured [`Tracer::privilege_mode`], such as for TCP probes
+    /// which are sent using a connected stream socket without elevated privileges.
+    #[must_use]
+    pub fn effective_privilege_mode(&self) -> Option<PrivilegeMode> {
+        self.inner.effective_privilege_mode()
+    }
+
+    /// The identifier of each ICMP echo request compared with that of its echo reply, ordered by
+    /// sequence number.
+    ///
+    /// This is empty unless an [`Tracer::icmp_identifier_range`] is set.
+    #[must_use]
+    pub fn identifier_comparisons(&self) -> Vec<IdentifierComparison> {
+        self.inner.identifier_comparisons()
+    }
+
     /// The target address of the tracer.
     #[must_use]
     pub fn target_addr(&self) -> IpAddr {
@@ -413,6 +529,66 @@ impl Tracer {
         self.inner.icmp_extension_parse_mode()
     }
 
+    /// The ICMP identifier match mode of the tracer.
+    #[must_use]
+    pub fn icmp_identifier_match_mode(&self) -> IcmpIdentifierMatchMode {
+        self.inner.icmp_identifier_match_mode()
+    }
+
+    /// The ICMP source match mode of the tracer.
+    #[must_use]
+    pub fn icmp_source_match_mode(&self) -> IcmpSourceMatchMode {
+        self.inner.icmp_source_match_mode()
+    }
+
+    /// The ICMP quoted payload mode of the tracer.
+    #[must_use]
+    pub fn icmp_quoted_payload_mode(&self) -> IcmpQuotedPayloadMode {
+        self.inner.icmp_quoted_payload_mode()
+    }
+
+    /// The firewall mark of the tracer.
+    #[must_use]
+    pub fn fwmark(&self) -> Option<u32> {
+        self.inner.fwmark()
+    }
+
+    /// The key used to derive the nonce included in ICMP echo request payloads.
+    #[must_use]
+    pub fn icmp_nonce_key(&self) -> Option<u64> {
+        self.inner.icmp_nonce_key()
+    }
+
+    /// The range of ICMP identifiers used to probe for identifier rewriting.
+    #[must_use]
+    pub fn icmp_identifier_range(&self) -> Option<u16> {
+        self.inner.icmp_identifier_range()
+    }
+
+    /// The exact length of the payload of ICMP probes, if set.
+    #[must_use]
+    pub fn icmp_payload_len(&self) -> Option<u16> {
+        self.inner.icmp_payload_len()
+    }
+
+    /// The ICMP code of ICMP echo request probes.
+    #[must_use]
+    pub fn icmp_echo_code(&self) -> u8 {
+        self.inner.icmp_echo_code()
+    }
+
+    /// The `Don't fragment` bit mode of IPv4 probes.
+    #[must_use]
+    pub fn dont_fragment_mode(&self) -> DontFragmentMode {
+        self.inner.dont_fragment_mode()
+    }
+
+    /// The TCP flags of TCP probes.
+    #[must_use]
+    pub fn tcp_flags(&self) -> TcpFlags {
+        self.inner.tcp_flags()
+    }
+
     /// The read timeout of the tracer.
     #[must_use]
     pub fn read_timeout(&self) -> Duration {
@@ -425,6 +601,12 @@ impl Tracer {
         self.inner.tcp_connect_timeout()
     }
 
+    /// The maximum number of attempts to bind an unprivileged ICMP socket.
+    #[must_use]
+    pub fn icmp_bind_attempts(&self) -> u8 {
+        self.inner.icmp_bind_attempts()
+    }
+
     /// The trace identifier of the tracer.
     #[must_use]
     pub fn trace_identifier(&self) -> TraceId {
@@ -437,6 +619,24 @@ impl Tracer {
         self.inner.max_rounds()
     }
 
+    /// The maximum number of probes sent by the tracer across all rounds.
+    #[must_use]
+    pub fn max_packets(&self) -> Option<MaxPackets> {
+        self.inner.max_packets()
+    }
+
+    /// The maximum number of consecutive rounds without a response from the target.
+    #[must_use]
+    pub fn max_silent_rounds(&self) -> Option<MaxRounds> {
+        self.inner.max_silent_rounds()
+    }
+
+    /// The minimum interval between sending consecutive probes.
+    #[must_use]
+    pub fn send_interval(&self) -> Option<Duration> {
+        self.inner.send_interval()
+    }
+
     /// The first time-to-live value of the tracer.
     #[must_use]
     pub fn first_ttl(&self) -> TimeToLive {
@@ -455,6 +655,12 @@ impl Tracer {
         self.inner.grace_duration()
     }
 
+    /// The grace duration of the tracer when the last response was received from the target.
+    #[must_use]
+    pub fn target_grace_duration(&self) -> Duration {
+        self.inner.target_grace_duration()
+    }
+
     /// The maximum number of in-flight probes of the tracer.
     #[must_use]
     pub fn max_inflight(&self) -> MaxInflight {
@@ -473,6 +679,12 @@ impl Tracer {
         self.inner.port_direction()
     }
 
+    /// The seed used to randomize the source port of each round of the tracer.
+    #[must_use]
+    pub fn src_port_seed(&self) -> Option<u64> {
+        self.inner.src_port_seed()
+    }
+
     /// The minimum round duration of the tracer.
     #[must_use]
     pub fn min_round_duration(&self) -> Duration {
@@ -484,21 +696,59 @@ impl Tracer {
     pub fn max_round_duration(&self) -> Duration {
         self.inner.max_round_duration()
     }
+
+    /// Whether the tracer continues probing past the target.
+    #[must_use]
+    pub fn continue_past_target(&self) -> bool {
+        self.inner.continue_past_target()
+    }
+
+    /// How `DestinationUnreachable` responses are classified.
+    #[must_use]
+    pub fn unreachable_mode(&self) -> UnreachableMode {
+        self.inner.unreachable_mode()
+    }
+
+    /// Whether a `DestinationUnreachable` response reaches the target in ICMP mode.
+    #[must_use]
+    pub fn icmp_unreachable_reached(&self) -> bool {
+        self.inner.icmp_unreachable_reached()
+    }
+
+    /// Which responses are attributed to a hop when multiple addresses respond to a probe.
+    #[must_use]
+    pub fn multi_response_mode(&self) -> MultiResponseMode {
+        self.inner.multi_response_mode()
+    }
+
+    /// Which hops are probed, by address, after the first round.
+    #[must_use]
+    pub fn hop_addr_filter(&self) -> &HopAddrFilter {
+        self.inner.hop_addr_filter()
+    }
 }
 
 mod inner {
     use crate::config::{ChannelConfig, StateConfig, StrategyConfig};
     use crate::error::Result;
+    use crate::net::identifier::IdentifierProbe;
+    use crate::net::pcap::PcapWriter;
     use crate::net::{PlatformImpl, SocketImpl};
     use crate::{
-        Action, Channel, Error, IcmpExtensionParseMode, MaxInflight, MaxRounds, MultipathStrategy,
+        Action, Channel, DontFragmentMode, Error, HopAddrFilter, IcmpExtensionParseMode,
+        IcmpIdentifierMatchMode, IcmpQuotedPayloadMode, IcmpSourceMatchMode, IdentifierComparison,
+        MaxInflight, MaxPackets, MaxRounds, MultiResponseMode, MultipathStrategy, NegativeRttMode,
         PacketSize, PayloadPattern, PortDirection, PrivilegeMode, Protocol, Round, Sequence,
-        SourceAddr, State, Strategy, TimeToLive, TraceId, TypeOfService,
+        SourceAddr, State, Strategy, TcpFlags, TimeToLive, TraceId, TraceObserver, TypeOfService,
+        UnreachableMode,
     };
     use parking_lot::RwLock;
     use std::fmt::Debug;
     use std::net::IpAddr;
-    use std::sync::OnceLock;
+    use std::num::NonZeroUsize;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, OnceLock};
     use std::time::Duration;
     use tracing::instrument;
     use trippy_privilege::Privilege;
@@ -507,6 +757,7 @@ mod inner {
     pub(super) struct TracerInner {
         source_addr: Option<IpAddr>,
         interface: Option<String>,
+        write_pcap: Option<PathBuf>,
         target_addr: IpAddr,
         privilege_mode: PrivilegeMode,
         protocol: Protocol,
@@ -514,30 +765,60 @@ mod inner {
         payload_pattern: PayloadPattern,
         tos: TypeOfService,
         icmp_extension_parse_mode: IcmpExtensionParseMode,
+        icmp_identifier_match_mode: IcmpIdentifierMatchMode,
+        icmp_source_match_mode: IcmpSourceMatchMode,
+        icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+        fwmark: Option<u32>,
+        icmp_nonce_key: Option<u64>,
+        icmp_identifier_range: Option<u16>,
+        icmp_payload_len: Option<u16>,
+        icmp_echo_code: u8,
+        dont_fragment_mode: DontFragmentMode,
+        tcp_flags: TcpFlags,
         read_timeout: Duration,
         tcp_connect_timeout: Duration,
+        icmp_bind_attempts: u8,
         trace_identifier: TraceId,
         max_rounds: Option<MaxRounds>,
+        max_packets: Option<MaxPackets>,
+        max_silent_rounds: Option<MaxRounds>,
+        send_interval: Option<Duration>,
         first_ttl: TimeToLive,
         max_ttl: TimeToLive,
         grace_duration: Duration,
+        target_grace_duration: Duration,
         max_inflight: MaxInflight,
         initial_sequence: Sequence,
         multipath_strategy: MultipathStrategy,
         port_direction: PortDirection,
+        src_port_seed: Option<u64>,
         min_round_duration: Duration,
         max_round_duration: Duration,
+        continue_past_target: bool,
+        unreachable_mode: UnreachableMode,
+        icmp_unreachable_reached: bool,
+        multi_response_mode: MultiResponseMode,
+        hop_addr_filter: HopAddrFilter,
         max_samples: usize,
         max_flows: usize,
+        negative_rtt_mode: NegativeRttMode,
+        window: Option<NonZeroUsize>,
+        max_addrs: Option<NonZeroUsize>,
         drop_privileges: bool,
+        observer: Option<Arc<dyn TraceObserver>>,
         state: RwLock<State>,
         src: OnceLock<IpAddr>,
+        protocol_mismatches: OnceLock<Arc<AtomicUsize>>,
+        truncated_responses: OnceLock<Arc<AtomicUsize>>,
+        effective_privilege_mode: OnceLock<Arc<OnceLock<PrivilegeMode>>>,
+        identifier_probe: OnceLock<Arc<IdentifierProbe>>,
     }
 
     impl TracerInner {
         #[expect(clippy::too_many_arguments)]
         pub(super) fn new(
             interface: Option<String>,
+            write_pcap: Option<PathBuf>,
             source_addr: Option<IpAddr>,
             target_addr: IpAddr,
             privilege_mode: PrivilegeMode,
@@ -546,26 +827,52 @@ mod inner {
             payload_pattern: PayloadPattern,
             tos: TypeOfService,
             icmp_extension_parse_mode: IcmpExtensionParseMode,
+            icmp_identifier_match_mode: IcmpIdentifierMatchMode,
+            icmp_source_match_mode: IcmpSourceMatchMode,
+            icmp_quoted_payload_mode: IcmpQuotedPayloadMode,
+            fwmark: Option<u32>,
+            icmp_nonce_key: Option<u64>,
+            icmp_identifier_range: Option<u16>,
+            icmp_payload_len: Option<u16>,
+            icmp_echo_code: u8,
+            dont_fragment_mode: DontFragmentMode,
+            tcp_flags: TcpFlags,
             read_timeout: Duration,
             tcp_connect_timeout: Duration,
+            icmp_bind_attempts: u8,
             trace_identifier: TraceId,
             max_rounds: Option<MaxRounds>,
+            max_packets: Option<MaxPackets>,
+            max_silent_rounds: Option<MaxRounds>,
+            send_interval: Option<Duration>,
             first_ttl: TimeToLive,
             max_ttl: TimeToLive,
             grace_duration: Duration,
+            target_grace_duration: Duration,
             max_inflight: MaxInflight,
             initial_sequence: Sequence,
             multipath_strategy: MultipathStrategy,
             port_direction: PortDirection,
+            src_port_seed: Option<u64>,
             min_round_duration: Duration,
             max_round_duration: Duration,
+            continue_past_target: bool,
+            unreachable_mode: UnreachableMode,
+            icmp_unreachable_reached: bool,
+            multi_response_mode: MultiResponseMode,
+            hop_addr_filter: HopAddrFilter,
             max_samples: usize,
             max_flows: usize,
+            negative_rtt_mode: NegativeRttMode,
+            window: Option<NonZeroUsize>,
+            max_addrs: Option<NonZeroUsize>,
             drop_privileges: bool,
+            observer: Option<Arc<dyn TraceObserver>>,
         ) -> Self {
             Self {
                 source_addr,
                 interface,
+                write_pcap,
                 target_addr,
                 privilege_mode,
                 protocol,
@@ -573,24 +880,60 @@ mod inner {
                 payload_pattern,
                 tos,
                 icmp_extension_parse_mode,
+                icmp_identifier_match_mode,
+                icmp_source_match_mode,
+                icmp_quoted_payload_mode,
+                fwmark,
+                icmp_nonce_key,
+                icmp_identifier_range,
+                icmp_payload_len,
+                icmp_echo_code,
+                dont_fragment_mode,
+                tcp_flags,
                 read_timeout,
                 tcp_connect_timeout,
+                icmp_bind_attempts,
                 trace_identifier,
                 max_rounds,
+                max_packets,
+                max_silent_rounds,
+                send_interval,
                 first_ttl,
                 max_ttl,
                 grace_duration,
+                target_grace_duration,
                 max_inflight,
                 initial_sequence,
                 multipath_strategy,
                 port_direction,
+                src_port_seed,
                 min_round_duration,
                 max_round_duration,
+                continue_past_target,
+                unreachable_mode,
+                icmp_unreachable_reached,
+                multi_response_mode,
+                hop_addr_filter,
                 max_samples,
                 max_flows,
+                negative_rtt_mode,
+                window,
+                max_addrs,
                 drop_privileges,
-                state: RwLock::new(State::new(Self::make_state_config(max_flows, max_samples))),
+                observer,
+                state: RwLock::new(State::new(Self::make_state_config(
+                    max_flows,
+                    max_samples,
+                    negative_rtt_mode,
+                    window,
+                    max_addrs,
+                    multipath_strategy,
+                ))),
                 src: OnceLock::new(),
+                protocol_mismatches: OnceLock::new(),
+                truncated_responses: OnceLock::new(),
+                effective_privilege_mode: OnceLock::new(),
+                identifier_probe: OnceLock::new(),
             }
         }
 
@@ -614,8 +957,14 @@ mod inner {
         }
 
         pub(super) fn clear(&self) {
-            *self.state.write() =
-                State::new(Self::make_state_config(self.max_flows, self.max_samples));
+            *self.state.write() = State::new(Self::make_state_config(
+                self.max_flows,
+                self.max_samples,
+                self.negative_rtt_mode,
+                self.window,
+                self.max_addrs,
+                self.multipath_strategy,
+            ));
         }
 
         pub(super) const fn max_flows(&self) -> usize {
@@ -626,6 +975,18 @@ mod inner {
             self.max_samples
         }
 
+        pub(super) const fn negative_rtt_mode(&self) -> NegativeRttMode {
+            self.negative_rtt_mode
+        }
+
+        pub(super) const fn window(&self) -> Option<NonZeroUsize> {
+            self.window
+        }
+
+        pub(super) const fn max_addrs(&self) -> Option<NonZeroUsize> {
+            self.max_addrs
+        }
+
         pub(super) const fn privilege_mode(&self) -> PrivilegeMode {
             self.privilege_mode
         }
@@ -638,10 +999,39 @@ mod inner {
             self.interface.as_deref()
         }
 
+        pub(super) fn write_pcap(&self) -> Option<&Path> {
+            self.write_pcap.as_deref()
+        }
+
         pub(super) fn source_addr(&self) -> Option<IpAddr> {
             self.src.get().copied()
         }
 
+        pub(super) fn protocol_mismatches(&self) -> usize {
+            self.protocol_mismatches
+                .get()
+                .map_or(0, |count| count.load(Ordering::Relaxed))
+        }
+
+        pub(super) fn truncated_responses(&self) -> usize {
+            self.truncated_responses
+                .get()
+                .map_or(0, |count| count.load(Ordering::Relaxed))
+        }
+
+        pub(super) fn effective_privilege_mode(&self) -> Option<PrivilegeMode> {
+            self.effective_privilege_mode
+                .get()
+                .and_then(|mode| mode.get().copied())
+        }
+
+        pub(super) fn identifier_comparisons(&self) -> Vec<IdentifierComparison> {
+            self.identifier_probe
+                .get()
+                .map(|probe| probe.comparisons())
+                .unwrap_or_default()
+        }
+
         pub(super) const fn target_addr(&self) -> IpAddr {
             self.target_addr
         }
@@ -666,6 +1056,46 @@ mod inner {
             self.icmp_extension_parse_mode
         }
 
+        pub(super) const fn icmp_identifier_match_mode(&self) -> IcmpIdentifierMatchMode {
+            self.icmp_identifier_match_mode
+        }
+
+        pub(super) const fn icmp_source_match_mode(&self) -> IcmpSourceMatchMode {
+            self.icmp_source_match_mode
+        }
+
+        pub(super) const fn icmp_quoted_payload_mode(&self) -> IcmpQuotedPayloadMode {
+            self.icmp_quoted_payload_mode
+        }
+
+        pub(super) const fn fwmark(&self) -> Option<u32> {
+            self.fwmark
+        }
+
+        pub(super) const fn icmp_nonce_key(&self) -> Option<u64> {
+            self.icmp_nonce_key
+        }
+
+        pub(super) const fn icmp_identifier_range(&self) -> Option<u16> {
+            self.icmp_identifier_range
+        }
+
+        pub(super) const fn icmp_payload_len(&self) -> Option<u16> {
+            self.icmp_payload_len
+        }
+
+        pub(super) const fn icmp_echo_code(&self) -> u8 {
+            self.icmp_echo_code
+        }
+
+        pub(super) const fn dont_fragment_mode(&self) -> DontFragmentMode {
+            self.dont_fragment_mode
+        }
+
+        pub(super) const fn tcp_flags(&self) -> TcpFlags {
+            self.tcp_flags
+        }
+
         pub(super) const fn read_timeout(&self) -> Duration {
             self.read_timeout
         }
@@ -674,6 +1104,10 @@ mod inner {
             self.tcp_connect_timeout
         }
 
+        pub(super) const fn icmp_bind_attempts(&self) -> u8 {
+            self.icmp_bind_attempts
+        }
+
         pub(super) const fn trace_identifier(&self) -> TraceId {
             self.trace_identifier
         }
@@ -682,6 +1116,18 @@ mod inner {
             self.max_rounds
         }
 
+        pub(super) const fn max_packets(&self) -> Option<MaxPackets> {
+            self.max_packets
+        }
+
+        pub(super) const fn max_silent_rounds(&self) -> Option<MaxRounds> {
+            self.max_silent_rounds
+        }
+
+        pub(super) const fn send_interval(&self) -> Option<Duration> {
+            self.send_interval
+        }
+
         pub(super) const fn first_ttl(&self) -> TimeToLive {
             self.first_ttl
         }
@@ -694,6 +1140,10 @@ mod inner {
             self.grace_duration
         }
 
+        pub(super) const fn target_grace_duration(&self) -> Duration {
+            self.target_grace_duration
+        }
+
         pub(super) const fn max_inflight(&self) -> MaxInflight {
             self.max_inflight
         }
@@ -706,6 +1156,10 @@ mod inner {
             self.port_direction
         }
 
+        pub(super) const fn src_port_seed(&self) -> Option<u64> {
+            self.src_port_seed
+        }
+
         pub(super) const fn min_round_duration(&self) -> Duration {
             self.min_round_duration
         }
@@ -714,6 +1168,26 @@ mod inner {
             self.max_round_duration
         }
 
+        pub(super) const fn continue_past_target(&self) -> bool {
+            self.continue_past_target
+        }
+
+        pub(super) const fn unreachable_mode(&self) -> UnreachableMode {
+            self.unreachable_mode
+        }
+
+        pub(super) const fn icmp_unreachable_reached(&self) -> bool {
+            self.icmp_unreachable_reached
+        }
+
+        pub(super) const fn multi_response_mode(&self) -> MultiResponseMode {
+            self.multi_response_mode
+        }
+
+        pub(super) const fn hop_addr_filter(&self) -> &HopAddrFilter {
+            &self.hop_addr_filter
+        }
+
         #[instrument(skip_all, level = "trace")]
         fn run_internal<F, R>(&self, func: F) -> Result<()>
         where
@@ -735,15 +1209,41 @@ mod inner {
                 .map_err(|_| Error::Other(String::from("failed to set source_addr")))?;
             let channel_config = self.make_channel_config(source_addr);
             let channel = Channel::<SocketImpl>::connect(&channel_config)?;
+            let channel = match &self.write_pcap {
+                Some(path) => channel.with_pcap(PcapWriter::create(path)?),
+                None => channel,
+            };
+            self.protocol_mismatches
+                .set(channel.protocol_mismatches())
+                .map_err(|_| Error::Other(String::from("failed to set protocol_mismatches")))?;
+            self.truncated_responses
+                .set(channel.truncated_responses())
+                .map_err(|_| Error::Other(String::from("failed to set truncated_responses")))?;
+            self.effective_privilege_mode
+                .set(channel.effective_privilege_mode())
+                .map_err(|_| {
+                    Error::Other(String::from("failed to set effective_privilege_mode"))
+                })?;
+            if let Some(identifier_probe) = channel.identifier_probe() {
+                self.identifier_probe
+                    .set(identifier_probe)
+                    .map_err(|_| Error::Other(String::from("failed to set identifier_probe")))?;
+            }
             if self.drop_privileges {
                 Privilege::drop_privileges()?;
             }
-            let strategy_config = self.make_strategy_config();
+            let strategy_config = StrategyConfig {
+                trace_identifier: channel.trace_identifier().unwrap_or(self.trace_identifier),
+                ..self.make_strategy_config()
+            };
             let strategy = Strategy::new(&strategy_config, |round| {
                 self.handler(round);
                 func(round).into()
-            });
-            strategy.run(channel)?;
+            })
+            .with_observer(self.observer.clone())
+            .with_hop_addr_filter(self.hop_addr_filter.clone());
+            let stop_reason = strategy.run(channel)?;
+            self.state.write().set_stop_reason(Some(stop_reason));
             Ok(())
         }
 
@@ -752,14 +1252,28 @@ mod inner {
         }
 
         fn handle_error(&self, err: Error) -> Error {
-            self.state.write().set_error(Some(err.to_string()));
+            let mut state = self.state.write();
+            state.set_error(Some(err.to_string()));
+            state.set_error_transient(err.is_transient());
+            drop(state);
             err
         }
 
-        const fn make_state_config(max_flows: usize, max_samples: usize) -> StateConfig {
+        const fn make_state_config(
+            max_flows: usize,
+            max_samples: usize,
+            negative_rtt_mode: NegativeRttMode,
+            window: Option<NonZeroUsize>,
+            max_addrs: Option<NonZeroUsize>,
+            multipath_strategy: MultipathStrategy,
+        ) -> StateConfig {
             StateConfig {
                 max_samples,
                 max_flows,
+                negative_rtt_mode,
+                window,
+                max_addrs,
+                flow_aware: multipath_strategy.is_flow_aware(),
             }
         }
 
@@ -774,8 +1288,20 @@ mod inner {
                 initial_sequence: self.initial_sequence,
                 tos: self.tos,
                 icmp_extension_parse_mode: self.icmp_extension_parse_mode,
+                icmp_identifier_match_mode: self.icmp_identifier_match_mode,
+                icmp_source_match_mode: self.icmp_source_match_mode,
+                icmp_quoted_payload_mode: self.icmp_quoted_payload_mode,
+                fwmark: self.fwmark,
+                icmp_nonce_key: self.icmp_nonce_key,
+                icmp_identifier_range: self.icmp_identifier_range,
+                icmp_payload_len: self.icmp_payload_len,
+                icmp_echo_code: self.icmp_echo_code,
+                dont_fragment_mode: self.dont_fragment_mode,
+                tcp_flags: self.tcp_flags,
                 read_timeout: self.read_timeout,
                 tcp_connect_timeout: self.tcp_connect_timeout,
+                trace_identifier: self.trace_identifier,
+                icmp_bind_attempts: self.icmp_bind_attempts,
             }
         }
 
@@ -785,15 +1311,24 @@ mod inner {
                 protocol: self.protocol,
                 trace_identifier: self.trace_identifier,
                 max_rounds: self.max_rounds,
+                max_packets: self.max_packets,
+                max_silent_rounds: self.max_silent_rounds,
+                send_interval: self.send_interval,
                 first_ttl: self.first_ttl,
                 max_ttl: self.max_ttl,
                 grace_duration: self.grace_duration,
+                target_grace_duration: self.target_grace_duration,
                 max_inflight: self.max_inflight,
                 initial_sequence: self.initial_sequence,
                 multipath_strategy: self.multipath_strategy,
                 port_direction: self.port_direction,
+                src_port_seed: self.src_port_seed,
                 min_round_duration: self.min_round_duration,
                 max_round_duration: self.max_round_duration,
+                continue_past_target: self.continue_past_target,
+                unreachable_mode: self.unreachable_mode,
+                icmp_unreachable_reached: self.icmp_unreachable_reached,
+                multi_response_mode: self.multi_response_mode,
             }
         }
     }
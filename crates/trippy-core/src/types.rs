@@ -1,5 +1,8 @@
 use bitflags::bitflags;
 use derive_more::{Add, AddAssign, Rem, Sub};
+use siphasher::sip::SipHasher13;
+use std::fmt::{Display, Formatter};
+use std::hash::Hasher;
 use std::num::NonZeroUsize;
 
 /// `Round` newtype.
@@ -10,6 +13,10 @@ pub struct RoundId(pub usize);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
 pub struct MaxRounds(pub NonZeroUsize);
 
+/// `MaxPackets` newtype.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Ord, PartialOrd)]
+pub struct MaxPackets(pub NonZeroUsize);
+
 /// `TimeToLive` (ttl) newtype.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd, Add, Sub, AddAssign)]
 pub struct TimeToLive(pub u8);
@@ -30,9 +37,61 @@ pub struct MaxInflight(pub u8);
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
 pub struct PacketSize(pub u16);
 
-/// `PayloadPattern` newtype.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
-pub struct PayloadPattern(pub u8);
+/// The pattern of the bytes in the payload of a probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadPattern {
+    /// Every byte of the payload is the given value.
+    Fixed(u8),
+    /// Each byte of the payload is its offset within the payload, wrapping at 256.
+    Incrementing,
+    /// Every byte of the payload is random.
+    ///
+    /// The bytes are derived from a seed which is fixed for the lifetime of a trace, and so every
+    /// probe of a trace carries the same payload.  This allows the checksum of a UDP probe quoted
+    /// in an ICMP error to be recalculated, as is required to detect NAT devices.
+    Random,
+}
+
+impl PayloadPattern {
+    /// Fill `buf` with the pattern.
+    ///
+    /// The `seed` is only used by the `Random` pattern, for which the same seed always yields the
+    /// same bytes.
+    pub fn fill(self, buf: &mut [u8], seed: u64) {
+        match self {
+            Self::Fixed(value) => buf.fill(value),
+            Self::Incrementing => {
+                for (byte, value) in buf.iter_mut().zip((0..=u8::MAX).cycle()) {
+                    *byte = value;
+                }
+            }
+            Self::Random => {
+                for (i, chunk) in buf.chunks_mut(size_of::<u64>()).enumerate() {
+                    let mut hasher = SipHasher13::new_with_keys(seed, 0);
+                    hasher.write_usize(i);
+                    let bytes = hasher.finish().to_be_bytes();
+                    chunk.copy_from_slice(&bytes[..chunk.len()]);
+                }
+            }
+        }
+    }
+}
+
+impl Default for PayloadPattern {
+    fn default() -> Self {
+        Self::Fixed(0)
+    }
+}
+
+impl Display for PayloadPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Fixed(value) => write!(f, "{value}"),
+            Self::Incrementing => write!(f, "incrementing"),
+            Self::Random => write!(f, "random"),
+        }
+    }
+}
 
 /// `TypeOfService` (aka `DSCP` & `ECN`) newtype.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Ord, PartialOrd)]
@@ -146,6 +205,37 @@ pub enum Dscp {
     Other(u8),
 }
 
+impl Display for Dscp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DF => write!(f, "DF"),
+            Self::AF11 => write!(f, "AF11"),
+            Self::AF12 => write!(f, "AF12"),
+            Self::AF13 => write!(f, "AF13"),
+            Self::AF21 => write!(f, "AF21"),
+            Self::AF22 => write!(f, "AF22"),
+            Self::AF23 => write!(f, "AF23"),
+            Self::AF31 => write!(f, "AF31"),
+            Self::AF32 => write!(f, "AF32"),
+            Self::AF33 => write!(f, "AF33"),
+            Self::AF41 => write!(f, "AF41"),
+            Self::AF42 => write!(f, "AF42"),
+            Self::AF43 => write!(f, "AF43"),
+            Self::CS1 => write!(f, "CS1"),
+            Self::CS2 => write!(f, "CS2"),
+            Self::CS3 => write!(f, "CS3"),
+            Self::CS4 => write!(f, "CS4"),
+            Self::CS5 => write!(f, "CS5"),
+            Self::CS6 => write!(f, "CS6"),
+            Self::CS7 => write!(f, "CS7"),
+            Self::EF => write!(f, "EF"),
+            Self::VA => write!(f, "VA"),
+            Self::LE => write!(f, "LE"),
+            Self::Other(other) => write!(f, "0x{other:02x}"),
+        }
+    }
+}
+
 impl TypeOfService {
     #[must_use]
     pub fn dscp(&self) -> Dscp {
@@ -207,4 +297,32 @@ mod tests {
         assert_eq!(tos.dscp(), dscp);
         assert_eq!(tos.ecn(), ecn);
     }
+
+    #[test]
+    fn test_payload_pattern_fixed() {
+        let mut buf = [0_u8; 4];
+        PayloadPattern::Fixed(0xaa).fill(&mut buf, 0);
+        assert_eq!([0xaa; 4], buf);
+    }
+
+    #[test]
+    fn test_payload_pattern_incrementing() {
+        let mut buf = [0_u8; 258];
+        PayloadPattern::Incrementing.fill(&mut buf, 0);
+        assert_eq!([0x00, 0x01, 0x02, 0x03], buf[..4]);
+        assert_eq!([0xfe, 0xff, 0x00, 0x01], buf[254..]);
+    }
+
+    #[test]
+    fn test_payload_pattern_random() {
+        let mut buf1 = [0_u8; 20];
+        let mut buf2 = [0_u8; 20];
+        let mut buf3 = [0_u8; 20];
+        PayloadPattern::Random.fill(&mut buf1, 1);
+        PayloadPattern::Random.fill(&mut buf2, 2);
+        PayloadPattern::Random.fill(&mut buf3, 1);
+        assert_ne!(buf1, buf2);
+        assert_eq!(buf1, buf3);
+        assert_ne!([0_u8; 20], buf1);
+    }
 }
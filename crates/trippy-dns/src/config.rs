@@ -1,4 +1,5 @@
 use crate::{IpAddrFamily, ResolveMethod};
+use std::net::SocketAddr;
 use std::time::Duration;
 
 /// A builder for DNS `Config`.
@@ -16,6 +17,8 @@ pub struct Builder {
     addr_family: IpAddrFamily,
     timeout: Duration,
     ttl: Duration,
+    server: Option<SocketAddr>,
+    doh: Option<String>,
 }
 
 impl Builder {
@@ -27,12 +30,14 @@ impl Builder {
             addr_family: Config::default().addr_family,
             timeout: Config::default().timeout,
             ttl: Config::default().ttl,
+            server: Config::default().server,
+            doh: Config::default().doh,
         }
     }
 
     /// Set the method to use for DNS resolution.
     #[must_use]
-    pub const fn resolve_method(self, resolve_method: ResolveMethod) -> Self {
+    pub fn resolve_method(self, resolve_method: ResolveMethod) -> Self {
         Self {
             resolve_method,
             ..self
@@ -41,7 +46,7 @@ impl Builder {
 
     /// Set the address family.
     #[must_use]
-    pub const fn addr_family(self, addr_family: IpAddrFamily) -> Self {
+    pub fn addr_family(self, addr_family: IpAddrFamily) -> Self {
         Self {
             addr_family,
             ..self
@@ -50,24 +55,38 @@ impl Builder {
 
     /// Set the timeout for DNS resolution.
     #[must_use]
-    pub const fn timeout(self, timeout: Duration) -> Self {
+    pub fn timeout(self, timeout: Duration) -> Self {
         Self { timeout, ..self }
     }
 
     /// Set the time-to-live (TTL) for DNS cache entries.
     #[must_use]
-    pub const fn ttl(self, ttl: Duration) -> Self {
+    pub fn ttl(self, ttl: Duration) -> Self {
         Self { ttl, ..self }
     }
 
+    /// Set the DNS server to query directly, if any.
+    #[must_use]
+    pub fn server(self, server: Option<SocketAddr>) -> Self {
+        Self { server, ..self }
+    }
+
+    /// Set the DNS-over-HTTPS resolver URL to query, if any.
+    #[must_use]
+    pub fn doh(self, doh: Option<String>) -> Self {
+        Self { doh, ..self }
+    }
+
     /// Build the DNS `Config`.
     #[must_use]
-    pub const fn build(self) -> Config {
+    pub fn build(self) -> Config {
         Config {
             resolve_method: self.resolve_method,
             addr_family: self.addr_family,
             timeout: self.timeout,
             ttl: self.ttl,
+            server: self.server,
+            doh: self.doh,
         }
     }
 }
@@ -79,7 +98,7 @@ impl Default for Builder {
 }
 
 /// Configuration for the `DnsResolver`.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Config {
     /// The method to use for DNS resolution.
     pub resolve_method: ResolveMethod,
@@ -89,6 +108,16 @@ pub struct Config {
     pub timeout: Duration,
     /// The time-to-live (TTL) for DNS cache entries.
     pub ttl: Duration,
+    /// The DNS server to query directly, if any.
+    ///
+    /// If set then all queries are sent to this server, regardless of the
+    /// `resolve_method`.
+    pub server: Option<SocketAddr>,
+    /// The DNS-over-HTTPS resolver URL to query, if any.
+    ///
+    /// If set then all queries are sent to this resolver using DNS-over-HTTPS (RFC 8484),
+    /// regardless of the `server` and `resolve_method`.  Requires the `doh` feature.
+    pub doh: Option<String>,
 }
 
 impl Config {
@@ -105,6 +134,8 @@ impl Config {
             addr_family,
             timeout,
             ttl,
+            server: None,
+            doh: None,
         }
     }
 }
@@ -116,6 +147,8 @@ impl Default for Config {
             addr_family: IpAddrFamily::Ipv4thenIpv6,
             timeout: Duration::from_millis(5000),
             ttl: Duration::from_secs(300),
+            server: None,
+            doh: None,
         }
     }
 }
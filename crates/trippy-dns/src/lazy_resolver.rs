@@ -2,7 +2,10 @@ use crate::config::Config;
 use crate::resolver::{DnsEntry, ResolvedIpAddrs, Resolver, Result};
 use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
-use std::rc::Rc;
+use std::sync::Arc;
+
+#[cfg(feature = "doh")]
+pub use inner::{asn_query_name, origin_query_name, parse_asn_query_txt, parse_origin_query_txt};
 
 /// How DNS queries will be resolved.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -48,14 +51,14 @@ impl Display for IpAddrFamily {
 /// A cheaply cloneable, non-blocking, caching, forward and reverse DNS resolver.
 #[derive(Clone)]
 pub struct DnsResolver {
-    inner: Rc<inner::DnsResolver>,
+    inner: Arc<inner::DnsResolver>,
 }
 
 impl DnsResolver {
     /// Create and start a new `DnsResolver`.
     pub fn start(config: Config) -> std::io::Result<Self> {
         Ok(Self {
-            inner: Rc::new(inner::DnsResolver::start(config)?),
+            inner: Arc::new(inner::DnsResolver::start(config)?),
         })
     }
 
@@ -92,9 +95,13 @@ impl Resolver for DnsResolver {
 /// Private impl of resolver.
 mod inner {
     use super::{Config, IpAddrFamily, ResolveMethod};
+    #[cfg(feature = "doh")]
+    use crate::doh::DohResolver;
     use crate::resolver::{AsInfo, DnsEntry, Error, Resolved, ResolvedIpAddrs, Result, Unresolved};
     use crossbeam::channel::{Receiver, Sender, bounded};
-    use hickory_resolver::config::{LookupIpStrategy, ResolverConfig, ResolverOpts};
+    use hickory_resolver::config::{
+        LookupIpStrategy, NameServerConfigGroup, ResolverConfig, ResolverOpts,
+    };
     use hickory_resolver::error::{ResolveError, ResolveErrorKind};
     use hickory_resolver::proto::error::ProtoError;
     use hickory_resolver::proto::rr::RecordType;
@@ -103,7 +110,7 @@ mod inner {
     use itertools::{Either, Itertools};
     use parking_lot::RwLock;
     use std::collections::HashMap;
-    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::net::IpAddr;
     use std::str::FromStr;
     use std::sync::Arc;
     use std::thread;
@@ -142,6 +149,24 @@ mod inner {
     enum DnsProvider {
         TrustDns(Arc<Resolver>),
         DnsLookup,
+        #[cfg(feature = "doh")]
+        Doh(Arc<DohResolver>),
+    }
+
+    /// Create a DNS-over-HTTPS provider which queries the resolver at `url`.
+    #[cfg(feature = "doh")]
+    fn doh_provider(url: &str, config: &Config) -> std::io::Result<DnsProvider> {
+        let resolver = DohResolver::new(url, config.addr_family, config.timeout)?;
+        Ok(DnsProvider::Doh(Arc::new(resolver)))
+    }
+
+    /// DNS-over-HTTPS is not supported without the `doh` feature.
+    #[cfg(not(feature = "doh"))]
+    fn doh_provider(_url: &str, _config: &Config) -> std::io::Result<DnsProvider> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "DNS-over-HTTPS requires the doh feature",
+        ))
     }
 
     #[derive(Debug, Clone)]
@@ -163,35 +188,15 @@ mod inner {
             let (tx, rx) = bounded(RESOLVER_MAX_QUEUE_SIZE);
             let addr_cache = Arc::new(RwLock::new(HashMap::new()));
 
-            let provider = if matches!(config.resolve_method, ResolveMethod::System) {
+            let provider = if let Some(url) = &config.doh {
+                doh_provider(url, &config)?
+            } else if config.server.is_none()
+                && matches!(config.resolve_method, ResolveMethod::System)
+            {
                 DnsProvider::DnsLookup
             } else {
-                let mut options = ResolverOpts::default();
-                #[expect(clippy::match_same_arms)]
-                let ip_strategy = match config.addr_family {
-                    IpAddrFamily::Ipv4Only => LookupIpStrategy::Ipv4Only,
-                    IpAddrFamily::Ipv6Only => LookupIpStrategy::Ipv6Only,
-                    IpAddrFamily::Ipv6thenIpv4 => LookupIpStrategy::Ipv6thenIpv4,
-                    IpAddrFamily::Ipv4thenIpv6 => LookupIpStrategy::Ipv4thenIpv6,
-                    // see issue #1469
-                    IpAddrFamily::System => LookupIpStrategy::Ipv4thenIpv6,
-                };
-                options.timeout = config.timeout;
-                options.ip_strategy = ip_strategy;
-                let res = match config.resolve_method {
-                    ResolveMethod::Resolv => {
-                        let (resolver_cfg, mut options) = read_system_conf()?;
-                        options.timeout = config.timeout;
-                        options.ip_strategy = ip_strategy;
-                        Resolver::new(resolver_cfg, options)
-                    }
-                    ResolveMethod::Google => Resolver::new(ResolverConfig::google(), options),
-                    ResolveMethod::Cloudflare => {
-                        Resolver::new(ResolverConfig::cloudflare(), options)
-                    }
-                    ResolveMethod::System => unreachable!(),
-                }?;
-                let resolver = Arc::new(res);
+                let (resolver_cfg, options) = resolver_config(&config)?;
+                let resolver = Arc::new(Resolver::new(resolver_cfg, options)?);
                 DnsProvider::TrustDns(resolver)
             };
 
@@ -226,6 +231,8 @@ mod inner {
                     .map_err(|err| Error::LookupFailed(Box::new(err)))?
                     .iter()
                     .collect::<Vec<_>>()),
+                #[cfg(feature = "doh")]
+                DnsProvider::Doh(resolver) => return resolver.lookup_addrs(hostname),
                 DnsProvider::DnsLookup => {
                     let all = dns_lookup::lookup_host(hostname)
                         .map_err(|err| Error::LookupFailed(Box::new(err)))?;
@@ -346,6 +353,41 @@ mod inner {
         }
     }
 
+    /// The resolver configuration and options for `config`.
+    ///
+    /// If a DNS server is configured then all queries are sent to it directly, regardless of the
+    /// resolve method.
+    fn resolver_config(config: &Config) -> std::io::Result<(ResolverConfig, ResolverOpts)> {
+        #[expect(clippy::match_same_arms)]
+        let ip_strategy = match config.addr_family {
+            IpAddrFamily::Ipv4Only => LookupIpStrategy::Ipv4Only,
+            IpAddrFamily::Ipv6Only => LookupIpStrategy::Ipv6Only,
+            IpAddrFamily::Ipv6thenIpv4 => LookupIpStrategy::Ipv6thenIpv4,
+            IpAddrFamily::Ipv4thenIpv6 => LookupIpStrategy::Ipv4thenIpv6,
+            // see issue #1469
+            IpAddrFamily::System => LookupIpStrategy::Ipv4thenIpv6,
+        };
+        let (resolver_cfg, mut options) = match (config.server, config.resolve_method) {
+            (Some(server), _) => (
+                ResolverConfig::from_parts(
+                    None,
+                    vec![],
+                    NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true),
+                ),
+                ResolverOpts::default(),
+            ),
+            (None, ResolveMethod::Resolv) => read_system_conf()?,
+            (None, ResolveMethod::Google) => (ResolverConfig::google(), ResolverOpts::default()),
+            (None, ResolveMethod::Cloudflare) => {
+                (ResolverConfig::cloudflare(), ResolverOpts::default())
+            }
+            (None, ResolveMethod::System) => unreachable!(),
+        };
+        options.timeout = config.timeout;
+        options.ip_strategy = ip_strategy;
+        Ok((resolver_cfg, options))
+    }
+
     /// Process each `IpAddr` from the resolver queue and perform the reverse DNS lookup.
     ///
     /// For each `IpAddr`, perform the reverse DNS lookup and update the cache with the result
@@ -364,6 +406,10 @@ mod inner {
     fn reverse_lookup(provider: &DnsProvider, addr: IpAddr, with_asinfo: bool) -> CacheEntry {
         let now = SystemTime::now();
         match &provider {
+            #[cfg(feature = "doh")]
+            DnsProvider::Doh(resolver) => {
+                CacheEntry::new(resolver.reverse_lookup_entry(addr, with_asinfo), now)
+            }
             DnsProvider::DnsLookup => {
                 // we can't distinguish between a failed lookup or a genuine error, and so we just
                 // assume all failures are `DnsEntry::NotFound`.
@@ -415,12 +461,11 @@ mod inner {
 
     /// Lookup up `AsInfo` for an `IpAddr` address.
     fn lookup_asinfo(resolver: &Arc<Resolver>, addr: IpAddr) -> Result<AsInfo> {
-        let origin_query_txt = match addr {
-            IpAddr::V4(addr) => query_asn_ipv4(resolver, addr)?,
-            IpAddr::V6(addr) => query_asn_ipv6(resolver, addr)?,
-        };
+        let origin_query_txt = query_txt(resolver, &origin_query_name(addr))
+            .and_then(|txt| txt.ok_or(Error::QueryAsnOriginFailed))?;
         let asinfo = parse_origin_query_txt(&origin_query_txt)?;
-        let asn_query_txt = query_asn_name(resolver, &asinfo.asn)?;
+        let asn_query_txt = query_txt(resolver, &asn_query_name(&asinfo.asn))
+            .and_then(|txt| txt.ok_or(Error::QueryAsnFailed))?;
         let as_name = parse_asn_query_txt(&asn_query_txt)?;
         Ok(AsInfo {
             asn: asinfo.asn,
@@ -432,59 +477,40 @@ mod inner {
         })
     }
 
-    /// Perform the `origin` query.
-    fn query_asn_ipv4(resolver: &Arc<Resolver>, addr: Ipv4Addr) -> Result<String> {
-        let query = format!(
-            "{}.origin.asn.cymru.com.",
-            addr.octets().iter().rev().join(".")
-        );
-        let name = Name::from_str(query.as_str()).map_err(proto_error)?;
+    /// Perform a TXT query and return the first record, if any.
+    fn query_txt(resolver: &Arc<Resolver>, query: &str) -> Result<Option<String>> {
+        let name = Name::from_str(query).map_err(proto_error)?;
         let response = resolver
             .lookup(name, RecordType::TXT)
             .map_err(resolve_error)?;
-        let data = response
+        Ok(response
             .iter()
             .next()
-            .ok_or_else(|| Error::QueryAsnOriginFailed)?;
-        let bytes = data.as_txt().ok_or_else(|| Error::QueryAsnOriginFailed)?;
-        Ok(bytes.to_string())
+            .and_then(|data| data.as_txt())
+            .map(ToString::to_string))
     }
 
-    /// Perform the `origin` query.
-    fn query_asn_ipv6(resolver: &Arc<Resolver>, addr: Ipv6Addr) -> Result<String> {
-        let query = format!(
-            "{:x}.origin6.asn.cymru.com.",
-            addr.octets()
-                .iter()
-                .rev()
-                .flat_map(|o| [o & 0x0F, (o & 0xF0) >> 4])
-                .format(".")
-        );
-        let name = Name::from_str(query.as_str()).map_err(proto_error)?;
-        let response = resolver
-            .lookup(name, RecordType::TXT)
-            .map_err(resolve_error)?;
-        let data = response
-            .iter()
-            .next()
-            .ok_or_else(|| Error::QueryAsnOriginFailed)?;
-        let bytes = data.as_txt().ok_or_else(|| Error::QueryAsnOriginFailed)?;
-        Ok(bytes.to_string())
+    /// The name of the `origin` query for an `IpAddr`.
+    pub fn origin_query_name(addr: IpAddr) -> String {
+        match addr {
+            IpAddr::V4(addr) => format!(
+                "{}.origin.asn.cymru.com.",
+                addr.octets().iter().rev().join(".")
+            ),
+            IpAddr::V6(addr) => format!(
+                "{:x}.origin6.asn.cymru.com.",
+                addr.octets()
+                    .iter()
+                    .rev()
+                    .flat_map(|o| [o & 0x0F, (o & 0xF0) >> 4])
+                    .format(".")
+            ),
+        }
     }
 
-    /// Perform the `asn` query.
-    fn query_asn_name(resolver: &Arc<Resolver>, asn: &str) -> Result<String> {
-        let query = format!("AS{asn}.asn.cymru.com.");
-        let name = Name::from_str(query.as_str()).map_err(proto_error)?;
-        let response = resolver
-            .lookup(name, RecordType::TXT)
-            .map_err(resolve_error)?;
-        let data = response
-            .iter()
-            .next()
-            .ok_or_else(|| Error::QueryAsnFailed)?;
-        let bytes = data.as_txt().ok_or_else(|| Error::QueryAsnFailed)?;
-        Ok(bytes.to_string())
+    /// The name of the `asn` query for an autonomous system number.
+    pub fn asn_query_name(asn: &str) -> String {
+        format!("AS{asn}.asn.cymru.com.")
     }
 
     /// The `origin` DNS query returns a TXT record in the formal:
@@ -494,7 +520,7 @@ mod inner {
     ///      `12301 | 81.0.100.0/22 | HU | ripencc | 2001-12-06`
     ///
     /// From this we extract all fields.
-    fn parse_origin_query_txt(origin_query_txt: &str) -> Result<AsInfo> {
+    pub fn parse_origin_query_txt(origin_query_txt: &str) -> Result<AsInfo> {
         if origin_query_txt.chars().filter(|c| *c == '|').count() != 4 {
             return Err(Error::ParseOriginQueryFailed(String::from(
                 origin_query_txt,
@@ -523,7 +549,7 @@ mod inner {
     ///      `12301 | HU | ripencc | 1999-02-25 | INVITECH, HU`
     ///
     /// From this we extract the 4th field (name, `INVITECH, HU` in this example)
-    fn parse_asn_query_txt(asn_query_txt: &str) -> Result<String> {
+    pub fn parse_asn_query_txt(asn_query_txt: &str) -> Result<String> {
         if asn_query_txt.chars().filter(|c| *c == '|').count() != 4 {
             return Err(Error::ParseAsnQueryFailed(String::from(asn_query_txt)));
         }
@@ -540,4 +566,41 @@ mod inner {
     fn proto_error(err: ProtoError) -> Error {
         Error::LookupFailed(Box::new(err))
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::config::Builder;
+        use hickory_resolver::config::Protocol;
+        use std::net::SocketAddr;
+
+        #[test]
+        fn test_resolver_config_server() {
+            let server = SocketAddr::from(([192, 0, 2, 53], 5353));
+            let config = Builder::new()
+                .resolve_method(ResolveMethod::Cloudflare)
+                .timeout(Duration::from_secs(2))
+                .server(Some(server))
+                .build();
+            let (resolver_cfg, options) = resolver_config(&config).unwrap();
+            let name_servers = resolver_cfg.name_servers();
+            assert_eq!(2, name_servers.len());
+            assert!(name_servers.iter().all(|ns| ns.socket_addr == server));
+            assert_eq!(Protocol::Udp, name_servers[0].protocol);
+            assert_eq!(Protocol::Tcp, name_servers[1].protocol);
+            assert_eq!(Duration::from_secs(2), options.timeout);
+        }
+
+        #[test]
+        fn test_resolver_config_no_server() {
+            let config = Builder::new()
+                .resolve_method(ResolveMethod::Cloudflare)
+                .build();
+            let (resolver_cfg, _) = resolver_config(&config).unwrap();
+            assert_eq!(
+                ResolverConfig::cloudflare().name_servers(),
+                resolver_cfg.name_servers()
+            );
+        }
+    }
 }
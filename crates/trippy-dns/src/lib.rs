@@ -72,9 +72,17 @@
 #![forbid(unsafe_code)]
 
 mod config;
+#[cfg(feature = "doh")]
+mod doh;
 mod lazy_resolver;
+mod recording;
 mod resolver;
 
 pub use config::{Builder, Config};
+#[cfg(feature = "doh")]
+pub use doh::DohResolver;
 pub use lazy_resolver::{DnsResolver, IpAddrFamily, ResolveMethod};
-pub use resolver::{AsInfo, DnsEntry, Error, Resolved, Resolver, Result, Unresolved};
+pub use recording::{PlaybackResolver, RecordingResolver};
+pub use resolver::{
+    AsInfo, DnsEntry, Error, Resolved, ResolvedIpAddrs, Resolver, Result, Unresolved,
+};
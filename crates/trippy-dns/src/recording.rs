@@ -0,0 +1,422 @@
+use crate::resolver::{
+    AsInfo, DnsEntry, Error, Resolved, ResolvedIpAddrs, Resolver, Result, Unresolved,
+};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// The separator between the fields of a recorded lookup.
+const SEPARATOR: char = '\t';
+
+/// A `Resolver` which records every lookup performed by an inner `Resolver`.
+///
+/// Each forward and reverse lookup, and its result, is appended to the recording as a single
+/// line which may later be replayed with a [`PlaybackResolver`].  Reverse lookups which are still
+/// pending are not recorded.
+///
+/// A failure to write to the recording does not fail the lookup.
+pub struct RecordingResolver<R> {
+    inner: R,
+    sink: Mutex<Box<dyn Write + Send>>,
+}
+
+impl<R: Resolver> RecordingResolver<R> {
+    /// Create a new recording at `path`, replacing any existing file.
+    pub fn create(inner: R, path: &Path) -> std::io::Result<Self> {
+        Ok(Self::new(inner, Box::new(File::create(path)?)))
+    }
+
+    /// Create a `RecordingResolver` which records the lookups of `inner` to `sink`.
+    pub fn new(inner: R, sink: Box<dyn Write + Send>) -> Self {
+        Self {
+            inner,
+            sink: Mutex::new(sink),
+        }
+    }
+
+    /// The inner `Resolver`.
+    pub const fn inner(&self) -> &R {
+        &self.inner
+    }
+
+    fn record(&self, fields: &[&str]) {
+        let line = fields.join(&SEPARATOR.to_string());
+        let mut sink = self.sink.lock();
+        _ = writeln!(sink, "{line}").and_then(|()| sink.flush());
+        drop(sink);
+    }
+
+    fn record_reverse(&self, with_asinfo: bool, entry: &DnsEntry) {
+        let kind = if with_asinfo {
+            "reverse-asinfo"
+        } else {
+            "reverse"
+        };
+        let (addr, mut fields) = match entry {
+            DnsEntry::Pending(_) => return,
+            DnsEntry::Resolved(Resolved::Normal(addr, hosts)) => {
+                (addr, vec![String::from("resolved"), hosts.join(" ")])
+            }
+            DnsEntry::Resolved(Resolved::WithAsInfo(addr, hosts, as_info)) => {
+                let mut fields = vec![String::from("resolved"), hosts.join(" ")];
+                fields.extend(format_as_info(as_info));
+                (addr, fields)
+            }
+            DnsEntry::NotFound(Unresolved::Normal(addr)) => (addr, vec![String::from("not-found")]),
+            DnsEntry::NotFound(Unresolved::WithAsInfo(addr, as_info)) => {
+                let mut fields = vec![String::from("not-found")];
+                fields.extend(format_as_info(as_info));
+                (addr, fields)
+            }
+            DnsEntry::Failed(addr) => (addr, vec![String::from("failed")]),
+            DnsEntry::Timeout(addr) => (addr, vec![String::from("timeout")]),
+        };
+        fields.insert(0, addr.to_string());
+        fields.insert(0, String::from(kind));
+        self.record(&fields.iter().map(String::as_str).collect::<Vec<_>>());
+    }
+}
+
+impl<R: Resolver> Resolver for RecordingResolver<R> {
+    fn lookup(&self, hostname: impl AsRef<str>) -> Result<ResolvedIpAddrs> {
+        let hostname = hostname.as_ref();
+        let result = self.inner.lookup(hostname);
+        match &result {
+            Ok(addrs) => self.record(&["lookup", hostname, "ok", &addrs.iter().join(" ")]),
+            Err(_) => self.record(&["lookup", hostname, "failed"]),
+        }
+        result
+    }
+
+    fn reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        let entry = self.inner.reverse_lookup(addr);
+        self.record_reverse(false, &entry);
+        entry
+    }
+
+    fn reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        let entry = self.inner.reverse_lookup_with_asinfo(addr);
+        self.record_reverse(true, &entry);
+        entry
+    }
+
+    fn lazy_reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        let entry = self.inner.lazy_reverse_lookup(addr);
+        self.record_reverse(false, &entry);
+        entry
+    }
+
+    fn lazy_reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        let entry = self.inner.lazy_reverse_lookup_with_asinfo(addr);
+        self.record_reverse(true, &entry);
+        entry
+    }
+}
+
+impl<R: Debug> Debug for RecordingResolver<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingResolver")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A `Resolver` which answers every lookup from a recording made by a [`RecordingResolver`].
+///
+/// No DNS queries are performed.  Where the same lookup was recorded more than once the last
+/// result is used.  A forward lookup which was not recorded fails and a reverse lookup which was
+/// not recorded returns `DnsEntry::Failed`.
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackResolver {
+    lookups: HashMap<String, Option<Vec<IpAddr>>>,
+    reverse_lookups: HashMap<(IpAddr, bool), DnsEntry>,
+}
+
+impl PlaybackResolver {
+    /// Load the recording at `path`.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read a recording from `reader`.
+    pub fn from_reader(reader: impl BufRead) -> std::io::Result<Self> {
+        let mut playback = Self::default();
+        for line in reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            playback.parse_line(&line).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid recorded lookup: {line}"),
+                )
+            })?;
+        }
+        Ok(playback)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<()> {
+        let fields = line.split(SEPARATOR).collect::<Vec<_>>();
+        match fields.as_slice() {
+            ["lookup", hostname, "ok", addrs] => {
+                let addrs = addrs
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<std::result::Result<Vec<IpAddr>, _>>()
+                    .ok()?;
+                self.lookups.insert((*hostname).to_string(), Some(addrs));
+            }
+            ["lookup", hostname, "failed"] => {
+                self.lookups.insert((*hostname).to_string(), None);
+            }
+            [kind @ ("reverse" | "reverse-asinfo"), addr, rest @ ..] => {
+                let addr = addr.parse().ok()?;
+                let entry = parse_entry(addr, rest)?;
+                self.reverse_lookups
+                    .insert((addr, *kind == "reverse-asinfo"), entry);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn reverse(&self, addr: IpAddr, with_asinfo: bool) -> DnsEntry {
+        self.reverse_lookups
+            .get(&(addr, with_asinfo))
+            .cloned()
+            .unwrap_or(DnsEntry::Failed(addr))
+    }
+}
+
+impl Resolver for PlaybackResolver {
+    fn lookup(&self, hostname: impl AsRef<str>) -> Result<ResolvedIpAddrs> {
+        let hostname = hostname.as_ref();
+        match self.lookups.get(hostname) {
+            Some(Some(addrs)) => Ok(ResolvedIpAddrs(addrs.clone())),
+            Some(None) => Err(Error::LookupFailed(Box::from(format!(
+                "recorded lookup of {hostname} failed"
+            )))),
+            None => Err(Error::LookupFailed(Box::from(format!(
+                "no recorded lookup of {hostname}"
+            )))),
+        }
+    }
+
+    fn reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse(addr.into(), false)
+    }
+
+    fn reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse(addr.into(), true)
+    }
+
+    fn lazy_reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse(addr.into(), false)
+    }
+
+    fn lazy_reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse(addr.into(), true)
+    }
+}
+
+fn format_as_info(as_info: &AsInfo) -> [String; 6] {
+    [
+        as_info.asn.clone(),
+        as_info.prefix.clone(),
+        as_info.cc.clone(),
+        as_info.registry.clone(),
+        as_info.allocated.clone(),
+        as_info.name.clone(),
+    ]
+}
+
+fn parse_as_info(fields: &[&str]) -> Option<AsInfo> {
+    let [asn, prefix, cc, registry, allocated, name] = fields else {
+        return None;
+    };
+    Some(AsInfo {
+        asn: (*asn).to_string(),
+        prefix: (*prefix).to_string(),
+        cc: (*cc).to_string(),
+        registry: (*registry).to_string(),
+        allocated: (*allocated).to_string(),
+        name: (*name).to_string(),
+    })
+}
+
+fn parse_entry(addr: IpAddr, fields: &[&str]) -> Option<DnsEntry> {
+    let hostnames = |hosts: &str| hosts.split_whitespace().map(String::from).collect();
+    Some(match fields {
+        ["resolved", hosts] => DnsEntry::Resolved(Resolved::Normal(addr, hostnames(hosts))),
+        ["resolved", hosts, as_info @ ..] => DnsEntry::Resolved(Resolved::WithAsInfo(
+            addr,
+            hostnames(hosts),
+            parse_as_info(as_info)?,
+        )),
+        ["not-found"] => DnsEntry::NotFound(Unresolved::Normal(addr)),
+        ["not-found", as_info @ ..] => {
+            DnsEntry::NotFound(Unresolved::WithAsInfo(addr, parse_as_info(as_info)?))
+        }
+        ["failed"] => DnsEntry::Failed(addr),
+        ["timeout"] => DnsEntry::Timeout(addr),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A recording sink which may be inspected after it has been written to.
+    #[derive(Clone, Default)]
+    struct SharedSink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedSink {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A `Resolver` with fixed answers which counts the lookups performed.
+    #[derive(Default)]
+    struct CountingResolver(AtomicUsize);
+
+    impl Resolver for CountingResolver {
+        fn lookup(&self, hostname: impl AsRef<str>) -> Result<ResolvedIpAddrs> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            match hostname.as_ref() {
+                "example.com" => Ok(ResolvedIpAddrs(vec![
+                    IpAddr::from_str("192.0.2.1").unwrap(),
+                    IpAddr::from_str("2001:db8::1").unwrap(),
+                ])),
+                _ => Err(Error::LookupFailed(Box::from("no such host"))),
+            }
+        }
+
+        fn reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let addr = addr.into();
+            if addr == IpAddr::from_str("192.0.2.1").unwrap() {
+                DnsEntry::Resolved(Resolved::Normal(
+                    addr,
+                    vec![
+                        String::from("one.example.com"),
+                        String::from("two.example.com"),
+                    ],
+                ))
+            } else {
+                DnsEntry::NotFound(Unresolved::Normal(addr))
+            }
+        }
+
+        fn reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let as_info = AsInfo {
+                asn: String::from("12301"),
+                prefix: String::from("192.0.2.0/24"),
+                cc: String::from("HU"),
+                registry: String::from("ripencc"),
+                allocated: String::from("1999-02-25"),
+                name: String::from("INVITECH, HU"),
+            };
+            DnsEntry::Resolved(Resolved::WithAsInfo(
+                addr.into(),
+                vec![String::from("one.example.com")],
+                as_info,
+            ))
+        }
+
+        fn lazy_reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            DnsEntry::Pending(addr.into())
+        }
+
+        fn lazy_reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+            self.lazy_reverse_lookup(addr)
+        }
+    }
+
+    fn recording() -> (RecordingResolver<CountingResolver>, SharedSink) {
+        let sink = SharedSink::default();
+        let recording = RecordingResolver::new(CountingResolver::default(), Box::new(sink.clone()));
+        (recording, sink)
+    }
+
+    fn playback(sink: &SharedSink) -> PlaybackResolver {
+        let recorded = sink.0.lock().clone();
+        PlaybackResolver::from_reader(recorded.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_playback_reverse_lookups() {
+        let one = IpAddr::from_str("192.0.2.1").unwrap();
+        let two = IpAddr::from_str("192.0.2.2").unwrap();
+        let (recording, sink) = recording();
+        let recorded = [
+            recording.reverse_lookup(one),
+            recording.reverse_lookup(two),
+            recording.reverse_lookup_with_asinfo(one),
+        ];
+        assert_eq!(3, recording.inner().0.load(Ordering::SeqCst));
+        let playback = playback(&sink);
+        let played = [
+            playback.reverse_lookup(one),
+            playback.reverse_lookup(two),
+            playback.reverse_lookup_with_asinfo(one),
+        ];
+        assert_eq!(3, recording.inner().0.load(Ordering::SeqCst));
+        assert_eq!(format!("{recorded:?}"), format!("{played:?}"));
+    }
+
+    #[test]
+    fn test_playback_forward_lookups() {
+        let (recording, sink) = recording();
+        recording.lookup("example.com").unwrap();
+        recording.lookup("missing.example.com").unwrap_err();
+        let playback = playback(&sink);
+        let addrs = playback.lookup("example.com").unwrap();
+        assert_eq!(
+            vec![
+                IpAddr::from_str("192.0.2.1").unwrap(),
+                IpAddr::from_str("2001:db8::1").unwrap()
+            ],
+            addrs.into_iter().collect::<Vec<_>>()
+        );
+        assert!(playback.lookup("missing.example.com").is_err());
+        assert!(playback.lookup("unknown.example.com").is_err());
+    }
+
+    #[test]
+    fn test_pending_not_recorded() {
+        let addr = IpAddr::from_str("192.0.2.1").unwrap();
+        let (recording, sink) = recording();
+        assert!(matches!(
+            recording.lazy_reverse_lookup(addr),
+            DnsEntry::Pending(_)
+        ));
+        assert!(sink.0.lock().is_empty());
+        assert!(matches!(
+            playback(&sink).lazy_reverse_lookup(addr),
+            DnsEntry::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_recording() {
+        let err = PlaybackResolver::from_reader(&b"reverse\tnot-an-ip\tfailed\n"[..]).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+}
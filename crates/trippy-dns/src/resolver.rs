@@ -118,6 +118,24 @@ impl DnsEntry {
             }
         }
     }
+
+    /// The display of this entry truncated to at most `max` characters.
+    ///
+    /// If the display is longer than `max` characters it is truncated and ends with an ellipsis,
+    /// which counts towards `max`.
+    #[must_use]
+    pub fn display_truncated(&self, max: usize) -> String {
+        let display = self.to_string();
+        if display.chars().count() <= max {
+            display
+        } else {
+            display
+                .chars()
+                .take(max.saturating_sub(1))
+                .chain(std::iter::once('…'))
+                .collect()
+        }
+    }
 }
 
 /// Information about a resolved `IpAddr`.
@@ -205,4 +223,21 @@ mod tests {
         assert_eq!(iter.next(), Some("three"));
         assert_eq!(iter.next(), None);
     }
+
+    #[test]
+    fn test_display_truncated() {
+        let entry = DnsEntry::Resolved(Resolved::Normal(
+            IpAddr::from_str("1.1.1.1").unwrap(),
+            vec!["one.example.com".to_string(), "two.example.com".to_string()],
+        ));
+        assert_eq!("one.example.com two.ex…", entry.display_truncated(23));
+        assert_eq!(
+            "one.example.com two.example.com",
+            entry.display_truncated(31)
+        );
+        assert_eq!(
+            "one.example.com two.example.com",
+            entry.display_truncated(64)
+        );
+    }
 }
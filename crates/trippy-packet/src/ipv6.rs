@@ -13,6 +13,23 @@ const HOP_LIMIT_OFFSET: usize = 7;
 const SOURCE_ADDRESS_OFFSET: usize = 8;
 const DESTINATION_ADDRESS_OFFSET: usize = 24;
 
+/// The IPv6 extension header types.
+///
+/// See the IANA [IPv6 Extension Header Types] registry.  The Encapsulating Security Payload (50)
+/// is deliberately excluded as the headers that follow it are encrypted.
+///
+/// [IPv6 Extension Header Types]: https://www.iana.org/assignments/ipv6-parameters/ipv6-parameters.xhtml#extension-header
+const HOP_BY_HOP: u8 = 0;
+const ROUTING: u8 = 43;
+const FRAGMENT: u8 = 44;
+const AUTHENTICATION: u8 = 51;
+const DESTINATION_OPTIONS: u8 = 60;
+const MOBILITY: u8 = 135;
+const HOST_IDENTITY: u8 = 139;
+const SHIM6: u8 = 140;
+const EXPERIMENTAL_1: u8 = 253;
+const EXPERIMENTAL_2: u8 = 254;
+
 /// Represents an IPv6 Packet.
 ///
 /// The internal representation is held in network byte order (big-endian) and all accessor methods
@@ -165,6 +182,59 @@ impl<'a> Ipv6Packet<'a> {
         }
         &self.buf.as_slice()[start..end]
     }
+
+    /// The protocol of the upper-layer header, skipping any extension headers.
+    #[must_use]
+    pub fn get_upper_layer_protocol(&self) -> IpProtocol {
+        IpProtocol::from(self.upper_layer().0)
+    }
+
+    /// The upper-layer header and payload, skipping any extension headers.
+    #[must_use]
+    pub fn upper_layer_payload(&self) -> &[u8] {
+        &self.payload()[self.upper_layer().1..]
+    }
+
+    /// Walk the extension header chain to locate the upper-layer header.
+    ///
+    /// Returns the next header value of the upper-layer header and its offset within the payload.
+    /// Extension headers are skipped using their header extension length field and so unfamiliar
+    /// extension headers need not be parsed.  A truncated chain yields an offset at the end of the
+    /// payload.
+    fn upper_layer(&self) -> (u8, usize) {
+        let payload = self.payload();
+        let mut next_header = self.buf.read(NEXT_HEADER_OFFSET);
+        let mut offset = 0;
+        while is_extension_header(next_header) {
+            let Some(&[header, len]) = payload.get(offset..offset + 2) else {
+                return (next_header, payload.len());
+            };
+            let header_len = match next_header {
+                FRAGMENT => 8,
+                AUTHENTICATION => (usize::from(len) + 2) * 4,
+                _ => (usize::from(len) + 1) * 8,
+            };
+            next_header = header;
+            offset = std::cmp::min(offset + header_len, payload.len());
+        }
+        (next_header, offset)
+    }
+}
+
+const fn is_extension_header(next_header: u8) -> bool {
+    matches!(
+        next_header,
+        HOP_BY_HOP
+            | ROUTING
+            | FRAGMENT
+            | AUTHENTICATION
+            | DESTINATION_OPTIONS
+            | MOBILITY
+            | HOST_IDENTITY
+            | SHIM6
+            | EXPERIMENTAL_1
+            | EXPERIMENTAL_2
+    )
 }
 
 impl Debug for Ipv6Packet<'_> {
@@ -371,6 +441,69 @@ mod tests {
         assert!(packet.payload().is_empty());
     }
 
+    #[test]
+    fn test_upper_layer_no_extension_headers() {
+        let mut buf = [0_u8; Ipv6Packet::minimum_packet_size() + 8];
+        let mut packet = Ipv6Packet::new(&mut buf).unwrap();
+        packet.set_payload_length(8);
+        packet.set_next_header(IpProtocol::Udp);
+        packet.set_payload(&[0x81, 0x05, 0x82, 0x9a, 0x00, 0x08, 0x00, 0x00]);
+        assert_eq!(IpProtocol::Udp, packet.get_upper_layer_protocol());
+        assert_eq!(packet.payload(), packet.upper_layer_payload());
+    }
+
+    #[test]
+    fn test_upper_layer_extension_headers() {
+        let mut buf = [0_u8; Ipv6Packet::minimum_packet_size() + 48];
+        let mut packet = Ipv6Packet::new(&mut buf).unwrap();
+        packet.set_payload_length(48);
+        packet.set_next_header(IpProtocol::new(0));
+        // Hop-by-Hop (next=Routing, len=0 => 8 octets)
+        let hop_by_hop = [0x2b, 0x00, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00];
+        // Routing (next=Fragment, len=1 => 16 octets)
+        let routing = [
+            0x2c, 0x01, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        // Fragment (next=Experimental, fixed 8 octets)
+        let fragment = [0xfd, 0x00, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78];
+        // Experimental (next=UDP, len=0 => 8 octets)
+        let experimental = [0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let udp = [0x81, 0x05, 0x82, 0x9a, 0x00, 0x08, 0x00, 0x00];
+        packet.set_payload(&[&hop_by_hop[..], &routing, &fragment, &experimental, &udp].concat());
+        assert_eq!(IpProtocol::Udp, packet.get_upper_layer_protocol());
+        assert_eq!(udp, packet.upper_layer_payload());
+    }
+
+    #[test]
+    fn test_upper_layer_authentication_header() {
+        let mut buf = [0_u8; Ipv6Packet::minimum_packet_size() + 20];
+        let mut packet = Ipv6Packet::new(&mut buf).unwrap();
+        packet.set_payload_length(20);
+        packet.set_next_header(IpProtocol::new(51));
+        // Authentication (next=TCP, len=1 => 12 octets)
+        let authentication = [
+            0x06, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+        ];
+        // TCP (truncated)
+        let tcp = [0x81, 0x05, 0x00, 0x50, 0x00, 0x00, 0x00, 0x01];
+        packet.set_payload(&[&authentication[..], &tcp].concat());
+        assert_eq!(IpProtocol::Tcp, packet.get_upper_layer_protocol());
+        assert_eq!(tcp, packet.upper_layer_payload());
+    }
+
+    #[test]
+    fn test_upper_layer_truncated_extension_headers() {
+        let mut buf = [0_u8; Ipv6Packet::minimum_packet_size() + 8];
+        let mut packet = Ipv6Packet::new(&mut buf).unwrap();
+        packet.set_payload_length(8);
+        packet.set_next_header(IpProtocol::new(0));
+        // Hop-by-Hop (next=Destination Options, len=1 => 16 octets) truncated after 8 octets
+        packet.set_payload(&[0x3c, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(IpProtocol::Other(60), packet.get_upper_layer_protocol());
+        assert!(packet.upper_layer_payload().is_empty());
+    }
+
     #[test]
     fn test_new_insufficient_buffer() {
         const SIZE: usize = Ipv6Packet::minimum_packet_size();
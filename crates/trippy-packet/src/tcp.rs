@@ -14,6 +14,22 @@ const WINDOW_SIZE_OFFSET: usize = 14;
 const CHECKSUM_OFFSET: usize = 16;
 const URGENT_POINTER_OFFSET: usize = 18;
 
+/// The TCP control flags.
+pub mod flags {
+    /// No more data from sender.
+    pub const FIN: u16 = 0x001;
+    /// Synchronize sequence numbers.
+    pub const SYN: u16 = 0x002;
+    /// Reset the connection.
+    pub const RST: u16 = 0x004;
+    /// Push function.
+    pub const PSH: u16 = 0x008;
+    /// Acknowledgment field significant.
+    pub const ACK: u16 = 0x010;
+    /// Urgent pointer field significant.
+    pub const URG: u16 = 0x020;
+}
+
 /// Represents an TCP Packet.
 ///
 /// The internal representation is held in network byte order (big-endian) and all accessor methods
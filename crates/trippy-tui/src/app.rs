@@ -1,16 +1,33 @@
-use crate::config::{LogFormat, LogSpanEvents, Mode, TrippyConfig};
+use crate::config::{self, LogFormat, LogSpanEvents, Mode, TrippyConfig};
+use crate::diagnostics::Diagnostics;
 use crate::frontend::TuiConfig;
-use crate::geoip::GeoIpLookup;
+use crate::geoip::{GeoIpLookup, GeoIpSchema};
 use crate::locale;
-use crate::{frontend, report};
-use anyhow::{Error, anyhow};
+use crate::report::classic::ClassicFormatter;
+use crate::report::csv::CsvFormatter;
+use crate::report::dot::DotFormatter;
+use crate::report::flows::FlowsFormatter;
+use crate::report::formatter::{Formatters, ReportFormatter};
+use crate::report::json::JsonFormatter;
+use crate::report::redact::Redaction;
+use crate::report::sparkline::{Charset, Sparkline};
+use crate::report::table::{TableFormatter, TableOptions};
+use crate::report::{MinSamples, ReportOptions};
+use crate::{concurrency, frontend, gateway, report, retry};
+use anyhow::anyhow;
+use comfy_table::presets::{ASCII_MARKDOWN, UTF8_FULL};
+use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
 use tracing::instrument;
 use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use trippy_core::{Builder, Tracer};
+use trippy_core::{Action, Builder, Tracer};
 use trippy_dns::{DnsResolver, Resolver};
 use trippy_privilege::Privilege;
 
@@ -21,58 +38,190 @@ use rust_embed::RustEmbed;
 struct Assets;
 
 /// Run the trippy application.
-pub fn run_trippy(cfg: &TrippyConfig, pid: u16) -> anyhow::Result<()> {
+///
+/// The report formatters of `formatters` may be selected, in addition to the built-in formatters,
+/// in format mode.
+pub fn run_trippy(cfg: &TrippyConfig, pid: u16, formatters: &Formatters) -> anyhow::Result<()> {
+    if let Some(format) = cfg.format.as_deref() {
+        formatters.validate(format)?;
+    }
     let locale = locale::set_locale(cfg.tui_locale.as_deref());
     let _guard = configure_logging(cfg);
     tracing::debug!(?cfg);
     let resolver = start_dns_resolver(cfg)?;
-    let geoip_lookup = create_geoip_lookup(cfg, &locale)?;
-    let addrs = resolve_targets(cfg, &resolver)?;
+    retry::retry(cfg.retry, cfg.retry_delay, |attempt| {
+        // The privileges dropped by a failed attempt must be reacquired to restart the tracers.
+        if attempt > 0 {
+            Privilege::acquire_privileges()?;
+        }
+        run_attempt(cfg, &locale, &resolver, pid, formatters)
+    })
+}
+
+/// Run a single attempt of the trace.
+///
+/// Once the frontend completes the tracers started by this attempt are stopped after their
+/// current round and joined, such that no tracer outlives the attempt.  The samples of the
+/// completed traces are then written to the `parquet` file, if any.
+fn run_attempt(
+    cfg: &TrippyConfig,
+    locale: &str,
+    resolver: &DnsResolver,
+    pid: u16,
+    formatters: &Formatters,
+) -> anyhow::Result<()> {
+    let geoip_lookup = create_geoip_lookup(cfg, locale)?;
+    let addrs = resolve_targets(cfg, resolver)?;
     if addrs.is_empty() {
-        return Err(anyhow!(
+        return Err(ResolveError(format!(
             "failed to find any valid IP addresses for {} for address family {}",
             cfg.targets.join(", "),
             cfg.addr_family,
-        ));
+        ))
+        .into());
+    }
+    config::validate_target_concurrency(cfg.max_rounds, cfg.target_concurrency, addrs.len())?;
+    let stopped = Arc::new(AtomicBool::new(false));
+    let (traces, handles) = start_tracers(cfg, &addrs, pid, &stopped)?;
+    #[cfg(feature = "parquet")]
+    let parquet = cfg
+        .parquet
+        .as_deref()
+        .map(|path| (Path::new(path), traces.clone()));
+    let result = Privilege::drop_privileges()
+        .map_err(anyhow::Error::from)
+        .and_then(|()| {
+            run_frontend(
+                cfg,
+                locale,
+                resolver.clone(),
+                geoip_lookup,
+                formatters,
+                traces,
+            )
+        });
+    stopped.store(true, Ordering::Relaxed);
+    join_tracers(handles)?;
+    result?;
+    #[cfg(feature = "parquet")]
+    if let Some((path, traces)) = parquet {
+        report::parquet::write_file(path, &traces)?;
     }
-    let traces = start_tracers(cfg, &addrs, pid)?;
-    Privilege::drop_privileges()?;
-    run_frontend(cfg, &locale, resolver, geoip_lookup, traces)
+    Ok(())
 }
 
-/// Start all tracers.
-#[instrument(skip(cfg), level = "trace")]
+/// Start all tracers, returning the trace info and the handle of the thread of each tracer.
+///
+/// No more than `target-concurrency` tracers run at once, any further tracers start as others
+/// complete.  The tracers stop after their current round once `stopped` is set.
+#[instrument(skip(cfg, stopped), level = "trace")]
 fn start_tracers(
     cfg: &TrippyConfig,
     addrs: &[TargetInfo],
     pid: u16,
-) -> anyhow::Result<Vec<TraceInfo>> {
-    addrs
+    stopped: &Arc<AtomicBool>,
+) -> anyhow::Result<(Vec<TraceInfo>, Vec<TracerHandle>)> {
+    let tracers = addrs
         .iter()
         .enumerate()
-        .map(|(i, TargetInfo { hostname, addr })| {
-            start_tracer(cfg, hostname, *addr, pid + i as u16)
+        .map(|(i, TargetInfo { addr, .. })| {
+            let write_pcap = cfg
+                .write_pcap
+                .as_deref()
+                .map(|path| pcap_path(path, i, addrs.len()));
+            build_tracer(cfg, *addr, pid + i as u16, write_pcap)
         })
-        .collect::<anyhow::Result<Vec<_>>>()
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let stopped = stopped.clone();
+    let handles =
+        concurrency::spawn_bounded(cfg.target_concurrency, tracers.clone(), move |tracer| {
+            // Tracers waiting to start after the attempt was stopped are not run at all.
+            if stopped.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            let stopped = stopped.clone();
+            tracer.run_with(move |_| {
+                if stopped.load(Ordering::Relaxed) {
+                    Action::Stop
+                } else {
+                    Action::Continue
+                }
+            })
+        })?;
+    Ok((
+        tracers
+            .into_iter()
+            .zip(addrs)
+            .map(|(tracer, TargetInfo { hostname, .. })| make_trace_info(tracer, hostname.clone()))
+            .collect(),
+        handles,
+    ))
 }
 
-/// Start a tracer to a given target.
+/// The handle of the thread of a tracer.
+type TracerHandle = JoinHandle<Result<(), trippy_core::Error>>;
+
+/// Wait for the thread of each tracer to complete.
+///
+/// The error of a failed tracer has already been recorded in the state of its trace, and reported
+/// by the frontend, and so is only logged here.
+fn join_tracers(handles: Vec<TracerHandle>) -> anyhow::Result<()> {
+    for handle in handles {
+        let name = handle.thread().name().unwrap_or_default().to_string();
+        match handle.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::debug!(name, ?err, "tracer failed"),
+            Err(_) => return Err(anyhow!("tracer thread {name} panicked")),
+        }
+    }
+    Ok(())
+}
+
+/// The pcap file to write the probes of the `index` of `count` targets to.
+///
+/// When tracing multiple targets each tracer writes to its own file, named by inserting the
+/// (1-based) target index before the file extension of `path`, such as `trace-2.pcap`.
+fn pcap_path(path: &str, index: usize, count: usize) -> PathBuf {
+    let path = Path::new(path);
+    if count <= 1 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{stem}-{}.{}", index + 1, ext.to_string_lossy()),
+        None => format!("{stem}-{}", index + 1),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Build a tracer to a given target.
 #[instrument(skip(cfg), level = "trace")]
-fn start_tracer(
+fn build_tracer(
     cfg: &TrippyConfig,
-    target_host: &str,
     target_addr: IpAddr,
     trace_identifier: u16,
-) -> Result<TraceInfo, Error> {
-    let (tracer, _) = Builder::new(target_addr)
+    write_pcap: Option<PathBuf>,
+) -> anyhow::Result<Tracer> {
+    let tracer = Builder::new(target_addr)
         .interface(cfg.interface.clone())
+        .write_pcap(write_pcap)
         .source_addr(cfg.source_addr)
         .privilege_mode(cfg.privilege_mode)
         .protocol(cfg.protocol)
         .packet_size(cfg.packet_size)
-        .payload_pattern(cfg.payload_pattern)
+        .payload_pattern_mode(cfg.payload_pattern)
         .tos(cfg.tos)
         .icmp_extension_parse_mode(cfg.icmp_extension_parse_mode)
+        .icmp_identifier_match_mode(cfg.icmp_identifier_match_mode)
+        .icmp_source_match_mode(cfg.icmp_source_match_mode)
+        .icmp_quoted_payload_mode(cfg.icmp_quoted_payload_mode)
+        .fwmark(cfg.fwmark)
+        .icmp_nonce_key(cfg.icmp_nonce_key)
+        .icmp_identifier_range(cfg.icmp_identifier_range)
+        .icmp_payload_len(cfg.icmp_payload_len)
+        .icmp_echo_code(cfg.icmp_echo_code)
+        .icmp_bind_attempts(cfg.icmp_bind_attempts)
+        .tcp_flags(cfg.tcp_flags)
         .read_timeout(cfg.read_timeout)
         .tcp_connect_timeout(cfg.min_round_duration)
         .trace_identifier(trace_identifier)
@@ -80,18 +229,30 @@ fn start_tracer(
         .first_ttl(cfg.first_ttl)
         .max_ttl(cfg.max_ttl)
         .grace_duration(cfg.grace_duration)
+        .target_grace_duration(cfg.target_grace_duration)
+        .continue_past_target(cfg.continue_past_target)
+        .unreachable_mode(cfg.unreachable_mode)
+        .icmp_unreachable_reached(cfg.icmp_unreachable_reached)
+        .multi_response_mode(cfg.multi_response_mode)
+        .hop_addr_filter(cfg.hop_addr_filter.clone())
         .max_inflight(cfg.max_inflight)
+        .max_packets(cfg.max_packets)
+        .max_silent_rounds(cfg.max_silent_rounds)
+        .send_interval(cfg.send_interval)
         .initial_sequence(cfg.initial_sequence)
         .multipath_strategy(cfg.multipath_strategy)
         .port_direction(cfg.port_direction)
+        .src_port_seed(cfg.src_port_seed)
         .min_round_duration(cfg.min_round_duration)
         .max_round_duration(cfg.max_round_duration)
         .max_flows(cfg.max_flows())
+        .negative_rtt_mode(cfg.negative_rtt_mode)
+        .window(cfg.window)
+        .max_addrs(cfg.max_addrs)
         .max_samples(cfg.max_samples)
         .drop_privileges(true)
-        .build()?
-        .spawn()?;
-    Ok(make_trace_info(tracer, target_host.to_string()))
+        .build()?;
+    Ok(tracer)
 }
 
 /// Run the TUI, stream or report.
@@ -101,8 +262,33 @@ fn run_frontend(
     locale: &str,
     resolver: DnsResolver,
     geoip_lookup: GeoIpLookup,
+    formatters: &Formatters,
     traces: Vec<TraceInfo>,
 ) -> anyhow::Result<()> {
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = args.otlp_endpoint.as_ref() {
+        report::otlp::spawn(
+            endpoint,
+            traces.clone(),
+            resolver.clone(),
+            args.report_addr_tie_break,
+        )?;
+    }
+    if let Some(addr) = args.map_server {
+        let geoip_mmdb_file = args.geoip_mmdb_file.clone();
+        let geoip_mmdb_schema_file = args.geoip_mmdb_schema_file.clone();
+        let locale = locale.to_string();
+        report::map::spawn(addr, traces[0].clone(), args.report_addr_tie_break, move || {
+            load_geoip_lookup(
+                geoip_mmdb_file.as_ref(),
+                geoip_mmdb_schema_file.as_ref(),
+                &locale,
+            )
+        })?;
+    }
+    let mut stdout = std::io::stdout();
+    let diagnostics = Diagnostics::stderr(args.quiet);
+    let options = make_report_options(args);
     match args.mode {
         Mode::Tui => frontend::run_frontend(
             traces,
@@ -110,22 +296,130 @@ fn run_frontend(
             resolver,
             geoip_lookup,
         )?,
-        Mode::Stream => report::stream::report(&traces[0], &resolver)?,
-        Mode::Csv => report::csv::report(&traces[0], args.report_cycles, &resolver)?,
-        Mode::Json => report::json::report(&traces[0], args.report_cycles, &resolver)?,
-        Mode::Pretty => {
-            report::table::report_pretty(&traces[0], args.report_cycles, &resolver, &geoip_lookup)?
-        }
-        Mode::Markdown => {
-            report::table::report_md(&traces[0], args.report_cycles, &resolver, &geoip_lookup)?
+        Mode::Stream => report::stream::report(&mut stdout, &traces[0], &options, &resolver)?,
+        Mode::Influx => report::influx::report(&mut stdout, &traces[0], &options)?,
+        Mode::Pretty
+        | Mode::Markdown
+        | Mode::Csv
+        | Mode::Json
+        | Mode::Classic
+        | Mode::Dot
+        | Mode::Flows
+        | Mode::Format => {
+            let name = match args.mode {
+                Mode::Pretty => "pretty",
+                Mode::Markdown => "markdown",
+                Mode::Csv => "csv",
+                Mode::Json => "json",
+                Mode::Classic => "classic",
+                Mode::Dot => "dot",
+                Mode::Flows => "flows",
+                _ => args
+                    .format
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("format mode requires a --format name"))?,
+            };
+            let table_options = make_table_options(args, &traces[0], name == "markdown");
+            let builtin: Option<Box<dyn ReportFormatter + '_>> = match name {
+                "pretty" | "markdown" => Some(Box::new(TableFormatter::new(
+                    &options,
+                    &table_options,
+                    &resolver,
+                    &geoip_lookup,
+                    &diagnostics,
+                ))),
+                "csv" => Some(Box::new(CsvFormatter::new(&resolver, &options))),
+                "json" => Some(Box::new(JsonFormatter::new(&resolver, &options))),
+                "classic" => Some(Box::new(ClassicFormatter::new(
+                    args.report_queries,
+                    &resolver,
+                    &options,
+                ))),
+                "dot" => Some(Box::new(DotFormatter::new(options.timestamps))),
+                "flows" => Some(Box::new(FlowsFormatter::new(options.timestamps))),
+                _ => None,
+            };
+            let formatter = builtin
+                .as_deref()
+                .or_else(|| formatters.get(name))
+                .ok_or_else(|| anyhow!("unknown report format {name}"))?;
+            report::formatter::report(&mut stdout, &traces[0], &options, formatter)?;
         }
-        Mode::Dot => report::dot::report(&traces[0], args.report_cycles)?,
-        Mode::Flows => report::flows::report(&traces[0], args.report_cycles)?,
-        Mode::Silent => report::silent::report(&traces[0], args.report_cycles)?,
+        Mode::Silent => report::silent::report(&traces[0], &options)?,
+        Mode::LogTable => report::log_table::report(
+            &traces[0],
+            &options,
+            &make_table_options(args, &traces[0], false),
+            Path::new(
+                args.log_table
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("log-table mode requires a --log-table file"))?,
+            ),
+            &resolver,
+            &geoip_lookup,
+        )?,
     }
     Ok(())
 }
 
+/// Make the options common to all report modes.
+fn make_report_options(args: &TrippyConfig) -> ReportOptions {
+    ReportOptions {
+        report_cycles: args.report_cycles,
+        fail_fast: args.report_fail_fast,
+        min_samples: args
+            .min_samples
+            .map(|samples| MinSamples::new(samples, args.min_samples_timeout)),
+        timestamps: args.report_timestamps,
+        response_dscp: args.report_response_dscp,
+        time_to_first: args.report_time_to_first,
+        config: args.report_config,
+        redaction: Redaction::new(&args.report_redact_domains, args.report_redact_max_ttl)
+            .with_strip_suffixes(&args.report_strip_suffixes)
+            .with_max_hostname_len(args.report_max_hostname_len),
+    }
+}
+
+/// Make the options of table reports of `info`, rendered as Markdown if `markdown` is set.
+fn make_table_options(args: &TrippyConfig, info: &TraceInfo, markdown: bool) -> TableOptions {
+    let charset = if args.ascii {
+        Charset::Ascii
+    } else {
+        Charset::Unicode
+    };
+    TableOptions {
+        preset: if markdown { ASCII_MARKDOWN } else { UTF8_FULL },
+        rate_limit_warnings: args.rate_limit_warnings,
+        destination_rtt: args.report_destination_rtt,
+        mos: args.report_mos,
+        cv_threshold: args.report_cv_threshold.map(|pct| f64::from(pct) / 100_f64),
+        column_widths: args.report_column_widths.clone(),
+        sparkline: args
+            .report_sparkline
+            .map(|samples| Sparkline::new(samples, charset)),
+        first_ttl: args.first_ttl,
+        unprobed_hops: args.report_unprobed_hops,
+        gateway: if args.report_gateway {
+            gateway::default_gateway(info.data.target_addr())
+        } else {
+            None
+        },
+        addr_tie_break: args.report_addr_tie_break,
+    }
+}
+
+/// The error raised when the targets could not be resolved.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResolveError(pub String);
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
 /// Resolve targets.
 #[instrument(skip_all, level = "trace")]
 fn resolve_targets(cfg: &TrippyConfig, resolver: &DnsResolver) -> anyhow::Result<Vec<TargetInfo>> {
@@ -146,7 +440,10 @@ fn resolve_targets(cfg: &TrippyConfig, resolver: &DnsResolver) -> anyhow::Result
                 })
                 .collect::<Vec<_>>()
                 .into_iter(),
-            Err(e) => vec![Err(anyhow!("failed to resolve target: {target} ({e})"))].into_iter(),
+            Err(e) => vec![Err(anyhow::Error::from(ResolveError(format!(
+                "failed to resolve target: {target} ({e})"
+            ))))]
+            .into_iter(),
         })
         .collect::<anyhow::Result<Vec<_>>>()
 }
@@ -154,26 +451,54 @@ fn resolve_targets(cfg: &TrippyConfig, resolver: &DnsResolver) -> anyhow::Result
 /// Start the DNS resolver.
 #[instrument(skip_all, level = "trace")]
 fn start_dns_resolver(cfg: &TrippyConfig) -> anyhow::Result<DnsResolver> {
-    Ok(DnsResolver::start(trippy_dns::Config::new(
-        cfg.dns_resolve_method,
-        cfg.addr_family,
-        cfg.dns_timeout,
-        cfg.dns_ttl,
-    ))?)
+    Ok(DnsResolver::start(
+        trippy_dns::Builder::new()
+            .resolve_method(cfg.dns_resolve_method)
+            .addr_family(cfg.addr_family)
+            .timeout(cfg.dns_timeout)
+            .ttl(cfg.dns_ttl)
+            .server(cfg.dns_server)
+            .doh(cfg.doh.clone())
+            .build(),
+    )?)
 }
 
 #[instrument(skip_all, level = "trace")]
 fn create_geoip_lookup(cfg: &TrippyConfig, locale: &str) -> anyhow::Result<GeoIpLookup> {
-	if let Some(v4_asset) = Assets::get("ip2region_v4.xdb") {
-		if let Some(v6_asset) = Assets::get("ip2region_v6.xdb") {
-			GeoIpLookup::load_xdb(v4_asset.data.into(), v6_asset.data.into(), String::from(locale))
-		} else {
-			Ok(GeoIpLookup::empty())
-		}
-    } else if let Some(path) = cfg.geoip_mmdb_file.as_ref() {
-        GeoIpLookup::from_file(path, String::from(locale))
-    } else {
-        Ok(GeoIpLookup::empty())
+    load_geoip_lookup(
+        cfg.geoip_mmdb_file.as_ref(),
+        cfg.geoip_mmdb_schema_file.as_ref(),
+        locale,
+    )
+}
+
+/// Load the embedded `xdb` databases if present and the `geoip_mmdb_file`, if any.
+///
+/// If both are present then the `geoip_mmdb_file` is used for addresses without an `xdb` entry.
+fn load_geoip_lookup(
+    geoip_mmdb_file: Option<&String>,
+    geoip_mmdb_schema_file: Option<&String>,
+    locale: &str,
+) -> anyhow::Result<GeoIpLookup> {
+    let xdb = Assets::get("ip2region_v4.xdb").zip(Assets::get("ip2region_v6.xdb"));
+    let geoip_lookup = match (xdb, geoip_mmdb_file) {
+        (Some((v4_asset, v6_asset)), mmdb_file) => {
+            let geoip_lookup = GeoIpLookup::load_xdb(
+                v4_asset.data.into(),
+                v6_asset.data.into(),
+                String::from(locale),
+            )?;
+            match mmdb_file {
+                Some(path) => geoip_lookup.with_mmdb_file(path)?,
+                None => geoip_lookup,
+            }
+        }
+        (None, Some(path)) => GeoIpLookup::from_file(path, String::from(locale))?,
+        (None, None) => return Ok(GeoIpLookup::empty()),
+    };
+    match geoip_mmdb_schema_file {
+        Some(schema_path) => Ok(geoip_lookup.with_schema(GeoIpSchema::from_file(schema_path)?)),
+        None => Ok(geoip_lookup),
     }
 }
 
@@ -235,6 +560,7 @@ fn make_tui_config(args: &TrippyConfig, locale: String) -> TuiConfig {
         &args.tui_bindings,
         &args.tui_custom_columns,
         args.geoip_mmdb_file.clone(),
+        args.geoip_mmdb_schema_file.clone(),
         args.dns_resolve_all,
         locale,
         args.tui_timezone,
@@ -269,3 +595,17 @@ struct TargetInfo {
     pub hostname: String,
     pub addr: IpAddr,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("trace.pcap", 0, 1, "trace.pcap"; "single target")]
+    #[test_case("trace.pcap", 0, 2, "trace-1.pcap"; "first of multiple targets")]
+    #[test_case("trace.pcap", 1, 2, "trace-2.pcap"; "second of multiple targets")]
+    #[test_case("dir/trace", 2, 3, "dir/trace-3"; "no extension")]
+    fn test_pcap_path(path: &str, index: usize, count: usize, expected: &str) {
+        assert_eq!(PathBuf::from(expected), pcap_path(path, index, count));
+    }
+}
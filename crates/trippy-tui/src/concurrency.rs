@@ -0,0 +1,127 @@
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+
+/// A counting semaphore which bounds the number of permits held at once.
+#[derive(Debug)]
+struct Semaphore {
+    permits: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+    const fn new(permits: usize) -> Self {
+        Self {
+            permits: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Acquire a permit, blocking until one is available.
+    ///
+    /// The permit is released when the returned `Permit` is dropped.
+    fn acquire(&self) -> Permit<'_> {
+        *self
+            .released
+            .wait_while(
+                self.permits.lock().unwrap_or_else(PoisonError::into_inner),
+                |permits| *permits == 0,
+            )
+            .unwrap_or_else(PoisonError::into_inner) -= 1;
+        Permit { semaphore: self }
+    }
+
+    fn release(&self) {
+        *self.permits.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        self.released.notify_one();
+    }
+}
+
+/// A permit held from a `Semaphore`.
+struct Permit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.release();
+    }
+}
+
+/// Spawn a thread to `run` each of `sessions`, such that no more than `limit` run at once.
+///
+/// A `limit` of zero does not bound the number of sessions which run at once.  Sessions beyond the
+/// limit wait for a running session to complete before they start.
+pub fn spawn_bounded<T, F, R>(
+    limit: usize,
+    sessions: Vec<T>,
+    run: F,
+) -> anyhow::Result<Vec<JoinHandle<R>>>
+where
+    T: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    let semaphore = (limit > 0).then(|| Arc::new(Semaphore::new(limit)));
+    let run = Arc::new(run);
+    sessions
+        .into_iter()
+        .enumerate()
+        .map(|(i, session)| {
+            let semaphore = semaphore.clone();
+            let run = run.clone();
+            let handle = thread::Builder::new()
+                .name(format!("session-{i}"))
+                .spawn(move || {
+                    let _permit = semaphore.as_deref().map(Semaphore::acquire);
+                    run(session)
+                })?;
+            Ok(handle)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Run `sessions` with `limit`, returning the number run and the most run at once.
+    fn run_sessions(limit: usize, sessions: usize) -> (usize, usize) {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let completed = Arc::new(AtomicUsize::new(0));
+        let handles = {
+            let (peak, completed) = (peak.clone(), completed.clone());
+            spawn_bounded(limit, (0..sessions).collect(), move |_: usize| {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                active.fetch_sub(1, Ordering::SeqCst);
+                completed.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        (
+            completed.load(Ordering::SeqCst),
+            peak.load(Ordering::SeqCst),
+        )
+    }
+
+    #[test]
+    fn test_spawn_bounded() {
+        let (completed, peak) = run_sessions(2, 3);
+        assert_eq!(3, completed);
+        assert!(peak <= 2, "{peak} sessions ran at once");
+    }
+
+    #[test]
+    fn test_spawn_unbounded() {
+        let (completed, peak) = run_sessions(0, 3);
+        assert_eq!(3, completed);
+        assert!(peak <= 3);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::config::constants::MAX_CIDR_HOSTS;
+use anyhow::anyhow;
+use std::net::{IpAddr, Ipv6Addr};
+
+/// Expand any targets given as a CIDR range, such as `192.0.2.0/28`, into their usable hosts.
+///
+/// Targets which are not a CIDR range are returned unchanged.
+pub fn expand_targets(targets: &[String]) -> anyhow::Result<Vec<String>> {
+    targets.iter().try_fold(vec![], |mut expanded, target| {
+        match parse_cidr(target)? {
+            Some((addr, prefix)) => expanded.extend(hosts(target, addr, prefix)?),
+            None => expanded.push(target.clone()),
+        }
+        Ok(expanded)
+    })
+}
+
+/// Parse a CIDR range, returning `None` if `target` is not of the form `addr/prefix`.
+fn parse_cidr(target: &str) -> anyhow::Result<Option<(IpAddr, u8)>> {
+    let Some((addr, prefix)) = target.split_once('/') else {
+        return Ok(None);
+    };
+    let addr = addr
+        .parse::<IpAddr>()
+        .map_err(|_| anyhow!("invalid CIDR range {target}: invalid address"))?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    match prefix.parse::<u8>() {
+        Ok(prefix) if prefix <= max_prefix => Ok(Some((addr, prefix))),
+        _ => Err(anyhow!(
+            "invalid CIDR range {target}: prefix must be in the range 0..={max_prefix}"
+        )),
+    }
+}
+
+/// The usable hosts of the CIDR range `addr/prefix`.
+///
+/// For IPv4 the network and broadcast addresses are excluded, except for `/31` and `/32`
+/// ranges.  For IPv6 the subnet-router anycast address is excluded, except for `/127` and
+/// `/128` ranges.
+///
+/// IPv4 ranges are expanded as IPv4-mapped IPv6 ranges.
+fn hosts(target: &str, addr: IpAddr, prefix: u8) -> anyhow::Result<Vec<String>> {
+    let (value, host_bits) = match addr {
+        IpAddr::V4(addr) => (addr.to_ipv6_mapped().to_bits(), 32 - u32::from(prefix)),
+        IpAddr::V6(addr) => (addr.to_bits(), 128 - u32::from(prefix)),
+    };
+    let network = value & u128::MAX.checked_shl(host_bits).unwrap_or(0);
+    let (first, count) = match 1_u128.checked_shl(host_bits) {
+        Some(size) if host_bits <= 1 => (network, size),
+        Some(size) if addr.is_ipv4() => (network + 1, size - 2),
+        Some(size) => (network + 1, size - 1),
+        None => (network + 1, u128::MAX),
+    };
+    if count > MAX_CIDR_HOSTS as u128 {
+        return Err(anyhow!(
+            "CIDR range {target} exceeds the maximum of {MAX_CIDR_HOSTS} hosts"
+        ));
+    }
+    Ok((first..first + count)
+        .map(|host| {
+            let host = Ipv6Addr::from_bits(host);
+            match host.to_ipv4_mapped() {
+                Some(host) if addr.is_ipv4() => host.to_string(),
+                _ => host.to_string(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("192.0.2.0/30", &["192.0.2.1", "192.0.2.2"]; "ipv4 /30")]
+    #[test_case("192.0.2.3/30", &["192.0.2.1", "192.0.2.2"]; "ipv4 /30 host bits set")]
+    #[test_case("192.0.2.0/31", &["192.0.2.0", "192.0.2.1"]; "ipv4 /31")]
+    #[test_case("192.0.2.7/32", &["192.0.2.7"]; "ipv4 /32")]
+    #[test_case("2001:db8::/126", &["2001:db8::1", "2001:db8::2", "2001:db8::3"]; "ipv6 /126")]
+    #[test_case("2001:db8::/127", &["2001:db8::", "2001:db8::1"]; "ipv6 /127")]
+    #[test_case("example.com", &["example.com"]; "hostname")]
+    #[test_case("192.0.2.1", &["192.0.2.1"]; "address")]
+    fn test_expand_targets(target: &str, expected: &[&str]) {
+        let expanded = expand_targets(&[String::from(target)]).unwrap();
+        assert_eq!(expected, expanded.as_slice());
+    }
+
+    #[test]
+    fn test_expand_targets_mixed() {
+        let targets = [String::from("example.com"), String::from("192.0.2.0/30")];
+        let expanded = expand_targets(&targets).unwrap();
+        assert_eq!(
+            ["example.com", "192.0.2.1", "192.0.2.2"],
+            expanded.as_slice()
+        );
+    }
+
+    #[test_case("192.0.2.0/23", "CIDR range 192.0.2.0/23 exceeds the maximum of 256 hosts"; "too many hosts")]
+    #[test_case("0.0.0.0/0", "CIDR range 0.0.0.0/0 exceeds the maximum of 256 hosts"; "all ipv4")]
+    #[test_case("::/0", "CIDR range ::/0 exceeds the maximum of 256 hosts"; "all ipv6")]
+    #[test_case("192.0.2.0/33", "invalid CIDR range 192.0.2.0/33: prefix must be in the range 0..=32"; "invalid ipv4 prefix")]
+    #[test_case("2001:db8::/abc", "invalid CIDR range 2001:db8::/abc: prefix must be in the range 0..=128"; "invalid ipv6 prefix")]
+    #[test_case("example.com/24", "invalid CIDR range example.com/24: invalid address"; "invalid address")]
+    fn test_expand_targets_invalid(target: &str, expected: &str) {
+        let err = expand_targets(&[String::from(target)]).unwrap_err();
+        assert_eq!(expected, err.to_string());
+    }
+}
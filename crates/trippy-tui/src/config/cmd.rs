@@ -1,9 +1,11 @@
 use crate::config::binding::TuiCommandItem;
 use crate::config::theme::TuiThemeItem;
 use crate::config::{
-    AddressFamilyConfig, AddressMode, AsMode, DnsResolveMethodConfig, GeoIpMode, IcmpExtensionMode,
-    LogFormat, LogSpanEvents, Mode, MultipathStrategyConfig, ProtocolConfig, TuiColor,
-    TuiKeyBinding,
+    AddrTieBreakConfig, AddressFamilyConfig, AddressMode, AsMode, DnsResolveMethodConfig,
+    GeoIpMode, IcmpExtensionMode, LogFormat, LogSpanEvents, Mode, MultiResponseModeConfig,
+    MultipathStrategyConfig, NegativeRttModeConfig, PayloadPatternModeConfig, ProtocolConfig,
+    ReportColumn, TcpFlagsConfig, TuiColor, TuiKeyBinding, UnprobedHopsConfig,
+    UnreachableModeConfig,
 };
 use anyhow::anyhow;
 use clap::Parser;
@@ -17,7 +19,7 @@ use std::time::Duration;
 #[derive(Parser, Debug)]
 #[command(name = "trip", author, version, about, long_about = None, arg_required_else_help(true), styles=clap_cargo::style::CLAP_STYLING)]
 pub struct Args {
-    /// A space delimited list of hostnames and IPs to trace
+    /// A space delimited list of hostnames, IPs and CIDR ranges to trace
     #[arg(required_unless_present_any(["print_tui_theme_items", "print_tui_binding_commands", "print_config_template", "generate", "generate_man", "print_locales"]), env = "TRIP_TARGETS")]
     pub targets: Vec<String>,
 
@@ -33,6 +35,18 @@ pub struct Args {
     #[arg(short = 'u', long, env = "TRIP_UNPRIVILEGED")]
     pub unprivileged: bool,
 
+    /// The number of times to retry the trace if it fails or no hops respond [default: 0]
+    #[arg(long, env = "TRIP_RETRY")]
+    pub retry: Option<usize>,
+
+    /// The delay before the first retry, doubled for each further retry [default: 1s]
+    #[arg(long, value_parser = parse_duration, env = "TRIP_RETRY_DELAY")]
+    pub retry_delay: Option<Duration>,
+
+    /// The maximum number of targets to trace at once, 0 for no limit [default: 0]
+    #[arg(long, env = "TRIP_TARGET_CONCURRENCY")]
+    pub target_concurrency: Option<usize>,
+
     /// Tracing protocol [default: icmp]
     #[arg(value_enum, short = 'p', long, env = "TRIP_PROTOCOL")]
     pub protocol: Option<ProtocolConfig>,
@@ -99,6 +113,10 @@ pub struct Args {
     #[arg(long, short = 'S', env = "TRIP_SOURCE_PORT")]
     pub source_port: Option<u16>,
 
+    /// The seed used to randomize the source port of each round (UDP paris & dublin only) [default: none]
+    #[arg(long, env = "TRIP_SOURCE_PORT_SEED")]
+    pub source_port_seed: Option<u64>,
+
     /// The source IP address [default: auto]
     #[arg(short = 'A', long, value_parser = parse_addr, conflicts_with = "interface", env = "TRIP_SOURCE_ADDRESS")]
     pub source_address: Option<IpAddr>,
@@ -107,6 +125,10 @@ pub struct Args {
     #[arg(short = 'I', long, env = "TRIP_INTERFACE")]
     pub interface: Option<String>,
 
+    /// Write the IPv4 probes sent to a pcap file (raw sockets only)
+    #[arg(long, value_hint = clap::ValueHint::FilePath, env = "TRIP_WRITE_PCAP")]
+    pub write_pcap: Option<String>,
+
     /// The minimum duration of every round [default: 1s]
     #[arg(short = 'i', long, value_parser = parse_duration, env = "TRIP_MIN_ROUND_DURATION")]
     pub min_round_duration: Option<Duration>,
@@ -120,6 +142,34 @@ pub struct Args {
     #[arg(short = 'g', long, value_parser = parse_duration, env = "TRIP_GRACE_DURATION")]
     pub grace_duration: Option<Duration>,
 
+    /// The grace duration to use if the latest response was from the target [default: 100ms]
+    #[arg(long, value_parser = parse_duration, env = "TRIP_TARGET_GRACE_DURATION")]
+    pub target_grace_duration: Option<Duration>,
+
+    /// Continue probing all TTLs up to max-ttl after the target has responded [default: false]
+    #[arg(long, env = "TRIP_CONTINUE_PAST_TARGET")]
+    pub continue_past_target: bool,
+
+    /// How to classify destination unreachable responses [default: address]
+    #[arg(value_enum, long, env = "TRIP_UNREACHABLE_MODE")]
+    pub unreachable_mode: Option<UnreachableModeConfig>,
+
+    /// Treat destination unreachable responses as reaching the target in icmp mode [default: false]
+    #[arg(long, env = "TRIP_ICMP_UNREACHABLE_REACHED")]
+    pub icmp_unreachable_reached: bool,
+
+    /// Which responses to attribute to a hop when multiple addresses respond to a probe [default: first]
+    #[arg(value_enum, long, env = "TRIP_MULTI_RESPONSE_MODE")]
+    pub multi_response_mode: Option<MultiResponseModeConfig>,
+
+    /// Probe only the hops with these addresses after the first round [default: all]
+    #[arg(long, value_delimiter(','), value_parser = parse_addr, conflicts_with = "hop_deny", env = "TRIP_HOP_ALLOW")]
+    pub hop_allow: Vec<IpAddr>,
+
+    /// Probe all hops except those with these addresses after the first round [default: none]
+    #[arg(long, value_delimiter(','), value_parser = parse_addr, env = "TRIP_HOP_DENY")]
+    pub hop_deny: Vec<IpAddr>,
+
     /// The initial sequence number [default: 33434]
     #[arg(long, env = "TRIP_INITIAL_SEQUENCE")]
     pub initial_sequence: Option<u16>,
@@ -132,6 +182,23 @@ pub struct Args {
     #[arg(short = 'U', long, env = "TRIP_MAX_INFLIGHT")]
     pub max_inflight: Option<u8>,
 
+    /// The maximum number of probes to send across all rounds [default: unlimited]
+    #[arg(long, env = "TRIP_MAX_PACKETS")]
+    pub max_packets: Option<usize>,
+
+    /// The number of consecutive rounds without a response from the target
+    /// after which the trace is finished [default: unlimited]
+    #[arg(long, env = "TRIP_MAX_SILENT_ROUNDS")]
+    pub max_silent_rounds: Option<usize>,
+
+    /// The minimum interval between sending consecutive probes [default: none]
+    #[arg(long, value_parser = parse_duration, conflicts_with = "rate", env = "TRIP_SEND_INTERVAL")]
+    pub send_interval: Option<Duration>,
+
+    /// The rate at which to send probes in packets per second [default: unlimited]
+    #[arg(long, env = "TRIP_RATE")]
+    pub rate: Option<u32>,
+
     /// The TTL to start from [default: 1]
     #[arg(short = 'f', long, env = "TRIP_FIRST_TTL")]
     pub first_ttl: Option<u8>,
@@ -148,7 +215,11 @@ pub struct Args {
     #[arg(long, env = "TRIP_PAYLOAD_PATTERN")]
     pub payload_pattern: Option<u8>,
 
-    /// The TOS (i.e. DSCP+ECN) IP header value (IPv4 only) [default: 0]
+    /// The pattern of the bytes in the payload of probes [default: fixed]
+    #[arg(value_enum, long, env = "TRIP_PAYLOAD_PATTERN_MODE")]
+    pub payload_pattern_mode: Option<PayloadPatternModeConfig>,
+
+    /// The TOS (i.e. DSCP+ECN) IPv4 header value or IPv6 traffic class [default: 0]
     #[arg(short = 'Q', long, env = "TRIP_TOS")]
     pub tos: Option<u8>,
 
@@ -156,6 +227,47 @@ pub struct Args {
     #[arg(short = 'e', long, env = "TRIP_ICMP_EXTENSIONS")]
     pub icmp_extensions: bool,
 
+    /// Match ICMP echo replies on sequence and source address only, ignoring the identifier
+    #[arg(long, env = "TRIP_ICMP_TOLERANT_IDENTIFIER")]
+    pub icmp_tolerant_identifier: bool,
+
+    /// Only accept ICMP echo replies which originate from the target address
+    #[arg(long, env = "TRIP_ICMP_STRICT_SOURCE")]
+    pub icmp_strict_source: bool,
+
+    /// Retain the original datagram quoted in ICMP error responses and show it in json reports
+    #[arg(long, env = "TRIP_ICMP_QUOTED_PAYLOAD")]
+    pub icmp_quoted_payload: bool,
+
+    /// The firewall mark (SO_MARK) to set on probe sockets (Linux only)
+    #[arg(long, env = "TRIP_FWMARK")]
+    pub fwmark: Option<u32>,
+
+    /// The key of the nonce to include in and verify on icmp echo payloads
+    #[arg(long, env = "TRIP_ICMP_NONCE_KEY")]
+    pub icmp_nonce_key: Option<u64>,
+
+    /// Probe for icmp identifier rewriting by sending echo requests with this many identifiers
+    #[arg(long, env = "TRIP_ICMP_IDENTIFIER_RANGE")]
+    pub icmp_identifier_range: Option<u16>,
+
+    /// The exact length of the payload of icmp probes, overriding the packet size (the packet
+    /// must not exceed 1024 bytes, a payload of at most 996 bytes for IPv4 or 976 for IPv6)
+    #[arg(long, env = "TRIP_ICMP_PAYLOAD_LEN")]
+    pub icmp_payload_len: Option<u16>,
+
+    /// The ICMP code of icmp echo request probes [default: 0]
+    #[arg(long, env = "TRIP_ICMP_ECHO_CODE")]
+    pub icmp_echo_code: Option<u8>,
+
+    /// The number of identifiers to try when binding an unprivileged icmp socket [default: 3]
+    #[arg(long, env = "TRIP_ICMP_BIND_ATTEMPTS")]
+    pub icmp_bind_attempts: Option<u8>,
+
+    /// The TCP flags to set on tcp probes [default: syn]
+    #[arg(value_enum, long, env = "TRIP_TCP_FLAGS")]
+    pub tcp_flags: Option<TcpFlagsConfig>,
+
     /// The socket read timeout [default: 10ms]
     #[arg(long, value_parser = parse_duration, env = "TRIP_READ_TIMEOUT")]
     pub read_timeout: Option<Duration>,
@@ -176,6 +288,14 @@ pub struct Args {
     #[arg(long, value_parser = parse_duration, env = "TRIP_DNS_TTL")]
     pub dns_ttl: Option<Duration>,
 
+    /// The DNS server to query directly, with an optional port [default: none]
+    #[arg(long, env = "TRIP_DNS_SERVER")]
+    pub dns_server: Option<String>,
+
+    /// The DNS-over-HTTPS (DoH) resolver URL to query [default: none]
+    #[arg(long, conflicts_with_all = ["dns_server", "dns_resolve_method"], env = "TRIP_DOH")]
+    pub doh: Option<String>,
+
     /// Lookup autonomous system (AS) information during DNS queries [default: false]
     #[arg(long, short = 'z', env = "TRIP_DNS_LOOKUP_AS_INFO")]
     pub dns_lookup_as_info: bool,
@@ -188,6 +308,18 @@ pub struct Args {
     #[arg(long, env = "TRIP_MAX_FLOWS")]
     pub max_flows: Option<usize>,
 
+    /// How to handle responses with a negative round trip time [default: clamp]
+    #[arg(value_enum, long, env = "TRIP_NEGATIVE_RTT_MODE")]
+    pub negative_rtt_mode: Option<NegativeRttModeConfig>,
+
+    /// The number of most recent rounds which contribute to the hop statistics [default: all]
+    #[arg(long, env = "TRIP_WINDOW")]
+    pub window: Option<usize>,
+
+    /// The maximum number of distinct addresses to record per hop [default: all]
+    #[arg(long, env = "TRIP_MAX_ADDRS")]
+    pub max_addrs: Option<usize>,
+
     /// How to render addresses [default: host]
     #[arg(value_enum, short = 'a', long, env = "TRIP_TUI_ADDRESS_MODE")]
     pub tui_address_mode: Option<AddressMode>,
@@ -256,10 +388,132 @@ pub struct Args {
     #[arg(short = 'C', long, env = "TRIP_REPORT_CYCLES")]
     pub report_cycles: Option<usize>,
 
+    /// Abort a report with exit code 3 if no hops have responded after this many rounds
+    /// [default: none]
+    #[arg(long, env = "TRIP_REPORT_FAIL_FAST")]
+    pub report_fail_fast: Option<usize>,
+
+    /// Extend a report until every responding hop has at least this many samples
+    /// [default: none]
+    #[arg(long, env = "TRIP_MIN_SAMPLES")]
+    pub min_samples: Option<usize>,
+
+    /// The maximum time to extend a report waiting for min-samples [default: 60s]
+    #[arg(long, value_parser = parse_duration, env = "TRIP_MIN_SAMPLES_TIMEOUT")]
+    pub min_samples_timeout: Option<Duration>,
+
+    /// The number of most recent round trip times to show per hop in classic mode [default: 3]
+    #[arg(long, env = "TRIP_REPORT_QUERIES")]
+    pub report_queries: Option<usize>,
+
+    /// The file to append a timestamped table of each round to in log-table mode
+    #[arg(long, value_hint = clap::ValueHint::FilePath, env = "TRIP_LOG_TABLE")]
+    pub log_table: Option<String>,
+
+    /// The name of the report formatter to use in format mode
+    #[arg(long, env = "TRIP_FORMAT")]
+    pub format: Option<String>,
+
+    /// Emit each completed round as OpenTelemetry spans to this OTLP/HTTP endpoint [e.g.
+    /// http://localhost:4318/v1/traces]
+    #[arg(long, value_hint = clap::ValueHint::Url, env = "TRIP_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Serve a live map of the hops of the trace over HTTP on this address [e.g. 127.0.0.1:8080]
+    #[arg(long, env = "TRIP_MAP_SERVER")]
+    pub map_server: Option<String>,
+
+    /// Write the samples of each hop to this Parquet file once tracing completes
+    #[arg(long, value_hint = clap::ValueHint::FilePath, env = "TRIP_PARQUET")]
+    pub parquet: Option<String>,
+
+    /// Warn when the loss at a hop is consistent with ICMP rate limiting
+    #[arg(long, env = "TRIP_RATE_LIMIT_WARNINGS")]
+    pub rate_limit_warnings: bool,
+
+    /// Suppress advisories and warnings, emitting only the report data
+    #[arg(long, env = "TRIP_QUIET")]
+    pub quiet: bool,
+
+    /// Show the end-to-end round trip time measured directly to the destination
+    #[arg(long, env = "TRIP_REPORT_DESTINATION_RTT")]
+    pub report_destination_rtt: bool,
+
+    /// Show the trace start and end timestamps in ISO-8601 format
+    #[arg(long, env = "TRIP_REPORT_TIMESTAMPS")]
+    pub report_timestamps: bool,
+
+    /// Include the DSCP of the latest response from each hop in csv reports
+    #[arg(long, env = "TRIP_REPORT_RESPONSE_DSCP")]
+    pub report_response_dscp: bool,
+
+    /// Include the time to the first response from each hop in table and csv reports
+    #[arg(long, env = "TRIP_REPORT_TIME_TO_FIRST")]
+    pub report_time_to_first: bool,
+
+    /// Include the estimated Mean Opinion Score of each hop in table reports
+    #[arg(long, env = "TRIP_REPORT_MOS")]
+    pub report_mos: bool,
+
+    /// Include the trace configuration in csv and influx reports
+    #[arg(long, env = "TRIP_REPORT_CONFIG")]
+    pub report_config: bool,
+
+    /// Note likely load-balanced hops whose RTT variation exceeds this percentage [default: none]
+    #[arg(long, env = "TRIP_REPORT_CV_THRESHOLD")]
+    pub report_cv_threshold: Option<u16>,
+
+    /// Show a sparkline of this many recent round trip times per hop in table reports [default:
+    /// none]
+    #[arg(long, env = "TRIP_REPORT_SPARKLINE")]
+    pub report_sparkline: Option<usize>,
+
+    /// Use ASCII rather than Unicode characters to render report sparklines
+    #[arg(long, env = "TRIP_ASCII")]
+    pub ascii: bool,
+
+    /// How to show the hops below the first time-to-live in table reports [default: omit]
+    #[arg(value_enum, long, env = "TRIP_REPORT_UNPROBED_HOPS")]
+    pub report_unprobed_hops: Option<UnprobedHopsConfig>,
+
+    /// Annotate the first hop as the gateway in table reports if it is the default gateway
+    #[arg(long, env = "TRIP_REPORT_GATEWAY")]
+    pub report_gateway: bool,
+
+    /// How to select the primary address of a hop which responded from several equally often
+    /// [default: first-seen]
+    #[arg(value_enum, long, env = "TRIP_REPORT_ADDR_TIE_BREAK")]
+    pub report_addr_tie_break: Option<AddrTieBreakConfig>,
+
+    /// Redact resolved hostnames matching these domain suffixes in reports [e.g. *.corp.example.com]
+    #[arg(long, value_delimiter(','), env = "TRIP_REPORT_REDACT_DOMAINS")]
+    pub report_redact_domains: Vec<String>,
+
+    /// The maximum ttl of hops whose resolved hostnames will be redacted in reports [default: none]
+    #[arg(long, env = "TRIP_REPORT_REDACT_MAX_TTL")]
+    pub report_redact_max_ttl: Option<u8>,
+
+    /// Strip these domain suffixes from resolved hostnames in table reports [e.g.
+    /// akamaitechnologies.com]
+    #[arg(long, value_delimiter(','), env = "TRIP_REPORT_STRIP_SUFFIXES")]
+    pub report_strip_suffixes: Vec<String>,
+
+    /// The maximum length of resolved hostnames in table reports, longer hostnames are truncated
+    #[arg(long, env = "TRIP_REPORT_MAX_HOSTNAME_LEN")]
+    pub report_max_hostname_len: Option<usize>,
+
+    /// The maximum widths of table report columns, longer content is truncated [column=width,..]
+    #[arg(long, value_delimiter(','), value_parser = super::parse_report_column_width, env = "TRIP_REPORT_COLUMN_WIDTHS")]
+    pub report_column_widths: Vec<(ReportColumn, u16)>,
+
     /// The supported MaxMind or IPinfo GeoIp mmdb file
     #[arg(short = 'G', long, value_hint = clap::ValueHint::FilePath, env = "TRIP_GEOIP_MMDB_FILE")]
     pub geoip_mmdb_file: Option<String>,
 
+    /// A JSON schema mapping custom GeoIp mmdb database fields
+    #[arg(long, value_hint = clap::ValueHint::FilePath, env = "TRIP_GEOIP_MMDB_SCHEMA_FILE")]
+    pub geoip_mmdb_schema_file: Option<String>,
+
     /// Generate shell completion
     #[arg(long, env = "TRIP_GENERATE")]
     pub generate: Option<Shell>,
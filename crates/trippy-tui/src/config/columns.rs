@@ -105,6 +105,18 @@ pub enum TuiColumn {
     Ecn,
     /// The autonomous system number for a hop.
     Asn,
+    /// The number of samples used to calculate the RTT statistics for a hop.
+    SampleCount,
+    /// The standard error of the mean RTT for a hop.
+    StdErr,
+    /// The autonomous system registry country code for a hop.
+    AsCc,
+    /// The time from the start of the trace to the first response for a hop.
+    TimeToFirst,
+    /// The percentage of responses which arrived out of order for a hop.
+    ReorderPct,
+    /// The largest number of consecutive rounds without a response for a hop.
+    MaxLossRun,
 }
 
 impl TryFrom<char> for TuiColumn {
@@ -140,6 +152,12 @@ impl TryFrom<char> for TuiColumn {
             'K' => Ok(Self::Dscp),
             'M' => Ok(Self::Ecn),
             'A' => Ok(Self::Asn),
+            'n' => Ok(Self::SampleCount),
+            'e' => Ok(Self::StdErr),
+            'c' => Ok(Self::AsCc),
+            'R' => Ok(Self::TimeToFirst),
+            'O' => Ok(Self::ReorderPct),
+            'L' => Ok(Self::MaxLossRun),
             c => Err(anyhow!(format!("unknown column code: {c}"))),
         }
     }
@@ -176,6 +194,12 @@ impl Display for TuiColumn {
             Self::Dscp => write!(f, "K"),
             Self::Ecn => write!(f, "M"),
             Self::Asn => write!(f, "A"),
+            Self::SampleCount => write!(f, "n"),
+            Self::StdErr => write!(f, "e"),
+            Self::AsCc => write!(f, "c"),
+            Self::TimeToFirst => write!(f, "R"),
+            Self::ReorderPct => write!(f, "O"),
+            Self::MaxLossRun => write!(f, "L"),
         }
     }
 }
@@ -198,6 +222,12 @@ mod tests {
     #[test_case('d', TuiColumn::StdDev)]
     #[test_case('t', TuiColumn::Status)]
     #[test_case('A', TuiColumn::Asn)]
+    #[test_case('n', TuiColumn::SampleCount)]
+    #[test_case('e', TuiColumn::StdErr)]
+    #[test_case('c', TuiColumn::AsCc)]
+    #[test_case('R', TuiColumn::TimeToFirst)]
+    #[test_case('O', TuiColumn::ReorderPct)]
+    #[test_case('L', TuiColumn::MaxLossRun)]
     fn test_try_from_char_for_tui_column(c: char, t: TuiColumn) {
         assert_eq!(TuiColumn::try_from(c).unwrap(), t);
     }
@@ -223,6 +253,12 @@ mod tests {
     #[test_case(TuiColumn::StdDev, "d")]
     #[test_case(TuiColumn::Status, "t")]
     #[test_case(TuiColumn::Asn, "A")]
+    #[test_case(TuiColumn::SampleCount, "n")]
+    #[test_case(TuiColumn::StdErr, "e")]
+    #[test_case(TuiColumn::AsCc, "c")]
+    #[test_case(TuiColumn::TimeToFirst, "R")]
+    #[test_case(TuiColumn::ReorderPct, "O")]
+    #[test_case(TuiColumn::MaxLossRun, "L")]
     fn test_display_formatting_for_tui_column(t: TuiColumn, letter: &'static str) {
         assert_eq!(format!("{t}"), letter);
     }
@@ -1,12 +1,16 @@
 use crate::config::{
-    AddressFamilyConfig, AddressMode, AsMode, DnsResolveMethodConfig, GeoIpMode, IcmpExtensionMode,
-    LogFormat, LogSpanEvents, Mode,
+    AddrTieBreakConfig, AddressFamilyConfig, AddressMode, AsMode, DnsResolveMethodConfig,
+    GeoIpMode, IcmpExtensionMode, LogFormat, LogSpanEvents, Mode, PayloadPatternModeConfig,
+    UnprobedHopsConfig,
 };
 use std::time::Duration;
 
 /// The default value for `mode`.
 pub const DEFAULT_MODE: Mode = Mode::Tui;
 
+/// The maximum number of hosts a CIDR range target may expand to.
+pub const MAX_CIDR_HOSTS: usize = 256;
+
 /// The default value for `dns-resolve-all`.
 pub const DEFAULT_DNS_RESOLVE_ALL: bool = false;
 
@@ -58,9 +62,69 @@ pub const DEFAULT_DNS_TIMEOUT: Duration = Duration::from_millis(5000);
 /// The default value for `dns-ttl`.
 pub const DEFAULT_DNS_TTL: Duration = Duration::from_secs(300);
 
+/// The default port for `dns-server`.
+pub const DEFAULT_DNS_SERVER_PORT: u16 = 53;
+
+/// The default value for `retry`.
+pub const DEFAULT_RETRY: usize = 0;
+
+/// The default value for `retry-delay`.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The default value for `target-concurrency`.
+pub const DEFAULT_TARGET_CONCURRENCY: usize = 0;
+
 /// The default value for `report-cycles`.
 pub const DEFAULT_REPORT_CYCLES: usize = 10;
 
+/// The default value for `min-samples-timeout`.
+pub const DEFAULT_MIN_SAMPLES_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The default value for `report-queries`.
+pub const DEFAULT_REPORT_QUERIES: usize = 3;
+
+/// The default value for `rate-limit-warnings`.
+pub const DEFAULT_REPORT_RATE_LIMIT_WARNINGS: bool = false;
+
+/// The default value for `quiet`.
+pub const DEFAULT_REPORT_QUIET: bool = false;
+
+/// The default value for `report-destination-rtt`.
+pub const DEFAULT_REPORT_DESTINATION_RTT: bool = false;
+
+/// The default value for `report-timestamps`.
+pub const DEFAULT_REPORT_TIMESTAMPS: bool = false;
+
+/// The default value for `report-response-dscp`.
+pub const DEFAULT_REPORT_RESPONSE_DSCP: bool = false;
+
+/// The default value for `report-time-to-first`.
+pub const DEFAULT_REPORT_TIME_TO_FIRST: bool = false;
+
+/// The default value for `report-mos`.
+pub const DEFAULT_REPORT_MOS: bool = false;
+
+/// The default value for `report-config`.
+pub const DEFAULT_REPORT_CONFIG: bool = false;
+
+/// The default value for `ascii`.
+pub const DEFAULT_ASCII: bool = false;
+
+/// The default value for `report-unprobed-hops`.
+pub const DEFAULT_REPORT_UNPROBED_HOPS: UnprobedHopsConfig = UnprobedHopsConfig::Omit;
+
+/// The default value for `report-addr-tie-break`.
+pub const DEFAULT_REPORT_ADDR_TIE_BREAK: AddrTieBreakConfig = AddrTieBreakConfig::FirstSeen;
+
+/// The default value for `report-gateway`.
+pub const DEFAULT_REPORT_GATEWAY: bool = false;
+
+/// The default value for `payload-pattern-mode`.
+pub const DEFAULT_PAYLOAD_PATTERN_MODE: PayloadPatternModeConfig = PayloadPatternModeConfig::Fixed;
+
+/// The minimum width of a table report column.
+pub const MIN_REPORT_COLUMN_WIDTH: u16 = 2;
+
 /// The minimum TUI refresh rate.
 pub const TUI_MIN_REFRESH_RATE_MS: Duration = Duration::from_millis(50);
 
@@ -79,6 +143,15 @@ pub const MIN_GRACE_DURATION_MS: Duration = Duration::from_millis(10);
 /// The maximum grace duration.
 pub const MAX_GRACE_DURATION_MS: Duration = Duration::from_millis(1000);
 
+/// The maximum number of retries.
+pub const MAX_RETRY: usize = 10;
+
+/// The minimum retry delay.
+pub const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// The maximum retry delay.
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 /// The minimum IPv4 packet size we allow.
 pub const MIN_PACKET_SIZE_IPV4: u16 = 28;
 
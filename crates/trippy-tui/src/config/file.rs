@@ -1,8 +1,10 @@
 use crate::config::binding::TuiKeyBinding;
 use crate::config::theme::TuiColor;
 use crate::config::{
-    AddressFamilyConfig, AddressMode, AsMode, DnsResolveMethodConfig, GeoIpMode, IcmpExtensionMode,
-    LogFormat, LogSpanEvents, Mode, MultipathStrategyConfig, ProtocolConfig,
+    AddrTieBreakConfig, AddressFamilyConfig, AddressMode, AsMode, DnsResolveMethodConfig,
+    GeoIpMode, IcmpExtensionMode, LogFormat, LogSpanEvents, Mode, MultiResponseModeConfig,
+    MultipathStrategyConfig, NegativeRttModeConfig, PayloadPatternModeConfig, ProtocolConfig,
+    TcpFlagsConfig, UnprobedHopsConfig, UnreachableModeConfig,
 };
 use anyhow::Context;
 use encoding_rs_io::DecodeReaderBytes;
@@ -111,6 +113,11 @@ impl Default for ConfigFile {
 pub struct ConfigTrippy {
     pub mode: Option<Mode>,
     pub unprivileged: Option<bool>,
+    pub retry: Option<usize>,
+    #[serde(default)]
+    #[serde(deserialize_with = "humantime_deser")]
+    pub retry_delay: Option<Duration>,
+    pub target_concurrency: Option<usize>,
     pub log_format: Option<LogFormat>,
     pub log_filter: Option<String>,
     pub log_span_events: Option<LogSpanEvents>,
@@ -121,6 +128,9 @@ impl Default for ConfigTrippy {
         Self {
             mode: Some(super::constants::DEFAULT_MODE),
             unprivileged: Some(defaults::DEFAULT_PRIVILEGE_MODE.is_unprivileged()),
+            retry: Some(super::constants::DEFAULT_RETRY),
+            retry_delay: Some(super::constants::DEFAULT_RETRY_DELAY),
+            target_concurrency: Some(super::constants::DEFAULT_TARGET_CONCURRENCY),
             log_format: Some(super::constants::DEFAULT_LOG_FORMAT),
             log_filter: Some(String::from(super::constants::DEFAULT_LOG_FILTER)),
             log_span_events: Some(super::constants::DEFAULT_LOG_SPAN_EVENTS),
@@ -135,10 +145,12 @@ pub struct ConfigStrategy {
     pub addr_family: Option<AddressFamilyConfig>,
     pub target_port: Option<u16>,
     pub source_port: Option<u16>,
+    pub source_port_seed: Option<u64>,
     #[serde(default)]
     #[serde(deserialize_with = "addr_deser")]
     pub source_address: Option<IpAddr>,
     pub interface: Option<String>,
+    pub write_pcap: Option<String>,
     #[serde(default)]
     #[serde(deserialize_with = "humantime_deser")]
     pub min_round_duration: Option<Duration>,
@@ -150,18 +162,47 @@ pub struct ConfigStrategy {
     #[serde(default)]
     #[serde(deserialize_with = "humantime_deser")]
     pub grace_duration: Option<Duration>,
+    #[serde(default)]
+    #[serde(deserialize_with = "humantime_deser")]
+    pub target_grace_duration: Option<Duration>,
+    pub continue_past_target: Option<bool>,
+    pub unreachable_mode: Option<UnreachableModeConfig>,
+    pub icmp_unreachable_reached: Option<bool>,
+    pub multi_response_mode: Option<MultiResponseModeConfig>,
+    pub hop_allow: Option<Vec<IpAddr>>,
+    pub hop_deny: Option<Vec<IpAddr>>,
     pub max_inflight: Option<u8>,
+    pub max_packets: Option<usize>,
+    pub max_silent_rounds: Option<usize>,
+    #[serde(default)]
+    #[serde(deserialize_with = "humantime_deser")]
+    pub send_interval: Option<Duration>,
+    pub rate: Option<u32>,
     pub first_ttl: Option<u8>,
     pub max_ttl: Option<u8>,
     pub packet_size: Option<u16>,
     pub payload_pattern: Option<u8>,
+    pub payload_pattern_mode: Option<PayloadPatternModeConfig>,
     pub tos: Option<u8>,
     pub icmp_extensions: Option<bool>,
+    pub icmp_tolerant_identifier: Option<bool>,
+    pub icmp_strict_source: Option<bool>,
+    pub icmp_quoted_payload: Option<bool>,
+    pub fwmark: Option<u32>,
+    pub icmp_nonce_key: Option<u64>,
+    pub icmp_identifier_range: Option<u16>,
+    pub icmp_payload_len: Option<u16>,
+    pub icmp_echo_code: Option<u8>,
+    pub icmp_bind_attempts: Option<u8>,
+    pub tcp_flags: Option<TcpFlagsConfig>,
     #[serde(default)]
     #[serde(deserialize_with = "humantime_deser")]
     pub read_timeout: Option<Duration>,
     pub max_samples: Option<usize>,
     pub max_flows: Option<usize>,
+    pub negative_rtt_mode: Option<NegativeRttModeConfig>,
+    pub window: Option<usize>,
+    pub max_addrs: Option<usize>,
 }
 
 impl Default for ConfigStrategy {
@@ -171,8 +212,10 @@ impl Default for ConfigStrategy {
             addr_family: Some(super::constants::DEFAULT_ADDR_FAMILY),
             target_port: None,
             source_port: None,
+            source_port_seed: None,
             source_address: None,
             interface: None,
+            write_pcap: None,
             min_round_duration: Some(defaults::DEFAULT_STRATEGY_MIN_ROUND_DURATION),
             max_round_duration: Some(defaults::DEFAULT_STRATEGY_MAX_ROUND_DURATION),
             initial_sequence: Some(defaults::DEFAULT_STRATEGY_INITIAL_SEQUENCE),
@@ -180,23 +223,55 @@ impl Default for ConfigStrategy {
                 defaults::DEFAULT_STRATEGY_MULTIPATH,
             )),
             grace_duration: Some(defaults::DEFAULT_STRATEGY_GRACE_DURATION),
+            target_grace_duration: Some(defaults::DEFAULT_STRATEGY_TARGET_GRACE_DURATION),
+            continue_past_target: Some(defaults::DEFAULT_STRATEGY_CONTINUE_PAST_TARGET),
+            unreachable_mode: Some(UnreachableModeConfig::from(
+                defaults::DEFAULT_UNREACHABLE_MODE,
+            )),
+            icmp_unreachable_reached: Some(defaults::DEFAULT_ICMP_UNREACHABLE_REACHED),
+            multi_response_mode: Some(MultiResponseModeConfig::from(
+                defaults::DEFAULT_MULTI_RESPONSE_MODE,
+            )),
+            hop_allow: None,
+            hop_deny: None,
             max_inflight: Some(defaults::DEFAULT_STRATEGY_MAX_INFLIGHT),
+            max_packets: None,
+            max_silent_rounds: None,
+            send_interval: None,
+            rate: None,
             first_ttl: Some(defaults::DEFAULT_STRATEGY_FIRST_TTL),
             max_ttl: Some(defaults::DEFAULT_STRATEGY_MAX_TTL),
             packet_size: Some(defaults::DEFAULT_STRATEGY_PACKET_SIZE),
             payload_pattern: Some(defaults::DEFAULT_STRATEGY_PAYLOAD_PATTERN),
+            payload_pattern_mode: Some(super::constants::DEFAULT_PAYLOAD_PATTERN_MODE),
             tos: Some(defaults::DEFAULT_STRATEGY_TOS),
             icmp_extensions: Some(defaults::DEFAULT_ICMP_EXTENSION_PARSE_MODE.is_enabled()),
+            icmp_tolerant_identifier: Some(
+                defaults::DEFAULT_ICMP_IDENTIFIER_MATCH_MODE.is_tolerant(),
+            ),
+            icmp_strict_source: Some(defaults::DEFAULT_ICMP_SOURCE_MATCH_MODE.is_strict()),
+            icmp_quoted_payload: Some(defaults::DEFAULT_ICMP_QUOTED_PAYLOAD_MODE.is_enabled()),
+            fwmark: None,
+            icmp_nonce_key: None,
+            icmp_identifier_range: None,
+            icmp_payload_len: None,
+            icmp_echo_code: Some(defaults::DEFAULT_ICMP_ECHO_CODE),
+            icmp_bind_attempts: Some(defaults::DEFAULT_ICMP_BIND_ATTEMPTS),
+            tcp_flags: Some(TcpFlagsConfig::from(defaults::DEFAULT_TCP_FLAGS)),
             read_timeout: Some(defaults::DEFAULT_STRATEGY_READ_TIMEOUT),
             max_samples: Some(defaults::DEFAULT_MAX_SAMPLES),
             max_flows: Some(defaults::DEFAULT_MAX_FLOWS),
+            negative_rtt_mode: Some(NegativeRttModeConfig::from(
+                defaults::DEFAULT_NEGATIVE_RTT_MODE,
+            )),
+            window: None,
+            max_addrs: None,
         }
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
-#[expect(clippy::struct_field_names)]
 pub struct ConfigDns {
     pub dns_resolve_method: Option<DnsResolveMethodConfig>,
     pub dns_resolve_all: Option<bool>,
@@ -207,6 +282,8 @@ pub struct ConfigDns {
     #[serde(default)]
     #[serde(deserialize_with = "humantime_deser")]
     pub dns_ttl: Option<Duration>,
+    pub dns_server: Option<String>,
+    pub doh: Option<String>,
 }
 
 impl Default for ConfigDns {
@@ -217,6 +294,8 @@ impl Default for ConfigDns {
             dns_lookup_as_info: Some(super::constants::DEFAULT_DNS_LOOKUP_AS_INFO),
             dns_timeout: Some(super::constants::DEFAULT_DNS_TIMEOUT),
             dns_ttl: Some(super::constants::DEFAULT_DNS_TTL),
+            dns_server: None,
+            doh: None,
         }
     }
 }
@@ -225,12 +304,70 @@ impl Default for ConfigDns {
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct ConfigReport {
     pub report_cycles: Option<usize>,
+    pub report_fail_fast: Option<usize>,
+    pub min_samples: Option<usize>,
+    #[serde(default)]
+    #[serde(deserialize_with = "humantime_deser")]
+    pub min_samples_timeout: Option<Duration>,
+    pub report_queries: Option<usize>,
+    pub log_table: Option<String>,
+    pub format: Option<String>,
+    pub otlp_endpoint: Option<String>,
+    pub map_server: Option<String>,
+    pub parquet: Option<String>,
+    pub rate_limit_warnings: Option<bool>,
+    pub quiet: Option<bool>,
+    pub report_destination_rtt: Option<bool>,
+    pub report_timestamps: Option<bool>,
+    pub report_response_dscp: Option<bool>,
+    pub report_time_to_first: Option<bool>,
+    pub report_mos: Option<bool>,
+    pub report_config: Option<bool>,
+    pub report_cv_threshold: Option<u16>,
+    pub report_sparkline: Option<usize>,
+    pub ascii: Option<bool>,
+    pub report_unprobed_hops: Option<UnprobedHopsConfig>,
+    pub report_gateway: Option<bool>,
+    pub report_addr_tie_break: Option<AddrTieBreakConfig>,
+    pub report_redact_domains: Option<Vec<String>>,
+    pub report_redact_max_ttl: Option<u8>,
+    pub report_strip_suffixes: Option<Vec<String>>,
+    pub report_max_hostname_len: Option<usize>,
+    pub report_column_widths: Option<Vec<String>>,
 }
 
 impl Default for ConfigReport {
     fn default() -> Self {
         Self {
             report_cycles: Some(super::constants::DEFAULT_REPORT_CYCLES),
+            report_fail_fast: None,
+            min_samples: None,
+            min_samples_timeout: Some(super::constants::DEFAULT_MIN_SAMPLES_TIMEOUT),
+            report_queries: Some(super::constants::DEFAULT_REPORT_QUERIES),
+            log_table: None,
+            format: None,
+            otlp_endpoint: None,
+            map_server: None,
+            parquet: None,
+            rate_limit_warnings: Some(super::constants::DEFAULT_REPORT_RATE_LIMIT_WARNINGS),
+            quiet: Some(super::constants::DEFAULT_REPORT_QUIET),
+            report_destination_rtt: Some(super::constants::DEFAULT_REPORT_DESTINATION_RTT),
+            report_timestamps: Some(super::constants::DEFAULT_REPORT_TIMESTAMPS),
+            report_response_dscp: Some(super::constants::DEFAULT_REPORT_RESPONSE_DSCP),
+            report_time_to_first: Some(super::constants::DEFAULT_REPORT_TIME_TO_FIRST),
+            report_mos: Some(super::constants::DEFAULT_REPORT_MOS),
+            report_config: Some(super::constants::DEFAULT_REPORT_CONFIG),
+            report_cv_threshold: None,
+            report_sparkline: None,
+            ascii: Some(super::constants::DEFAULT_ASCII),
+            report_unprobed_hops: Some(super::constants::DEFAULT_REPORT_UNPROBED_HOPS),
+            report_gateway: Some(super::constants::DEFAULT_REPORT_GATEWAY),
+            report_addr_tie_break: Some(super::constants::DEFAULT_REPORT_ADDR_TIE_BREAK),
+            report_redact_domains: None,
+            report_redact_max_ttl: None,
+            report_strip_suffixes: None,
+            report_max_hostname_len: None,
+            report_column_widths: None,
         }
     }
 }
@@ -249,6 +386,7 @@ pub struct ConfigTui {
     pub tui_geoip_mode: Option<GeoIpMode>,
     pub tui_max_addrs: Option<u8>,
     pub geoip_mmdb_file: Option<String>,
+    pub geoip_mmdb_schema_file: Option<String>,
     pub tui_custom_columns: Option<String>,
     pub tui_locale: Option<String>,
     pub tui_timezone: Option<String>,
@@ -273,6 +411,7 @@ impl Default for ConfigTui {
             tui_locale: None,
             tui_timezone: None,
             geoip_mmdb_file: None,
+            geoip_mmdb_schema_file: None,
             deprecated_tui_max_samples: None,
             deprecated_tui_max_flows: None,
         }
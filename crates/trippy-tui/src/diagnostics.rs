@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::io::Write;
+
+/// A sink for diagnostic output, such as warnings and advisories.
+///
+/// Diagnostics are kept separate from report data, which is written to stdout, so that the report
+/// data may be piped.  All diagnostics are discarded in quiet mode.
+pub struct Diagnostics {
+    sink: Option<RefCell<Box<dyn Write>>>,
+}
+
+impl Diagnostics {
+    /// Create `Diagnostics` which are written to stderr, or discarded if `quiet`.
+    pub fn stderr(quiet: bool) -> Self {
+        if quiet {
+            Self::quiet()
+        } else {
+            Self::new(Box::new(std::io::stderr()))
+        }
+    }
+
+    /// Create `Diagnostics` which are written to `sink`.
+    pub fn new(sink: Box<dyn Write>) -> Self {
+        Self {
+            sink: Some(RefCell::new(sink)),
+        }
+    }
+
+    /// Create `Diagnostics` which are discarded.
+    pub const fn quiet() -> Self {
+        Self { sink: None }
+    }
+
+    /// Emit a diagnostic `message` on a line of its own.
+    ///
+    /// A failure to write the diagnostic is ignored.
+    pub fn emit(&self, message: impl Display) {
+        if let Some(sink) = &self.sink {
+            _ = writeln!(sink.borrow_mut(), "{message}");
+        }
+    }
+}
+
+/// A sink which may be inspected after being boxed.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct SharedSink(pub std::rc::Rc<RefCell<Vec<u8>>>);
+
+#[cfg(test)]
+impl SharedSink {
+    /// The output written to the sink.
+    pub fn output(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Write for SharedSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit() {
+        let sink = SharedSink::default();
+        let diagnostics = Diagnostics::new(Box::new(sink.clone()));
+        diagnostics.emit("warning: one");
+        diagnostics.emit("note: two");
+        assert_eq!("warning: one\nnote: two\n", sink.output());
+    }
+
+    #[test]
+    fn test_quiet() {
+        let diagnostics = Diagnostics::quiet();
+        diagnostics.emit("warning: one");
+        assert!(diagnostics.sink.is_none());
+    }
+}
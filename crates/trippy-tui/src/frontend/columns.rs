@@ -201,6 +201,18 @@ pub enum ColumnType {
     Ecn,
     /// The autonomous system number for a hop.
     Asn,
+    /// The number of samples used to calculate the RTT statistics for a hop.
+    SampleCount,
+    /// The standard error of the mean RTT for a hop.
+    StdErr,
+    /// The autonomous system registry country code for a hop.
+    AsCc,
+    /// The time from the start of the trace to the first response for a hop.
+    TimeToFirst,
+    /// The percentage of responses which arrived out of order for a hop.
+    ReorderPct,
+    /// The largest number of consecutive rounds without a response for a hop.
+    MaxLossRun,
 }
 
 impl From<ColumnType> for char {
@@ -234,6 +246,12 @@ impl From<ColumnType> for char {
             ColumnType::Dscp => 'K',
             ColumnType::Ecn => 'M',
             ColumnType::Asn => 'A',
+            ColumnType::SampleCount => 'n',
+            ColumnType::StdErr => 'e',
+            ColumnType::AsCc => 'c',
+            ColumnType::TimeToFirst => 'R',
+            ColumnType::ReorderPct => 'O',
+            ColumnType::MaxLossRun => 'L',
         }
     }
 }
@@ -269,6 +287,12 @@ impl From<TuiColumn> for Column {
             TuiColumn::Dscp => Self::new_shown(ColumnType::Dscp),
             TuiColumn::Ecn => Self::new_shown(ColumnType::Ecn),
             TuiColumn::Asn => Self::new_shown(ColumnType::Asn),
+            TuiColumn::SampleCount => Self::new_shown(ColumnType::SampleCount),
+            TuiColumn::StdErr => Self::new_shown(ColumnType::StdErr),
+            TuiColumn::AsCc => Self::new_shown(ColumnType::AsCc),
+            TuiColumn::TimeToFirst => Self::new_shown(ColumnType::TimeToFirst),
+            TuiColumn::ReorderPct => Self::new_shown(ColumnType::ReorderPct),
+            TuiColumn::MaxLossRun => Self::new_shown(ColumnType::MaxLossRun),
         }
     }
 }
@@ -311,6 +335,12 @@ impl ColumnType {
             Self::Dscp => t!("column_dscp"),
             Self::Ecn => t!("column_ecn"),
             Self::Asn => t!("column_asn"),
+            Self::SampleCount => t!("column_smpl"),
+            Self::StdErr => t!("column_sterr"),
+            Self::AsCc => t!("column_ascc"),
+            Self::TimeToFirst => t!("column_first"),
+            Self::ReorderPct => t!("column_reorder_pct"),
+            Self::MaxLossRun => t!("column_max_loss_run"),
         }
     }
 
@@ -355,6 +385,12 @@ impl ColumnType {
             Self::Dscp => ColumnWidth::Fixed(width.max(7)),
             Self::Ecn => ColumnWidth::Fixed(width.max(7)),
             Self::Asn => ColumnWidth::Fixed(width.max(8)),
+            Self::SampleCount => ColumnWidth::Fixed(width.max(7)),
+            Self::StdErr => ColumnWidth::Fixed(width.max(8)),
+            Self::AsCc => ColumnWidth::Fixed(width.max(7)),
+            Self::TimeToFirst => ColumnWidth::Fixed(width.max(8)),
+            Self::ReorderPct => ColumnWidth::Fixed(width.max(8)),
+            Self::MaxLossRun => ColumnWidth::Fixed(width.max(6)),
         }
     }
 }
@@ -421,6 +457,12 @@ mod tests {
                 Column::new_hidden(ColumnType::Dscp),
                 Column::new_hidden(ColumnType::Ecn),
                 Column::new_hidden(ColumnType::Asn),
+                Column::new_hidden(ColumnType::SampleCount),
+                Column::new_hidden(ColumnType::StdErr),
+                Column::new_hidden(ColumnType::AsCc),
+                Column::new_hidden(ColumnType::TimeToFirst),
+                Column::new_hidden(ColumnType::ReorderPct),
+                Column::new_hidden(ColumnType::MaxLossRun),
             ])
         );
     }
@@ -446,6 +488,12 @@ mod tests {
     #[test_case(ColumnType::StdDev, "StDev")]
     #[test_case(ColumnType::Status, "Sts")]
     #[test_case(ColumnType::Asn, "ASN")]
+    #[test_case(ColumnType::SampleCount, "Smpl")]
+    #[test_case(ColumnType::StdErr, "StErr")]
+    #[test_case(ColumnType::AsCc, "AS CC")]
+    #[test_case(ColumnType::TimeToFirst, "First")]
+    #[test_case(ColumnType::ReorderPct, "Reord%")]
+    #[test_case(ColumnType::MaxLossRun, "LRun")]
     fn test_column_display_formatting(c: ColumnType, heading: &'static str) {
         assert_eq!(format!("{c}"), heading);
     }
@@ -34,6 +34,7 @@ pub struct TuiConfig {
     /// The columns to display in the hops table.
     pub tui_columns: Columns,
     pub geoip_mmdb_file: Option<String>,
+    pub geoip_mmdb_schema_file: Option<String>,
     pub dns_resolve_all: bool,
     /// The current locale.
     pub locale: String,
@@ -56,6 +57,7 @@ impl TuiConfig {
         tui_bindings: &TuiBindings,
         tui_columns: &TuiColumns,
         geoip_mmdb_file: Option<String>,
+        geoip_mmdb_schema_file: Option<String>,
         dns_resolve_all: bool,
         locale: String,
         timezone: Option<Tz>,
@@ -74,6 +76,7 @@ impl TuiConfig {
             bindings: Bindings::from(*tui_bindings),
             tui_columns: Columns::from(tui_columns.clone()),
             geoip_mmdb_file,
+            geoip_mmdb_schema_file,
             dns_resolve_all,
             locale,
             timezone,
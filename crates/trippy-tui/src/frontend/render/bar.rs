@@ -27,8 +27,11 @@ pub fn render(f: &mut Frame<'_>, rect: Rect, app: &TuiApp) {
         ),
     });
 
+    let tracer = &app.tracer_config().data;
     let privilege_mode = Span::raw(fmt_privilege_mode(
-        app.tracer_config().data.privilege_mode(),
+        tracer
+            .effective_privilege_mode()
+            .unwrap_or_else(|| tracer.privilege_mode()),
     ));
 
     let as_mode = match app.resolver.config().resolve_method {
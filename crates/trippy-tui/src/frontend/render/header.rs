@@ -10,7 +10,7 @@ use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
 use std::net::IpAddr;
 use std::str::FromStr;
 use std::time::Duration;
-use trippy_core::{Hop, PortDirection};
+use trippy_core::{Hop, PortDirection, StopReason};
 use trippy_dns::Resolver;
 
 /// Render the title, target, clock and basic keyboard controls.
@@ -236,6 +236,16 @@ fn render_destination(app: &TuiApp) -> String {
     }
 }
 
+/// Render the reason the tracing stopped.
+fn render_stop_reason(stop_reason: StopReason) -> String {
+    String::from(match stop_reason {
+        StopReason::Stopped => t!("stop_reason_stopped"),
+        StopReason::MaxRounds => t!("stop_reason_max_rounds"),
+        StopReason::PacketsExhausted => t!("stop_reason_packets_exhausted"),
+        StopReason::DestinationSilent => t!("stop_reason_destination_silent"),
+    })
+}
+
 /// Render the headline status of the tracing.
 fn render_status(app: &TuiApp) -> String {
     let failure_count: usize = app
@@ -269,6 +279,12 @@ fn render_status(app: &TuiApp) -> String {
     };
     if app.selected_tracer_data.error().is_some() {
         String::from(t!("status_failed"))
+    } else if let Some(stop_reason) = app.selected_tracer_data.stop_reason() {
+        format!(
+            "{} ({}){failures}",
+            t!("status_finished"),
+            render_stop_reason(stop_reason)
+        )
     } else if let Some(start) = app.frozen_start {
         let frozen = format_duration(Duration::from_secs(
             start.elapsed().unwrap_or_default().as_secs(),
@@ -226,6 +226,7 @@ fn format_tui_settings(app: &TuiApp) -> Vec<SettingsItem> {
 }
 
 /// Format trace settings.
+#[expect(clippy::too_many_lines)]
 fn format_trace_settings(app: &TuiApp) -> Vec<SettingsItem> {
     let cfg = app.tracer_config();
     let interface = if let Some(iface) = cfg.data.interface() {
@@ -233,6 +234,14 @@ fn format_trace_settings(app: &TuiApp) -> Vec<SettingsItem> {
     } else {
         t!("auto").to_string()
     };
+    let window = cfg
+        .data
+        .window()
+        .map_or_else(|| t!("none").to_string(), |window| window.to_string());
+    let fwmark = cfg
+        .data
+        .fwmark()
+        .map_or_else(|| t!("na").to_string(), |mark| mark.to_string());
     let (src_port, dst_port) = match cfg.data.port_direction() {
         PortDirection::None => (t!("na").to_string(), t!("na").to_string()),
         PortDirection::FixedDest(dst) => (t!("auto").to_string(), format!("{}", dst.0)),
@@ -254,6 +263,26 @@ fn format_trace_settings(app: &TuiApp) -> Vec<SettingsItem> {
             "grace-duration",
             format!("{}", format_duration(cfg.data.grace_duration())),
         ),
+        SettingsItem::new(
+            "target-grace-duration",
+            format!("{}", format_duration(cfg.data.target_grace_duration())),
+        ),
+        SettingsItem::new(
+            "continue-past-target",
+            format!("{}", cfg.data.continue_past_target()),
+        ),
+        SettingsItem::new(
+            "unreachable-mode",
+            format!("{}", cfg.data.unreachable_mode()),
+        ),
+        SettingsItem::new(
+            "icmp-unreachable-reached",
+            format!("{}", cfg.data.icmp_unreachable_reached()),
+        ),
+        SettingsItem::new(
+            "multi-response-mode",
+            format!("{}", cfg.data.multi_response_mode()),
+        ),
         SettingsItem::new("max-inflight", format!("{}", cfg.data.max_inflight().0)),
         SettingsItem::new(
             "initial-sequence",
@@ -264,15 +293,22 @@ fn format_trace_settings(app: &TuiApp) -> Vec<SettingsItem> {
             format!("{}", format_duration(cfg.data.read_timeout())),
         ),
         SettingsItem::new("packet-size", format!("{}", cfg.data.packet_size().0)),
-        SettingsItem::new(
-            "payload-pattern",
-            format!("{}", cfg.data.payload_pattern().0),
-        ),
+        SettingsItem::new("payload-pattern", format!("{}", cfg.data.payload_pattern())),
         SettingsItem::new("tos", format!("{}", cfg.data.tos().0)),
         SettingsItem::new(
             "icmp-extensions",
             format!("{}", cfg.data.icmp_extension_parse_mode()),
         ),
+        SettingsItem::new(
+            "icmp-tolerant-identifier",
+            format!("{}", cfg.data.icmp_identifier_match_mode()),
+        ),
+        SettingsItem::new(
+            "icmp-strict-source",
+            format!("{}", cfg.data.icmp_source_match_mode()),
+        ),
+        SettingsItem::new("fwmark", fwmark),
+        SettingsItem::new("tcp-flags", cfg.data.tcp_flags().to_string()),
         SettingsItem::new("interface", interface),
         SettingsItem::new(
             "multipath-strategy",
@@ -288,6 +324,11 @@ fn format_trace_settings(app: &TuiApp) -> Vec<SettingsItem> {
             "max-flows",
             format!("{}", app.selected_tracer_data.max_flows()),
         ),
+        SettingsItem::new(
+            "negative-rtt-mode",
+            cfg.data.negative_rtt_mode().to_string(),
+        ),
+        SettingsItem::new("window", window),
     ]
 }
 
@@ -319,13 +360,22 @@ fn format_dns_settings(app: &TuiApp) -> Vec<SettingsItem> {
 
 /// Format `GeoIp` settings.
 fn format_geoip_settings(app: &TuiApp) -> Vec<SettingsItem> {
-    vec![SettingsItem::new(
-        "geoip-mmdb-file",
-        app.tui_config
-            .geoip_mmdb_file
-            .as_deref()
-            .map_or_else(|| t!("none").to_string(), ToString::to_string),
-    )]
+    vec![
+        SettingsItem::new(
+            "geoip-mmdb-file",
+            app.tui_config
+                .geoip_mmdb_file
+                .as_deref()
+                .map_or_else(|| t!("none").to_string(), ToString::to_string),
+        ),
+        SettingsItem::new(
+            "geoip-mmdb-schema-file",
+            app.tui_config
+                .geoip_mmdb_schema_file
+                .as_deref()
+                .map_or_else(|| t!("none").to_string(), ToString::to_string),
+        ),
+    ]
 }
 
 /// Format binding settings.
@@ -551,9 +601,9 @@ pub const SETTINGS_TAB_COLUMNS: usize = 6;
 pub fn settings_tabs() -> [(String, usize); 7] {
     [
         (t!("settings_tab_tui_title").to_string(), 10),
-        (t!("settings_tab_trace_title").to_string(), 18),
+        (t!("settings_tab_trace_title").to_string(), 29),
         (t!("settings_tab_dns_title").to_string(), 5),
-        (t!("settings_tab_geoip_title").to_string(), 1),
+        (t!("settings_tab_geoip_title").to_string(), 2),
         (t!("settings_tab_bindings_title").to_string(), 37),
         (t!("settings_tab_theme_title").to_string(), 35),
         (t!("settings_tab_columns_title").to_string(), 0),
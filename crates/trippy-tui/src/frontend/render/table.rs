@@ -56,7 +56,11 @@ pub fn render(f: &mut Frame<'_>, app: &mut TuiApp, rect: Rect) {
     };
 
     let has_selection = !app.route_selections.is_empty();
-    let all_hops: Vec<&Hop> = app.tracer_data().hops_for_flow(app.selected_flow).iter().collect();
+    let all_hops: Vec<&Hop> = app
+        .tracer_data()
+        .hops_for_flow(app.selected_flow)
+        .iter()
+        .collect();
     let mut hop_num: usize = 0;
     let mut visible_hops: Vec<(&Hop, usize)> = Vec::new();
 
@@ -84,7 +88,14 @@ pub fn render(f: &mut Frame<'_>, app: &mut TuiApp, rect: Rect) {
     }
 
     let rows = visible_hops.iter().map(|&(hop, _num)| {
-        render_table_row(app, hop, &app.resolver, &app.geoip_lookup, &app.tui_config, &config.tui_columns)
+        render_table_row(
+            app,
+            hop,
+            &app.resolver,
+            &app.geoip_lookup,
+            &app.tui_config,
+            &config.tui_columns,
+        )
     });
     let table = Table::new(rows, widths.as_slice())
         .header(header)
@@ -136,7 +147,15 @@ fn render_table_row(
     let cells: Vec<Cell<'_>> = custom_columns
         .columns()
         .map(|column| {
-            new_cell(column.typ, is_selected_hop, app, hop, dns, geoip_lookup, config)
+            new_cell(
+                column.typ,
+                is_selected_hop,
+                app,
+                hop,
+                dns,
+                geoip_lookup,
+                config,
+            )
         })
         .collect();
     let row_color = if has_route_sel {
@@ -199,7 +218,13 @@ fn new_cell(
         ColumnType::FlossPct => render_pct_cell(hop.forward_loss_pct()),
         ColumnType::Dscp => render_dscp_cell(hop.dscp()),
         ColumnType::Ecn => render_ecn_cell(hop.ecn()),
-        ColumnType::Asn => render_asn_cell(hop, dns, config),
+        ColumnType::Asn => render_asinfo_cell(hop, dns, config, format_asn_cell),
+        ColumnType::SampleCount => render_usize_cell(hop.sample_count()),
+        ColumnType::StdErr => render_float_cell(hop.stderr_ms(), 1, total_recv),
+        ColumnType::AsCc => render_asinfo_cell(hop, dns, config, format_as_cc_cell),
+        ColumnType::TimeToFirst => render_float_cell(hop.time_to_first_ms(), 1, total_recv),
+        ColumnType::ReorderPct => render_pct_cell(hop.reorder_ratio() * 100_f64),
+        ColumnType::MaxLossRun => render_usize_cell(hop.max_loss_run()),
     }
 }
 
@@ -285,30 +310,7 @@ fn render_port_cell(port: u16) -> Cell<'static> {
 
 fn render_dscp_cell(dscp: Option<Dscp>) -> Cell<'static> {
     match dscp {
-        Some(Dscp::DF) => Cell::from("DF"),
-        Some(Dscp::AF11) => Cell::from("AF11"),
-        Some(Dscp::AF12) => Cell::from("AF12"),
-        Some(Dscp::AF13) => Cell::from("AF13"),
-        Some(Dscp::AF21) => Cell::from("AF21"),
-        Some(Dscp::AF22) => Cell::from("AF22"),
-        Some(Dscp::AF23) => Cell::from("AF23"),
-        Some(Dscp::AF31) => Cell::from("AF31"),
-        Some(Dscp::AF32) => Cell::from("AF32"),
-        Some(Dscp::AF33) => Cell::from("AF33"),
-        Some(Dscp::AF41) => Cell::from("AF41"),
-        Some(Dscp::AF42) => Cell::from("AF42"),
-        Some(Dscp::AF43) => Cell::from("AF43"),
-        Some(Dscp::CS1) => Cell::from("CS1"),
-        Some(Dscp::CS2) => Cell::from("CS2"),
-        Some(Dscp::CS3) => Cell::from("CS3"),
-        Some(Dscp::CS4) => Cell::from("CS4"),
-        Some(Dscp::CS5) => Cell::from("CS5"),
-        Some(Dscp::CS6) => Cell::from("CS6"),
-        Some(Dscp::CS7) => Cell::from("CS7"),
-        Some(Dscp::EF) => Cell::from("EF"),
-        Some(Dscp::VA) => Cell::from("VA"),
-        Some(Dscp::LE) => Cell::from("LE"),
-        Some(Dscp::Other(other)) => Cell::from(format!("0x{other:02x}")),
+        Some(dscp) => Cell::from(dscp.to_string()),
         None => Cell::from(t!("na")),
     }
 }
@@ -323,7 +325,13 @@ fn render_ecn_cell(ecn: Option<Ecn>) -> Cell<'static> {
     }
 }
 
-fn render_asn_cell(hop: &Hop, dns: &DnsResolver, config: &TuiConfig) -> Cell<'static> {
+/// Render a cell with AS information, formatted by `format`, for each visible address of a hop.
+fn render_asinfo_cell(
+    hop: &Hop,
+    dns: &DnsResolver,
+    config: &TuiConfig,
+    format: fn(DnsEntry) -> String,
+) -> Cell<'static> {
     if hop.total_recv() == 0 {
         Cell::from(t!("na"))
     } else if config.privacy_max_ttl >= Some(hop.ttl()) {
@@ -334,7 +342,7 @@ fn render_asn_cell(hop: &Hop, dns: &DnsResolver, config: &TuiConfig) -> Cell<'st
         let (addrs, _) = visible_addresses(hop, config.max_addrs);
         let content = addrs
             .into_iter()
-            .map(|(addr, _)| format_asinfo_cell(*addr, dns))
+            .map(|(addr, _)| format(dns.lazy_reverse_lookup_with_asinfo(*addr)))
             .join("\n");
         Cell::from(content)
     }
@@ -355,7 +363,8 @@ fn render_hostname(
                 visible_addresses(hop, app.tui_config.max_addrs)
             } else {
                 // 用 is_addr_visible 过滤隐藏的地址
-                let filtered: Vec<_> = hop.addrs_with_counts()
+                let filtered: Vec<_> = hop
+                    .addrs_with_counts()
                     .enumerate()
                     .filter(|(i, _)| app.is_addr_visible(hop.ttl(), *i))
                     .map(|(_, item)| item)
@@ -509,13 +518,23 @@ fn format_asinfo(asinfo: &AsInfo, as_mode: AsMode) -> String {
     }
 }
 
-fn format_asinfo_cell(addr: IpAddr, dns: &DnsResolver) -> String {
-    match dns.lazy_reverse_lookup_with_asinfo(addr) {
+/// Format the ASN of a `DnsEntry` for the ASN column.
+fn format_asn_cell(dns_entry: DnsEntry) -> String {
+    format_asinfo_entry(dns_entry, |asinfo| format_asinfo(asinfo, AsMode::Asn))
+}
+
+/// Format the AS registry country code of a `DnsEntry` for the AS CC column.
+fn format_as_cc_cell(dns_entry: DnsEntry) -> String {
+    format_asinfo_entry(dns_entry, |asinfo| asinfo.cc.clone())
+}
+
+fn format_asinfo_entry(dns_entry: DnsEntry, format: impl Fn(&AsInfo) -> String) -> String {
+    match dns_entry {
         DnsEntry::Resolved(Resolved::WithAsInfo(_, _, asinfo))
         | DnsEntry::NotFound(Unresolved::WithAsInfo(_, asinfo))
             if !asinfo.asn.is_empty() =>
         {
-            format_asinfo(&asinfo, AsMode::Asn)
+            format(&asinfo)
         }
         DnsEntry::Pending(_) => String::new(),
         DnsEntry::Failed(_) | DnsEntry::Timeout(_) => "?????".to_string(),
@@ -820,3 +839,37 @@ fn fmt_details_line(
         "{addr}{nat_fmt} [{index} of {count}]\n{hosts_rendered}\n{as_fmt}\n{geoip_fmt}\n{ext_fmt}"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_format_as_cc_cell() {
+        let addr = IpAddr::V4(Ipv4Addr::new(81, 0, 100, 1));
+        let asinfo = AsInfo {
+            asn: String::from("12301"),
+            prefix: String::from("81.0.100.0/22"),
+            cc: String::from("HU"),
+            registry: String::from("ripencc"),
+            allocated: String::from("1999-02-25"),
+            name: String::from("INVITECH, HU"),
+        };
+        let entry = DnsEntry::Resolved(Resolved::WithAsInfo(
+            addr,
+            vec![String::from("example.com")],
+            asinfo.clone(),
+        ));
+        assert_eq!("HU", format_as_cc_cell(entry));
+        let entry = DnsEntry::NotFound(Unresolved::WithAsInfo(addr, asinfo));
+        assert_eq!("HU", format_as_cc_cell(entry));
+    }
+
+    #[test]
+    fn test_format_as_cc_cell_without_asinfo() {
+        let addr = IpAddr::V4(Ipv4Addr::new(81, 0, 100, 1));
+        assert_eq!("", format_as_cc_cell(DnsEntry::Pending(addr)));
+        assert_eq!("?????", format_as_cc_cell(DnsEntry::Timeout(addr)));
+    }
+}
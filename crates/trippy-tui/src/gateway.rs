@@ -0,0 +1,123 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The `RTF_GATEWAY` route flag, set if the route is via a gateway.
+const RTF_GATEWAY: u32 = 0x0002;
+
+/// The default gateway of the system for the address family of `target`, if any.
+///
+/// The default gateway is read from the routing table, which is only supported on Linux.
+#[cfg(target_os = "linux")]
+pub fn default_gateway(target: IpAddr) -> Option<IpAddr> {
+    match target {
+        IpAddr::V4(_) => std::fs::read_to_string("/proc/net/route")
+            .ok()
+            .and_then(|routes| parse_ipv4_routes(&routes))
+            .map(IpAddr::V4),
+        IpAddr::V6(_) => std::fs::read_to_string("/proc/net/ipv6_route")
+            .ok()
+            .and_then(|routes| parse_ipv6_routes(&routes))
+            .map(IpAddr::V6),
+    }
+}
+
+/// The default gateway of the system for the address family of `target`, if any.
+///
+/// The default gateway is read from the routing table, which is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub const fn default_gateway(_target: IpAddr) -> Option<IpAddr> {
+    None
+}
+
+/// Find the gateway of the default route in the content of `/proc/net/route`.
+///
+/// Each line after the header describes a route as whitespace separated fields, where the
+/// destination, gateway, flags and mask are the second, third, fourth and eighth fields and all
+/// addresses are hex encoded in network byte order.
+fn parse_ipv4_routes(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|route| {
+        let fields = route.split_whitespace().collect::<Vec<_>>();
+        let (destination, gateway, flags, mask) = (
+            fields.get(1)?,
+            fields.get(2)?,
+            fields.get(3)?,
+            fields.get(7)?,
+        );
+        let flags = u32::from_str_radix(flags, 16).ok()?;
+        if *destination == "00000000" && *mask == "00000000" && flags & RTF_GATEWAY != 0 {
+            let gateway = u32::from_str_radix(gateway, 16).ok()?;
+            Some(Ipv4Addr::from(gateway.to_ne_bytes()))
+        } else {
+            None
+        }
+    })
+}
+
+/// Find the gateway of the default route in the content of `/proc/net/ipv6_route`.
+///
+/// Each line describes a route as whitespace separated fields, where the destination, destination
+/// prefix length, next hop and flags are the first, second, fifth and ninth fields and all
+/// addresses are hex encoded.
+fn parse_ipv6_routes(routes: &str) -> Option<Ipv6Addr> {
+    routes.lines().find_map(|route| {
+        let fields = route.split_whitespace().collect::<Vec<_>>();
+        let (destination, prefix_len, next_hop, flags) = (
+            fields.first()?,
+            fields.get(1)?,
+            fields.get(4)?,
+            fields.get(8)?,
+        );
+        let flags = u32::from_str_radix(flags, 16).ok()?;
+        let next_hop = u128::from_str_radix(next_hop, 16).ok()?;
+        if u128::from_str_radix(destination, 16).ok()? == 0
+            && *prefix_len == "00"
+            && next_hop != 0
+            && flags & RTF_GATEWAY != 0
+        {
+            Some(Ipv6Addr::from(next_hop))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    const IPV4_ROUTES: &str = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t0000A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+eth0\t00000000\t0100A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+";
+
+    const IPV6_ROUTES: &str = "\
+20010db8000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000002 00000000 00450003     eth0
+";
+
+    #[test]
+    #[cfg(target_endian = "little")]
+    fn test_parse_ipv4_routes() {
+        assert_eq!(
+            Some(Ipv4Addr::new(192, 168, 0, 1)),
+            parse_ipv4_routes(IPV4_ROUTES)
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6_routes() {
+        assert_eq!(
+            Some(Ipv6Addr::from_str("fe80::1").unwrap()),
+            parse_ipv6_routes(IPV6_ROUTES)
+        );
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n"; "header only")]
+    #[test_case("Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\neth0\t0000A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\n"; "no default route")]
+    fn test_parse_ipv4_routes_no_gateway(routes: &str) {
+        assert_eq!(None, parse_ipv4_routes(routes));
+    }
+}
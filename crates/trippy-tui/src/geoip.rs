@@ -1,6 +1,6 @@
 use anyhow::Context;
 use itertools::Itertools;
-use maxminddb::Reader;
+use maxminddb::{Mmap, Reader};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::net::IpAddr;
@@ -11,6 +11,12 @@ use std::sync::Arc;
 // use xdb::{search_by_ip, searcher_init, searcher_load};
 use ip2region::{CachePolicy, Searcher};
 
+mod schema;
+mod speed;
+
+pub use schema::GeoIpSchema;
+pub use speed::is_impossible_speed;
+
 #[derive(Debug, Clone, Default)]
 pub struct GeoIpCity {
     latitude: Option<f64>,
@@ -427,18 +433,18 @@ mod ipinfo {
             };
         }
 
-        fn geoip_lookup(bytes: &'static [u8]) -> GeoIpLookup {
+        fn geoip_lookup(bytes: &'static [u8]) -> GeoIpLookup<Vec<u8>> {
             GeoIpLookup {
                 reader: Some(Reader::from_source(bytes.to_vec()).unwrap()),
                 cache: Cache::default(),
                 locale: String::from("en"),
-                xdb: false,
                 v4_searcher: None,
                 v6_searcher: None,
+                schema: None,
             }
         }
 
-        fn lookup(geoip_lookup: &GeoIpLookup, addr: &str) -> GeoIpCity {
+        fn lookup<S: AsRef<[u8]>>(geoip_lookup: &GeoIpLookup<S>, addr: &str) -> GeoIpCity {
             geoip_lookup
                 .lookup(addr.parse().unwrap())
                 .unwrap()
@@ -477,6 +483,23 @@ mod ipinfo {
             assert_eq!(Some("Oceania"), geo.continent.as_deref());
         }
 
+        #[test]
+        fn test_ipinfo_country_asn_sample_mmdb_from_file() {
+            let path = concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/resources/ipinfo/ip_country_asn_sample.mmdb"
+            );
+            let geoip_lookup = GeoIpLookup::from_file(path, String::from("en")).unwrap();
+            let geo = lookup(&geoip_lookup, "1.0.0.0");
+            assert_eq!(None, geo.latitude);
+            assert_eq!(None, geo.longitude);
+            assert_eq!(None, geo.accuracy_radius);
+            assert_eq!(None, geo.city.as_deref());
+            assert_eq!(Some("Australia"), geo.country.as_deref());
+            assert_eq!(Some("AU"), geo.country_code.as_deref());
+            assert_eq!(Some("Oceania"), geo.continent.as_deref());
+        }
+
         #[test]
         fn test_ipinfo_extended_location_sample_mmdb() {
             let geoip_lookup =
@@ -637,13 +660,15 @@ const FALLBACK_LOCALE: &str = "en";
 type Cache = RefCell<HashMap<IpAddr, Option<Rc<GeoIpCity>>>>;
 
 /// Lookup `GeoIpCity` data form an `IpAddr`.
-pub struct GeoIpLookup {
-    reader: Option<Reader<Vec<u8>>>,
+///
+/// `MaxMind` DB files are memory-mapped rather than read into memory.
+pub struct GeoIpLookup<S: AsRef<[u8]> = Mmap> {
+    reader: Option<Reader<S>>,
     cache: Cache,
     locale: String,
-    xdb: bool,
     v4_searcher: Option<Arc<Searcher>>,
     v6_searcher: Option<Arc<Searcher>>,
+    schema: Option<GeoIpSchema>,
 }
 
 impl GeoIpLookup {
@@ -655,23 +680,33 @@ impl GeoIpLookup {
             reader: None,
             cache: RefCell::new(HashMap::new()),
             locale,
-            xdb: true,
             v4_searcher: Some(ipv4_searcher),
             v6_searcher: Some(ipv6_searcher),
+            schema: None,
         })
     }
 
-    /// Create a new `GeoIpLookup` from a `MaxMind` DB file.
+    /// Create a new `GeoIpLookup` from a memory-mapped `MaxMind` DB file.
+    ///
+    /// The file must not be modified or truncated whilst the `GeoIpLookup` exists.
     pub fn from_file<P: AsRef<Path>>(path: P, locale: String) -> anyhow::Result<Self> {
-        let reader =
-            Reader::open_readfile(path.as_ref()).context(format!("{}", path.as_ref().display()))?;
         Ok(Self {
-            reader: Some(reader),
+            reader: Some(open_mmdb(path)?),
             cache: RefCell::new(HashMap::new()),
             locale,
-            xdb: false,
             v4_searcher: None,
             v6_searcher: None,
+            schema: None,
+        })
+    }
+
+    /// Fall back to a memory-mapped `MaxMind` DB file for any `IpAddr` without an `xdb` entry.
+    ///
+    /// The file must not be modified or truncated whilst the `GeoIpLookup` exists.
+    pub fn with_mmdb_file<P: AsRef<Path>>(self, path: P) -> anyhow::Result<Self> {
+        Ok(Self {
+            reader: Some(open_mmdb(path)?),
+            ..self
         })
     }
 
@@ -681,115 +716,315 @@ impl GeoIpLookup {
             reader: None,
             cache: RefCell::new(HashMap::new()),
             locale: FALLBACK_LOCALE.to_string(),
-            xdb: false,
             v4_searcher: None,
             v6_searcher: None,
+            schema: None,
+        }
+    }
+}
+
+impl<S: AsRef<[u8]>> GeoIpLookup<S> {
+    /// Decode `MaxMind` DB records using a custom `GeoIpSchema`.
+    ///
+    /// The schema takes precedence over the database type detected from the DB metadata.
+    #[must_use]
+    pub fn with_schema(self, schema: GeoIpSchema) -> Self {
+        Self {
+            schema: Some(schema),
+            ..self
         }
     }
 
     /// Lookup an `GeoIpCity` for an `IpAddr`.
     ///
+    /// An IPv4-mapped IPv6 address is looked up as the IPv4 address it maps.  The `xdb` database
+    /// for the address family is searched first, falling back to the `MaxMind` DB, if any, where
+    /// it has no entry.  As `xdb` entries hold no coordinates these are taken from the `MaxMind`
+    /// DB entry, if any.
+    ///
     /// If an entry is found it is cached and returned, otherwise None is returned.
     pub fn lookup(&self, addr: IpAddr) -> anyhow::Result<Option<Rc<GeoIpCity>>> {
         if let Some(geo) = self.cache.borrow().get(&addr) {
             return Ok(geo.clone());
         }
-        if self.xdb {
-            if addr.is_ipv4() {
-                if let IpAddr::V4(ip) = addr {
-                    if let Some(ipv4_searcher) = &self.v4_searcher {
-                        if let Ok(ips) = ipv4_searcher.search(ip) {
-                            let parts = ips.split('|').collect::<Vec<&str>>();
-                            let city_data = Some(GeoIpCity {
-                                latitude: Some(0.0),
-                                longitude: Some(0.0),
-                                accuracy_radius: Some(0),
-                                city: Some(parts[0].to_string()),
-                                subdivision: Some(parts[2].to_string()),
-                                subdivision_code: Some(parts[2].to_string()),
-                                country: Some(parts[3].to_string()),
-                                country_code: Some(parts[3].to_string()),
-                                continent: Some(parts[4].to_string()),
-                                raw: Some(ips.clone()),
-                            });
-                            let cached = city_data.map(Rc::new);
-                            self.cache.borrow_mut().insert(addr, cached.clone());
-                            return Ok(cached);
-                        }
-                    }
-                }
-            } else {
-                if let IpAddr::V6(ip) = addr {
-                    if let Some(ipv6_searcher) = &self.v6_searcher {
-                        if let Ok(ips) = ipv6_searcher.search(ip) {
-                            let parts = ips.split('|').collect::<Vec<&str>>();
-                            let city_data = Some(GeoIpCity {
-                                latitude: Some(0.0),
-                                longitude: Some(0.0),
-                                accuracy_radius: Some(0),
-                                city: Some(parts[0].to_string()),
-                                subdivision: Some(parts[2].to_string()),
-                                subdivision_code: Some(parts[2].to_string()),
-                                country: Some(parts[3].to_string()),
-                                country_code: Some(parts[3].to_string()),
-                                continent: Some(parts[4].to_string()),
-                                raw: Some(ips.clone()),
-                            });
-                            let cached = city_data.map(Rc::new);
-                            self.cache.borrow_mut().insert(addr, cached.clone());
-                            return Ok(cached);
-                        }
-                    }
-                }
+        let canonical = addr.to_canonical();
+        let city_data = match self.lookup_xdb(canonical) {
+            Some(city) => Some(self.with_mmdb_coordinates(city, canonical)?),
+            None => self.lookup_mmdb(canonical)?,
+        };
+        let cached = city_data.map(Rc::new);
+        self.cache.borrow_mut().insert(addr, cached.clone());
+        Ok(cached)
+    }
+
+    /// Lookup `addr` in the `xdb` database of its address family, if any.
+    fn lookup_xdb(&self, addr: IpAddr) -> Option<GeoIpCity> {
+        let region = match addr {
+            IpAddr::V4(ip) => self.v4_searcher.as_ref()?.search(ip),
+            IpAddr::V6(ip) => self.v6_searcher.as_ref()?.search(ip),
+        };
+        region.ok().and_then(|region| xdb_city(&region))
+    }
+
+    /// Fill the coordinates of an `xdb` `city` for `addr` from the `MaxMind` DB, if any.
+    fn with_mmdb_coordinates(&self, city: GeoIpCity, addr: IpAddr) -> anyhow::Result<GeoIpCity> {
+        Ok(match self.lookup_mmdb(addr)? {
+            Some(mmdb_city) => GeoIpCity {
+                latitude: mmdb_city.latitude,
+                longitude: mmdb_city.longitude,
+                accuracy_radius: mmdb_city.accuracy_radius,
+                ..city
+            },
+            None => city,
+        })
+    }
+
+    /// Lookup `addr` in the `MaxMind` DB, if any.
+    fn lookup_mmdb(&self, addr: IpAddr) -> anyhow::Result<Option<GeoIpCity>> {
+        let Some(reader) = &self.reader else {
+            return Ok(None);
+        };
+        let lookup_result = reader.lookup(addr)?;
+        Ok(if let Some(schema) = &self.schema {
+            lookup_result
+                .decode::<serde_json::Value>()?
+                .map(|record| schema.apply(&record))
+        } else {
+            match ipinfo::DatabaseFormat::try_from(reader.metadata.database_type.as_ref()).ok() {
+                Some(ipinfo::DatabaseFormat::Legacy) => lookup_result
+                    .decode::<ipinfo::IpInfoGeoIpLegacy>()?
+                    .map(GeoIpCity::from),
+                Some(ipinfo::DatabaseFormat::Current) => lookup_result
+                    .decode::<ipinfo::IpInfoGeoIp>()?
+                    .map(GeoIpCity::from),
+                None => lookup_result
+                    .decode::<maxminddb::geoip2::City<'_>>()?
+                    .map(|city| GeoIpCity::from((city, self.locale.as_ref()))),
             }
-        } else if let Some(reader) = &self.reader {
-            let lookup_result = reader.lookup(addr)?;
-            let city_data =
-                match ipinfo::DatabaseFormat::try_from(reader.metadata.database_type.as_ref()).ok()
-                {
-                    Some(ipinfo::DatabaseFormat::Legacy) => lookup_result
-                        .decode::<ipinfo::IpInfoGeoIpLegacy>()?
-                        .map(GeoIpCity::from),
-                    Some(ipinfo::DatabaseFormat::Current) => lookup_result
-                        .decode::<ipinfo::IpInfoGeoIp>()?
-                        .map(GeoIpCity::from),
-                    None => lookup_result
-                        .decode::<maxminddb::geoip2::City<'_>>()?
-                        .map(|city| GeoIpCity::from((city, self.locale.as_ref()))),
-                };
-            let cached = city_data.map(Rc::new);
-            self.cache.borrow_mut().insert(addr, cached.clone());
-            return Ok(cached);
-        }
-        Ok(None)
+        })
+    }
+}
+
+/// The `GeoIpCity` for an `xdb` region record, if it is not empty.
+///
+/// The `xdb` databases hold no coordinates and so these are always unknown.
+fn xdb_city(region: &str) -> Option<GeoIpCity> {
+    if region.is_empty() {
+        return None;
     }
+    let parts = region.split('|').collect::<Vec<&str>>();
+    let part = |i: usize| parts.get(i).map(ToString::to_string);
+    Some(GeoIpCity {
+        latitude: None,
+        longitude: None,
+        accuracy_radius: None,
+        city: part(0),
+        subdivision: part(2),
+        subdivision_code: part(2),
+        country: part(3),
+        country_code: part(3),
+        continent: part(4),
+        raw: Some(region.to_string()),
+    })
 }
 
+/// Memory-map the `MaxMind` DB file at `path`.
+fn open_mmdb<P: AsRef<Path>>(path: P) -> anyhow::Result<Reader<Mmap>> {
+    // SAFETY: the database file is treated as read-only for the lifetime of the trace.
+    #[expect(unsafe_code)]
+    let reader = unsafe { Reader::open_mmap(path.as_ref()) }
+        .context(format!("{}", path.as_ref().display()))?;
+    Ok(reader)
+}
+
+/// The name for `locale`, falling back to the `FALLBACK_LOCALE` name and then to any available
+/// name.
 fn localized_name(names: &maxminddb::geoip2::Names<'_>, locale: &str) -> Option<String> {
     lookup_locale(names, locale)
         .or_else(|| lookup_locale(names, FALLBACK_LOCALE))
+        .or_else(|| any_name(names))
         .map(ToString::to_string)
 }
 
 /// Map a Trippy locale code to the closest `maxminddb` locale field.
 ///
+/// Any region subtag is ignored, such that `de-DE` and `de_AT` map as `de` does.
+///
 /// - `pt*` (e.g. `pt`, `pt-BR`, `pt-PT`) use `brazilian_portuguese`
 /// - `zh*` (e.g. `zh`, `zh-TW`) use `simplified_chinese`
 /// - Other languages that are supported map directly (`en`, `de`, `es`, `fr`, `ja`, `ru`).
 fn lookup_locale<'a>(names: &maxminddb::geoip2::Names<'a>, code: &str) -> Option<&'a str> {
-    if code.starts_with("pt") {
-        names.brazilian_portuguese
-    } else if code.starts_with("zh") {
-        names.simplified_chinese
-    } else {
-        match code {
-            "de" => names.german,
-            "en" => names.english,
-            "es" => names.spanish,
-            "fr" => names.french,
-            "ja" => names.japanese,
-            "ru" => names.russian,
-            _ => None,
-        }
+    let language = code.split(['-', '_']).next().unwrap_or(code);
+    match language {
+        "de" => names.german,
+        "en" => names.english,
+        "es" => names.spanish,
+        "fr" => names.french,
+        "ja" => names.japanese,
+        "pt" => names.brazilian_portuguese,
+        "ru" => names.russian,
+        "zh" => names.simplified_chinese,
+        _ => None,
+    }
+}
+
+/// The first available name in any locale.
+fn any_name<'a>(names: &maxminddb::geoip2::Names<'a>) -> Option<&'a str> {
+    [
+        names.english,
+        names.german,
+        names.spanish,
+        names.french,
+        names.japanese,
+        names.brazilian_portuguese,
+        names.russian,
+        names.simplified_chinese,
+    ]
+    .into_iter()
+    .flatten()
+    .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maxminddb::geoip2::Names;
+    use test_case::test_case;
+
+    const XDB_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../assets/xdb");
+    const MMDB_FILE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/resources/ipinfo/ip_geolocation_extended_sample.mmdb"
+    );
+
+    const NAMES: Names<'static> = Names {
+        german: Some("Deutschland"),
+        english: Some("Germany"),
+        spanish: None,
+        french: Some("Allemagne"),
+        japanese: None,
+        brazilian_portuguese: Some("Alemanha"),
+        russian: None,
+        simplified_chinese: Some("德国"),
+    };
+
+    #[test_case("fr", Some("Allemagne"); "french")]
+    #[test_case("de", Some("Deutschland"); "german")]
+    #[test_case("de-DE", Some("Deutschland"); "german with region")]
+    #[test_case("de_AT", Some("Deutschland"); "german with underscore region")]
+    #[test_case("pt-PT", Some("Alemanha"); "portuguese")]
+    #[test_case("zh-TW", Some("德国"); "chinese")]
+    #[test_case("es", Some("Germany"); "fallback to english")]
+    #[test_case("xx", Some("Germany"); "unknown locale")]
+    fn test_localized_name(locale: &str, expected: Option<&str>) {
+        assert_eq!(
+            expected.map(ToString::to_string),
+            localized_name(&NAMES, locale)
+        );
+    }
+
+    #[test]
+    fn test_localized_name_fallback_to_any() {
+        let names = Names {
+            japanese: Some("ドイツ"),
+            ..Names::default()
+        };
+        assert_eq!(Some(String::from("ドイツ")), localized_name(&names, "fr"));
+        assert_eq!(None, localized_name(&Names::default(), "fr"));
+    }
+
+    fn xdb_searcher(file_name: &str) -> Arc<Searcher> {
+        let bytes = std::fs::read(format!("{XDB_DIR}/{file_name}")).unwrap();
+        Arc::new(Searcher::from_bytes(bytes, CachePolicy::VectorIndex).unwrap())
+    }
+
+    fn lookup_raw(geoip_lookup: &GeoIpLookup, addr: &str) -> Option<String> {
+        geoip_lookup
+            .lookup(addr.parse().unwrap())
+            .unwrap()
+            .and_then(|geo| geo.raw.clone())
+    }
+
+    #[test]
+    fn test_xdb_ipv4_mapped_ipv6() {
+        let v4 = std::fs::read(format!("{XDB_DIR}/ip2region_v4.xdb")).unwrap();
+        let v6 = std::fs::read(format!("{XDB_DIR}/ip2region_v6.xdb")).unwrap();
+        let geoip_lookup = GeoIpLookup::load_xdb(v4, v6, String::from("en")).unwrap();
+        let expected = Some(String::from("Australia|Queensland|0|0|AU"));
+        assert_eq!(expected, lookup_raw(&geoip_lookup, "1.0.0.0"));
+        assert_eq!(expected, lookup_raw(&geoip_lookup, "::ffff:1.0.0.0"));
+        assert_eq!(
+            Some(String::from("United States|Florida|Miami|Google LLC|US")),
+            lookup_raw(&geoip_lookup, "2001:4860:4860::8888")
+        );
+    }
+
+    #[test]
+    fn test_xdb_fallback_to_mmdb() {
+        let geoip_lookup = GeoIpLookup {
+            v4_searcher: None,
+            v6_searcher: Some(xdb_searcher("ip2region_v6.xdb")),
+            ..GeoIpLookup::from_file(MMDB_FILE, String::from("en")).unwrap()
+        };
+        let geo = geoip_lookup
+            .lookup("::ffff:1.0.0.0".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("Sydney"), geo.city.as_deref());
+        assert!(
+            geoip_lookup
+                .lookup("::1".parse().unwrap())
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_xdb_coordinates_from_mmdb() {
+        let xdb_only = GeoIpLookup {
+            v4_searcher: Some(xdb_searcher("ip2region_v4.xdb")),
+            ..GeoIpLookup::empty()
+        };
+        let geo = xdb_only
+            .lookup("1.0.0.0".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(None, geo.coordinates());
+        let with_mmdb = GeoIpLookup {
+            v4_searcher: Some(xdb_searcher("ip2region_v4.xdb")),
+            ..GeoIpLookup::from_file(MMDB_FILE, String::from("en")).unwrap()
+        };
+        let geo = with_mmdb
+            .lookup("1.0.0.0".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some("Australia|Queensland|0|0|AU"), geo.raw.as_deref());
+        assert_eq!(Some((-33.86785, 151.20732, 5000)), geo.coordinates());
+    }
+
+    #[test]
+    fn test_mmdb_ipv4_mapped_ipv6() {
+        let geoip_lookup = GeoIpLookup::from_file(MMDB_FILE, String::from("en")).unwrap();
+        let geo = geoip_lookup
+            .lookup("::ffff:1.0.0.0".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(-33.86785), geo.latitude);
+        assert_eq!(Some(151.20732), geo.longitude);
+    }
+
+    #[test_case("", None; "empty")]
+    #[test_case("Australia|Queensland|0|0|AU", Some(("Australia", "0", "AU")); "full")]
+    #[test_case("Australia", Some(("Australia", "", "")); "truncated")]
+    fn test_xdb_city(region: &str, expected: Option<(&str, &str, &str)>) {
+        let city = xdb_city(region);
+        assert_eq!(
+            expected,
+            city.as_ref().map(|city| (
+                city.city.as_deref().unwrap_or_default(),
+                city.country.as_deref().unwrap_or_default(),
+                city.continent.as_deref().unwrap_or_default(),
+            ))
+        );
     }
 }
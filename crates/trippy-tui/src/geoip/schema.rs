@@ -0,0 +1,238 @@
+use crate::geoip::GeoIpCity;
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A mapping from the fields of a custom mmdb database record to `GeoIpCity` fields.
+///
+/// Each field is a JSON Pointer (see <https://datatracker.ietf.org/doc/html/rfc6901>) into the
+/// record, such as `/location/lat`.  Fields which are not mapped, or which are absent from a
+/// record, are left empty.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GeoIpSchema {
+    latitude: Option<String>,
+    longitude: Option<String>,
+    accuracy_radius: Option<String>,
+    city: Option<String>,
+    subdivision: Option<String>,
+    subdivision_code: Option<String>,
+    country: Option<String>,
+    country_code: Option<String>,
+    continent: Option<String>,
+}
+
+impl GeoIpSchema {
+    /// Load a `GeoIpSchema` from a JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let file = std::fs::read_to_string(path.as_ref())
+            .context(format!("{}", path.as_ref().display()))?;
+        Self::from_json(&file).context(format!("{}", path.as_ref().display()))
+    }
+
+    /// Parse a `GeoIpSchema` from a JSON string.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let schema: Self = serde_json::from_str(json)?;
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    /// Map a decoded mmdb database record to a `GeoIpCity`.
+    pub fn apply(&self, record: &Value) -> GeoIpCity {
+        let field = |pointer: &Option<String>| {
+            pointer
+                .as_deref()
+                .and_then(|pointer| record.pointer(pointer))
+        };
+        GeoIpCity {
+            latitude: field(&self.latitude).and_then(as_f64),
+            longitude: field(&self.longitude).and_then(as_f64),
+            accuracy_radius: field(&self.accuracy_radius).and_then(as_u16),
+            city: field(&self.city).and_then(as_string),
+            subdivision: field(&self.subdivision).and_then(as_string),
+            subdivision_code: field(&self.subdivision_code).and_then(as_string),
+            country: field(&self.country).and_then(as_string),
+            country_code: field(&self.country_code).and_then(as_string),
+            continent: field(&self.continent).and_then(as_string),
+            raw: None,
+        }
+    }
+
+    /// Validate that all fields are JSON Pointers.
+    fn validate(&self) -> anyhow::Result<()> {
+        [
+            ("latitude", &self.latitude),
+            ("longitude", &self.longitude),
+            ("accuracy-radius", &self.accuracy_radius),
+            ("city", &self.city),
+            ("subdivision", &self.subdivision),
+            ("subdivision-code", &self.subdivision_code),
+            ("country", &self.country),
+            ("country-code", &self.country_code),
+            ("continent", &self.continent),
+        ]
+        .into_iter()
+        .filter_map(|(name, pointer)| pointer.as_deref().map(|pointer| (name, pointer)))
+        .find(|(_, pointer)| !pointer.is_empty() && !pointer.starts_with('/'))
+        .map_or(Ok(()), |(name, pointer)| {
+            Err(anyhow!(
+                "invalid schema field {name}: {pointer} is not a JSON Pointer"
+            ))
+        })
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => f64::from_str(s).ok(),
+        _ => None,
+    }
+}
+
+fn as_u16(value: &Value) -> Option<u16> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| u16::try_from(n).ok()),
+        Value::String(s) => u16::from_str(s).ok(),
+        _ => None,
+    }
+}
+
+fn as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geoip::{Cache, GeoIpLookup};
+    use maxminddb::Reader;
+
+    const SCHEMA: &str = r#"
+        {
+            "latitude": "/location/lat",
+            "longitude": "/location/lon",
+            "accuracy-radius": "/location/radius_km",
+            "city": "/place/city/name",
+            "subdivision": "/place/region/name",
+            "subdivision-code": "/place/region/code",
+            "country": "/place/country/name",
+            "country-code": "/place/country/iso",
+            "continent": "/place/continent"
+        }
+        "#;
+
+    #[test]
+    fn test_custom_record() {
+        let schema = GeoIpSchema::from_json(SCHEMA).unwrap();
+        let record: Value = serde_json::from_str(
+            r#"
+            {
+                "location": { "lat": -33.86785, "lon": "151.20732", "radius_km": 500 },
+                "place": {
+                    "city": { "name": "Sydney" },
+                    "region": { "name": "New South Wales", "code": "NSW" },
+                    "country": { "name": "Australia", "iso": "AU" },
+                    "continent": "Oceania"
+                }
+            }
+            "#,
+        )
+        .unwrap();
+        let geo = schema.apply(&record);
+        assert_eq!(Some(-33.86785), geo.latitude);
+        assert_eq!(Some(151.20732), geo.longitude);
+        assert_eq!(Some(500), geo.accuracy_radius);
+        assert_eq!(Some("Sydney"), geo.city.as_deref());
+        assert_eq!(Some("New South Wales"), geo.subdivision.as_deref());
+        assert_eq!(Some("NSW"), geo.subdivision_code.as_deref());
+        assert_eq!(Some("Australia"), geo.country.as_deref());
+        assert_eq!(Some("AU"), geo.country_code.as_deref());
+        assert_eq!(Some("Oceania"), geo.continent.as_deref());
+    }
+
+    #[test]
+    fn test_custom_record_missing_and_invalid_fields() {
+        let schema = GeoIpSchema::from_json(SCHEMA).unwrap();
+        let record: Value = serde_json::from_str(
+            r#"
+            {
+                "location": { "lat": "north", "radius_km": 100000 },
+                "place": { "city": { "name": "" }, "country": { "iso": "AU" } }
+            }
+            "#,
+        )
+        .unwrap();
+        let geo = schema.apply(&record);
+        assert_eq!(None, geo.latitude);
+        assert_eq!(None, geo.longitude);
+        assert_eq!(None, geo.accuracy_radius);
+        assert_eq!(None, geo.city.as_deref());
+        assert_eq!(None, geo.subdivision.as_deref());
+        assert_eq!(None, geo.country.as_deref());
+        assert_eq!(Some("AU"), geo.country_code.as_deref());
+        assert_eq!(None, geo.continent.as_deref());
+    }
+
+    #[test]
+    fn test_empty_schema() {
+        let schema = GeoIpSchema::from_json("{}").unwrap();
+        assert_eq!(GeoIpSchema::default(), schema);
+        let geo = schema.apply(&serde_json::from_str(r#"{"city": "Sydney"}"#).unwrap());
+        assert_eq!(None, geo.city.as_deref());
+    }
+
+    #[test]
+    fn test_unknown_schema_field() {
+        let err = GeoIpSchema::from_json(r#"{"town": "/city"}"#).unwrap_err();
+        assert!(err.to_string().starts_with("unknown field `town`"));
+    }
+
+    #[test]
+    fn test_invalid_schema_pointer() {
+        let err = GeoIpSchema::from_json(r#"{"city": "place.city"}"#).unwrap_err();
+        assert_eq!(
+            "invalid schema field city: place.city is not a JSON Pointer",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_schema_lookup_mmdb() {
+        let schema = GeoIpSchema::from_json(
+            r#"
+            {
+                "country": "/country",
+                "country-code": "/country_code",
+                "continent": "/continent_code"
+            }
+            "#,
+        )
+        .unwrap();
+        let bytes = include_bytes!("../../tests/resources/ipinfo/ipinfo_lite_sample.mmdb");
+        let geoip_lookup = GeoIpLookup {
+            reader: Some(Reader::from_source(bytes.to_vec()).unwrap()),
+            cache: Cache::default(),
+            locale: String::from("en"),
+            v4_searcher: None,
+            v6_searcher: None,
+            schema: None,
+        }
+        .with_schema(schema);
+        let geo = geoip_lookup
+            .lookup("1.0.0.0".parse().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(None, geo.latitude);
+        assert_eq!(None, geo.city.as_deref());
+        assert_eq!(Some("Australia"), geo.country.as_deref());
+        assert_eq!(Some("AU"), geo.country_code.as_deref());
+        assert_eq!(Some("OC"), geo.continent.as_deref());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::geoip::GeoIpCity;
+
+/// The mean radius of the Earth in kilometres.
+const EARTH_RADIUS_KM: f64 = 6371_f64;
+
+/// The propagation speed of light in optical fibre (~2/3 c) in kilometres per second.
+pub const FIBRE_SPEED_KM_PER_SEC: f64 = 199_862_f64;
+
+/// The great-circle distance in kilometres between two `(latitude, longitude)` coordinates.
+pub fn haversine_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, long1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, long2) = (to.0.to_radians(), to.1.to_radians());
+    let half_lat = ((lat2 - lat1) / 2_f64).sin();
+    let half_long = ((long2 - long1) / 2_f64).sin();
+    let a = (lat1.cos() * lat2.cos()).mul_add(half_long.powi(2), half_lat.powi(2));
+    2_f64 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// The propagation speed, in kilometres per second, implied by a probe covering `distance_km`
+/// with an increase in round trip time of `rtt_delta_ms`.
+///
+/// The distance is covered twice within the round trip.  An increase of zero or less implies an
+/// infinite speed.
+pub fn implied_speed_km_per_sec(distance_km: f64, rtt_delta_ms: f64) -> f64 {
+    if rtt_delta_ms <= 0_f64 {
+        f64::INFINITY
+    } else {
+        2_f64 * distance_km / (rtt_delta_ms / 1000_f64)
+    }
+}
+
+/// Would covering `distance_km` with an increase in round trip time of `rtt_delta_ms` require
+/// travel faster than light in fibre?
+pub fn is_impossible_speed(distance_km: f64, rtt_delta_ms: f64) -> bool {
+    distance_km > 0_f64
+        && implied_speed_km_per_sec(distance_km, rtt_delta_ms) > FIBRE_SPEED_KM_PER_SEC
+}
+
+impl GeoIpCity {
+    /// The least distance in kilometres between this location and `other`.
+    ///
+    /// The accuracy radius of both locations is taken into account such that locations whose
+    /// areas overlap are a distance of zero apart.
+    pub fn min_distance_km(&self, other: &Self) -> Option<f64> {
+        let (lat1, long1, radius1) = self.coordinates()?;
+        let (lat2, long2, radius2) = other.coordinates()?;
+        let distance = haversine_km((lat1, long1), (lat2, long2));
+        Some((distance - f64::from(radius1) - f64::from(radius2)).max(0_f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn city(latitude: f64, longitude: f64, accuracy_radius: u16) -> GeoIpCity {
+        GeoIpCity {
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            accuracy_radius: Some(accuracy_radius),
+            ..GeoIpCity::default()
+        }
+    }
+
+    #[test]
+    fn test_haversine_km() {
+        let london = (51.5074, -0.1278);
+        let new_york = (40.7128, -74.0060);
+        assert!((haversine_km(london, new_york) - 5570_f64).abs() < 10_f64);
+        assert!(haversine_km(london, london).abs() < f64::EPSILON);
+    }
+
+    #[test_case(10_000_f64, 1_f64, true; "10000km in 1ms")]
+    #[test_case(10_000_f64, 50_f64, true; "10000km in 50ms")]
+    #[test_case(10_000_f64, 120_f64, false; "10000km in 120ms")]
+    #[test_case(100_f64, 0_f64, true; "100km in no time")]
+    #[test_case(100_f64, -1_f64, true; "100km in negative time")]
+    #[test_case(0_f64, -1_f64, false; "no distance")]
+    fn test_is_impossible_speed(distance_km: f64, rtt_delta_ms: f64, expected: bool) {
+        assert_eq!(expected, is_impossible_speed(distance_km, rtt_delta_ms));
+    }
+
+    #[test]
+    fn test_implied_speed_km_per_sec() {
+        assert!((implied_speed_km_per_sec(10_000_f64, 1_f64) - 2e7).abs() < 1_f64);
+        assert!(implied_speed_km_per_sec(10_000_f64, 0_f64).is_infinite());
+    }
+
+    #[test]
+    fn test_min_distance_km() {
+        let london = city(51.5074, -0.1278, 50);
+        let new_york = city(40.7128, -74.0060, 100);
+        let distance = london.min_distance_km(&new_york).unwrap();
+        assert!((distance - 5420_f64).abs() < 10_f64);
+        assert_eq!(Some(0_f64), london.min_distance_km(&city(51.6, -0.2, 20)));
+        assert_eq!(None, london.min_distance_km(&GeoIpCity::default()));
+    }
+}
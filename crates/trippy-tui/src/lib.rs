@@ -3,7 +3,7 @@
     clippy::cast_sign_loss,
     clippy::struct_field_names
 )]
-#![forbid(unsafe_code)]
+#![deny(unsafe_code)]
 
 use crate::config::TrippyAction;
 use clap::Parser;
@@ -12,22 +12,43 @@ use std::process;
 use trippy_privilege::Privilege;
 
 mod app;
+mod concurrency;
 mod config;
+mod diagnostics;
 mod frontend;
+mod gateway;
 mod geoip;
 mod locale;
 mod print;
 mod report;
+mod retry;
 pub mod route_verify;
 mod util;
 
+pub use report::formatter::{BUILTIN_FORMATTERS, Formatters, ReportData, ReportFormatter};
+
 /// Run the Trippy application.
 pub fn trippy() -> anyhow::Result<()> {
+    trippy_with_formatters(&Formatters::default())
+}
+
+/// Run the Trippy application with additional report formatters.
+///
+/// The registered formatters may be selected by name with `--mode format --format <name>`.
+pub fn trippy_with_formatters(formatters: &Formatters) -> anyhow::Result<()> {
     let args = Args::parse();
     let privilege = Privilege::acquire_privileges()?;
     let pid = u16::try_from(process::id() % u32::from(u16::MAX))?;
     match TrippyAction::from(args, &privilege, pid)? {
-        TrippyAction::Trippy(cfg) => app::run_trippy(&cfg, pid)?,
+        TrippyAction::Trippy(cfg) => {
+            if let Err(err) = app::run_trippy(&cfg, pid, formatters) {
+                if let Some(code) = report::exit_code(&err) {
+                    eprintln!("Error: {err}");
+                    process::exit(code);
+                }
+                return Err(err);
+            }
+        }
         TrippyAction::PrintTuiThemeItems => print::print_tui_theme_items(),
         TrippyAction::PrintTuiBindingCommands => print::print_tui_binding_commands(),
         TrippyAction::PrintConfigTemplate => print::print_config_template(),
@@ -1,26 +1,327 @@
-use anyhow::anyhow;
+use crate::report::redact::Redaction;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
 use trippy_core::State;
 use trippy_core::Tracer;
 
+pub mod classic;
 pub mod csv;
 pub mod dot;
 pub mod flows;
+pub mod formatter;
+pub mod influx;
 pub mod json;
+pub mod log_table;
+pub mod map;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod redact;
 pub mod silent;
+pub mod sparkline;
 pub mod stream;
 pub mod table;
 mod types;
 
-/// Block until trace data for round `round` is available.
-fn wait_for_round(trace_data: &Tracer, report_cycles: usize) -> anyhow::Result<State> {
-    let mut trace = trace_data.snapshot();
-    while trace.round(State::default_flow_id()).is_none()
-        || trace.round(State::default_flow_id()) < Some(report_cycles - 1)
-    {
-        trace = trace_data.snapshot();
-        if let Some(err) = trace.error() {
-            return Err(anyhow!("error: {err}"));
+#[cfg(test)]
+mod testing;
+
+/// The process exit code used when a report is aborted as no hops responded.
+pub const NO_RESPONSE_EXIT_CODE: i32 = 3;
+
+/// No hops responded to any probe within the given number of rounds.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NoResponseError(pub usize);
+
+impl Display for NoResponseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no hops responded after {} rounds", self.0)
+    }
+}
+
+impl std::error::Error for NoResponseError {}
+
+/// The error raised when a report is aborted as the tracer failed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TraceError {
+    /// The error message of the tracer.
+    pub message: String,
+    /// Is the error transient, such that retrying the trace may succeed?
+    pub transient: bool,
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error: {}", self.message)
+    }
+}
+
+impl std::error::Error for TraceError {}
+
+/// The minimum number of samples required from every responding hop before reporting.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MinSamples {
+    /// The number of samples required from each responding hop.
+    pub samples: usize,
+    /// The maximum time to wait for the samples.
+    pub timeout: Duration,
+}
+
+impl MinSamples {
+    pub const fn new(samples: usize, timeout: Duration) -> Self {
+        Self { samples, timeout }
+    }
+}
+
+/// The options common to all report modes.
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// The number of rounds to run before reporting.
+    pub report_cycles: usize,
+    /// Abort the report if no hops have responded after this many rounds.
+    pub fail_fast: Option<usize>,
+    /// Continue to run, beyond `report_cycles`, until every responding hop has this many samples.
+    pub min_samples: Option<MinSamples>,
+    /// Show the trace start and end times.
+    pub timestamps: bool,
+    /// Show the DSCP of the latest response from each hop.
+    pub response_dscp: bool,
+    /// Show the time to the first response from each hop.
+    pub time_to_first: bool,
+    /// Show the configuration of the trace.
+    pub config: bool,
+    /// The redaction and shortening of resolved hostnames.
+    pub redaction: Redaction,
+}
+
+/// Block until trace data for round `report_cycles` is available.
+///
+/// If `fail_fast` is set then abort with a `NoResponseError` if no hops have responded after
+/// that many rounds.
+///
+/// If `min_samples` is set then continue to wait, beyond `report_cycles`, until every responding
+/// hop has the minimum number of samples or the timeout has elapsed.
+///
+/// Stop waiting if the tracer finishes first, such as when the `max_packets` budget is exhausted
+/// before `report_cycles` rounds have completed.
+fn wait_for_round(trace_data: &Tracer, options: &ReportOptions) -> anyhow::Result<State> {
+    let start = Instant::now();
+    loop {
+        let trace = trace_data.snapshot();
+        if is_report_ready(&trace, options, start.elapsed())? {
+            return Ok(trace);
         }
     }
-    Ok(trace)
+}
+
+/// Is `trace` ready to be reported after waiting for `elapsed`?
+fn is_report_ready(
+    trace: &State,
+    options: &ReportOptions,
+    elapsed: Duration,
+) -> anyhow::Result<bool> {
+    check_error(trace)?;
+    check_responded(trace, options.fail_fast)?;
+    let round_ready = trace
+        .round(State::default_flow_id())
+        .is_some_and(|round| round + 1 >= options.report_cycles);
+    Ok(trace.finished() || (round_ready && has_min_samples(trace, options.min_samples, elapsed)))
+}
+
+/// Check that the tracer of `trace` has not failed.
+fn check_error(trace: &State) -> Result<(), TraceError> {
+    match trace.error() {
+        Some(err) => Err(TraceError {
+            message: err.to_string(),
+            transient: trace.is_error_transient(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Check that at least one hop has responded if `fail_fast` rounds have completed.
+fn check_responded(trace: &State, fail_fast: Option<usize>) -> Result<(), NoResponseError> {
+    match fail_fast {
+        Some(rounds)
+            if trace.round_count(State::default_flow_id()) >= rounds
+                && trace.hops().iter().all(|hop| hop.total_recv() == 0) =>
+        {
+            Err(NoResponseError(rounds))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Has every responding hop of `trace` the minimum number of samples, or has the timeout elapsed?
+fn has_min_samples(trace: &State, min_samples: Option<MinSamples>, elapsed: Duration) -> bool {
+    min_samples.is_none_or(|min_samples| {
+        elapsed >= min_samples.timeout
+            || trace
+                .hops()
+                .iter()
+                .filter(|hop| hop.total_recv() > 0)
+                .all(|hop| hop.total_recv() >= min_samples.samples)
+    })
+}
+
+/// The start and end time of `trace`, if any probes have been sent.
+fn trace_timestamps(trace: &State) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    Some((
+        DateTime::<Utc>::from(trace.start_time()?),
+        DateTime::<Utc>::from(trace.end_time()?),
+    ))
+}
+
+/// The start and end time of `trace` in ISO-8601 format, if any probes have been sent.
+fn iso8601_timestamps(trace: &State) -> Option<(String, String)> {
+    trace_timestamps(trace).map(|(start, end)| {
+        (
+            start.to_rfc3339_opts(SecondsFormat::Millis, true),
+            end.to_rfc3339_opts(SecondsFormat::Millis, true),
+        )
+    })
+}
+
+/// Summary lines of the start and end time of `trace` in ISO-8601 format.
+fn timestamp_summary(trace: &State) -> Vec<String> {
+    match iso8601_timestamps(trace) {
+        Some((start, end)) => vec![format!("trace start: {start}"), format!("trace end: {end}")],
+        None => vec![],
+    }
+}
+
+/// The process exit code for an error, if it has a distinct exit code.
+pub fn exit_code(err: &anyhow::Error) -> Option<i32> {
+    err.downcast_ref::<NoResponseError>()
+        .map(|_| NO_RESPONSE_EXIT_CODE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::net::Ipv4Addr;
+    use test_case::test_case;
+    use trippy_core::StopReason;
+
+    #[test_case(None, 5, Ok(()); "disabled")]
+    #[test_case(Some(3), 2, Ok(()); "before fail fast rounds")]
+    #[test_case(Some(3), 3, Err(NoResponseError(3)); "at fail fast rounds")]
+    #[test_case(Some(3), 5, Err(NoResponseError(3)); "after fail fast rounds")]
+    fn test_check_responded_no_response(
+        fail_fast: Option<usize>,
+        rounds: usize,
+        expected: Result<(), NoResponseError>,
+    ) {
+        let trace = testing::unresponsive_trace(rounds);
+        assert_eq!(expected, check_responded(&trace, fail_fast));
+    }
+
+    #[test]
+    fn test_check_responded_with_response() {
+        let trace = testing::trace();
+        assert_eq!(Ok(()), check_responded(&trace, Some(1)));
+    }
+
+    // Hop 1 responds in every round whereas hop 2 only responds in alternate rounds, and so hop 2
+    // is the last to reach the minimum samples.
+    #[test_case(3, 3, Duration::ZERO, false; "slowest hop below min samples")]
+    #[test_case(4, 3, Duration::ZERO, false; "fastest hop at min samples")]
+    #[test_case(5, 3, Duration::ZERO, true; "slowest hop at min samples")]
+    #[test_case(3, 3, Duration::from_secs(60), true; "timeout elapsed")]
+    fn test_has_min_samples(rounds: usize, samples: usize, elapsed: Duration, expected: bool) {
+        let hop1 = Ipv4Addr::new(10, 0, 0, 1);
+        let hop2 = Ipv4Addr::new(10, 0, 0, 2);
+        let rounds = (0..rounds)
+            .map(|round| {
+                if round % 2 == 0 {
+                    vec![hop1, hop2]
+                } else {
+                    vec![hop1]
+                }
+            })
+            .collect::<Vec<_>>();
+        let rounds = rounds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let trace = testing::routed_trace(&rounds);
+        let min_samples = MinSamples::new(samples, Duration::from_secs(60));
+        assert_eq!(
+            expected,
+            has_min_samples(&trace, Some(min_samples), elapsed)
+        );
+    }
+
+    #[test]
+    fn test_has_min_samples_disabled() {
+        let trace = testing::unresponsive_trace(1);
+        assert!(has_min_samples(&trace, None, Duration::ZERO));
+    }
+
+    // A single round trace whilst waiting for three report cycles, such as when the `max_packets`
+    // budget is too small to complete every cycle.
+    #[test_case(None, false; "running")]
+    #[test_case(Some(StopReason::PacketsExhausted), true; "packets exhausted")]
+    #[test_case(Some(StopReason::DestinationSilent), true; "destination silent")]
+    fn test_is_report_ready_finished(stop_reason: Option<StopReason>, expected: bool) {
+        let mut trace = testing::trace();
+        trace.set_stop_reason(stop_reason);
+        let options = ReportOptions {
+            report_cycles: 3,
+            ..testing::report_options()
+        };
+        assert_eq!(
+            expected,
+            is_report_ready(&trace, &options, Duration::ZERO).unwrap()
+        );
+    }
+
+    // Hop 2 responds in only one of the two rounds and so never reaches the minimum samples if
+    // the tracer finishes, such as when the `max_packets` budget is exhausted.
+    #[test_case(None, false; "running")]
+    #[test_case(Some(StopReason::PacketsExhausted), true; "packets exhausted")]
+    fn test_is_report_ready_min_samples_finished(stop_reason: Option<StopReason>, expected: bool) {
+        let round = [Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)];
+        let mut trace = testing::routed_trace(&[&round, &round[..1]]);
+        trace.set_stop_reason(stop_reason);
+        let options = ReportOptions {
+            min_samples: Some(MinSamples::new(2, Duration::from_secs(60))),
+            ..testing::report_options()
+        };
+        assert_eq!(
+            expected,
+            is_report_ready(&trace, &options, Duration::ZERO).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_is_report_ready_error() {
+        let mut trace = testing::trace();
+        trace.set_error(Some(String::from("failed")));
+        trace.set_error_transient(true);
+        let err = is_report_ready(&trace, &testing::report_options(), Duration::ZERO).unwrap_err();
+        assert_eq!("error: failed", err.to_string());
+        assert_eq!(
+            Some(&TraceError {
+                message: String::from("failed"),
+                transient: true
+            }),
+            err.downcast_ref()
+        );
+    }
+
+    #[test]
+    fn test_timestamp_summary_not_started() {
+        let trace = testing::unresponsive_trace(0);
+        assert_eq!(None, trace_timestamps(&trace));
+        assert!(timestamp_summary(&trace).is_empty());
+    }
+
+    #[test]
+    fn test_no_response_exit_code() {
+        let err = anyhow::Error::from(NoResponseError(3));
+        assert_eq!("no hops responded after 3 rounds", err.to_string());
+        assert_eq!(Some(NO_RESPONSE_EXIT_CODE), exit_code(&err));
+        assert_eq!(None, exit_code(&anyhow!("error: failed")));
+    }
 }
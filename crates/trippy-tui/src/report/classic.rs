@@ -0,0 +1,229 @@
+use crate::report::ReportOptions;
+use crate::report::formatter::{ReportData, ReportFormatter};
+use crate::report::redact::Redaction;
+use itertools::Itertools;
+use std::io::Write;
+use std::time::Duration;
+use trippy_core::{Hop, State};
+use trippy_dns::Resolver;
+
+/// A `ReportFormatter` which writes a classic traceroute style report of the trace.
+///
+/// Each hop is shown on a single line with the last `queries` round trip time samples, oldest
+/// first, in the style of `traceroute -q`.  If the `timestamps` report option is set then the
+/// trace start and end times are shown after the hops.
+pub struct ClassicFormatter<'a, R> {
+    queries: usize,
+    resolver: &'a R,
+    options: &'a ReportOptions,
+}
+
+impl<'a, R: Resolver> ClassicFormatter<'a, R> {
+    pub const fn new(queries: usize, resolver: &'a R, options: &'a ReportOptions) -> Self {
+        Self {
+            queries,
+            resolver,
+            options,
+        }
+    }
+}
+
+impl<R: Resolver> ReportFormatter for ClassicFormatter<'_, R> {
+    fn name(&self) -> &'static str {
+        "classic"
+    }
+
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+        write_classic(
+            sink,
+            data.trace,
+            self.queries,
+            self.options.timestamps,
+            self.resolver,
+            &self.options.redaction,
+        )
+    }
+}
+
+/// Write a line for each of the hops in `trace` to `sink`.
+fn write_classic<R: Resolver>(
+    sink: &mut dyn Write,
+    trace: &State,
+    queries: usize,
+    timestamps: bool,
+    resolver: &R,
+    redaction: &Redaction,
+) -> anyhow::Result<()> {
+    for hop in trace.hops() {
+        let hosts = hop
+            .addrs()
+            .map(|addr| {
+                let hostname = redaction.hostname(hop.ttl(), &resolver.reverse_lookup(*addr));
+                if hostname == addr.to_string() {
+                    hostname
+                } else {
+                    format!("{hostname} ({addr})")
+                }
+            })
+            .join(" ");
+        let rtts = format_samples(hop, queries);
+        // Mark filtered hops with `!X`, as `traceroute` does for administratively prohibited.
+        let rtts = if hop.is_filtered() {
+            format!("{rtts} !X")
+        } else {
+            rtts
+        };
+        if hosts.is_empty() {
+            writeln!(sink, "{:>2}  {rtts}", hop.ttl())?;
+        } else {
+            writeln!(sink, "{:>2}  {hosts}  {rtts}", hop.ttl())?;
+        }
+    }
+    if trace.destination_reached(State::default_flow_id()) {
+        writeln!(sink, "destination reached")?;
+    } else if trace.destination_silent(State::default_flow_id()) {
+        writeln!(sink, "destination silent")?;
+    } else {
+        writeln!(sink, "destination not reached")?;
+    }
+    if timestamps {
+        for line in super::timestamp_summary(trace) {
+            writeln!(sink, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Format the last `queries` samples of `hop`, oldest first.
+///
+/// Probes which did not receive a response are recorded as a zero sample and shown as `*`.
+fn format_samples(hop: &Hop, queries: usize) -> String {
+    hop.samples()
+        .iter()
+        .take(queries)
+        .rev()
+        .map(|sample| {
+            if *sample == Duration::default() {
+                String::from("*")
+            } else {
+                format!("{:.3} ms", sample.as_secs_f64() * 1000_f64)
+            }
+        })
+        .join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{
+        NullResolver, StaticResolver, TARGET_ADDR, filtered_trace, sampled_trace,
+        unresponsive_trace,
+    };
+
+    #[test]
+    fn test_write_classic() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[Some(10), None, Some(12), Some(9)]);
+        write_classic(
+            &mut sink,
+            &trace,
+            3,
+            false,
+            &NullResolver,
+            &Redaction::default(),
+        )
+        .unwrap();
+        let expected = " 1  10.0.0.2  *  12.000 ms  9.000 ms\ndestination reached\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_classic_resolved() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[Some(10), Some(11)]);
+        let resolver = StaticResolver(vec![(TARGET_ADDR, "target.example.net")]);
+        write_classic(
+            &mut sink,
+            &trace,
+            3,
+            false,
+            &resolver,
+            &Redaction::default(),
+        )
+        .unwrap();
+        let expected =
+            " 1  target.example.net (10.0.0.2)  10.000 ms  11.000 ms\ndestination reached\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_classic_no_response() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[None, None, None]);
+        write_classic(
+            &mut sink,
+            &trace,
+            3,
+            false,
+            &NullResolver,
+            &Redaction::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            " 1  *  *  *\ndestination not reached\n",
+            String::from_utf8(sink).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_write_classic_filtered() {
+        let mut sink = Vec::new();
+        let trace = filtered_trace();
+        write_classic(
+            &mut sink,
+            &trace,
+            3,
+            false,
+            &NullResolver,
+            &Redaction::default(),
+        )
+        .unwrap();
+        let expected = " 1  10.0.0.1  10.000 ms\n 2  10.0.0.2  20.000 ms !X\ndestination reached\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_classic_destination_not_reached() {
+        let mut sink = Vec::new();
+        let trace = unresponsive_trace(2);
+        write_classic(
+            &mut sink,
+            &trace,
+            3,
+            false,
+            &NullResolver,
+            &Redaction::default(),
+        )
+        .unwrap();
+        let expected = " 1  *  *\n 2  *  *\n 3  *  *\ndestination not reached\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_classic_timestamps() {
+        let mut sink = Vec::new();
+        let trace = filtered_trace();
+        write_classic(
+            &mut sink,
+            &trace,
+            3,
+            true,
+            &NullResolver,
+            &Redaction::default(),
+        )
+        .unwrap();
+        let expected = " 1  10.0.0.1  10.000 ms\n 2  10.0.0.2  20.000 ms !X\ndestination reached\n\
+            trace start: 2023-11-14T22:13:20.000Z\ntrace end: 2023-11-14T22:13:20.020Z\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+}
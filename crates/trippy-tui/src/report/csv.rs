@@ -1,30 +1,61 @@
-use crate::app::TraceInfo;
-use crate::report::types::fixed_width;
+use crate::report::ReportOptions;
+use crate::report::formatter::{ReportData, ReportFormatter};
+use crate::report::types::{Config, fixed_width};
 use itertools::Itertools;
 use serde::Serialize;
+use std::io::Write;
 use std::net::IpAddr;
-use tracing::instrument;
+use trippy_core::State;
 use trippy_dns::Resolver;
 
-/// Generate a CSV report of trace data.
-#[instrument(skip_all, level = "trace")]
-pub fn report<R: Resolver>(
-    info: &TraceInfo,
-    report_cycles: usize,
-    resolver: &R,
-) -> anyhow::Result<()> {
-    let trace = super::wait_for_round(&info.data, report_cycles)?;
-    let mut writer = csv::Writer::from_writer(std::io::stdout());
-    for hop in trace.hops() {
-        let row = CsvRow::new(
-            &info.target_hostname,
-            info.data.target_addr(),
-            hop,
-            resolver,
-        );
-        writer.serialize(row)?;
+/// A `ReportFormatter` which writes a CSV row for each of the hops of the trace.
+///
+/// If the `timestamps` report option is set then each row includes the trace start and end
+/// times, if the `response_dscp` report option is set then each row includes the DSCP of the
+/// latest response from the hop and if the `time_to_first` report option is set then each row
+/// includes the time from the start of the trace to the first response from the hop.  If the
+/// `config` report option is set then each row includes the configuration of the trace.
+pub struct CsvFormatter<'a, R> {
+    resolver: &'a R,
+    options: &'a ReportOptions,
+}
+
+impl<'a, R: Resolver> CsvFormatter<'a, R> {
+    pub const fn new(resolver: &'a R, options: &'a ReportOptions) -> Self {
+        Self { resolver, options }
+    }
+}
+
+impl<R: Resolver> ReportFormatter for CsvFormatter<'_, R> {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(sink);
+        let destination_reached = data.trace.destination_reached(State::default_flow_id());
+        let timestamps = if self.options.timestamps {
+            super::iso8601_timestamps(data.trace)
+        } else {
+            None
+        };
+        let config = self.options.config.then(|| Config::from(data.tracer));
+        for hop in data.trace.hops() {
+            let row = CsvRow::new(
+                data.target_hostname,
+                data.tracer.target_addr(),
+                destination_reached,
+                timestamps.clone(),
+                hop,
+                self.resolver,
+                self.options,
+            )
+            .with_config(config.as_ref());
+            writer.serialize(row)?;
+        }
+        writer.flush()?;
+        Ok(())
     }
-    Ok(())
 }
 
 #[derive(Serialize)]
@@ -58,14 +89,43 @@ pub struct CsvRow {
     #[serde(rename = "StdDev")]
     #[serde(serialize_with = "fixed_width")]
     pub stddev: f64,
+    #[serde(rename = "DestinationReached")]
+    pub destination_reached: bool,
+    /// The DSCP of the latest response from the hop, which reveals any remarking.
+    #[serde(rename = "RespDscp", skip_serializing_if = "Option::is_none")]
+    pub response_dscp: Option<String>,
+    /// The time from the start of the trace to the first response from the hop.
+    #[serde(rename = "TimeToFirst", skip_serializing_if = "Option::is_none")]
+    pub time_to_first: Option<String>,
+    #[serde(rename = "Protocol", skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+    #[serde(rename = "SrcPort", skip_serializing_if = "Option::is_none")]
+    pub src_port: Option<String>,
+    #[serde(rename = "DestPort", skip_serializing_if = "Option::is_none")]
+    pub dest_port: Option<String>,
+    #[serde(rename = "PacketSize", skip_serializing_if = "Option::is_none")]
+    pub packet_size: Option<u16>,
+    #[serde(rename = "Strategy", skip_serializing_if = "Option::is_none")]
+    pub multipath_strategy: Option<String>,
+    #[serde(rename = "FirstTtl", skip_serializing_if = "Option::is_none")]
+    pub first_ttl: Option<u8>,
+    #[serde(rename = "MaxTtl", skip_serializing_if = "Option::is_none")]
+    pub max_ttl: Option<u8>,
+    #[serde(rename = "TraceStart", skip_serializing_if = "Option::is_none")]
+    pub trace_start: Option<String>,
+    #[serde(rename = "TraceEnd", skip_serializing_if = "Option::is_none")]
+    pub trace_end: Option<String>,
 }
 
 impl CsvRow {
     fn new<R: Resolver>(
         target: &str,
         target_addr: IpAddr,
+        destination_reached: bool,
+        timestamps: Option<(String, String)>,
         hop: &trippy_core::Hop,
         resolver: &R,
+        options: &ReportOptions,
     ) -> Self {
         let ttl = hop.ttl();
         let ips = hop.addrs().join(":");
@@ -74,7 +134,14 @@ impl CsvRow {
         } else {
             ips
         };
-        let hosts = hop.addrs().map(|ip| resolver.reverse_lookup(*ip)).join(":");
+        let hosts = hop
+            .addrs()
+            .map(|ip| {
+                options
+                    .redaction
+                    .hostname(ttl, &resolver.reverse_lookup(*ip))
+            })
+            .join(":");
         let host = if hosts.is_empty() {
             String::from("???")
         } else {
@@ -94,6 +161,15 @@ impl CsvRow {
         let stddev = hop.stddev_ms();
         let avg = hop.avg_ms();
         let loss_pct = hop.loss_pct();
+        let response_dscp = options.response_dscp.then(|| {
+            hop.response_dscp()
+                .map_or_else(|| String::from("???"), |dscp| dscp.to_string())
+        });
+        let time_to_first = options.time_to_first.then(|| {
+            hop.time_to_first_ms()
+                .map_or_else(|| String::from("???"), |ttf| format!("{ttf:.1}"))
+        });
+        let (trace_start, trace_end) = timestamps.unzip();
 
         Self {
             target_hostname: String::from(target),
@@ -109,6 +185,171 @@ impl CsvRow {
             best,
             worst,
             stddev,
+            destination_reached,
+            response_dscp,
+            time_to_first,
+            protocol: None,
+            src_port: None,
+            dest_port: None,
+            packet_size: None,
+            multipath_strategy: None,
+            first_ttl: None,
+            max_ttl: None,
+            trace_start,
+            trace_end,
+        }
+    }
+
+    /// Include the protocol, ports, packet size, multipath strategy and TTL range of `config`.
+    ///
+    /// The ports which are not fixed by the configuration are left empty.
+    fn with_config(self, config: Option<&Config>) -> Self {
+        match config {
+            Some(config) => Self {
+                protocol: Some(config.protocol.clone()),
+                src_port: Some(
+                    config
+                        .src_port
+                        .map(|port| port.to_string())
+                        .unwrap_or_default(),
+                ),
+                dest_port: Some(
+                    config
+                        .dest_port
+                        .map(|port| port.to_string())
+                        .unwrap_or_default(),
+                ),
+                packet_size: Some(config.packet_size),
+                multipath_strategy: Some(config.multipath_strategy.clone()),
+                first_ttl: Some(config.first_ttl),
+                max_ttl: Some(config.max_ttl),
+                ..self
+            },
+            None => self,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{
+        NullResolver, TARGET_ADDR, format, format_with_tracer, report_options, trace,
+        unresponsive_trace,
+    };
+    use trippy_core::{Builder, PortDirection, Protocol};
+
+    #[test]
+    fn test_write_csv() {
+        let options = report_options();
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format(&formatter, &trace());
+        let expected = "\
+Target,TargetIp,Hop,IPs,Addrs,Loss%,Snt,Recv,Last,Avg,Best,Wrst,StdDev,DestinationReached
+target,10.0.0.2,1,10.0.0.1,10.0.0.1,0.00,1,1,10.0,10.00,10.0,10.0,0.00,true
+target,10.0.0.2,2,10.0.0.2,10.0.0.2,0.00,1,1,20.0,20.00,20.0,20.0,0.00,true
+";
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_csv_destination_not_reached() {
+        let options = report_options();
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format(&formatter, &unresponsive_trace(1));
+        let expected = "\
+Target,TargetIp,Hop,IPs,Addrs,Loss%,Snt,Recv,Last,Avg,Best,Wrst,StdDev,DestinationReached
+target,10.0.0.2,1,???,???,100.00,1,0,???,0.00,???,???,0.00,false
+target,10.0.0.2,2,???,???,100.00,1,0,???,0.00,???,???,0.00,false
+target,10.0.0.2,3,???,???,100.00,1,0,???,0.00,???,???,0.00,false
+";
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_csv_timestamps() {
+        let options = ReportOptions {
+            timestamps: true,
+            ..report_options()
+        };
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format(&formatter, &trace());
+        let expected = "\
+Target,TargetIp,Hop,IPs,Addrs,Loss%,Snt,Recv,Last,Avg,Best,Wrst,StdDev,DestinationReached,TraceStart,TraceEnd
+target,10.0.0.2,1,10.0.0.1,10.0.0.1,0.00,1,1,10.0,10.00,10.0,10.0,0.00,true,2023-11-14T22:13:20.000Z,2023-11-14T22:13:20.020Z
+target,10.0.0.2,2,10.0.0.2,10.0.0.2,0.00,1,1,20.0,20.00,20.0,20.0,0.00,true,2023-11-14T22:13:20.000Z,2023-11-14T22:13:20.020Z
+";
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_csv_response_dscp() {
+        let options = ReportOptions {
+            response_dscp: true,
+            ..report_options()
+        };
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format(&formatter, &trace());
+        let expected = "\
+Target,TargetIp,Hop,IPs,Addrs,Loss%,Snt,Recv,Last,Avg,Best,Wrst,StdDev,DestinationReached,RespDscp
+target,10.0.0.2,1,10.0.0.1,10.0.0.1,0.00,1,1,10.0,10.00,10.0,10.0,0.00,true,CS6
+target,10.0.0.2,2,10.0.0.2,10.0.0.2,0.00,1,1,20.0,20.00,20.0,20.0,0.00,true,EF
+";
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_csv_time_to_first() {
+        let options = ReportOptions {
+            time_to_first: true,
+            ..report_options()
+        };
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format(&formatter, &trace());
+        let expected = "\
+Target,TargetIp,Hop,IPs,Addrs,Loss%,Snt,Recv,Last,Avg,Best,Wrst,StdDev,DestinationReached,TimeToFirst
+target,10.0.0.2,1,10.0.0.1,10.0.0.1,0.00,1,1,10.0,10.00,10.0,10.0,0.00,true,10.0
+target,10.0.0.2,2,10.0.0.2,10.0.0.2,0.00,1,1,20.0,20.00,20.0,20.0,0.00,true,20.0
+";
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_csv_config() {
+        let options = ReportOptions {
+            config: true,
+            ..report_options()
+        };
+        let tracer = Builder::new(TARGET_ADDR)
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::new_fixed_src(5000))
+            .max_ttl(20)
+            .packet_size(100)
+            .build()
+            .unwrap();
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format_with_tracer(&formatter, &tracer, &trace());
+        let expected = "\
+Target,TargetIp,Hop,IPs,Addrs,Loss%,Snt,Recv,Last,Avg,Best,Wrst,StdDev,DestinationReached,Protocol,SrcPort,DestPort,PacketSize,Strategy,FirstTtl,MaxTtl
+target,10.0.0.2,1,10.0.0.1,10.0.0.1,0.00,1,1,10.0,10.00,10.0,10.0,0.00,true,udp,5000,,100,classic,1,20
+target,10.0.0.2,2,10.0.0.2,10.0.0.2,0.00,1,1,20.0,20.00,20.0,20.0,0.00,true,udp,5000,,100,classic,1,20
+";
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_write_csv_response_dscp_no_response() {
+        let options = ReportOptions {
+            response_dscp: true,
+            ..report_options()
+        };
+        let formatter = CsvFormatter::new(&NullResolver, &options);
+        let output = format(&formatter, &unresponsive_trace(1));
+        assert!(
+            output
+                .lines()
+                .skip(1)
+                .all(|line| line.ends_with(",false,???"))
+        );
+    }
+}
@@ -1,22 +1,43 @@
-use crate::app::TraceInfo;
+use crate::report::formatter::{ReportData, ReportFormatter};
 use petgraph::dot::{Config, Dot};
 use petgraph::graphmap::DiGraphMap;
 use std::fmt::{Debug, Formatter};
+use std::io::Write;
 use std::net::{IpAddr, Ipv4Addr};
-use tracing::instrument;
-use trippy_core::FlowEntry;
+use trippy_core::{FlowEntry, State};
 
-/// Run a trace and generate a dot file.
-#[instrument(skip_all, level = "trace")]
-pub fn report(info: &TraceInfo, report_cycles: usize) -> anyhow::Result<()> {
+/// A `ReportFormatter` which writes a dot graph of the flows of the trace.
+///
+/// If `timestamps` is set then the trace start and end times are written as comments before the
+/// graph.
+pub struct DotFormatter {
+    timestamps: bool,
+}
+
+impl DotFormatter {
+    pub const fn new(timestamps: bool) -> Self {
+        Self { timestamps }
+    }
+}
+
+impl ReportFormatter for DotFormatter {
+    fn name(&self) -> &'static str {
+        "dot"
+    }
+
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+        write_dot(sink, data.trace, self.timestamps)
+    }
+}
+
+/// Write a dot graph of the flows in `trace` to `sink`.
+fn write_dot(sink: &mut dyn Write, trace: &State, timestamps: bool) -> anyhow::Result<()> {
     struct DotWrapper<'a>(Dot<'a, &'a DiGraphMap<IpAddr, ()>>);
     impl Debug for DotWrapper<'_> {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
             self.0.fmt(f)
         }
     }
-    super::wait_for_round(&info.data, report_cycles)?;
-    let trace = info.data.snapshot();
     let mut graph: DiGraphMap<IpAddr, ()> = DiGraphMap::new();
     for (flow, _id) in trace.flows() {
         for (fst, snd) in flow.entries.windows(2).map(|pair| (pair[0], pair[1])) {
@@ -34,7 +55,32 @@ pub fn report(info: &TraceInfo, report_cycles: usize) -> anyhow::Result<()> {
             }
         }
     }
+    if timestamps {
+        for line in super::timestamp_summary(trace) {
+            writeln!(sink, "// {line}")?;
+        }
+    }
     let dot = DotWrapper(Dot::with_config(&graph, &[Config::EdgeNoLabel]));
-    print!("{dot:?}");
+    write!(sink, "{dot:?}")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::trace;
+
+    #[test]
+    fn test_write_dot_timestamps() {
+        let mut sink = Vec::new();
+        write_dot(&mut sink, &trace(), true).unwrap();
+        let output = String::from_utf8(sink).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(
+            Some("// trace start: 2023-11-14T22:13:20.000Z"),
+            lines.next()
+        );
+        assert_eq!(Some("// trace end: 2023-11-14T22:13:20.020Z"), lines.next());
+        assert_eq!(Some("digraph {"), lines.next());
+    }
+}
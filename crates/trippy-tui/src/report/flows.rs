@@ -1,13 +1,63 @@
-use crate::app::TraceInfo;
-use tracing::instrument;
-
-/// Run a trace and report all flows observed.
-#[instrument(skip_all, level = "trace")]
-pub fn report(info: &TraceInfo, report_cycles: usize) -> anyhow::Result<()> {
-    super::wait_for_round(&info.data, report_cycles)?;
-    let trace = info.data.snapshot();
+use crate::report::formatter::{ReportData, ReportFormatter};
+use std::io::Write;
+use trippy_core::State;
+
+/// A `ReportFormatter` which writes all flows observed by the trace.
+///
+/// If `timestamps` is set then the trace start and end times are shown after the flows.
+pub struct FlowsFormatter {
+    timestamps: bool,
+}
+
+impl FlowsFormatter {
+    pub const fn new(timestamps: bool) -> Self {
+        Self { timestamps }
+    }
+}
+
+impl ReportFormatter for FlowsFormatter {
+    fn name(&self) -> &'static str {
+        "flows"
+    }
+
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+        write_flows(sink, data.trace, self.timestamps)
+    }
+}
+
+/// Write a line for each of the flows in `trace` to `sink`.
+fn write_flows(sink: &mut dyn Write, trace: &State, timestamps: bool) -> anyhow::Result<()> {
     for (flow, flow_id) in trace.flows() {
-        println!("flow {flow_id}: {flow}");
+        writeln!(sink, "flow {flow_id}: {flow}")?;
+    }
+    if timestamps {
+        for line in super::timestamp_summary(trace) {
+            writeln!(sink, "{line}")?;
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::trace;
+
+    #[test]
+    fn test_write_flows_timestamps() {
+        let mut sink = Vec::new();
+        write_flows(&mut sink, &trace(), true).unwrap();
+        let output = String::from_utf8(sink).unwrap();
+        let summary = output
+            .lines()
+            .skip_while(|line| line.starts_with("flow "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "trace start: 2023-11-14T22:13:20.000Z",
+                "trace end: 2023-11-14T22:13:20.020Z",
+            ],
+            summary
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::app::TraceInfo;
+use crate::report::ReportOptions;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use std::io::Write;
+use tracing::instrument;
+use trippy_core::{State, Tracer};
+
+/// The names of the built-in formatters.
+pub const BUILTIN_FORMATTERS: [&str; 7] = [
+    "pretty", "markdown", "csv", "json", "classic", "dot", "flows",
+];
+
+/// The trace data provided to a `ReportFormatter`.
+pub struct ReportData<'a> {
+    /// The hostname of the target.
+    pub target_hostname: &'a str,
+    /// The tracer, from which the target address and configuration may be obtained.
+    pub tracer: &'a Tracer,
+    /// The trace, from which the hops and summary may be obtained.
+    pub trace: &'a State,
+    /// The time the trace started.
+    pub start_timestamp: DateTime<Utc>,
+    /// The time the trace ended.
+    pub end_timestamp: DateTime<Utc>,
+}
+
+impl<'a> ReportData<'a> {
+    /// Create the `ReportData` of `trace` from `tracer`.
+    ///
+    /// The start and end times are those of the first and last probe of the trace, or the
+    /// current time if no probes have been sent.
+    #[must_use]
+    pub fn new(target_hostname: &'a str, tracer: &'a Tracer, trace: &'a State) -> Self {
+        let (start_timestamp, end_timestamp) =
+            super::trace_timestamps(trace).unwrap_or_else(|| (Utc::now(), Utc::now()));
+        Self {
+            target_hostname,
+            tracer,
+            trace,
+            start_timestamp,
+            end_timestamp,
+        }
+    }
+}
+
+/// A formatter of trace data.
+///
+/// A report is generated by waiting for the configured number of rounds to complete and then
+/// passing the trace data to the formatter, which writes its output to the sink.  Implement this
+/// trait, and register it with `Formatters`, to produce bespoke output selectable with
+/// `--mode format --format <name>` without modifying the report module.
+pub trait ReportFormatter {
+    /// The name of the formatter.
+    fn name(&self) -> &'static str;
+
+    /// Write a report of `data` to `sink`.
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()>;
+}
+
+/// A registry of user provided `ReportFormatter`s.
+///
+/// The built-in formatters, named in `BUILTIN_FORMATTERS`, are always available and so their
+/// names may not be registered.
+#[derive(Default)]
+pub struct Formatters {
+    formatters: Vec<Box<dyn ReportFormatter>>,
+}
+
+impl Formatters {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `formatter`.
+    ///
+    /// Returns an error if a formatter of the same name is built-in or already registered.
+    pub fn register(&mut self, formatter: impl ReportFormatter + 'static) -> anyhow::Result<()> {
+        let name = formatter.name();
+        if self.contains(name) {
+            return Err(anyhow!("report formatter {name} is already registered"));
+        }
+        self.formatters.push(Box::new(formatter));
+        Ok(())
+    }
+
+    /// The registered formatter `name`, if any.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&dyn ReportFormatter> {
+        self.formatters
+            .iter()
+            .find(|formatter| formatter.name() == name)
+            .map(AsRef::as_ref)
+    }
+
+    /// Is there a built-in or registered formatter `name`?
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        BUILTIN_FORMATTERS.contains(&name) || self.get(name).is_some()
+    }
+
+    /// Validate that there is a built-in or registered formatter `name`.
+    pub fn validate(&self, name: &str) -> anyhow::Result<()> {
+        if self.contains(name) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "unknown report format {name}, expected one of: {}",
+                BUILTIN_FORMATTERS
+                    .into_iter()
+                    .chain(self.formatters.iter().map(|formatter| formatter.name()))
+                    .join(", ")
+            ))
+        }
+    }
+}
+
+/// Generate a report of trace data with `formatter`.
+#[instrument(skip_all, fields(formatter = formatter.name()), level = "trace")]
+pub fn report(
+    sink: &mut dyn Write,
+    info: &TraceInfo,
+    options: &ReportOptions,
+    formatter: &dyn ReportFormatter,
+) -> anyhow::Result<()> {
+    let trace = super::wait_for_round(&info.data, options)?;
+    formatter.format(
+        sink,
+        &ReportData::new(&info.target_hostname, &info.data, &trace),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{trace, tracer};
+
+    /// A trivial formatter which writes the ttl and first address of each hop.
+    struct HopsFormatter;
+
+    impl ReportFormatter for HopsFormatter {
+        fn name(&self) -> &'static str {
+            "hops"
+        }
+
+        fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+            writeln!(
+                sink,
+                "{} ({})",
+                data.target_hostname,
+                data.tracer.target_addr()
+            )?;
+            for hop in data.trace.hops() {
+                let addr = hop
+                    .addrs()
+                    .next()
+                    .map_or_else(|| String::from("*"), ToString::to_string);
+                writeln!(sink, "{} {addr}", hop.ttl())?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_format_custom_formatter() {
+        let mut formatters = Formatters::new();
+        formatters.register(HopsFormatter).unwrap();
+        let tracer = tracer();
+        let trace = trace();
+        let mut sink = Vec::new();
+        formatters
+            .get("hops")
+            .unwrap()
+            .format(&mut sink, &ReportData::new("target", &tracer, &trace))
+            .unwrap();
+        let expected = "target (10.0.0.2)\n1 10.0.0.1\n2 10.0.0.2\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_register_duplicate() {
+        let mut formatters = Formatters::new();
+        formatters.register(HopsFormatter).unwrap();
+        let err = formatters.register(HopsFormatter).unwrap_err();
+        assert_eq!(
+            "report formatter hops is already registered",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        struct Csv;
+        impl ReportFormatter for Csv {
+            fn name(&self) -> &'static str {
+                "csv"
+            }
+            fn format(&self, _sink: &mut dyn Write, _data: &ReportData<'_>) -> anyhow::Result<()> {
+                Ok(())
+            }
+        }
+        let mut formatters = Formatters::new();
+        assert!(formatters.register(Csv).is_err());
+        assert!(formatters.get("csv").is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut formatters = Formatters::new();
+        formatters.register(HopsFormatter).unwrap();
+        assert!(formatters.validate("json").is_ok());
+        assert!(formatters.validate("hops").is_ok());
+        let err = formatters.validate("foo").unwrap_err();
+        assert_eq!(
+            "unknown report format foo, expected one of: pretty, markdown, csv, json, classic, dot, flows, hops",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use crate::app::TraceInfo;
+use crate::report::ReportOptions;
+use crate::report::types::Config;
+use itertools::Itertools;
+use std::io::Write;
+use std::thread::sleep;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::instrument;
+use trippy_core::State;
+
+/// The name of the `InfluxDB` measurement.
+const MEASUREMENT: &str = "trippy";
+
+/// Generate an `InfluxDB` line protocol report of trace data for each round for N cycles.
+///
+/// One line is written for each hop after each round, see
+/// <https://docs.influxdata.com/influxdb/v2/reference/syntax/line-protocol/>.  If the
+/// `timestamps` report option is set then each point includes the trace start and end times and if
+/// the `config` report option is set then each point includes the configuration of the trace.
+#[instrument(skip_all, level = "trace")]
+pub fn report(
+    sink: &mut dyn Write,
+    info: &TraceInfo,
+    options: &ReportOptions,
+) -> anyhow::Result<()> {
+    let config = options.config.then(|| Config::from(&info.data));
+    let mut round_count = 0;
+    loop {
+        let trace = info.data.snapshot();
+        super::check_error(&trace)?;
+        super::check_responded(&trace, options.fail_fast)?;
+        let current = trace.round_count(State::default_flow_id());
+        if current > round_count {
+            write_influx(
+                sink,
+                &info.target_hostname,
+                &trace,
+                SystemTime::now(),
+                options.timestamps,
+                config.as_ref(),
+            )?;
+            round_count = current;
+        }
+        if round_count >= options.report_cycles || trace.finished() {
+            return Ok(());
+        }
+        sleep(info.data.min_round_duration());
+    }
+}
+
+/// Write a line protocol point for each of the hops in `trace` to `sink`.
+///
+/// Each point includes whether the destination has been reached as a boolean field, if `config` is
+/// set, the protocol, fixed ports, packet size, multipath strategy and TTL range of the trace and,
+/// if `timestamps` is set, the trace start and end times in ISO-8601 format as string fields.
+fn write_influx(
+    sink: &mut dyn Write,
+    target: &str,
+    trace: &State,
+    timestamp: SystemTime,
+    timestamps: bool,
+    config: Option<&Config>,
+) -> anyhow::Result<()> {
+    let timestamp = timestamp.duration_since(UNIX_EPOCH)?.as_nanos();
+    let reached = trace.destination_reached(State::default_flow_id());
+    let trace_timestamps = if timestamps {
+        super::iso8601_timestamps(trace)
+            .map(|(start, end)| format!(",trace_start=\"{start}\",trace_end=\"{end}\""))
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let trace_config = config.map_or_else(String::new, config_fields);
+    for hop in trace.hops() {
+        let addrs = hop.addrs().join(",");
+        let addr = if addrs.is_empty() {
+            String::new()
+        } else {
+            format!(",addr={}", escape(&addrs))
+        };
+        let rtt = hop
+            .last_ms()
+            .map_or_else(String::new, |rtt| format!("rtt_ms={rtt},"));
+        writeln!(
+            sink,
+            "{MEASUREMENT},target={},ttl={}{addr} {rtt}loss={},sent={}i,recv={}i,reached={reached}{trace_config}{trace_timestamps} {timestamp}",
+            escape(target),
+            hop.ttl(),
+            hop.loss_pct(),
+            hop.total_sent(),
+            hop.total_recv()
+        )?;
+    }
+    Ok(())
+}
+
+/// The line protocol fields of the configuration of a trace.
+fn config_fields(config: &Config) -> String {
+    let src_port = config
+        .src_port
+        .map_or_else(String::new, |port| format!(",src_port={port}i"));
+    let dest_port = config
+        .dest_port
+        .map_or_else(String::new, |port| format!(",dest_port={port}i"));
+    format!(
+        ",protocol=\"{}\"{src_port}{dest_port},packet_size={}i,multipath_strategy=\"{}\",first_ttl={}i,max_ttl={}i",
+        config.protocol,
+        config.packet_size,
+        config.multipath_strategy,
+        config.first_ttl,
+        config.max_ttl
+    )
+}
+
+/// Escape a tag value for the line protocol.
+///
+/// Commas, equals signs and spaces must be escaped with a backslash.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .fold(String::with_capacity(value.len()), |mut escaped, c| {
+            if matches!(c, ',' | '=' | ' ') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{TARGET_ADDR, sampled_trace, unresponsive_trace};
+    use std::time::Duration;
+    use test_case::test_case;
+    use trippy_core::{Builder, PortDirection, Protocol};
+
+    const TIMESTAMP: Duration = Duration::from_secs(1_700_000_000);
+
+    #[test]
+    fn test_write_influx() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[Some(10), None, Some(12), Some(9)]);
+        write_influx(
+            &mut sink,
+            "example.com",
+            &trace,
+            UNIX_EPOCH + TIMESTAMP,
+            false,
+            None,
+        )
+        .unwrap();
+        let expected = "trippy,target=example.com,ttl=1,addr=10.0.0.2 rtt_ms=9,loss=25,sent=4i,recv=3i,reached=true 1700000000000000000\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_influx_escaped() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[Some(10)]);
+        write_influx(
+            &mut sink,
+            "my host,a=b",
+            &trace,
+            UNIX_EPOCH + TIMESTAMP,
+            false,
+            None,
+        )
+        .unwrap();
+        let expected = "trippy,target=my\\ host\\,a\\=b,ttl=1,addr=10.0.0.2 rtt_ms=10,loss=0,sent=1i,recv=1i,reached=true 1700000000000000000\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_influx_no_response() {
+        let mut sink = Vec::new();
+        let trace = unresponsive_trace(2);
+        write_influx(
+            &mut sink,
+            "example.com",
+            &trace,
+            UNIX_EPOCH + TIMESTAMP,
+            false,
+            None,
+        )
+        .unwrap();
+        let expected = [
+            "trippy,target=example.com,ttl=1 loss=100,sent=2i,recv=0i,reached=false 1700000000000000000",
+            "trippy,target=example.com,ttl=2 loss=100,sent=2i,recv=0i,reached=false 1700000000000000000",
+            "trippy,target=example.com,ttl=3 loss=100,sent=2i,recv=0i,reached=false 1700000000000000000",
+        ];
+        assert_eq!(expected.join("\n") + "\n", String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_influx_timestamps() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[Some(10)]);
+        write_influx(
+            &mut sink,
+            "example.com",
+            &trace,
+            UNIX_EPOCH + TIMESTAMP,
+            true,
+            None,
+        )
+        .unwrap();
+        let expected = "trippy,target=example.com,ttl=1,addr=10.0.0.2 rtt_ms=10,loss=0,sent=1i,recv=1i,reached=true,trace_start=\"2023-11-14T22:13:20.000Z\",trace_end=\"2023-11-14T22:13:20.010Z\" 1700000000000000000\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_influx_config() {
+        let mut sink = Vec::new();
+        let trace = sampled_trace(&[Some(10)]);
+        let tracer = Builder::new(TARGET_ADDR)
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::new_fixed_src(5000))
+            .max_ttl(20)
+            .packet_size(100)
+            .build()
+            .unwrap();
+        write_influx(
+            &mut sink,
+            "example.com",
+            &trace,
+            UNIX_EPOCH + TIMESTAMP,
+            false,
+            Some(&Config::from(&tracer)),
+        )
+        .unwrap();
+        let expected = "trippy,target=example.com,ttl=1,addr=10.0.0.2 rtt_ms=10,loss=0,sent=1i,recv=1i,reached=true,protocol=\"udp\",src_port=5000i,packet_size=100i,multipath_strategy=\"classic\",first_ttl=1i,max_ttl=20i 1700000000000000000\n";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test_case("example.com", "example.com"; "no escaping")]
+    #[test_case("a b", "a\\ b"; "space")]
+    #[test_case("a,b", "a\\,b"; "comma")]
+    #[test_case("a=b", "a\\=b"; "equals")]
+    #[test_case("10.0.0.1,10.0.0.2", "10.0.0.1\\,10.0.0.2"; "multiple addrs")]
+    fn test_escape(value: &str, expected: &str) {
+        assert_eq!(expected, escape(value));
+    }
+}
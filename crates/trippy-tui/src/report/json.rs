@@ -1,34 +1,165 @@
-use crate::app::TraceInfo;
-use crate::report::types::{Hop, Host, Info, Report};
-use tracing::instrument;
+use crate::report::ReportOptions;
+use crate::report::formatter::{ReportData, ReportFormatter};
+use crate::report::types::{Config, Hop, Host, IdentifierComparison, Info, Report, SCHEMA_VERSION};
+use std::io::Write;
+use trippy_core::State;
 use trippy_dns::Resolver;
 
-/// Generate a json report of trace data.
-#[instrument(skip_all, level = "trace")]
-pub fn report<R: Resolver>(
-    info: &TraceInfo,
-    report_cycles: usize,
+/// A `ReportFormatter` which writes a json report of the trace.
+pub struct JsonFormatter<'a, R> {
+    resolver: &'a R,
+    options: &'a ReportOptions,
+}
+
+impl<'a, R: Resolver> JsonFormatter<'a, R> {
+    pub const fn new(resolver: &'a R, options: &'a ReportOptions) -> Self {
+        Self { resolver, options }
+    }
+}
+
+impl<R: Resolver> ReportFormatter for JsonFormatter<'_, R> {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+        write_report(sink, data, self.options, self.resolver)
+    }
+}
+
+/// Write a json report of `data` to `sink`.
+fn write_report<R: Resolver>(
+    sink: &mut dyn Write,
+    data: &ReportData<'_>,
+    options: &ReportOptions,
     resolver: &R,
 ) -> anyhow::Result<()> {
-    let start_timestamp = chrono::Utc::now();
-    let trace = super::wait_for_round(&info.data, report_cycles)?;
-    let end_timestamp = chrono::Utc::now();
+    let trace = data.trace;
     let hops: Vec<Hop> = trace
         .hops()
         .iter()
-        .map(|hop| Hop::from((hop, resolver)))
+        .map(|hop| {
+            let mut report_hop = Hop::from((hop, resolver));
+            for host in &mut report_hop.hosts.0 {
+                host.hostname = options
+                    .redaction
+                    .hostname(hop.ttl(), &resolver.reverse_lookup(host.ip));
+            }
+            report_hop
+        })
         .collect();
     let report = Report {
+        schema_version: SCHEMA_VERSION,
         info: Info {
             target: Host {
-                ip: info.data.target_addr(),
-                hostname: info.target_hostname.clone(),
+                ip: data.tracer.target_addr(),
+                hostname: data.target_hostname.to_string(),
             },
-            start_timestamp,
-            end_timestamp,
+            destination_reached: trace.destination_reached(State::default_flow_id()),
+            destination_confidence: trace
+                .destination_confidence(State::default_flow_id(), data.tracer.target_addr()),
+            path_stability: trace.path_stability(State::default_flow_id()),
+            effective_privilege_mode: data
+                .tracer
+                .effective_privilege_mode()
+                .map(|mode| mode.to_string()),
+            identifier_comparisons: data
+                .tracer
+                .identifier_comparisons()
+                .into_iter()
+                .map(IdentifierComparison::from)
+                .collect(),
+            start_timestamp: data.start_timestamp,
+            end_timestamp: data.end_timestamp,
         },
+        config: Config::from(data.tracer),
         hops,
     };
-    serde_json::to_writer_pretty(std::io::stdout(), &report)?;
+    serde_json::to_writer_pretty(sink, &report)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{
+        NullResolver, TARGET_ADDR, format, format_with_tracer, quoted_trace, report_options, trace,
+        unresponsive_trace,
+    };
+    use chrono::{DateTime, Utc};
+    use trippy_core::{Builder, PortDirection, Protocol};
+
+    #[test]
+    fn test_report_schema_version() {
+        let options = report_options();
+        let output = format(&JsonFormatter::new(&NullResolver, &options), &trace());
+        assert!(output.starts_with(&format!("{{\n  \"schema_version\": {SCHEMA_VERSION},")));
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        assert!(json["schema_version"].is_u64());
+        assert_eq!(
+            Some(u64::from(SCHEMA_VERSION)),
+            json["schema_version"].as_u64()
+        );
+    }
+
+    #[test]
+    fn test_report_quoted_payload() {
+        let trace = quoted_trace(&[0x45, 0x00, 0x00, 0x1c]);
+        let hops = trace
+            .hops()
+            .iter()
+            .map(|hop| serde_json::to_value(Hop::from((hop, &NullResolver))).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!("4500001c", hops[0]["quoted_payload"]);
+        assert!(hops[1].get("quoted_payload").is_none());
+    }
+
+    #[test]
+    fn test_report_time_to_first() {
+        let options = report_options();
+        let formatter = JsonFormatter::new(&NullResolver, &options);
+        let json =
+            serde_json::from_str::<serde_json::Value>(&format(&formatter, &trace())).unwrap();
+        assert_eq!("10.00", json["hops"][0]["time_to_first"]);
+        assert_eq!("20.00", json["hops"][1]["time_to_first"]);
+        let output = format(&formatter, &unresponsive_trace(1));
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        assert!(json["hops"][0].get("time_to_first").is_none());
+    }
+
+    #[test]
+    fn test_report_config() {
+        let tracer = Builder::new(TARGET_ADDR)
+            .protocol(Protocol::Udp)
+            .port_direction(PortDirection::new_fixed_src(5000))
+            .max_ttl(20)
+            .packet_size(100)
+            .build()
+            .unwrap();
+        let options = report_options();
+        let formatter = JsonFormatter::new(&NullResolver, &options);
+        let output = format_with_tracer(&formatter, &tracer, &trace());
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        assert_eq!("udp", json["config"]["protocol"]);
+        assert_eq!(20, json["config"]["max_ttl"]);
+        assert_eq!(100, json["config"]["packet_size"]);
+        assert_eq!(5000, json["config"]["src_port"]);
+    }
+
+    #[test]
+    fn test_report_timestamps() {
+        let options = report_options();
+        let output = format(&JsonFormatter::new(&NullResolver, &options), &trace());
+        let json = serde_json::from_str::<serde_json::Value>(&output).unwrap();
+        let parse = |field: &str| {
+            DateTime::parse_from_rfc3339(json["info"][field].as_str().unwrap())
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let start = parse("start_timestamp");
+        let end = parse("end_timestamp");
+        assert_eq!("2023-11-14T22:13:20+00:00", start.to_rfc3339());
+        assert_eq!("2023-11-14T22:13:20.020+00:00", end.to_rfc3339());
+        assert!(end >= start);
+    }
+}
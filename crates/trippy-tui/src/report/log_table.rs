@@ -0,0 +1,145 @@
+use crate::app::TraceInfo;
+use crate::geoip::GeoIpLookup;
+use crate::report::ReportOptions;
+use crate::report::table::TableOptions;
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use tracing::instrument;
+use trippy_core::State;
+use trippy_dns::Resolver;
+
+/// The size, in bytes, beyond which the table log is rotated.
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Append a timestamped table of trace data to a file, once per round, for N cycles.
+#[instrument(skip_all, level = "trace")]
+pub fn report<R: Resolver>(
+    info: &TraceInfo,
+    options: &ReportOptions,
+    table_options: &TableOptions,
+    path: &Path,
+    resolver: &R,
+    geoip_lookup: &GeoIpLookup,
+) -> anyhow::Result<()> {
+    let log = TableLog::new(path, MAX_LOG_SIZE);
+    let mut round_count = 0;
+    loop {
+        let trace = info.data.snapshot();
+        super::check_error(&trace)?;
+        super::check_responded(&trace, options.fail_fast)?;
+        let current = trace.round_count(State::default_flow_id());
+        if current > round_count {
+            let table = super::table::render_table(
+                &trace,
+                table_options,
+                resolver,
+                geoip_lookup,
+                &options.redaction,
+                options.time_to_first,
+            );
+            log.append(Local::now(), &table.to_string())
+                .with_context(|| format!("{}", path.display()))?;
+            round_count = current;
+        }
+        if round_count >= options.report_cycles || trace.finished() {
+            return Ok(());
+        }
+        sleep(info.data.min_round_duration());
+    }
+}
+
+/// An append-only log of tables which is rotated when it exceeds a maximum size.
+///
+/// On rotation the current log is renamed with a `.1` suffix, replacing any
+/// previously rotated log, and a new log is started.
+#[derive(Debug)]
+struct TableLog {
+    path: PathBuf,
+    max_size: u64,
+}
+
+impl TableLog {
+    fn new(path: &Path, max_size: u64) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            max_size,
+        }
+    }
+
+    /// Append a table, preceded by a timestamp header, to the log.
+    fn append(&self, timestamp: DateTime<Local>, table: &str) -> std::io::Result<()> {
+        let block = format!("# {}\n{table}\n\n", timestamp.to_rfc3339());
+        if let Ok(metadata) = fs::metadata(&self.path)
+            && metadata.len() > 0
+            && metadata.len() + block.len() as u64 > self.max_size
+        {
+            fs::rename(&self.path, self.rotated_path())?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(block.as_bytes())
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".1");
+        PathBuf::from(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn temp_log_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "trippy-log-table-{}-{name}.log",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(TableLog::new(&path, 0).rotated_path());
+        path
+    }
+
+    fn timestamp(secs: i64) -> DateTime<Local> {
+        Local.timestamp_opt(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_append_two_cycles() {
+        let path = temp_log_path("append");
+        let log = TableLog::new(&path, MAX_LOG_SIZE);
+        log.append(timestamp(1_700_000_000), "table one").unwrap();
+        log.append(timestamp(1_700_000_001), "table two").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let expected = format!(
+            "# {}\ntable one\n\n# {}\ntable two\n\n",
+            timestamp(1_700_000_000).to_rfc3339(),
+            timestamp(1_700_000_001).to_rfc3339()
+        );
+        assert_eq!(expected, contents);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_append_rotates_when_full() {
+        let path = temp_log_path("rotate");
+        let log = TableLog::new(&path, 64);
+        log.append(timestamp(1_700_000_000), "table one").unwrap();
+        log.append(timestamp(1_700_000_001), "table two").unwrap();
+        let rotated = fs::read_to_string(log.rotated_path()).unwrap();
+        let current = fs::read_to_string(&path).unwrap();
+        assert!(rotated.contains("table one"));
+        assert!(!current.contains("table one"));
+        assert!(current.contains("table two"));
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(log.rotated_path()).unwrap();
+    }
+}
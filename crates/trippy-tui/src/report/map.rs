@@ -0,0 +1,268 @@
+use crate::app::TraceInfo;
+use crate::geoip::GeoIpLookup;
+use anyhow::Context;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+use tracing::instrument;
+use trippy_core::{AddrTieBreak, State};
+
+/// The page which plots the hops of the trace on a map.
+///
+/// The page polls the `/hops` endpoint and redraws the map as each round completes.
+const INDEX_HTML: &str = include_str!("map.html");
+
+/// The maximum time to wait to read a request from, or write a response to, a connection.
+///
+/// Connections are served in turn and so a stalled client must not be allowed to block the server.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The geographic data of the hops of a trace, served as json from the `/hops` endpoint.
+#[derive(Debug, Serialize)]
+pub struct MapData {
+    pub target: String,
+    pub round_count: usize,
+    pub hops: Vec<MapHop>,
+}
+
+/// The location of a single hop.
+#[derive(Debug, Serialize)]
+pub struct MapHop {
+    pub ttl: u8,
+    pub addr: IpAddr,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy_radius: u16,
+    pub location: String,
+}
+
+/// Serve a live map of the hops of `info` over HTTP on `addr`.
+///
+/// The server runs on a background thread for as long as the trace does.  The `GeoIpLookup` is
+/// created on that thread by `geoip_lookup` as it may not be shared between threads.
+///
+/// Each hop is located using its primary address, selected using `tie_break`.
+#[instrument(skip(info, geoip_lookup), level = "trace")]
+pub fn spawn<F>(
+    addr: SocketAddr,
+    info: TraceInfo,
+    tie_break: AddrTieBreak,
+    geoip_lookup: F,
+) -> anyhow::Result<()>
+where
+    F: FnOnce() -> anyhow::Result<GeoIpLookup> + Send + 'static,
+{
+    let listener = TcpListener::bind(addr).with_context(|| format!("map-server ({addr})"))?;
+    std::thread::Builder::new()
+        .name(String::from("map-server"))
+        .spawn(move || match geoip_lookup() {
+            Ok(geoip_lookup) => serve(&listener, &info, &geoip_lookup, tie_break),
+            Err(err) => tracing::error!(?err, "map-server geoip lookup failed"),
+        })?;
+    Ok(())
+}
+
+/// Serve each connection to `listener` in turn.
+fn serve(
+    listener: &TcpListener,
+    info: &TraceInfo,
+    geoip_lookup: &GeoIpLookup,
+    tie_break: AddrTieBreak,
+) {
+    for stream in listener.incoming().flatten() {
+        let trace = info.data.snapshot();
+        if let Err(err) = handle(
+            stream,
+            &info.target_hostname,
+            &trace,
+            geoip_lookup,
+            tie_break,
+            CONNECTION_TIMEOUT,
+        ) {
+            tracing::debug!(?err, "map-server request failed");
+        }
+    }
+}
+
+/// Respond to a single HTTP request.
+///
+/// The page is served for `/` and the current `MapData` for `/hops`, any other request is not
+/// found.  The request fails if reading it or writing the response takes longer than `timeout`.
+fn handle(
+    mut stream: TcpStream,
+    target: &str,
+    trace: &State,
+    geoip_lookup: &GeoIpLookup,
+    tie_break: AddrTieBreak,
+    timeout: Duration,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // read and discard the request headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+    let (status, content_type, body) = match request_path(&request_line) {
+        Some("/") => ("200 OK", "text/html; charset=utf-8", INDEX_HTML.to_string()),
+        Some("/hops") => (
+            "200 OK",
+            "application/json",
+            serde_json::to_string(&map_data(target, trace, geoip_lookup, tie_break))?,
+        ),
+        _ => ("404 Not Found", "text/plain", String::from("not found")),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// The path of a `GET` request line, without any query string.
+fn request_path(request_line: &str) -> Option<&str> {
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => target.split('?').next(),
+        _ => None,
+    }
+}
+
+/// The location of each hop of `trace` for which `GeoIp` coordinates are known.
+///
+/// The location of a hop is that of its primary address, selected using `tie_break`.
+fn map_data(
+    target: &str,
+    trace: &State,
+    geoip_lookup: &GeoIpLookup,
+    tie_break: AddrTieBreak,
+) -> MapData {
+    let hops = trace
+        .hops()
+        .iter()
+        .filter_map(|hop| {
+            let addr = hop.best_addr(tie_break)?;
+            let geo = geoip_lookup.lookup(addr).ok().flatten()?;
+            let (latitude, longitude, accuracy_radius) = geo.coordinates()?;
+            Some(MapHop {
+                ttl: hop.ttl(),
+                addr,
+                latitude,
+                longitude,
+                accuracy_radius,
+                location: geo.long_name(),
+            })
+        })
+        .collect();
+    MapData {
+        target: target.to_string(),
+        round_count: trace.round_count(State::default_flow_id()),
+        hops,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::routed_trace;
+    use std::io::Read;
+    use std::net::Ipv4Addr;
+
+    fn geoip_lookup() -> GeoIpLookup {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/resources/ipinfo/ip_geolocation_extended_sample.mmdb"
+        );
+        GeoIpLookup::from_file(path, String::from("en")).unwrap()
+    }
+
+    /// Send a `GET` request for `path` to a server handling a single request for `trace`.
+    fn get(path: &'static str, trace: &State) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        handle(
+            stream,
+            "example.com",
+            trace,
+            &geoip_lookup(),
+            AddrTieBreak::FirstSeen,
+            CONNECTION_TIMEOUT,
+        )
+        .unwrap();
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn test_hops_endpoint() {
+        let trace = routed_trace(&[&[Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(1, 0, 0, 0)]]);
+        let response = get("/hops", &trace);
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains("Content-Type: application/json"));
+        let data: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!("example.com", data["target"]);
+        assert_eq!(1, data["round_count"]);
+        let hops = data["hops"].as_array().unwrap();
+        assert_eq!(1, hops.len());
+        assert_eq!(2, hops[0]["ttl"]);
+        assert_eq!("1.0.0.0", hops[0]["addr"]);
+        assert_eq!(Some(-33.86785), hops[0]["latitude"].as_f64());
+        assert_eq!(Some(151.20732), hops[0]["longitude"].as_f64());
+        assert_eq!(5000, hops[0]["accuracy_radius"]);
+    }
+
+    #[test]
+    fn test_index_page() {
+        let response = get("/", &routed_trace(&[]));
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Content-Type: text/html"));
+        assert!(response.ends_with(INDEX_HTML));
+    }
+
+    #[test]
+    fn test_not_found() {
+        let response = get("/other", &routed_trace(&[]));
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn test_stalled_client_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let err = handle(
+            stream,
+            "example.com",
+            &routed_trace(&[]),
+            &geoip_lookup(),
+            AddrTieBreak::FirstSeen,
+            Duration::from_millis(10),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ));
+        drop(client);
+    }
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(Some("/hops"), request_path("GET /hops?t=1 HTTP/1.1\r\n"));
+        assert_eq!(None, request_path("POST /hops HTTP/1.1\r\n"));
+        assert_eq!(None, request_path(""));
+    }
+}
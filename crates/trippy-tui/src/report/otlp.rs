@@ -0,0 +1,286 @@
+use crate::app::TraceInfo;
+use opentelemetry::trace::{Span, SpanKind, Status, TraceContextExt, Tracer, TracerProvider as _};
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use std::thread::sleep;
+use std::time::SystemTime;
+use tracing::instrument;
+use trippy_core::{AddrTieBreak, Hop, State};
+use trippy_dns::{DnsEntry, Resolved, Resolver, Unresolved};
+
+/// The name of the OpenTelemetry tracer.
+const TRACER_NAME: &str = "trippy";
+
+/// Emit each completed round of every trace of `traces` as OpenTelemetry spans to the OTLP/HTTP
+/// `endpoint`.
+///
+/// The spans are exported from a background thread which runs for as long as any of the traces
+/// do, using `resolver` to look up the hostname and AS of the primary address of each hop,
+/// selected using `tie_break`.
+#[instrument(skip(traces, resolver), level = "trace")]
+pub fn spawn<R: Resolver + Send + 'static>(
+    endpoint: &str,
+    traces: Vec<TraceInfo>,
+    resolver: R,
+    tie_break: AddrTieBreak,
+) -> anyhow::Result<()> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter)
+        .build();
+    std::thread::Builder::new()
+        .name(String::from("otlp"))
+        .spawn(move || export(&provider.tracer(TRACER_NAME), &traces, &resolver, tie_break))?;
+    Ok(())
+}
+
+/// Emit the spans for each round of each of `infos` as it completes, until every trace has
+/// failed or finished.
+fn export<R: Resolver>(
+    tracer: &SdkTracer,
+    infos: &[TraceInfo],
+    resolver: &R,
+    tie_break: AddrTieBreak,
+) {
+    let mut round_counts = vec![0; infos.len()];
+    let mut done = vec![false; infos.len()];
+    let interval = infos
+        .iter()
+        .map(|info| info.data.min_round_duration())
+        .min()
+        .unwrap_or_default();
+    while done.contains(&false) {
+        for ((info, round_count), done) in infos.iter().zip(&mut round_counts).zip(&mut done) {
+            if *done {
+                continue;
+            }
+            let trace = info.data.snapshot();
+            if trace.error().is_some() {
+                *done = true;
+                continue;
+            }
+            let current = trace.round_count(State::default_flow_id());
+            if current > *round_count {
+                emit_round(tracer, &info.target_hostname, &trace, resolver, tie_break);
+                *round_count = current;
+            }
+            *done = trace.finished();
+        }
+        sleep(interval);
+    }
+}
+
+/// Emit a span for the latest round of `trace` with a child span for each hop.
+///
+/// The child span of a hop starts when the probe was sent and lasts for the round trip time of
+/// the response, or is marked as an error if the probe did not receive a response.
+fn emit_round<R: Resolver>(
+    tracer: &SdkTracer,
+    target: &str,
+    trace: &State,
+    resolver: &R,
+    tie_break: AddrTieBreak,
+) {
+    let hops = trace.hops();
+    let Some(start) = hops.iter().filter_map(Hop::last_sent).min() else {
+        return;
+    };
+    let end = hops.iter().filter_map(hop_end).max().unwrap_or(start);
+    let reached = trace.destination_reached(State::default_flow_id());
+    let root = tracer
+        .span_builder(format!("trace {target}"))
+        .with_kind(SpanKind::Client)
+        .with_start_time(start)
+        .with_attributes([
+            KeyValue::new("trippy.target", target.to_string()),
+            KeyValue::new("trippy.destination_reached", reached),
+        ])
+        .start(tracer);
+    let cx = Context::current_with_span(root);
+    for hop in hops {
+        let Some(sent) = hop.last_sent() else {
+            continue;
+        };
+        let mut span = tracer
+            .span_builder(format!("hop {}", hop.ttl()))
+            .with_kind(SpanKind::Client)
+            .with_start_time(sent)
+            .with_attributes(hop_attributes(hop, resolver, tie_break))
+            .start_with_context(tracer, &cx);
+        if let Some(end) = hop_end(hop) {
+            span.end_with_timestamp(end);
+        } else {
+            span.set_status(Status::error("no response"));
+            span.end_with_timestamp(sent);
+        }
+    }
+    cx.span().end_with_timestamp(end);
+}
+
+/// The time the response to the latest probe of `hop` was received, if any.
+fn hop_end(hop: &Hop) -> Option<SystemTime> {
+    let rtt = hop.samples().first().filter(|rtt| !rtt.is_zero())?;
+    hop.last_sent().map(|sent| sent + *rtt)
+}
+
+/// The attributes of the span for `hop`.
+///
+/// The hostname and AS information are those of the primary address of the hop, selected using
+/// `tie_break`.
+fn hop_attributes<R: Resolver>(hop: &Hop, resolver: &R, tie_break: AddrTieBreak) -> Vec<KeyValue> {
+    let mut attributes = vec![
+        KeyValue::new("trippy.hop.ttl", i64::from(hop.ttl())),
+        KeyValue::new("trippy.hop.loss_pct", hop.loss_pct()),
+    ];
+    let Some(addr) = hop.best_addr(tie_break) else {
+        return attributes;
+    };
+    attributes.push(KeyValue::new("trippy.hop.addr", addr.to_string()));
+    let entry = resolver.lazy_reverse_lookup_with_asinfo(addr);
+    if let Some(hostname) = entry.hostnames().next() {
+        attributes.push(KeyValue::new("trippy.hop.hostname", hostname.to_string()));
+    }
+    if let DnsEntry::Resolved(Resolved::WithAsInfo(_, _, asinfo))
+    | DnsEntry::NotFound(Unresolved::WithAsInfo(_, asinfo)) = entry
+    {
+        attributes.push(KeyValue::new("trippy.hop.asn", asinfo.asn));
+        attributes.push(KeyValue::new("trippy.hop.as_name", asinfo.name));
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{
+        AsnResolver, NullResolver, StaticResolver, trace, unresponsive_trace,
+    };
+    use opentelemetry::Value;
+    use opentelemetry::trace::SpanId;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    }
+
+    #[test]
+    fn test_emit_round() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let resolver = StaticResolver(vec![
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), "hop1.example.com"),
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), "hop2.example.com"),
+        ]);
+        emit_round(
+            &provider.tracer(TRACER_NAME),
+            "example.com",
+            &trace(),
+            &resolver,
+            AddrTieBreak::FirstSeen,
+        );
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(3, spans.len());
+        let root = spans
+            .iter()
+            .find(|span| span.name == "trace example.com")
+            .unwrap();
+        assert_eq!(SpanId::INVALID, root.parent_span_id);
+        assert_eq!(
+            Some(Value::from(true)),
+            attribute(root, "trippy.destination_reached")
+        );
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(start, root.start_time);
+        assert_eq!(start + Duration::from_millis(20), root.end_time);
+        for (ttl, addr, rtt_ms) in [
+            (1, Ipv4Addr::new(10, 0, 0, 1), 10),
+            (2, Ipv4Addr::new(10, 0, 0, 2), 20),
+        ] {
+            let hop = spans
+                .iter()
+                .find(|span| span.name == format!("hop {ttl}"))
+                .unwrap();
+            assert_eq!(root.span_context.trace_id(), hop.span_context.trace_id());
+            assert_eq!(root.span_context.span_id(), hop.parent_span_id);
+            assert_eq!(start, hop.start_time);
+            assert_eq!(start + Duration::from_millis(rtt_ms), hop.end_time);
+            assert_eq!(Status::Unset, hop.status);
+            assert_eq!(Some(Value::from(ttl)), attribute(hop, "trippy.hop.ttl"));
+            assert_eq!(
+                Some(Value::from(addr.to_string())),
+                attribute(hop, "trippy.hop.addr")
+            );
+            assert_eq!(
+                Some(Value::from(format!("hop{ttl}.example.com"))),
+                attribute(hop, "trippy.hop.hostname")
+            );
+            assert_eq!(None, attribute(hop, "trippy.hop.asn"));
+        }
+    }
+
+    #[test]
+    fn test_emit_round_asn() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let resolver = AsnResolver(vec![
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), "64511"),
+            (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), "64512"),
+        ]);
+        emit_round(
+            &provider.tracer(TRACER_NAME),
+            "example.com",
+            &trace(),
+            &resolver,
+            AddrTieBreak::FirstSeen,
+        );
+        let spans = exporter.get_finished_spans().unwrap();
+        for (ttl, asn) in [(1, "64511"), (2, "64512")] {
+            let hop = spans
+                .iter()
+                .find(|span| span.name == format!("hop {ttl}"))
+                .unwrap();
+            assert_eq!(Some(Value::from(asn)), attribute(hop, "trippy.hop.asn"));
+            assert_eq!(None, attribute(hop, "trippy.hop.hostname"));
+        }
+    }
+
+    #[test]
+    fn test_emit_round_no_response() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        emit_round(
+            &provider.tracer(TRACER_NAME),
+            "example.com",
+            &unresponsive_trace(1),
+            &NullResolver,
+            AddrTieBreak::FirstSeen,
+        );
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(4, spans.len());
+        let hops = spans
+            .iter()
+            .filter(|span| span.name.starts_with("hop "))
+            .collect::<Vec<_>>();
+        assert_eq!(3, hops.len());
+        for hop in hops {
+            assert_eq!(Status::error("no response"), hop.status);
+            assert_eq!(hop.start_time, hop.end_time);
+            assert_eq!(None, attribute(hop, "trippy.hop.addr"));
+        }
+    }
+}
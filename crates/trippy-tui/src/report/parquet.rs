@@ -0,0 +1,175 @@
+use crate::app::TraceInfo;
+use anyhow::Context;
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt8Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::instrument;
+use trippy_core::State;
+
+/// Write the samples of each hop of `traces` to a Parquet file at `path`.
+///
+/// The file holds one row per sample of each hop, with the columns:
+///
+/// - `target`: the hostname of the target of the trace
+/// - `ttl`: the time-to-live of the hop
+/// - `addr`: the address which responded to the probe of the sample, or null if it was lost
+/// - `round`: the round of the tracer in which the probe of the sample was sent
+/// - `rtt_ms`: the round trip time of the sample in milliseconds, or null if it was lost
+/// - `loss_pct`: the loss of the hop over the whole trace
+///
+/// Only the most recent `max-samples` samples of each hop are retained by the tracer.
+#[instrument(skip(traces), level = "trace")]
+pub fn write_file(path: &Path, traces: &[TraceInfo]) -> anyhow::Result<()> {
+    let file = File::create(path).with_context(|| format!("parquet ({})", path.display()))?;
+    let traces = traces
+        .iter()
+        .map(|info| (info.target_hostname.as_str(), info.data.snapshot()))
+        .collect::<Vec<_>>();
+    write(file, &traces)
+}
+
+/// Write the samples of each hop of the named `traces` to `writer` as Parquet.
+fn write<W: Write + Send>(writer: W, traces: &[(&str, State)]) -> anyhow::Result<()> {
+    let batch = record_batch(traces)?;
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// An Arrow `RecordBatch` with a row for each sample of each hop of the named `traces`.
+///
+/// The samples of a hop, and the rounds and responding addresses of their probes, are held newest
+/// first and so are reversed such that the rows of each hop are in round order.
+fn record_batch(traces: &[(&str, State)]) -> anyhow::Result<RecordBatch> {
+    let mut target = Vec::new();
+    let mut ttl = Vec::new();
+    let mut addr = Vec::new();
+    let mut round = Vec::new();
+    let mut rtt_ms = Vec::new();
+    let mut loss_pct = Vec::new();
+    for (name, trace) in traces {
+        for hop in trace.hops() {
+            let samples = hop
+                .samples()
+                .iter()
+                .rev()
+                .zip(hop.sample_rounds().rev())
+                .zip(hop.sample_addrs().rev());
+            for ((sample, sample_round), sample_addr) in samples {
+                target.push(*name);
+                ttl.push(hop.ttl());
+                addr.push(sample_addr.map(|addr| addr.to_string()));
+                round.push(sample_round as u64);
+                rtt_ms.push((!sample.is_zero()).then_some(sample.as_secs_f64() * 1000_f64));
+                loss_pct.push(hop.loss_pct());
+            }
+        }
+    }
+    let schema = Schema::new(vec![
+        Field::new("target", DataType::Utf8, false),
+        Field::new("ttl", DataType::UInt8, false),
+        Field::new("addr", DataType::Utf8, true),
+        Field::new("round", DataType::UInt64, false),
+        Field::new("rtt_ms", DataType::Float64, true),
+        Field::new("loss_pct", DataType::Float64, false),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(target)),
+        Arc::new(UInt8Array::from(ttl)),
+        Arc::new(StringArray::from(addr)),
+        Arc::new(UInt64Array::from(round)),
+        Arc::new(Float64Array::from(rtt_ms)),
+        Arc::new(Float64Array::from(loss_pct)),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{routed_trace, sampled_trace, trace};
+    use arrow_array::Array;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::net::Ipv4Addr;
+    use std::path::PathBuf;
+
+    const HOP_A: Ipv4Addr = Ipv4Addr::new(192, 168, 0, 1);
+    const HOP_B: Ipv4Addr = Ipv4Addr::new(192, 168, 0, 2);
+    const HOP_C: Ipv4Addr = Ipv4Addr::new(192, 168, 0, 3);
+
+    fn temp_parquet_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "trippy-parquet-{}-{name}.parquet",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn column<'a, T: 'static>(batch: &'a RecordBatch, name: &str) -> &'a T {
+        batch
+            .column_by_name(name)
+            .unwrap()
+            .as_any()
+            .downcast_ref::<T>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_write_read_back() {
+        let path = temp_parquet_path("read-back");
+        let traces = [
+            ("sampled", sampled_trace(&[Some(10), None, Some(30)])),
+            ("routed", trace()),
+            ("ecmp", routed_trace(&[&[HOP_A, HOP_B], &[HOP_A, HOP_C]])),
+        ];
+        write(File::create(&path).unwrap(), &traces).unwrap();
+        let batches = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(1, batches.len());
+        let batch = &batches[0];
+        assert_eq!(9, batch.num_rows());
+        let target = column::<StringArray>(batch, "target");
+        assert_eq!("sampled", target.value(0));
+        assert_eq!("routed", target.value(3));
+        assert_eq!("ecmp", target.value(5));
+        let ttl = column::<UInt8Array>(batch, "ttl");
+        assert_eq!(vec![1, 1, 1, 1, 2, 1, 1, 2, 2], ttl.values().to_vec());
+        let addr = column::<StringArray>(batch, "addr");
+        assert_eq!(
+            vec![
+                Some("10.0.0.2"),
+                None,
+                Some("10.0.0.2"),
+                Some("10.0.0.1"),
+                Some("10.0.0.2"),
+                Some("192.168.0.1"),
+                Some("192.168.0.1"),
+                Some("192.168.0.2"),
+                Some("192.168.0.3"),
+            ],
+            addr.iter().collect::<Vec<_>>()
+        );
+        let round = column::<UInt64Array>(batch, "round");
+        assert_eq!(vec![0, 1, 2, 0, 0, 0, 1, 0, 1], round.values().to_vec());
+        let rtt_ms = column::<Float64Array>(batch, "rtt_ms");
+        assert_eq!(
+            vec![Some(10.0), None, Some(30.0), Some(10.0), Some(20.0)],
+            rtt_ms.iter().take(5).collect::<Vec<_>>()
+        );
+        let loss_pct = column::<Float64Array>(batch, "loss_pct");
+        assert!(!loss_pct.is_null(0));
+        assert!((loss_pct.value(0) - 100_f64 / 3_f64).abs() < f64::EPSILON * 100_f64);
+        assert_eq!(Some(0.0), loss_pct.iter().nth(3).flatten());
+    }
+}
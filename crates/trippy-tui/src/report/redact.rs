@@ -0,0 +1,226 @@
+use trippy_dns::{DnsEntry, Resolved};
+
+/// The text which replaces a redacted hostname.
+const REDACTED: &str = "[redacted]";
+
+/// The redaction and shortening of resolved hostnames in reports.
+///
+/// A resolved hostname is redacted if the hop is within `max_ttl` or if the hostname matches
+/// any of the configured domain suffixes.  The IP address of the hop is never redacted.
+///
+/// A resolved hostname may additionally be shortened for display by stripping any of the
+/// configured strip suffixes and truncating it to a maximum length, see `short_hostname`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Redaction {
+    /// The domain suffixes to redact, lowercase and without any leading `*.` wildcard.
+    domains: Vec<Domain>,
+    /// The maximum ttl of hops whose hostnames are redacted.
+    max_ttl: Option<u8>,
+    /// The domain suffixes to strip for display, lowercase and with a leading `.`.
+    strip_suffixes: Vec<String>,
+    /// The maximum length of hostnames for display.
+    max_hostname_len: Option<usize>,
+}
+
+impl Redaction {
+    /// Create a `Redaction` for the given domain suffixes and maximum ttl.
+    ///
+    /// A domain suffix such as `*.corp.example.com` matches any subdomain of `corp.example.com`
+    /// whereas `corp.example.com` also matches the domain itself.
+    pub fn new(domains: &[String], max_ttl: Option<u8>) -> Self {
+        Self {
+            domains: domains
+                .iter()
+                .map(|domain| Domain::from(domain.as_str()))
+                .collect(),
+            max_ttl,
+            strip_suffixes: vec![],
+            max_hostname_len: None,
+        }
+    }
+
+    /// Strip the given domain suffixes from hostnames returned by `short_hostname`.
+    ///
+    /// A suffix such as `akamaitechnologies.com` shortens `a1.deploy.akamaitechnologies.com` to
+    /// `a1.deploy` but a hostname which is equal to the suffix is not shortened.
+    #[must_use]
+    pub fn with_strip_suffixes(self, strip_suffixes: &[String]) -> Self {
+        Self {
+            strip_suffixes: strip_suffixes
+                .iter()
+                .map(|suffix| format!(".{}", suffix.trim_matches('.').to_ascii_lowercase()))
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Truncate hostnames returned by `short_hostname` to at most `max_hostname_len` characters.
+    ///
+    /// A truncated hostname ends with an ellipsis.
+    #[must_use]
+    pub fn with_max_hostname_len(self, max_hostname_len: Option<usize>) -> Self {
+        Self {
+            max_hostname_len,
+            ..self
+        }
+    }
+
+    /// The hostname(s) of `entry` for the hop at `ttl`, redacted if required.
+    pub fn hostname(&self, ttl: u8, entry: &DnsEntry) -> String {
+        if self.is_entry_redacted(ttl, entry) {
+            String::from(REDACTED)
+        } else {
+            entry.to_string()
+        }
+    }
+
+    /// The hostname(s) of `entry` for the hop at `ttl`, redacted if required and otherwise with
+    /// any strip suffixes removed and truncated to the maximum hostname length.
+    pub fn short_hostname(&self, ttl: u8, entry: &DnsEntry) -> String {
+        let strip = |hosts: &[String]| hosts.iter().map(|host| self.strip(host)).collect();
+        let display = |entry: &DnsEntry| match self.max_hostname_len {
+            Some(max) => entry.display_truncated(max),
+            None => entry.to_string(),
+        };
+        match entry {
+            _ if self.is_entry_redacted(ttl, entry) => String::from(REDACTED),
+            DnsEntry::Resolved(Resolved::Normal(addr, hosts)) => {
+                display(&DnsEntry::Resolved(Resolved::Normal(*addr, strip(hosts))))
+            }
+            DnsEntry::Resolved(Resolved::WithAsInfo(addr, hosts, asinfo)) => display(
+                &DnsEntry::Resolved(Resolved::WithAsInfo(*addr, strip(hosts), asinfo.clone())),
+            ),
+            _ => display(entry),
+        }
+    }
+
+    fn strip(&self, hostname: &str) -> String {
+        let hostname = hostname.trim_end_matches('.');
+        let lowercase = hostname.to_ascii_lowercase();
+        self.strip_suffixes
+            .iter()
+            .find(|suffix| lowercase.len() > suffix.len() && lowercase.ends_with(suffix.as_str()))
+            .map_or(hostname, |suffix| {
+                &hostname[..hostname.len() - suffix.len()]
+            })
+            .to_string()
+    }
+
+    fn is_entry_redacted(&self, ttl: u8, entry: &DnsEntry) -> bool {
+        entry
+            .hostnames()
+            .any(|hostname| self.is_redacted(ttl, hostname))
+    }
+
+    fn is_redacted(&self, ttl: u8, hostname: &str) -> bool {
+        let hostname = hostname.trim_end_matches('.').to_ascii_lowercase();
+        self.max_ttl.is_some_and(|max_ttl| ttl <= max_ttl)
+            || self.domains.iter().any(|domain| domain.matches(&hostname))
+    }
+}
+
+/// A domain suffix to redact.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Domain {
+    suffix: String,
+    subdomains_only: bool,
+}
+
+impl Domain {
+    fn matches(&self, hostname: &str) -> bool {
+        hostname
+            .strip_suffix(self.suffix.as_str())
+            .is_some_and(|prefix| {
+                prefix.ends_with('.') || (prefix.is_empty() && !self.subdomains_only)
+            })
+    }
+}
+
+impl From<&str> for Domain {
+    fn from(value: &str) -> Self {
+        let value = value.trim_end_matches('.').to_ascii_lowercase();
+        match value.strip_prefix("*.") {
+            Some(suffix) => Self {
+                suffix: suffix.to_string(),
+                subdomains_only: true,
+            },
+            None => Self {
+                suffix: value.trim_start_matches('.').to_string(),
+                subdomains_only: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+    use test_case::test_case;
+    use trippy_dns::{Resolved, Unresolved};
+
+    fn resolved(hostname: &str) -> DnsEntry {
+        DnsEntry::Resolved(Resolved::Normal(
+            IpAddr::from([10, 0, 0, 1]),
+            vec![String::from(hostname)],
+        ))
+    }
+
+    #[test_case("*.corp.example.com", "gw.corp.example.com", "[redacted]"; "wildcard subdomain")]
+    #[test_case("*.corp.example.com", "GW.Corp.Example.com.", "[redacted]"; "wildcard subdomain case and trailing dot")]
+    #[test_case("*.corp.example.com", "corp.example.com", "corp.example.com"; "wildcard domain itself")]
+    #[test_case("corp.example.com", "corp.example.com", "[redacted]"; "domain itself")]
+    #[test_case("corp.example.com", "a.b.corp.example.com", "[redacted]"; "nested subdomain")]
+    #[test_case("corp.example.com", "notcorp.example.com", "notcorp.example.com"; "partial label")]
+    #[test_case("corp.example.com", "one.one.one.one", "one.one.one.one"; "non matching")]
+    fn test_redact_domain(domain: &str, hostname: &str, expected: &str) {
+        let redaction = Redaction::new(&[String::from(domain)], None);
+        assert_eq!(expected, redaction.hostname(5, &resolved(hostname)));
+    }
+
+    #[test_case(1, "[redacted]"; "first hop")]
+    #[test_case(2, "[redacted]"; "max ttl hop")]
+    #[test_case(3, "host.example.com"; "after max ttl")]
+    fn test_redact_max_ttl(ttl: u8, expected: &str) {
+        let redaction = Redaction::new(&[], Some(2));
+        assert_eq!(
+            expected,
+            redaction.hostname(ttl, &resolved("host.example.com"))
+        );
+    }
+
+    #[test_case("a1-2-3-4.deploy.static.akamaitechnologies.com", "a1-2-3-4.deploy.static"; "strip suffix")]
+    #[test_case("A1-2-3-4.Deploy.Static.AkamaiTechnologies.com.", "A1-2-3-4.Deploy.Static"; "strip suffix case and trailing dot")]
+    #[test_case("akamaitechnologies.com", "akamaitechnologies.com"; "hostname equal to suffix")]
+    #[test_case("notakamaitechnologies.com", "notakamaitechnologies.com"; "partial label")]
+    #[test_case("one.one.one.one", "one.one.one.one"; "non matching")]
+    fn test_short_hostname(hostname: &str, expected: &str) {
+        let redaction =
+            Redaction::default().with_strip_suffixes(&[String::from(".akamaitechnologies.com")]);
+        assert_eq!(expected, redaction.short_hostname(5, &resolved(hostname)));
+        assert_eq!(hostname, redaction.hostname(5, &resolved(hostname)));
+    }
+
+    #[test_case("host.example.com", "host.example.com"; "shorter than max")]
+    #[test_case("very-long-hostname.example.com", "very-long-hostname.…"; "longer than max")]
+    fn test_short_hostname_max_len(hostname: &str, expected: &str) {
+        let redaction = Redaction::default().with_max_hostname_len(Some(20));
+        assert_eq!(expected, redaction.short_hostname(5, &resolved(hostname)));
+        assert_eq!(hostname, redaction.hostname(5, &resolved(hostname)));
+    }
+
+    #[test]
+    fn test_short_hostname_redacted() {
+        let redaction = Redaction::new(&[String::from("akamaitechnologies.com")], None)
+            .with_strip_suffixes(&[String::from("akamaitechnologies.com")]);
+        let entry = resolved("a1-2-3-4.deploy.static.akamaitechnologies.com");
+        assert_eq!("[redacted]", redaction.short_hostname(5, &entry));
+    }
+
+    #[test]
+    fn test_unresolved_not_redacted() {
+        let redaction = Redaction::new(&[String::from("example.com")], Some(10));
+        let entry = DnsEntry::NotFound(Unresolved::Normal(IpAddr::from([10, 0, 0, 1])));
+        assert_eq!("10.0.0.1", redaction.hostname(1, &entry));
+    }
+}
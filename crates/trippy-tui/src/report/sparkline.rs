@@ -0,0 +1,115 @@
+use std::time::Duration;
+use trippy_core::Hop;
+
+/// The Unicode characters of a sparkline, from lowest to highest.
+const UNICODE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The ASCII characters of a sparkline, from lowest to highest.
+const ASCII_LEVELS: [char; 8] = ['_', '.', '-', ':', '=', '+', '*', '#'];
+
+/// The character shown for a probe which did not receive a response.
+const NO_RESPONSE: char = ' ';
+
+/// The characters used to render a sparkline.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Charset {
+    /// Unicode block elements.
+    Unicode,
+    /// ASCII characters only.
+    Ascii,
+}
+
+/// A sparkline of the most recent round trip times of a hop.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Sparkline {
+    samples: usize,
+    charset: Charset,
+}
+
+impl Sparkline {
+    pub const fn new(samples: usize, charset: Charset) -> Self {
+        Self { samples, charset }
+    }
+
+    /// Render the last `samples` round trip times of `hop`, oldest first.
+    pub fn render(&self, hop: &Hop) -> String {
+        let samples = hop
+            .samples()
+            .iter()
+            .take(self.samples)
+            .rev()
+            .copied()
+            .collect::<Vec<_>>();
+        render(&samples, self.charset)
+    }
+}
+
+/// Render a character for each of `samples`, scaled between the smallest and largest of them.
+///
+/// Probes which did not receive a response are recorded as a zero sample and are not considered
+/// when scaling.
+fn render(samples: &[Duration], charset: Charset) -> String {
+    let levels = match charset {
+        Charset::Unicode => UNICODE_LEVELS,
+        Charset::Ascii => ASCII_LEVELS,
+    };
+    let responded = samples.iter().filter(|sample| !sample.is_zero());
+    let (Some(min), Some(max)) = (responded.clone().min(), responded.max()) else {
+        return samples.iter().map(|_| NO_RESPONSE).collect();
+    };
+    let range = max.saturating_sub(*min).as_nanos();
+    let top = (levels.len() - 1) as u128;
+    samples
+        .iter()
+        .map(|sample| {
+            if sample.is_zero() {
+                NO_RESPONSE
+            } else {
+                let offset = sample.saturating_sub(*min).as_nanos();
+                (offset * top + range / 2)
+                    .checked_div(range)
+                    .map_or(levels[0], |level| levels[level as usize])
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::sampled_trace;
+    use test_case::test_case;
+
+    #[test]
+    fn test_sparkline_ascending() {
+        let rtts = (1..=8).map(|rtt| Some(rtt * 10)).collect::<Vec<_>>();
+        let trace = sampled_trace(&rtts);
+        let sparkline = Sparkline::new(8, Charset::Unicode).render(&trace.hops()[0]);
+        let levels = sparkline
+            .chars()
+            .map(|c| UNICODE_LEVELS.iter().position(|&level| level == c).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(8, levels.len());
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!("▁▂▃▄▅▆▇█", sparkline);
+    }
+
+    #[test]
+    fn test_sparkline_ascii() {
+        let rtts = (1..=8).map(|rtt| Some(rtt * 10)).collect::<Vec<_>>();
+        let trace = sampled_trace(&rtts);
+        let sparkline = Sparkline::new(8, Charset::Ascii).render(&trace.hops()[0]);
+        assert!(sparkline.is_ascii());
+        assert_eq!("_.-:=+*#", sparkline);
+    }
+
+    #[test_case(&[Some(10), None, Some(30)], 3, "▁ █"; "no response")]
+    #[test_case(&[Some(10), Some(10)], 2, "▁▁"; "flat")]
+    #[test_case(&[None, None], 2, "  "; "all no response")]
+    #[test_case(&[Some(10), Some(20), Some(30)], 2, "▁█"; "most recent only")]
+    fn test_sparkline(rtts: &[Option<u64>], samples: usize, expected: &str) {
+        let trace = sampled_trace(rtts);
+        let sparkline = Sparkline::new(samples, Charset::Unicode).render(&trace.hops()[0]);
+        assert_eq!(expected, sparkline);
+    }
+}
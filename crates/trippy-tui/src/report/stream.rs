@@ -1,40 +1,155 @@
 use crate::app::TraceInfo;
+use crate::report::ReportOptions;
 use crate::report::types::Hop;
-use anyhow::anyhow;
+use std::io::Write;
+use std::net::IpAddr;
 use std::thread::sleep;
 use tracing::instrument;
-use trippy_dns::Resolver;
+use trippy_core::{AsnChange, AsnTracker, State};
+use trippy_dns::{DnsEntry, Resolved, Resolver, Unresolved};
 
 /// Display a continuous stream of trace data.
+///
+/// A hop whose AS has changed since the prior update, indicating a reroute, is marked with the
+/// old and new AS numbers and a hop whose route is flapping is marked as such.  If the
+/// `timestamps` report option is set then the trace start and end times are shown after each
+/// update.
 #[instrument(skip_all, level = "trace")]
-pub fn report<R: Resolver>(info: &TraceInfo, resolver: &R) -> anyhow::Result<()> {
-    println!(
+pub fn report<R: Resolver>(
+    sink: &mut dyn Write,
+    info: &TraceInfo,
+    options: &ReportOptions,
+    resolver: &R,
+) -> anyhow::Result<()> {
+    writeln!(
+        sink,
         "Tracing to {} ({})",
         info.target_hostname,
         info.data.target_addr()
-    );
+    )?;
+    let mut asn_tracker = AsnTracker::default();
     loop {
         let trace_data = &info.data.snapshot();
-        if let Some(err) = trace_data.error() {
-            return Err(anyhow!("error: {err}"));
-        }
-        for hop in trace_data.hops() {
-            let hop = Hop::from((hop, resolver));
-            let ttl = hop.ttl;
-            let addrs = hop.hosts.to_string();
-            let exts = hop.extensions.to_string();
-            let sent = hop.sent;
-            let recv = hop.recv;
-            let last = hop.last;
-            let best = hop.best;
-            let worst = hop.worst;
-            let stddev = hop.stddev;
-            let avg = hop.avg;
-            let loss_pct = hop.loss_pct;
-            println!(
-                "ttl={ttl} addrs={addrs} exts={exts} loss_pct={loss_pct:.1} sent={sent} recv={recv} last={last:.1} best={best:.1} worst={worst:.1} avg={avg:.1} stddev={stddev:.1}"
-            );
+        super::check_error(trace_data)?;
+        let asn_changes = asn_tracker.update(trace_data, |addr| asn(resolver, addr));
+        write_update(sink, trace_data, &asn_changes, options.timestamps, resolver)?;
+        if trace_data.finished() {
+            return Ok(());
         }
         sleep(info.data.min_round_duration());
     }
 }
+
+/// Write a line for each of the hops in `trace_data` to `sink`.
+fn write_update<R: Resolver>(
+    sink: &mut dyn Write,
+    trace_data: &State,
+    asn_changes: &[AsnChange],
+    timestamps: bool,
+    resolver: &R,
+) -> anyhow::Result<()> {
+    for hop in trace_data.hops() {
+        let asn_change = asn_changes
+            .iter()
+            .find(|change| change.ttl == hop.ttl())
+            .map(|change| format!(" asn_change=AS{}->AS{}", change.old, change.new))
+            .unwrap_or_default();
+        let hop = Hop::from((hop, resolver));
+        let ttl = hop.ttl;
+        let addrs = hop.hosts.to_string();
+        let exts = hop.extensions.to_string();
+        let sent = hop.sent;
+        let recv = hop.recv;
+        let last = hop.last;
+        let best = hop.best;
+        let worst = hop.worst;
+        let stddev = hop.stddev;
+        let avg = hop.avg;
+        let loss_pct = hop.loss_pct;
+        let flapping = if hop.flapping { " flapping" } else { "" };
+        writeln!(
+            sink,
+            "ttl={ttl} addrs={addrs} exts={exts} loss_pct={loss_pct:.1} sent={sent} recv={recv} last={last:.1} best={best:.1} worst={worst:.1} avg={avg:.1} stddev={stddev:.1}{asn_change}{flapping}"
+        )?;
+    }
+    if timestamps {
+        for line in super::timestamp_summary(trace_data) {
+            writeln!(sink, "{line}")?;
+        }
+    }
+    Ok(())
+}
+
+/// The AS number of `addr`, if known.
+fn asn<R: Resolver>(resolver: &R, addr: IpAddr) -> Option<String> {
+    match resolver.lazy_reverse_lookup_with_asinfo(addr) {
+        DnsEntry::Resolved(Resolved::WithAsInfo(_, _, asinfo))
+        | DnsEntry::NotFound(Unresolved::WithAsInfo(_, asinfo))
+            if !asinfo.asn.is_empty() =>
+        {
+            Some(asinfo.asn)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::{AsnResolver, NullResolver, routed_trace, trace};
+    use std::net::Ipv4Addr;
+
+    const HOP1: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const HOP2: Ipv4Addr = Ipv4Addr::new(10, 0, 1, 2);
+    const HOP2_REROUTED: Ipv4Addr = Ipv4Addr::new(10, 0, 2, 2);
+
+    #[test]
+    fn test_asn_change() {
+        let resolver = AsnResolver(vec![
+            (IpAddr::V4(HOP1), "64500"),
+            (IpAddr::V4(HOP2), "64501"),
+            (IpAddr::V4(HOP2_REROUTED), "64502"),
+        ]);
+        let mut tracker = AsnTracker::default();
+        let trace = routed_trace(&[&[HOP1, HOP2]]);
+        assert!(
+            tracker
+                .update(&trace, |addr| asn(&resolver, addr))
+                .is_empty()
+        );
+        let trace = routed_trace(&[&[HOP1, HOP2], &[HOP1, HOP2_REROUTED]]);
+        assert_eq!(
+            vec![AsnChange {
+                ttl: 2,
+                old: String::from("64501"),
+                new: String::from("64502"),
+            }],
+            tracker.update(&trace, |addr| asn(&resolver, addr))
+        );
+    }
+
+    #[test]
+    fn test_asn_unknown() {
+        let resolver = AsnResolver(vec![(IpAddr::V4(HOP1), "")]);
+        assert_eq!(None, asn(&resolver, IpAddr::V4(HOP1)));
+        assert_eq!(None, asn(&resolver, IpAddr::V4(HOP2)));
+    }
+
+    #[test]
+    fn test_write_update_timestamps() {
+        let mut sink = Vec::new();
+        write_update(&mut sink, &trace(), &[], true, &NullResolver).unwrap();
+        let output = String::from_utf8(sink).unwrap();
+        let summary = output
+            .lines()
+            .skip_while(|line| line.starts_with("ttl="))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "trace start: 2023-11-14T22:13:20.000Z",
+                "trace end: 2023-11-14T22:13:20.020Z",
+            ],
+            summary
+        );
+    }
+}
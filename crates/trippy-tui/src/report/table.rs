@@ -1,49 +1,309 @@
-use crate::app::TraceInfo;
-use crate::geoip::GeoIpLookup;
+use crate::config::{ReportColumn, UnprobedHopsConfig};
+use crate::diagnostics::Diagnostics;
+use crate::geoip::{GeoIpCity, GeoIpLookup, is_impossible_speed};
+use crate::report::ReportOptions;
+use crate::report::formatter::{ReportData, ReportFormatter};
+use crate::report::redact::Redaction;
+use crate::report::sparkline::Sparkline;
 use comfy_table::presets::{ASCII_MARKDOWN, UTF8_FULL};
-use comfy_table::{ContentArrangement, Table};
+use comfy_table::{ColumnConstraint, ContentArrangement, Table, Width};
 use itertools::Itertools;
-use tracing::instrument;
+use std::io::Write;
+use std::net::IpAddr;
+use std::rc::Rc;
+use trippy_core::{
+    AddrTieBreak, AddressClass, Hop, IcmpPacketCode, IcmpPacketType, State, address_class,
+};
 use trippy_dns::Resolver;
 
-/// Generate a Markdown table report of trace data.
-#[instrument(skip_all, level = "trace")]
-pub fn report_md<R: Resolver>(
-    info: &TraceInfo,
-    report_cycles: usize,
-    resolver: &R,
-    geoip_lookup: &GeoIpLookup,
-) -> anyhow::Result<()> {
-    run_report_table(info, report_cycles, resolver, ASCII_MARKDOWN, geoip_lookup)
+/// The options of table reports.
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    /// The `comfy_table` preset used to render the table.
+    pub preset: &'static str,
+    /// Warn of hops whose loss is consistent with ICMP rate limiting.
+    pub rate_limit_warnings: bool,
+    /// Show the end-to-end round trip time measured directly to the destination.
+    pub destination_rtt: bool,
+    /// Show the estimated Mean Opinion Score of each hop.
+    pub mos: bool,
+    /// Note hops whose round trip time coefficient of variation exceeds this threshold.
+    pub cv_threshold: Option<f64>,
+    /// The maximum width of the content of each column.
+    pub column_widths: Vec<(ReportColumn, u16)>,
+    /// Show a sparkline of the recent round trip times of each hop.
+    pub sparkline: Option<Sparkline>,
+    /// The first time-to-live which is probed.
+    pub first_ttl: u8,
+    /// How to show the hops below `first_ttl`.
+    pub unprobed_hops: UnprobedHopsConfig,
+    /// The default gateway, which is annotated if it is the first hop.
+    pub gateway: Option<IpAddr>,
+    /// How to select the primary address of a hop, which is used for its `GeoIp` location.
+    pub addr_tie_break: AddrTieBreak,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self {
+            preset: UTF8_FULL,
+            rate_limit_warnings: false,
+            destination_rtt: false,
+            mos: false,
+            cv_threshold: None,
+            column_widths: vec![],
+            sparkline: None,
+            first_ttl: 1,
+            unprobed_hops: UnprobedHopsConfig::Omit,
+            gateway: None,
+            addr_tie_break: AddrTieBreak::FirstSeen,
+        }
+    }
+}
+
+/// A `ReportFormatter` which writes a table of the hops of the trace.
+///
+/// The formatter is named `markdown` if the table is rendered with the `ASCII_MARKDOWN` preset and
+/// `pretty` otherwise.
+pub struct TableFormatter<'a, R> {
+    options: &'a ReportOptions,
+    table_options: &'a TableOptions,
+    resolver: &'a R,
+    geoip_lookup: &'a GeoIpLookup,
+    diagnostics: &'a Diagnostics,
 }
 
-/// Generate a pretty table report of trace data.
-#[instrument(skip_all, level = "trace")]
-pub fn report_pretty<R: Resolver>(
-    info: &TraceInfo,
-    report_cycles: usize,
+impl<'a, R: Resolver> TableFormatter<'a, R> {
+    pub const fn new(
+        options: &'a ReportOptions,
+        table_options: &'a TableOptions,
+        resolver: &'a R,
+        geoip_lookup: &'a GeoIpLookup,
+        diagnostics: &'a Diagnostics,
+    ) -> Self {
+        Self {
+            options,
+            table_options,
+            resolver,
+            geoip_lookup,
+            diagnostics,
+        }
+    }
+}
+
+impl<R: Resolver> ReportFormatter for TableFormatter<'_, R> {
+    fn name(&self) -> &'static str {
+        if self.table_options.preset == ASCII_MARKDOWN {
+            "markdown"
+        } else {
+            "pretty"
+        }
+    }
+
+    fn format(&self, sink: &mut dyn Write, data: &ReportData<'_>) -> anyhow::Result<()> {
+        write_table(
+            sink,
+            data.trace,
+            self.options,
+            self.table_options,
+            self.resolver,
+            self.geoip_lookup,
+            self.diagnostics,
+        )?;
+        if let Some(source_addr) = data.tracer.source_addr()
+            && let Some(warning) =
+                source_addr_advisory(data.trace, source_addr, data.tracer.target_addr())
+        {
+            self.diagnostics.emit(warning);
+        }
+        Ok(())
+    }
+}
+
+/// Write a table of the hops in `trace` to `sink`, and any advisories to `diagnostics`.
+pub fn write_table<R: Resolver>(
+    sink: &mut dyn Write,
+    trace: &State,
+    options: &ReportOptions,
+    table_options: &TableOptions,
     resolver: &R,
     geoip_lookup: &GeoIpLookup,
+    diagnostics: &Diagnostics,
 ) -> anyhow::Result<()> {
-    run_report_table(info, report_cycles, resolver, UTF8_FULL, geoip_lookup)
+    let table = render_table(
+        trace,
+        table_options,
+        resolver,
+        geoip_lookup,
+        &options.redaction,
+        options.time_to_first,
+    );
+    writeln!(sink, "{table}")?;
+    writeln!(sink, "{}", destination_summary(trace))?;
+    writeln!(sink, "{}", path_stability_summary(trace))?;
+    if table_options.destination_rtt {
+        writeln!(sink, "{}", destination_rtt_summary(trace))?;
+    }
+    if options.timestamps {
+        for line in super::timestamp_summary(trace) {
+            writeln!(sink, "{line}")?;
+        }
+    }
+    if table_options.rate_limit_warnings {
+        for warning in rate_limit_advisories(trace) {
+            diagnostics.emit(warning);
+        }
+    }
+    if let Some(cv_threshold) = table_options.cv_threshold {
+        for note in load_balance_advisories(trace, cv_threshold) {
+            diagnostics.emit(note);
+        }
+    }
+    Ok(())
 }
 
-fn run_report_table<R: Resolver>(
-    info: &TraceInfo,
-    report_cycles: usize,
+/// A summary line stating whether the destination was reached.
+fn destination_summary(trace: &State) -> &'static str {
+    if trace.destination_reached(State::default_flow_id()) {
+        "destination reached: yes"
+    } else if trace.destination_silent(State::default_flow_id()) {
+        "destination reached: no (silent)"
+    } else {
+        "destination reached: no"
+    }
+}
+
+/// A summary line of the stability score of the path.
+fn path_stability_summary(trace: &State) -> String {
+    format!(
+        "path stability: {:.0}/100",
+        trace.path_stability(State::default_flow_id())
+    )
+}
+
+/// A summary line of the end-to-end round trip time measured directly to the destination.
+fn destination_rtt_summary(trace: &State) -> String {
+    let rtt = trace.destination_rtt(State::default_flow_id());
+    match (rtt.last_ms(), rtt.best_ms(), rtt.worst_ms()) {
+        (Some(last), Some(best), Some(worst)) => format!(
+            "destination rtt: last {last:.1} ms, avg {:.1} ms, best {best:.1} ms, worst {worst:.1} ms, stddev {:.1} ms",
+            rtt.avg_ms(),
+            rtt.stddev_ms()
+        ),
+        _ => String::from("destination rtt: ???"),
+    }
+}
+
+/// Advisories for intermediate hops whose loss is consistent with ICMP rate limiting.
+fn rate_limit_advisories(trace: &State) -> Vec<String> {
+    trace
+        .hops()
+        .iter()
+        .filter(|hop| !trace.is_target(hop, State::default_flow_id()))
+        .filter(|hop| hop.is_icmp_rate_limited())
+        .map(|hop| {
+            format!(
+                "warning: loss at hop {} is consistent with ICMP rate limiting and may not reflect forwarding loss (hint: try increasing --min-round-duration)",
+                hop.ttl()
+            )
+        })
+        .collect()
+}
+
+/// An advisory for a private `source_addr` used to trace a public `target_addr` which was not reached.
+///
+/// Tracing a public target from a private source address is normal behind a NAT, so the advisory
+/// is only given if the target did not respond, as can happen if the wrong source address is
+/// selected on a multi-homed host.
+fn source_addr_advisory(trace: &State, source_addr: IpAddr, target_addr: IpAddr) -> Option<String> {
+    (!trace.destination_reached(State::default_flow_id())
+        && address_class(source_addr) == AddressClass::Private
+        && address_class(target_addr) == AddressClass::Public)
+        .then(|| {
+            format!(
+                "warning: private source address {source_addr} used for public target {target_addr} which was not reached (hint: try setting --source-address or --interface)"
+            )
+        })
+}
+
+/// Advisories for hops whose round trip time coefficient of variation exceeds `cv_threshold`.
+fn load_balance_advisories(trace: &State, cv_threshold: f64) -> Vec<String> {
+    trace
+        .hops()
+        .iter()
+        .filter(|hop| hop.is_likely_load_balanced(cv_threshold))
+        .map(|hop| {
+            format!(
+                "note: rtt at hop {} varies widely (cv {:.0}%) and the hop is likely load-balanced",
+                hop.ttl(),
+                hop.cv().unwrap_or_default() * 100_f64
+            )
+        })
+        .collect()
+}
+
+/// Render a table of the hops in `trace` using the `comfy_table` preset of `options`.
+///
+/// Resolved hostnames are redacted and shortened as determined by `redaction`, and the content
+/// of each column in `column_widths` is truncated to the given maximum width.  If `mos` is set
+/// then a column showing the estimated Mean Opinion Score of each hop is added.  If `sparkline` is
+/// set then a column showing a sparkline of the recent round trip times of each hop is added and
+/// if `time_to_first` is set then a column showing the time from the start of the trace to the
+/// first response from each hop is added.
+///
+/// The hops below `first_ttl` are never probed and are either omitted or shown as not probed, as
+/// determined by `unprobed_hops`.
+///
+/// If `gateway` is set and the first hop responded from it then the hop is annotated as the
+/// gateway.  Hops whose `GeoIp` location could not have been reached from the prior hop without
+/// travelling faster than light in fibre are annotated as having suspect `GeoIp` data.
+pub fn render_table<R: Resolver>(
+    trace: &State,
+    options: &TableOptions,
     resolver: &R,
-    preset: &str,
     geoip_lookup: &GeoIpLookup,
-) -> anyhow::Result<()> {
-    let trace = super::wait_for_round(&info.data, report_cycles)?;
-    let columns = vec![
+    redaction: &Redaction,
+    time_to_first: bool,
+) -> Table {
+    let mut columns = vec![
         "Hop", "IPs", "Addrs", "Loss%", "Snt", "Recv", "Last", "Avg", "Best", "Wrst", "StdDev",
     ];
+    if options.mos {
+        columns.push("MOS");
+    }
+    let column_widths = options
+        .column_widths
+        .iter()
+        .copied()
+        .filter(|&(column, _)| options.mos || column != ReportColumn::Mos)
+        .collect::<Vec<_>>();
+    if time_to_first {
+        columns.push("TTF");
+    }
+    if options.sparkline.is_some() {
+        columns.push("Trend");
+    }
+    let columns_len = columns.len();
     let mut table = Table::new();
     table
-        .load_preset(preset)
+        .load_preset(options.preset)
         .set_content_arrangement(ContentArrangement::Dynamic)
         .set_header(columns);
+    for &(column, width) in &column_widths {
+        if let Some(column) = table.column_mut(column as usize) {
+            column.set_constraint(ColumnConstraint::UpperBoundary(Width::Fixed(width)));
+        }
+    }
+    if options.unprobed_hops == UnprobedHopsConfig::Show {
+        for ttl in 1..options.first_ttl {
+            let mut row = unprobed_row(ttl, columns_len);
+            for &(column, width) in &column_widths {
+                let cell = &mut row[column as usize];
+                *cell = truncate_lines(cell, usize::from(width));
+            }
+            table.add_row(row);
+        }
+    }
     for hop in trace.hops() {
         let ttl = hop.ttl().to_string();
         let ips = hop.addrs().join("\n");
@@ -52,21 +312,7 @@ fn run_report_table<R: Resolver>(
         } else {
             ips
         };
-        let hosts = hop
-            .addrs()
-            .map(|ip| {
-                if let Ok(Some(geo)) = geoip_lookup.lookup(*ip) {
-                    geo.raw().unwrap_or_default().to_string()
-                } else {
-                    resolver.reverse_lookup(*ip).to_string()
-                }
-            })
-            .join("\n");
-        let host = if hosts.is_empty() {
-            String::from("???")
-        } else {
-            hosts
-        };
+        let host = render_host(trace, hop, options, resolver, geoip_lookup, redaction);
         let sent = hop.total_sent().to_string();
         let recv = hop.total_recv().to_string();
         let last = hop
@@ -81,10 +327,689 @@ fn run_report_table<R: Resolver>(
         let stddev = format!("{:.1}", hop.stddev_ms());
         let avg = format!("{:.1}", hop.avg_ms());
         let loss_pct = format!("{:.1}", hop.loss_pct());
-        table.add_row(vec![
-            &ttl, &ip, &host, &loss_pct, &sent, &recv, &last, &avg, &best, &worst, &stddev,
+        let mut row = vec![
+            ttl, ip, host, loss_pct, sent, recv, last, avg, best, worst, stddev,
+        ];
+        if options.mos {
+            row.push(
+                hop.mos()
+                    .map_or_else(|| String::from("???"), |mos| format!("{mos:.2}")),
+            );
+        }
+        for &(column, width) in &column_widths {
+            let cell = &mut row[column as usize];
+            *cell = truncate_lines(cell, usize::from(width));
+        }
+        if time_to_first {
+            row.push(
+                hop.time_to_first_ms()
+                    .map_or_else(|| String::from("???"), |ttf| format!("{ttf:.1}")),
+            );
+        }
+        if let Some(sparkline) = options.sparkline {
+            row.push(sparkline.render(hop));
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+/// Render the hosts of `hop`, annotated as filtered, unreachable, the gateway or as having
+/// suspect `GeoIp` data.
+fn render_host<R: Resolver>(
+    trace: &State,
+    hop: &Hop,
+    options: &TableOptions,
+    resolver: &R,
+    geoip_lookup: &GeoIpLookup,
+    redaction: &Redaction,
+) -> String {
+    let hosts = hop
+        .addrs()
+        .map(|ip| {
+            if let Ok(Some(geo)) = geoip_lookup.lookup(*ip) {
+                geo.raw().unwrap_or_default().to_string()
+            } else {
+                redaction.short_hostname(hop.ttl(), &resolver.reverse_lookup(*ip))
+            }
+        })
+        .join("\n");
+    let host = if hosts.is_empty() {
+        String::from("???")
+    } else if hop.is_filtered() {
+        format!("{hosts} [filtered]")
+    } else if let Some(label) = unreachable_label(hop) {
+        format!("{hosts} [{label}]")
+    } else {
+        hosts
+    };
+    let host = if hop.ttl() == 1
+        && options
+            .gateway
+            .is_some_and(|gateway| hop.addrs().any(|addr| *addr == gateway))
+    {
+        format!("{host} [gateway]")
+    } else {
+        host
+    };
+    if is_suspect_geoip(trace, hop, geoip_lookup, options.addr_tie_break) {
+        format!("{host} [suspect geoip]")
+    } else {
+        host
+    }
+}
+
+/// A label for the ICMP `DestinationUnreachable` code of the latest response from `hop`.
+///
+/// Only the IPv4 codes 6 (destination network unknown) and 7 (destination host unknown) are
+/// labelled, as these indicate a routing black hole rather than filtering or an unreachable port.
+fn unreachable_label(hop: &Hop) -> Option<&'static str> {
+    match (hop.last_icmp_packet_type(), hop.addrs().next()) {
+        (Some(IcmpPacketType::Unreachable(IcmpPacketCode(6))), Some(IpAddr::V4(_))) => {
+            Some("net unknown")
+        }
+        (Some(IcmpPacketType::Unreachable(IcmpPacketCode(7))), Some(IpAddr::V4(_))) => {
+            Some("host unknown")
+        }
+        _ => None,
+    }
+}
+
+/// Is the `GeoIp` location of `hop` suspect?
+///
+/// The location is suspect if reaching it from the location of the nearest prior hop would
+/// require travel faster than light in fibre, given the increase in best round trip time between
+/// the two hops, which indicates that the `GeoIp` data for one of them is wrong.
+///
+/// The location of each hop is that of its primary address, selected using `tie_break`.
+fn is_suspect_geoip(
+    trace: &State,
+    hop: &Hop,
+    geoip_lookup: &GeoIpLookup,
+    tie_break: AddrTieBreak,
+) -> bool {
+    let locate = |hop: &Hop| -> Option<(Rc<GeoIpCity>, f64)> {
+        let geo = geoip_lookup.lookup(hop.best_addr(tie_break)?).ok()??;
+        Some((geo, hop.best_ms()?))
+    };
+    let Some((geo, best)) = locate(hop) else {
+        return false;
+    };
+    trace
+        .hops()
+        .iter()
+        .rev()
+        .filter(|prior| prior.ttl() < hop.ttl())
+        .find_map(locate)
+        .and_then(|(prior_geo, prior_best)| {
+            geo.min_distance_km(&prior_geo)
+                .map(|distance| is_impossible_speed(distance, best - prior_best))
+        })
+        .unwrap_or_default()
+}
+
+/// A row for a hop at `ttl` which was not probed, distinct from a hop which did not respond.
+fn unprobed_row(ttl: u8, columns_len: usize) -> Vec<String> {
+    let mut row = vec![String::from("-"); columns_len];
+    row[0] = ttl.to_string();
+    row[1] = String::from("not probed");
+    row
+}
+
+/// Truncate each line of `content` to at most `width` characters.
+///
+/// Truncated lines end with an ellipsis.
+fn truncate_lines(content: &str, width: usize) -> String {
+    content
+        .lines()
+        .map(|line| {
+            if line.chars().count() > width {
+                let mut truncated = line.chars().take(width - 1).collect::<String>();
+                truncated.push('…');
+                truncated
+            } else {
+                line.to_string()
+            }
+        })
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::SharedSink;
+    use crate::report::sparkline::Charset;
+    use crate::report::testing::{
+        NullResolver, StaticResolver, TARGET_ADDR, filtered_trace, first_ttl_trace, report_options,
+        sampled_trace, trace, unreachable_trace, unresponsive_trace,
+    };
+    use std::net::{IpAddr, Ipv4Addr};
+    use test_case::test_case;
+
+    fn markdown() -> TableOptions {
+        TableOptions {
+            preset: ASCII_MARKDOWN,
+            ..TableOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_write_table_md() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &report_options(),
+            &markdown(),
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.1 | 10.0.0.1 | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 2   | 10.0.0.2 | 10.0.0.2 | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_redacted() {
+        let resolver = StaticResolver(vec![
+            (
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                "gw.corp.example.com",
+            ),
+            (TARGET_ADDR, "target.example.net"),
         ]);
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &ReportOptions {
+                redaction: Redaction::new(&[String::from("*.corp.example.com")], None),
+                ..report_options()
+            },
+            &markdown(),
+            &resolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs              | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|--------------------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.1 | [redacted]         | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 2   | 10.0.0.2 | target.example.net | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_strip_suffixes() {
+        let resolver = StaticResolver(vec![
+            (
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                "a1-2-3-4.deploy.static.akamaitechnologies.com",
+            ),
+            (TARGET_ADDR, "target.example.net"),
+        ]);
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &ReportOptions {
+                redaction: Redaction::default()
+                    .with_strip_suffixes(&[String::from(".akamaitechnologies.com")]),
+                ..report_options()
+            },
+            &markdown(),
+            &resolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs                  | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|------------------------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.1 | a1-2-3-4.deploy.static | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 2   | 10.0.0.2 | target.example.net     | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_column_widths() {
+        let resolver = StaticResolver(vec![(
+            TARGET_ADDR,
+            "a-very-long-reverse-dns-name.target.example.net",
+        )]);
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &report_options(),
+            &TableOptions {
+                column_widths: vec![(ReportColumn::Addrs, 16)],
+                ..markdown()
+            },
+            &resolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs            | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|------------------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.1 | 10.0.0.1         | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 2   | 10.0.0.2 | a-very-long-rev… | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_sparkline() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &sampled_trace(&[Some(10), Some(20), Some(30)]),
+            &report_options(),
+            &TableOptions {
+                sparkline: Some(Sparkline::new(3, Charset::Ascii)),
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev | Trend |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|-------|
+| 1   | 10.0.0.2 | 10.0.0.2 | 0.0   | 3   | 3    | 30.0 | 20.0 | 10.0 | 30.0 | 7.9    | _=#   |
+destination reached: yes
+path stability: 92/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test_case(UnprobedHopsConfig::Omit, "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|
+| 5   | 10.0.0.1 | 10.0.0.1 | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 6   | ???      | ???      | 100.0 | 1   | 0    | ???  | 0.0  | ???  | ???  | 0.0    |
+| 7   | 10.0.0.2 | 10.0.0.2 | 0.0   | 1   | 1    | 30.0 | 30.0 | 30.0 | 30.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+"; "omit")]
+    #[test_case(UnprobedHopsConfig::Show, "\
+| Hop | IPs        | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|------------|----------|-------|-----|------|------|------|------|------|--------|
+| 1   | not probed | -        | -     | -   | -    | -    | -    | -    | -    | -      |
+| 2   | not probed | -        | -     | -   | -    | -    | -    | -    | -    | -      |
+| 3   | not probed | -        | -     | -   | -    | -    | -    | -    | -    | -      |
+| 4   | not probed | -        | -     | -   | -    | -    | -    | -    | -    | -      |
+| 5   | 10.0.0.1   | 10.0.0.1 | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 6   | ???        | ???      | 100.0 | 1   | 0    | ???  | 0.0  | ???  | ???  | 0.0    |
+| 7   | 10.0.0.2   | 10.0.0.2 | 0.0   | 1   | 1    | 30.0 | 30.0 | 30.0 | 30.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+"; "show")]
+    fn test_write_table_md_unprobed_hops(unprobed_hops: UnprobedHopsConfig, expected: &str) {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &first_ttl_trace(5),
+            &report_options(),
+            &TableOptions {
+                first_ttl: 5,
+                unprobed_hops,
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test_case(Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))), true; "gateway")]
+    #[test_case(Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 254))), false; "other gateway")]
+    #[test_case(Some(TARGET_ADDR), false; "not first hop")]
+    #[test_case(None, false; "no gateway")]
+    fn test_write_table_md_gateway(gateway: Option<IpAddr>, expected: bool) {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &report_options(),
+            &TableOptions {
+                gateway,
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let table = String::from_utf8(sink).unwrap();
+        let hops = table.lines().skip(2).take(2).collect::<Vec<_>>();
+        assert_eq!(expected, hops[0].contains("| 10.0.0.1 [gateway] |"));
+        assert!(!hops[1].contains("[gateway]"));
+    }
+
+    #[test_case("example.com", 20, "example.com"; "shorter")]
+    #[test_case("example.com", 11, "example.com"; "exact")]
+    #[test_case("host.example.com", 8, "host.ex…"; "longer")]
+    #[test_case("a.example.com\nb.example.com", 6, "a.exa…\nb.exa…"; "multiple lines")]
+    fn test_truncate_lines(content: &str, width: usize, expected: &str) {
+        assert_eq!(expected, truncate_lines(content, width));
+    }
+
+    #[test]
+    fn test_write_table_md_filtered() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &filtered_trace(),
+            &report_options(),
+            &markdown(),
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs               | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|---------------------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.1 | 10.0.0.1            | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    |
+| 2   | 10.0.0.2 | 10.0.0.2 [filtered] | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test_case(6, "| 10.0.0.2 [net unknown] |"; "net unknown")]
+    #[test_case(7, "| 10.0.0.2 [host unknown] |"; "host unknown")]
+    fn test_write_table_md_unreachable(code: u8, expected_host: &str) {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &unreachable_trace(code),
+            &report_options(),
+            &markdown(),
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let report = String::from_utf8(sink).unwrap();
+        let hop = report.lines().nth(3).unwrap();
+        assert!(hop.starts_with("| 2 "), "{hop}");
+        assert!(hop.contains(expected_host), "{hop}");
+    }
+
+    #[test]
+    fn test_write_table_md_destination_not_reached() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &unresponsive_trace(1),
+            &report_options(),
+            &markdown(),
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs | Addrs | Loss% | Snt | Recv | Last | Avg | Best | Wrst | StdDev |
+|-----|-----|-------|-------|-----|------|------|-----|------|------|--------|
+| 1   | ??? | ???   | 100.0 | 1   | 0    | ???  | 0.0 | ???  | ???  | 0.0    |
+| 2   | ??? | ???   | 100.0 | 1   | 0    | ???  | 0.0 | ???  | ???  | 0.0    |
+| 3   | ??? | ???   | 100.0 | 1   | 0    | ???  | 0.0 | ???  | ???  | 0.0    |
+destination reached: no
+path stability: 0/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_destination_rtt() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &sampled_trace(&[Some(10), None, Some(12), Some(9)]),
+            &report_options(),
+            &TableOptions {
+                destination_rtt: true,
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.2 | 10.0.0.2 | 25.0  | 4   | 3    | 9.0  | 10.3 | 9.0  | 12.0 | 1.2    |
+destination reached: yes
+path stability: 88/100
+destination rtt: last 9.0 ms, avg 10.3 ms, best 9.0 ms, worst 12.0 ms, stddev 1.5 ms
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_time_to_first() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &ReportOptions {
+                time_to_first: true,
+                ..report_options()
+            },
+            &markdown(),
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev | TTF  |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|------|
+| 1   | 10.0.0.1 | 10.0.0.1 | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    | 10.0 |
+| 2   | 10.0.0.2 | 10.0.0.2 | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    | 20.0 |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_mos() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &report_options(),
+            &TableOptions {
+                mos: true,
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev | MOS  |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|------|
+| 1   | 10.0.0.1 | 10.0.0.1 | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    | 4.39 |
+| 2   | 10.0.0.2 | 10.0.0.2 | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    | 4.37 |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    // The width of the MOS column is ignored if the column is not shown, rather than applied to
+    // the column which follows it.
+    #[test]
+    fn test_write_table_md_mos_width_without_mos() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &trace(),
+            &ReportOptions {
+                time_to_first: true,
+                ..report_options()
+            },
+            &TableOptions {
+                column_widths: vec![(ReportColumn::Mos, 3)],
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev | TTF  |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|------|
+| 1   | 10.0.0.1 | 10.0.0.1 | 0.0   | 1   | 1    | 10.0 | 10.0 | 10.0 | 10.0 | 0.0    | 10.0 |
+| 2   | 10.0.0.2 | 10.0.0.2 | 0.0   | 1   | 1    | 20.0 | 20.0 | 20.0 | 20.0 | 0.0    | 20.0 |
+destination reached: yes
+path stability: 100/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+    }
+
+    #[test]
+    fn test_write_table_md_timestamps() {
+        let mut sink = Vec::new();
+        write_table(
+            &mut sink,
+            &sampled_trace(&[Some(10), Some(12)]),
+            &ReportOptions {
+                timestamps: true,
+                ..report_options()
+            },
+            &markdown(),
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &Diagnostics::quiet(),
+        )
+        .unwrap();
+        let output = String::from_utf8(sink).unwrap();
+        let summary: Vec<_> = output
+            .lines()
+            .skip_while(|line| line.starts_with('|'))
+            .collect();
+        assert_eq!(
+            vec![
+                "destination reached: yes",
+                "path stability: 98/100",
+                "trace start: 2023-11-14T22:13:20.000Z",
+                "trace end: 2023-11-14T22:13:20.012Z",
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn test_destination_rtt_summary_not_reached() {
+        assert_eq!(
+            "destination rtt: ???",
+            destination_rtt_summary(&unresponsive_trace(1))
+        );
+    }
+
+    #[test_case(false; "not quiet")]
+    #[test_case(true; "quiet")]
+    fn test_write_table_md_load_balanced(quiet: bool) {
+        let mut sink = Vec::new();
+        let diagnostics_sink = SharedSink::default();
+        let diagnostics = if quiet {
+            Diagnostics::quiet()
+        } else {
+            Diagnostics::new(Box::new(diagnostics_sink.clone()))
+        };
+        write_table(
+            &mut sink,
+            &sampled_trace(&[Some(10), Some(90)]),
+            &report_options(),
+            &TableOptions {
+                cv_threshold: Some(0.5),
+                ..markdown()
+            },
+            &NullResolver,
+            &GeoIpLookup::empty(),
+            &diagnostics,
+        )
+        .unwrap();
+        let expected = "\
+| Hop | IPs      | Addrs    | Loss% | Snt | Recv | Last | Avg  | Best | Wrst | StdDev |
+|-----|----------|----------|-------|-----|------|------|------|------|------|--------|
+| 1   | 10.0.0.2 | 10.0.0.2 | 0.0   | 2   | 2    | 90.0 | 50.0 | 10.0 | 90.0 | 40.0   |
+destination reached: yes
+path stability: 84/100
+";
+        assert_eq!(expected, String::from_utf8(sink).unwrap());
+        let expected_diagnostics = if quiet {
+            ""
+        } else {
+            "note: rtt at hop 1 varies widely (cv 80%) and the hop is likely load-balanced\n"
+        };
+        assert_eq!(expected_diagnostics, diagnostics_sink.output());
+    }
+
+    #[test_case(&[Some(10), Some(90)], 0.5, 1; "high variance")]
+    #[test_case(&[Some(48), Some(52)], 0.5, 0; "low variance")]
+    #[test_case(&[Some(10), Some(90)], 0.9, 0; "high variance below threshold")]
+    #[test_case(&[Some(50)], 0.0, 0; "single sample")]
+    fn test_load_balance_advisories(rtts: &[Option<u64>], cv_threshold: f64, expected: usize) {
+        let trace = sampled_trace(rtts);
+        assert_eq!(
+            expected,
+            load_balance_advisories(&trace, cv_threshold).len()
+        );
+    }
+
+    #[test_case(false, "192.168.0.1", "1.1.1.1", true; "private source public target not reached")]
+    #[test_case(true, "192.168.0.1", "1.1.1.1", false; "private source public target reached")]
+    #[test_case(false, "10.0.0.1", "10.0.0.2", false; "private source private target")]
+    #[test_case(false, "8.8.8.8", "1.1.1.1", false; "public source public target")]
+    fn test_source_addr_advisory(
+        reached: bool,
+        source_addr: &str,
+        target_addr: &str,
+        expected: bool,
+    ) {
+        let trace = if reached {
+            trace()
+        } else {
+            unresponsive_trace(1)
+        };
+        let source_addr = source_addr.parse().unwrap();
+        let target_addr = target_addr.parse().unwrap();
+        assert_eq!(
+            expected,
+            source_addr_advisory(&trace, source_addr, target_addr).is_some()
+        );
     }
-    println!("{table}");
-    Ok(())
 }
@@ -0,0 +1,383 @@
+use crate::report::ReportOptions;
+use crate::report::formatter::{ReportData, ReportFormatter};
+use crate::report::redact::Redaction;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, SystemTime};
+use trippy_core::{
+    Builder, CompletionReason, Flags, IcmpPacketCode, IcmpPacketType, Port, Probe, ProbeComplete,
+    ProbeStatus, Round, RoundId, Sequence, State, TimeToLive, TraceId, Tracer, TypeOfService,
+};
+use trippy_dns::{AsInfo, DnsEntry, Resolved, ResolvedIpAddrs, Resolver, Unresolved};
+
+/// The `ReportOptions` of a report of a single round.
+pub fn report_options() -> ReportOptions {
+    ReportOptions {
+        report_cycles: 1,
+        fail_fast: None,
+        min_samples: None,
+        timestamps: false,
+        response_dscp: false,
+        time_to_first: false,
+        config: false,
+        redaction: Redaction::default(),
+    }
+}
+
+/// A tracer to `TARGET_ADDR` with the default configuration.
+pub fn tracer() -> Tracer {
+    Builder::new(TARGET_ADDR).build().unwrap()
+}
+
+/// Format `trace`, of a trace to `TARGET_ADDR` named `target`, with `formatter`.
+pub fn format(formatter: &dyn ReportFormatter, trace: &State) -> String {
+    format_with_tracer(formatter, &tracer(), trace)
+}
+
+/// Format `trace`, of a trace by `tracer` named `target`, with `formatter`.
+pub fn format_with_tracer(
+    formatter: &dyn ReportFormatter,
+    tracer: &Tracer,
+    trace: &State,
+) -> String {
+    let mut sink = Vec::new();
+    formatter
+        .format(&mut sink, &ReportData::new("target", tracer, trace))
+        .unwrap();
+    String::from_utf8(sink).unwrap()
+}
+
+/// A `Resolver` which never resolves any address.
+pub struct NullResolver;
+
+impl Resolver for NullResolver {
+    fn lookup(&self, _hostname: impl AsRef<str>) -> trippy_dns::Result<ResolvedIpAddrs> {
+        unimplemented!()
+    }
+
+    fn reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        DnsEntry::NotFound(Unresolved::Normal(addr.into()))
+    }
+
+    fn reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup(addr)
+    }
+
+    fn lazy_reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup(addr)
+    }
+
+    fn lazy_reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup(addr)
+    }
+}
+
+/// A `Resolver` which resolves each address to a fixed hostname, if any.
+pub struct StaticResolver(pub Vec<(IpAddr, &'static str)>);
+
+impl Resolver for StaticResolver {
+    fn lookup(&self, _hostname: impl AsRef<str>) -> trippy_dns::Result<ResolvedIpAddrs> {
+        unimplemented!()
+    }
+
+    fn reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        let addr = addr.into();
+        self.0
+            .iter()
+            .find(|(ip, _)| *ip == addr)
+            .map_or(DnsEntry::NotFound(Unresolved::Normal(addr)), |(_, host)| {
+                DnsEntry::Resolved(Resolved::Normal(addr, vec![String::from(*host)]))
+            })
+    }
+
+    fn reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup(addr)
+    }
+
+    fn lazy_reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup(addr)
+    }
+
+    fn lazy_reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup(addr)
+    }
+}
+
+/// A `Resolver` which resolves each address to a fixed AS number, if any.
+pub struct AsnResolver(pub Vec<(IpAddr, &'static str)>);
+
+impl Resolver for AsnResolver {
+    fn lookup(&self, _hostname: impl AsRef<str>) -> trippy_dns::Result<ResolvedIpAddrs> {
+        unimplemented!()
+    }
+
+    fn reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup_with_asinfo(addr)
+    }
+
+    fn reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        let addr = addr.into();
+        self.0.iter().find(|(ip, _)| *ip == addr).map_or(
+            DnsEntry::NotFound(Unresolved::Normal(addr)),
+            |(_, asn)| {
+                let asinfo = AsInfo {
+                    asn: String::from(*asn),
+                    ..AsInfo::default()
+                };
+                DnsEntry::NotFound(Unresolved::WithAsInfo(addr, asinfo))
+            },
+        )
+    }
+
+    fn lazy_reverse_lookup(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup_with_asinfo(addr)
+    }
+
+    fn lazy_reverse_lookup_with_asinfo(&self, addr: impl Into<IpAddr>) -> DnsEntry {
+        self.reverse_lookup_with_asinfo(addr)
+    }
+}
+
+/// The target address of the synthesized trace.
+pub const TARGET_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+/// Synthesize a single round trace of two hops, with 10ms and 20ms round trip times.
+pub fn trace() -> State {
+    two_hop_trace(IcmpPacketType::NotApplicable, None)
+}
+
+/// Synthesize a single round trace of two hops where the first hop quotes `quoted_payload`.
+pub fn quoted_trace(quoted_payload: &[u8]) -> State {
+    two_hop_trace(IcmpPacketType::NotApplicable, Some(quoted_payload))
+}
+
+/// Synthesize a single round trace of two hops where the second hop is filtered.
+///
+/// The second hop responds with an ICMP `DestinationUnreachable` with code 13 (communication
+/// administratively prohibited).
+pub fn filtered_trace() -> State {
+    two_hop_trace(IcmpPacketType::Unreachable(IcmpPacketCode(13)), None)
+}
+
+/// Synthesize a single round trace of two hops where the second hop is unreachable.
+///
+/// The second hop responds with an ICMP `DestinationUnreachable` with the given `code`.
+pub fn unreachable_trace(code: u8) -> State {
+    two_hop_trace(IcmpPacketType::Unreachable(IcmpPacketCode(code)), None)
+}
+
+/// Synthesize a single round trace of two hops, where the second hop responds with
+/// `icmp_packet_type` and the first hop quotes `quoted_payload`.
+///
+/// The first hop responds with DSCP CS6 (48) and the second with DSCP EF (46).
+#[expect(clippy::default_trait_access, reason = "StateConfig is not public")]
+fn two_hop_trace(icmp_packet_type: IcmpPacketType, quoted_payload: Option<&[u8]>) -> State {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let probe = |ttl: u8, host: Ipv4Addr, rtt_ms: u64, icmp_packet_type: IcmpPacketType| {
+        ProbeStatus::Complete(ProbeComplete {
+            sequence: Sequence(33000 + u16::from(ttl)),
+            identifier: TraceId(1),
+            src_port: Port(0),
+            dest_port: Port(0),
+            ttl: TimeToLive(ttl),
+            round: RoundId(0),
+            sent: start,
+            host: IpAddr::V4(host),
+            received: start + Duration::from_millis(rtt_ms),
+            icmp_packet_type,
+            tos: None,
+            expected_udp_checksum: None,
+            actual_udp_checksum: None,
+            extensions: None,
+            extra_hosts: vec![],
+            quoted_payload: quoted_payload.filter(|_| ttl == 1).map(<[u8]>::to_vec),
+            response_tos: Some(TypeOfService(if ttl == 1 { 0xc0 } else { 0xb8 })),
+        })
+    };
+    let probes = [
+        probe(
+            1,
+            Ipv4Addr::new(10, 0, 0, 1),
+            10,
+            IcmpPacketType::NotApplicable,
+        ),
+        probe(2, Ipv4Addr::new(10, 0, 0, 2), 20, icmp_packet_type),
+    ];
+    let mut trace = State::new(Default::default());
+    trace.update_from_round(&Round::new(
+        &probes,
+        TimeToLive(2),
+        CompletionReason::TargetFound,
+    ));
+    trace
+}
+
+/// Synthesize a trace of `rounds` rounds of three hops, none of which responded.
+#[expect(clippy::default_trait_access, reason = "StateConfig is not public")]
+pub fn unresponsive_trace(rounds: usize) -> State {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut trace = State::new(Default::default());
+    for round in 0..rounds {
+        let probes = (1..=3)
+            .map(|ttl: u8| {
+                ProbeStatus::Awaited(Probe {
+                    sequence: Sequence(33000 + u16::from(ttl)),
+                    identifier: TraceId(1),
+                    src_port: Port(0),
+                    dest_port: Port(0),
+                    ttl: TimeToLive(ttl),
+                    round: RoundId(round),
+                    sent: start,
+                    flags: Flags::empty(),
+                })
+            })
+            .collect::<Vec<_>>();
+        trace.update_from_round(&Round::new(
+            &probes,
+            TimeToLive(3),
+            CompletionReason::RoundTimeLimitExceeded,
+        ));
+    }
+    trace
+}
+
+/// Synthesize a trace with one round for each of `rounds`, where each round is the addresses of
+/// the hops which responded, starting from a ttl of 1.
+#[expect(clippy::default_trait_access, reason = "StateConfig is not public")]
+pub fn routed_trace(rounds: &[&[Ipv4Addr]]) -> State {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut trace = State::new(Default::default());
+    for (round, hosts) in rounds.iter().enumerate() {
+        let probes = (1..)
+            .zip(hosts.iter())
+            .map(|(ttl, host): (u8, _)| {
+                ProbeStatus::Complete(ProbeComplete {
+                    sequence: Sequence(33000 + u16::from(ttl)),
+                    identifier: TraceId(1),
+                    src_port: Port(0),
+                    dest_port: Port(0),
+                    ttl: TimeToLive(ttl),
+                    round: RoundId(round),
+                    sent: start,
+                    host: IpAddr::V4(*host),
+                    received: start + Duration::from_millis(10),
+                    icmp_packet_type: IcmpPacketType::NotApplicable,
+                    tos: None,
+                    expected_udp_checksum: None,
+                    actual_udp_checksum: None,
+                    extensions: None,
+                    extra_hosts: vec![],
+                    quoted_payload: None,
+                    response_tos: None,
+                })
+            })
+            .collect::<Vec<_>>();
+        let largest_ttl = TimeToLive(u8::try_from(probes.len()).unwrap_or_default());
+        trace.update_from_round(&Round::new(
+            &probes,
+            largest_ttl,
+            CompletionReason::TargetFound,
+        ));
+    }
+    trace
+}
+
+/// Synthesize a trace of a single hop, the target, with one round for each of `rtts` (in ms).
+///
+/// A round trip time of `None` is a probe which did not receive a response, and so a round which
+/// did not reach the target.
+#[expect(clippy::default_trait_access, reason = "StateConfig is not public")]
+pub fn sampled_trace(rtts: &[Option<u64>]) -> State {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut trace = State::new(Default::default());
+    for (round, rtt) in rtts.iter().enumerate() {
+        let probe = Probe {
+            sequence: Sequence(33000),
+            identifier: TraceId(1),
+            src_port: Port(0),
+            dest_port: Port(0),
+            ttl: TimeToLive(1),
+            round: RoundId(round),
+            sent: start,
+            flags: Flags::empty(),
+        };
+        let probe = match rtt {
+            Some(rtt) => ProbeStatus::Complete(ProbeComplete {
+                sequence: probe.sequence,
+                identifier: probe.identifier,
+                src_port: probe.src_port,
+                dest_port: probe.dest_port,
+                ttl: probe.ttl,
+                round: probe.round,
+                sent: probe.sent,
+                host: TARGET_ADDR,
+                received: start + Duration::from_millis(*rtt),
+                icmp_packet_type: IcmpPacketType::NotApplicable,
+                tos: None,
+                expected_udp_checksum: None,
+                actual_udp_checksum: None,
+                extensions: None,
+                extra_hosts: vec![],
+                quoted_payload: None,
+                response_tos: None,
+            }),
+            None => ProbeStatus::Awaited(probe),
+        };
+        let reason = if rtt.is_some() {
+            CompletionReason::TargetFound
+        } else {
+            CompletionReason::RoundTimeLimitExceeded
+        };
+        trace.update_from_round(&Round::new(&[probe], TimeToLive(1), reason));
+    }
+    trace
+}
+
+/// Synthesize a single round trace of three hops, starting at `first_ttl`, where the second hop
+/// did not respond.
+#[expect(clippy::default_trait_access, reason = "StateConfig is not public")]
+pub fn first_ttl_trace(first_ttl: u8) -> State {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let awaited = |ttl: u8| Probe {
+        sequence: Sequence(33000 + u16::from(ttl)),
+        identifier: TraceId(1),
+        src_port: Port(0),
+        dest_port: Port(0),
+        ttl: TimeToLive(ttl),
+        round: RoundId(0),
+        sent: start,
+        flags: Flags::empty(),
+    };
+    let complete = |ttl: u8, host: IpAddr, rtt_ms: u64| {
+        ProbeStatus::Complete(ProbeComplete {
+            sequence: Sequence(33000 + u16::from(ttl)),
+            identifier: TraceId(1),
+            src_port: Port(0),
+            dest_port: Port(0),
+            ttl: TimeToLive(ttl),
+            round: RoundId(0),
+            sent: start,
+            host,
+            received: start + Duration::from_millis(rtt_ms),
+            icmp_packet_type: IcmpPacketType::NotApplicable,
+            tos: None,
+            expected_udp_checksum: None,
+            actual_udp_checksum: None,
+            extensions: None,
+            extra_hosts: vec![],
+            quoted_payload: None,
+            response_tos: None,
+        })
+    };
+    let probes = [
+        complete(first_ttl, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 10),
+        ProbeStatus::Awaited(awaited(first_ttl + 1)),
+        complete(first_ttl + 2, TARGET_ADDR, 30),
+    ];
+    let mut trace = State::new(Default::default());
+    trace.update_from_round(&Round::new(
+        &probes,
+        TimeToLive(first_ttl + 2),
+        CompletionReason::TargetFound,
+    ));
+    trace
+}
@@ -1,24 +1,107 @@
 use chrono::Utc;
+use humantime::format_duration;
 use itertools::Itertools;
 use serde::{Serialize, Serializer};
 use std::fmt::{Display, Formatter};
 use std::net::IpAddr;
-use trippy_core::NatStatus;
+use trippy_core::{IcmpExtensionParseMode, NatStatus, Tracer};
 use trippy_dns::Resolver;
 
+/// The version of the structure of the json report.
+///
+/// This must be incremented whenever the structure of the `Report` changes.
+pub const SCHEMA_VERSION: u32 = 5;
+
 #[derive(Serialize)]
 pub struct Report {
+    pub schema_version: u32,
     pub info: Info,
+    pub config: Config,
     pub hops: Vec<Hop>,
 }
 
 #[derive(Serialize)]
 pub struct Info {
     pub target: Host,
+    pub destination_reached: bool,
+    #[serde(serialize_with = "fixed_width")]
+    pub destination_confidence: f64,
+    #[serde(serialize_with = "fixed_width")]
+    pub path_stability: f64,
+    pub effective_privilege_mode: Option<String>,
+    pub identifier_comparisons: Vec<IdentifierComparison>,
     pub start_timestamp: chrono::DateTime<Utc>,
     pub end_timestamp: chrono::DateTime<Utc>,
 }
 
+/// The effective configuration of the tracer which produced a report.
+#[derive(Serialize)]
+pub struct Config {
+    pub protocol: String,
+    pub privilege_mode: String,
+    pub src_port: Option<u16>,
+    pub dest_port: Option<u16>,
+    pub multipath_strategy: String,
+    pub first_ttl: u8,
+    pub max_ttl: u8,
+    pub packet_size: u16,
+    pub payload_pattern: String,
+    pub tos: u8,
+    pub initial_sequence: u16,
+    pub max_inflight: u8,
+    pub min_round_duration: String,
+    pub max_round_duration: String,
+    pub grace_duration: String,
+    pub read_timeout: String,
+    pub icmp_extensions: bool,
+    pub icmp_quoted_payload: bool,
+}
+
+impl From<&Tracer> for Config {
+    fn from(value: &Tracer) -> Self {
+        let port_direction = value.port_direction();
+        Self {
+            protocol: value.protocol().to_string(),
+            privilege_mode: value.privilege_mode().to_string(),
+            src_port: port_direction.src().map(|port| port.0),
+            dest_port: port_direction.dest().map(|port| port.0),
+            multipath_strategy: value.multipath_strategy().to_string(),
+            first_ttl: value.first_ttl().0,
+            max_ttl: value.max_ttl().0,
+            packet_size: value.packet_size().0,
+            payload_pattern: value.payload_pattern().to_string(),
+            tos: value.tos().0,
+            initial_sequence: value.initial_sequence().0,
+            max_inflight: value.max_inflight().0,
+            min_round_duration: format_duration(value.min_round_duration()).to_string(),
+            max_round_duration: format_duration(value.max_round_duration()).to_string(),
+            grace_duration: format_duration(value.grace_duration()).to_string(),
+            read_timeout: format_duration(value.read_timeout()).to_string(),
+            icmp_extensions: value.icmp_extension_parse_mode() == IcmpExtensionParseMode::Enabled,
+            icmp_quoted_payload: value.icmp_quoted_payload_mode().is_enabled(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct IdentifierComparison {
+    pub sequence: u16,
+    pub sent: u16,
+    pub received: u16,
+    pub rewritten: bool,
+}
+
+impl From<trippy_core::IdentifierComparison> for IdentifierComparison {
+    fn from(value: trippy_core::IdentifierComparison) -> Self {
+        Self {
+            sequence: value.sequence.0,
+            sent: value.sent,
+            received: value.received,
+            rewritten: value.is_rewritten(),
+        }
+    }
+}
+
 #[derive(Serialize)]
 pub struct Hop {
     pub ttl: u8,
@@ -48,6 +131,12 @@ pub struct Hop {
     pub jinta: f64,
     pub nat: Option<bool>,
     pub tos: u8,
+    pub filtered: bool,
+    pub flapping: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quoted_payload: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_to_first: Option<String>,
 }
 
 impl<R: Resolver> From<(&trippy_core::Hop, &R)> for Hop {
@@ -76,6 +165,14 @@ impl<R: Resolver> From<(&trippy_core::Hop, &R)> for Hop {
                 NatStatus::Detected => Some(true),
             },
             tos: value.tos().unwrap_or_default().0,
+            filtered: value.is_filtered(),
+            flapping: value.is_flapping(),
+            quoted_payload: value
+                .last_quoted_payload()
+                .map(|payload| format!("{:02x}", payload.iter().format(""))),
+            time_to_first: value
+                .time_to_first_ms()
+                .map(|time_to_first| format!("{time_to_first:.2}")),
         }
     }
 }
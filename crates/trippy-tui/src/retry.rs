@@ -0,0 +1,113 @@
+use crate::app::ResolveError;
+use crate::report::{NoResponseError, TraceError};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Run `attempt` until it succeeds, retrying up to `retries` times if it fails with a transient
+/// error.
+///
+/// The attempt number, starting from zero, is passed to `attempt`.  The delay before the first
+/// retry is `delay` and it is doubled for each further retry.  If every attempt fails, or an
+/// attempt fails with an error which is not transient, then the error from that attempt is
+/// returned.
+pub fn retry<T>(
+    retries: usize,
+    delay: Duration,
+    mut attempt: impl FnMut(usize) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut count = 0;
+    let mut delay = delay;
+    loop {
+        match attempt(count) {
+            Err(err) if count < retries && is_transient(&err) => {
+                tracing::debug!(attempt = count, ?delay, %err, "retrying trace");
+                sleep(delay);
+                delay = delay.saturating_mul(2);
+                count += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Is `err` transient, such that retrying the trace may succeed?
+///
+/// A trace is retried if no hops responded, the targets could not be resolved or the tracer
+/// failed with a transient network error.  Any other error, such as insufficient privileges, is
+/// not retried.
+fn is_transient(err: &anyhow::Error) -> bool {
+    if err.is::<NoResponseError>() || err.is::<ResolveError>() {
+        true
+    } else if let Some(err) = err.downcast_ref::<TraceError>() {
+        err.transient
+    } else {
+        err.downcast_ref::<trippy_core::Error>()
+            .is_some_and(trippy_core::Error::is_transient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use test_case::test_case;
+
+    #[test]
+    fn test_retry_succeeds_after_no_response() {
+        let mut attempts = 0;
+        let result = retry(3, Duration::ZERO, |attempt| {
+            attempts += 1;
+            if attempt == 0 {
+                Err(NoResponseError(3).into())
+            } else {
+                Ok(attempt)
+            }
+        });
+        assert_eq!(2, attempts);
+        assert_eq!(1, result.unwrap());
+    }
+
+    #[test]
+    fn test_retry_exhausted() {
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = retry(2, Duration::ZERO, |_| {
+            attempts += 1;
+            Err(NoResponseError(3).into())
+        });
+        assert_eq!(3, attempts);
+        let err = result.unwrap_err();
+        assert_eq!(Some(&NoResponseError(3)), err.downcast_ref());
+    }
+
+    #[test]
+    fn test_retry_disabled() {
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = retry(0, Duration::ZERO, |_| {
+            attempts += 1;
+            Err(NoResponseError(3).into())
+        });
+        assert_eq!(1, attempts);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_retry_not_transient() {
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = retry(3, Duration::ZERO, |_| {
+            attempts += 1;
+            Err(anyhow!("permission denied"))
+        });
+        assert_eq!(1, attempts);
+        assert!(result.is_err());
+    }
+
+    #[test_case(&NoResponseError(3).into(), true; "no response")]
+    #[test_case(&ResolveError(String::from("failed")).into(), true; "resolve")]
+    #[test_case(&TraceError { message: String::from("failed"), transient: true }.into(), true; "transient trace error")]
+    #[test_case(&TraceError { message: String::from("failed"), transient: false }.into(), false; "trace error")]
+    #[test_case(&trippy_core::Error::BadConfig(String::from("bad")).into(), false; "bad config")]
+    #[test_case(&anyhow!("failed"), false; "other")]
+    fn test_is_transient(err: &anyhow::Error, expected: bool) {
+        assert_eq!(expected, is_transient(err));
+    }
+}